@@ -9,7 +9,8 @@ use byte_unit::Byte;
 use eyre::Result;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use milli::update::UpdateIndexingStep::{
-    ComputeIdsAndMergeDocuments, IndexDocuments, MergeDataIntoFinalDatabase, RemapDocumentAddition,
+    ComputeIdsAndMergeDocuments, DocumentsChunkSize, ExtractDatabase, IndexDocuments,
+    MergeDataIntoFinalDatabase, RemapDocumentAddition, WriteDatabase,
 };
 use milli::update::{IndexDocumentsConfig, IndexDocumentsMethod, IndexerConfig};
 use milli::Index;
@@ -264,6 +265,11 @@ impl Performer for DocumentAddition {
 }
 
 fn indexing_callback(step: milli::update::UpdateIndexingStep, bars: &[ProgressBar]) {
+    // The metrics of the databases are interleaved with the progress of the steps.
+    if matches!(step, DocumentsChunkSize { .. } | ExtractDatabase { .. } | WriteDatabase { .. }) {
+        return;
+    }
+
     let step_index = step.step();
     let bar = &bars[step_index];
     if step_index > 0 {
@@ -301,6 +307,7 @@ fn indexing_callback(step: milli::update::UpdateIndexingStep, bars: &[ProgressBa
             bar.set_message("Merging databases...");
             bar.set_position(databases_seen as u64);
         }
+        DocumentsChunkSize { .. } | ExtractDatabase { .. } | WriteDatabase { .. } => (),
     }
     bar.enable_steady_tick(200);
 }
@@ -438,7 +445,7 @@ impl Search {
         let documents = index.documents(&txn, result.documents_ids)?;
         let mut jsons = Vec::new();
         for (_, obkv) in documents {
-            let json = milli::obkv_to_json(&displayed_fields, &fields_ids_map, obkv)?;
+            let json = milli::obkv_to_json(&displayed_fields, &fields_ids_map, obkv.as_obkv())?;
             jsons.push(json);
         }
 
@@ -3,15 +3,19 @@
 //! ```text
 //! condition      = value ("==" | ">" ...) value
 //! to             = value value TO value
+//! in             = value ("ALL" | "ANY") "IN" "[" value ("," value)* "]"
 //! ```
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
+use nom::character::complete::char;
 use nom::combinator::cut;
-use nom::sequence::tuple;
+use nom::multi::separated_list1;
+use nom::sequence::{preceded, terminated, tuple};
 use Condition::*;
 
-use crate::{parse_value, FilterCondition, IResult, Span, Token};
+use crate::error::cut_with_err;
+use crate::{parse_value, ws, Error, ErrorKind, FilterCondition, IResult, Span, Token};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Condition<'a> {
@@ -22,6 +26,10 @@ pub enum Condition<'a> {
     LowerThan(Token<'a>),
     LowerThanOrEqual(Token<'a>),
     Between { from: Token<'a>, to: Token<'a> },
+    AnyIn(Vec<Token<'a>>),
+    AllIn(Vec<Token<'a>>),
+    NotAnyIn(Vec<Token<'a>>),
+    NotAllIn(Vec<Token<'a>>),
 }
 
 impl<'a> Condition<'a> {
@@ -36,6 +44,10 @@ impl<'a> Condition<'a> {
             LowerThan(n) => (GreaterThanOrEqual(n), None),
             LowerThanOrEqual(n) => (GreaterThan(n), None),
             Between { from, to } => (LowerThan(from), Some(GreaterThan(to))),
+            AnyIn(values) => (NotAnyIn(values), None),
+            AllIn(values) => (NotAllIn(values), None),
+            NotAnyIn(values) => (AnyIn(values), None),
+            NotAllIn(values) => (AllIn(values), None),
         }
     }
 }
@@ -65,3 +77,32 @@ pub fn parse_to(input: Span) -> IResult<FilterCondition> {
 
     Ok((input, FilterCondition::Condition { fid: key, op: Between { from, to } }))
 }
+
+/// in             = value ("ALL" | "ANY") "IN" "[" value ("," value)* "]"
+/// `ANY` matches when one of the values of the field is in the list and `ALL` when the field
+/// has values and all of them are in the list.
+/// If we parse the opening bracket we MUST parse the values and the closing bracket.
+pub fn parse_in<'a>(input: Span<'a>) -> IResult<FilterCondition<'a>> {
+    let quantifier = alt((tag("ALL"), tag("ANY")));
+    let values = |input: Span<'a>| {
+        preceded(
+            ws(char('[')),
+            cut(terminated(
+                separated_list1(char(','), parse_value),
+                cut_with_err(ws(char(']')), |c| {
+                    Error::new_from_kind(input, ErrorKind::MissingClosingDelimiter(c.char()))
+                }),
+            )),
+        )(input)
+    };
+    let (input, (fid, quantifier, _, values)) =
+        tuple((parse_value, quantifier, ws(tag("IN")), values))(input)?;
+
+    let condition = match *quantifier.fragment() {
+        "ALL" => FilterCondition::Condition { fid, op: AllIn(values) },
+        "ANY" => FilterCondition::Condition { fid, op: AnyIn(values) },
+        _ => unreachable!(),
+    };
+
+    Ok((input, condition))
+}
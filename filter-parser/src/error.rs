@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::ops::Range;
 
 use nom::error::{self, ParseError};
 use nom::Parser;
@@ -72,6 +73,12 @@ impl<'a> Error<'a> {
         &self.context
     }
 
+    /// Returns the byte offsets of the part of the filter the error is about.
+    pub fn span(&self) -> Range<usize> {
+        let start = self.context.location_offset();
+        start..start + self.context.fragment().len()
+    }
+
     pub fn new_from_kind(context: Span<'a>, kind: ErrorKind<'a>) -> Self {
         Self { context, kind }
     }
@@ -88,6 +95,27 @@ impl<'a> Error<'a> {
     }
 }
 
+impl<'a> ErrorKind<'a> {
+    /// Returns the tokens that were expected where the error was found,
+    /// empty when the error isn't about a missing token.
+    pub fn expected(&self) -> Vec<String> {
+        match self {
+            Self::InvalidPrimary => ["=", "!=", ">=", ">", "<=", "<", "TO", "_geoRadius"]
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            Self::ExpectedEof => vec!["AND".to_string(), "OR".to_string()],
+            Self::ExpectedValue | Self::MalformedValue => vec!["value".to_string()],
+            Self::MissingClosingDelimiter(c) | Self::Char(c) => vec![c.to_string()],
+            Self::Geo => vec!["_geoRadius(latitude, longitude, radius)".to_string()],
+            Self::ReservedGeo(_)
+            | Self::MisusedGeo
+            | Self::InternalError(_)
+            | Self::External(_) => Vec::new(),
+        }
+    }
+}
+
 impl<'a> ParseError<Span<'a>> for Error<'a> {
     fn from_error_kind(input: Span<'a>, kind: error::ErrorKind) -> Self {
         let kind = match kind {
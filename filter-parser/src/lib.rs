@@ -6,9 +6,10 @@
 //! or             = and (~ "OR" ~ and)
 //! and            = not (~ "AND" not)*
 //! not            = ("NOT" ~ not) | primary
-//! primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | to
+//! primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | in | to
 //! condition      = value ("==" | ">" ...) value
 //! to             = value value TO value
+//! in             = value ("ALL" | "ANY") "IN" "[" value ("," value)* "]"
 //! value          = WS* ~ ( word | singleQuoted | doubleQuoted) ~ WS*
 //! singleQuoted   = "'" .* all but quotes "'"
 //! doubleQuoted   = "\"" .* all but double quotes "\""
@@ -43,7 +44,7 @@ use std::fmt::Debug;
 use std::ops::Deref;
 use std::str::FromStr;
 
-pub use condition::{parse_condition, parse_to, Condition};
+pub use condition::{parse_condition, parse_in, parse_to, Condition};
 use error::{cut_with_err, NomErrorExt};
 pub use error::{Error, ErrorKind};
 use nom::branch::alt;
@@ -240,7 +241,7 @@ fn parse_geo_point(input: Span) -> IResult<FilterCondition> {
     Err(nom::Err::Failure(Error::new_from_kind(input, ErrorKind::ReservedGeo("_geoPoint"))))
 }
 
-/// primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | to
+/// primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | in | to
 fn parse_primary(input: Span) -> IResult<FilterCondition> {
     alt((
         // if we find a first parenthesis, then we must parse an expression and find the closing parenthesis
@@ -253,6 +254,7 @@ fn parse_primary(input: Span) -> IResult<FilterCondition> {
         ),
         parse_geo_radius,
         parse_condition,
+        parse_in,
         parse_to,
         // the next lines are only for error handling and are written at the end to have the less possible performance impact
         parse_geo_point,
@@ -450,6 +452,33 @@ pub mod tests {
                     .into(),
                 ),
             ),
+            (
+                "tags ANY IN [rust, 'web assembly']",
+                Fc::Condition {
+                    fid: rtok("", "tags"),
+                    op: Condition::AnyIn(vec![
+                        rtok("tags ANY IN [", "rust"),
+                        rtok("tags ANY IN [rust, '", "web assembly"),
+                    ]),
+                },
+            ),
+            (
+                "tags ALL IN [ rust ]",
+                Fc::Condition {
+                    fid: rtok("", "tags"),
+                    op: Condition::AllIn(vec![rtok("tags ALL IN [ ", "rust")]),
+                },
+            ),
+            (
+                "NOT tags ALL IN [rust,go]",
+                Fc::Condition {
+                    fid: rtok("NOT ", "tags"),
+                    op: Condition::NotAllIn(vec![
+                        rtok("NOT tags ALL IN [", "rust"),
+                        rtok("NOT tags ALL IN [rust,", "go"),
+                    ]),
+                },
+            ),
             (
                 "_geoRadius(12, 13, 14)",
                 Fc::GeoLowerThan {
@@ -595,6 +624,8 @@ pub mod tests {
             ("channel = \"ponce", "Expression `\\\"ponce` is missing the following closing delimiter: `\"`."),
             ("channel = mv OR (followers >= 1000", "Expression `(followers >= 1000` is missing the following closing delimiter: `)`."),
             ("channel = mv OR followers >= 1000)", "Found unexpected characters at the end of the filter: `)`. You probably forgot an `OR` or an `AND` rule."),
+            ("tags ANY IN [rust, go", "Expression `[rust, go` is missing the following closing delimiter: `]`."),
+            ("tags ALL IN []", "Was expecting a value but instead got `]`."),
         ];
 
         for (input, expected) in test_case {
@@ -611,6 +642,27 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn error_span() {
+        let input = "channel = Ponce = 12";
+        let error = FilterCondition::parse(input).unwrap_err();
+        assert_eq!(&input[error.span()], "= 12");
+        assert_eq!(error.kind().expected(), vec!["AND", "OR"]);
+
+        let input = "channel Ponce";
+        let error = FilterCondition::parse(input).unwrap_err();
+        assert_eq!(error.span(), 0..13);
+        assert_eq!(
+            error.kind().expected(),
+            vec!["=", "!=", ">=", ">", "<=", "<", "TO", "_geoRadius"]
+        );
+
+        let input = "channel = mv OR (followers >= 1000";
+        let error = FilterCondition::parse(input).unwrap_err();
+        assert_eq!(&input[error.span()], "(followers >= 1000");
+        assert_eq!(error.kind().expected(), vec![")"]);
+    }
+
     #[test]
     fn depth() {
         let filter = FilterCondition::parse("account_ids=1 OR account_ids=2 OR account_ids=3 OR account_ids=4 OR account_ids=5 OR account_ids=6").unwrap().unwrap();
@@ -25,8 +25,8 @@ use milli::update::{
     ClearDocuments, IndexDocumentsConfig, IndexDocumentsMethod, IndexerConfig, Setting,
 };
 use milli::{
-    obkv_to_json, CompressionType, Filter as MilliFilter, FilterCondition, Index, MatchingWords,
-    SearchResult, SortError,
+    obkv_to_json, CompressionType, Filter as MilliFilter, FilterCondition, FormatOptions, Index,
+    MatcherBuilder, SearchResult, SortError,
 };
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
@@ -136,6 +136,11 @@ pub struct IndexerOpt {
     /// Any value higher than 65535 will be clamped.
     #[structopt(long)]
     pub max_positions_per_attributes: Option<u32>,
+
+    /// Maximum proximity between the pairs of words that are stored.
+    /// Any value higher than 7 will be clamped.
+    #[structopt(long)]
+    pub proximity_window: Option<u32>,
 }
 
 struct Highlighter<'a, A> {
@@ -151,43 +156,27 @@ impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
         Self { analyzer }
     }
 
-    fn highlight_value(&self, value: Value, matching_words: &MatchingWords) -> Value {
+    fn highlight_value(&self, value: Value, matcher_builder: &MatcherBuilder) -> Value {
         match value {
             Value::Null => Value::Null,
             Value::Bool(boolean) => Value::Bool(boolean),
             Value::Number(number) => Value::Number(number),
             Value::String(old_string) => {
-                let mut string = String::new();
                 let analyzed = self.analyzer.analyze(&old_string);
-                for (word, token) in analyzed.reconstruct() {
-                    if token.is_word() {
-                        match matching_words.matching_bytes(&token) {
-                            Some(chars_to_highlight) => {
-                                let mut chars = word.chars();
-
-                                string.push_str("<mark>");
-                                // push the part to highlight
-                                string.extend(chars.by_ref().take(chars_to_highlight));
-                                string.push_str("</mark>");
-                                // push the suffix after highlight
-                                string.extend(chars);
-                            }
-                            // no highlight
-                            None => string.push_str(word),
-                        }
-                    } else {
-                        string.push_str(word);
-                    }
-                }
+                let tokens: Vec<_> = analyzed.tokens().collect();
+                let mut matcher = matcher_builder.build(&tokens, &old_string);
+
+                let format_options = FormatOptions { highlight: true, crop: None };
+                let string = matcher.format(format_options).into_owned();
                 Value::String(string)
             }
             Value::Array(values) => Value::Array(
-                values.into_iter().map(|v| self.highlight_value(v, matching_words)).collect(),
+                values.into_iter().map(|v| self.highlight_value(v, matcher_builder)).collect(),
             ),
             Value::Object(object) => Value::Object(
                 object
                     .into_iter()
-                    .map(|(k, v)| (k, self.highlight_value(v, matching_words)))
+                    .map(|(k, v)| (k, self.highlight_value(v, matcher_builder)))
                     .collect(),
             ),
         }
@@ -196,14 +185,14 @@ impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
     fn highlight_record(
         &self,
         object: &mut Map<String, Value>,
-        matching_words: &MatchingWords,
+        matcher_builder: &MatcherBuilder,
         attributes_to_highlight: &HashSet<String>,
     ) {
         // TODO do we need to create a string for element that are not and needs to be highlight?
         for (key, value) in object.iter_mut() {
             if attributes_to_highlight.contains(key) {
                 let old_value = mem::take(value);
-                *value = self.highlight_value(old_value, matching_words);
+                *value = self.highlight_value(old_value, matcher_builder);
             }
         }
     }
@@ -333,6 +322,7 @@ async fn main() -> anyhow::Result<()> {
         max_nb_chunks: opt.indexer.max_nb_chunks,
         chunk_compression_level: opt.indexer.chunk_compression_level,
         max_positions_per_attributes: opt.indexer.max_positions_per_attributes,
+        proximity_window: opt.indexer.proximity_window,
         thread_pool: Some(pool),
         log_every_n: Some(opt.indexer.log_every_n),
         max_memory: Some(opt.indexer.max_memory.get_bytes() as usize),
@@ -391,6 +381,9 @@ async fn main() -> anyhow::Result<()> {
                             MergeDataIntoFinalDatabase { databases_seen, total_databases } => {
                                 (databases_seen, Some(total_databases))
                             }
+                            DocumentsChunkSize { .. }
+                            | ExtractDatabase { .. }
+                            | WriteDatabase { .. } => return,
                         };
                         let _ = update_status_sender_cloned.send(UpdateStatus::Progressing {
                             update_id,
@@ -523,6 +516,9 @@ async fn main() -> anyhow::Result<()> {
                             MergeDataIntoFinalDatabase { databases_seen, total_databases } => {
                                 (databases_seen, Some(total_databases))
                             }
+                            DocumentsChunkSize { .. }
+                            | ExtractDatabase { .. }
+                            | WriteDatabase { .. } => return,
                         };
                         let _ = update_status_sender_cloned.send(UpdateStatus::Progressing {
                             update_id,
@@ -794,7 +790,7 @@ async fn main() -> anyhow::Result<()> {
                 search.sort_criteria(vec![sort.parse().map_err(SortError::from).unwrap()]);
             }
 
-            let SearchResult { matching_words, candidates, documents_ids } =
+            let SearchResult { matching_words, candidates, documents_ids, .. } =
                 search.execute().unwrap();
 
             let number_of_candidates = candidates.len();
@@ -817,13 +813,17 @@ async fn main() -> anyhow::Result<()> {
 
             let stop_words = fst::Set::default();
             let highlighter = Highlighter::new(&stop_words);
+            let mut matcher_builder = MatcherBuilder::from_matching_words(matching_words);
+            matcher_builder.highlight_prefix("<mark>".to_string());
+            matcher_builder.highlight_suffix("</mark>".to_string());
 
             for (_id, obkv) in index.documents(&rtxn, documents_ids).unwrap() {
-                let mut object = obkv_to_json(&displayed_fields, &fields_ids_map, obkv).unwrap();
+                let mut object =
+                    obkv_to_json(&displayed_fields, &fields_ids_map, obkv.as_obkv()).unwrap();
                 if !disable_highlighting {
                     highlighter.highlight_record(
                         &mut object,
-                        &matching_words,
+                        &matcher_builder,
                         &attributes_to_highlight,
                     );
                 }
@@ -858,7 +858,8 @@ async fn main() -> anyhow::Result<()> {
                     let document_id = document_id as u32;
                     let (_, obkv) =
                         index.documents(&rtxn, Some(document_id)).unwrap().pop().unwrap();
-                    let document = obkv_to_json(&displayed_fields, &fields_ids_map, obkv).unwrap();
+                    let document =
+                        obkv_to_json(&displayed_fields, &fields_ids_map, obkv.as_obkv()).unwrap();
 
                     Response::builder()
                         .header("Content-Type", "application/json")
@@ -18,22 +18,27 @@ static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 const ALL_DATABASE_NAMES: &[&str] = &[
     MAIN,
     WORD_DOCIDS,
+    EXACT_WORD_DOCIDS,
     WORD_PREFIX_DOCIDS,
     DOCID_WORD_POSITIONS,
     WORD_PAIR_PROXIMITY_DOCIDS,
     WORD_PREFIX_PAIR_PROXIMITY_DOCIDS,
     WORD_POSITION_DOCIDS,
     WORD_PREFIX_POSITION_DOCIDS,
+    WORD_FID_DOCIDS,
     FIELD_ID_WORD_COUNT_DOCIDS,
     FACET_ID_F64_DOCIDS,
     FACET_ID_STRING_DOCIDS,
     FIELD_ID_DOCID_FACET_F64S,
     FIELD_ID_DOCID_FACET_STRINGS,
     DOCUMENTS,
+    DOCID_EXTERNAL_IDS,
+    ACL_TOKEN_DOCIDS,
 ];
 
 const POSTINGS_DATABASE_NAMES: &[&str] = &[
     WORD_DOCIDS,
+    EXACT_WORD_DOCIDS,
     WORD_PREFIX_DOCIDS,
     DOCID_WORD_POSITIONS,
     WORD_PAIR_PROXIMITY_DOCIDS,
@@ -315,8 +320,12 @@ fn most_common_words(index: &Index, rtxn: &heed::RoTxn, limit: usize) -> anyhow:
     use std::cmp::Reverse;
     use std::collections::BinaryHeap;
 
+    use heed::types::{ByteSlice, Str};
+    use milli::{RoaringBitmapCodec, SkipCorrupted};
+
     let mut heap = BinaryHeap::with_capacity(limit + 1);
-    for result in index.word_docids.iter(rtxn)? {
+    let iter = index.word_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?;
+    for result in SkipCorrupted::<Str, RoaringBitmapCodec>::new(WORD_DOCIDS, iter) {
         if limit == 0 {
             break;
         }
@@ -375,18 +384,24 @@ fn biggest_value_sizes(index: &Index, rtxn: &heed::RoTxn, limit: usize) -> anyho
         env: _env,
         main,
         word_docids,
+        exact_word_docids: _,
         word_prefix_docids,
         docid_word_positions,
         word_pair_proximity_docids,
         word_prefix_pair_proximity_docids,
         word_position_docids,
         word_prefix_position_docids,
+        word_fid_docids: _,
         field_id_word_count_docids,
         facet_id_f64_docids,
         facet_id_string_docids,
+        facet_id_date_docids: _,
         field_id_docid_facet_f64s: _,
         field_id_docid_facet_strings: _,
         documents,
+        docid_external_ids: _,
+        acl_token_docids: _,
+        ..
     } = index;
 
     let main_name = "main";
@@ -857,7 +872,8 @@ fn export_documents(
 ) -> anyhow::Result<()> {
     use std::io::{BufWriter, Write as _};
 
-    use milli::{obkv_to_json, BEU32};
+    use heed::types::{ByteSlice, OwnedType};
+    use milli::{obkv_to_json, ObkvCodec, SkipCorrupted, BEU32};
 
     let stdout = io::stdout();
     let mut out = BufWriter::new(stdout);
@@ -865,8 +881,26 @@ fn export_documents(
     let fields_ids_map = index.fields_ids_map(rtxn)?;
     let displayed_fields: Vec<_> = fields_ids_map.iter().map(|(id, _name)| id).collect();
 
+    // The compressed documents can't be read raw, they are decompressed by the index.
+    if index.documents_compressed(rtxn)? {
+        let documents = if internal_ids.is_empty() {
+            index.all_documents(rtxn)?.collect::<milli::Result<Vec<_>>>()?
+        } else {
+            index.documents(rtxn, internal_ids)?
+        };
+        for (_id, document) in documents {
+            let document = obkv_to_json(&displayed_fields, &fields_ids_map, document.as_obkv())?;
+            serde_json::to_writer(&mut out, &document)?;
+            writeln!(&mut out)?;
+        }
+        out.into_inner()?;
+        return Ok(());
+    }
+
     let iter: Box<dyn Iterator<Item = _>> = if internal_ids.is_empty() {
-        Box::new(index.documents.iter(rtxn)?.map(|result| result.map(|(_id, obkv)| obkv)))
+        let iter = index.documents.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?;
+        let documents = SkipCorrupted::<OwnedType<BEU32>, ObkvCodec>::new(DOCUMENTS, iter);
+        Box::new(documents.map(|result| result.map(|(_id, obkv)| obkv)))
     } else {
         Box::new(
             internal_ids
@@ -956,18 +990,23 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
         env: _env,
         main,
         word_docids,
+        exact_word_docids,
         word_prefix_docids,
         docid_word_positions,
         word_pair_proximity_docids,
         word_prefix_pair_proximity_docids,
         word_position_docids,
         word_prefix_position_docids,
+        word_fid_docids,
         field_id_word_count_docids,
         facet_id_f64_docids,
         facet_id_string_docids,
         field_id_docid_facet_f64s,
         field_id_docid_facet_strings,
         documents,
+        docid_external_ids,
+        acl_token_docids,
+        ..
     } = index;
 
     let names = if names.is_empty() {
@@ -981,11 +1020,13 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
             MAIN => &main,
             WORD_PREFIX_DOCIDS => word_prefix_docids.as_polymorph(),
             WORD_DOCIDS => word_docids.as_polymorph(),
+            EXACT_WORD_DOCIDS => exact_word_docids.as_polymorph(),
             DOCID_WORD_POSITIONS => docid_word_positions.as_polymorph(),
             WORD_PAIR_PROXIMITY_DOCIDS => word_pair_proximity_docids.as_polymorph(),
             WORD_PREFIX_PAIR_PROXIMITY_DOCIDS => word_prefix_pair_proximity_docids.as_polymorph(),
             WORD_POSITION_DOCIDS => word_position_docids.as_polymorph(),
             WORD_PREFIX_POSITION_DOCIDS => word_prefix_position_docids.as_polymorph(),
+            WORD_FID_DOCIDS => word_fid_docids.as_polymorph(),
             FIELD_ID_WORD_COUNT_DOCIDS => field_id_word_count_docids.as_polymorph(),
             FACET_ID_F64_DOCIDS => facet_id_f64_docids.as_polymorph(),
             FACET_ID_STRING_DOCIDS => facet_id_string_docids.as_polymorph(),
@@ -993,6 +1034,8 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
             FIELD_ID_DOCID_FACET_STRINGS => field_id_docid_facet_strings.as_polymorph(),
 
             DOCUMENTS => documents.as_polymorph(),
+            DOCID_EXTERNAL_IDS => docid_external_ids.as_polymorph(),
+            ACL_TOKEN_DOCIDS => acl_token_docids.as_polymorph(),
             unknown => anyhow::bail!("unknown database {:?}", unknown),
         };
 
@@ -1076,6 +1119,10 @@ fn database_stats(index: &Index, rtxn: &heed::RoTxn, name: &str) -> anyhow::Resu
             let db = index.word_docids.as_polymorph();
             compute_stats::<RoaringBitmapCodec>(*db, rtxn, name)
         }
+        EXACT_WORD_DOCIDS => {
+            let db = index.exact_word_docids.as_polymorph();
+            compute_stats::<RoaringBitmapCodec>(*db, rtxn, name)
+        }
         WORD_PREFIX_DOCIDS => {
             let db = index.word_prefix_docids.as_polymorph();
             compute_stats::<RoaringBitmapCodec>(*db, rtxn, name)
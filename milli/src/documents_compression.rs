@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::io;
+
+use heed::types::ByteSlice;
+use heed::{RoTxn, RwTxn};
+use tracing::warn;
+use zstd::bulk::{Compressor, Decompressor};
+
+use crate::{FieldId, Index, Result};
+
+/// The maximum size of the dictionaries trained to compress the documents.
+const DICTIONARY_MAX_SIZE: usize = 64 * 1024;
+/// The maximum number of documents sampled to train a dictionary.
+const DICTIONARY_SAMPLES: usize = 10_000;
+/// The zstd compression level of the documents.
+const COMPRESSION_LEVEL: i32 = 3;
+/// The maximum size of the compressed documents once decompressed, the size prefixing the
+/// compressed documents is checked against it before allocating the decompressed document.
+const MAX_DOCUMENT_SIZE: usize = 256 * 1024 * 1024; // 256MiB
+
+/// A document of the `documents` database, it is borrowed from the database
+/// unless it was compressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredDocument<'t> {
+    bytes: Cow<'t, [u8]>,
+}
+
+impl StoredDocument<'_> {
+    pub fn as_obkv(&self) -> obkv::KvReaderU16 {
+        obkv::KvReaderU16::new(&self.bytes)
+    }
+
+    pub fn get(&self, field_id: FieldId) -> Option<&[u8]> {
+        self.as_obkv().get(field_id)
+    }
+
+    pub fn iter(&self) -> obkv::KvIter<FieldId> {
+        self.as_obkv().iter()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Compresses and decompresses the documents with the dictionary of the index,
+/// the documents are left untouched when the index doesn't compress them.
+pub(crate) struct DocumentsCodec {
+    zstd: Option<(Compressor<'static>, Decompressor<'static>)>,
+}
+
+impl DocumentsCodec {
+    pub fn new(dictionary: Option<&[u8]>) -> Result<DocumentsCodec> {
+        let zstd = match dictionary {
+            Some(dictionary) => Some((
+                Compressor::with_dictionary(COMPRESSION_LEVEL, dictionary)?,
+                Decompressor::with_dictionary(dictionary)?,
+            )),
+            None => None,
+        };
+        Ok(DocumentsCodec { zstd })
+    }
+
+    /// The compressed documents are prefixed by their decompressed size.
+    pub fn compress<'a>(&mut self, obkv: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        match &mut self.zstd {
+            Some((compressor, _)) => {
+                if obkv.len() > MAX_DOCUMENT_SIZE {
+                    let error = "the document is too big to be compressed";
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, error).into());
+                }
+                let mut bytes = (obkv.len() as u32).to_be_bytes().to_vec();
+                bytes.extend_from_slice(&compressor.compress(obkv)?);
+                Ok(Cow::Owned(bytes))
+            }
+            None => Ok(Cow::Borrowed(obkv)),
+        }
+    }
+
+    pub fn decompress<'a>(&mut self, bytes: &'a [u8]) -> Result<StoredDocument<'a>> {
+        match &mut self.zstd {
+            Some((_, decompressor)) => {
+                if bytes.len() < 4 {
+                    let error = "the compressed document is truncated";
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, error).into());
+                }
+                let (size, compressed) = bytes.split_at(4);
+                let size = u32::from_be_bytes(size.try_into().unwrap()) as usize;
+                if size > MAX_DOCUMENT_SIZE {
+                    let error = "the size of the compressed document is corrupted";
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, error).into());
+                }
+                let obkv = decompressor.decompress(compressed, size)?;
+                Ok(StoredDocument { bytes: Cow::Owned(obkv) })
+            }
+            None => Ok(StoredDocument { bytes: Cow::Borrowed(bytes) }),
+        }
+    }
+}
+
+/// Trains a dictionary on a sample of the documents of the index.
+pub(crate) fn train_dictionary(rtxn: &RoTxn, index: &Index) -> Result<Vec<u8>> {
+    let docids = index.sample_documents(rtxn, DICTIONARY_SAMPLES, 0)?;
+    let samples: Vec<_> = index
+        .documents(rtxn, docids)?
+        .into_iter()
+        .map(|(_, document)| document.as_bytes().to_vec())
+        .collect();
+
+    // zstd can't train a dictionary on too few documents, they are compressed without one.
+    match zstd::dict::from_samples(&samples, DICTIONARY_MAX_SIZE) {
+        Ok(dictionary) => Ok(dictionary),
+        Err(error) => {
+            warn!("Compressing the documents without a dictionary, it can't be trained: {}", error);
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Rewrites all the documents of the index compressed with the new dictionary,
+/// or decompressed when there is none.
+pub(crate) fn recompress_documents(
+    wtxn: &mut RwTxn,
+    index: &Index,
+    dictionary: Option<&[u8]>,
+) -> Result<()> {
+    let mut old_codec = index.documents_codec(wtxn)?;
+    let mut new_codec = DocumentsCodec::new(dictionary)?;
+
+    let mut iter = index.documents.remap_data_type::<ByteSlice>().iter_mut(wtxn)?;
+    while let Some(result) = iter.next() {
+        let (docid, bytes) = result?;
+        let document = old_codec.decompress(bytes)?;
+        let bytes = new_codec.compress(document.as_bytes())?.into_owned();
+        // safety: we don't keep references from inside the LMDB database.
+        unsafe { iter.put_current(&docid, &bytes)? };
+    }
+    drop(iter);
+
+    match dictionary {
+        Some(dictionary) => index.put_documents_dictionary(wtxn, dictionary)?,
+        None => {
+            index.delete_documents_dictionary(wtxn)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use serde_json::json;
+
+    use super::*;
+    use crate::update::{
+        DeleteDocuments, IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod, IndexerConfig,
+        Settings,
+    };
+
+    #[test]
+    fn compress_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let description = "a long description repeated in the documents ".repeat(20);
+        let documents: Vec<_> = (0..100)
+            .map(
+                |i| json!({ "id": i, "title": format!("title {}", i), "description": description }),
+            )
+            .collect();
+        let content = documents!(documents);
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        let raw = index.documents.remap_data_type::<ByteSlice>();
+        let original = raw.get(&wtxn, &crate::BEU32::new(0)).unwrap().unwrap().to_vec();
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_compress_documents(true);
+        builder.execute(|_| ()).unwrap();
+        assert!(index.documents_compressed(&wtxn).unwrap());
+        let compressed = raw.get(&wtxn, &crate::BEU32::new(0)).unwrap().unwrap();
+        assert!(compressed.len() < original.len());
+
+        // The documents are decompressed transparently.
+        let (_, document) = index.documents(&wtxn, Some(0)).unwrap().remove(0);
+        assert_eq!(document.as_bytes(), &original[..]);
+        assert_eq!(index.all_documents(&wtxn).unwrap().count(), 100);
+
+        // The new documents are compressed and can be updated partially.
+        let content = documents!([{ "id": 100, "title": "new" }, { "id": 1, "title": "updated" }]);
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("100");
+        builder.execute().unwrap();
+
+        let documents = index.displayed_documents(&wtxn, Some(1)).unwrap();
+        assert_eq!(documents[0].1["title"], json!("updated"));
+        assert_eq!(documents[0].1["description"], json!(description));
+        assert_eq!(index.field_distribution(&wtxn).unwrap()["title"], 100);
+
+        // The documents are written back decompressed.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_compress_documents();
+        builder.execute(|_| ()).unwrap();
+        assert!(!index.documents_compressed(&wtxn).unwrap());
+        let decompressed = raw.get(&wtxn, &crate::BEU32::new(0)).unwrap().unwrap();
+        assert_eq!(decompressed, &original[..]);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn decompress_corrupted_size() {
+        let mut codec = DocumentsCodec::new(Some(&[])).unwrap();
+        let mut bytes = codec.compress(b"a document").unwrap().into_owned();
+        assert_eq!(codec.decompress(&bytes).unwrap().as_bytes(), b"a document");
+
+        // The size isn't trusted to allocate the decompressed document.
+        bytes[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(codec.decompress(&bytes).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Dumps of the documents and the settings of an index in a format that doesn't depend on
+//! the way the engine stores them, to migrate an index from a version of the engine to another.
+//!
+//! A dump is a stream of JSON objects separated by new lines, the first one is a header
+//! containing the settings of the index and every following one is a document.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Write};
+
+use heed::types::{ByteSlice, OwnedType};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use crate::error::{InternalError, UserError};
+use crate::index::db_name;
+use crate::update::{
+    IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod, IndexerConfig, Settings,
+};
+use crate::{
+    obkv_to_json, EmojiPolicy, Index, ProtectedPattern, ProximityPrecision, Result, SkipCorrupted,
+    TypoCosts, BEU32,
+};
+
+/// The version of the format of the dumps, bumped on every incompatible change.
+pub const DUMP_VERSION: u32 = 1;
+
+/// The number of documents indexed at once when a dump is imported.
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+/// The first line of a dump.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpHeader {
+    pub version: u32,
+    pub settings: DumpSettings,
+}
+
+/// The settings of an index as written in a dump, the settings that are not set
+/// are the default ones and the synonyms are the normalized ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpSettings {
+    pub primary_key: Option<String>,
+    pub searchable_fields: Option<Vec<String>>,
+    pub displayed_fields: Option<Vec<String>>,
+    pub filterable_fields: BTreeSet<String>,
+    pub sortable_fields: BTreeSet<String>,
+    pub criteria: Vec<String>,
+    pub distinct_field: Option<String>,
+    pub stop_words: BTreeSet<String>,
+    pub separator_tokens: Option<BTreeSet<String>>,
+    pub non_separator_tokens: Option<BTreeSet<String>>,
+    pub protected_patterns: Option<Vec<ProtectedPattern>>,
+    pub emoji_policy: EmojiPolicy,
+    #[serde(default)]
+    pub prefix_databases_disabled: bool,
+    #[serde(default)]
+    pub proximity_precision: ProximityPrecision,
+    pub dictionary: Option<BTreeSet<String>>,
+    pub split_identifiers_fields: BTreeSet<String>,
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub typo_costs: TypoCosts,
+}
+
+impl DumpSettings {
+    /// Reads the current settings of the index.
+    pub fn from_index(index: &Index, rtxn: &heed::RoTxn) -> Result<DumpSettings> {
+        let to_strings = |names: Vec<&str>| names.into_iter().map(String::from).collect();
+
+        let stop_words = match index.stop_words(rtxn)? {
+            Some(stop_words) => stop_words.stream().into_strs()?.into_iter().collect(),
+            None => BTreeSet::new(),
+        };
+
+        let synonyms = index
+            .synonyms(rtxn)?
+            .into_iter()
+            .map(|(word, synonyms)| {
+                let synonyms = synonyms.into_iter().map(|synonym| synonym.join(" ")).collect();
+                (word.join(" "), synonyms)
+            })
+            .collect();
+
+        Ok(DumpSettings {
+            primary_key: index.primary_key(rtxn)?.map(String::from),
+            searchable_fields: index.searchable_fields(rtxn)?.map(to_strings),
+            displayed_fields: index.displayed_fields(rtxn)?.map(to_strings),
+            filterable_fields: index.filterable_fields(rtxn)?.into_iter().collect(),
+            sortable_fields: index.sortable_fields(rtxn)?.into_iter().collect(),
+            criteria: index.criteria(rtxn)?.iter().map(ToString::to_string).collect(),
+            distinct_field: index.distinct_field(rtxn)?.map(String::from),
+            stop_words,
+            separator_tokens: index.separator_tokens(rtxn)?,
+            non_separator_tokens: index.non_separator_tokens(rtxn)?,
+            protected_patterns: index.protected_patterns(rtxn)?,
+            emoji_policy: index.emoji_policy(rtxn)?,
+            prefix_databases_disabled: !index.prefix_databases(rtxn)?,
+            proximity_precision: index.proximity_precision(rtxn)?,
+            dictionary: index.dictionary(rtxn)?,
+            split_identifiers_fields: index.split_identifiers_fields(rtxn)?.into_iter().collect(),
+            synonyms,
+            typo_costs: index.typo_costs(rtxn)?,
+        })
+    }
+
+    /// Sets these settings on the builder, the ones that are not set are reset
+    /// and the primary key is only set when there is one.
+    pub fn apply(self, builder: &mut Settings) {
+        let DumpSettings {
+            primary_key,
+            searchable_fields,
+            displayed_fields,
+            filterable_fields,
+            sortable_fields,
+            criteria,
+            distinct_field,
+            stop_words,
+            separator_tokens,
+            non_separator_tokens,
+            protected_patterns,
+            emoji_policy,
+            prefix_databases_disabled,
+            proximity_precision,
+            dictionary,
+            split_identifiers_fields,
+            synonyms,
+            typo_costs,
+        } = self;
+
+        if let Some(primary_key) = primary_key {
+            builder.set_primary_key(primary_key);
+        }
+        match searchable_fields {
+            Some(fields) => builder.set_searchable_fields(fields),
+            None => builder.reset_searchable_fields(),
+        }
+        match displayed_fields {
+            Some(fields) => builder.set_displayed_fields(fields),
+            None => builder.reset_displayed_fields(),
+        }
+        builder.set_filterable_fields(filterable_fields.into_iter().collect());
+        builder.set_sortable_fields(sortable_fields.into_iter().collect());
+        builder.set_criteria(criteria);
+        match distinct_field {
+            Some(field) => builder.set_distinct_field(field),
+            None => builder.reset_distinct_field(),
+        }
+        builder.set_stop_words(stop_words);
+        match separator_tokens {
+            Some(tokens) => builder.set_separator_tokens(tokens),
+            None => builder.reset_separator_tokens(),
+        }
+        match non_separator_tokens {
+            Some(tokens) => builder.set_non_separator_tokens(tokens),
+            None => builder.reset_non_separator_tokens(),
+        }
+        match protected_patterns {
+            Some(patterns) => builder.set_protected_patterns(patterns),
+            None => builder.reset_protected_patterns(),
+        }
+        builder.set_emoji_policy(emoji_policy);
+        builder.set_prefix_databases(!prefix_databases_disabled);
+        builder.set_proximity_precision(proximity_precision);
+        match dictionary {
+            Some(dictionary) => builder.set_dictionary(dictionary),
+            None => builder.reset_dictionary(),
+        }
+        builder.set_split_identifiers_fields(split_identifiers_fields.into_iter().collect());
+        builder.set_synonyms(synonyms.into_iter().collect());
+        builder.set_typo_costs(typo_costs);
+    }
+}
+
+/// Writes the settings and all the documents of the index into the writer,
+/// returns the number of documents written.
+///
+/// The documents that can't be decoded are logged and skipped, they are not written.
+pub fn export_dump<W: Write>(index: &Index, rtxn: &heed::RoTxn, mut writer: W) -> Result<u64> {
+    let header =
+        DumpHeader { version: DUMP_VERSION, settings: DumpSettings::from_index(index, rtxn)? };
+    serde_json::to_writer(&mut writer, &header).map_err(InternalError::SerdeJson)?;
+    writer.write_all(b"\n")?;
+
+    index.ensure_documents_stored(rtxn, "dump the index")?;
+    let fields_ids_map = index.fields_ids_map(rtxn)?;
+    let all_fields: Vec<_> = fields_ids_map.ids().collect();
+    let mut codec = index.documents_codec(rtxn)?;
+    let mut count = 0;
+    let iter = index.documents.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?;
+    let mut documents = SkipCorrupted::<OwnedType<BEU32>, ByteSlice>::new(db_name::DOCUMENTS, iter);
+    let mut skipped = 0;
+    for result in documents.by_ref() {
+        let (docid, bytes) = result?;
+        let document = codec
+            .decompress(bytes)
+            .and_then(|document| obkv_to_json(&all_fields, &fields_ids_map, document.as_obkv()));
+        let document = match document {
+            Ok(document) => document,
+            Err(error) => {
+                warn!("Skipping the corrupted document {}: {}", docid.get(), error);
+                skipped += 1;
+                continue;
+            }
+        };
+        serde_json::to_writer(&mut writer, &document).map_err(InternalError::SerdeJson)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    skipped += documents.skipped();
+    if skipped > 0 {
+        warn!("{} corrupted documents were not exported", skipped);
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Applies the settings of a dump to the index and indexes its documents, the documents
+/// already in the index with the same primary key are replaced. Returns the number of
+/// documents imported.
+pub fn import_dump<'i, R: BufRead>(
+    wtxn: &mut heed::RwTxn<'i, '_>,
+    index: &'i Index,
+    indexer_config: &IndexerConfig,
+    reader: R,
+) -> Result<u64> {
+    let mut lines = reader.lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let header: DumpHeader = serde_json::from_str(&header).map_err(UserError::SerdeJson)?;
+    if header.version != DUMP_VERSION {
+        return Err(UserError::InvalidDumpVersion(header.version).into());
+    }
+
+    // The primary key of an index can't be changed once set.
+    let mut settings = header.settings;
+    if index.primary_key(wtxn)?.is_some() {
+        settings.primary_key = None;
+    }
+
+    let mut builder = Settings::new(wtxn, index, indexer_config);
+    settings.apply(&mut builder);
+    builder.execute(|_| ())?;
+
+    let mut count = 0;
+    let mut batch = Vec::new();
+    loop {
+        let line = lines.next().transpose()?;
+        if let Some(line) = line.as_deref().filter(|line| !line.trim().is_empty()) {
+            batch.push(serde_json::from_str(line).map_err(UserError::SerdeJson)?);
+        }
+
+        if batch.len() == IMPORT_BATCH_SIZE || (line.is_none() && !batch.is_empty()) {
+            count += batch.len() as u64;
+            index_documents(wtxn, index, indexer_config, &batch)?;
+            batch.clear();
+        }
+
+        if line.is_none() {
+            return Ok(count);
+        }
+    }
+}
+
+fn index_documents<'i>(
+    wtxn: &mut heed::RwTxn<'i, '_>,
+    index: &'i Index,
+    indexer_config: &IndexerConfig,
+    documents: &[serde_json::Value],
+) -> Result<()> {
+    let mut writer = std::io::Cursor::new(Vec::new());
+    let mut builder = DocumentBatchBuilder::new(&mut writer)?;
+    let json = serde_json::to_vec(documents).map_err(InternalError::SerdeJson)?;
+    builder.extend_from_json(&json[..])?;
+    builder.finish()?;
+    writer.set_position(0);
+    let reader = DocumentBatchReader::from_reader(writer)?;
+
+    let config = IndexDocumentsConfig {
+        update_method: IndexDocumentsMethod::ReplaceDocuments,
+        ..Default::default()
+    };
+    let mut builder = IndexDocuments::new(wtxn, index, indexer_config, config, |_| ());
+    builder.add_documents(reader)?;
+    builder.execute()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::{btreeset, hashmap, hashset};
+
+    use super::*;
+
+    #[test]
+    fn export_and_import() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_primary_key(S("id"));
+        builder.set_filterable_fields(hashset! { S("genre") });
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.set_synonyms(hashmap! { S("dragon") => vec![S("wyvern")] });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the dragon", "genre": "fantasy" },
+            { "id": 1, "title": "the cooking book", "genre": "cooking", "pages": 120 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut dump = Vec::new();
+        assert_eq!(export_dump(&index, &rtxn, &mut dump).unwrap(), 2);
+        let settings = DumpSettings::from_index(&index, &rtxn).unwrap();
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let imported = Index::new(options, &path).unwrap();
+
+        let mut wtxn = imported.write_txn().unwrap();
+        assert_eq!(import_dump(&mut wtxn, &imported, &config, &dump[..]).unwrap(), 2);
+        wtxn.commit().unwrap();
+
+        let imported_rtxn = imported.read_txn().unwrap();
+        assert_eq!(DumpSettings::from_index(&imported, &imported_rtxn).unwrap(), settings);
+
+        let mut imported_dump = Vec::new();
+        export_dump(&imported, &imported_rtxn, &mut imported_dump).unwrap();
+        assert_eq!(imported_dump, dump);
+
+        let filter = crate::Filter::from_str("genre = cooking").unwrap().unwrap();
+        let crate::SearchResult { documents_ids, .. } =
+            imported.search(&imported_rtxn).filter(filter).execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+
+        // The dumps of another version are refused.
+        let dump = String::from_utf8(dump).unwrap().replacen("\"version\":1", "\"version\":0", 1);
+        let mut wtxn = imported.write_txn().unwrap();
+        let result = import_dump(&mut wtxn, &imported, &config, dump.as_bytes());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn export_skips_corrupted_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 0, "title": "the dragon" }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // A key that is not a document id and a document that is not JSON.
+        let raw = index.documents.remap_types::<ByteSlice, ByteSlice>();
+        raw.put(&mut wtxn, b"bad", b"").unwrap();
+        let mut writer = obkv::KvWriter::<_, crate::FieldId>::memory();
+        writer.insert(0, b"{not json").unwrap();
+        raw.put(&mut wtxn, &5u32.to_be_bytes(), &writer.into_inner().unwrap()).unwrap();
+
+        let mut dump = Vec::new();
+        assert_eq!(export_dump(&index, &wtxn, &mut dump).unwrap(), 1);
+        assert_eq!(dump.split(|b| *b == b'\n').filter(|line| !line.is_empty()).count(), 2);
+    }
+}
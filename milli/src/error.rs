@@ -1,13 +1,16 @@
 use std::collections::BTreeSet;
 use std::convert::Infallible;
 use std::error::Error as StdError;
+use std::ops::Range;
 use std::{fmt, io, str};
 
 use heed::{Error as HeedError, MdbError};
 use rayon::ThreadPoolBuildError;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
-use crate::{CriterionError, DocumentId, FieldId, SortError};
+use crate::update::DocumentIdPolicy;
+use crate::{documents, limits, CriterionError, DocumentId, FieldId, SortError};
 
 pub type Object = Map<String, Value>;
 
@@ -26,6 +29,7 @@ pub enum Error {
 pub enum InternalError {
     DatabaseClosing,
     DatabaseMissingEntry { db_name: &'static str, key: Option<&'static str> },
+    DocumentsBatch(documents::Error),
     FieldIdMapMissingEntry(FieldIdMapMissingEntry),
     Fst(fst::Error),
     GrenadInvalidCompressionType,
@@ -57,25 +61,186 @@ pub enum UserError {
     AttributeLimitReached,
     CriterionError(CriterionError),
     DocumentLimitReached,
+    DocumentsNotStored { operation: &'static str },
+    DocumentIdPolicyCannotBeChanged { current: DocumentIdPolicy },
+    InvalidAclField { document_id: Value, value: Value },
     InvalidDocumentId { document_id: Value },
+    InvalidDumpVersion(u32),
     InvalidFacetsDistribution { invalid_facets_name: BTreeSet<String> },
+    InvalidFacetValueRename { field: String, message: &'static str },
+    InvalidFieldRename { field: String, message: &'static str },
     InvalidGeoField { document_id: Value, object: Value },
-    InvalidFilter(String),
+    InvalidIndexVersion { version: u32, expected: u32 },
+    InvalidFilter(FilterParseError),
+    InvalidNumericFieldUpdate { field: String, message: &'static str },
+    InvalidProtectedPattern { pattern: String, message: String },
     InvalidSortableAttribute { field: String, valid_fields: BTreeSet<String> },
     SortRankingRuleMissing,
     InvalidStoreFile,
+    SequentialDocumentIdsExhausted,
     MaxDatabaseSizeReached,
+    MaxTmpDiskUsageReached { phase: &'static str, used: u64, max: u64 },
     MissingDocumentId { primary_key: String, document: Object },
     MissingPrimaryKey,
     NoSpaceLeftOnDevice,
     PrimaryKeyCannotBeChanged(String),
     SerdeJson(serde_json::Error),
     SortError(SortError),
+    UnknownExternalDocumentId { document_id: String },
     UnknownInternalDocumentId { document_id: DocumentId },
 }
 
+/// An invalid filter, along with the part of the expression that is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterParseError {
+    pub message: String,
+    /// The byte offsets of the invalid part in the filter expression,
+    /// `None` when the error isn't about a part of an expression.
+    pub span: Option<Range<usize>>,
+    /// The tokens that were expected at the start of the span, e.g. `AND` and `OR`.
+    pub expected: Vec<String>,
+}
+
+impl FilterParseError {
+    /// Returns an error that isn't about a part of an expression.
+    pub fn new(message: String) -> FilterParseError {
+        FilterParseError { message, span: None, expected: Vec::new() }
+    }
+}
+
+/// A stable and machine-readable code identifying the kind of an [`Error`],
+/// it is serialized in snake case, e.g. `invalid_filter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // The errors caused by the user.
+    AttributeLimitReached,
+    InvalidCriterion,
+    DocumentLimitReached,
+    DocumentsNotStored,
+    DocumentIdPolicyCannotBeChanged,
+    InvalidAclField,
+    InvalidDocumentId,
+    InvalidDumpVersion,
+    InvalidFacetsDistribution,
+    InvalidFacetValueRename,
+    InvalidFieldRename,
+    InvalidGeoField,
+    InvalidIndexVersion,
+    InvalidFilter,
+    InvalidNumericFieldUpdate,
+    InvalidProtectedPattern,
+    InvalidSortableAttribute,
+    SortRankingRuleMissing,
+    InvalidStoreFile,
+    SequentialDocumentIdsExhausted,
+    MaxDatabaseSizeReached,
+    MaxTmpDiskUsageReached,
+    MissingDocumentId,
+    MissingPrimaryKey,
+    NoSpaceLeftOnDevice,
+    PrimaryKeyAlreadyPresent,
+    MalformedPayload,
+    InvalidSort,
+    UnknownExternalDocumentId,
+    UnknownInternalDocumentId,
+    // The errors caused by the engine or the system.
+    Io,
+    DatabaseClosing,
+    DatabaseMissingEntry,
+    InvalidDocumentsBatch,
+    FieldIdMapMissingEntry,
+    Fst,
+    GrenadInvalidCompressionType,
+    GrenadInvalidFormatVersion,
+    IndexingMergingKeys,
+    InvalidDatabaseTyping,
+    ThreadPool,
+    SerdeJson,
+    Serialization,
+    Store,
+    Utf8,
+}
+
+impl Error {
+    /// Returns the code of this error, to identify it without matching on its message.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InternalError(error) => error.code(),
+            Self::IoError(_) => ErrorCode::Io,
+            Self::UserError(error) => error.code(),
+        }
+    }
+}
+
+impl InternalError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::DatabaseClosing => ErrorCode::DatabaseClosing,
+            Self::DatabaseMissingEntry { .. } => ErrorCode::DatabaseMissingEntry,
+            Self::DocumentsBatch(_) => ErrorCode::InvalidDocumentsBatch,
+            Self::FieldIdMapMissingEntry(_) => ErrorCode::FieldIdMapMissingEntry,
+            Self::Fst(_) => ErrorCode::Fst,
+            Self::GrenadInvalidCompressionType => ErrorCode::GrenadInvalidCompressionType,
+            Self::GrenadInvalidFormatVersion => ErrorCode::GrenadInvalidFormatVersion,
+            Self::IndexingMergingKeys { .. } => ErrorCode::IndexingMergingKeys,
+            Self::InvalidDatabaseTyping => ErrorCode::InvalidDatabaseTyping,
+            Self::RayonThreadPool(_) => ErrorCode::ThreadPool,
+            Self::SerdeJson(_) => ErrorCode::SerdeJson,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::Store(_) => ErrorCode::Store,
+            Self::Utf8(_) => ErrorCode::Utf8,
+        }
+    }
+}
+
+impl UserError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::AttributeLimitReached => ErrorCode::AttributeLimitReached,
+            Self::CriterionError(_) => ErrorCode::InvalidCriterion,
+            Self::DocumentLimitReached => ErrorCode::DocumentLimitReached,
+            Self::DocumentsNotStored { .. } => ErrorCode::DocumentsNotStored,
+            Self::DocumentIdPolicyCannotBeChanged { .. } => {
+                ErrorCode::DocumentIdPolicyCannotBeChanged
+            }
+            Self::InvalidAclField { .. } => ErrorCode::InvalidAclField,
+            Self::InvalidDocumentId { .. } => ErrorCode::InvalidDocumentId,
+            Self::InvalidDumpVersion(_) => ErrorCode::InvalidDumpVersion,
+            Self::InvalidFacetsDistribution { .. } => ErrorCode::InvalidFacetsDistribution,
+            Self::InvalidFacetValueRename { .. } => ErrorCode::InvalidFacetValueRename,
+            Self::InvalidFieldRename { .. } => ErrorCode::InvalidFieldRename,
+            Self::InvalidGeoField { .. } => ErrorCode::InvalidGeoField,
+            Self::InvalidIndexVersion { .. } => ErrorCode::InvalidIndexVersion,
+            Self::InvalidFilter(_) => ErrorCode::InvalidFilter,
+            Self::InvalidNumericFieldUpdate { .. } => ErrorCode::InvalidNumericFieldUpdate,
+            Self::InvalidProtectedPattern { .. } => ErrorCode::InvalidProtectedPattern,
+            Self::InvalidSortableAttribute { .. } => ErrorCode::InvalidSortableAttribute,
+            Self::SortRankingRuleMissing => ErrorCode::SortRankingRuleMissing,
+            Self::InvalidStoreFile => ErrorCode::InvalidStoreFile,
+            Self::SequentialDocumentIdsExhausted => ErrorCode::SequentialDocumentIdsExhausted,
+            Self::MaxDatabaseSizeReached => ErrorCode::MaxDatabaseSizeReached,
+            Self::MaxTmpDiskUsageReached { .. } => ErrorCode::MaxTmpDiskUsageReached,
+            Self::MissingDocumentId { .. } => ErrorCode::MissingDocumentId,
+            Self::MissingPrimaryKey => ErrorCode::MissingPrimaryKey,
+            Self::NoSpaceLeftOnDevice => ErrorCode::NoSpaceLeftOnDevice,
+            Self::PrimaryKeyCannotBeChanged(_) => ErrorCode::PrimaryKeyAlreadyPresent,
+            Self::SerdeJson(_) => ErrorCode::MalformedPayload,
+            Self::SortError(_) => ErrorCode::InvalidSort,
+            Self::UnknownExternalDocumentId { .. } => ErrorCode::UnknownExternalDocumentId,
+            Self::UnknownInternalDocumentId { .. } => ErrorCode::UnknownInternalDocumentId,
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Error {
+        // The user errors raised while writing, e.g. by the temporary files
+        // of the indexing, are wrapped into io errors by the writers.
+        if error.get_ref().map_or(false, |inner| inner.is::<UserError>()) {
+            let inner = error.into_inner().unwrap();
+            return Error::UserError(*inner.downcast::<UserError>().unwrap());
+        }
         // TODO must be improved and more precise
         Error::IoError(error)
     }
@@ -93,7 +258,7 @@ where
 {
     fn from(error: grenad::Error<E>) -> Error {
         match error {
-            grenad::Error::Io(error) => Error::IoError(error),
+            grenad::Error::Io(error) => Error::from(error),
             grenad::Error::Merge(error) => Error::from(error),
             grenad::Error::InvalidCompressionType => {
                 Error::InternalError(InternalError::GrenadInvalidCompressionType)
@@ -105,6 +270,15 @@ where
     }
 }
 
+impl From<documents::Error> for Error {
+    fn from(error: documents::Error) -> Error {
+        match error {
+            documents::Error::Io(error) => Error::from(error),
+            error => Error::InternalError(InternalError::DocumentsBatch(error)),
+        }
+    }
+}
+
 impl From<str::Utf8Error> for Error {
     fn from(error: str::Utf8Error) -> Error {
         Error::InternalError(InternalError::Utf8(error))
@@ -185,6 +359,7 @@ impl fmt::Display for InternalError {
             Self::DatabaseMissingEntry { db_name, key } => {
                 write!(f, "Missing {} in the {} database.", key.unwrap_or("key"), db_name)
             }
+            Self::DocumentsBatch(error) => error.fmt(f),
             Self::FieldIdMapMissingEntry(error) => error.fmt(f),
             Self::Fst(error) => error.fmt(f),
             Self::GrenadInvalidCompressionType => {
@@ -212,10 +387,14 @@ impl StdError for InternalError {}
 impl fmt::Display for UserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::InvalidFilter(error) => f.write_str(error),
-            Self::AttributeLimitReached => f.write_str("A document cannot contain more than 65,535 fields."),
+            Self::InvalidFilter(error) => f.write_str(&error.message),
+            Self::AttributeLimitReached => {
+                write!(f, "An index cannot contain more than {} fields.", limits::MAX_FIELDS)
+            }
             Self::CriterionError(error) => write!(f, "{}", error),
-            Self::DocumentLimitReached => f.write_str("Maximum number of documents reached."),
+            Self::DocumentLimitReached => {
+                write!(f, "An index cannot contain more than {} documents.", limits::MAX_DOCUMENTS)
+            }
             Self::InvalidFacetsDistribution { invalid_facets_name } => {
                 let name_list =
                     invalid_facets_name.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ");
@@ -225,6 +404,28 @@ impl fmt::Display for UserError {
                     name_list
                 )
             }
+            Self::InvalidFacetValueRename { field, message } => {
+                write!(
+                    f,
+                    "Values of attribute `{}` can't be renamed without reindexing: {}.",
+                    field, message
+                )
+            }
+            Self::InvalidFieldRename { field, message } => {
+                write!(f, "Attribute `{}` can't be renamed: {}.", field, message)
+            }
+            Self::InvalidAclField { document_id, value } => {
+                let document_id = match document_id {
+                    Value::String(id) => id.clone(),
+                    _ => document_id.to_string(),
+                };
+                write!(
+                    f,
+                    "The document with the id: `{}` contains an invalid _acl field: `{}`, \
+                    expected a string or an array of strings.",
+                    document_id, value
+                )
+            }
             Self::InvalidGeoField { document_id, object } => {
                 let document_id = match document_id {
                     Value::String(id) => id.clone(),
@@ -240,6 +441,21 @@ impl fmt::Display for UserError {
                     document_id, object
                 )
             },
+            Self::InvalidNumericFieldUpdate { field, message } => {
+                write!(f, "Attribute `{}` can't be updated without reindexing: {}.", field, message)
+            }
+            Self::InvalidProtectedPattern { pattern, message } => {
+                write!(f, "Invalid protected pattern `{}`: {}", pattern, message)
+            }
+            Self::InvalidDumpVersion(version) => {
+                write!(f, "The dump version `{}` is not supported.", version)
+            }
+            Self::InvalidIndexVersion { version, expected } => write!(
+                f,
+                "The index is written with the format version `{}` but the version `{}` is \
+expected, the indexes written with an older version must be migrated first.",
+                version, expected
+            ),
             Self::InvalidDocumentId { document_id } => {
                 let document_id = match document_id {
                     Value::String(id) => id.clone(),
@@ -268,17 +484,48 @@ ranking rules settings to use the sort parameter at search time.",
             ),
             Self::MissingDocumentId { primary_key, document } => {
                 let json = serde_json::to_string(document).unwrap();
-                write!(f, "Document doesn't have a `{}` attribute: `{}`.", primary_key, json)
+                if primary_key.contains('.') {
+                    let path = primary_key.split('.').collect::<Vec<_>>().join("` > `");
+                    write!(
+                        f,
+                        "Document doesn't have a `{}` attribute nor a value at the `{}` path of its nested objects: `{}`.",
+                        primary_key, path, json
+                    )
+                } else {
+                    write!(f, "Document doesn't have a `{}` attribute: `{}`.", primary_key, json)
+                }
             }
             Self::MissingPrimaryKey => f.write_str("The primary key inference process failed because the engine did not find any fields containing `id` substring in their name. If your document identifier does not contain any `id` substring, you can set the primary key of the index."),
             Self::MaxDatabaseSizeReached => f.write_str("Maximum database size has been reached."),
+            Self::MaxTmpDiskUsageReached { phase, used, max } => write!(
+                f,
+                "The temporary files of the indexing used {} bytes during the `{}` phase, \
+which is more than the maximum of {} bytes. Consider increasing the maximum temporary disk usage.",
+                used, phase, max
+            ),
             Self::NoSpaceLeftOnDevice => f.write_str("There is no more space left on the device. Consider increasing the size of the disk/partition."),
             Self::InvalidStoreFile => f.write_str("The database file is in an invalid state."),
+            Self::SequentialDocumentIdsExhausted => f.write_str(
+                "The sequential document ids are exhausted, the biggest integer document id is already used.",
+            ),
+            Self::DocumentsNotStored { operation } => write!(
+                f,
+                "The documents are not stored in this index, they are required to {}.",
+                operation
+            ),
+            Self::DocumentIdPolicyCannotBeChanged { current } => write!(
+                f,
+                "The document ids of the index are stored with the `{:?}` normalization and the `{:?}` charset, they can't be changed.",
+                current.normalization, current.charset
+            ),
             Self::PrimaryKeyCannotBeChanged(primary_key) => {
                 write!(f, "Index already has a primary key: `{}`.", primary_key)
             }
             Self::SerdeJson(error) => error.fmt(f),
             Self::SortError(error) => write!(f, "{}", error),
+            Self::UnknownExternalDocumentId { document_id } => {
+                write!(f, "An unknown external document id have been used: `{}`.", document_id)
+            }
             Self::UnknownInternalDocumentId { document_id } => {
                 write!(f, "An unknown internal document id have been used: `{}`.", document_id)
             }
@@ -320,3 +567,25 @@ impl fmt::Display for SerializationError {
 }
 
 impl StdError for SerializationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes() {
+        let error =
+            Error::from(UserError::InvalidFilter(FilterParseError::new(String::from("bad"))));
+        assert_eq!(error.code(), ErrorCode::InvalidFilter);
+        assert_eq!(serde_json::to_string(&error.code()).unwrap(), r#""invalid_filter""#);
+
+        let error = Error::from(HeedError::Mdb(MdbError::MapFull));
+        assert_eq!(error.code(), ErrorCode::MaxDatabaseSizeReached);
+
+        let error = Error::from(InternalError::DatabaseMissingEntry { db_name: "main", key: None });
+        assert_eq!(error.code(), ErrorCode::DatabaseMissingEntry);
+
+        let code: ErrorCode = serde_json::from_str(r#""primary_key_already_present""#).unwrap();
+        assert_eq!(code, ErrorCode::PrimaryKeyAlreadyPresent);
+    }
+}
@@ -0,0 +1,24 @@
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Parses an RFC3339 date, e.g. `2021-11-20T10:00:00+02:00`, into the number of
+/// milliseconds since the unix epoch, the dates are faceted and sorted by this timestamp.
+pub fn parse_date(value: &str) -> Option<i64> {
+    let date = OffsetDateTime::parse(value.trim(), &Rfc3339).ok()?;
+    Some((date.unix_timestamp_nanos() / 1_000_000) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rfc3339_dates() {
+        assert_eq!(parse_date("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_date("1970-01-01T00:00:01.5Z"), Some(1500));
+        assert_eq!(parse_date("1970-01-01T02:00:00+02:00"), Some(0));
+        assert_eq!(parse_date("1969-12-31T23:59:59Z"), Some(-1000));
+        assert_eq!(parse_date("2021-01-01"), None);
+        assert_eq!(parse_date("hello"), None);
+    }
+}
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 pub enum FacetType {
     String,
     Number,
+    /// The RFC3339 strings, also stored as strings.
+    Date,
 }
 
 impl fmt::Display for FacetType {
@@ -15,6 +17,7 @@ impl fmt::Display for FacetType {
         match self {
             FacetType::String => f.write_str("string"),
             FacetType::Number => f.write_str("number"),
+            FacetType::Date => f.write_str("date"),
         }
     }
 }
@@ -27,6 +30,8 @@ impl FromStr for FacetType {
             Ok(FacetType::String)
         } else if s.trim().eq_ignore_ascii_case("number") {
             Ok(FacetType::Number)
+        } else if s.trim().eq_ignore_ascii_case("date") {
+            Ok(FacetType::Date)
         } else {
             Err(InvalidFacetType)
         }
@@ -38,7 +43,7 @@ pub struct InvalidFacetType;
 
 impl fmt::Display for InvalidFacetType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(r#"Invalid facet type, must be "string" or "number""#)
+        f.write_str(r#"Invalid facet type, must be "string", "number" or "date""#)
     }
 }
 
@@ -13,6 +13,18 @@ pub fn f64_into_bytes(float: f64) -> Option<[u8; 8]> {
     None
 }
 
+/// Encodes the integer in bytes which are ordered like the integers.
+#[inline]
+pub fn i64_into_bytes(integer: i64) -> [u8; 8] {
+    xor_first_bit(integer.to_be_bytes())
+}
+
+/// Decodes the integer encoded by [`i64_into_bytes`].
+#[inline]
+pub fn i64_from_bytes(bytes: [u8; 8]) -> i64 {
+    i64::from_be_bytes(xor_first_bit(bytes))
+}
+
 #[inline]
 fn xor_first_bit(mut x: [u8; 8]) -> [u8; 8] {
     x[0] ^= 0x80;
@@ -46,4 +58,14 @@ mod tests {
         let vec: Vec<_> = [a, b, c, d, e].iter().cloned().map(f64_into_bytes).collect();
         assert!(is_sorted(&vec), "{:?}", vec);
     }
+
+    #[test]
+    fn ordered_i64_bytes() {
+        let integers = [i64::MIN, -13, -1, 0, 1, 43, i64::MAX];
+        let vec: Vec<_> = integers.iter().copied().map(i64_into_bytes).collect();
+        assert!(is_sorted(&vec), "{:?}", vec);
+
+        let decoded: Vec<_> = vec.into_iter().map(i64_from_bytes).collect();
+        assert_eq!(decoded, integers);
+    }
 }
@@ -0,0 +1,150 @@
+use std::collections::btree_map::Entry;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{FieldIdMapMissingEntry, InternalError};
+use crate::{FieldTypesDistribution, FieldsIdsMap, Result};
+
+/// The number of values of each JSON type that a field has in the documents,
+/// the values of the arrays are not counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldTypes {
+    pub string: u64,
+    pub number: u64,
+    pub boolean: u64,
+    pub object: u64,
+    pub array: u64,
+    pub null: u64,
+}
+
+impl FieldTypes {
+    /// Returns `true` if no value is counted.
+    pub fn is_empty(&self) -> bool {
+        *self == FieldTypes::default()
+    }
+
+    /// Counts the type of the value.
+    pub(crate) fn add(&mut self, value: &Value) {
+        *self.count_mut(value) += 1;
+    }
+
+    /// Uncounts the type of the value.
+    pub(crate) fn remove(&mut self, value: &Value) {
+        let count = self.count_mut(value);
+        *count = count.saturating_sub(1);
+    }
+
+    fn count_mut(&mut self, value: &Value) -> &mut u64 {
+        match value {
+            Value::String(_) => &mut self.string,
+            Value::Number(_) => &mut self.number,
+            Value::Bool(_) => &mut self.boolean,
+            Value::Object(_) => &mut self.object,
+            Value::Array(_) => &mut self.array,
+            Value::Null => &mut self.null,
+        }
+    }
+}
+
+/// Counts the types of the values of the document in the distribution.
+pub(crate) fn add_document_types(
+    distribution: &mut FieldTypesDistribution,
+    fields_ids_map: &FieldsIdsMap,
+    obkv: obkv::KvReaderU16,
+) -> Result<()> {
+    for (field_id, bytes) in obkv.iter() {
+        let name = fields_ids_map
+            .name(field_id)
+            .ok_or(FieldIdMapMissingEntry::FieldId { field_id, process: "add_document_types" })?;
+        let value: Value = serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?;
+        distribution.entry(name.to_string()).or_default().add(&value);
+    }
+    Ok(())
+}
+
+/// Removes the types of the values of the document from the distribution,
+/// the fields without any value left are removed.
+pub(crate) fn remove_document_types(
+    distribution: &mut FieldTypesDistribution,
+    fields_ids_map: &FieldsIdsMap,
+    obkv: obkv::KvReaderU16,
+) -> Result<()> {
+    for (field_id, bytes) in obkv.iter() {
+        let name = fields_ids_map.name(field_id).ok_or(FieldIdMapMissingEntry::FieldId {
+            field_id,
+            process: "remove_document_types",
+        })?;
+        let value: Value = serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?;
+        if let Entry::Occupied(mut entry) = distribution.entry(name.to_string()) {
+            entry.get_mut().remove(&value);
+            if entry.get().is_empty() {
+                entry.remove();
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::{DeleteDocuments, IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+    use crate::Index;
+
+    #[test]
+    fn field_types_distribution() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "price": 10, "tags": ["a"], "meta": { "a": 1 } },
+            { "id": 1, "price": "cheap", "tags": null },
+            { "id": 2, "price": 20, "tags": true }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let distribution = index.field_types_distribution(&rtxn).unwrap();
+        assert_eq!(distribution["id"], FieldTypes { number: 3, ..Default::default() });
+        assert_eq!(
+            distribution["price"],
+            FieldTypes { number: 2, string: 1, ..Default::default() }
+        );
+        assert_eq!(
+            distribution["tags"],
+            FieldTypes { array: 1, null: 1, boolean: 1, ..Default::default() }
+        );
+        assert_eq!(distribution["meta"], FieldTypes { object: 1, ..Default::default() });
+        drop(rtxn);
+
+        // The types of the replaced and deleted documents are not counted anymore.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 1, "price": 15 }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("0");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let distribution = index.field_types_distribution(&rtxn).unwrap();
+        assert_eq!(distribution["id"], FieldTypes { number: 2, ..Default::default() });
+        assert_eq!(distribution["price"], FieldTypes { number: 2, ..Default::default() });
+        assert_eq!(distribution["tags"], FieldTypes { boolean: 1, ..Default::default() });
+        assert!(!distribution.contains_key("meta"));
+    }
+}
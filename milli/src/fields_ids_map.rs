@@ -59,6 +59,18 @@ impl FieldsIdsMap {
         }
     }
 
+    /// Gives a new name to a field, it keeps its id. Returns `None` if the field is not known
+    /// or if the new name is already used by another field.
+    pub fn rename(&mut self, name: &str, new_name: &str) -> Option<FieldId> {
+        if self.names_ids.contains_key(new_name) {
+            return None;
+        }
+        let id = self.names_ids.remove(name)?;
+        self.names_ids.insert(new_name.to_owned(), id);
+        self.ids_names.insert(id, new_name.to_owned());
+        Some(id)
+    }
+
     /// Iterate over the ids and names in the ids order.
     pub fn iter(&self) -> impl Iterator<Item = (FieldId, &str)> {
         self.ids_names.iter().map(|(id, name)| (*id, name.as_str()))
@@ -119,5 +131,14 @@ mod tests {
         assert_eq!(iter.next(), Some((2, "description")));
         assert_eq!(iter.next(), Some((3, "title")));
         assert_eq!(iter.next(), None);
+        drop(iter);
+
+        assert_eq!(map.rename("title", "name"), Some(3));
+        assert_eq!(map.rename("title", "name"), None);
+        assert_eq!(map.rename("name", "id"), None);
+        assert_eq!(map.id("name"), Some(3));
+        assert_eq!(map.id("title"), None);
+        assert_eq!(map.name(3), Some("name"));
+        assert_eq!(map.len(), 3);
     }
 }
@@ -0,0 +1,29 @@
+use std::borrow::Cow;
+
+use crate::facet::value_encoding::{i64_from_bytes, i64_into_bytes};
+use crate::{try_split_array_at, FieldId};
+
+/// Encodes the field id and the timestamp of a date, the dates of a field are ordered.
+pub struct FieldIdDateCodec;
+
+impl<'a> heed::BytesDecode<'a> for FieldIdDateCodec {
+    type DItem = (FieldId, i64);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let (field_id_bytes, bytes) = try_split_array_at(bytes)?;
+        let field_id = u16::from_be_bytes(field_id_bytes);
+        let (timestamp_bytes, _nothing) = try_split_array_at(bytes)?;
+        Some((field_id, i64_from_bytes(timestamp_bytes)))
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for FieldIdDateCodec {
+    type EItem = (FieldId, i64);
+
+    fn bytes_encode((field_id, timestamp): &Self::EItem) -> Option<Cow<[u8]>> {
+        let mut bytes = Vec::with_capacity(2 + 8);
+        bytes.extend_from_slice(&field_id.to_be_bytes());
+        bytes.extend_from_slice(&i64_into_bytes(*timestamp));
+        Some(Cow::Owned(bytes))
+    }
+}
@@ -36,3 +36,37 @@ impl<'a> heed::BytesEncode<'a> for StrBEU32Codec {
         Some(Cow::Owned(bytes))
     }
 }
+
+pub struct StrBEU16Codec;
+
+impl<'a> heed::BytesDecode<'a> for StrBEU16Codec {
+    type DItem = (&'a str, u16);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let footer_len = size_of::<u16>();
+
+        if bytes.len() < footer_len {
+            return None;
+        }
+
+        let (word, bytes) = bytes.split_at(bytes.len() - footer_len);
+        let word = str::from_utf8(word).ok()?;
+        let fid = bytes.try_into().map(u16::from_be_bytes).ok()?;
+
+        Some((word, fid))
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for StrBEU16Codec {
+    type EItem = (&'a str, u16);
+
+    fn bytes_encode((word, fid): &Self::EItem) -> Option<Cow<[u8]>> {
+        let fid = fid.to_be_bytes();
+
+        let mut bytes = Vec::with_capacity(word.len() + fid.len());
+        bytes.extend_from_slice(word.as_bytes());
+        bytes.extend_from_slice(&fid[..]);
+
+        Some(Cow::Owned(bytes))
+    }
+}
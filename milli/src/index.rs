@@ -1,68 +1,131 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{Read, Write};
 use std::mem::size_of;
 use std::path::Path;
+use std::time::Duration;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 
 use heed::flags::Flags;
 use heed::types::*;
-use heed::{Database, PolyDatabase, RoTxn, RwTxn};
+use heed::{CompactionOption, Database, PolyDatabase, RoTxn, RwTxn};
 use roaring::RoaringBitmap;
 use rstar::RTree;
+use serde_json::{Map, Value};
 use time::OffsetDateTime;
+use uuid::Uuid;
 
-use crate::error::{InternalError, UserError};
+use crate::documents_compression::DocumentsCodec;
+use crate::error::{Error, InternalError, SerializationError, UserError};
 use crate::fields_ids_map::FieldsIdsMap;
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
-    FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
+    FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec, FieldIdDateCodec,
 };
+use crate::tokenization::CompiledPatternsCache;
+use crate::update::{DocumentIdPolicy, PrefixPairProximityCaps};
 use crate::{
-    default_criteria, BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec, Criterion,
-    DocumentId, ExternalDocumentsIds, FacetDistribution, FieldDistribution, FieldId,
-    FieldIdWordCountCodec, GeoPoint, ObkvCodec, Result, RoaringBitmapCodec, RoaringBitmapLenCodec,
-    Search, StrBEU32Codec, StrStrU8Codec, BEU32,
+    default_criteria, obkv_to_json, relative_from_absolute_position, BEU32StrCodec,
+    BoRoaringBitmapCodec, CboRoaringBitmapCodec, CboRoaringBitmapLenCodec, Criterion, DocumentId,
+    DocumentsQuery, EmojiPolicy, ExternalDocumentsIds, FacetDistribution, FieldDistribution,
+    FieldId, FieldIdWordCountCodec, FieldTypesDistribution, Filter, GeoPoint, ObkvCodec,
+    ProtectedPattern, ProximityPrecision, Result, RoaringBitmapCodec, RoaringBitmapLenCodec,
+    Search, StoredDocument, StoredQuery, StrBEU16Codec, StrBEU32Codec, StrStrU8Codec, TypoCosts,
+    BEU32, BEU64,
 };
 
+/// The first bytes of the snapshots written by [`Index::snapshot_to`].
+const SNAPSHOT_MAGIC: &[u8; 8] = b"MILLISNP";
+/// The version of the format of the snapshots, bumped on every incompatible change.
+const SNAPSHOT_VERSION: u32 = 1;
+/// The interval at which the progress of [`Index::copy_to_path`] is reported.
+const COPY_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The number of LMDB databases of an index, the environments shared by several indexes
+/// must be opened with as many databases per index.
+pub const NUMBER_OF_DATABASES: u32 = 19;
+
+/// The version of the layout of the databases of the index, bumped on every incompatible
+/// change. The indexes written with an older version are upgraded by [`crate::migrate`].
+pub const FORMAT_VERSION: u32 = 4;
+
+/// The reserved field containing the access control tokens of a document, a string or an
+/// array of strings, the documents are restricted to some tokens with [`Search::with_acl`].
+pub const ACL_FIELD_NAME: &str = "_acl";
+
 pub mod main_key {
     pub const CRITERIA_KEY: &str = "criteria";
     pub const DISPLAYED_FIELDS_KEY: &str = "displayed-fields";
+    pub const DICTIONARY_KEY: &str = "dictionary";
     pub const DISTINCT_FIELD_KEY: &str = "distinct-field-key";
+    pub const DOCUMENTS_DICTIONARY_KEY: &str = "documents-dictionary";
+    pub const DOCUMENT_ID_POLICY_KEY: &str = "document-id-policy";
     pub const DOCUMENTS_IDS_KEY: &str = "documents-ids";
+    pub const EMOJI_POLICY_KEY: &str = "emoji-policy";
     pub const FILTERABLE_FIELDS_KEY: &str = "filterable-fields";
+    pub const FLATTENED_FACETED_FIELDS_KEY: &str = "flattened-faceted-fields";
+    pub const FORMAT_VERSION_KEY: &str = "format-version";
     pub const SORTABLE_FIELDS_KEY: &str = "sortable-fields";
+    pub const SPLIT_IDENTIFIERS_FIELDS_KEY: &str = "split-identifiers-fields";
     pub const FIELD_DISTRIBUTION_KEY: &str = "fields-distribution";
+    pub const FIELD_TYPES_DISTRIBUTION_KEY: &str = "field-types-distribution";
     pub const FIELDS_IDS_MAP_KEY: &str = "fields-ids-map";
     pub const GEO_FACETED_DOCUMENTS_IDS_KEY: &str = "geo-faceted-documents-ids";
     pub const GEO_RTREE_KEY: &str = "geo-rtree";
+    pub const FACET_ID_IS_FALSE_DOCIDS_PREFIX: &str = "facet-id-is-false-docids";
+    pub const FACET_ID_IS_TRUE_DOCIDS_PREFIX: &str = "facet-id-is-true-docids";
     pub const HARD_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "hard-external-documents-ids";
+    pub const INDEX_UUID_KEY: &str = "index-uuid";
     pub const NUMBER_FACETED_DOCUMENTS_IDS_PREFIX: &str = "number-faceted-documents-ids";
+    pub const PREFIX_DATABASES_KEY: &str = "prefix-databases";
+    pub const PREFIX_PAIR_PROXIMITY_CAPS_KEY: &str = "prefix-pair-proximity-caps";
     pub const PRIMARY_KEY_KEY: &str = "primary-key";
+    pub const PROTECTED_PATTERNS_KEY: &str = "protected-patterns";
+    pub const PROXIMITY_PRECISION_KEY: &str = "proximity-precision";
+    pub const PROXIMITY_WINDOW_KEY: &str = "proximity-window";
+    pub const RESERVED_DOCUMENTS_IDS_KEY: &str = "reserved-documents-ids";
     pub const SEARCHABLE_FIELDS_KEY: &str = "searchable-fields";
+    pub const SEPARATOR_TOKENS_KEY: &str = "separator-tokens";
+    pub const SETTINGS_GENERATION_KEY: &str = "settings-generation";
+    pub const NON_SEPARATOR_TOKENS_KEY: &str = "non-separator-tokens";
     pub const SOFT_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "soft-external-documents-ids";
     pub const STOP_WORDS_KEY: &str = "stop-words";
+    pub const STORE_DOCUMENTS_KEY: &str = "store-documents";
+    pub const STORED_QUERIES_KEY: &str = "stored-queries";
     pub const STRING_FACETED_DOCUMENTS_IDS_PREFIX: &str = "string-faceted-documents-ids";
     pub const SYNONYMS_KEY: &str = "synonyms";
+    pub const TYPO_COSTS_KEY: &str = "typo-costs";
     pub const WORDS_FST_KEY: &str = "words-fst";
     pub const WORDS_PREFIXES_FST_KEY: &str = "words-prefixes-fst";
     pub const CREATED_AT_KEY: &str = "created-at";
     pub const UPDATED_AT_KEY: &str = "updated-at";
+    pub const USER_METADATA_PREFIX: &str = "user-metadata";
 }
 
 pub mod db_name {
     pub const MAIN: &str = "main";
     pub const WORD_DOCIDS: &str = "word-docids";
+    pub const EXACT_WORD_DOCIDS: &str = "exact-word-docids";
     pub const WORD_PREFIX_DOCIDS: &str = "word-prefix-docids";
     pub const DOCID_WORD_POSITIONS: &str = "docid-word-positions";
     pub const WORD_PAIR_PROXIMITY_DOCIDS: &str = "word-pair-proximity-docids";
     pub const WORD_PREFIX_PAIR_PROXIMITY_DOCIDS: &str = "word-prefix-pair-proximity-docids";
     pub const WORD_POSITION_DOCIDS: &str = "word-position-docids";
     pub const WORD_PREFIX_POSITION_DOCIDS: &str = "word-prefix-position-docids";
+    pub const WORD_FID_DOCIDS: &str = "word-fid-docids";
     pub const FIELD_ID_WORD_COUNT_DOCIDS: &str = "field-id-word-count-docids";
     pub const FACET_ID_F64_DOCIDS: &str = "facet-id-f64-docids";
     pub const FACET_ID_STRING_DOCIDS: &str = "facet-id-string-docids";
+    pub const FACET_ID_DATE_DOCIDS: &str = "facet-id-date-docids";
     pub const FIELD_ID_DOCID_FACET_F64S: &str = "field-id-docid-facet-f64s";
     pub const FIELD_ID_DOCID_FACET_STRINGS: &str = "field-id-docid-facet-strings";
     pub const DOCUMENTS: &str = "documents";
+    pub const DOCID_EXTERNAL_IDS: &str = "docid-external-ids";
+    pub const ACL_TOKEN_DOCIDS: &str = "acl-token-docids";
 }
 
 #[derive(Clone)]
@@ -75,6 +138,9 @@ pub struct Index {
 
     /// A word and all the documents ids containing the word.
     pub word_docids: Database<Str, RoaringBitmapCodec>,
+    /// A word as written in the documents, only lowercased, and all the documents ids
+    /// containing it, the exactness criterion ranks the documents with it.
+    pub exact_word_docids: Database<Str, RoaringBitmapCodec>,
     /// A prefix of word and all the documents ids containing this prefix.
     pub word_prefix_docids: Database<Str, RoaringBitmapCodec>,
 
@@ -92,11 +158,16 @@ pub struct Index {
     pub field_id_word_count_docids: Database<FieldIdWordCountCodec, CboRoaringBitmapCodec>,
     /// Maps the position of a word prefix with all the docids where this prefix appears.
     pub word_prefix_position_docids: Database<StrBEU32Codec, CboRoaringBitmapCodec>,
+    /// Maps the word and the field id with the docids that corresponds to it.
+    pub word_fid_docids: Database<StrBEU16Codec, CboRoaringBitmapCodec>,
 
     /// Maps the facet field id, level and the number with the docids that corresponds to it.
     pub facet_id_f64_docids: Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
     /// Maps the facet field id and the string with the original string and docids that corresponds to it.
     pub facet_id_string_docids: Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+    /// Maps the facet field id and the timestamp of the RFC3339 strings with the docids
+    /// that corresponds to it, the timestamps are in milliseconds since the unix epoch.
+    pub facet_id_date_docids: Database<FieldIdDateCodec, CboRoaringBitmapCodec>,
 
     /// Maps the document id, the facet field id and the numbers.
     pub field_id_docid_facet_f64s: Database<FieldDocIdFacetF64Codec, Unit>,
@@ -105,56 +176,155 @@ pub struct Index {
 
     /// Maps the document id to the document as an obkv store.
     pub documents: Database<OwnedType<BEU32>, ObkvCodec>,
+    /// Maps the document id to its external document id, the value of its primary key.
+    pub docid_external_ids: Database<OwnedType<BEU32>, Str>,
+
+    /// Maps the access control tokens of the `_acl` field with the docids containing them.
+    pub acl_token_docids: Database<Str, RoaringBitmapCodec>,
+
+    /// The protected patterns and the dictionary compiled for the current settings generation.
+    pub(crate) compiled_patterns: CompiledPatternsCache,
 }
 
 impl Index {
-    pub fn new<P: AsRef<Path>>(mut options: heed::EnvOpenOptions, path: P) -> Result<Index> {
-        use db_name::*;
+    /// Opens or creates the index at the given path, the indexes written with another
+    /// format version are refused, the older ones can be upgraded by [`crate::migrate`].
+    pub fn new<P: AsRef<Path>>(options: heed::EnvOpenOptions, path: P) -> Result<Index> {
+        let index = Index::new_unchecked(options, path)?;
+        Index::check_format_version(&index.env, index.main)?;
+        Ok(index)
+    }
+
+    /// Opens the existing index at the given path in read-only mode, no write transaction can
+    /// be created from the returned index. The indexes written with another format version
+    /// are refused.
+    ///
+    /// LMDB still writes its lock file, unless the `MdbNoLock` flag is set on the options,
+    /// in which case the index must not be written by another process while it is opened.
+    pub fn open_read_only<P: AsRef<Path>>(
+        mut options: heed::EnvOpenOptions,
+        path: P,
+    ) -> Result<ReadOnlyIndex> {
+        options.max_dbs(NUMBER_OF_DATABASES);
+        unsafe { options.flag(Flags::MdbRdOnly) };
 
-        options.max_dbs(14);
+        let env = options.open(path)?;
+        // The databases added since an older format are missing, they can't be opened before
+        // the version of the index is checked.
+        let main = env
+            .open_poly_database(Some(db_name::MAIN))?
+            .ok_or(InternalError::DatabaseMissingEntry { db_name: db_name::MAIN, key: None })?;
+        Index::check_format_version(&env, main)?;
+        let index = Index::open_databases(env, None, false)?;
+        Ok(ReadOnlyIndex { index })
+    }
+
+    /// Opens or creates the index at the given path whatever its format version.
+    pub(crate) fn new_unchecked<P: AsRef<Path>>(
+        mut options: heed::EnvOpenOptions,
+        path: P,
+    ) -> Result<Index> {
+        options.max_dbs(NUMBER_OF_DATABASES);
         unsafe { options.flag(Flags::MdbAlwaysFreePages) };
 
         let env = options.open(path)?;
-        let main = env.create_poly_database(Some(MAIN))?;
-        let word_docids = env.create_database(Some(WORD_DOCIDS))?;
-        let word_prefix_docids = env.create_database(Some(WORD_PREFIX_DOCIDS))?;
-        let docid_word_positions = env.create_database(Some(DOCID_WORD_POSITIONS))?;
-        let word_pair_proximity_docids = env.create_database(Some(WORD_PAIR_PROXIMITY_DOCIDS))?;
-        let word_prefix_pair_proximity_docids =
-            env.create_database(Some(WORD_PREFIX_PAIR_PROXIMITY_DOCIDS))?;
-        let word_position_docids = env.create_database(Some(WORD_POSITION_DOCIDS))?;
-        let field_id_word_count_docids = env.create_database(Some(FIELD_ID_WORD_COUNT_DOCIDS))?;
-        let word_prefix_position_docids = env.create_database(Some(WORD_PREFIX_POSITION_DOCIDS))?;
-        let facet_id_f64_docids = env.create_database(Some(FACET_ID_F64_DOCIDS))?;
-        let facet_id_string_docids = env.create_database(Some(FACET_ID_STRING_DOCIDS))?;
-        let field_id_docid_facet_f64s = env.create_database(Some(FIELD_ID_DOCID_FACET_F64S))?;
-        let field_id_docid_facet_strings =
-            env.create_database(Some(FIELD_ID_DOCID_FACET_STRINGS))?;
-        let documents = env.create_database(Some(DOCUMENTS))?;
-
-        Index::initialize_creation_dates(&env, main)?;
+        let index = Index::open_databases(env, None, true)?;
+        Index::initialize_metadata(&index.env, index.main)?;
+        Ok(index)
+    }
+
+    /// Opens or creates the index of the given name in an environment shared by several
+    /// indexes, the names of its databases are prefixed by the name of the index. The
+    /// environment must be opened with [`NUMBER_OF_DATABASES`] databases per index.
+    ///
+    /// The indexes written with another format version are refused. Closing, copying or
+    /// snapshotting the environment applies to all of its indexes.
+    pub fn open_in_env(env: &heed::Env, name: &str) -> Result<Index> {
+        let index = Index::open_databases(env.clone(), Some(name), true)?;
+        Index::initialize_metadata(&index.env, index.main)?;
+        Index::check_format_version(&index.env, index.main)?;
+        Ok(index)
+    }
+
+    /// Opens the databases of the environment, they are created if they don't exist yet
+    /// unless the environment is read-only. The names of the databases of an index sharing
+    /// its environment are prefixed with the name of the index.
+    fn open_databases(env: heed::Env, prefix: Option<&str>, create: bool) -> Result<Index> {
+        use db_name::*;
+
+        // The names of the databases never contain the separator, they can't collide.
+        let database_name = |name: &str| match prefix {
+            Some(prefix) => format!("{}:{}", prefix, name),
+            None => name.to_string(),
+        };
+
+        macro_rules! database {
+            ($open:ident, $create:ident, $name:expr) => {
+                if create {
+                    env.$create(Some(database_name($name).as_str()))?
+                } else {
+                    env.$open(Some(database_name($name).as_str()))?
+                        .ok_or(InternalError::DatabaseMissingEntry { db_name: $name, key: None })?
+                }
+            };
+            ($name:expr) => {
+                database!(open_database, create_database, $name)
+            };
+        }
 
         Ok(Index {
+            main: database!(open_poly_database, create_poly_database, MAIN),
+            word_docids: database!(WORD_DOCIDS),
+            exact_word_docids: database!(EXACT_WORD_DOCIDS),
+            word_prefix_docids: database!(WORD_PREFIX_DOCIDS),
+            docid_word_positions: database!(DOCID_WORD_POSITIONS),
+            word_pair_proximity_docids: database!(WORD_PAIR_PROXIMITY_DOCIDS),
+            word_prefix_pair_proximity_docids: database!(WORD_PREFIX_PAIR_PROXIMITY_DOCIDS),
+            word_position_docids: database!(WORD_POSITION_DOCIDS),
+            word_prefix_position_docids: database!(WORD_PREFIX_POSITION_DOCIDS),
+            word_fid_docids: database!(WORD_FID_DOCIDS),
+            field_id_word_count_docids: database!(FIELD_ID_WORD_COUNT_DOCIDS),
+            facet_id_f64_docids: database!(FACET_ID_F64_DOCIDS),
+            facet_id_string_docids: database!(FACET_ID_STRING_DOCIDS),
+            facet_id_date_docids: database!(FACET_ID_DATE_DOCIDS),
+            field_id_docid_facet_f64s: database!(FIELD_ID_DOCID_FACET_F64S),
+            field_id_docid_facet_strings: database!(FIELD_ID_DOCID_FACET_STRINGS),
+            documents: database!(DOCUMENTS),
+            docid_external_ids: database!(DOCID_EXTERNAL_IDS),
+            acl_token_docids: database!(ACL_TOKEN_DOCIDS),
+            compiled_patterns: CompiledPatternsCache::default(),
             env,
-            main,
-            word_docids,
-            word_prefix_docids,
-            docid_word_positions,
-            word_pair_proximity_docids,
-            word_prefix_pair_proximity_docids,
-            word_position_docids,
-            word_prefix_position_docids,
-            field_id_word_count_docids,
-            facet_id_f64_docids,
-            facet_id_string_docids,
-            field_id_docid_facet_f64s,
-            field_id_docid_facet_strings,
-            documents,
         })
     }
 
-    fn initialize_creation_dates(env: &heed::Env, main: PolyDatabase) -> heed::Result<()> {
+    /// Returns an error if the index is not written with the current format version,
+    /// only its main database is read.
+    fn check_format_version(env: &heed::Env, main: PolyDatabase) -> Result<()> {
+        let rtxn = env.read_txn()?;
+        let version = main
+            .get::<_, Str, OwnedType<BEU32>>(&rtxn, main_key::FORMAT_VERSION_KEY)?
+            .map_or(0, |version| version.get());
+        if version == FORMAT_VERSION {
+            Ok(())
+        } else {
+            Err(UserError::InvalidIndexVersion { version, expected: FORMAT_VERSION }.into())
+        }
+    }
+
+    fn initialize_metadata(env: &heed::Env, main: PolyDatabase) -> heed::Result<()> {
         let mut txn = env.write_txn()?;
+        let mut modified = false;
+
+        // The db was just created, it is written with the current format.
+        if main.is_empty(&txn)? {
+            main.put::<_, Str, OwnedType<BEU32>>(
+                &mut txn,
+                main_key::FORMAT_VERSION_KEY,
+                &BEU32::new(FORMAT_VERSION),
+            )?;
+            modified = true;
+        }
+
         // The db was just created, we update its metadata with the relevant information.
         if main.get::<_, Str, SerdeJson<OffsetDateTime>>(&txn, main_key::CREATED_AT_KEY)?.is_none()
         {
@@ -169,6 +339,18 @@ impl Index {
                 main_key::CREATED_AT_KEY,
                 &now,
             )?;
+            modified = true;
+        }
+
+        // The indexes created before the uuid was introduced are given one the first time
+        // they are opened, it then stays the same for the whole life of the index.
+        if main.get::<_, Str, Str>(&txn, main_key::INDEX_UUID_KEY)?.is_none() {
+            let uuid = Uuid::new_v4().to_hyphenated().to_string();
+            main.put::<_, Str, Str>(&mut txn, main_key::INDEX_UUID_KEY, &uuid)?;
+            modified = true;
+        }
+
+        if modified {
             txn.commit()?;
         }
         Ok(())
@@ -184,6 +366,26 @@ impl Index {
         self.env.read_txn()
     }
 
+    /// Commits the write transaction and calls the hook around the commit, the transaction
+    /// is aborted when [`CommitHook::before_commit`] returns an error.
+    pub fn commit_with_hook<H: CommitHook>(&self, mut wtxn: RwTxn, hook: &mut H) -> Result<()> {
+        let result = match hook.before_commit(self, &mut wtxn) {
+            Ok(()) => wtxn.commit().map_err(Into::into),
+            Err(error) => {
+                wtxn.abort()?;
+                Err(error)
+            }
+        };
+
+        match result {
+            Ok(()) => hook.after_commit(self),
+            Err(error) => {
+                hook.aborted(self, &error);
+                Err(error)
+            }
+        }
+    }
+
     /// Returns the canonicalized path where the heed `Env` of this `Index` lives.
     pub fn path(&self) -> &Path {
         self.env.path()
@@ -198,6 +400,179 @@ impl Index {
         self.env.prepare_for_closing()
     }
 
+    /* snapshots */
+
+    /// Returns all the databases of the index with their names.
+    fn databases(&self) -> Vec<(&'static str, PolyDatabase)> {
+        use db_name::*;
+
+        vec![
+            (MAIN, self.main),
+            (WORD_DOCIDS, *self.word_docids.as_polymorph()),
+            (EXACT_WORD_DOCIDS, *self.exact_word_docids.as_polymorph()),
+            (WORD_PREFIX_DOCIDS, *self.word_prefix_docids.as_polymorph()),
+            (DOCID_WORD_POSITIONS, *self.docid_word_positions.as_polymorph()),
+            (WORD_PAIR_PROXIMITY_DOCIDS, *self.word_pair_proximity_docids.as_polymorph()),
+            (
+                WORD_PREFIX_PAIR_PROXIMITY_DOCIDS,
+                *self.word_prefix_pair_proximity_docids.as_polymorph(),
+            ),
+            (WORD_POSITION_DOCIDS, *self.word_position_docids.as_polymorph()),
+            (WORD_PREFIX_POSITION_DOCIDS, *self.word_prefix_position_docids.as_polymorph()),
+            (WORD_FID_DOCIDS, *self.word_fid_docids.as_polymorph()),
+            (FIELD_ID_WORD_COUNT_DOCIDS, *self.field_id_word_count_docids.as_polymorph()),
+            (FACET_ID_F64_DOCIDS, *self.facet_id_f64_docids.as_polymorph()),
+            (FACET_ID_STRING_DOCIDS, *self.facet_id_string_docids.as_polymorph()),
+            (FACET_ID_DATE_DOCIDS, *self.facet_id_date_docids.as_polymorph()),
+            (FIELD_ID_DOCID_FACET_F64S, *self.field_id_docid_facet_f64s.as_polymorph()),
+            (FIELD_ID_DOCID_FACET_STRINGS, *self.field_id_docid_facet_strings.as_polymorph()),
+            (DOCUMENTS, *self.documents.as_polymorph()),
+            (DOCID_EXTERNAL_IDS, *self.docid_external_ids.as_polymorph()),
+            (ACL_TOKEN_DOCIDS, *self.acl_token_docids.as_polymorph()),
+        ]
+    }
+
+    /// Writes a gzip compressed copy of all the databases of the index, as seen by the given
+    /// transaction, into the writer. The snapshot is consistent even if some write transactions
+    /// are committed while it is written and can be restored with [`Index::restore_snapshot`].
+    pub fn snapshot_to<W: Write>(&self, rtxn: &RoTxn, writer: W) -> Result<W> {
+        let mut writer = GzEncoder::new(writer, Compression::default());
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_u32::<BigEndian>(SNAPSHOT_VERSION)?;
+
+        let databases = self.databases();
+        writer.write_u16::<BigEndian>(databases.len() as u16)?;
+        for (name, database) in databases {
+            writer.write_u16::<BigEndian>(name.len() as u16)?;
+            writer.write_all(name.as_bytes())?;
+
+            writer.write_u64::<BigEndian>(database.len(rtxn)?)?;
+            for result in database.iter::<_, ByteSlice, ByteSlice>(rtxn)? {
+                let (key, value) = result?;
+                writer.write_u32::<BigEndian>(key.len() as u32)?;
+                writer.write_all(key)?;
+                writer.write_u32::<BigEndian>(value.len() as u32)?;
+                writer.write_all(value)?;
+            }
+        }
+
+        Ok(writer.finish()?)
+    }
+
+    /// Creates an index at the given path from a snapshot written by [`Index::snapshot_to`],
+    /// the content of the index that may already exist at this path is replaced.
+    pub fn restore_snapshot<P: AsRef<Path>, R: Read>(
+        options: heed::EnvOpenOptions,
+        path: P,
+        reader: R,
+    ) -> Result<Index> {
+        fn read_bytes<R: Read>(reader: &mut R, buffer: &mut Vec<u8>, len: usize) -> Result<()> {
+            buffer.resize(len, 0);
+            reader.read_exact(buffer)?;
+            Ok(())
+        }
+
+        let mut reader = GzDecoder::new(reader);
+        let mut magic = [0; SNAPSHOT_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC || reader.read_u32::<BigEndian>()? != SNAPSHOT_VERSION {
+            return Err(UserError::InvalidStoreFile.into());
+        }
+
+        let index = Index::new_unchecked(options, path)?;
+        let databases: HashMap<_, _> = index.databases().into_iter().collect();
+        let mut wtxn = index.write_txn()?;
+        for database in databases.values() {
+            database.clear(&mut wtxn)?;
+        }
+
+        let (mut key, mut value) = (Vec::new(), Vec::new());
+        for _ in 0..reader.read_u16::<BigEndian>()? {
+            let len = reader.read_u16::<BigEndian>()? as usize;
+            read_bytes(&mut reader, &mut key, len)?;
+            let database = std::str::from_utf8(&key)
+                .ok()
+                .and_then(|name| databases.get(name))
+                .copied()
+                .ok_or(UserError::InvalidStoreFile)?;
+
+            for _ in 0..reader.read_u64::<BigEndian>()? {
+                let len = reader.read_u32::<BigEndian>()? as usize;
+                read_bytes(&mut reader, &mut key, len)?;
+                let len = reader.read_u32::<BigEndian>()? as usize;
+                read_bytes(&mut reader, &mut value, len)?;
+                database.put::<_, ByteSlice, ByteSlice>(&mut wtxn, &key, &value)?;
+            }
+        }
+
+        wtxn.commit()?;
+        Ok(index)
+    }
+
+    /// Copies the LMDB environment of the index into a new index directory at the given path,
+    /// the pages freed by the deletions are not copied when the copy is compacted. The readers
+    /// and the writer of the index are not blocked while it is copied, the copy is the index
+    /// as seen by a read transaction.
+    ///
+    /// LMDB doesn't report its progress, the size of the copy is reported periodically.
+    pub fn copy_to_path<P, F>(
+        &self,
+        path: P,
+        option: CompactionOption,
+        mut progress: F,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(CopyProgress),
+    {
+        std::fs::create_dir_all(&path)?;
+        let copy_path = path.as_ref().join("data.mdb");
+        let total_bytes = std::fs::metadata(self.path().join("data.mdb"))?.len();
+
+        let env = self.env.clone();
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        let thread_copy_path = copy_path.clone();
+        let handle = std::thread::spawn(move || {
+            let _ = sender.send(env.copy_to_path(thread_copy_path, option).map(drop));
+        });
+
+        loop {
+            let copied_bytes = || std::fs::metadata(&copy_path).map_or(0, |m| m.len());
+            match receiver.recv_timeout(COPY_PROGRESS_INTERVAL) {
+                Ok(result) => {
+                    result?;
+                    progress(CopyProgress { copied_bytes: copied_bytes(), total_bytes });
+                    return Ok(());
+                }
+                Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                    progress(CopyProgress { copied_bytes: copied_bytes(), total_bytes })
+                }
+                // The copying thread panicked without sending its result.
+                Err(crossbeam_channel::RecvTimeoutError::Disconnected) => match handle.join() {
+                    Ok(()) => unreachable!("the copying thread always sends its result"),
+                    Err(panic) => std::panic::resume_unwind(panic),
+                },
+            }
+        }
+    }
+
+    /// Returns the number of entries and the size of the entries of every database of the
+    /// index, by database name, to find out which databases take the most space.
+    pub fn database_sizes(&self, rtxn: &RoTxn) -> Result<BTreeMap<&'static str, DatabaseSize>> {
+        let mut sizes = BTreeMap::new();
+        for (name, database) in self.databases() {
+            let mut size = DatabaseSize::default();
+            for result in database.iter::<_, ByteSlice, ByteSlice>(rtxn)? {
+                let (key, value) = result?;
+                size.entries += 1;
+                size.keys_bytes += key.len() as u64;
+                size.values_bytes += value.len() as u64;
+            }
+            sizes.insert(name, size);
+        }
+        Ok(sizes)
+    }
+
     /* documents ids */
 
     /// Writes the documents ids that corresponds to the user-ids-documents-ids FST.
@@ -217,6 +592,32 @@ impl Index {
             .unwrap_or_default())
     }
 
+    /// Writes the internal ids that are kept for the external ids retained by a clear.
+    pub(crate) fn put_reserved_documents_ids(
+        &self,
+        wtxn: &mut RwTxn,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, RoaringBitmapCodec>(
+            wtxn,
+            main_key::RESERVED_DOCUMENTS_IDS_KEY,
+            docids,
+        )
+    }
+
+    pub(crate) fn delete_reserved_documents_ids(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::RESERVED_DOCUMENTS_IDS_KEY)
+    }
+
+    /// Returns the internal ids that must not be given to new documents as they are
+    /// associated with external ids retained by a clear.
+    pub fn reserved_documents_ids(&self, rtxn: &RoTxn) -> heed::Result<RoaringBitmap> {
+        Ok(self
+            .main
+            .get::<_, Str, RoaringBitmapCodec>(rtxn, main_key::RESERVED_DOCUMENTS_IDS_KEY)?
+            .unwrap_or_default())
+    }
+
     /// Returns the number of documents indexed in the database.
     pub fn number_of_documents(&self, rtxn: &RoTxn) -> Result<u64> {
         let count =
@@ -242,6 +643,31 @@ impl Index {
         self.main.get::<_, Str, Str>(rtxn, main_key::PRIMARY_KEY_KEY)
     }
 
+    /* document id policy */
+
+    /// Writes the way the document ids sent by the user are stored in the external documents ids.
+    pub(crate) fn put_document_id_policy(
+        &self,
+        wtxn: &mut RwTxn,
+        policy: &DocumentIdPolicy,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<DocumentIdPolicy>>(
+            wtxn,
+            main_key::DOCUMENT_ID_POLICY_KEY,
+            policy,
+        )
+    }
+
+    pub(crate) fn delete_document_id_policy(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DOCUMENT_ID_POLICY_KEY)
+    }
+
+    /// Returns the way the document ids sent by the user are stored in the external documents
+    /// ids, `None` until the first document addition.
+    pub fn document_id_policy(&self, rtxn: &RoTxn) -> heed::Result<Option<DocumentIdPolicy>> {
+        self.main.get::<_, Str, SerdeJson<DocumentIdPolicy>>(rtxn, main_key::DOCUMENT_ID_POLICY_KEY)
+    }
+
     /* external documents ids */
 
     /// Writes the external documents ids and internal ids (i.e. `u32`).
@@ -388,6 +814,34 @@ impl Index {
             .unwrap_or_default())
     }
 
+    /* field types distribution */
+
+    /// Writes the field types distribution which associates every field name with
+    /// the number of values of each JSON type it has in the documents.
+    pub(crate) fn put_field_types_distribution(
+        &self,
+        wtxn: &mut RwTxn,
+        distribution: &FieldTypesDistribution,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<FieldTypesDistribution>>(
+            wtxn,
+            main_key::FIELD_TYPES_DISTRIBUTION_KEY,
+            distribution,
+        )
+    }
+
+    /// Returns the field types distribution which associates every field name with
+    /// the number of values of each JSON type it has in the documents.
+    pub fn field_types_distribution(&self, rtxn: &RoTxn) -> heed::Result<FieldTypesDistribution> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<FieldTypesDistribution>>(
+                rtxn,
+                main_key::FIELD_TYPES_DISTRIBUTION_KEY,
+            )?
+            .unwrap_or_default())
+    }
+
     /* displayed fields */
 
     /// Writes the fields that must be displayed in the defined order.
@@ -515,6 +969,37 @@ impl Index {
         Ok(fields_ids)
     }
 
+    /* split identifiers fields */
+
+    /// Writes the names of the fields in which the camelCase and snake_case identifiers
+    /// are split into sub-words.
+    pub(crate) fn put_split_identifiers_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &HashSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::SPLIT_IDENTIFIERS_FIELDS_KEY, fields)
+    }
+
+    pub(crate) fn delete_split_identifiers_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::SPLIT_IDENTIFIERS_FIELDS_KEY)
+    }
+
+    /// Returns the names of the fields in which the identifiers are split into sub-words.
+    pub fn split_identifiers_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::SPLIT_IDENTIFIERS_FIELDS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Identical to `split_identifiers_fields`, but returns ids instead.
+    pub fn split_identifiers_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
+        let fields = self.split_identifiers_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        Ok(fields.iter().filter_map(|name| fields_ids_map.id(name)).collect())
+    }
+
     /* sortable fields */
 
     /// Writes the sortable fields names in the database.
@@ -568,6 +1053,15 @@ impl Index {
             faceted_fields.insert(field.to_owned());
         }
 
+        // The fields flattened out of the objects of a faceted field are faceted too.
+        let flattened_fields = self.flattened_faceted_fields(rtxn)?;
+        let flattened_fields: Vec<_> = flattened_fields
+            .into_iter()
+            .filter(|(_, parent)| faceted_fields.contains(parent))
+            .map(|(name, _)| name)
+            .collect();
+        faceted_fields.extend(flattened_fields);
+
         Ok(faceted_fields)
     }
 
@@ -586,6 +1080,45 @@ impl Index {
         Ok(fields_ids)
     }
 
+    /* flattened faceted fields */
+
+    /// Writes the names of the fields flattened out of the objects of the faceted fields,
+    /// e.g. `tags.name` for `tags: [{ "name": .. }]`, associated with their top-level field.
+    pub(crate) fn put_flattened_faceted_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &BTreeMap<String, String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::FLATTENED_FACETED_FIELDS_KEY, fields)
+    }
+
+    pub(crate) fn delete_flattened_faceted_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::FLATTENED_FACETED_FIELDS_KEY)
+    }
+
+    /// Returns the names of the fields flattened out of the objects of the faceted fields,
+    /// associated with the name of the top-level field they were found in.
+    pub fn flattened_faceted_fields(&self, rtxn: &RoTxn) -> heed::Result<BTreeMap<String, String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::FLATTENED_FACETED_FIELDS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Returns the filterable fields names along with the names of the fields
+    /// flattened out of the objects of the filterable fields.
+    pub fn filterable_fields_with_flattened(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        let mut fields = self.filterable_fields(rtxn)?;
+        let flattened_fields: Vec<_> = self
+            .flattened_faceted_fields(rtxn)?
+            .into_iter()
+            .filter(|(_, parent)| fields.contains(parent))
+            .map(|(name, _)| name)
+            .collect();
+        fields.extend(flattened_fields);
+        Ok(fields)
+    }
+
     /* faceted documents ids */
 
     /// Writes the documents ids that are faceted with numbers under this field id.
@@ -656,6 +1189,50 @@ impl Index {
         }
     }
 
+    /// Writes the documents ids that have the given boolean under this field id.
+    pub(crate) fn put_facet_id_bool_docids(
+        &self,
+        wtxn: &mut RwTxn,
+        field_id: FieldId,
+        value: bool,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        let key = facet_bool_docids_key(field_id, value);
+        self.main.put::<_, ByteSlice, RoaringBitmapCodec>(wtxn, &key, docids)
+    }
+
+    /// Retrieve all the documents ids that have the given boolean under this field id.
+    pub fn facet_id_bool_docids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+        value: bool,
+    ) -> heed::Result<RoaringBitmap> {
+        let key = facet_bool_docids_key(field_id, value);
+        match self.main.get::<_, ByteSlice, RoaringBitmapCodec>(rtxn, &key)? {
+            Some(docids) => Ok(docids),
+            None => Ok(RoaringBitmap::new()),
+        }
+    }
+
+    /// Retrieve all the documents ids that are `true` under this field id.
+    pub fn facet_id_is_true_docids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+    ) -> heed::Result<RoaringBitmap> {
+        self.facet_id_bool_docids(rtxn, field_id, true)
+    }
+
+    /// Retrieve all the documents ids that are `false` under this field id.
+    pub fn facet_id_is_false_docids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+    ) -> heed::Result<RoaringBitmap> {
+        self.facet_id_bool_docids(rtxn, field_id, false)
+    }
+
     /* distinct field */
 
     pub(crate) fn put_distinct_field(
@@ -735,258 +1312,1936 @@ impl Index {
         }
     }
 
-    /* synonyms */
+    /// Returns `true` if the word is one of the stop words, they are removed from the queries.
+    pub fn is_stop_word(&self, rtxn: &RoTxn, word: &str) -> Result<bool> {
+        Ok(self.stop_words(rtxn)?.map_or(false, |stop_words| stop_words.contains(word)))
+    }
 
-    pub(crate) fn put_synonyms(
+    /* separator tokens */
+
+    pub(crate) fn put_separator_tokens(
         &self,
         wtxn: &mut RwTxn,
-        synonyms: &HashMap<Vec<String>, Vec<Vec<String>>>,
+        separator_tokens: &BTreeSet<String>,
     ) -> heed::Result<()> {
-        self.main.put::<_, Str, SerdeBincode<_>>(wtxn, main_key::SYNONYMS_KEY, synonyms)
-    }
-
-    pub(crate) fn delete_synonyms(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
-        self.main.delete::<_, Str>(wtxn, main_key::SYNONYMS_KEY)
+        self.main.put::<_, Str, SerdeJson<_>>(
+            wtxn,
+            main_key::SEPARATOR_TOKENS_KEY,
+            separator_tokens,
+        )
     }
 
-    pub fn synonyms(&self, rtxn: &RoTxn) -> heed::Result<HashMap<Vec<String>, Vec<Vec<String>>>> {
-        Ok(self
-            .main
-            .get::<_, Str, SerdeBincode<_>>(rtxn, main_key::SYNONYMS_KEY)?
-            .unwrap_or_default())
+    pub(crate) fn delete_separator_tokens(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::SEPARATOR_TOKENS_KEY)
     }
 
-    pub fn words_synonyms<S: AsRef<str>>(
-        &self,
-        rtxn: &RoTxn,
-        words: &[S],
-    ) -> heed::Result<Option<Vec<Vec<String>>>> {
-        let words: Vec<_> = words.iter().map(|s| s.as_ref().to_owned()).collect();
-        Ok(self.synonyms(rtxn)?.remove(&words))
+    /// Returns the additional strings that separate the words.
+    pub fn separator_tokens(&self, rtxn: &RoTxn) -> heed::Result<Option<BTreeSet<String>>> {
+        self.main.get::<_, Str, SerdeJson<_>>(rtxn, main_key::SEPARATOR_TOKENS_KEY)
     }
 
-    /* words prefixes fst */
+    /* non separator tokens */
 
-    /// Writes the FST which is the words prefixes dictionnary of the engine.
-    pub(crate) fn put_words_prefixes_fst<A: AsRef<[u8]>>(
+    pub(crate) fn put_non_separator_tokens(
         &self,
         wtxn: &mut RwTxn,
-        fst: &fst::Set<A>,
+        non_separator_tokens: &BTreeSet<String>,
     ) -> heed::Result<()> {
-        self.main.put::<_, Str, ByteSlice>(
+        self.main.put::<_, Str, SerdeJson<_>>(
             wtxn,
-            main_key::WORDS_PREFIXES_FST_KEY,
-            fst.as_fst().as_bytes(),
+            main_key::NON_SEPARATOR_TOKENS_KEY,
+            non_separator_tokens,
         )
     }
 
-    /// Returns the FST which is the words prefixes dictionnary of the engine.
-    pub fn words_prefixes_fst<'t>(&self, rtxn: &'t RoTxn) -> Result<fst::Set<Cow<'t, [u8]>>> {
-        match self.main.get::<_, Str, ByteSlice>(rtxn, main_key::WORDS_PREFIXES_FST_KEY)? {
-            Some(bytes) => Ok(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?),
-            None => Ok(fst::Set::default().map_data(Cow::Owned)?),
-        }
+    pub(crate) fn delete_non_separator_tokens(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::NON_SEPARATOR_TOKENS_KEY)
     }
 
-    /* word documents count */
-
-    /// Returns the number of documents ids associated with the given word,
-    /// it is much faster than deserializing the bitmap and getting the length of it.
-    pub fn word_documents_count(&self, rtxn: &RoTxn, word: &str) -> heed::Result<Option<u64>> {
-        self.word_docids.remap_data_type::<RoaringBitmapLenCodec>().get(rtxn, word)
+    /// Returns the strings that are not considered as separators anymore.
+    pub fn non_separator_tokens(&self, rtxn: &RoTxn) -> heed::Result<Option<BTreeSet<String>>> {
+        self.main.get::<_, Str, SerdeJson<_>>(rtxn, main_key::NON_SEPARATOR_TOKENS_KEY)
     }
 
-    /* documents */
+    /* protected patterns */
 
-    /// Returns a [`Vec`] of the requested documents. Returns an error if a document is missing.
-    pub fn documents<'t>(
+    pub(crate) fn put_protected_patterns(
         &self,
-        rtxn: &'t RoTxn,
-        ids: impl IntoIterator<Item = DocumentId>,
-    ) -> Result<Vec<(DocumentId, obkv::KvReaderU16<'t>)>> {
-        let mut documents = Vec::new();
+        wtxn: &mut RwTxn,
+        patterns: &[ProtectedPattern],
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::PROTECTED_PATTERNS_KEY, &patterns)
+    }
 
-        for id in ids {
-            let kv = self
-                .documents
-                .get(rtxn, &BEU32::new(id))?
-                .ok_or_else(|| UserError::UnknownInternalDocumentId { document_id: id })?;
-            documents.push((id, kv));
-        }
+    pub(crate) fn delete_protected_patterns(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::PROTECTED_PATTERNS_KEY)
+    }
 
-        Ok(documents)
+    /// Returns the patterns whose matches are indexed as single words.
+    pub fn protected_patterns(&self, rtxn: &RoTxn) -> heed::Result<Option<Vec<ProtectedPattern>>> {
+        self.main.get::<_, Str, SerdeJson<_>>(rtxn, main_key::PROTECTED_PATTERNS_KEY)
     }
 
-    /// Returns an iterator over all the documents in the index.
-    pub fn all_documents<'t>(
+    /* dictionary */
+
+    pub(crate) fn put_dictionary(
         &self,
-        rtxn: &'t RoTxn,
-    ) -> Result<impl Iterator<Item = heed::Result<(DocumentId, obkv::KvReaderU16<'t>)>>> {
-        Ok(self
-            .documents
-            .iter(rtxn)?
-            // we cast the BEU32 to a DocumentId
-            .map(|document| document.map(|(id, obkv)| (id.get(), obkv))))
+        wtxn: &mut RwTxn,
+        dictionary: &BTreeSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::DICTIONARY_KEY, dictionary)
     }
 
-    pub fn facets_distribution<'a>(&'a self, rtxn: &'a RoTxn) -> FacetDistribution<'a> {
-        FacetDistribution::new(rtxn, self)
+    pub(crate) fn delete_dictionary(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DICTIONARY_KEY)
     }
 
-    pub fn search<'a>(&'a self, rtxn: &'a RoTxn) -> Search<'a> {
-        Search::new(rtxn, self)
+    /// Returns the words that must not be split by the tokenizer.
+    pub fn dictionary(&self, rtxn: &RoTxn) -> heed::Result<Option<BTreeSet<String>>> {
+        self.main.get::<_, Str, SerdeJson<_>>(rtxn, main_key::DICTIONARY_KEY)
     }
 
-    /// Returns the index creation time.
-    pub fn created_at(&self, rtxn: &RoTxn) -> Result<OffsetDateTime> {
+    /* emoji policy */
+
+    pub(crate) fn put_emoji_policy(
+        &self,
+        wtxn: &mut RwTxn,
+        policy: EmojiPolicy,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<EmojiPolicy>>(wtxn, main_key::EMOJI_POLICY_KEY, &policy)
+    }
+
+    pub(crate) fn delete_emoji_policy(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::EMOJI_POLICY_KEY)
+    }
+
+    /// Returns the way the emoji are handled, they are indexed by default.
+    pub fn emoji_policy(&self, rtxn: &RoTxn) -> heed::Result<EmojiPolicy> {
         Ok(self
             .main
-            .get::<_, Str, SerdeJson<OffsetDateTime>>(rtxn, main_key::CREATED_AT_KEY)?
+            .get::<_, Str, SerdeJson<EmojiPolicy>>(rtxn, main_key::EMOJI_POLICY_KEY)?
+            .unwrap_or_default())
+    }
+
+    /* proximity precision */
+
+    pub(crate) fn put_proximity_precision(
+        &self,
+        wtxn: &mut RwTxn,
+        precision: ProximityPrecision,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<ProximityPrecision>>(
+            wtxn,
+            main_key::PROXIMITY_PRECISION_KEY,
+            &precision,
+        )
+    }
+
+    pub(crate) fn delete_proximity_precision(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::PROXIMITY_PRECISION_KEY)
+    }
+
+    /// Returns the precision of the proximity between the words, it is exact by default.
+    pub fn proximity_precision(&self, rtxn: &RoTxn) -> heed::Result<ProximityPrecision> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<ProximityPrecision>>(rtxn, main_key::PROXIMITY_PRECISION_KEY)?
+            .unwrap_or_default())
+    }
+
+    /* prefix databases */
+
+    pub(crate) fn put_prefix_databases(&self, wtxn: &mut RwTxn, enabled: bool) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, main_key::PREFIX_DATABASES_KEY, &enabled)
+    }
+
+    pub(crate) fn delete_prefix_databases(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::PREFIX_DATABASES_KEY)
+    }
+
+    /// Returns whether the prefixes of the words and their databases are computed, they are
+    /// by default. Without them the prefix queries are resolved from the words databases.
+    pub fn prefix_databases(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<bool>>(rtxn, main_key::PREFIX_DATABASES_KEY)?
+            .unwrap_or(true))
+    }
+
+    /* store documents */
+
+    pub(crate) fn put_store_documents(&self, wtxn: &mut RwTxn, enabled: bool) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, main_key::STORE_DOCUMENTS_KEY, &enabled)
+    }
+
+    pub(crate) fn delete_store_documents(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::STORE_DOCUMENTS_KEY)
+    }
+
+    /// Returns whether the documents are stored, they are by default. Without them the
+    /// searches only return the documents ids and the documents can't be reindexed.
+    pub fn store_documents(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<bool>>(rtxn, main_key::STORE_DOCUMENTS_KEY)?
+            .unwrap_or(true))
+    }
+
+    /// Returns an error when the documents are not stored, they are required by the operation.
+    pub(crate) fn ensure_documents_stored(
+        &self,
+        rtxn: &RoTxn,
+        operation: &'static str,
+    ) -> Result<()> {
+        if self.store_documents(rtxn)? {
+            Ok(())
+        } else {
+            Err(UserError::DocumentsNotStored { operation }.into())
+        }
+    }
+
+    /* documents compression */
+
+    pub(crate) fn put_documents_dictionary(
+        &self,
+        wtxn: &mut RwTxn,
+        dictionary: &[u8],
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, ByteSlice>(wtxn, main_key::DOCUMENTS_DICTIONARY_KEY, dictionary)
+    }
+
+    pub(crate) fn delete_documents_dictionary(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DOCUMENTS_DICTIONARY_KEY)
+    }
+
+    /// Returns whether the documents are compressed with a dictionary trained on the
+    /// documents of the index, they are not by default.
+    pub fn documents_compressed(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        Ok(self.main.get::<_, Str, ByteSlice>(rtxn, main_key::DOCUMENTS_DICTIONARY_KEY)?.is_some())
+    }
+
+    /// Returns the codec that must be used to read and write the `documents` database.
+    pub(crate) fn documents_codec(&self, rtxn: &RoTxn) -> Result<DocumentsCodec> {
+        let dictionary =
+            self.main.get::<_, Str, ByteSlice>(rtxn, main_key::DOCUMENTS_DICTIONARY_KEY)?;
+        DocumentsCodec::new(dictionary)
+    }
+
+    pub(crate) fn put_prefix_pair_proximity_caps(
+        &self,
+        wtxn: &mut RwTxn,
+        caps: PrefixPairProximityCaps,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<PrefixPairProximityCaps>>(
+            wtxn,
+            main_key::PREFIX_PAIR_PROXIMITY_CAPS_KEY,
+            &caps,
+        )
+    }
+
+    /// Returns the bounds of the pairs stored in the word prefix pair proximity database.
+    pub fn prefix_pair_proximity_caps(
+        &self,
+        rtxn: &RoTxn,
+    ) -> heed::Result<PrefixPairProximityCaps> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<PrefixPairProximityCaps>>(
+                rtxn,
+                main_key::PREFIX_PAIR_PROXIMITY_CAPS_KEY,
+            )?
+            .unwrap_or_default())
+    }
+
+    /* proximity window */
+
+    pub(crate) fn put_proximity_window(&self, wtxn: &mut RwTxn, window: u32) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<u32>>(wtxn, main_key::PROXIMITY_WINDOW_KEY, &window)
+    }
+
+    pub(crate) fn delete_proximity_window(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::PROXIMITY_WINDOW_KEY)
+    }
+
+    /// Returns the smallest proximity window the pairs of words were stored with, when the
+    /// [`IndexerConfig`](crate::update::IndexerConfig) restricted it.
+    pub fn proximity_window(&self, rtxn: &RoTxn) -> heed::Result<Option<u32>> {
+        self.main.get::<_, Str, SerdeJson<u32>>(rtxn, main_key::PROXIMITY_WINDOW_KEY)
+    }
+
+    /* typo costs */
+
+    pub(crate) fn put_typo_costs(&self, wtxn: &mut RwTxn, costs: &TypoCosts) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<TypoCosts>>(wtxn, main_key::TYPO_COSTS_KEY, costs)
+    }
+
+    pub(crate) fn delete_typo_costs(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::TYPO_COSTS_KEY)
+    }
+
+    /// Returns the way the typo criterion counts the typos, each typo costs one by default.
+    pub fn typo_costs(&self, rtxn: &RoTxn) -> heed::Result<TypoCosts> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<TypoCosts>>(rtxn, main_key::TYPO_COSTS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /* synonyms */
+
+    pub(crate) fn put_synonyms(
+        &self,
+        wtxn: &mut RwTxn,
+        synonyms: &HashMap<Vec<String>, Vec<Vec<String>>>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeBincode<_>>(wtxn, main_key::SYNONYMS_KEY, synonyms)
+    }
+
+    pub(crate) fn delete_synonyms(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::SYNONYMS_KEY)
+    }
+
+    pub fn synonyms(&self, rtxn: &RoTxn) -> heed::Result<HashMap<Vec<String>, Vec<Vec<String>>>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeBincode<_>>(rtxn, main_key::SYNONYMS_KEY)?
+            .unwrap_or_default())
+    }
+
+    pub fn words_synonyms<S: AsRef<str>>(
+        &self,
+        rtxn: &RoTxn,
+        words: &[S],
+    ) -> heed::Result<Option<Vec<Vec<String>>>> {
+        let words: Vec<_> = words.iter().map(|s| s.as_ref().to_owned()).collect();
+        Ok(self.synonyms(rtxn)?.remove(&words))
+    }
+
+    /* stored queries */
+
+    /// Stores a query under the given id, replacing the query previously stored under it,
+    /// the indexed documents can be matched against the stored queries.
+    pub fn put_stored_query(&self, wtxn: &mut RwTxn, id: &str, query: &StoredQuery) -> Result<()> {
+        // We make sure that the filter is valid before storing it.
+        if let Some(filter) = &query.filter {
+            Filter::from_str(filter)?;
+        }
+
+        let mut queries = self.stored_queries(wtxn)?;
+        queries.insert(id.to_string(), query.clone());
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::STORED_QUERIES_KEY, &queries)?;
+        Ok(())
+    }
+
+    /// Deletes the query stored under the given id, returns `true` if it existed.
+    pub fn delete_stored_query(&self, wtxn: &mut RwTxn, id: &str) -> heed::Result<bool> {
+        let mut queries = self.stored_queries(wtxn)?;
+        if queries.remove(id).is_none() {
+            return Ok(false);
+        }
+
+        if queries.is_empty() {
+            self.main.delete::<_, Str>(wtxn, main_key::STORED_QUERIES_KEY)?;
+        } else {
+            self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::STORED_QUERIES_KEY, &queries)?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the stored queries by id.
+    pub fn stored_queries(&self, rtxn: &RoTxn) -> heed::Result<BTreeMap<String, StoredQuery>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::STORED_QUERIES_KEY)?
+            .unwrap_or_default())
+    }
+
+    /* user metadata */
+
+    /// The key of the user metadata in the main database, the namespace is prefixed by its
+    /// length for the keys of a namespace to never be mixed up with another one.
+    fn user_metadata_key(namespace: &str, key: &str) -> Vec<u8> {
+        let prefix = main_key::USER_METADATA_PREFIX.as_bytes();
+        let mut buffer = Vec::with_capacity(prefix.len() + 2 + namespace.len() + key.len());
+        buffer.extend_from_slice(prefix);
+        buffer.extend_from_slice(&(namespace.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(namespace.as_bytes());
+        buffer.extend_from_slice(key.as_bytes());
+        buffer
+    }
+
+    /// Writes a value under the key of the namespace, the values are opaque to the index and
+    /// are written along with the content changes of the transaction, e.g. the id of the task
+    /// the documents come from. The namespace and the key must be shorter than 490 bytes.
+    pub fn put_user_metadata(
+        &self,
+        wtxn: &mut RwTxn,
+        namespace: &str,
+        key: &str,
+        value: &[u8],
+    ) -> heed::Result<()> {
+        let key = Index::user_metadata_key(namespace, key);
+        self.main.put::<_, ByteSlice, ByteSlice>(wtxn, &key, value)
+    }
+
+    pub fn delete_user_metadata(
+        &self,
+        wtxn: &mut RwTxn,
+        namespace: &str,
+        key: &str,
+    ) -> heed::Result<bool> {
+        let key = Index::user_metadata_key(namespace, key);
+        self.main.delete::<_, ByteSlice>(wtxn, &key)
+    }
+
+    /// Returns the value written under the key of the namespace, if any.
+    pub fn get_user_metadata<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        namespace: &str,
+        key: &str,
+    ) -> heed::Result<Option<&'t [u8]>> {
+        let key = Index::user_metadata_key(namespace, key);
+        self.main.get::<_, ByteSlice, ByteSlice>(rtxn, &key)
+    }
+
+    /// Returns all the keys of the namespace with their values.
+    pub fn user_metadata<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        namespace: &str,
+    ) -> Result<BTreeMap<&'t str, &'t [u8]>> {
+        let prefix = Index::user_metadata_key(namespace, "");
+        let mut metadata = BTreeMap::new();
+        for result in self.main.prefix_iter::<_, ByteSlice, ByteSlice>(rtxn, &prefix)? {
+            let (key, value) = result?;
+            let key = std::str::from_utf8(&key[prefix.len()..])
+                .map_err(|_| SerializationError::Decoding { db_name: Some(db_name::MAIN) })?;
+            metadata.insert(key, value);
+        }
+        Ok(metadata)
+    }
+
+    /* words prefixes fst */
+
+    /// Writes the FST which is the words prefixes dictionnary of the engine.
+    pub(crate) fn put_words_prefixes_fst<A: AsRef<[u8]>>(
+        &self,
+        wtxn: &mut RwTxn,
+        fst: &fst::Set<A>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, ByteSlice>(
+            wtxn,
+            main_key::WORDS_PREFIXES_FST_KEY,
+            fst.as_fst().as_bytes(),
+        )
+    }
+
+    /// Returns the FST which is the words prefixes dictionnary of the engine.
+    pub fn words_prefixes_fst<'t>(&self, rtxn: &'t RoTxn) -> Result<fst::Set<Cow<'t, [u8]>>> {
+        match self.main.get::<_, Str, ByteSlice>(rtxn, main_key::WORDS_PREFIXES_FST_KEY)? {
+            Some(bytes) => Ok(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?),
+            None => Ok(fst::Set::default().map_data(Cow::Owned)?),
+        }
+    }
+
+    /* word documents count */
+
+    /// Returns the number of documents ids associated with the given word,
+    /// it is much faster than deserializing the bitmap and getting the length of it.
+    pub fn word_documents_count(&self, rtxn: &RoTxn, word: &str) -> heed::Result<Option<u64>> {
+        self.word_docids.remap_data_type::<RoaringBitmapLenCodec>().get(rtxn, word)
+    }
+
+    /// Returns the statistics of a word, it must be normalized like the words of the documents.
+    ///
+    /// The number of documents containing the word among the candidates is only
+    /// computed when candidates are given, e.g. the documents matching a query.
+    pub fn term_stats(
+        &self,
+        rtxn: &RoTxn,
+        word: &str,
+        candidates: Option<&RoaringBitmap>,
+    ) -> Result<TermStats> {
+        let is_prefix = self.words_prefixes_fst(rtxn)?.contains(word);
+        let (documents_count, candidates_documents_count) = match candidates {
+            Some(candidates) => {
+                let docids = self.word_docids.get(rtxn, word)?.unwrap_or_default();
+                (docids.len(), Some((&docids & candidates).len()))
+            }
+            None => (self.word_documents_count(rtxn, word)?.unwrap_or(0), None),
+        };
+        Ok(TermStats { documents_count, is_prefix, candidates_documents_count })
+    }
+
+    /// Returns the statistics of the words and of the facet values of every field, they are
+    /// computed by reading the whole databases, which can take a while on large indexes.
+    pub fn fields_stats(&self, rtxn: &RoTxn) -> Result<BTreeMap<FieldId, FieldStats>> {
+        let mut stats: BTreeMap<FieldId, FieldStats> = BTreeMap::new();
+
+        let word_fid_docids = self.word_fid_docids.remap_data_type::<CboRoaringBitmapLenCodec>();
+        for result in word_fid_docids.iter(rtxn)? {
+            let ((_word, field_id), documents_count) = result?;
+            let field_stats = stats.entry(field_id).or_default();
+            field_stats.unique_words += 1;
+            field_stats.word_postings += documents_count;
+        }
+
+        // The positions are absolute, the field id is in their highest bits.
+        for result in self.docid_word_positions.iter(rtxn)? {
+            let (_key, positions) = result?;
+            for position in positions {
+                let (field_id, _) = relative_from_absolute_position(position);
+                stats.entry(field_id).or_default().word_positions += 1;
+            }
+        }
+
+        // The keys of the facet values start with the field id followed by the level.
+        let facet_dbs = [
+            self.facet_id_f64_docids.remap_types::<ByteSlice, DecodeIgnore>(),
+            self.facet_id_string_docids.remap_types::<ByteSlice, DecodeIgnore>(),
+        ];
+        for db in &facet_dbs {
+            for result in db.iter(rtxn)? {
+                if let ([high, low, 0, ..], ()) = result? {
+                    let field_id = FieldId::from_be_bytes([*high, *low]);
+                    stats.entry(field_id).or_default().facet_values += 1;
+                }
+            }
+        }
+        for field_id in self.fields_ids_map(rtxn)?.ids() {
+            for value in [true, false].iter() {
+                if !self.facet_id_bool_docids(rtxn, field_id, *value)?.is_empty() {
+                    stats.entry(field_id).or_default().facet_values += 1;
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /* documents */
+
+    /// Returns a [`Vec`] of the requested documents, decompressed if needed. Returns an error
+    /// if a document is missing or if the documents are not stored.
+    pub fn documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+    ) -> Result<Vec<(DocumentId, StoredDocument<'t>)>> {
+        self.ensure_documents_stored(rtxn, "return the documents")?;
+        let mut codec = self.documents_codec(rtxn)?;
+        let mut documents = Vec::new();
+
+        for id in ids {
+            let bytes = self
+                .documents
+                .remap_data_type::<ByteSlice>()
+                .get(rtxn, &BEU32::new(id))?
+                .ok_or_else(|| UserError::UnknownInternalDocumentId { document_id: id })?;
+            documents.push((id, codec.decompress(bytes)?));
+        }
+
+        Ok(documents)
+    }
+
+    /// Returns the requested documents as JSON objects only made of the requested fields,
+    /// in the requested order, the other fields are not decoded. The fields that are not
+    /// displayed are skipped. Returns an error if a document is missing.
+    pub fn documents_with_fields(
+        &self,
+        rtxn: &RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+        fields: &[&str],
+    ) -> Result<Vec<(DocumentId, Map<String, Value>)>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let displayed_fields = self.displayed_fields(rtxn)?;
+
+        let mut fields_ids = Vec::new();
+        for name in fields {
+            if displayed_fields.as_ref().map_or(false, |displayed| !displayed.contains(name)) {
+                continue;
+            }
+            if let Some(field_id) = fields_ids_map.id(name) {
+                if !fields_ids.contains(&field_id) {
+                    fields_ids.push(field_id);
+                }
+            }
+        }
+
+        self.documents(rtxn, ids)?
+            .into_iter()
+            .map(|(id, document)| {
+                Ok((id, obkv_to_json(&fields_ids, &fields_ids_map, document.as_obkv())?))
+            })
+            .collect()
+    }
+
+    /// Returns the requested documents as JSON objects made of their displayed fields.
+    /// Returns an error if a document is missing.
+    pub fn displayed_documents(
+        &self,
+        rtxn: &RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+    ) -> Result<Vec<(DocumentId, Map<String, Value>)>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let fields_ids = match self.displayed_fields_ids(rtxn)? {
+            Some(fields_ids) => fields_ids,
+            None => fields_ids_map.ids().collect(),
+        };
+
+        self.documents(rtxn, ids)?
+            .into_iter()
+            .map(|(id, document)| {
+                Ok((id, obkv_to_json(&fields_ids, &fields_ids_map, document.as_obkv())?))
+            })
+            .collect()
+    }
+
+    /// Returns the document as the JSON object it was sent, made of all of its fields whether
+    /// they are displayed or not, the objects are returned as they are and not flattened.
+    /// Returns an error if the document is missing or if the documents are not stored.
+    pub fn document_json(&self, rtxn: &RoTxn, docid: DocumentId) -> Result<Map<String, Value>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let (_, document) = self.documents(rtxn, Some(docid))?.remove(0);
+        // The nested fields are never stored, only the top-level fields they are extracted from.
+        let fields_ids: Vec<_> = document.iter().map(|(field_id, _)| field_id).collect();
+        obkv_to_json(&fields_ids, &fields_ids_map, document.as_obkv())
+    }
+
+    /// Returns `n` documents ids picked at random among the documents of the index, in
+    /// ascending order. The same seed always picks the same documents of the same index.
+    pub fn sample_documents(&self, rtxn: &RoTxn, n: usize, seed: u64) -> Result<Vec<DocumentId>> {
+        let documents_ids = self.documents_ids(rtxn)?;
+        Ok(sample_docids(&documents_ids, n, seed))
+    }
+
+    /// Identical to `sample_documents`, but picks the documents among the ones
+    /// matching the filter.
+    pub fn sample_filtered_documents(
+        &self,
+        rtxn: &RoTxn,
+        filter: &Filter,
+        n: usize,
+        seed: u64,
+    ) -> Result<Vec<DocumentId>> {
+        let candidates = filter.evaluate(rtxn, self)?;
+        Ok(sample_docids(&candidates, n, seed))
+    }
+
+    /// Returns an iterator over all the documents in the index, decompressed if needed.
+    /// Returns an error if the documents are not stored.
+    pub fn all_documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+    ) -> Result<impl Iterator<Item = Result<(DocumentId, StoredDocument<'t>)>>> {
+        self.ensure_documents_stored(rtxn, "return the documents")?;
+        let mut codec = self.documents_codec(rtxn)?;
+        Ok(self.documents.remap_data_type::<ByteSlice>().iter(rtxn)?.map(move |result| {
+            let (id, bytes) = result?;
+            // we cast the BEU32 to a DocumentId
+            Ok((id.get(), codec.decompress(bytes)?))
+        }))
+    }
+
+    /// Returns a [`Vec`] of the external ids of the requested documents, in the same order.
+    /// Returns an error if a document is missing.
+    pub fn external_ids<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+    ) -> Result<Vec<&'t str>> {
+        let mut external_ids = Vec::new();
+
+        for id in ids {
+            let external_id = self
+                .docid_external_ids
+                .get(rtxn, &BEU32::new(id))?
+                .ok_or_else(|| UserError::UnknownInternalDocumentId { document_id: id })?;
+            external_ids.push(external_id);
+        }
+
+        Ok(external_ids)
+    }
+
+    pub fn facets_distribution<'a>(&'a self, rtxn: &'a RoTxn) -> FacetDistribution<'a> {
+        FacetDistribution::new(rtxn, self)
+    }
+
+    pub fn search<'a>(&'a self, rtxn: &'a RoTxn) -> Search<'a> {
+        Search::new(rtxn, self)
+    }
+
+    /// Returns a builder to browse the documents without a query, they can be filtered,
+    /// sorted by their sortable fields and paginated.
+    pub fn documents_iter<'a>(&'a self, rtxn: &'a RoTxn) -> DocumentsQuery<'a> {
+        DocumentsQuery::new(rtxn, self)
+    }
+
+    /// Returns the stable identifier of the index, generated when it is created.
+    pub fn uuid(&self, rtxn: &RoTxn) -> Result<Uuid> {
+        let uuid = self.main.get::<_, Str, Str>(rtxn, main_key::INDEX_UUID_KEY)?.ok_or(
+            InternalError::DatabaseMissingEntry {
+                db_name: db_name::MAIN,
+                key: Some(main_key::INDEX_UUID_KEY),
+            },
+        )?;
+        let uuid = Uuid::parse_str(uuid)
+            .map_err(|_| SerializationError::Decoding { db_name: Some(db_name::MAIN) })?;
+        Ok(uuid)
+    }
+
+    /// Returns a span identifying this index, the events emitted while it is entered carry
+    /// the path and the uuid of the index, the phase and the id of the update, if any.
+    pub(crate) fn span(&self, rtxn: &RoTxn, phase: &'static str) -> tracing::Span {
+        let uuid = self.uuid(rtxn).map(|uuid| uuid.to_string()).unwrap_or_default();
+        tracing::info_span!(
+            "index",
+            path = %self.path().display(),
+            uuid = %uuid,
+            phase,
+            update_id = tracing::field::Empty,
+        )
+    }
+
+    /// Returns the version of the format the index is written with,
+    /// the indexes written before the version was stored are of version 0.
+    pub fn format_version(&self, rtxn: &RoTxn) -> heed::Result<u32> {
+        Ok(self
+            .main
+            .get::<_, Str, OwnedType<BEU32>>(rtxn, main_key::FORMAT_VERSION_KEY)?
+            .map_or(0, |version| version.get()))
+    }
+
+    pub(crate) fn put_format_version(&self, wtxn: &mut RwTxn, version: u32) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<BEU32>>(
+            wtxn,
+            main_key::FORMAT_VERSION_KEY,
+            &BEU32::new(version),
+        )
+    }
+
+    /// Returns the identity of the index along with its creation and last update times,
+    /// it allows to correlate the index directories with the logical indexes.
+    pub fn metadata(&self, rtxn: &RoTxn) -> Result<IndexMetadata> {
+        Ok(IndexMetadata {
+            uuid: self.uuid(rtxn)?,
+            created_at: self.created_at(rtxn)?,
+            updated_at: self.updated_at(rtxn)?,
+        })
+    }
+
+    /// Returns the index creation time.
+    pub fn created_at(&self, rtxn: &RoTxn) -> Result<OffsetDateTime> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<OffsetDateTime>>(rtxn, main_key::CREATED_AT_KEY)?
             .ok_or(InternalError::DatabaseMissingEntry {
                 db_name: db_name::MAIN,
                 key: Some(main_key::CREATED_AT_KEY),
             })?)
     }
 
-    /// Returns the index last updated time.
-    pub fn updated_at(&self, rtxn: &RoTxn) -> Result<OffsetDateTime> {
-        Ok(self
-            .main
-            .get::<_, Str, SerdeJson<OffsetDateTime>>(rtxn, main_key::UPDATED_AT_KEY)?
-            .ok_or(InternalError::DatabaseMissingEntry {
-                db_name: db_name::MAIN,
-                key: Some(main_key::UPDATED_AT_KEY),
-            })?)
+    /// Returns the generation of the settings, it is incremented every time the settings
+    /// are updated and can be used to invalidate the caches that depend on them.
+    pub fn settings_generation(&self, rtxn: &RoTxn) -> heed::Result<u64> {
+        Ok(self
+            .main
+            .get::<_, Str, OwnedType<BEU64>>(rtxn, main_key::SETTINGS_GENERATION_KEY)?
+            .map_or(0, |generation| generation.get()))
+    }
+
+    pub(crate) fn increment_settings_generation(&self, wtxn: &mut RwTxn) -> heed::Result<u64> {
+        let generation = self.settings_generation(wtxn)? + 1;
+        self.main.put::<_, Str, OwnedType<BEU64>>(
+            wtxn,
+            main_key::SETTINGS_GENERATION_KEY,
+            &BEU64::new(generation),
+        )?;
+        self.compiled_patterns.clear();
+        Ok(generation)
+    }
+
+    /// Returns the index last updated time.
+    pub fn updated_at(&self, rtxn: &RoTxn) -> Result<OffsetDateTime> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<OffsetDateTime>>(rtxn, main_key::UPDATED_AT_KEY)?
+            .ok_or(InternalError::DatabaseMissingEntry {
+                db_name: db_name::MAIN,
+                key: Some(main_key::UPDATED_AT_KEY),
+            })?)
+    }
+
+    pub(crate) fn set_updated_at(
+        &self,
+        wtxn: &mut RwTxn,
+        time: &OffsetDateTime,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<OffsetDateTime>>(wtxn, main_key::UPDATED_AT_KEY, &time)
+    }
+}
+
+/// An index opened with [`Index::open_read_only`], e.g. by the search replicas of an index
+/// shared over a read-only mount. It only gives access to read transactions and to the
+/// methods reading the index, it can't be given to the updates.
+#[derive(Clone)]
+pub struct ReadOnlyIndex {
+    index: Index,
+}
+
+impl ReadOnlyIndex {
+    /// Create a read transaction to be able to read the index.
+    pub fn read_txn(&self) -> heed::Result<RoTxn> {
+        self.index.read_txn()
+    }
+
+    pub fn search<'a>(&'a self, rtxn: &'a RoTxn) -> Search<'a> {
+        self.index.search(rtxn)
+    }
+
+    pub fn documents_iter<'a>(&'a self, rtxn: &'a RoTxn) -> DocumentsQuery<'a> {
+        self.index.documents_iter(rtxn)
+    }
+
+    pub fn facets_distribution<'a>(&'a self, rtxn: &'a RoTxn) -> FacetDistribution<'a> {
+        self.index.facets_distribution(rtxn)
+    }
+
+    pub fn documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+    ) -> Result<Vec<(DocumentId, StoredDocument<'t>)>> {
+        self.index.documents(rtxn, ids)
+    }
+
+    pub fn displayed_documents(
+        &self,
+        rtxn: &RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+    ) -> Result<Vec<(DocumentId, Map<String, Value>)>> {
+        self.index.displayed_documents(rtxn, ids)
+    }
+
+    pub fn document_json(&self, rtxn: &RoTxn, docid: DocumentId) -> Result<Map<String, Value>> {
+        self.index.document_json(rtxn, docid)
+    }
+
+    pub fn documents_ids(&self, rtxn: &RoTxn) -> heed::Result<RoaringBitmap> {
+        self.index.documents_ids(rtxn)
+    }
+
+    pub fn number_of_documents(&self, rtxn: &RoTxn) -> Result<u64> {
+        self.index.number_of_documents(rtxn)
+    }
+
+    pub fn external_documents_ids<'t>(&self, rtxn: &'t RoTxn) -> Result<ExternalDocumentsIds<'t>> {
+        self.index.external_documents_ids(rtxn)
+    }
+
+    pub fn fields_ids_map(&self, rtxn: &RoTxn) -> heed::Result<FieldsIdsMap> {
+        self.index.fields_ids_map(rtxn)
+    }
+
+    pub fn primary_key<'t>(&self, rtxn: &'t RoTxn) -> heed::Result<Option<&'t str>> {
+        self.index.primary_key(rtxn)
+    }
+
+    pub fn metadata(&self, rtxn: &RoTxn) -> Result<IndexMetadata> {
+        self.index.metadata(rtxn)
+    }
+
+    /// Returns the canonicalized path where the heed `Env` of this index lives.
+    pub fn path(&self) -> &Path {
+        self.index.path()
+    }
+
+    /// Returns an `EnvClosingEvent` that can be used to wait for the closing event,
+    /// see [`Index::prepare_for_closing`].
+    pub fn prepare_for_closing(self) -> heed::EnvClosingEvent {
+        self.index.prepare_for_closing()
+    }
+}
+
+/// Callbacks invoked around the commit of the write transaction of an index update, see
+/// [`Index::commit_with_hook`], to coordinate the commits with external systems.
+pub trait CommitHook {
+    /// Called once the update wrote all the databases, just before the commit. The hook
+    /// can write into the transaction, e.g. the offset of the update in an external log,
+    /// returning an error aborts the transaction.
+    fn before_commit(&mut self, _index: &Index, _wtxn: &mut RwTxn) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once the transaction is durably committed.
+    fn after_commit(&mut self, _index: &Index) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the transaction is aborted, by [`CommitHook::before_commit`] or
+    /// because the commit itself failed.
+    fn aborted(&mut self, _index: &Index, _error: &Error) {}
+}
+
+/// The progress of [`Index::copy_to_path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// The number of bytes written into the copy.
+    pub copied_bytes: u64,
+    /// The size of the file of the index, the copy is at most as large.
+    pub total_bytes: u64,
+}
+
+/// The identity of an index, see [`Index::metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexMetadata {
+    pub uuid: Uuid,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+/// The size of a database of the index, see [`Index::database_sizes`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DatabaseSize {
+    /// The number of entries in the database.
+    pub entries: u64,
+    /// The sum of the lengths of the keys.
+    pub keys_bytes: u64,
+    /// The sum of the lengths of the values.
+    pub values_bytes: u64,
+}
+
+impl DatabaseSize {
+    /// The size of a node header in the pages of LMDB, stored along with every entry.
+    const NODE_HEADER_SIZE: u64 = 8;
+
+    /// An estimation of the space taken on disk by the entries, it doesn't take
+    /// the free space of the pages and the branch pages of the B-tree into account.
+    pub fn estimated_disk_size(&self) -> u64 {
+        self.keys_bytes + self.values_bytes + self.entries * Self::NODE_HEADER_SIZE
+    }
+}
+
+/// The statistics of a word of the index, see [`Index::term_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermStats {
+    /// The number of documents containing the word, its document frequency.
+    pub documents_count: u64,
+    /// Whether the word is one of the prefixes for which the documents are precomputed.
+    pub is_prefix: bool,
+    /// The number of candidates containing the word, when candidates are given.
+    pub candidates_documents_count: Option<u64>,
+}
+
+/// The statistics of a field of the index, see [`Index::fields_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FieldStats {
+    /// The number of distinct words of the field.
+    pub unique_words: u64,
+    /// The number of documents of every word of the field, summed.
+    pub word_postings: u64,
+    /// The number of words of the field in every document, summed. The proximities
+    /// between the words of the documents grow with it.
+    pub word_positions: u64,
+    /// The number of distinct facet values of the field.
+    pub facet_values: u64,
+}
+
+/// Picks `n` distinct ids of the bitmap at random, their ranks are drawn with Floyd's
+/// algorithm and the ids at these ranks are selected in a single pass over the bitmap.
+fn sample_docids(docids: &RoaringBitmap, n: usize, seed: u64) -> Vec<DocumentId> {
+    let len = docids.len();
+    let n = (n as u64).min(len);
+    if n == len {
+        return docids.iter().collect();
+    }
+
+    let mut state = seed;
+    let mut ranks = BTreeSet::new();
+    for max in (len - n)..len {
+        let rank = splitmix64(&mut state) % (max + 1);
+        if !ranks.insert(rank) {
+            ranks.insert(max);
+        }
+    }
+
+    let mut ranks = ranks.into_iter().peekable();
+    let mut output = Vec::with_capacity(n as usize);
+    for (rank, docid) in (0..).zip(docids.iter()) {
+        match ranks.peek() {
+            Some(&wanted) if wanted == rank => {
+                output.push(docid);
+                ranks.next();
+            }
+            Some(_) => (),
+            None => break,
+        }
+    }
+    output
+}
+
+/// A small and fast seeded pseudorandom number generator, the next number of the sequence
+/// is returned and the state is advanced.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The key of the documents ids that have the given boolean under this field id.
+fn facet_bool_docids_key(field_id: FieldId, value: bool) -> Vec<u8> {
+    let prefix = if value {
+        main_key::FACET_ID_IS_TRUE_DOCIDS_PREFIX
+    } else {
+        main_key::FACET_ID_IS_FALSE_DOCIDS_PREFIX
+    };
+    let mut key = Vec::with_capacity(prefix.len() + size_of::<FieldId>());
+    key.extend_from_slice(prefix.as_bytes());
+    key.extend_from_slice(&field_id.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::ops::Deref;
+
+    use big_s::S;
+    use heed::types::Str;
+    use heed::{CompactionOption, EnvOpenOptions, RwTxn};
+    use maplit::{btreemap, btreeset, hashset};
+    use roaring::RoaringBitmap;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::{
+        db_name, main_key, CommitHook, DatabaseSize, TermStats, FORMAT_VERSION, NUMBER_OF_DATABASES,
+    };
+    use crate::error::{Error, UserError};
+    use crate::update::{
+        DeleteDocuments, IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings,
+    };
+    use crate::{Filter, Index};
+
+    pub(crate) struct TempIndex {
+        inner: Index,
+        _tempdir: TempDir,
+    }
+
+    impl Deref for TempIndex {
+        type Target = Index;
+
+        fn deref(&self) -> &Self::Target {
+            &self.inner
+        }
+    }
+
+    impl TempIndex {
+        /// Creates a temporary index, with a default `4096 * 100` size. This should be enough for
+        /// most tests.
+        pub fn new() -> Self {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(100 * 4096);
+            let _tempdir = TempDir::new_in(".").unwrap();
+            let inner = Index::new(options, _tempdir.path()).unwrap();
+            Self { inner, _tempdir }
+        }
+    }
+
+    #[test]
+    fn initial_field_distribution() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "bob", "age": 20 },
+            { "id": 2, "name": "bob", "age": 20 }
+        ]);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let field_distribution = index.field_distribution(&rtxn).unwrap();
+        assert_eq!(
+            field_distribution,
+            btreemap! {
+                "id".to_string() => 2,
+                "name".to_string() => 2,
+                "age".to_string() => 1,
+            }
+        );
+
+        // we add all the documents a second time. we are supposed to get the same
+        // field_distribution in the end
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "bob", "age": 20 },
+            { "id": 2, "name": "bob", "age": 20 }
+        ]);
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let field_distribution = index.field_distribution(&rtxn).unwrap();
+        assert_eq!(
+            field_distribution,
+            btreemap! {
+                "id".to_string() => 2,
+                "name".to_string() => 2,
+                "age".to_string() => 1,
+            }
+        );
+
+        // then we update a document by removing one field and another by adding one field
+        let content = documents!([
+            { "id": 1, "name": "kevin", "has_dog": true },
+            { "id": 2, "name": "bob" }
+        ]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let field_distribution = index.field_distribution(&rtxn).unwrap();
+        assert_eq!(
+            field_distribution,
+            btreemap! {
+                "id".to_string() => 2,
+                "name".to_string() => 2,
+                "has_dog".to_string() => 1,
+            }
+        );
+    }
+
+    #[test]
+    fn search_external_ids() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "kevin-1", "name": "kevin" },
+            { "id": 2, "name": "bob" }
+        ]);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let crate::SearchResult { documents_ids, external_ids, .. } =
+            index.search(&rtxn).query("kevin").external_ids(true).execute().unwrap();
+        assert_eq!(documents_ids.len(), 1);
+        assert_eq!(external_ids, vec!["kevin-1".to_string()]);
+
+        let crate::SearchResult { external_ids, .. } = index.search(&rtxn).execute().unwrap();
+        assert!(external_ids.is_empty());
+
+        let all_ids = index.documents_ids(&rtxn).unwrap();
+        let mut external_ids = index.external_ids(&rtxn, all_ids).unwrap();
+        external_ids.sort_unstable();
+        assert_eq!(external_ids, vec!["2", "kevin-1"]);
+    }
+
+    #[test]
+    fn search_matched_words() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevin bob" },
+            { "id": 2, "name": "alice" }
+        ]);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let crate::SearchResult { documents_ids, matched_words, query_words, .. } =
+            index.search(&rtxn).query("kevin bob").execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0]);
+        assert_eq!(matched_words, vec![2, 1]);
+        assert_eq!(query_words, 2);
+
+        let crate::SearchResult { documents_ids, matched_words, query_words, .. } =
+            index.search(&rtxn).execute().unwrap();
+        assert_eq!(documents_ids.len(), 3);
+        assert_eq!(matched_words, vec![0, 0, 0]);
+        assert_eq!(query_words, 0);
+    }
+
+    #[test]
+    fn search_restricted_candidates() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevin bob" },
+            { "id": 2, "name": "alice" }
+        ]);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let allowed: RoaringBitmap = vec![0, 2].into_iter().collect();
+        let crate::SearchResult { documents_ids, candidates, .. } =
+            index.search(&rtxn).query("kevin").candidates(allowed.clone()).execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+        assert_eq!(candidates.iter().collect::<Vec<_>>(), vec![0]);
+
+        let crate::SearchResult { documents_ids, .. } =
+            index.search(&rtxn).candidates(allowed).execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2]);
+
+        let crate::SearchResult { documents_ids, .. } =
+            index.search(&rtxn).candidates(RoaringBitmap::new()).execute().unwrap();
+        assert!(documents_ids.is_empty());
+    }
+
+    #[test]
+    fn search_restricted_candidates_with_filter() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "kevin", "color": "red" },
+            { "id": 1, "name": "kevin bob", "color": "blue" },
+            { "id": 2, "name": "alice", "color": "red" },
+            { "id": 3, "name": "bob", "color": "red" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("3");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // The deleted and the unknown ids are never returned.
+        let allowed: RoaringBitmap = vec![1, 2, 3, 42].into_iter().collect();
+        let crate::SearchResult { documents_ids, candidates, .. } =
+            index.search(&rtxn).candidates(allowed.clone()).execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 2]);
+        assert_eq!(candidates.iter().collect::<Vec<_>>(), vec![1, 2]);
+
+        let filter = Filter::from_str("color = red").unwrap().unwrap();
+        let crate::SearchResult { documents_ids, .. } = index
+            .search(&rtxn)
+            .candidates(allowed.clone())
+            .filter(filter.clone())
+            .execute()
+            .unwrap();
+        assert_eq!(documents_ids, vec![2]);
+
+        let crate::SearchResult { documents_ids, .. } = index
+            .search(&rtxn)
+            .query("kevin")
+            .candidates(allowed)
+            .filter(filter)
+            .execute()
+            .unwrap();
+        assert!(documents_ids.is_empty());
+    }
+
+    #[test]
+    fn search_with_acl() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin", "_acl": "admin" },
+            { "id": 1, "name": "kevin bob", "_acl": ["admin", "bob"] },
+            { "id": 2, "name": "kevin alice", "_acl": null },
+            { "id": 3, "name": "alice" }
+        ]);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let admin = index.acl_token_docids.get(&rtxn, "admin").unwrap().unwrap();
+        assert_eq!(admin.iter().collect::<Vec<_>>(), vec![0, 1]);
+        let bob = index.acl_token_docids.get(&rtxn, "bob").unwrap().unwrap();
+        assert_eq!(bob.iter().collect::<Vec<_>>(), vec![1]);
+
+        // The access control tokens are not searchable.
+        let crate::SearchResult { documents_ids, .. } =
+            index.search(&rtxn).query("admin").execute().unwrap();
+        assert!(documents_ids.is_empty());
+
+        let crate::SearchResult { documents_ids, .. } =
+            index.search(&rtxn).query("kevin").with_acl(&["bob"]).execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+
+        let crate::SearchResult { mut documents_ids, .. } =
+            index.search(&rtxn).query("kevin").with_acl(&["bob", "admin"]).execute().unwrap();
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        let crate::SearchResult { documents_ids, .. } =
+            index.search(&rtxn).with_acl::<&str>(&[]).execute().unwrap();
+        assert!(documents_ids.is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 4, "name": "kevin", "_acl": 42 }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(crate::error::UserError::InvalidAclField { .. })
+        ));
+    }
+
+    #[test]
+    fn term_stats() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // There must be enough words starting with "hel" for it to be a prefix.
+        let words: Vec<_> =
+            ('a'..='z').flat_map(|a| ('a'..='b').map(move |b| format!("hel{}{}", a, b))).collect();
+        let words = words.join(" ");
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "hello world" },
+            { "id": 1, "name": "hello help" },
+            { "id": 2, "name": "hello" },
+            { "id": 3, "name": words }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let stats = index.term_stats(&rtxn, "hello", None).unwrap();
+        assert_eq!(
+            stats,
+            TermStats { documents_count: 3, is_prefix: false, candidates_documents_count: None }
+        );
+
+        let candidates: RoaringBitmap = (1..3).collect();
+        let stats = index.term_stats(&rtxn, "help", Some(&candidates)).unwrap();
+        assert_eq!(stats.documents_count, 1);
+        assert_eq!(stats.candidates_documents_count, Some(1));
+
+        assert!(index.term_stats(&rtxn, "hel", None).unwrap().is_prefix);
+        let stats = index.term_stats(&rtxn, "unknown", Some(&candidates)).unwrap();
+        assert_eq!(stats.documents_count, 0);
+        assert_eq!(stats.candidates_documents_count, Some(0));
+    }
+
+    #[test]
+    fn snapshot() {
+        let path = tempfile::tempdir().unwrap();
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            options
+        };
+        let index = Index::new(options(), &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "bob" }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let snapshot = index.snapshot_to(&rtxn, Vec::new()).unwrap();
+
+        // The snapshot is not affected by the changes committed after its transaction.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = crate::update::ClearDocuments::new(&mut wtxn, &index);
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        assert_eq!(index.snapshot_to(&rtxn, Vec::new()).unwrap(), snapshot);
+        drop(rtxn);
+
+        let restored_path = tempfile::tempdir().unwrap();
+        let restored = Index::restore_snapshot(options(), &restored_path, &snapshot[..]).unwrap();
+        let rtxn = restored.read_txn().unwrap();
+        assert_eq!(restored.number_of_documents(&rtxn).unwrap(), 2);
+        let crate::SearchResult { documents_ids, .. } =
+            restored.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+        drop(rtxn);
+
+        let other_path = tempfile::tempdir().unwrap();
+        let result = Index::restore_snapshot(options(), &other_path, &b"nope"[..]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn database_sizes() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevin bob" }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let sizes = index.database_sizes(&rtxn).unwrap();
+        assert_eq!(sizes.len(), index.databases().len());
+
+        let documents = sizes[crate::index::db_name::DOCUMENTS];
+        assert_eq!(documents.entries, 2);
+        assert_eq!(documents.keys_bytes, 8);
+        assert!(documents.values_bytes > 0);
+        assert!(documents.estimated_disk_size() > documents.values_bytes);
+
+        // "kevin" and "bob"
+        assert_eq!(sizes[crate::index::db_name::WORD_DOCIDS].entries, 2);
+        assert_eq!(sizes[crate::index::db_name::FACET_ID_F64_DOCIDS], DatabaseSize::default());
     }
 
-    pub(crate) fn set_updated_at(
-        &self,
-        wtxn: &mut RwTxn,
-        time: &OffsetDateTime,
-    ) -> heed::Result<()> {
-        self.main.put::<_, Str, SerdeJson<OffsetDateTime>>(wtxn, main_key::UPDATED_AT_KEY, &time)
+    #[test]
+    fn commit_with_hook() {
+        #[derive(Default)]
+        struct Hook {
+            fail: bool,
+            calls: Vec<&'static str>,
+        }
+
+        impl CommitHook for Hook {
+            fn before_commit(&mut self, index: &Index, wtxn: &mut RwTxn) -> crate::Result<()> {
+                self.calls.push("before");
+                index.put_stored_query(wtxn, "offset", &crate::StoredQuery::new("42"))?;
+                if self.fail {
+                    Err(crate::error::UserError::InvalidStoreFile.into())
+                } else {
+                    Ok(())
+                }
+            }
+
+            fn after_commit(&mut self, _index: &Index) -> crate::Result<()> {
+                self.calls.push("after");
+                Ok(())
+            }
+
+            fn aborted(&mut self, _index: &Index, _error: &crate::Error) {
+                self.calls.push("aborted");
+            }
+        }
+
+        let index = TempIndex::new();
+
+        let mut hook = Hook { fail: true, ..Default::default() };
+        let wtxn = index.write_txn().unwrap();
+        assert!(index.commit_with_hook(wtxn, &mut hook).is_err());
+        assert_eq!(hook.calls, vec!["before", "aborted"]);
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.stored_queries(&rtxn).unwrap().is_empty());
+        drop(rtxn);
+
+        // The writes of the hook are committed with the transaction.
+        let mut hook = Hook::default();
+        let wtxn = index.write_txn().unwrap();
+        index.commit_with_hook(wtxn, &mut hook).unwrap();
+        assert_eq!(hook.calls, vec!["before", "after"]);
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.stored_queries(&rtxn).unwrap().contains_key("offset"));
     }
-}
 
-#[cfg(test)]
-pub(crate) mod tests {
-    use std::ops::Deref;
+    #[test]
+    fn metadata() {
+        let path = tempfile::tempdir().unwrap();
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            options
+        };
+        let index = Index::new(options(), &path).unwrap();
 
-    use heed::EnvOpenOptions;
-    use maplit::btreemap;
-    use tempfile::TempDir;
+        let rtxn = index.read_txn().unwrap();
+        let metadata = index.metadata(&rtxn).unwrap();
+        assert_eq!(metadata.created_at, metadata.updated_at);
+        drop(rtxn);
 
-    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
-    use crate::Index;
+        // The uuid is the same when the index is reopened.
+        index.prepare_for_closing().wait();
+        let index = Index::new(options(), &path).unwrap();
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.metadata(&rtxn).unwrap(), metadata);
 
-    pub(crate) struct TempIndex {
-        inner: Index,
-        _tempdir: TempDir,
+        // But not for another index.
+        let other = TempIndex::new();
+        let rtxn = other.read_txn().unwrap();
+        assert_ne!(other.uuid(&rtxn).unwrap(), metadata.uuid);
     }
 
-    impl Deref for TempIndex {
-        type Target = Index;
+    #[test]
+    fn copy_to_path() {
+        let path = tempfile::tempdir().unwrap();
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            options
+        };
+        let index = Index::new(options(), &path).unwrap();
 
-        fn deref(&self) -> &Self::Target {
-            &self.inner
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let documents: Vec<_> = (0..500)
+            .map(|i| json!({ "id": i, "title": format!("the document number {} of the index", i) }))
+            .collect();
+        let content = documents!(documents);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        for i in 1..500 {
+            builder.delete_external_id(&i.to_string());
         }
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The pages freed by the deletions are not copied.
+        let copy_path = tempfile::tempdir().unwrap();
+        let mut last_progress = None;
+        index
+            .copy_to_path(&copy_path, CompactionOption::Enabled, |p| last_progress = Some(p))
+            .unwrap();
+        let last_progress = last_progress.unwrap();
+        let copy_size = std::fs::metadata(copy_path.path().join("data.mdb")).unwrap().len();
+        assert_eq!(last_progress.copied_bytes, copy_size);
+        assert!(last_progress.copied_bytes < last_progress.total_bytes);
+
+        let copy = Index::new(options(), &copy_path).unwrap();
+        let rtxn = copy.read_txn().unwrap();
+        assert_eq!(copy.number_of_documents(&rtxn).unwrap(), 1);
+        let result = copy.search(&rtxn).query("document").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
     }
 
-    impl TempIndex {
-        /// Creates a temporary index, with a default `4096 * 100` size. This should be enough for
-        /// most tests.
-        pub fn new() -> Self {
-            let mut options = EnvOpenOptions::new();
-            options.map_size(100 * 4096);
-            let _tempdir = TempDir::new_in(".").unwrap();
-            let inner = Index::new(options, _tempdir.path()).unwrap();
-            Self { inner, _tempdir }
+    #[test]
+    fn shared_environment() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        options.max_dbs(2 * super::NUMBER_OF_DATABASES);
+        let env = options.open(&path).unwrap();
+
+        let config = IndexerConfig::default();
+        for (name, title) in [("first", "the cat"), ("second", "the dog")].iter() {
+            let index = Index::open_in_env(&env, name).unwrap();
+            let mut wtxn = index.write_txn().unwrap();
+            let content = documents!([{ "id": 0, "title": title }]);
+            let indexing_config = IndexDocumentsConfig::default();
+            let mut builder =
+                IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
         }
+
+        // The indexes don't see the documents of each other.
+        let first = Index::open_in_env(&env, "first").unwrap();
+        let second = Index::open_in_env(&env, "second").unwrap();
+        let rtxn = env.read_txn().unwrap();
+        assert_eq!(first.search(&rtxn).query("cat").execute().unwrap().documents_ids, vec![0]);
+        assert!(first.search(&rtxn).query("dog").execute().unwrap().documents_ids.is_empty());
+        assert_eq!(second.search(&rtxn).query("dog").execute().unwrap().documents_ids, vec![0]);
+        assert_ne!(first.uuid(&rtxn).unwrap(), second.uuid(&rtxn).unwrap());
+
+        // A third index can't be opened in the environment.
+        drop(rtxn);
+        assert!(Index::open_in_env(&env, "third").is_err());
     }
 
     #[test]
-    fn initial_field_distribution() {
+    fn user_metadata() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        index.put_user_metadata(&mut wtxn, "tasks", "last", b"42").unwrap();
+        index.put_user_metadata(&mut wtxn, "tasks", "first", b"1").unwrap();
+        index.put_user_metadata(&mut wtxn, "task", "slast", b"0").unwrap();
+        index.put_user_metadata(&mut wtxn, "schema", "hash", b"abc").unwrap();
+        wtxn.commit().unwrap();
+
+        // The values written by an aborted transaction are lost.
+        let mut wtxn = index.write_txn().unwrap();
+        index.put_user_metadata(&mut wtxn, "tasks", "last", b"43").unwrap();
+        wtxn.abort().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.get_user_metadata(&rtxn, "tasks", "last").unwrap(), Some(&b"42"[..]));
+        assert_eq!(index.get_user_metadata(&rtxn, "schema", "last").unwrap(), None);
+        assert_eq!(
+            index.user_metadata(&rtxn, "tasks").unwrap(),
+            btreemap! { "first" => &b"1"[..], "last" => &b"42"[..] }
+        );
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        assert!(index.delete_user_metadata(&mut wtxn, "tasks", "last").unwrap());
+        assert!(!index.delete_user_metadata(&mut wtxn, "tasks", "last").unwrap());
+        assert_eq!(index.user_metadata(&wtxn, "tasks").unwrap().len(), 1);
+        assert_eq!(index.user_metadata(&wtxn, "task").unwrap().len(), 1);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn open_read_only() {
+        let path = tempfile::tempdir().unwrap();
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            options
+        };
+
+        // The index is never created in read-only mode.
+        assert!(Index::open_read_only(options(), &path).is_err());
+
+        let index = Index::new(options(), &path).unwrap();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 0, "title": "the cat" }, { "id": 1, "title": "a dog" }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        index.prepare_for_closing().wait();
+
+        let index = Index::open_read_only(options(), &path).unwrap();
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+        let result = index.search(&rtxn).query("cat").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let json = index.document_json(&rtxn, 0).unwrap();
+        assert_eq!(serde_json::Value::Object(json), json!({ "id": 0, "title": "the cat" }));
+        drop(rtxn);
+        index.prepare_for_closing().wait();
+    }
+
+    #[test]
+    fn open_read_only_older_format() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
-        let index = Index::new(options, &path).unwrap();
+        options.max_dbs(NUMBER_OF_DATABASES);
+
+        // An index of the version 0 format, the databases added since then are missing.
+        let env = options.open(&path).unwrap();
+        let main = env.create_poly_database(Some(db_name::MAIN)).unwrap();
+        let documents = env.create_database::<Str, Str>(Some(db_name::DOCUMENTS)).unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        main.put::<_, Str, Str>(&mut wtxn, main_key::PRIMARY_KEY_KEY, "id").unwrap();
+        documents.put(&mut wtxn, "0", "{}").unwrap();
+        wtxn.commit().unwrap();
+        env.prepare_for_closing().wait();
+
+        let result = Index::open_read_only(options, &path);
+        assert!(matches!(
+            result,
+            Err(Error::UserError(UserError::InvalidIndexVersion {
+                version: 0,
+                expected: FORMAT_VERSION
+            }))
+        ));
+    }
 
+    #[test]
+    fn stop_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
         let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+
         let content = documents!([
-            { "id": 1, "name": "kevin" },
-            { "id": 2, "name": "bob", "age": 20 },
-            { "id": 2, "name": "bob", "age": 20 }
+            { "id": 0, "title": "the cat" },
+            { "id": 1, "title": "the dog" },
+            { "id": 2, "title": "a bird" }
         ]);
-
-        let config = IndexerConfig::default();
         let indexing_config = IndexDocumentsConfig::default();
-        let mut builder =
-            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
         builder.add_documents(content).unwrap();
         builder.execute().unwrap();
         wtxn.commit().unwrap();
 
         let rtxn = index.read_txn().unwrap();
+        assert!(index.is_stop_word(&rtxn, "the").unwrap());
+        assert!(!index.is_stop_word(&rtxn, "bird").unwrap());
 
-        let field_distribution = index.field_distribution(&rtxn).unwrap();
-        assert_eq!(
-            field_distribution,
-            btreemap! {
-                "id".to_string() => 2,
-                "name".to_string() => 2,
-                "age".to_string() => 1,
-            }
-        );
+        // The stop word is dropped from the query...
+        let mut search = index.search(&rtxn);
+        search.query("the bird").optional_words(false);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![2]);
+
+        // ...unless it must be searched.
+        search.ignore_stop_words(false);
+        assert!(search.execute().unwrap().documents_ids.is_empty());
+        search.query("the dog");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1]);
+    }
+
+    #[test]
+    fn documents_with_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
 
-        // we add all the documents a second time. we are supposed to get the same
-        // field_distribution in the end
         let mut wtxn = index.write_txn().unwrap();
-        let mut builder =
-            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
         let content = documents!([
-            { "id": 1, "name": "kevin" },
-            { "id": 2, "name": "bob", "age": 20 },
-            { "id": 2, "name": "bob", "age": 20 }
+            { "id": 0, "title": "the cat", "price": 10, "secret": "a" },
+            { "id": 1, "title": "the dog", "secret": "b" }
         ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
         builder.add_documents(content).unwrap();
         builder.execute().unwrap();
         wtxn.commit().unwrap();
 
         let rtxn = index.read_txn().unwrap();
+        let documents =
+            index.documents_with_fields(&rtxn, vec![1, 0], &["price", "title", "price"]).unwrap();
+        assert_eq!(
+            documents,
+            vec![
+                (1, json!({ "title": "the dog" }).as_object().unwrap().clone()),
+                (0, json!({ "price": 10, "title": "the cat" }).as_object().unwrap().clone()),
+            ]
+        );
+        let fields: Vec<_> = documents[1].1.keys().collect();
+        assert_eq!(fields, vec!["price", "title"]);
+        drop(rtxn);
 
-        let field_distribution = index.field_distribution(&rtxn).unwrap();
+        // The fields that are not displayed are never returned.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_displayed_fields(vec![S("title"), S("price")]);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let documents = index.documents_with_fields(&rtxn, vec![0], &["secret", "title"]).unwrap();
         assert_eq!(
-            field_distribution,
-            btreemap! {
-                "id".to_string() => 2,
-                "name".to_string() => 2,
-                "age".to_string() => 1,
-            }
+            documents,
+            vec![(0, json!({ "title": "the cat" }).as_object().unwrap().clone())]
         );
 
-        // then we update a document by removing one field and another by adding one field
-        let content = documents!([
-            { "id": 1, "name": "kevin", "has_dog": true },
-            { "id": 2, "name": "bob" }
-        ]);
+        let documents = index.displayed_documents(&rtxn, vec![0]).unwrap();
+        assert_eq!(
+            documents,
+            vec![(0, json!({ "title": "the cat", "price": 10 }).as_object().unwrap().clone())]
+        );
+        assert!(index.documents_with_fields(&rtxn, vec![2], &["title"]).is_err());
+    }
 
+    #[test]
+    fn document_json() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
         let mut wtxn = index.write_txn().unwrap();
-        let mut builder =
-            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("address.city") });
+        builder.set_displayed_fields(vec![S("title")]);
+        builder.execute(|_| ()).unwrap();
+
+        let document = json!({
+            "id": 0,
+            "title": "the cat",
+            "address": { "city": "Paris", "geo": { "lat": 48.8, "lng": 2.3 } },
+            "tags": [{ "name": "pet" }, "animal"],
+            "secret": null
+        });
+        let content = documents!([document]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
         builder.add_documents(content).unwrap();
         builder.execute().unwrap();
         wtxn.commit().unwrap();
 
+        // All the fields are returned, the nested ones inside of their objects.
         let rtxn = index.read_txn().unwrap();
+        assert!(index.fields_ids_map(&rtxn).unwrap().id("address.city").is_some());
+        let json = index.document_json(&rtxn, 0).unwrap();
+        assert_eq!(serde_json::Value::Object(json), document);
+        assert!(index.document_json(&rtxn, 1).is_err());
+    }
 
-        let field_distribution = index.field_distribution(&rtxn).unwrap();
-        assert_eq!(
-            field_distribution,
-            btreemap! {
-                "id".to_string() => 2,
-                "name".to_string() => 2,
-                "has_dog".to_string() => 1,
-            }
-        );
+    #[test]
+    fn sample_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("even") });
+        builder.execute(|_| ()).unwrap();
+
+        let documents: Vec<_> = (0..100).map(|i| json!({ "id": i, "even": i % 2 == 0 })).collect();
+        let content = documents!(documents);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let sample = index.sample_documents(&rtxn, 10, 42).unwrap();
+        assert_eq!(sample.len(), 10);
+        assert!(sample.windows(2).all(|w| w[0] < w[1]));
+        assert!(sample.iter().all(|&id| id < 100));
+        assert_eq!(index.sample_documents(&rtxn, 10, 42).unwrap(), sample);
+        assert_ne!(index.sample_documents(&rtxn, 10, 43).unwrap(), sample);
+
+        assert_eq!(index.sample_documents(&rtxn, 1000, 42).unwrap().len(), 100);
+        assert!(index.sample_documents(&rtxn, 0, 42).unwrap().is_empty());
+
+        let filter = Filter::from_str("even = true").unwrap().unwrap();
+        let sample = index.sample_filtered_documents(&rtxn, &filter, 20, 7).unwrap();
+        assert_eq!(sample.len(), 20);
+        let evens = filter.evaluate(&rtxn, &index).unwrap();
+        assert!(sample.iter().all(|id| evens.contains(*id)));
+    }
+
+    #[test]
+    fn fields_stats() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title")]);
+        builder.set_filterable_fields(hashset! { S("color"), S("price"), S("sold") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the red cat", "color": "red", "price": 10, "sold": true },
+            { "id": 1, "title": "the blue cat", "color": "blue", "price": 10, "sold": true },
+            { "id": 2, "title": "a dog", "color": "Red", "price": 20 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let stats = index.fields_stats(&rtxn).unwrap();
+        let field_stats = |name| stats[&fields_ids_map.id(name).unwrap()];
+
+        let title = field_stats("title");
+        // the, red, cat, blue, a, dog
+        assert_eq!(title.unique_words, 6);
+        assert_eq!(title.word_postings, 8);
+        assert_eq!(title.word_positions, 8);
+        assert_eq!(title.facet_values, 0);
+
+        assert_eq!(field_stats("color").facet_values, 2);
+        assert_eq!(field_stats("price").facet_values, 2);
+        assert_eq!(field_stats("sold").facet_values, 1);
     }
 }
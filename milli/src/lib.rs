@@ -1,16 +1,23 @@
 #[macro_use]
 pub mod documents;
+pub mod dump;
 
 mod asc_desc;
 mod criterion;
+mod documents_compression;
 mod error;
 mod external_documents_ids;
 pub mod facet;
+mod field_types;
 mod fields_ids_map;
 pub mod heed_codec;
 pub mod index;
+pub mod limits;
+pub mod migrate;
 pub mod proximity;
 mod search;
+mod skip_corrupted;
+mod tokenization;
 pub mod update;
 
 use std::collections::{BTreeMap, HashMap};
@@ -20,23 +27,45 @@ use std::hash::BuildHasherDefault;
 pub use filter_parser::{Condition, FilterCondition};
 use fxhash::{FxHasher32, FxHasher64};
 pub use grenad::CompressionType;
+pub use heed::CompactionOption;
 pub use meilisearch_tokenizer as tokenizer;
 use serde_json::{Map, Value};
 
 pub use self::asc_desc::{AscDesc, AscDescError, Member, SortError};
 pub use self::criterion::{default_criteria, Criterion, CriterionError};
+pub use self::documents_compression::StoredDocument;
 pub use self::error::{
-    Error, FieldIdMapMissingEntry, InternalError, SerializationError, UserError,
+    Error, ErrorCode, FieldIdMapMissingEntry, FilterParseError, InternalError, SerializationError,
+    UserError,
 };
 pub use self::external_documents_ids::ExternalDocumentsIds;
+pub use self::field_types::FieldTypes;
 pub use self::fields_ids_map::FieldsIdsMap;
 pub use self::heed_codec::{
     BEU32StrCodec, BoRoaringBitmapCodec, BoRoaringBitmapLenCodec, CboRoaringBitmapCodec,
     CboRoaringBitmapLenCodec, FieldIdWordCountCodec, ObkvCodec, RoaringBitmapCodec,
-    RoaringBitmapLenCodec, StrBEU32Codec, StrStrU8Codec,
+    RoaringBitmapLenCodec, StrBEU16Codec, StrBEU32Codec, StrStrU8Codec,
+};
+pub use self::index::{
+    CommitHook, CopyProgress, DatabaseSize, FieldStats, Index, IndexMetadata, ReadOnlyIndex,
+    TermStats,
+};
+pub use self::limits::MAX_POSITION_PER_ATTRIBUTE;
+pub use self::proximity::ProximityPrecision;
+pub use self::search::{
+    Comparison, Completion, Completions, CorrectedSearchResult, Decay, DecayCurve,
+    DocumentFeatures, DocumentsQuery, DocumentsQueryResult, FacetDistribution, FacetVocabulary,
+    Filter, FormatOptions, GeoFilterStats, HighlightOptions, InterpretedFilter, MatchBounds,
+    Matcher, MatcherBuilder, MatchingStrategy, MatchingWords, MinWordMatch, NumericVocabulary,
+    Percolate, PrefixAudit, PrefixDiscrepancy, QueryInterpretation, QueryInterpreter,
+    RankingFeatures, RelaxationHint, Search, SearchQuery, SearchResult, SimilarDocuments,
+    SimilarDocumentsResult, Similarity, StoredQuery, Suggest, Suggestion, TypoCosts,
+    UnindexedFilterWarning,
+};
+pub use self::skip_corrupted::SkipCorrupted;
+pub use self::tokenization::{
+    DefaultTokenizer, EmojiPolicy, ProtectedPattern, Tokenize, TokenizerSettings,
 };
-pub use self::index::Index;
-pub use self::search::{FacetDistribution, Filter, MatchingWords, Search, SearchResult};
 
 pub type Result<T> = std::result::Result<T, error::Error>;
 
@@ -54,14 +83,13 @@ pub type FieldId = u16;
 pub type Position = u32;
 pub type RelativePosition = u16;
 pub type FieldDistribution = BTreeMap<String, u64>;
+pub type FieldTypesDistribution = BTreeMap<String, FieldTypes>;
 
 /// A GeoPoint is a point in cartesian plan, called xyz_point in the code. Its metadata
 /// is a tuple composed of 1. the DocumentId of the associated document and 2. the original point
 /// expressed in term of latitude and longitude.
 pub type GeoPoint = rstar::primitives::GeomWithData<[f64; 3], (DocumentId, [f64; 2])>;
 
-pub const MAX_POSITION_PER_ATTRIBUTE: u32 = u16::MAX as u32 + 1;
-
 // Convert an absolute word position into a relative position.
 // Return the field id of the attribute related to the absolute position
 // and the relative position in the attribute.
@@ -0,0 +1,33 @@
+//! The limits of an index.
+//!
+//! The hard limits come from the encoding of the databases and cannot be changed, the soft
+//! ones are the defaults of the [`IndexerConfig`](crate::update::IndexerConfig) settings
+//! which can only be lowered.
+
+use crate::{DocumentId, FieldId};
+
+/// The maximum number of fields of an index, the field ids are encoded on an `u16`.
+pub const MAX_FIELDS: usize = FieldId::MAX as usize + 1;
+
+/// The maximum number of documents of an index, the internal document ids are encoded on an `u32`.
+pub const MAX_DOCUMENTS: u64 = DocumentId::MAX as u64 + 1;
+
+/// The maximum number of positions of the words of an attribute,
+/// the positions are encoded with the field id in an `u32`.
+pub const MAX_POSITION_PER_ATTRIBUTE: u32 = u16::MAX as u32 + 1;
+
+/// The maximum length, in bytes, of the keys of LMDB, the longer keys are never written.
+pub const MAX_LMDB_KEY_LENGTH: usize = 511;
+
+/// The default maximum length, in bytes, of the words stored in the word databases,
+/// the longer words are not indexed.
+///
+/// It is half the LMDB key length as two words are stored in the keys of the word pair
+/// proximity databases.
+pub const MAX_WORD_LENGTH: usize = MAX_LMDB_KEY_LENGTH / 2;
+
+/// The default maximum length, in bytes, of the normalized facet strings,
+/// the longer facet values are not indexed.
+///
+/// It leaves room for the field id, the document id and the level stored in the facet keys.
+pub const MAX_FACET_VALUE_LENGTH: usize = MAX_LMDB_KEY_LENGTH - 32;
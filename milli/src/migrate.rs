@@ -0,0 +1,198 @@
+//! Upgrades in place the indexes written with an older format version,
+//! see [`FORMAT_VERSION`](crate::index::FORMAT_VERSION).
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use heed::types::ByteSlice;
+use heed::{EnvOpenOptions, RwTxn};
+use roaring::RoaringBitmap;
+use serde_json::Value;
+use tracing::info;
+
+use crate::error::{InternalError, UserError};
+use crate::field_types::add_document_types;
+use crate::index::{ACL_FIELD_NAME, FORMAT_VERSION};
+use crate::limits::MAX_LMDB_KEY_LENGTH;
+use crate::{FieldTypesDistribution, Index, Result, BEU32};
+
+/// Upgrades the index at the given path to the current format version and opens it,
+/// the index is opened as is when it is already written with the current version.
+///
+/// All the upgrades are done in a single write transaction, the index is left untouched
+/// when one of them fails.
+pub fn migrate<P: AsRef<Path>>(options: EnvOpenOptions, path: P) -> Result<Index> {
+    let index = Index::new_unchecked(options, path)?;
+    let mut wtxn = index.write_txn()?;
+
+    let mut version = index.format_version(&wtxn)?;
+    if version > FORMAT_VERSION {
+        return Err(UserError::InvalidIndexVersion { version, expected: FORMAT_VERSION }.into());
+    }
+
+    while version < FORMAT_VERSION {
+        info!("Migrating the index from the format version {} to {}", version, version + 1);
+        match version {
+            0 => v0_to_v1(&index, &mut wtxn)?,
+            1 => v1_to_v2(&index, &mut wtxn)?,
+            2 => v2_to_v3(&index, &mut wtxn)?,
+            3 => v3_to_v4(&index, &mut wtxn)?,
+            _ => unreachable!("missing migration from the format version {}", version),
+        }
+        version += 1;
+    }
+
+    index.put_format_version(&mut wtxn, version)?;
+    wtxn.commit()?;
+
+    Ok(index)
+}
+
+/// The version 1 maps the internal documents ids to their external ids in their own database.
+fn v0_to_v1(index: &Index, wtxn: &mut RwTxn) -> Result<()> {
+    if index.docid_external_ids.is_empty(wtxn)? {
+        let external_documents_ids = index.external_documents_ids(wtxn)?;
+        for (external_id, docid) in external_documents_ids.to_hash_map() {
+            index.docid_external_ids.put(wtxn, &BEU32::new(docid), &external_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// The version 2 counts the JSON types of the values of the fields of the documents.
+fn v1_to_v2(index: &Index, wtxn: &mut RwTxn) -> Result<()> {
+    let fields_ids_map = index.fields_ids_map(wtxn)?;
+    let mut field_types_distribution = FieldTypesDistribution::default();
+    for result in index.all_documents(wtxn)? {
+        let (_docid, obkv) = result?;
+        add_document_types(&mut field_types_distribution, &fields_ids_map, obkv.as_obkv())?;
+    }
+    index.put_field_types_distribution(wtxn, &field_types_distribution)?;
+    Ok(())
+}
+
+/// The version 3 stores the words as written in the documents, the words of the documents
+/// already indexed are unknown, their normalized words are used until they are reindexed.
+fn v2_to_v3(index: &Index, wtxn: &mut RwTxn) -> Result<()> {
+    if index.exact_word_docids.is_empty(wtxn)? {
+        let word_docids = index.word_docids.remap_data_type::<ByteSlice>();
+        let exact_word_docids = index.exact_word_docids.remap_data_type::<ByteSlice>();
+        // The words must be collected as the databases can't be read while being written.
+        let words: Vec<(String, Vec<u8>)> = word_docids
+            .iter(wtxn)?
+            .map(|result| result.map(|(word, docids)| (word.to_string(), docids.to_vec())))
+            .collect::<heed::Result<_>>()?;
+        for (word, docids) in words {
+            exact_word_docids.put(wtxn, &word, &docids)?;
+        }
+    }
+    Ok(())
+}
+
+/// The version 4 maps the access control tokens of the `_acl` field to the documents,
+/// the invalid values of the documents already indexed are ignored.
+fn v3_to_v4(index: &Index, wtxn: &mut RwTxn) -> Result<()> {
+    let acl_field_id = match index.fields_ids_map(wtxn)?.id(ACL_FIELD_NAME) {
+        Some(acl_field_id) => acl_field_id,
+        None => return Ok(()),
+    };
+
+    let mut acl_token_docids: HashMap<String, RoaringBitmap> = HashMap::new();
+    for result in index.all_documents(wtxn)? {
+        let (docid, obkv) = result?;
+        let value = match obkv.as_obkv().get(acl_field_id) {
+            Some(value) => value,
+            None => continue,
+        };
+        let tokens = match serde_json::from_slice(value).map_err(InternalError::SerdeJson)? {
+            Value::String(token) => vec![token],
+            Value::Array(values) => values
+                .into_iter()
+                .filter_map(|value| match value {
+                    Value::String(token) => Some(token),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        for token in tokens {
+            if !token.is_empty() && token.len() <= MAX_LMDB_KEY_LENGTH {
+                acl_token_docids.entry(token).or_default().insert(docid);
+            }
+        }
+    }
+
+    for (token, docids) in acl_token_docids {
+        index.acl_token_docids.put(wtxn, &token, &docids)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::index::main_key;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn migrate_v0_index() {
+        let path = tempfile::tempdir().unwrap();
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            options
+        };
+        let index = Index::new(options(), &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "kevin", "name": "kevin", "_acl": ["admin", "kevin"] },
+            { "id": "bob", "name": "bob", "_acl": "admin" }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // We turn the index into a version 0 index, without the external ids database,
+        // the field types distribution, the exact words and the access control tokens databases.
+        index.main.delete::<_, heed::types::Str>(&mut wtxn, main_key::FORMAT_VERSION_KEY).unwrap();
+        index.docid_external_ids.clear(&mut wtxn).unwrap();
+        index.exact_word_docids.clear(&mut wtxn).unwrap();
+        index.acl_token_docids.clear(&mut wtxn).unwrap();
+        let types_key = main_key::FIELD_TYPES_DISTRIBUTION_KEY;
+        index.main.delete::<_, heed::types::Str>(&mut wtxn, types_key).unwrap();
+        wtxn.commit().unwrap();
+        index.prepare_for_closing().wait();
+
+        let result = Index::new(options(), &path);
+        assert!(matches!(
+            result,
+            Err(Error::UserError(UserError::InvalidIndexVersion {
+                version: 0,
+                expected: FORMAT_VERSION
+            }))
+        ));
+
+        let index = migrate(options(), &path).unwrap();
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.format_version(&rtxn).unwrap(), FORMAT_VERSION);
+        let mut external_ids = index.external_ids(&rtxn, 0..2).unwrap();
+        external_ids.sort_unstable();
+        assert_eq!(external_ids, vec!["bob", "kevin"]);
+        let field_types_distribution = index.field_types_distribution(&rtxn).unwrap();
+        assert_eq!(field_types_distribution["name"].string, 2);
+        assert!(index.exact_word_docids.get(&rtxn, "kevin").unwrap().is_some());
+        assert_eq!(index.acl_token_docids.get(&rtxn, "admin").unwrap().unwrap().len(), 2);
+        assert_eq!(index.acl_token_docids.get(&rtxn, "kevin").unwrap().unwrap().len(), 1);
+        drop(rtxn);
+        index.prepare_for_closing().wait();
+
+        // The index can now be opened.
+        let index = Index::new(options(), &path).unwrap();
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.format_version(&rtxn).unwrap(), FORMAT_VERSION);
+    }
+}
@@ -1,9 +1,32 @@
 use std::cmp;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{relative_from_absolute_position, Position};
 
 pub const MAX_DISTANCE: u32 = 8;
 
+/// The maximum proximity between the pairs of words that are stored.
+pub const MAX_PAIR_PROXIMITY: u32 = MAX_DISTANCE - 1;
+
+/// The precision of the proximity between the words of the documents, the proximity
+/// criterion ranks the documents with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProximityPrecision {
+    /// The distance between the words is computed, the pairs of close words are stored.
+    Exact,
+    /// The words are only considered close when they appear in the same attribute, the pairs
+    /// of words aren't stored and the databases are much smaller.
+    ByAttribute,
+}
+
+impl Default for ProximityPrecision {
+    fn default() -> Self {
+        ProximityPrecision::Exact
+    }
+}
+
 pub fn index_proximity(lhs: u32, rhs: u32) -> u32 {
     if lhs <= rhs {
         cmp::min(rhs - lhs, MAX_DISTANCE)
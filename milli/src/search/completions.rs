@@ -0,0 +1,162 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Streamer};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+
+use super::Filter;
+use crate::{Index, Result, RoaringBitmapLenCodec};
+
+/// Returns the words of the index starting with a prefix, the ones contained
+/// in the most documents first, to build the autocomplete of a search bar.
+pub struct Completions<'a> {
+    prefix: String,
+    limit: usize,
+    filter: Option<Filter<'a>>,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> Completions<'a> {
+    pub fn new(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        prefix: impl Into<String>,
+    ) -> Completions<'a> {
+        Completions { prefix: prefix.into(), limit: 10, filter: None, rtxn, index }
+    }
+
+    pub fn limit(&mut self, limit: usize) -> &mut Completions<'a> {
+        self.limit = limit;
+        self
+    }
+
+    /// Only counts the documents matching the filter, the words
+    /// that are not contained in any of them are not returned.
+    pub fn filter(&mut self, condition: Filter<'a>) -> &mut Completions<'a> {
+        self.filter = Some(condition);
+        self
+    }
+
+    pub fn execute(&self) -> Result<Vec<Completion>> {
+        // We normalize the prefix the same way the words of the documents are.
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let analyzed = analyzer.analyze(&self.prefix);
+        let prefix = match analyzed.tokens().find(|token| token.is_word()) {
+            Some(token) => token.text().to_string(),
+            None => return Ok(Vec::new()),
+        };
+
+        let candidates = match &self.filter {
+            Some(condition) => Some(condition.evaluate(self.rtxn, self.index)?),
+            None => None,
+        };
+
+        let words_fst = self.index.words_fst(self.rtxn)?;
+        let mut stream = words_fst.search(Str::new(&prefix).starts_with()).into_stream();
+
+        // We only keep the best completions in a min-heap.
+        let mut heap = BinaryHeap::new();
+        while let Some(word) = stream.next() {
+            let word = std::str::from_utf8(word)?;
+            let documents_count = match &candidates {
+                Some(candidates) => match self.index.word_docids.get(self.rtxn, word)? {
+                    Some(docids) => (docids & candidates).len(),
+                    None => 0,
+                },
+                None => self
+                    .index
+                    .word_docids
+                    .remap_data_type::<RoaringBitmapLenCodec>()
+                    .get(self.rtxn, word)?
+                    .unwrap_or(0),
+            };
+
+            if documents_count != 0 {
+                heap.push(Reverse((documents_count, Reverse(word.to_string()))));
+                if heap.len() > self.limit {
+                    heap.pop();
+                }
+            }
+        }
+
+        let completions = heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((documents_count, Reverse(word)))| Completion { word, documents_count })
+            .collect();
+
+        Ok(completions)
+    }
+}
+
+impl fmt::Debug for Completions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Completions { prefix, limit, filter, rtxn: _, index: _ } = self;
+        f.debug_struct("Completions")
+            .field("prefix", prefix)
+            .field("limit", limit)
+            .field("filter", filter)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub word: String,
+    /// The number of documents containing the word.
+    pub documents_count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    #[test]
+    fn completions() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("lang") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "hello world", "lang": "en" },
+            { "id": 1, "title": "hello help", "lang": "en" },
+            { "id": 2, "title": "helmet", "lang": "fr" },
+            { "id": 3, "title": "helmet hello", "lang": "fr" },
+            { "id": 4, "title": "nothing", "lang": "fr" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let completions = Completions::new(&rtxn, &index, "HEL").execute().unwrap();
+        let words: Vec<_> =
+            completions.iter().map(|c| (c.word.as_str(), c.documents_count)).collect();
+        assert_eq!(words, vec![("hello", 3), ("helmet", 2), ("help", 1)]);
+
+        let mut completions = Completions::new(&rtxn, &index, "hel");
+        completions.limit(1).filter(Filter::from_str("lang = fr").unwrap().unwrap());
+        let completions = completions.execute().unwrap();
+        let words: Vec<_> =
+            completions.iter().map(|c| (c.word.as_str(), c.documents_count)).collect();
+        assert_eq!(words, vec![("helmet", 2)]);
+    }
+}
@@ -1,9 +1,9 @@
 use std::mem::take;
 
 use itertools::Itertools;
-use log::debug;
 use ordered_float::OrderedFloat;
 use roaring::RoaringBitmap;
+use tracing::debug;
 
 use super::{Criterion, CriterionParameters, CriterionResult};
 use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
@@ -61,7 +61,9 @@ impl<'t> AscDesc<'t> {
             Some(field_id) => {
                 let number_faceted = index.number_faceted_documents_ids(rtxn, field_id)?;
                 let string_faceted = index.string_faceted_documents_ids(rtxn, field_id)?;
-                number_faceted | string_faceted
+                let true_faceted = index.facet_id_is_true_docids(rtxn, field_id)?;
+                let false_faceted = index.facet_id_is_false_docids(rtxn, field_id)?;
+                number_faceted | string_faceted | true_faceted | false_faceted
             }
             None => RoaringBitmap::default(),
         };
@@ -165,15 +167,26 @@ impl<'t> Criterion for AscDesc<'t> {
 
 /// Returns an iterator over groups of the given candidates in ascending or descending order.
 ///
+/// The documents with numbers come first, then the ones with booleans, `false` being lower than
+/// `true`, then the ones with dates, ordered by their timestamps, and then the ones with other
+/// strings.
+///
 /// It will either use an iterative or a recursive method on the whole facet database depending
 /// on the number of candidates to rank.
-fn facet_ordered<'t>(
+pub(crate) fn facet_ordered<'t>(
     index: &'t Index,
     rtxn: &'t heed::RoTxn,
     field_id: FieldId,
     is_ascending: bool,
     candidates: RoaringBitmap,
 ) -> Result<Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + 't>> {
+    let number_faceted = index.number_faceted_documents_ids(rtxn, field_id)?;
+    let (bool_groups, candidates) =
+        facet_bool_ordered(index, rtxn, field_id, is_ascending, candidates, &number_faceted)?;
+    let (date_groups, candidates) =
+        facet_date_ordered(index, rtxn, field_id, is_ascending, candidates, &number_faceted)?;
+    let bool_date_iter = bool_groups.into_iter().chain(date_groups).map(Ok);
+
     if candidates.len() <= CANDIDATES_THRESHOLD {
         let number_iter = iterative_facet_number_ordered_iter(
             index,
@@ -184,7 +197,8 @@ fn facet_ordered<'t>(
         )?;
         let string_iter =
             iterative_facet_string_ordered_iter(index, rtxn, field_id, is_ascending, candidates)?;
-        Ok(Box::new(number_iter.chain(string_iter).map(Ok)) as Box<dyn Iterator<Item = _>>)
+        let iter = number_iter.map(Ok).chain(bool_date_iter).chain(string_iter.map(Ok));
+        Ok(Box::new(iter) as Box<dyn Iterator<Item = _>>)
     } else {
         let facet_number_fn = if is_ascending {
             FacetNumberIter::new_reducing
@@ -202,8 +216,72 @@ fn facet_ordered<'t>(
         let string_iter = facet_string_fn(rtxn, index, field_id, candidates)?
             .map(|res| res.map(|(_, _, docids)| docids));
 
-        Ok(Box::new(number_iter.chain(string_iter)))
+        Ok(Box::new(number_iter.chain(bool_date_iter).chain(string_iter)))
+    }
+}
+
+/// Groups the candidates that have booleans, and no numbers, in ascending or descending order.
+///
+/// Returns the groups and the candidates left to rank by their dates and strings.
+fn facet_bool_ordered(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    field_id: FieldId,
+    is_ascending: bool,
+    mut candidates: RoaringBitmap,
+    number_faceted: &RoaringBitmap,
+) -> Result<(Vec<RoaringBitmap>, RoaringBitmap)> {
+    let mut bool_candidates = &candidates - number_faceted;
+    let values = if is_ascending { [false, true] } else { [true, false] };
+
+    let mut groups = Vec::new();
+    for value in values {
+        let group = index.facet_id_bool_docids(rtxn, field_id, value)? & &bool_candidates;
+        if !group.is_empty() {
+            bool_candidates -= &group;
+            candidates -= &group;
+            groups.push(group);
+        }
+    }
+
+    Ok((groups, candidates))
+}
+
+/// Groups the candidates that have dates, and no numbers, by their timestamps
+/// in ascending or descending order, the documents are ranked by their first date.
+///
+/// Returns the groups and the candidates left to rank by their strings.
+fn facet_date_ordered(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    field_id: FieldId,
+    is_ascending: bool,
+    mut candidates: RoaringBitmap,
+    number_faceted: &RoaringBitmap,
+) -> Result<(Vec<RoaringBitmap>, RoaringBitmap)> {
+    let mut date_candidates = &candidates - number_faceted;
+    let range = (field_id, i64::MIN)..=(field_id, i64::MAX);
+    let iter = if is_ascending {
+        Box::new(index.facet_id_date_docids.range(rtxn, &range)?) as Box<dyn Iterator<Item = _>>
+    } else {
+        Box::new(index.facet_id_date_docids.rev_range(rtxn, &range)?)
+    };
+
+    let mut groups = Vec::new();
+    for result in iter {
+        if date_candidates.is_empty() {
+            break;
+        }
+        let (_key, docids) = result?;
+        let group = &date_candidates & docids;
+        if !group.is_empty() {
+            date_candidates -= &group;
+            candidates -= &group;
+            groups.push(group);
+        }
     }
+
+    Ok((groups, candidates))
 }
 
 /// Fetch the whole list of candidates facet number values one by one and order them by it.
@@ -3,6 +3,7 @@ use std::collections::binary_heap::PeekMut;
 use std::collections::{btree_map, BTreeMap, BinaryHeap, HashMap};
 use std::iter::Peekable;
 use std::mem::take;
+use std::vec;
 
 use roaring::RoaringBitmap;
 
@@ -10,7 +11,7 @@ use super::{resolve_query_tree, Context, Criterion, CriterionParameters, Criteri
 use crate::search::criteria::Query;
 use crate::search::query_tree::{Operation, QueryKind};
 use crate::search::{build_dfa, word_derivations, WordDerivationsCache};
-use crate::Result;
+use crate::{FieldId, Result};
 
 /// To be able to divide integers by the number of words in the query
 /// we want to find a multiplier that allow us to divide by any number between 1 and 10.
@@ -30,6 +31,7 @@ pub struct Attribute<'t> {
     parent: Box<dyn Criterion + 't>,
     linear_buckets: Option<btree_map::IntoIter<u64, RoaringBitmap>>,
     set_buckets: Option<BinaryHeap<Branch<'t>>>,
+    field_buckets: Option<vec::IntoIter<RoaringBitmap>>,
 }
 
 impl<'t> Attribute<'t> {
@@ -41,8 +43,18 @@ impl<'t> Attribute<'t> {
             parent,
             linear_buckets: None,
             set_buckets: None,
+            field_buckets: None,
         }
     }
+
+    /// Returns the next non-empty bucket of candidates ranked by field
+    /// and forgets about the buckets computed for the previous one.
+    fn next_field_bucket(&mut self, excluded_candidates: &RoaringBitmap) -> Option<RoaringBitmap> {
+        self.linear_buckets = None;
+        self.set_buckets = None;
+        let field_buckets = self.field_buckets.as_mut()?;
+        field_buckets.map(|candidates| candidates - excluded_candidates).find(|c| !c.is_empty())
+    }
 }
 
 impl<'t> Criterion for Attribute<'t> {
@@ -55,13 +67,23 @@ impl<'t> Criterion for Attribute<'t> {
 
         loop {
             match self.state.take() {
-                Some((query_tree, _, allowed_candidates)) if allowed_candidates.is_empty() => {
-                    return Ok(Some(CriterionResult {
-                        query_tree: Some(query_tree),
-                        candidates: Some(RoaringBitmap::new()),
-                        filtered_candidates: None,
-                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
-                    }));
+                Some((query_tree, flattened_query_tree, allowed_candidates))
+                    if allowed_candidates.is_empty() =>
+                {
+                    match self.next_field_bucket(params.excluded_candidates) {
+                        Some(candidates) => {
+                            self.state = Some((query_tree, flattened_query_tree, candidates));
+                            continue;
+                        }
+                        None => {
+                            return Ok(Some(CriterionResult {
+                                query_tree: Some(query_tree),
+                                candidates: Some(RoaringBitmap::new()),
+                                filtered_candidates: None,
+                                bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                            }));
+                        }
+                    }
                 }
                 Some((query_tree, flattened_query_tree, mut allowed_candidates)) => {
                     let found_candidates = if allowed_candidates.len() < CANDIDATES_THRESHOLD {
@@ -79,14 +101,21 @@ impl<'t> Criterion for Attribute<'t> {
 
                         match linear_buckets.next() {
                             Some((_score, candidates)) => candidates,
-                            None => {
-                                return Ok(Some(CriterionResult {
-                                    query_tree: Some(query_tree),
-                                    candidates: Some(RoaringBitmap::new()),
-                                    filtered_candidates: None,
-                                    bucket_candidates: Some(take(&mut self.bucket_candidates)),
-                                }));
-                            }
+                            None => match self.next_field_bucket(params.excluded_candidates) {
+                                Some(candidates) => {
+                                    self.state =
+                                        Some((query_tree, flattened_query_tree, candidates));
+                                    continue;
+                                }
+                                None => {
+                                    return Ok(Some(CriterionResult {
+                                        query_tree: Some(query_tree),
+                                        candidates: Some(RoaringBitmap::new()),
+                                        filtered_candidates: None,
+                                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                                    }));
+                                }
+                            },
                         }
                     } else {
                         let mut set_buckets = match self.set_buckets.as_mut() {
@@ -104,14 +133,21 @@ impl<'t> Criterion for Attribute<'t> {
 
                         match set_compute_candidates(&mut set_buckets, &allowed_candidates)? {
                             Some((_score, candidates)) => candidates,
-                            None => {
-                                return Ok(Some(CriterionResult {
-                                    query_tree: Some(query_tree),
-                                    candidates: Some(RoaringBitmap::new()),
-                                    filtered_candidates: None,
-                                    bucket_candidates: Some(take(&mut self.bucket_candidates)),
-                                }));
-                            }
+                            None => match self.next_field_bucket(params.excluded_candidates) {
+                                Some(candidates) => {
+                                    self.state =
+                                        Some((query_tree, flattened_query_tree, candidates));
+                                    continue;
+                                }
+                                None => {
+                                    return Ok(Some(CriterionResult {
+                                        query_tree: Some(query_tree),
+                                        candidates: Some(RoaringBitmap::new()),
+                                        filtered_candidates: None,
+                                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                                    }));
+                                }
+                            },
                         }
                     };
 
@@ -153,8 +189,25 @@ impl<'t> Criterion for Attribute<'t> {
                             None => self.bucket_candidates |= &candidates,
                         }
 
+                        // When there is too many candidates we first rank them by the
+                        // field in which the words appear, using the word fid docids.
+                        let mut field_buckets = if candidates.len() >= CANDIDATES_THRESHOLD {
+                            initialize_field_buckets(
+                                self.ctx,
+                                &flattened_query_tree,
+                                &candidates,
+                                params.wdcache,
+                            )?
+                        } else {
+                            vec![candidates]
+                        }
+                        .into_iter();
+
+                        let candidates = field_buckets.next().unwrap_or_default();
                         self.state = Some((query_tree, flattened_query_tree, candidates));
+                        self.field_buckets = Some(field_buckets);
                         self.linear_buckets = None;
+                        self.set_buckets = None;
                     }
                     Some(CriterionResult {
                         query_tree: None,
@@ -450,6 +503,78 @@ fn set_compute_candidates(
     Ok(final_candidates)
 }
 
+/// Splits the candidates into buckets by the first searchable field, in the searchable fields
+/// order, in which all the words of at least one branch appear. The candidates for which the
+/// words are spread over many fields are returned in the last bucket.
+fn initialize_field_buckets(
+    ctx: &dyn Context,
+    branches: &FlattenedQueryTree,
+    allowed_candidates: &RoaringBitmap,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<Vec<RoaringBitmap>> {
+    let mut remaining_candidates = allowed_candidates.clone();
+    let mut buckets = Vec::new();
+
+    for fid in ctx.searchable_fields_ids()? {
+        if remaining_candidates.is_empty() {
+            break;
+        }
+
+        let mut field_candidates = RoaringBitmap::new();
+        for branch in branches {
+            let mut branch_candidates = remaining_candidates.clone();
+            for derivates in branch {
+                let mut derivates_candidates = RoaringBitmap::new();
+                for query in derivates {
+                    derivates_candidates |= query_fid_docids(ctx, query, fid, wdcache)?;
+                }
+                branch_candidates &= derivates_candidates;
+                if branch_candidates.is_empty() {
+                    break;
+                }
+            }
+            field_candidates |= branch_candidates;
+        }
+
+        remaining_candidates -= &field_candidates;
+        buckets.push(field_candidates);
+    }
+
+    buckets.push(remaining_candidates);
+    buckets.retain(|candidates| !candidates.is_empty());
+
+    Ok(buckets)
+}
+
+/// Returns the documents ids in which the query appears in the given field.
+fn query_fid_docids(
+    ctx: &dyn Context,
+    query: &Query,
+    fid: FieldId,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<RoaringBitmap> {
+    match &query.kind {
+        QueryKind::Exact { word, .. } if !query.prefix => {
+            Ok(ctx.word_fid_docids(word, fid)?.unwrap_or_default())
+        }
+        QueryKind::Exact { word, .. } => {
+            let mut docids = RoaringBitmap::new();
+            for (word, _) in word_derivations(word, true, 0, ctx.words_fst(), wdcache)? {
+                docids |= ctx.word_fid_docids(word, fid)?.unwrap_or_default();
+            }
+            Ok(docids)
+        }
+        QueryKind::Tolerant { typo, word } => {
+            let mut docids = RoaringBitmap::new();
+            for (word, _) in word_derivations(word, query.prefix, *typo, ctx.words_fst(), wdcache)?
+            {
+                docids |= ctx.word_fid_docids(word, fid)?.unwrap_or_default();
+            }
+            Ok(docids)
+        }
+    }
+}
+
 fn initialize_linear_buckets(
     ctx: &dyn Context,
     branches: &FlattenedQueryTree,
@@ -594,9 +719,13 @@ fn flatten_query_tree(query_tree: &Operation) -> FlattenedQueryTree {
 #[cfg(test)]
 mod tests {
     use big_s::S;
+    use heed::EnvOpenOptions;
+    use serde_json::json;
 
     use super::*;
     use crate::search::criteria::QueryKind;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::{Index, Search};
 
     #[test]
     fn simple_flatten_query_tree() {
@@ -653,4 +782,45 @@ mod tests {
         let result = flatten_query_tree(&query_tree);
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn field_buckets_above_candidates_threshold() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("description")]);
+        builder.set_criteria(vec![S("attribute")]);
+        builder.execute(|_| ()).unwrap();
+
+        // The words are in the description, spread over both fields or in the title.
+        let count = CANDIDATES_THRESHOLD as usize;
+        let documents: Vec<_> = (0..count * 3)
+            .map(|i| match i / count {
+                0 => json!({ "id": i, "title": "nothing", "description": "red fish" }),
+                1 => json!({ "id": i, "title": "red", "description": "fish" }),
+                _ => json!({ "id": i, "title": "red fish", "description": "nothing" }),
+            })
+            .collect();
+        let content = documents!(documents);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("red fish").limit(count * 3);
+        let result = search.execute().unwrap();
+
+        let count = count as u32;
+        let expected: Vec<_> =
+            (count * 2..count * 3).chain(0..count).chain(count..count * 2).collect();
+        assert_eq!(result.documents_ids, expected);
+    }
 }
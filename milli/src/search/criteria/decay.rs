@@ -0,0 +1,270 @@
+use std::mem::take;
+use std::ops::Bound::Included;
+
+use heed::types::DecodeIgnore;
+use roaring::RoaringBitmap;
+use tracing::debug;
+
+use super::{Criterion, CriterionParameters, CriterionResult};
+use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
+use crate::search::facet::Filter;
+use crate::search::query_tree::Operation;
+use crate::{FieldId, Index, Result};
+
+/// The number of buckets the documents near the origin are split into, the documents
+/// are ranked by tenths of their score, the ones scoring less than 0.1 come last.
+const SCORE_STEPS: usize = 10;
+
+/// The shape of the score of a [`Decay`] as the distance to the origin grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecayCurve {
+    Gauss,
+    Linear,
+}
+
+/// Boosts the documents whose numeric value of a field is close to an origin, e.g. the
+/// most recent documents when the field is a timestamp and the origin is now.
+///
+/// The documents score 1 up to `offset` from the origin, then their score decreases with
+/// the distance to reach `decay` at `offset + scale`. The scores are not computed for each
+/// document, the documents are ranked by buckets of scores read from the facet levels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decay {
+    pub field: String,
+    pub origin: f64,
+    pub scale: f64,
+    pub offset: f64,
+    pub decay: f64,
+    pub curve: DecayCurve,
+}
+
+impl Decay {
+    /// A decay without offset that halves the score at `scale` from the origin.
+    pub fn new(field: impl Into<String>, origin: f64, scale: f64, curve: DecayCurve) -> Decay {
+        Decay { field: field.into(), origin, scale, offset: 0.0, decay: 0.5, curve }
+    }
+
+    /// The distance to the origin at which the documents have the given score.
+    fn distance(&self, score: f64) -> f64 {
+        let scale = self.scale.abs();
+        let decay = self.decay.max(f64::EPSILON).min(1.0 - f64::EPSILON);
+        let distance = match self.curve {
+            DecayCurve::Gauss => scale * (score.ln() / decay.ln()).sqrt(),
+            DecayCurve::Linear => (1.0 - score) * scale / (1.0 - decay),
+        };
+        self.offset.abs() + distance
+    }
+}
+
+pub struct FacetDecay<'t> {
+    index: &'t Index,
+    rtxn: &'t heed::RoTxn<'t>,
+    field_name: String,
+    query_tree: Option<Operation>,
+    buckets: Vec<RoaringBitmap>,
+    candidates: std::vec::IntoIter<RoaringBitmap>,
+    allowed_candidates: RoaringBitmap,
+    bucket_candidates: RoaringBitmap,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> FacetDecay<'t> {
+    pub fn new(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        decay: &Decay,
+    ) -> Result<Self> {
+        let fields_ids_map = index.fields_ids_map(rtxn)?;
+        let buckets = match fields_ids_map.id(&decay.field) {
+            Some(field_id) => decay_buckets(index, rtxn, field_id, decay)?,
+            None => Vec::new(),
+        };
+
+        Ok(FacetDecay {
+            index,
+            rtxn,
+            field_name: decay.field.clone(),
+            query_tree: None,
+            buckets,
+            candidates: Vec::new().into_iter(),
+            allowed_candidates: RoaringBitmap::new(),
+            bucket_candidates: RoaringBitmap::new(),
+            parent,
+        })
+    }
+}
+
+impl<'t> Criterion for FacetDecay<'t> {
+    #[logging_timer::time("FacetDecay::{}")]
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        // remove excluded candidates when next is called, instead of doing it in the loop.
+        self.allowed_candidates -= params.excluded_candidates;
+
+        loop {
+            debug!("Facet Decay({}) iteration", self.field_name);
+
+            match self.candidates.next() {
+                None if !self.allowed_candidates.is_empty() => {
+                    return Ok(Some(CriterionResult {
+                        query_tree: self.query_tree.clone(),
+                        candidates: Some(take(&mut self.allowed_candidates)),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                    }));
+                }
+                None => match self.parent.next(params)? {
+                    Some(CriterionResult {
+                        query_tree,
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        self.query_tree = query_tree;
+                        let mut candidates = match (&self.query_tree, candidates) {
+                            (_, Some(candidates)) => candidates,
+                            (Some(qt), None) => {
+                                let context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
+                                resolve_query_tree(&context, qt, params.wdcache)?
+                            }
+                            (None, None) => self.index.documents_ids(self.rtxn)?,
+                        };
+
+                        if let Some(filtered_candidates) = filtered_candidates {
+                            candidates &= filtered_candidates;
+                        }
+
+                        match bucket_candidates {
+                            Some(bucket_candidates) => self.bucket_candidates |= bucket_candidates,
+                            None => self.bucket_candidates |= &candidates,
+                        }
+
+                        if candidates.is_empty() {
+                            continue;
+                        }
+
+                        self.allowed_candidates = &candidates - params.excluded_candidates;
+                        self.candidates = self
+                            .buckets
+                            .iter()
+                            .map(|bucket| bucket & &candidates)
+                            .filter(|bucket| !bucket.is_empty())
+                            .collect::<Vec<_>>()
+                            .into_iter();
+                    }
+                    None => return Ok(None),
+                },
+                Some(mut candidates) => {
+                    candidates -= params.excluded_candidates;
+                    self.allowed_candidates -= &candidates;
+                    return Ok(Some(CriterionResult {
+                        query_tree: self.query_tree.clone(),
+                        candidates: Some(candidates),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the documents of each bucket of scores, from the highest to the lowest score,
+/// the documents of a bucket are the ones in a range around the origin that are not part
+/// of the previous buckets.
+fn decay_buckets(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    field_id: FieldId,
+    decay: &Decay,
+) -> Result<Vec<RoaringBitmap>> {
+    let db = index.facet_id_f64_docids;
+    let biggest_level = db
+        .remap_data_type::<DecodeIgnore>()
+        .get_lower_than_or_equal_to(rtxn, &(field_id, u8::MAX, f64::MAX, f64::MAX))?
+        .and_then(|((id, level, _, _), _)| if id == field_id { Some(level) } else { None });
+
+    let level = match biggest_level {
+        Some(level) => level,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut buckets = Vec::with_capacity(SCORE_STEPS);
+    let mut previous = RoaringBitmap::new();
+    for step in 0..SCORE_STEPS {
+        let score = 1.0 - step as f64 / SCORE_STEPS as f64;
+        let distance = decay.distance(score);
+        let left = Included(decay.origin - distance);
+        let right = Included(decay.origin + distance);
+        let mut docids = RoaringBitmap::new();
+        Filter::explore_facet_number_levels(rtxn, db, field_id, level, left, right, &mut docids)?;
+        docids -= &previous;
+        previous |= &docids;
+        buckets.push(docids);
+    }
+
+    Ok(buckets)
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::Search;
+
+    #[test]
+    fn decay_around_origin() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("date") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "date": 100 },
+            { "id": 1, "date": 85 },
+            { "id": 2, "date": 95 },
+            { "id": 3 },
+            { "id": 4, "date": 0 },
+            { "id": 5, "date": 109 },
+            { "id": 6, "date": "yesterday" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        for &curve in &[DecayCurve::Gauss, DecayCurve::Linear] {
+            let mut search = Search::new(&rtxn, &index);
+            search.decay(Decay::new("date", 100.0, 10.0, curve));
+            let result = search.execute().unwrap();
+            let (first, rest) = result.documents_ids.split_at(4);
+            assert_eq!(first, &[0, 2, 5, 1]);
+            let mut rest = rest.to_vec();
+            rest.sort_unstable();
+            assert_eq!(rest, vec![3, 4, 6]);
+        }
+
+        // The documents within the offset are in the same bucket.
+        let mut decay = Decay::new("date", 100.0, 10.0, DecayCurve::Linear);
+        decay.offset = 10.0;
+        let mut search = Search::new(&rtxn, &index);
+        search.decay(decay);
+        let result = search.execute().unwrap();
+        let mut first = result.documents_ids[..3].to_vec();
+        first.sort_unstable();
+        assert_eq!(first, vec![0, 2, 5]);
+        assert_eq!(result.documents_ids[3], 1);
+    }
+}
@@ -1,10 +1,11 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::mem::take;
 use std::ops::BitOr;
 
 use itertools::Itertools;
-use log::debug;
 use roaring::RoaringBitmap;
+use tracing::debug;
 
 use crate::search::criteria::{
     resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult,
@@ -19,17 +20,25 @@ pub struct Exactness<'t> {
     bucket_candidates: RoaringBitmap,
     parent: Box<dyn Criterion + 't>,
     query: Vec<ExactQueryPart>,
+    /// The documents containing each part of the query as written in the query.
+    exact_parts_docids: Vec<RoaringBitmap>,
 }
 
 impl<'t> Exactness<'t> {
+    /// The `original_words` are the words of the query, only lowercased,
+    /// associated with their normalized form.
     pub fn new(
         ctx: &'t dyn Context<'t>,
         parent: Box<dyn Criterion + 't>,
         primitive_query: &[PrimitiveQueryPart],
+        original_words: &HashMap<String, String>,
     ) -> heed::Result<Self> {
         let mut query: Vec<_> = Vec::with_capacity(primitive_query.len());
+        let mut exact_parts_docids = Vec::with_capacity(primitive_query.len());
         for part in primitive_query {
-            query.push(ExactQueryPart::from_primitive_query_part(ctx, part)?);
+            let part = ExactQueryPart::from_primitive_query_part(ctx, part)?;
+            exact_parts_docids.push(part.exact_docids(ctx, original_words)?);
+            query.push(part);
         }
 
         Ok(Exactness {
@@ -39,6 +48,7 @@ impl<'t> Exactness<'t> {
             bucket_candidates: RoaringBitmap::new(),
             parent,
             query,
+            exact_parts_docids,
         })
     }
 }
@@ -61,7 +71,12 @@ impl<'t> Criterion for Exactness<'t> {
                     self.query_tree = None;
                 }
                 Some(state) => {
-                    let (candidates, state) = resolve_state(self.ctx, take(state), &self.query)?;
+                    let (candidates, state) = resolve_state(
+                        self.ctx,
+                        take(state),
+                        &self.query,
+                        &self.exact_parts_docids,
+                    )?;
                     self.state = state;
 
                     return Ok(Some(CriterionResult {
@@ -120,11 +135,14 @@ impl<'t> Criterion for Exactness<'t> {
 
 #[derive(Debug)]
 enum State {
-    /// Extract the documents that have an attribute that contains exactly the query.
+    /// Extract the documents that have an attribute that contains exactly the query,
+    /// with the words as written in the query.
     ExactAttribute(RoaringBitmap),
-    /// Extract the documents that have an attribute that starts with exactly the query.
+    /// Extract the documents that have an attribute that starts with exactly the query,
+    /// with the words as written in the query.
     AttributeStartsWith(RoaringBitmap),
-    /// Rank the remaining documents by the number of exact words contained.
+    /// Rank the remaining documents by the number of exact words contained,
+    /// the words written in the documents as in the query.
     ExactWords(RoaringBitmap),
     Remainings(Vec<RoaringBitmap>),
 }
@@ -169,6 +187,7 @@ fn resolve_state(
     ctx: &dyn Context,
     state: State,
     query: &[ExactQueryPart],
+    exact_parts_docids: &[RoaringBitmap],
 ) -> Result<(RoaringBitmap, Option<State>)> {
     use State::*;
     match state {
@@ -187,6 +206,8 @@ fn resolve_state(
                     }
                 }
 
+                // only keep the documents containing all the words as written in the query
+                candidates &= intersection_of(exact_parts_docids.iter().collect());
                 // only keep allowed candidates
                 candidates &= &allowed_candidates;
                 // remove current candidates from allowed candidates
@@ -203,6 +224,8 @@ fn resolve_state(
                 candidates |= intersection_of(attribute_candidates_array.iter().collect());
             }
 
+            // only keep the documents containing all the words as written in the query
+            candidates &= intersection_of(exact_parts_docids.iter().collect());
             // only keep allowed candidates
             candidates &= &allowed_candidates;
             // remove current candidates from allowed candidates
@@ -211,38 +234,7 @@ fn resolve_state(
         }
         ExactWords(mut allowed_candidates) => {
             let number_of_part = query.len();
-            let mut parts_candidates_array = Vec::with_capacity(number_of_part);
-
-            for part in query {
-                let mut candidates = RoaringBitmap::new();
-                use ExactQueryPart::*;
-                match part {
-                    Synonyms(synonyms) => {
-                        for synonym in synonyms {
-                            if let Some(synonym_candidates) = ctx.word_docids(synonym)? {
-                                candidates |= synonym_candidates;
-                            }
-                        }
-                    }
-                    // compute intersection on pair of words with a proximity of 0.
-                    Phrase(phrase) => {
-                        let mut bitmaps = Vec::with_capacity(phrase.len().saturating_sub(1));
-                        for words in phrase.windows(2) {
-                            if let [left, right] = words {
-                                match ctx.word_pair_proximity_docids(left, right, 0)? {
-                                    Some(docids) => bitmaps.push(docids),
-                                    None => {
-                                        bitmaps.clear();
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        candidates |= intersection_of(bitmaps.iter().collect());
-                    }
-                }
-                parts_candidates_array.push(candidates);
-            }
+            let parts_candidates_array = exact_parts_docids;
 
             let mut candidates_array = Vec::new();
 
@@ -369,4 +361,45 @@ impl ExactQueryPart {
 
         Ok(part)
     }
+
+    /// Returns the documents containing this part as written in the query, the words
+    /// are neither matched as prefixes nor with typos.
+    fn exact_docids(
+        &self,
+        ctx: &dyn Context,
+        original_words: &HashMap<String, String>,
+    ) -> heed::Result<RoaringBitmap> {
+        let original = |word: &String| original_words.get(word).unwrap_or(word).as_str();
+        match self {
+            ExactQueryPart::Synonyms(synonyms) => {
+                let mut candidates = RoaringBitmap::new();
+                for synonym in synonyms {
+                    if let Some(synonym_candidates) = ctx.exact_word_docids(original(synonym))? {
+                        candidates |= synonym_candidates;
+                    }
+                }
+                Ok(candidates)
+            }
+            // compute intersection on the exact words and on the pairs of words
+            // with a proximity of 1, the words following each other.
+            ExactQueryPart::Phrase(phrase) => {
+                let mut bitmaps = Vec::with_capacity(phrase.len() * 2);
+                for word in phrase {
+                    match ctx.exact_word_docids(original(word))? {
+                        Some(docids) => bitmaps.push(docids),
+                        None => return Ok(RoaringBitmap::new()),
+                    }
+                }
+                for words in phrase.windows(2) {
+                    if let [left, right] = words {
+                        match ctx.word_pair_proximity_docids(left, right, 1)? {
+                            Some(docids) => bitmaps.push(docids),
+                            None => return Ok(RoaringBitmap::new()),
+                        }
+                    }
+                }
+                Ok(intersection_of(bitmaps.iter().collect()))
+            }
+        }
+    }
 }
@@ -1,5 +1,5 @@
-use log::debug;
 use roaring::RoaringBitmap;
+use tracing::debug;
 
 use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
 use crate::search::query_tree::Operation;
@@ -126,7 +126,7 @@ impl Criterion for Geo<'_> {
     }
 }
 
-fn geo_point(
+pub(crate) fn geo_point(
     rtree: &RTree<GeoPoint>,
     mut candidates: RoaringBitmap,
     point: [f64; 2],
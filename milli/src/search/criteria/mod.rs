@@ -3,21 +3,31 @@ use std::collections::HashMap;
 
 use roaring::RoaringBitmap;
 
+pub(crate) use self::asc_desc::facet_ordered;
 use self::asc_desc::AscDesc;
 use self::attribute::Attribute;
+use self::decay::FacetDecay;
+pub use self::decay::{Decay, DecayCurve};
 use self::exactness::Exactness;
 use self::initial::Initial;
 use self::proximity::Proximity;
 use self::r#final::Final;
 use self::typo::Typo;
+pub use self::typo::TypoCosts;
 use self::words::Words;
 use super::query_tree::{Operation, PrimitiveQueryPart, Query, QueryKind};
+use crate::proximity::MAX_PAIR_PROXIMITY;
+pub(crate) use crate::search::criteria::geo::geo_point;
 use crate::search::criteria::geo::Geo;
 use crate::search::{word_derivations, WordDerivationsCache};
-use crate::{AscDesc as AscDescName, DocumentId, FieldId, Index, Member, Result};
+use crate::update::PrefixPairProximityCaps;
+use crate::{
+    AscDesc as AscDescName, DocumentId, FieldId, Index, Member, ProximityPrecision, Result,
+};
 
 mod asc_desc;
 mod attribute;
+mod decay;
 mod exactness;
 pub mod r#final;
 mod geo;
@@ -68,6 +78,7 @@ impl Default for Candidates {
 pub trait Context<'c> {
     fn documents_ids(&self) -> heed::Result<RoaringBitmap>;
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
+    fn exact_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn word_pair_proximity_docids(
         &self,
@@ -83,6 +94,7 @@ pub trait Context<'c> {
     ) -> heed::Result<Option<RoaringBitmap>>;
     fn words_fst<'t>(&self) -> &'t fst::Set<Cow<[u8]>>;
     fn in_prefix_cache(&self, word: &str) -> bool;
+    fn in_prefix_pair_cache(&self, prefix: &str, proximity: u8) -> bool;
     fn docid_words_positions(
         &self,
         docid: DocumentId,
@@ -100,6 +112,7 @@ pub trait Context<'c> {
         word_count: u8,
     ) -> heed::Result<Option<RoaringBitmap>>;
     fn word_position_docids(&self, word: &str, pos: u32) -> heed::Result<Option<RoaringBitmap>>;
+    fn word_fid_docids(&self, word: &str, fid: FieldId) -> heed::Result<Option<RoaringBitmap>>;
 }
 
 pub struct CriteriaBuilder<'t> {
@@ -107,6 +120,10 @@ pub struct CriteriaBuilder<'t> {
     index: &'t Index,
     words_fst: fst::Set<Cow<'t, [u8]>>,
     words_prefixes_fst: fst::Set<Cow<'t, [u8]>>,
+    prefix_pair_proximity_caps: PrefixPairProximityCaps,
+    proximity_precision: ProximityPrecision,
+    proximity_window: u8,
+    searchable_fields_ids: Vec<FieldId>,
 }
 
 impl<'c> Context<'c> for CriteriaBuilder<'c> {
@@ -118,6 +135,10 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         self.index.word_docids.get(self.rtxn, &word)
     }
 
+    fn exact_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
+        self.index.exact_word_docids.get(self.rtxn, &word)
+    }
+
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
         self.index.word_prefix_docids.get(self.rtxn, &word)
     }
@@ -128,8 +149,17 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         right: &str,
         proximity: u8,
     ) -> heed::Result<Option<RoaringBitmap>> {
-        let key = (left, right, proximity);
-        self.index.word_pair_proximity_docids.get(self.rtxn, &key)
+        // The proximities of the pairs that are not stored are computed by attribute.
+        let stored = match self.proximity_precision {
+            ProximityPrecision::Exact => proximity <= self.proximity_window,
+            ProximityPrecision::ByAttribute => proximity == 0,
+        };
+        if stored {
+            let key = (left, right, proximity);
+            self.index.word_pair_proximity_docids.get(self.rtxn, &key)
+        } else {
+            self.same_attribute_docids(left, right)
+        }
     }
 
     fn word_prefix_pair_proximity_docids(
@@ -150,6 +180,10 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         self.words_prefixes_fst.contains(word)
     }
 
+    fn in_prefix_pair_cache(&self, prefix: &str, proximity: u8) -> bool {
+        self.prefix_pair_proximity_caps.contains(prefix, proximity) && self.in_prefix_cache(prefix)
+    }
+
     fn docid_words_positions(
         &self,
         docid: DocumentId,
@@ -207,21 +241,62 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         let key = (word, pos);
         self.index.word_position_docids.get(self.rtxn, &key)
     }
+
+    fn word_fid_docids(&self, word: &str, fid: FieldId) -> heed::Result<Option<RoaringBitmap>> {
+        let key = (word, fid);
+        self.index.word_fid_docids.get(self.rtxn, &key)
+    }
 }
 
 impl<'t> CriteriaBuilder<'t> {
     pub fn new(rtxn: &'t heed::RoTxn<'t>, index: &'t Index) -> Result<Self> {
         let words_fst = index.words_fst(rtxn)?;
         let words_prefixes_fst = index.words_prefixes_fst(rtxn)?;
-        Ok(Self { rtxn, index, words_fst, words_prefixes_fst })
+        let prefix_pair_proximity_caps = index.prefix_pair_proximity_caps(rtxn)?;
+        let proximity_precision = index.proximity_precision(rtxn)?;
+        let proximity_window = index.proximity_window(rtxn)?.unwrap_or(MAX_PAIR_PROXIMITY);
+        let searchable_fields_ids = match index.searchable_fields_ids(rtxn)? {
+            Some(searchable_fields_ids) => searchable_fields_ids,
+            None => index.fields_ids_map(rtxn)?.ids().collect(),
+        };
+        Ok(Self {
+            rtxn,
+            index,
+            words_fst,
+            words_prefixes_fst,
+            prefix_pair_proximity_caps,
+            proximity_precision,
+            proximity_window: proximity_window as u8,
+            searchable_fields_ids,
+        })
+    }
+
+    /// Returns the documents where both words appear in the same attribute, it replaces
+    /// the proximities of the pairs of words that are not stored.
+    fn same_attribute_docids(
+        &self,
+        left: &str,
+        right: &str,
+    ) -> heed::Result<Option<RoaringBitmap>> {
+        let mut docids = RoaringBitmap::new();
+        for &fid in &self.searchable_fields_ids {
+            if let Some(left_docids) = self.word_fid_docids(left, fid)? {
+                if let Some(right_docids) = self.word_fid_docids(right, fid)? {
+                    docids |= left_docids & right_docids;
+                }
+            }
+        }
+        Ok(if docids.is_empty() { None } else { Some(docids) })
     }
 
     pub fn build(
         &'t self,
         query_tree: Option<Operation>,
         primitive_query: Option<Vec<PrimitiveQueryPart>>,
+        original_words: HashMap<String, String>,
         filtered_candidates: Option<RoaringBitmap>,
         sort_criteria: Option<Vec<AscDescName>>,
+        decay: Option<Decay>,
     ) -> Result<Final<'t>> {
         use crate::criterion::Criterion as Name;
 
@@ -232,9 +307,12 @@ impl<'t> CriteriaBuilder<'t> {
         for name in self.index.criteria(&self.rtxn)? {
             criterion = match name {
                 Name::Words => Box::new(Words::new(self, criterion)),
-                Name::Typo => Box::new(Typo::new(self, criterion)),
-                Name::Sort => match sort_criteria {
-                    Some(ref sort_criteria) => {
+                Name::Typo => {
+                    let costs = self.index.typo_costs(self.rtxn)?;
+                    Box::new(Typo::with_costs(self, criterion, costs))
+                }
+                Name::Sort => {
+                    if let Some(ref sort_criteria) = sort_criteria {
                         for asc_desc in sort_criteria {
                             criterion = match asc_desc {
                                 AscDescName::Asc(Member::Field(field)) => Box::new(AscDesc::asc(
@@ -263,13 +341,18 @@ impl<'t> CriteriaBuilder<'t> {
                                 )?),
                             };
                         }
-                        criterion
                     }
-                    None => criterion,
-                },
+                    if let Some(ref decay) = decay {
+                        criterion =
+                            Box::new(FacetDecay::new(&self.index, &self.rtxn, criterion, decay)?);
+                    }
+                    criterion
+                }
                 Name::Proximity => Box::new(Proximity::new(self, criterion)),
                 Name::Attribute => Box::new(Attribute::new(self, criterion)),
-                Name::Exactness => Box::new(Exactness::new(self, criterion, &primitive_query)?),
+                Name::Exactness => {
+                    Box::new(Exactness::new(self, criterion, &primitive_query, &original_words)?)
+                }
                 Name::Asc(field) => {
                     Box::new(AscDesc::asc(&self.index, &self.rtxn, criterion, field)?)
                 }
@@ -437,11 +520,13 @@ fn query_pair_proximity_docids(
     match (&left.kind, &right.kind) {
         (QueryKind::Exact { word: left, .. }, QueryKind::Exact { word: right, .. }) => {
             if prefix {
-                match ctx.word_prefix_pair_proximity_docids(
-                    left.as_str(),
-                    right.as_str(),
-                    proximity,
-                )? {
+                // The pairs out of the bounds of the prefix database are computed on the fly.
+                let docids = if ctx.in_prefix_pair_cache(right, proximity) {
+                    ctx.word_prefix_pair_proximity_docids(left.as_str(), right.as_str(), proximity)?
+                } else {
+                    None
+                };
+                match docids {
                     Some(docids) => Ok(docids),
                     None => {
                         let r_words = word_derivations(&right, true, 0, ctx.words_fst(), wdcache)?;
@@ -459,12 +544,18 @@ fn query_pair_proximity_docids(
                 word_derivations(&left, false, *typo, ctx.words_fst(), wdcache)?.to_owned();
             if prefix {
                 let mut docids = RoaringBitmap::new();
+                let in_prefix_pair_cache = ctx.in_prefix_pair_cache(right, proximity);
                 for (left, _) in l_words {
-                    let current_docids = match ctx.word_prefix_pair_proximity_docids(
-                        left.as_str(),
-                        right.as_str(),
-                        proximity,
-                    )? {
+                    let docids = if in_prefix_pair_cache {
+                        ctx.word_prefix_pair_proximity_docids(
+                            left.as_str(),
+                            right.as_str(),
+                            proximity,
+                        )?
+                    } else {
+                        None
+                    };
+                    let current_docids = match docids {
                         Some(docids) => Ok(docids),
                         None => {
                             let r_words =
@@ -526,6 +617,10 @@ pub mod test {
             Ok(self.word_docids.get(&word.to_string()).cloned())
         }
 
+        fn exact_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
+            Ok(self.word_docids.get(&word.to_string()).cloned())
+        }
+
         fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
             Ok(self.word_prefix_docids.get(&word.to_string()).cloned())
         }
@@ -558,6 +653,10 @@ pub mod test {
             self.word_prefix_docids.contains_key(&word.to_string())
         }
 
+        fn in_prefix_pair_cache(&self, _prefix: &str, _proximity: u8) -> bool {
+            true
+        }
+
         fn docid_words_positions(
             &self,
             docid: DocumentId,
@@ -608,6 +707,14 @@ pub mod test {
         ) -> heed::Result<Option<RoaringBitmap>> {
             todo!()
         }
+
+        fn word_fid_docids(
+            &self,
+            _word: &str,
+            _fid: FieldId,
+        ) -> heed::Result<Option<RoaringBitmap>> {
+            todo!()
+        }
     }
 
     impl<'a> Default for TestContext<'a> {
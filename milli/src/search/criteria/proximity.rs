@@ -2,8 +2,8 @@ use std::collections::btree_map::{self, BTreeMap};
 use std::collections::hash_map::HashMap;
 use std::mem::take;
 
-use log::debug;
 use roaring::RoaringBitmap;
+use tracing::debug;
 
 use super::{
     query_docids, query_pair_proximity_docids, resolve_query_tree, Context, Criterion,
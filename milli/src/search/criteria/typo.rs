@@ -1,9 +1,10 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::mem::take;
 
-use log::debug;
 use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
 
 use super::{
     query_docids, resolve_query_tree, Candidates, Context, Criterion, CriterionParameters,
@@ -16,8 +17,35 @@ use crate::Result;
 /// Maximum number of typo for a word of any length.
 const MAX_TYPOS_PER_WORD: u8 = 2;
 
+/// The way the typo criterion counts the typos of the query words, the documents are
+/// ranked by the sum of the costs of the typos of the words they match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TypoCosts {
+    /// The maximum number of buckets the documents are ranked into, the documents
+    /// whose typos cost more than the last bucket are all ranked in it.
+    #[serde(default)]
+    pub max_buckets: Option<u8>,
+    /// The number of typos that cost nothing in the words at least as long as the key,
+    /// e.g. `{ 12: 1 }` ranks the words of 12 characters or more with one typo as exact.
+    #[serde(default)]
+    pub free_typos: BTreeMap<usize, u8>,
+}
+
+impl TypoCosts {
+    /// Returns the cost of the typos of a query word of this number of characters.
+    pub fn cost(&self, word_len: usize, typos: u8) -> u8 {
+        typos.saturating_sub(self.free_typos(word_len))
+    }
+
+    fn free_typos(&self, word_len: usize) -> u8 {
+        self.free_typos.range(..=word_len).next_back().map_or(0, |(_, free)| *free)
+    }
+}
+
 pub struct Typo<'t> {
     ctx: &'t dyn Context<'t>,
+    costs: TypoCosts,
     /// (max_typos, query_tree, candidates)
     state: Option<(u8, Operation, Candidates)>,
     typos: u8,
@@ -28,8 +56,17 @@ pub struct Typo<'t> {
 
 impl<'t> Typo<'t> {
     pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+        Typo::with_costs(ctx, parent, TypoCosts::default())
+    }
+
+    pub fn with_costs(
+        ctx: &'t dyn Context<'t>,
+        parent: Box<dyn Criterion + 't>,
+        costs: TypoCosts,
+    ) -> Self {
         Typo {
             ctx,
+            costs,
             state: None,
             typos: 0,
             bucket_candidates: None,
@@ -65,22 +102,29 @@ impl<'t> Criterion for Typo<'t> {
                 Some((_, _, Allowed(allowed_candidates))) if allowed_candidates.is_empty() => {
                     self.state = None; // reset state
                 }
-                Some((_, query_tree, candidates_authorization)) => {
+                Some((max_typos, query_tree, candidates_authorization)) => {
                     let fst = self.ctx.words_fst();
+                    // The documents with more typos than the buckets allow are all in the last one.
+                    let last_bucket = match self.costs.max_buckets {
+                        Some(buckets) => self.typos + 1 >= buckets,
+                        None => false,
+                    };
                     let new_query_tree = match self.typos {
-                        typos if typos < MAX_TYPOS_PER_WORD => alterate_query_tree(
+                        typos if typos < MAX_TYPOS_PER_WORD && !last_bucket => alterate_query_tree(
                             &fst,
+                            &self.costs,
                             query_tree.clone(),
                             self.typos,
                             params.wdcache,
                         )?,
-                        MAX_TYPOS_PER_WORD => {
+                        typos if typos <= MAX_TYPOS_PER_WORD => {
                             // When typos >= MAX_TYPOS_PER_WORD, no more alteration of the query tree is possible,
                             // we keep the altered query tree
                             *query_tree = alterate_query_tree(
                                 &fst,
+                                &self.costs,
                                 query_tree.clone(),
-                                self.typos,
+                                MAX_TYPOS_PER_WORD,
                                 params.wdcache,
                             )?;
                             // we compute the allowed candidates
@@ -100,13 +144,19 @@ impl<'t> Criterion for Typo<'t> {
                         _otherwise => query_tree.clone(),
                     };
 
-                    let mut candidates = resolve_candidates(
-                        self.ctx,
-                        &new_query_tree,
-                        self.typos,
-                        &mut self.candidates_cache,
-                        params.wdcache,
-                    )?;
+                    let mut candidates = match candidates_authorization {
+                        Allowed(allowed_candidates) if last_bucket => {
+                            *max_typos = self.typos;
+                            allowed_candidates.clone()
+                        }
+                        _ => resolve_candidates(
+                            self.ctx,
+                            &new_query_tree,
+                            self.typos,
+                            &mut self.candidates_cache,
+                            params.wdcache,
+                        )?,
+                    };
 
                     match candidates_authorization {
                         Allowed(allowed_candidates) => {
@@ -179,15 +229,18 @@ impl<'t> Criterion for Typo<'t> {
 
 /// Modify the query tree by replacing every tolerant query by an Or operation
 /// containing all of the corresponding exact words in the words FST. Each tolerant
-/// query will only be replaced by exact query with up to `number_typos` maximum typos.
+/// query will only be replaced by exact query with typos costing up to `number_typos`,
+/// the exact queries keep the cost of their typos.
 fn alterate_query_tree(
     words_fst: &fst::Set<Cow<[u8]>>,
+    costs: &TypoCosts,
     mut query_tree: Operation,
     number_typos: u8,
     wdcache: &mut WordDerivationsCache,
 ) -> Result<Operation> {
     fn recurse(
         words_fst: &fst::Set<Cow<[u8]>>,
+        costs: &TypoCosts,
         operation: &mut Operation,
         number_typos: u8,
         wdcache: &mut WordDerivationsCache,
@@ -195,22 +248,24 @@ fn alterate_query_tree(
         use Operation::{And, Or, Phrase};
 
         match operation {
-            And(ops) | Or(_, ops) => {
-                ops.iter_mut().try_for_each(|op| recurse(words_fst, op, number_typos, wdcache))
-            }
+            And(ops) | Or(_, ops) => ops
+                .iter_mut()
+                .try_for_each(|op| recurse(words_fst, costs, op, number_typos, wdcache)),
             // Because Phrases don't allow typos, no alteration can be done.
             Phrase(_words) => return Ok(()),
             Operation::Query(q) => {
                 if let QueryKind::Tolerant { typo, word } = &q.kind {
+                    // the free typos of the long words are allowed in the cheaper buckets.
+                    let word_len = word.chars().count();
+                    let typo = (*typo).min(number_typos.saturating_add(costs.free_typos(word_len)));
                     // if no typo is allowed we don't call word_derivations function,
                     // and directly create an Exact query
-                    if number_typos == 0 {
+                    if typo == 0 {
                         *operation = Operation::Query(Query {
                             prefix: q.prefix,
                             kind: QueryKind::Exact { original_typo: 0, word: word.clone() },
                         });
                     } else {
-                        let typo = *typo.min(&number_typos);
                         let words = word_derivations(word, q.prefix, typo, words_fst, wdcache)?;
                         let queries = words
                             .iter()
@@ -218,7 +273,7 @@ fn alterate_query_tree(
                                 Operation::Query(Query {
                                     prefix: false,
                                     kind: QueryKind::Exact {
-                                        original_typo: *typo,
+                                        original_typo: costs.cost(word_len, *typo),
                                         word: word.to_string(),
                                     },
                                 })
@@ -234,7 +289,7 @@ fn alterate_query_tree(
         }
     }
 
-    recurse(words_fst, &mut query_tree, number_typos, wdcache)?;
+    recurse(words_fst, costs, &mut query_tree, number_typos, wdcache)?;
     Ok(query_tree)
 }
 
@@ -349,6 +404,21 @@ mod test {
     use super::super::test::TestContext;
     use super::*;
 
+    #[test]
+    fn typo_costs() {
+        let costs = TypoCosts::default();
+        assert_eq!(costs.cost(20, 2), 2);
+
+        let mut free_typos = BTreeMap::new();
+        free_typos.insert(8, 1);
+        free_typos.insert(12, 2);
+        let costs = TypoCosts { max_buckets: None, free_typos };
+        assert_eq!(costs.cost(5, 1), 1);
+        assert_eq!(costs.cost(8, 1), 0);
+        assert_eq!(costs.cost(10, 2), 1);
+        assert_eq!(costs.cost(12, 2), 0);
+    }
+
     #[test]
     fn initial_placeholder_no_facets() {
         let context = TestContext::default();
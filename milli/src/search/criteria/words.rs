@@ -1,7 +1,7 @@
 use std::mem::take;
 
-use log::debug;
 use roaring::RoaringBitmap;
+use tracing::debug;
 
 use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
 use crate::search::query_tree::Operation;
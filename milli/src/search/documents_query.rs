@@ -0,0 +1,212 @@
+use roaring::RoaringBitmap;
+
+use super::criteria::{facet_ordered, geo_point};
+use super::Filter;
+use crate::error::UserError;
+use crate::{AscDesc, DocumentId, Index, Member, Result};
+
+/// Browses the documents of the index without a query, the documents are filtered,
+/// sorted by the sort criteria and paginated.
+///
+/// The documents are sorted by traversing the facet levels of the sortable fields, only
+/// the documents of the requested page are ordered, the documents without a value for a
+/// sort criterion come after the other ones.
+pub struct DocumentsQuery<'a> {
+    filter: Option<Filter<'a>>,
+    sort_criteria: Vec<AscDesc>,
+    offset: usize,
+    limit: usize,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> DocumentsQuery<'a> {
+    pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index) -> DocumentsQuery<'a> {
+        DocumentsQuery {
+            filter: None,
+            sort_criteria: Vec::new(),
+            offset: 0,
+            limit: 20,
+            rtxn,
+            index,
+        }
+    }
+
+    pub fn filter(&mut self, condition: Filter<'a>) -> &mut DocumentsQuery<'a> {
+        self.filter = Some(condition);
+        self
+    }
+
+    /// The documents are sorted by the first criterion, then by the second one, etc.
+    /// Without sort criteria they are returned in the order of their internal ids.
+    pub fn sort_criteria(&mut self, criteria: Vec<AscDesc>) -> &mut DocumentsQuery<'a> {
+        self.sort_criteria = criteria;
+        self
+    }
+
+    pub fn offset(&mut self, offset: usize) -> &mut DocumentsQuery<'a> {
+        self.offset = offset;
+        self
+    }
+
+    pub fn limit(&mut self, limit: usize) -> &mut DocumentsQuery<'a> {
+        self.limit = limit;
+        self
+    }
+
+    pub fn execute(&self) -> Result<DocumentsQueryResult> {
+        let sortable_fields = self.index.sortable_fields(self.rtxn)?;
+        for asc_desc in &self.sort_criteria {
+            let field = match asc_desc.member() {
+                Member::Field(field) => field.as_str(),
+                Member::Geo(_) => "_geo",
+            };
+            if !sortable_fields.contains(field) {
+                return Err(UserError::InvalidSortableAttribute {
+                    field: field.to_string(),
+                    valid_fields: sortable_fields.into_iter().collect(),
+                })?;
+            }
+        }
+
+        let candidates = match &self.filter {
+            Some(condition) => condition.evaluate(self.rtxn, self.index)?,
+            None => self.index.documents_ids(self.rtxn)?,
+        };
+
+        let wanted = self.offset.saturating_add(self.limit);
+        let mut documents_ids = Vec::new();
+        self.sort(candidates.clone(), &self.sort_criteria, wanted, &mut documents_ids)?;
+        let documents_ids = documents_ids.into_iter().skip(self.offset).collect();
+
+        Ok(DocumentsQueryResult { documents_ids, candidates })
+    }
+
+    /// Pushes the candidates in the output in the order of the criteria,
+    /// until the output contains the wanted number of documents.
+    fn sort(
+        &self,
+        candidates: RoaringBitmap,
+        criteria: &[AscDesc],
+        wanted: usize,
+        output: &mut Vec<DocumentId>,
+    ) -> Result<()> {
+        let (asc_desc, criteria) = match criteria.split_first() {
+            Some(split) if candidates.len() > 1 => split,
+            _ => {
+                let missing = wanted.saturating_sub(output.len());
+                output.extend(candidates.into_iter().take(missing));
+                return Ok(());
+            }
+        };
+
+        let is_ascending = matches!(asc_desc, AscDesc::Asc(_));
+        let groups: Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + '_> =
+            match asc_desc.member() {
+                Member::Field(field) => match self.index.fields_ids_map(self.rtxn)?.id(field) {
+                    Some(field_id) => facet_ordered(
+                        self.index,
+                        self.rtxn,
+                        field_id,
+                        is_ascending,
+                        candidates.clone(),
+                    )?,
+                    None => Box::new(std::iter::empty()),
+                },
+                Member::Geo(point) => match self.index.geo_rtree(self.rtxn)? {
+                    Some(rtree) => Box::new(
+                        geo_point(&rtree, candidates.clone(), *point, is_ascending).map(Ok),
+                    ),
+                    None => Box::new(std::iter::empty()),
+                },
+            };
+
+        // A document can have multiple values, it is ranked by the first one.
+        let mut remaining = candidates;
+        for group in groups {
+            if output.len() >= wanted || remaining.is_empty() {
+                return Ok(());
+            }
+            let group = group? & &remaining;
+            if !group.is_empty() {
+                remaining -= &group;
+                self.sort(group, criteria, wanted, output)?;
+            }
+        }
+
+        // The documents without a value for this criterion are ranked last.
+        self.sort(remaining, criteria, wanted, output)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DocumentsQueryResult {
+    /// The documents of the requested page, in order.
+    pub documents_ids: Vec<DocumentId>,
+    /// All the documents matching the filter.
+    pub candidates: RoaringBitmap,
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    #[test]
+    fn filter_sort_and_paginate() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.set_sortable_fields(hashset! { S("price"), S("name") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "color": "red", "price": 30, "name": "b" },
+            { "id": 1, "color": "blue", "price": 10, "name": "a" },
+            { "id": 2, "color": "red", "price": 10, "name": "c" },
+            { "id": 3, "color": "red", "name": "d" },
+            { "id": 4, "color": "red", "price": 20, "name": "a" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let result = index.documents_iter(&rtxn).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1, 2, 3, 4]);
+
+        let asc = |field: &str| AscDesc::Asc(Member::Field(field.to_string()));
+        let desc = |field: &str| AscDesc::Desc(Member::Field(field.to_string()));
+
+        let mut query = index.documents_iter(&rtxn);
+        query.sort_criteria(vec![asc("price"), desc("name")]);
+        assert_eq!(query.execute().unwrap().documents_ids, vec![2, 1, 4, 0, 3]);
+
+        query.offset(1).limit(2);
+        assert_eq!(query.execute().unwrap().documents_ids, vec![1, 4]);
+
+        let mut query = index.documents_iter(&rtxn);
+        query.filter(Filter::from_str("color = red").unwrap().unwrap());
+        query.sort_criteria(vec![desc("price")]).limit(3);
+        let result = query.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 4, 2]);
+        assert_eq!(result.candidates.iter().collect::<Vec<_>>(), vec![0, 2, 3, 4]);
+
+        let mut query = index.documents_iter(&rtxn);
+        query.sort_criteria(vec![asc("color")]);
+        assert!(query.execute().is_err());
+    }
+}
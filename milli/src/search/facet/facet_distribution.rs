@@ -10,7 +10,7 @@ use crate::facet::FacetType;
 use crate::heed_codec::facet::{
     FacetStringLevelZeroCodec, FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
 };
-use crate::search::facet::{FacetNumberIter, FacetNumberRange, FacetStringIter};
+use crate::search::facet::{FacetNumberIter, FacetNumberRange, FacetStringIter, Filter};
 use crate::{FieldId, Index, Result};
 
 /// Threshold on the number of candidates that will make
@@ -20,13 +20,14 @@ const CANDIDATES_THRESHOLD: u64 = 3000;
 pub struct FacetDistribution<'a> {
     facets: Option<HashSet<String>>,
     candidates: Option<RoaringBitmap>,
+    disjunctive_filter: Option<Filter<'a>>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
 
 impl<'a> FacetDistribution<'a> {
     pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index) -> FacetDistribution<'a> {
-        FacetDistribution { facets: None, candidates: None, rtxn, index }
+        FacetDistribution { facets: None, candidates: None, disjunctive_filter: None, rtxn, index }
     }
 
     pub fn facets<I: IntoIterator<Item = A>, A: AsRef<str>>(&mut self, names: I) -> &mut Self {
@@ -39,6 +40,15 @@ impl<'a> FacetDistribution<'a> {
         self
     }
 
+    /// The filter applied to the candidates, the distribution of every facet is computed with
+    /// the conditions of the filter on this facet excluded. This way the distribution of a facet
+    /// gives the counts of the values that could be added to its selection, like the multi-select
+    /// facets of a search UI. The candidates must not be already filtered by this filter.
+    pub fn disjunctive_filter(&mut self, filter: Filter<'a>) -> &mut Self {
+        self.disjunctive_filter = Some(filter);
+        self
+    }
+
     /// There is a small amount of candidates OR we ask for facet string values so we
     /// decide to iterate over the facet values of each one of them, one by one.
     fn facet_distribution_from_documents(
@@ -67,7 +77,8 @@ impl<'a> FacetDistribution<'a> {
                     }
                 }
             }
-            FacetType::String => {
+            // The dates are counted with their original strings.
+            FacetType::String | FacetType::Date => {
                 let mut normalized_distribution = BTreeMap::new();
                 let mut key_buffer: Vec<_> = field_id.to_be_bytes().iter().copied().collect();
 
@@ -141,6 +152,26 @@ impl<'a> FacetDistribution<'a> {
         Ok(())
     }
 
+    /// The booleans are not facet strings, they are counted from their own bitmaps.
+    fn facet_bools_distribution(
+        &self,
+        field_id: FieldId,
+        candidates: Option<&RoaringBitmap>,
+        distribution: &mut BTreeMap<String, u64>,
+    ) -> heed::Result<()> {
+        for value in [false, true] {
+            let mut docids = self.index.facet_id_bool_docids(self.rtxn, field_id, value)?;
+            if let Some(candidates) = candidates {
+                docids &= candidates;
+            }
+            if !docids.is_empty() {
+                *distribution.entry(value.to_string()).or_insert(0) += docids.len();
+            }
+        }
+
+        Ok(())
+    }
+
     /// Placeholder search, a.k.a. no candidates were specified. We iterate throught the
     /// facet values one by one and iterate on the facet level 0 for numbers.
     fn facet_values_from_raw_facet_database(
@@ -175,14 +206,20 @@ impl<'a> FacetDistribution<'a> {
             .map(|(_normalized, (original, count))| (original.to_string(), count));
         distribution.extend(iter);
 
+        self.facet_bools_distribution(field_id, None, &mut distribution)?;
+
         Ok(distribution)
     }
 
-    fn facet_values(&self, field_id: FieldId) -> heed::Result<BTreeMap<String, u64>> {
+    fn facet_values(
+        &self,
+        field_id: FieldId,
+        candidates: Option<&RoaringBitmap>,
+    ) -> heed::Result<BTreeMap<String, u64>> {
         use FacetType::{Number, String};
 
-        match self.candidates {
-            Some(ref candidates) => {
+        match candidates {
+            Some(candidates) => {
                 // Classic search, candidates were specified, we must return facet values only related
                 // to those candidates. We also enter here for facet strings for performance reasons.
                 let mut distribution = BTreeMap::new();
@@ -211,6 +248,7 @@ impl<'a> FacetDistribution<'a> {
                         &mut distribution,
                     )?;
                 }
+                self.facet_bools_distribution(field_id, Some(candidates), &mut distribution)?;
                 Ok(distribution)
             }
             None => self.facet_values_from_raw_facet_database(field_id),
@@ -219,7 +257,7 @@ impl<'a> FacetDistribution<'a> {
 
     pub fn execute(&self) -> Result<BTreeMap<String, BTreeMap<String, u64>>> {
         let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
-        let filterable_fields = self.index.filterable_fields(self.rtxn)?;
+        let filterable_fields = self.index.filterable_fields_with_flattened(self.rtxn)?;
         let fields = match self.facets {
             Some(ref facets) => {
                 let invalid_fields: HashSet<_> = facets.difference(&filterable_fields).collect();
@@ -229,16 +267,56 @@ impl<'a> FacetDistribution<'a> {
                     }
                     .into());
                 } else {
-                    facets.clone()
+                    // The distribution of a field is given along with the distributions
+                    // of the fields flattened out of its objects, e.g. `tags.name`.
+                    let flattened_fields = self.index.flattened_faceted_fields(self.rtxn)?;
+                    let mut fields = facets.clone();
+                    fields.extend(
+                        flattened_fields
+                            .into_iter()
+                            .filter(|(_, parent)| facets.contains(parent))
+                            .map(|(name, _)| name),
+                    );
+                    fields
                 }
             }
             None => filterable_fields,
         };
 
+        let conditions = match self.disjunctive_filter {
+            Some(ref filter) => filter.conditions(),
+            None => Vec::new(),
+        };
+
+        // Every condition is evaluated once, the candidates of the facets that are not
+        // filtered by any condition are the candidates filtered by all the conditions.
+        let mut conditions_docids = Vec::with_capacity(conditions.len());
+        let mut all_conditions_candidates = self.candidates.clone();
+        for (attributes, filter) in conditions {
+            let docids = filter.evaluate(self.rtxn, self.index)?;
+            all_conditions_candidates = Some(match all_conditions_candidates {
+                Some(candidates) => candidates & &docids,
+                None => docids.clone(),
+            });
+            conditions_docids.push((attributes, docids));
+        }
+
         let mut distribution = BTreeMap::new();
         for name in fields {
             if let Some(fid) = fields_ids_map.id(&name) {
-                let values = self.facet_values(fid)?;
+                let values = if conditions_docids.iter().any(|(attrs, _)| attrs.contains(&name)) {
+                    let mut candidates = self.candidates.clone();
+                    for (_, docids) in conditions_docids.iter().filter(|(a, _)| !a.contains(&name))
+                    {
+                        candidates = Some(match candidates {
+                            Some(candidates) => candidates & docids,
+                            None => docids.clone(),
+                        });
+                    }
+                    self.facet_values(fid, candidates.as_ref())?
+                } else {
+                    self.facet_values(fid, all_conditions_candidates.as_ref())?
+                };
                 distribution.insert(name, values);
             }
         }
@@ -249,11 +327,79 @@ impl<'a> FacetDistribution<'a> {
 
 impl fmt::Debug for FacetDistribution<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let FacetDistribution { facets, candidates, rtxn: _, index: _ } = self;
+        let FacetDistribution { facets, candidates, disjunctive_filter, rtxn: _, index: _ } = self;
 
         f.debug_struct("FacetDistribution")
             .field("facets", facets)
             .field("candidates", candidates)
+            .field("disjunctive_filter", disjunctive_filter)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::{btreemap, hashset};
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    #[test]
+    fn disjunctive_facets_distribution() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color"), S("size") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "color": "red", "size": 1 },
+            { "id": 1, "color": "red", "size": 2 },
+            { "id": 2, "color": "blue", "size": 1 },
+            { "id": 3, "color": "green", "size": 2 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("(color = red OR color = blue) AND size = 1").unwrap();
+        let distribution = FacetDistribution::new(&rtxn, &index)
+            .disjunctive_filter(filter.unwrap())
+            .execute()
+            .unwrap();
+
+        // The colors are only filtered by the size and the sizes by the colors.
+        assert_eq!(
+            distribution,
+            btreemap! {
+                S("color") => btreemap! { S("blue") => 1, S("red") => 1 },
+                S("size") => btreemap! { S("1") => 2, S("2") => 1 },
+            }
+        );
+
+        // The candidates are shared by all the facets.
+        let filter = Filter::from_str("color = green").unwrap();
+        let distribution = FacetDistribution::new(&rtxn, &index)
+            .candidates((1..4).collect())
+            .disjunctive_filter(filter.unwrap())
+            .execute()
+            .unwrap();
+        assert_eq!(
+            distribution,
+            btreemap! {
+                S("color") => btreemap! { S("blue") => 1, S("green") => 1, S("red") => 1 },
+                S("size") => btreemap! { S("2") => 1 },
+            }
+        );
+    }
+}
@@ -399,6 +399,60 @@ impl<'t> Iterator for FacetStringLevelZeroRevRange<'t> {
     }
 }
 
+/// An iterator over the facet strings of a field that are in a lexicographic range.
+///
+/// The bounds are lowercased to be compared with the normalized facet strings,
+/// it yields the original facet strings and the documents ids associated with them.
+pub struct FacetStringRange<'t> {
+    iter: FacetStringLevelZeroRange<'t>,
+}
+
+impl<'t> FacetStringRange<'t> {
+    pub fn new(
+        rtxn: &'t heed::RoTxn,
+        index: &'t Index,
+        field_id: FieldId,
+        left: Bound<&str>,
+        right: Bound<&str>,
+    ) -> heed::Result<FacetStringRange<'t>> {
+        fn normalize(bound: Bound<&str>) -> Bound<String> {
+            match bound {
+                Included(value) => Included(value.to_lowercase()),
+                Excluded(value) => Excluded(value.to_lowercase()),
+                Unbounded => Unbounded,
+            }
+        }
+
+        fn as_str(bound: &Bound<String>) -> Bound<&str> {
+            match bound {
+                Included(value) => Included(value.as_str()),
+                Excluded(value) => Excluded(value.as_str()),
+                Unbounded => Unbounded,
+            }
+        }
+
+        let (left, right) = (normalize(left), normalize(right));
+        let iter = FacetStringLevelZeroRange::new(
+            rtxn,
+            index.facet_id_string_docids,
+            field_id,
+            as_str(&left),
+            as_str(&right),
+        )?;
+        Ok(FacetStringRange { iter })
+    }
+}
+
+impl<'t> Iterator for FacetStringRange<'t> {
+    type Item = heed::Result<(&'t str, RoaringBitmap)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|result| result.map(|(_normalized, original, docids)| (original, docids)))
+    }
+}
+
 type EitherStringRange<'t> = Either<FacetStringGroupRange<'t>, FacetStringLevelZeroRange<'t>>;
 type EitherStringRevRange<'t> =
     Either<FacetStringGroupRevRange<'t>, FacetStringLevelZeroRevRange<'t>>;
@@ -1,17 +1,23 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fmt::{Debug, Display};
-use std::ops::Bound::{self, Excluded, Included};
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
 use std::ops::Deref;
 
 use either::Either;
 pub use filter_parser::{Condition, Error as FPError, FilterCondition, Span, Token};
-use heed::types::DecodeIgnore;
-use log::debug;
+use heed::types::{ByteSlice, DecodeIgnore};
 use roaring::RoaringBitmap;
+use rstar::AABB;
+use serde_json::Value;
+use tracing::debug;
 
-use super::FacetNumberRange;
-use crate::error::{Error, UserError};
+use super::{FacetNumberRange, FacetStringRange};
+use crate::error::{Error, FilterParseError, InternalError, UserError};
+use crate::facet::parse_date;
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
+    FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
 };
 use crate::{
     distance_between_two_points, lat_lng_to_xyz, CboRoaringBitmapCodec, FieldId, Index, Result,
@@ -20,9 +26,47 @@ use crate::{
 /// The maximum number of filters the filter AST can process.
 const MAX_FILTER_DEPTH: usize = 2000;
 
+/// The polar radius of the earth, the smallest one, the bounding boxes of the geo
+/// filters computed with it are a bit larger than the circles they contain.
+const MIN_EARTH_RADIUS_METERS: f64 = 6_356_752.0;
+
+/// The number of points discarded at each stage of the evaluation of the geo filters,
+/// the points are first found in the bounding box of the circle, then the points whose
+/// documents don't match the other conditions are discarded, and the distance of the
+/// remaining ones is checked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GeoFilterStats {
+    /// The points found in the bounding boxes of the circles.
+    pub bounding_box_points: u64,
+    /// The points whose documents don't match the conditions ANDed with the geo filters.
+    pub discarded_by_facets: u64,
+    /// The points of the bounding boxes that are too far from the centers of the circles.
+    pub discarded_by_distance: u64,
+}
+
+/// Reported when a filter allowed to filter on the fields that are not filterable did so,
+/// the documents were scanned to evaluate the conditions, which is slow on large indexes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UnindexedFilterWarning {
+    /// The fields that are not filterable the filter has conditions on.
+    pub fields: Vec<String>,
+    /// The number of documents read to evaluate these conditions.
+    pub scanned_documents: u64,
+}
+
+/// What is gathered while evaluating a filter.
+#[derive(Default)]
+struct EvaluationStats {
+    geo: GeoFilterStats,
+    unindexed: Option<UnindexedFilterWarning>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Filter<'a> {
     condition: FilterCondition<'a>,
+    /// Whether the conditions on the fields that are not filterable are evaluated by
+    /// scanning the documents instead of returning an error.
+    unindexed: bool,
 }
 
 #[derive(Debug)]
@@ -63,7 +107,11 @@ impl<'a> Display for FilterError<'a> {
 
 impl<'a> From<FPError<'a>> for Error {
     fn from(error: FPError<'a>) -> Self {
-        Self::UserError(UserError::InvalidFilter(error.to_string()))
+        Self::UserError(UserError::InvalidFilter(FilterParseError {
+            message: error.to_string(),
+            span: Some(error.span()),
+            expected: error.kind().expected(),
+        }))
     }
 }
 
@@ -124,28 +172,105 @@ impl<'a> Filter<'a> {
             return Err(token.as_external_error(FilterError::TooDeep).into());
         }
 
-        Ok(ands.map(|ands| Self { condition: ands }))
+        Ok(ands.map(Self::from))
     }
 
     pub fn from_str(expression: &'a str) -> Result<Option<Self>> {
         let condition = match FilterCondition::parse(expression) {
             Ok(Some(fc)) => Ok(fc),
             Ok(None) => return Ok(None),
-            Err(e) => Err(Error::UserError(UserError::InvalidFilter(e.to_string()))),
+            Err(e) => Err(Error::from(e)),
         }?;
 
         if let Some(token) = condition.token_at_depth(MAX_FILTER_DEPTH) {
             return Err(token.as_external_error(FilterError::TooDeep).into());
         }
 
-        Ok(Some(Self { condition }))
+        Ok(Some(Self::from(condition)))
+    }
+
+    /// Allows the conditions on the fields that are not filterable, they are evaluated by
+    /// reading all the documents, which is only meant for ad-hoc queries as it is slow.
+    /// The fields filtered this way are reported by [`Filter::evaluate_with_warning`].
+    pub fn allow_unindexed_fields(&mut self, allow: bool) -> &mut Self {
+        self.unindexed = allow;
+        self
+    }
+
+    /// Returns a filter made of the condition, with the same options as this one.
+    fn with_condition(&self, condition: FilterCondition<'a>) -> Filter<'a> {
+        Filter { condition, unindexed: self.unindexed }
+    }
+
+    /// Returns, for every condition of the top-level conjunction of this filter, the attributes
+    /// it filters on and the filter without it, `None` when no other condition is left.
+    pub(crate) fn relaxations(&self) -> Vec<(Vec<String>, Option<Filter<'a>>)> {
+        let mut conditions = Vec::new();
+        conjunction(&self.condition, &mut conditions);
+
+        (0..conditions.len())
+            .map(|i| {
+                let mut removed_attributes = Vec::new();
+                filtered_attributes(conditions[i], &mut removed_attributes);
+                let remaining = conditions
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, condition)| (*condition).clone())
+                    .reduce(|left, right| FilterCondition::And(Box::new(left), Box::new(right)));
+                (removed_attributes, remaining.map(|c| self.with_condition(c)))
+            })
+            .collect()
+    }
+
+    /// Returns the conditions of the top-level conjunction of this filter,
+    /// along with the attributes each one of them filters on.
+    pub(crate) fn conditions(&self) -> Vec<(Vec<String>, Filter<'a>)> {
+        let mut conditions = Vec::new();
+        conjunction(&self.condition, &mut conditions);
+
+        conditions
+            .into_iter()
+            .map(|condition| {
+                let mut attributes = Vec::new();
+                filtered_attributes(condition, &mut attributes);
+                (attributes, self.with_condition(condition.clone()))
+            })
+            .collect()
+    }
+}
+
+fn conjunction<'c, 'a>(
+    condition: &'c FilterCondition<'a>,
+    output: &mut Vec<&'c FilterCondition<'a>>,
+) {
+    match condition {
+        FilterCondition::And(left, right) => {
+            conjunction(left, output);
+            conjunction(right, output);
+        }
+        condition => output.push(condition),
+    }
+}
+
+fn filtered_attributes(condition: &FilterCondition, output: &mut Vec<String>) {
+    let attribute = match condition {
+        FilterCondition::Condition { fid, .. } => fid.value(),
+        FilterCondition::GeoLowerThan { .. } | FilterCondition::GeoGreaterThan { .. } => "_geo",
+        FilterCondition::Or(left, right) | FilterCondition::And(left, right) => {
+            filtered_attributes(left, output);
+            return filtered_attributes(right, output);
+        }
+    };
+    if !output.iter().any(|a| a == attribute) {
+        output.push(attribute.to_string());
     }
 }
 
 impl<'a> Filter<'a> {
     /// Aggregates the documents ids that are part of the specified range automatically
     /// going deeper through the levels.
-    fn explore_facet_number_levels(
+    pub(crate) fn explore_facet_number_levels(
         rtxn: &heed::RoTxn,
         db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
         field_id: FieldId,
@@ -263,12 +388,46 @@ impl<'a> Filter<'a> {
         // as the facets values are all in the same database and prefixed by the
         // field id and the level.
 
+        // The ranges on RFC3339 dates are evaluated on their timestamps, the ranges on
+        // the other values that are not numbers are evaluated on the facet strings,
+        // compared lexicographically, e.g. `release_date >= 2021-01-01`.
+        let number = |token: &Token| token.parse::<f64>().ok();
+        let date = |token: &Token| parse_date(token.value());
+        let boolean = |token: &Token| token.value().trim().to_lowercase().parse::<bool>().ok();
+        let string_range = |left: Bound<&str>, right: Bound<&str>| {
+            Self::evaluate_string_range(rtxn, index, field_id, left, right)
+        };
+        let date_range = |left: Bound<i64>, right: Bound<i64>| {
+            Self::evaluate_date_range(rtxn, index, field_id, left, right)
+        };
         let (left, right) = match operator {
-            Condition::GreaterThan(val) => (Excluded(val.parse()?), Included(f64::MAX)),
-            Condition::GreaterThanOrEqual(val) => (Included(val.parse()?), Included(f64::MAX)),
-            Condition::LowerThan(val) => (Included(f64::MIN), Excluded(val.parse()?)),
-            Condition::LowerThanOrEqual(val) => (Included(f64::MIN), Included(val.parse()?)),
-            Condition::Between { from, to } => (Included(from.parse()?), Included(to.parse()?)),
+            Condition::GreaterThan(val) => match (number(val), date(val)) {
+                (Some(n), _) => (Excluded(n), Included(f64::MAX)),
+                (None, Some(d)) => return date_range(Excluded(d), Unbounded),
+                (None, None) => return string_range(Excluded(val.value()), Unbounded),
+            },
+            Condition::GreaterThanOrEqual(val) => match (number(val), date(val)) {
+                (Some(n), _) => (Included(n), Included(f64::MAX)),
+                (None, Some(d)) => return date_range(Included(d), Unbounded),
+                (None, None) => return string_range(Included(val.value()), Unbounded),
+            },
+            Condition::LowerThan(val) => match (number(val), date(val)) {
+                (Some(n), _) => (Included(f64::MIN), Excluded(n)),
+                (None, Some(d)) => return date_range(Unbounded, Excluded(d)),
+                (None, None) => return string_range(Unbounded, Excluded(val.value())),
+            },
+            Condition::LowerThanOrEqual(val) => match (number(val), date(val)) {
+                (Some(n), _) => (Included(f64::MIN), Included(n)),
+                (None, Some(d)) => return date_range(Unbounded, Included(d)),
+                (None, None) => return string_range(Unbounded, Included(val.value())),
+            },
+            Condition::Between { from, to } => match number(from).zip(number(to)) {
+                Some((from, to)) => (Included(from), Included(to)),
+                None => match date(from).zip(date(to)) {
+                    Some((from, to)) => return date_range(Included(from), Included(to)),
+                    None => return string_range(Included(from.value()), Included(to.value())),
+                },
+            },
             Condition::Equal(val) => {
                 let (_original_value, string_docids) =
                     strings_db.get(rtxn, &(field_id, &val.to_lowercase()))?.unwrap_or_default();
@@ -290,7 +449,12 @@ impl<'a> Filter<'a> {
                     }
                     None => RoaringBitmap::new(),
                 };
-                return Ok(string_docids | number_docids);
+                // The booleans are read from their own bitmaps, not from the facet strings.
+                let bool_docids = match boolean(val) {
+                    Some(b) => index.facet_id_bool_docids(rtxn, field_id, b)?,
+                    None => RoaringBitmap::new(),
+                };
+                return Ok(string_docids | number_docids | bool_docids);
             }
             Condition::NotEqual(val) => {
                 let number = val.parse::<f64>().ok();
@@ -299,12 +463,42 @@ impl<'a> Filter<'a> {
                 } else {
                     RoaringBitmap::new()
                 };
+                let all_bools_ids = if boolean(val).is_some() {
+                    index.facet_id_is_true_docids(rtxn, field_id)?
+                        | index.facet_id_is_false_docids(rtxn, field_id)?
+                } else {
+                    RoaringBitmap::new()
+                };
                 let all_strings_ids = index.string_faceted_documents_ids(rtxn, field_id)?;
                 let operator = Condition::Equal(val.clone());
                 let docids = Self::evaluate_operator(
                     rtxn, index, numbers_db, strings_db, field_id, &operator,
                 )?;
-                return Ok((all_numbers_ids | all_strings_ids) - docids);
+                return Ok((all_numbers_ids | all_bools_ids | all_strings_ids) - docids);
+            }
+            Condition::AnyIn(vals) => {
+                let mut docids = RoaringBitmap::new();
+                for val in vals {
+                    let operator = Condition::Equal(val.clone());
+                    docids |= Self::evaluate_operator(
+                        rtxn, index, numbers_db, strings_db, field_id, &operator,
+                    )?;
+                }
+                return Ok(docids);
+            }
+            Condition::AllIn(vals) => {
+                return Self::evaluate_all_in(rtxn, index, numbers_db, strings_db, field_id, vals);
+            }
+            Condition::NotAnyIn(_) | Condition::NotAllIn(_) => {
+                let all_ids = index.number_faceted_documents_ids(rtxn, field_id)?
+                    | index.string_faceted_documents_ids(rtxn, field_id)?
+                    | index.facet_id_is_true_docids(rtxn, field_id)?
+                    | index.facet_id_is_false_docids(rtxn, field_id)?;
+                let (operator, _) = operator.clone().negate();
+                let docids = Self::evaluate_operator(
+                    rtxn, index, numbers_db, strings_db, field_id, &operator,
+                )?;
+                return Ok(all_ids - docids);
             }
         };
 
@@ -333,13 +527,308 @@ impl<'a> Filter<'a> {
         }
     }
 
+    /// Returns the documents that have values for this field and whose values are all in the
+    /// list, the documents having one of the values are checked one by one by reading their
+    /// facet values.
+    fn evaluate_all_in(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        numbers_db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+        strings_db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+        field_id: FieldId,
+        vals: &[Token<'a>],
+    ) -> Result<RoaringBitmap> {
+        let operator = Condition::AnyIn(vals.to_vec());
+        let candidates =
+            Self::evaluate_operator(rtxn, index, numbers_db, strings_db, field_id, &operator)?;
+
+        // The values are normalized the way the equality conditions normalize them.
+        let strings: HashSet<_> = vals.iter().map(|val| val.to_lowercase()).collect();
+        let numbers: Vec<f64> = vals.iter().filter_map(|val| val.parse().ok()).collect();
+        let boolean = |val: &Token| val.value().trim().to_lowercase().parse::<bool>().ok();
+        let bools: Vec<bool> = vals.iter().filter_map(boolean).collect();
+        let true_ids = index.facet_id_is_true_docids(rtxn, field_id)?;
+        let false_ids = index.facet_id_is_false_docids(rtxn, field_id)?;
+
+        let docid_strings_db = index.field_id_docid_facet_strings.remap_key_type::<ByteSlice>();
+        let docid_numbers_db = index.field_id_docid_facet_f64s.remap_key_type::<ByteSlice>();
+
+        let mut output = RoaringBitmap::new();
+        let mut prefix = Vec::new();
+        'candidates: for docid in candidates {
+            if (true_ids.contains(docid) && !bools.contains(&true))
+                || (false_ids.contains(docid) && !bools.contains(&false))
+            {
+                continue;
+            }
+
+            prefix.clear();
+            prefix.extend_from_slice(&field_id.to_be_bytes());
+            prefix.extend_from_slice(&docid.to_be_bytes());
+
+            let iter = docid_strings_db
+                .prefix_iter(rtxn, &prefix)?
+                .remap_types::<FieldDocIdFacetStringCodec, DecodeIgnore>();
+            for result in iter {
+                let ((_, _, normalized), _) = result?;
+                if !strings.contains(normalized) {
+                    continue 'candidates;
+                }
+            }
+
+            let iter = docid_numbers_db
+                .prefix_iter(rtxn, &prefix)?
+                .remap_types::<FieldDocIdFacetF64Codec, DecodeIgnore>();
+            for result in iter {
+                let ((_, _, number), _) = result?;
+                if !numbers.contains(&number) {
+                    continue 'candidates;
+                }
+            }
+
+            output.insert(docid);
+        }
+
+        Ok(output)
+    }
+
+    /// Returns the documents whose facet strings are in the lexicographic range.
+    fn evaluate_string_range(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        field_id: FieldId,
+        left: Bound<&str>,
+        right: Bound<&str>,
+    ) -> Result<RoaringBitmap> {
+        let mut output = RoaringBitmap::new();
+        for result in FacetStringRange::new(rtxn, index, field_id, left, right)? {
+            let (_original, docids) = result?;
+            output |= docids;
+        }
+        Ok(output)
+    }
+
+    /// Returns the documents whose dates are in the range of timestamps.
+    fn evaluate_date_range(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        field_id: FieldId,
+        left: Bound<i64>,
+        right: Bound<i64>,
+    ) -> Result<RoaringBitmap> {
+        let left = match left {
+            Included(left) => Included((field_id, left)),
+            Excluded(left) => Excluded((field_id, left)),
+            Unbounded => Included((field_id, i64::MIN)),
+        };
+        let right = match right {
+            Included(right) => Included((field_id, right)),
+            Excluded(right) => Excluded((field_id, right)),
+            Unbounded => Included((field_id, i64::MAX)),
+        };
+
+        let mut output = RoaringBitmap::new();
+        for result in index.facet_id_date_docids.range(rtxn, &(left, right))? {
+            let (_key, docids) = result?;
+            output |= docids;
+        }
+        Ok(output)
+    }
+
+    /// Resolves the equality conditions on the primary key directly from the external
+    /// documents ids, the primary key doesn't need to be declared filterable for that.
+    /// The values are stored as the document ids, following the policy of the index.
+    ///
+    /// Returns `None` when the condition must be evaluated on the facet databases, i.e. when
+    /// the primary key is filterable, or by scanning the documents when it is allowed and a
+    /// numeric value, e.g. `1.0`, isn't the id of a document.
+    fn evaluate_primary_key(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        fid: &Token,
+        operator: &Condition<'a>,
+        unindexed: bool,
+    ) -> Result<Option<RoaringBitmap>> {
+        if index.primary_key(rtxn)? != Some(fid.value()) {
+            return Ok(None);
+        }
+        if index.filterable_fields_with_flattened(rtxn)?.contains(fid.value()) {
+            return Ok(None);
+        }
+
+        let vals = match operator {
+            Condition::Equal(val) | Condition::NotEqual(val) => std::slice::from_ref(val),
+            Condition::AnyIn(vals)
+            | Condition::AllIn(vals)
+            | Condition::NotAnyIn(vals)
+            | Condition::NotAllIn(vals) => vals.as_slice(),
+            _ => return Ok(None),
+        };
+
+        // The external ids can be retained by a clear, we only keep the existing documents.
+        let documents_ids = index.documents_ids(rtxn)?;
+        let external_documents_ids = index.external_documents_ids(rtxn)?;
+        let policy = index.document_id_policy(rtxn)?;
+        let mut ids = RoaringBitmap::new();
+        for val in vals {
+            let external_id = match &policy {
+                Some(policy) => policy.external_id(val.value()),
+                None => Some(Cow::Borrowed(val.value())),
+            };
+            let docid = external_id
+                .and_then(|id| external_documents_ids.get(&*id))
+                .filter(|docid| documents_ids.contains(*docid));
+            match docid {
+                Some(docid) => {
+                    ids.insert(docid);
+                }
+                None if unindexed && val.parse::<f64>().is_ok() => return Ok(None),
+                None => (),
+            }
+        }
+
+        match operator {
+            // A document has a single primary key, `ALL IN` is the same as `ANY IN`.
+            Condition::Equal(_) | Condition::AnyIn(_) | Condition::AllIn(_) => Ok(Some(ids)),
+            _ => Ok(Some(documents_ids - ids)),
+        }
+    }
+
+    /// Evaluates the condition on a field that is not filterable by reading the value of the
+    /// field in the documents, only the candidates are read when there are some.
+    fn evaluate_scan(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        attribute: &str,
+        operator: &Condition<'a>,
+        candidates: Option<&RoaringBitmap>,
+        stats: &mut EvaluationStats,
+    ) -> Result<RoaringBitmap> {
+        let warning = stats.unindexed.get_or_insert_with(UnindexedFilterWarning::default);
+        if !warning.fields.iter().any(|field| field == attribute) {
+            warning.fields.push(attribute.to_string());
+        }
+
+        let field_id = match index.fields_ids_map(rtxn)?.id(attribute) {
+            Some(field_id) => field_id,
+            None => return Ok(RoaringBitmap::new()),
+        };
+
+        let documents = match candidates {
+            Some(candidates) => {
+                Either::Left(index.documents(rtxn, candidates)?.into_iter().map(Ok))
+            }
+            None => Either::Right(index.all_documents(rtxn)?),
+        };
+
+        let mut output = RoaringBitmap::new();
+        for result in documents {
+            let (docid, obkv) = result?;
+            warning.scanned_documents += 1;
+            let value: Value = match obkv.get(field_id) {
+                Some(bytes) => serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?,
+                None => continue,
+            };
+            let matches = match operator {
+                Condition::NotEqual(_) | Condition::NotAnyIn(_) | Condition::NotAllIn(_) => {
+                    let (operator, _) = operator.clone().negate();
+                    has_value(&value) && !value_matches(&value, &operator)
+                }
+                operator => value_matches(&value, operator),
+            };
+            if matches {
+                output.insert(docid);
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Returns the documents of the facet partitions selected by this filter when it is only
+    /// made of equalities on filterable fields (e.g. `tenant = a OR tenant = b`), the docids
+    /// are directly read from the facet databases.
+    ///
+    /// Returns `None` when the filter must be evaluated with [`Filter::evaluate`].
+    pub(crate) fn facet_partitions(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+    ) -> Result<Option<RoaringBitmap>> {
+        match &self.condition {
+            FilterCondition::Condition { fid, op: op @ Condition::Equal(_) } => {
+                if !index.filterable_fields_with_flattened(rtxn)?.contains(fid.value()) {
+                    return Ok(None);
+                }
+                match index.fields_ids_map(rtxn)?.id(fid.value()) {
+                    Some(fid) => {
+                        let numbers_db = index.facet_id_f64_docids;
+                        let strings_db = index.facet_id_string_docids;
+                        let docids =
+                            Self::evaluate_operator(rtxn, index, numbers_db, strings_db, fid, op)?;
+                        Ok(Some(docids))
+                    }
+                    None => Ok(Some(RoaringBitmap::new())),
+                }
+            }
+            FilterCondition::Or(lhs, rhs) => {
+                let lhs = Self::facet_partitions(&(lhs.as_ref().clone()).into(), rtxn, index)?;
+                let rhs = Self::facet_partitions(&(rhs.as_ref().clone()).into(), rtxn, index)?;
+                Ok(lhs.zip(rhs).map(|(lhs, rhs)| lhs | rhs))
+            }
+            _ => Ok(None),
+        }
+    }
+
     pub fn evaluate(&self, rtxn: &heed::RoTxn, index: &Index) -> Result<RoaringBitmap> {
+        self.evaluate_with_geo_stats(rtxn, index).map(|(docids, _)| docids)
+    }
+
+    /// Returns the documents matching this filter and the number
+    /// of points discarded by the geo conditions of the filter.
+    pub fn evaluate_with_geo_stats(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+    ) -> Result<(RoaringBitmap, GeoFilterStats)> {
+        let mut stats = EvaluationStats::default();
+        let docids = self.evaluate_within(rtxn, index, None, &mut stats)?;
+        Ok((docids, stats.geo))
+    }
+
+    /// Returns the documents matching this filter and, when the filter has conditions on
+    /// fields that are not filterable, a warning that the documents were scanned.
+    pub fn evaluate_with_warning(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+    ) -> Result<(RoaringBitmap, Option<UnindexedFilterWarning>)> {
+        let mut stats = EvaluationStats::default();
+        let docids = self.evaluate_within(rtxn, index, None, &mut stats)?;
+        Ok((docids, stats.unindexed))
+    }
+
+    /// Evaluates the filter, the geo conditions only check the distance of the points of the
+    /// documents that are part of the candidates, the caller must intersect the candidates
+    /// with the returned documents.
+    fn evaluate_within(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        candidates: Option<&RoaringBitmap>,
+        stats: &mut EvaluationStats,
+    ) -> Result<RoaringBitmap> {
         let numbers_db = index.facet_id_f64_docids;
         let strings_db = index.facet_id_string_docids;
 
         match &self.condition {
             FilterCondition::Condition { fid, op } => {
-                let filterable_fields = index.filterable_fields(rtxn)?;
+                if let Some(docids) =
+                    Self::evaluate_primary_key(rtxn, index, fid, op, self.unindexed)?
+                {
+                    return Ok(docids);
+                }
+
+                let filterable_fields = index.filterable_fields_with_flattened(rtxn)?;
                 if filterable_fields.contains(fid.value()) {
                     let field_ids_map = index.fields_ids_map(rtxn)?;
                     if let Some(fid) = field_ids_map.id(fid.value()) {
@@ -358,6 +847,11 @@ impl<'a> Filter<'a> {
                         attribute @ "_geoDistance" => {
                             return Err(fid.as_external_error(FilterError::Reserved(attribute)))?;
                         }
+                        attribute if self.unindexed => {
+                            return Self::evaluate_scan(
+                                rtxn, index, attribute, op, candidates, stats,
+                            );
+                        }
                         attribute => {
                             return Err(fid.as_external_error(
                                 FilterError::AttributeNotFilterable {
@@ -373,14 +867,45 @@ impl<'a> Filter<'a> {
                 }
             }
             FilterCondition::Or(lhs, rhs) => {
-                let lhs = Self::evaluate(&(lhs.as_ref().clone()).into(), rtxn, index)?;
-                let rhs = Self::evaluate(&(rhs.as_ref().clone()).into(), rtxn, index)?;
+                let lhs = Self::evaluate_within(
+                    &self.with_condition(lhs.as_ref().clone()),
+                    rtxn,
+                    index,
+                    candidates,
+                    stats,
+                )?;
+                let rhs = Self::evaluate_within(
+                    &self.with_condition(rhs.as_ref().clone()),
+                    rtxn,
+                    index,
+                    candidates,
+                    stats,
+                )?;
                 Ok(lhs | rhs)
             }
             FilterCondition::And(lhs, rhs) => {
-                let lhs = Self::evaluate(&(lhs.as_ref().clone()).into(), rtxn, index)?;
-                let rhs = Self::evaluate(&(rhs.as_ref().clone()).into(), rtxn, index)?;
-                Ok(lhs & rhs)
+                // The geo conditions are evaluated last, on the documents matching the other
+                // conditions, to only compute the distance of the points of these documents.
+                let (first, second) =
+                    if is_geo(lhs) && !is_geo(rhs) { (rhs, lhs) } else { (lhs, rhs) };
+                let mut first = Self::evaluate_within(
+                    &self.with_condition(first.as_ref().clone()),
+                    rtxn,
+                    index,
+                    candidates,
+                    stats,
+                )?;
+                if let Some(candidates) = candidates {
+                    first &= candidates;
+                }
+                let second = Self::evaluate_within(
+                    &self.with_condition(second.as_ref().clone()),
+                    rtxn,
+                    index,
+                    Some(&first),
+                    stats,
+                )?;
+                Ok(first & second)
             }
             FilterCondition::GeoLowerThan { point, radius } => {
                 let filterable_fields = index.filterable_fields(rtxn)?;
@@ -402,15 +927,28 @@ impl<'a> Filter<'a> {
                         None => return Ok(RoaringBitmap::new()),
                     };
 
-                    let xyz_base_point = lat_lng_to_xyz(&base_point);
+                    // The points in the circle are at most at this distance of its center,
+                    // in a straight line through the sphere of radius 1 of the rtree.
+                    let angle = (radius / MIN_EARTH_RADIUS_METERS).min(std::f64::consts::PI);
+                    let chord = 2.0 * (angle / 2.0).sin();
+                    let [x, y, z] = lat_lng_to_xyz(&base_point);
+                    let bounding_box = AABB::from_corners(
+                        [x - chord, y - chord, z - chord],
+                        [x + chord, y + chord, z + chord],
+                    );
 
-                    let result = rtree
-                        .nearest_neighbor_iter(&xyz_base_point)
-                        .take_while(|point| {
-                            distance_between_two_points(&base_point, &point.data.1) < radius
-                        })
-                        .map(|point| point.data.0)
-                        .collect();
+                    let mut result = RoaringBitmap::new();
+                    for point in rtree.locate_in_envelope(&bounding_box) {
+                        let (docid, lat_lng) = point.data;
+                        stats.geo.bounding_box_points += 1;
+                        if candidates.map_or(false, |candidates| !candidates.contains(docid)) {
+                            stats.geo.discarded_by_facets += 1;
+                        } else if distance_between_two_points(&base_point, &lat_lng) < radius {
+                            result.insert(docid);
+                        } else {
+                            stats.geo.discarded_by_distance += 1;
+                        }
+                    }
 
                     Ok(result)
                 } else {
@@ -421,11 +959,15 @@ impl<'a> Filter<'a> {
                 }
             }
             FilterCondition::GeoGreaterThan { point, radius } => {
-                let result = Self::evaluate(
-                    &FilterCondition::GeoLowerThan { point: point.clone(), radius: radius.clone() }
-                        .into(),
+                let result = Self::evaluate_within(
+                    &self.with_condition(FilterCondition::GeoLowerThan {
+                        point: point.clone(),
+                        radius: radius.clone(),
+                    }),
                     rtxn,
                     index,
+                    candidates,
+                    stats,
                 )?;
                 let geo_faceted_doc_ids = index.geo_faceted_documents_ids(rtxn)?;
                 Ok(geo_faceted_doc_ids - result)
@@ -434,9 +976,116 @@ impl<'a> Filter<'a> {
     }
 }
 
+/// Returns `true` if the condition is a geo condition.
+fn is_geo(condition: &FilterCondition) -> bool {
+    matches!(
+        condition,
+        FilterCondition::GeoLowerThan { .. } | FilterCondition::GeoGreaterThan { .. }
+    )
+}
+
 impl<'a> From<FilterCondition<'a>> for Filter<'a> {
     fn from(fc: FilterCondition<'a>) -> Self {
-        Self { condition: fc }
+        Self { condition: fc, unindexed: false }
+    }
+}
+
+/// Returns `true` if the JSON value is or contains a value that can be filtered.
+fn has_value(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Object(_) => false,
+        Value::Array(values) => values.iter().any(has_value),
+        _ => true,
+    }
+}
+
+/// Evaluates the condition on a JSON value the way it is evaluated on the facets, the numbers
+/// are compared to the numbers, the dates to the dates and the other ranges lexicographically
+/// on the normalized strings, an array matches when one of its values matches.
+fn value_matches(value: &Value, operator: &Condition) -> bool {
+    let number = |token: &Token| token.parse::<f64>().ok();
+    let date = |token: &Token| parse_date(token.value());
+    let string = |token: &Token| Some(token.value().trim().to_lowercase());
+    let equal_to_any = |value: &Value, vals: &[Token]| {
+        vals.iter().any(|val| value_matches(value, &Condition::Equal(val.clone())))
+    };
+    let tokens = match operator {
+        Condition::Between { from, to } => vec![from, to],
+        Condition::GreaterThan(val)
+        | Condition::GreaterThanOrEqual(val)
+        | Condition::LowerThan(val)
+        | Condition::LowerThanOrEqual(val)
+        | Condition::Equal(val)
+        | Condition::NotEqual(val) => vec![val],
+        Condition::AnyIn(vals) => return equal_to_any(value, vals),
+        Condition::AllIn(vals) => {
+            return has_value(value) && all_values(value, &|value| equal_to_any(value, vals))
+        }
+        Condition::NotAnyIn(_) | Condition::NotAllIn(_) => return false,
+    };
+    let numbers = tokens.iter().all(|token| number(token).is_some());
+    let dates = !numbers && tokens.iter().all(|token| date(token).is_some());
+
+    match (value, operator) {
+        (Value::Array(values), operator) => {
+            values.iter().any(|value| value_matches(value, operator))
+        }
+        (Value::Null | Value::Object(_), _) | (_, Condition::NotEqual(_)) => false,
+        (Value::Bool(b), Condition::Equal(val)) => {
+            val.value().trim().to_lowercase().parse::<bool>().ok() == Some(*b)
+        }
+        (Value::Bool(_), _) => false,
+        (Value::Number(n), Condition::Equal(val)) => {
+            n.as_f64().is_some() && number(val) == n.as_f64()
+        }
+        (Value::Number(n), operator) => match n.as_f64() {
+            Some(n) if numbers => in_range(n, operator, number),
+            _ => false,
+        },
+        (Value::String(s), Condition::Equal(val)) => {
+            string(val).as_deref() == Some(s.trim().to_lowercase().as_str())
+        }
+        (Value::String(s), operator) if dates => match parse_date(s) {
+            Some(timestamp) => in_range(timestamp, operator, date),
+            None => false,
+        },
+        (Value::String(s), operator) => {
+            !numbers && in_range(s.trim().to_lowercase(), operator, string)
+        }
+    }
+}
+
+/// Returns `true` if all the values that can be filtered, of the JSON value and of its
+/// array, satisfy the predicate.
+fn all_values(value: &Value, predicate: &dyn Fn(&Value) -> bool) -> bool {
+    match value {
+        Value::Null | Value::Object(_) => true,
+        Value::Array(values) => values.iter().all(|value| all_values(value, predicate)),
+        value => predicate(value),
+    }
+}
+
+/// Returns `true` if the value is in the range of the condition, the bounds of the range
+/// are parsed from the tokens.
+fn in_range<T: PartialOrd>(
+    value: T,
+    operator: &Condition,
+    parse: impl Fn(&Token) -> Option<T>,
+) -> bool {
+    match operator {
+        Condition::GreaterThan(val) => parse(val).map_or(false, |val| value > val),
+        Condition::GreaterThanOrEqual(val) => parse(val).map_or(false, |val| value >= val),
+        Condition::LowerThan(val) => parse(val).map_or(false, |val| value < val),
+        Condition::LowerThanOrEqual(val) => parse(val).map_or(false, |val| value <= val),
+        Condition::Between { from, to } => {
+            parse(from).zip(parse(to)).map_or(false, |(from, to)| from <= value && value <= to)
+        }
+        Condition::Equal(_)
+        | Condition::NotEqual(_)
+        | Condition::AnyIn(_)
+        | Condition::AllIn(_)
+        | Condition::NotAnyIn(_)
+        | Condition::NotAllIn(_) => false,
     }
 }
 
@@ -450,8 +1099,11 @@ mod tests {
     use maplit::hashset;
 
     use super::*;
-    use crate::update::{IndexerConfig, Settings};
-    use crate::Index;
+    use crate::update::{
+        DeleteDocuments, DocumentIdCharset, DocumentIdNormalization, IndexDocuments,
+        IndexDocumentsConfig, IndexerConfig, Settings,
+    };
+    use crate::{AscDesc, Index, Member};
 
     #[test]
     fn empty_db() {
@@ -588,6 +1240,107 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn primary_key_filter() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": "2", "name": "kevina" },
+            { "id": "benoit", "name": "benoit" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let external_ids = |filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            let docids = filter.evaluate(&rtxn, &index).unwrap();
+            let mut ids = index.external_ids(&rtxn, docids).unwrap();
+            ids.sort_unstable();
+            ids
+        };
+
+        // the primary key is not filterable but can be filtered on by equality.
+        assert_eq!(external_ids("id = benoit"), vec!["benoit"]);
+        assert_eq!(external_ids("id = 1 OR id = 2 OR id = 3"), vec!["1", "2"]);
+        assert_eq!(external_ids("id != 1"), vec!["2", "benoit"]);
+        assert_eq!(external_ids("NOT id = benoit"), vec!["1", "2"]);
+        assert!(external_ids("id = kevin").is_empty());
+
+        // ranges still go through the facet databases.
+        let filter = Filter::from_str("id > 1").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with("Attribute `id` is not filterable."));
+    }
+
+    #[test]
+    fn primary_key_filter_with_document_id_policy() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "007", "name": "kevin" },
+            { "id": "café", "name": "kevina" },
+            { "id": 2, "name": "benoit" }
+        ]);
+        let indexing_config = IndexDocumentsConfig {
+            document_id_normalization: DocumentIdNormalization::StripLeadingZeros,
+            document_id_charset: DocumentIdCharset::Any,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let names = |rtxn: &heed::RoTxn, filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            let docids = filter.evaluate(rtxn, &index).unwrap();
+            let fields_ids_map = index.fields_ids_map(rtxn).unwrap();
+            let name = fields_ids_map.id("name").unwrap();
+            let mut names: Vec<_> = index
+                .documents(rtxn, docids)
+                .unwrap()
+                .into_iter()
+                .map(|(_, doc)| serde_json::from_slice::<String>(doc.get(name).unwrap()).unwrap())
+                .collect();
+            names.sort_unstable();
+            names
+        };
+
+        // The values are normalized and hashed like the ids of the documents.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(names(&rtxn, "id = 007"), vec!["kevin"]);
+        assert_eq!(names(&rtxn, "id = 7"), vec!["kevin"]);
+        assert_eq!(names(&rtxn, "id = café"), vec!["kevina"]);
+        assert_eq!(names(&rtxn, "id != café"), vec!["benoit", "kevin"]);
+        assert!(names(&rtxn, "id = 2.0").is_empty());
+        drop(rtxn);
+
+        // A filterable primary key is evaluated on the facet databases.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("id") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(names(&rtxn, "id = 2.0"), vec!["benoit"]);
+    }
+
     #[test]
     fn geo_radius_error() {
         let path = tempfile::tempdir().unwrap();
@@ -643,6 +1396,353 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn string_ranges() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("release_date"), S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "release_date": "2019-06-01", "price": 10 },
+            { "id": 1, "release_date": "2020-12-31", "price": 20 },
+            { "id": 2, "release_date": "2021-01-01", "price": 30 },
+            { "id": 3, "release_date": "2021-07-14", "price": 40 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docids = |filter: &str| -> Vec<u32> {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().into_iter().collect()
+        };
+
+        assert_eq!(docids("release_date >= 2021-01-01"), vec![2, 3]);
+        assert_eq!(docids("release_date > 2021-01-01"), vec![3]);
+        assert_eq!(docids("release_date < 2020-12-31"), vec![0]);
+        assert_eq!(docids("release_date <= 2020-12-31"), vec![0, 1]);
+        assert_eq!(docids("release_date 2020-01-01 TO 2021-01-01"), vec![1, 2]);
+        // The numbers are still compared as numbers.
+        assert_eq!(docids("price >= 20 AND price < 40"), vec![1, 2]);
+
+        let fid = index.fields_ids_map(&rtxn).unwrap().id("release_date").unwrap();
+        let range = FacetStringRange::new(&rtxn, &index, fid, Excluded("2019"), Excluded("2021"));
+        let values: Vec<_> = range.unwrap().map(|result| result.unwrap().0).collect();
+        assert_eq!(values, vec!["2019-06-01", "2020-12-31"]);
+    }
+
+    #[test]
+    fn date_ranges_and_sort() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("published_at") });
+        builder.set_sortable_fields(hashset! { S("published_at") });
+        builder.execute(|_| ()).unwrap();
+
+        // The lexicographic order of these dates is not their chronological order.
+        let content = documents!([
+            { "id": 0, "published_at": "2021-01-01T12:00:00+02:00" },
+            { "id": 1, "published_at": "2021-01-01T11:00:00Z" },
+            { "id": 2, "published_at": "2021-01-01T09:00:00-03:00" },
+            { "id": 3, "published_at": "yesterday" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.facet_id_date_docids.len(&rtxn).unwrap(), 3);
+
+        let docids = |filter: &str| -> Vec<u32> {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().into_iter().collect()
+        };
+
+        assert_eq!(docids("published_at > '2021-01-01T10:00:00Z'"), vec![1, 2]);
+        assert_eq!(docids("published_at >= '2021-01-01T10:00:00Z'"), vec![0, 1, 2]);
+        assert_eq!(docids("published_at < '2021-01-01T12:00:00+01:00'"), vec![0]);
+        assert_eq!(docids("published_at <= '2021-01-01T12:00:00+01:00'"), vec![0, 1]);
+        assert_eq!(
+            docids("published_at '2021-01-01T10:30:00Z' TO '2021-01-01T12:00:00Z'"),
+            vec![1, 2]
+        );
+
+        let sorted = |member: AscDesc| -> Vec<u32> {
+            let mut search = crate::Search::new(&rtxn, &index);
+            search.sort_criteria(vec![member]);
+            search.execute().unwrap().documents_ids
+        };
+
+        let published_at = || Member::Field(S("published_at"));
+        assert_eq!(sorted(AscDesc::Asc(published_at())), vec![0, 1, 2, 3]);
+        assert_eq!(sorted(AscDesc::Desc(published_at())), vec![2, 1, 0, 3]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("1");
+        builder.execute().unwrap();
+        assert_eq!(index.facet_id_date_docids.len(&wtxn).unwrap(), 2);
+    }
+
+    #[test]
+    fn bool_filters() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("is_available") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "is_available": true },
+            { "id": 1, "is_available": false },
+            { "id": 2, "is_available": [true, false] },
+            { "id": 3, "is_available": "true" },
+            { "id": 4, "is_available": null }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fid = index.fields_ids_map(&rtxn).unwrap().id("is_available").unwrap();
+        let is_true: Vec<_> = index.facet_id_is_true_docids(&rtxn, fid).unwrap().iter().collect();
+        let is_false: Vec<_> = index.facet_id_is_false_docids(&rtxn, fid).unwrap().iter().collect();
+        assert_eq!(is_true, vec![0, 2]);
+        assert_eq!(is_false, vec![1, 2]);
+        // The booleans are not faceted as strings.
+        assert_eq!(index.string_faceted_documents_ids(&rtxn, fid).unwrap().len(), 1);
+
+        let docids = |filter: &str| -> Vec<u32> {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().into_iter().collect()
+        };
+
+        assert_eq!(docids("is_available = true"), vec![0, 2, 3]);
+        assert_eq!(docids("is_available = FALSE"), vec![1, 2]);
+        assert_eq!(docids("is_available != false"), vec![0, 3]);
+        assert_eq!(docids("NOT is_available = true"), vec![1]);
+
+        let distribution = crate::FacetDistribution::new(&rtxn, &index).execute().unwrap();
+        assert_eq!(
+            distribution["is_available"],
+            maplit::btreemap! { S("false") => 2, S("true") => 3 }
+        );
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("2");
+        builder.execute().unwrap();
+        assert_eq!(index.facet_id_is_true_docids(&wtxn, fid).unwrap().len(), 1);
+        assert_eq!(index.facet_id_is_false_docids(&wtxn, fid).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn all_and_any_in() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("tags") });
+        builder.execute(|_| ()).unwrap();
+
+        // The labels are the same as the tags but are not filterable.
+        let content = documents!([
+            { "id": 0, "tags": ["rust", "go"], "labels": ["rust", "go"] },
+            { "id": 1, "tags": ["Rust"], "labels": ["Rust"] },
+            { "id": 2, "tags": ["rust", "python"], "labels": ["rust", "python"] },
+            { "id": 3, "tags": "go", "labels": "go" },
+            { "id": 4, "tags": [1, "go"], "labels": [1, "go"] },
+            { "id": 5, "tags": null, "labels": null },
+            { "id": 6 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docids = |filter: &str| -> Vec<u32> {
+            let facets = Filter::from_str(filter).unwrap().unwrap();
+            let docids: Vec<_> = facets.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+
+            // The scanned documents must match the same way the facets do.
+            let labels = filter.replace("tags", "labels");
+            let mut scan = Filter::from_str(&labels).unwrap().unwrap();
+            scan.allow_unindexed_fields(true);
+            let scanned: Vec<_> = scan.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+            assert_eq!(docids, scanned, "{}", filter);
+
+            docids
+        };
+
+        assert_eq!(docids("tags ANY IN [rust, go]"), vec![0, 1, 2, 3, 4]);
+        assert_eq!(docids("tags ALL IN [rust, go]"), vec![0, 1, 3]);
+        assert_eq!(docids("tags ALL IN [go, 1]"), vec![3, 4]);
+        assert_eq!(docids("tags ALL IN [python]"), Vec::<u32>::new());
+        assert_eq!(docids("NOT tags ALL IN [rust, go]"), vec![2, 4]);
+        assert_eq!(docids("NOT tags ANY IN [python, 1]"), vec![0, 1, 3]);
+        assert_eq!(docids("tags ALL IN [rust, go] AND tags = rust"), vec![0, 1]);
+        assert_eq!(docids("id ANY IN [1, 3, 42]"), vec![1, 3]);
+    }
+
+    #[test]
+    fn geo_radius_with_facets() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo"), S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "color": "red", "_geo": { "lat": 45.0, "lng": 5.0 } },
+            // 111 meters to the north.
+            { "id": 1, "color": "blue", "_geo": { "lat": 45.001, "lng": 5.0 } },
+            // 786 meters to the east, out of the bounding box.
+            { "id": 2, "color": "red", "_geo": { "lat": 45.0, "lng": 5.01 } },
+            { "id": 3, "color": "red", "_geo": { "lat": 50.0, "lng": 5.0 } },
+            // 566 meters to the north-east, in the bounding box.
+            { "id": 4, "color": "red", "_geo": { "lat": 45.0036, "lng": 5.0051 } }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let filter = Filter::from_str("_geoRadius(45, 5, 500)").unwrap().unwrap();
+        let (docids, stats) = filter.evaluate_with_geo_stats(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1]);
+        let expected = GeoFilterStats {
+            bounding_box_points: 3,
+            discarded_by_facets: 0,
+            discarded_by_distance: 1,
+        };
+        assert_eq!(stats, expected);
+
+        // The points of the documents that are not red are not checked.
+        for filter in
+            &["color = red AND _geoRadius(45, 5, 500)", "_geoRadius(45, 5, 500) AND color = red"]
+        {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            let (docids, stats) = filter.evaluate_with_geo_stats(&rtxn, &index).unwrap();
+            assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0]);
+            let expected = GeoFilterStats {
+                bounding_box_points: 3,
+                discarded_by_facets: 1,
+                discarded_by_distance: 1,
+            };
+            assert_eq!(stats, expected);
+        }
+
+        let filter = Filter::from_str("color = red AND _geoRadius(45, 5, 1000)").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2, 4]);
+
+        let filter =
+            Filter::from_str("color = red AND NOT _geoRadius(45, 5, 500)").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn unindexed_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "color": "red", "price": 10, "tags": ["Sale", "new"] },
+            { "id": 1, "color": "blue", "price": 25.5, "tags": "sale" },
+            { "id": 2, "color": "red", "price": 40, "released": "2021-03-01T00:00:00Z" },
+            { "id": 3, "color": "red", "price": null, "released": "2022-03-01T00:00:00Z" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // The fields that are not filterable are still rejected by default.
+        let filter = Filter::from_str("price > 20").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).is_err());
+
+        let evaluate = |filter: &str| {
+            let mut filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.allow_unindexed_fields(true);
+            let (docids, warning) = filter.evaluate_with_warning(&rtxn, &index).unwrap();
+            (docids.iter().collect::<Vec<_>>(), warning)
+        };
+
+        let (docids, warning) = evaluate("price > 20");
+        assert_eq!(docids, vec![1, 2]);
+        let warning = warning.unwrap();
+        assert_eq!(warning.fields, vec![S("price")]);
+        assert_eq!(warning.scanned_documents, 4);
+
+        assert_eq!(evaluate("price 10 TO 25.5").0, vec![0, 1]);
+        assert_eq!(evaluate("tags = SALE").0, vec![0, 1]);
+        assert_eq!(evaluate("tags != new").0, vec![1]);
+        assert_eq!(evaluate("released < '2022-01-01T00:00:00Z'").0, vec![2]);
+
+        // Only the documents matching the filterable conditions are scanned.
+        let (docids, warning) = evaluate("color = red AND price <= 40");
+        assert_eq!(docids, vec![0, 2]);
+        assert_eq!(warning.unwrap().scanned_documents, 3);
+
+        // No warning when the filter only uses filterable fields.
+        let (docids, warning) = evaluate("color = blue");
+        assert_eq!(docids, vec![1]);
+        assert_eq!(warning, None);
+    }
+
     #[test]
     fn filter_depth() {
         // generates a big (2 MiB) filter with too much of ORs.
@@ -663,6 +1763,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn filter_error_span() {
+        let error = Filter::from_str("channel = Ponce dog").unwrap_err();
+        match error {
+            Error::UserError(UserError::InvalidFilter(FilterParseError {
+                span, expected, ..
+            })) => {
+                assert_eq!(span, Some(16..19));
+                assert_eq!(expected, vec![S("AND"), S("OR")]);
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
     #[test]
     fn empty_filter() {
         let option = Filter::from_str("     ").unwrap();
@@ -1,7 +1,7 @@
 pub use self::facet_distribution::FacetDistribution;
 pub use self::facet_number::{FacetNumberIter, FacetNumberRange, FacetNumberRevRange};
-pub use self::facet_string::FacetStringIter;
-pub use self::filter::Filter;
+pub use self::facet_string::{FacetStringIter, FacetStringRange};
+pub use self::filter::{Filter, GeoFilterStats, UnindexedFilterWarning};
 
 mod facet_distribution;
 mod facet_number;
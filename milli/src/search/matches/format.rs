@@ -0,0 +1,105 @@
+use std::borrow::Cow;
+
+use super::Matcher;
+
+/// Defines how the text of a field must be formatted by a [`Matcher`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FormatOptions {
+    /// Surrounds the matches with the highlight prefix and suffix.
+    pub highlight: bool,
+    /// Crops the text to this number of words, around the densest window of matches.
+    pub crop: Option<usize>,
+}
+
+impl FormatOptions {
+    pub fn should_format(&self) -> bool {
+        self.highlight || self.crop.is_some()
+    }
+}
+
+impl<'t> Matcher<'t, '_> {
+    /// Returns the text formatted according to the given options, the crop markers are
+    /// only inserted where words have been removed from the text.
+    pub fn format(&mut self, format_options: FormatOptions) -> Cow<'t, str> {
+        if !format_options.should_format() {
+            return Cow::Borrowed(self.text);
+        }
+
+        let bounds = match format_options.crop {
+            Some(crop_size) => self.crop_bounds(crop_size),
+            None => 0..self.text.len(),
+        };
+
+        let matches = if format_options.highlight { self.matches() } else { Vec::new() };
+
+        let mut formatted = String::new();
+        let mut words_positions = self.tokens.iter().filter(|t| t.is_word());
+        if words_positions.any(|word| word.byte_end <= bounds.start) {
+            formatted.push_str(self.crop_marker);
+        }
+
+        let mut byte_index = bounds.start;
+        let matches = matches
+            .into_iter()
+            .filter(|m| m.start >= bounds.start && m.start + m.length <= bounds.end);
+        for m in matches {
+            formatted.push_str(&self.text[byte_index..m.start]);
+            formatted.push_str(self.highlight_prefix);
+            formatted.push_str(&self.text[m.start..m.start + m.length]);
+            formatted.push_str(self.highlight_suffix);
+            byte_index = m.start + m.length;
+        }
+        formatted.push_str(&self.text[byte_index..bounds.end]);
+
+        let mut words_positions = self.tokens.iter().filter(|t| t.is_word());
+        if words_positions.any(|word| word.byte_start >= bounds.end) {
+            formatted.push_str(self.crop_marker);
+        }
+
+        Cow::Owned(formatted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+
+    use super::*;
+    use crate::search::matches::tests::matching_words;
+    use crate::search::matches::MatcherBuilder;
+
+    #[test]
+    fn format_highlight_and_crop() {
+        let mut builder = MatcherBuilder::from_matching_words(matching_words());
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+
+        let text = "a b c d e f split g h i j k l m n o p q world the r s t";
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+
+        let mut matcher = builder.build(&tokens, text);
+        let format_options = FormatOptions::default();
+        assert_eq!(matcher.format(format_options), Cow::Borrowed(text));
+
+        let format_options = FormatOptions { highlight: true, crop: Some(4) };
+        assert_eq!(matcher.format(format_options), "…q <em>world</em> <em>the</em> r…");
+
+        let format_options = FormatOptions { highlight: false, crop: Some(2) };
+        assert_eq!(matcher.format(format_options), "…world the…");
+
+        builder.crop_marker(String::from("[...]"));
+        builder.highlight_prefix(String::from("*"));
+        builder.highlight_suffix(String::from("*"));
+
+        let text = "Splitting the Word";
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        let mut matcher = builder.build(&tokens, text);
+
+        let format_options = FormatOptions { highlight: true, crop: None };
+        assert_eq!(matcher.format(format_options), "*Split*ting *the* *Wor*d");
+
+        let format_options = FormatOptions { highlight: true, crop: Some(1) };
+        assert_eq!(matcher.format(format_options), "*Split*ting[...]");
+    }
+}
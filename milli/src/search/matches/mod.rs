@@ -0,0 +1,234 @@
+use std::ops::Range;
+
+use meilisearch_tokenizer::Token;
+
+pub use self::format::FormatOptions;
+use crate::search::matching_words::MatchingWords;
+
+mod format;
+
+pub(crate) const DEFAULT_CROP_MARKER: &str = "…";
+pub(crate) const DEFAULT_HIGHLIGHT_PREFIX: &str = "<em>";
+pub(crate) const DEFAULT_HIGHLIGHT_SUFFIX: &str = "</em>";
+
+/// Builds `Matcher`s for the words of a query,
+/// it can be reused to build a `Matcher` for every field of every document to highlight.
+pub struct MatcherBuilder {
+    matching_words: MatchingWords,
+    crop_marker: Option<String>,
+    highlight_prefix: Option<String>,
+    highlight_suffix: Option<String>,
+}
+
+impl MatcherBuilder {
+    pub fn from_matching_words(matching_words: MatchingWords) -> Self {
+        Self { matching_words, crop_marker: None, highlight_prefix: None, highlight_suffix: None }
+    }
+
+    /// The marker inserted where the text has been cropped, defaults to `…`.
+    pub fn crop_marker(&mut self, marker: String) -> &mut Self {
+        self.crop_marker = Some(marker);
+        self
+    }
+
+    /// The marker inserted before a match, defaults to `<em>`.
+    pub fn highlight_prefix(&mut self, prefix: String) -> &mut Self {
+        self.highlight_prefix = Some(prefix);
+        self
+    }
+
+    /// The marker inserted after a match, defaults to `</em>`.
+    pub fn highlight_suffix(&mut self, suffix: String) -> &mut Self {
+        self.highlight_suffix = Some(suffix);
+        self
+    }
+
+    /// Builds a `Matcher` for the given text and the tokens produced by the analyzer for it.
+    pub fn build<'t, 'm>(&'m self, tokens: &'t [Token<'t>], text: &'t str) -> Matcher<'t, 'm> {
+        Matcher {
+            text,
+            tokens,
+            matching_words: &self.matching_words,
+            crop_marker: self.crop_marker.as_deref().unwrap_or(DEFAULT_CROP_MARKER),
+            highlight_prefix: self.highlight_prefix.as_deref().unwrap_or(DEFAULT_HIGHLIGHT_PREFIX),
+            highlight_suffix: self.highlight_suffix.as_deref().unwrap_or(DEFAULT_HIGHLIGHT_SUFFIX),
+            matches: None,
+        }
+    }
+}
+
+/// The bytes bounds of a match in the original text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchBounds {
+    pub start: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Match {
+    /// The bounds of the match in the original text.
+    bounds: MatchBounds,
+    /// The position of the matching word, only word tokens are counted.
+    word_position: usize,
+}
+
+pub struct Matcher<'t, 'm> {
+    text: &'t str,
+    tokens: &'t [Token<'t>],
+    matching_words: &'m MatchingWords,
+    crop_marker: &'m str,
+    highlight_prefix: &'m str,
+    highlight_suffix: &'m str,
+    matches: Option<Vec<Match>>,
+}
+
+impl<'t> Matcher<'t, '_> {
+    /// Finds the words of the text that match the query words,
+    /// typo-derived and prefix matches included.
+    fn compute_matches(&mut self) -> &[Match] {
+        if self.matches.is_none() {
+            let mut matches = Vec::new();
+            let words = self.tokens.iter().filter(|token| token.is_word());
+            for (word_position, token) in words.enumerate() {
+                if let Some(chars_to_highlight) = self.matching_words.matching_bytes(token) {
+                    let original = &self.text[token.byte_start..token.byte_end];
+                    let length =
+                        original.chars().take(chars_to_highlight).map(char::len_utf8).sum();
+                    let bounds = MatchBounds { start: token.byte_start, length };
+                    matches.push(Match { bounds, word_position });
+                }
+            }
+            self.matches = Some(matches);
+        }
+
+        self.matches.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the bounds of the matches in the original text, in the order they appear.
+    pub fn matches(&mut self) -> Vec<MatchBounds> {
+        self.compute_matches().iter().map(|m| m.bounds).collect()
+    }
+
+    /// Returns the bytes range of the window of `crop_size` words containing
+    /// the most matches, the matches of this window are centered in it.
+    ///
+    /// When nothing matches, the window starts at the first word of the text.
+    pub fn crop_bounds(&mut self, crop_size: usize) -> Range<usize> {
+        let tokens = self.tokens;
+        let words: Vec<_> = tokens.iter().filter(|token| token.is_word()).collect();
+        if crop_size == 0 || words.is_empty() {
+            return 0..0;
+        }
+
+        let (first_match, last_match) = best_matches_window(self.compute_matches(), crop_size);
+
+        // We distribute the words that are not part of the matches
+        // window evenly before and after it, when possible.
+        let remaining_words = crop_size - (last_match - first_match + 1);
+        let start = first_match.saturating_sub(remaining_words / 2);
+        let end = (start + crop_size).min(words.len());
+        let start = end.saturating_sub(crop_size);
+
+        words[start].byte_start..words[end - 1].byte_end
+    }
+}
+
+/// Returns the positions of the first and last words of the densest window of matches
+/// that fits in `crop_size` words, the shortest window wins when two windows contain
+/// as many matches, the first one when they are also as long.
+fn best_matches_window(matches: &[Match], crop_size: usize) -> (usize, usize) {
+    let mut best = None;
+    let mut last = 0;
+    for (first, first_match) in matches.iter().enumerate() {
+        last = last.max(first);
+        while matches
+            .get(last + 1)
+            .map_or(false, |m| m.word_position - first_match.word_position < crop_size)
+        {
+            last += 1;
+        }
+
+        let count = last - first + 1;
+        let span = matches[last].word_position - first_match.word_position;
+        match best {
+            Some((best_count, best_span, _, _))
+                if best_count > count || (best_count == count && best_span <= span) => {}
+            _ => best = Some((count, span, first_match.word_position, matches[last].word_position)),
+        }
+    }
+
+    best.map_or((0, 0), |(_, _, first, last)| (first, last))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+
+    use super::*;
+    use crate::search::query_tree::{Operation, Query, QueryKind};
+
+    pub(crate) fn matching_words() -> MatchingWords {
+        let query_tree = Operation::Or(
+            false,
+            vec![Operation::And(vec![
+                Operation::Query(Query {
+                    prefix: true,
+                    kind: QueryKind::exact("split".to_string()),
+                }),
+                Operation::Query(Query {
+                    prefix: false,
+                    kind: QueryKind::exact("the".to_string()),
+                }),
+                Operation::Query(Query {
+                    prefix: true,
+                    kind: QueryKind::tolerant(1, "world".to_string()),
+                }),
+            ])],
+        );
+
+        MatchingWords::from_query_tree(&query_tree)
+    }
+
+    #[test]
+    fn matches_bounds() {
+        let builder = MatcherBuilder::from_matching_words(matching_words());
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+
+        let text = "Splitting the Word in two";
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        let mut matcher = builder.build(&tokens, text);
+
+        let matches: Vec<_> =
+            matcher.matches().into_iter().map(|m| &text[m.start..m.start + m.length]).collect();
+        assert_eq!(matches, vec!["Split", "the", "Wor"]);
+
+        let text = "nothing to see here";
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        let mut matcher = builder.build(&tokens, text);
+        assert!(matcher.matches().is_empty());
+    }
+
+    #[test]
+    fn crop_bounds() {
+        let builder = MatcherBuilder::from_matching_words(matching_words());
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+
+        let text = "a b c d e f split g h i j k l m n o p q world the r s t";
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        let mut matcher = builder.build(&tokens, text);
+
+        // the densest window is the one around "world the".
+        assert_eq!(&text[matcher.crop_bounds(4)], "q world the r");
+        // the window is moved back when it overflows the end of the text.
+        assert_eq!(&text[matcher.crop_bounds(10)], "m n o p q world the r s t");
+
+        let text = "nothing to see here";
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        let mut matcher = builder.build(&tokens, text);
+        assert_eq!(&text[matcher.crop_bounds(2)], "nothing to");
+    }
+}
@@ -9,39 +9,83 @@ use std::time::Instant;
 use distinct::{Distinct, DocIter, FacetDistinct, NoopDistinct};
 use fst::{IntoStreamer, Streamer};
 use levenshtein_automata::{LevenshteinAutomatonBuilder as LevBuilder, DFA};
-use log::debug;
 use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
 use once_cell::sync::Lazy;
 use roaring::bitmap::RoaringBitmap;
-
-pub use self::facet::{FacetDistribution, FacetNumberIter, Filter};
+use tracing::debug;
+
+pub use self::completions::{Completion, Completions};
+pub use self::criteria::{Decay, DecayCurve, TypoCosts};
+pub use self::documents_query::{DocumentsQuery, DocumentsQueryResult};
+pub use self::facet::{
+    FacetDistribution, FacetNumberIter, Filter, GeoFilterStats, UnindexedFilterWarning,
+};
+pub use self::matches::{FormatOptions, MatchBounds, Matcher, MatcherBuilder};
 pub use self::matching_words::MatchingWords;
-use self::query_tree::QueryTreeBuilder;
+pub use self::percolate::{Percolate, StoredQuery};
+pub use self::prefix_audit::{PrefixAudit, PrefixDiscrepancy};
+pub use self::query_tree::MinWordMatch;
+use self::query_tree::{matched_parts_count, PrimitiveQueryPart, QueryTreeBuilder};
+pub use self::query_understanding::{
+    Comparison, FacetVocabulary, InterpretedFilter, NumericVocabulary, QueryInterpretation,
+    QueryInterpreter,
+};
+pub use self::ranking_features::{DocumentFeatures, RankingFeatures};
+pub use self::search_query::{
+    HighlightOptions, MatchingStrategy, SearchQuery, DEFAULT_CROP_LENGTH, DEFAULT_SEARCH_LIMIT,
+};
+use self::similar::{shared_terms_scores, sort_scores};
+pub use self::similar::{SimilarDocuments, SimilarDocumentsResult, Similarity};
+pub use self::suggest::{Suggest, Suggestion};
 use crate::error::UserError;
 use crate::search::criteria::r#final::{Final, FinalResult};
-use crate::{AscDesc, Criterion, DocumentId, Index, Member, Result};
+use crate::tokenization::mark_stop_words;
+use crate::{
+    AscDesc, Criterion, DocumentId, Index, Member, Result, RoaringBitmapLenCodec, Tokenize,
+    TokenizerSettings,
+};
 
 // Building these factories is not free.
 static LEVDIST0: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(0, true));
 static LEVDIST1: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(1, true));
 static LEVDIST2: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(2, true));
 
+mod completions;
 mod criteria;
 mod distinct;
+mod documents_query;
 mod facet;
+mod matches;
 mod matching_words;
+mod percolate;
+mod prefix_audit;
 mod query_tree;
+mod query_understanding;
+mod ranking_features;
+mod search_query;
+mod similar;
+mod suggest;
 
 pub struct Search<'a> {
     query: Option<String>,
     // this should be linked to the String in the query
     filter: Option<Filter<'a>>,
+    candidates: Option<RoaringBitmap>,
+    acl: Option<Vec<String>>,
     offset: usize,
     limit: usize,
     sort_criteria: Option<Vec<AscDesc>>,
+    decay: Option<Decay>,
     optional_words: bool,
+    min_word_match: Option<MinWordMatch>,
     authorize_typos: bool,
     words_limit: usize,
+    ignore_stop_words: bool,
+    external_ids: bool,
+    relaxation_hints: bool,
+    correction_threshold: Option<u64>,
+    similar_to: Option<DocumentId>,
+    tokenizer: Option<&'a dyn Tokenize>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
@@ -51,12 +95,22 @@ impl<'a> Search<'a> {
         Search {
             query: None,
             filter: None,
+            candidates: None,
+            acl: None,
             offset: 0,
-            limit: 20,
+            limit: DEFAULT_SEARCH_LIMIT,
             sort_criteria: None,
+            decay: None,
             optional_words: true,
+            min_word_match: None,
             authorize_typos: true,
             words_limit: 10,
+            ignore_stop_words: true,
+            external_ids: false,
+            relaxation_hints: false,
+            correction_threshold: None,
+            similar_to: None,
+            tokenizer: None,
             rtxn,
             index,
         }
@@ -82,11 +136,25 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Ranks the documents by the closeness of a numeric field to an origin, applied at the
+    /// position of the sort ranking rule, after the sort criteria.
+    pub fn decay(&mut self, decay: Decay) -> &mut Search<'a> {
+        self.decay = Some(decay);
+        self
+    }
+
     pub fn optional_words(&mut self, value: bool) -> &mut Search<'a> {
         self.optional_words = value;
         self
     }
 
+    /// The minimum number of query words, or fraction of the query words, that the documents
+    /// must contain when the words are optional, by default a single word is enough.
+    pub fn min_word_match(&mut self, value: MinWordMatch) -> &mut Search<'a> {
+        self.min_word_match = Some(value);
+        self
+    }
+
     pub fn authorize_typos(&mut self, value: bool) -> &mut Search<'a> {
         self.authorize_typos = value;
         self
@@ -97,31 +165,119 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// When set to `false` the stop words of the index are searched like any other word
+    /// instead of being dropped from the query, default value if not called: `true`.
+    pub fn ignore_stop_words(&mut self, value: bool) -> &mut Search<'a> {
+        self.ignore_stop_words = value;
+        self
+    }
+
     pub fn filter(&mut self, condition: Filter<'a>) -> &mut Search<'a> {
         self.filter = Some(condition);
         self
     }
 
+    /// Restricts the search to these internal documents ids, intersected with the filter
+    /// before the criteria run, e.g. the documents a user is allowed to see.
+    pub fn candidates(&mut self, candidates: RoaringBitmap) -> &mut Search<'a> {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    /// Restricts the search to the documents whose `_acl` field contains at least one
+    /// of these access control tokens, the documents without an `_acl` field are excluded.
+    pub fn with_acl<S: AsRef<str>>(&mut self, tokens: &[S]) -> &mut Search<'a> {
+        self.acl = Some(tokens.iter().map(|token| token.as_ref().to_string()).collect());
+        self
+    }
+
+    /// Also returns the external ids of the documents, the values of their primary key.
+    pub fn external_ids(&mut self, value: bool) -> &mut Search<'a> {
+        self.external_ids = value;
+        self
+    }
+
+    /// When the search returns no document, computes the filter conditions and the query word
+    /// whose removal would have returned some, see [`SearchResult::relaxation_hints`].
+    pub fn relaxation_hints(&mut self, value: bool) -> &mut Search<'a> {
+        self.relaxation_hints = value;
+        self
+    }
+
+    /// When the search returns fewer candidates than the threshold, also executes it with the
+    /// best "did you mean" correction of the query, see [`SearchResult::corrected`].
+    pub fn correction_threshold(&mut self, threshold: u64) -> &mut Search<'a> {
+        self.correction_threshold = Some(threshold);
+        self
+    }
+
+    /// Searches for the documents sharing the most rare words with this one, weighted by their
+    /// inverse document frequency, instead of the query. The document itself is not returned.
+    pub fn similar_to(&mut self, docid: DocumentId) -> &mut Search<'a> {
+        self.similar_to = Some(docid);
+        self
+    }
+
+    /// The custom tokenizer used to split the query, it must be the one used at indexing time.
+    pub fn tokenizer(&mut self, tokenizer: &'a dyn Tokenize) -> &mut Search<'a> {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
     pub fn execute(&self) -> Result<SearchResult> {
+        let span = self.index.span(self.rtxn, "search");
+        let _entered = span.enter();
+
+        if let Some(docid) = self.similar_to {
+            return self.execute_similar_to(docid);
+        }
+
         // We create the query tree by spliting the query into tokens.
         let before = Instant::now();
+        let mut original_words = HashMap::new();
         let (query_tree, primitive_query) = match self.query.as_ref() {
             Some(query) => {
                 let mut builder = QueryTreeBuilder::new(self.rtxn, self.index);
                 builder.optional_words(self.optional_words);
+                if let Some(min_word_match) = self.min_word_match {
+                    builder.min_word_match(min_word_match);
+                }
                 builder.authorize_typos(self.authorize_typos);
                 builder.words_limit(self.words_limit);
+                builder.ignore_stop_words(self.ignore_stop_words);
                 // We make sure that the analyzer is aware of the stop words
                 // this ensures that the query builder is able to properly remove them.
                 let mut config = AnalyzerConfig::default();
-                let stop_words = self.index.stop_words(self.rtxn)?;
+                let stop_words = self.stop_words()?;
                 if let Some(ref stop_words) = stop_words {
                     config.stop_words(stop_words);
                 }
                 let analyzer = Analyzer::new(config);
-                let result = analyzer.analyze(query);
-                let tokens = result.tokens();
-                builder.build(tokens)?.map_or((None, None), |(qt, pq)| (Some(qt), Some(pq)))
+                let result;
+                let tokens = match self.tokenizer {
+                    Some(tokenizer) => tokenizer.tokenize(query),
+                    None => {
+                        result = analyzer.analyze(query);
+                        result.tokens().collect()
+                    }
+                };
+                let tokenizer_settings = TokenizerSettings::from_index(self.index, self.rtxn)?;
+                let searchable_fields = self.index.searchable_fields_ids(self.rtxn)?;
+                let tokens = tokenizer_settings.retokenize_query(
+                    searchable_fields.as_deref(),
+                    query,
+                    tokens,
+                );
+                let tokens = mark_stop_words(tokens, stop_words.as_ref());
+                // The exactness criterion ranks the words as written in the query.
+                for token in tokens.iter().filter(|token| token.is_word()) {
+                    if let Some(original) = query.get(token.byte_start..token.byte_end) {
+                        original_words.insert(token.word.to_string(), original.to_lowercase());
+                    }
+                }
+                builder
+                    .build(tokens.into_iter())?
+                    .map_or((None, None), |(qt, pq)| (Some(qt), Some(pq)))
             }
             None => (None, None),
         };
@@ -130,9 +286,13 @@ impl<'a> Search<'a> {
 
         // We create the original candidates with the facet conditions results.
         let before = Instant::now();
-        let filtered_candidates = match &self.filter {
-            Some(condition) => Some(condition.evaluate(self.rtxn, self.index)?),
-            None => None,
+        let filtered_candidates = match (&self.filter, self.allowed_candidates()?) {
+            (Some(condition), Some(candidates)) => {
+                Some(condition.evaluate(self.rtxn, self.index)? & candidates)
+            }
+            (Some(condition), None) => Some(condition.evaluate(self.rtxn, self.index)?),
+            (None, Some(candidates)) => Some(candidates),
+            (None, None) => None,
         };
 
         debug!("facet candidates: {:?} took {:.02?}", filtered_candidates, before.elapsed());
@@ -165,30 +325,44 @@ impl<'a> Search<'a> {
             }
         }
 
+        if let Some(decay) = &self.decay {
+            let sortable_fields = self.index.sortable_fields(self.rtxn)?;
+            if !sortable_fields.contains(&decay.field) {
+                return Err(UserError::InvalidSortableAttribute {
+                    field: decay.field.clone(),
+                    valid_fields: sortable_fields.into_iter().collect(),
+                })?;
+            }
+        }
+
         // We check that the sort ranking rule exists and throw an
         // error if we try to use it and that it doesn't.
         let sort_ranking_rule_missing = !self.index.criteria(self.rtxn)?.contains(&Criterion::Sort);
-        let empty_sort_criteria = self.sort_criteria.as_ref().map_or(true, |s| s.is_empty());
+        let empty_sort_criteria =
+            self.sort_criteria.as_ref().map_or(true, |s| s.is_empty()) && self.decay.is_none();
         if sort_ranking_rule_missing && !empty_sort_criteria {
             return Err(UserError::SortRankingRuleMissing.into());
         }
 
+        let query_parts = primitive_query.clone().unwrap_or_default();
         let criteria_builder = criteria::CriteriaBuilder::new(self.rtxn, self.index)?;
         let criteria = criteria_builder.build(
             query_tree,
             primitive_query,
+            original_words,
             filtered_candidates,
             self.sort_criteria.clone(),
+            self.decay.clone(),
         )?;
 
         match self.index.distinct_field(self.rtxn)? {
-            None => self.perform_sort(NoopDistinct, matching_words, criteria),
+            None => self.perform_sort(NoopDistinct, matching_words, &query_parts, criteria),
             Some(name) => {
                 let field_ids_map = self.index.fields_ids_map(self.rtxn)?;
                 match field_ids_map.id(name) {
                     Some(fid) => {
                         let distinct = FacetDistinct::new(fid, self.index, self.rtxn);
-                        self.perform_sort(distinct, matching_words, criteria)
+                        self.perform_sort(distinct, matching_words, &query_parts, criteria)
                     }
                     None => Ok(SearchResult::default()),
                 }
@@ -196,18 +370,50 @@ impl<'a> Search<'a> {
         }
     }
 
+    /// Ranks the documents by the sum of the inverse document frequencies of the rarest words
+    /// they share with the reference document, the query and the criteria are ignored.
+    fn execute_similar_to(&self, docid: DocumentId) -> Result<SearchResult> {
+        let mut candidates = match &self.filter {
+            Some(condition) => condition.evaluate(self.rtxn, self.index)?,
+            None => self.index.documents_ids(self.rtxn)?,
+        };
+        if let Some(allowed_candidates) = self.allowed_candidates()? {
+            candidates &= allowed_candidates;
+        }
+        candidates.remove(docid);
+
+        let max_terms = self.words_limit;
+        let scores = shared_terms_scores(self.rtxn, self.index, docid, max_terms, &candidates)?;
+        let candidates = scores.iter().map(|(docid, _)| *docid).collect();
+        let documents_ids: Vec<_> = sort_scores(scores, self.offset, self.limit)
+            .into_iter()
+            .map(|(docid, _score)| docid)
+            .collect();
+
+        let external_ids = if self.external_ids {
+            let external_ids = self.index.external_ids(self.rtxn, documents_ids.iter().copied())?;
+            external_ids.into_iter().map(String::from).collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(SearchResult { candidates, documents_ids, external_ids, ..Default::default() })
+    }
+
     fn perform_sort<D: Distinct>(
         &self,
         mut distinct: D,
         matching_words: MatchingWords,
+        query_parts: &[PrimitiveQueryPart],
         mut criteria: Final,
     ) -> Result<SearchResult> {
         let mut offset = self.offset;
         let mut initial_candidates = RoaringBitmap::new();
         let mut excluded_candidates = RoaringBitmap::new();
         let mut documents_ids = Vec::new();
+        let mut matched_words = Vec::new();
 
-        while let Some(FinalResult { candidates, bucket_candidates, .. }) =
+        while let Some(FinalResult { query_tree, candidates, bucket_candidates }) =
             criteria.next(&excluded_candidates)?
         {
             debug!("Number of candidates found {}", candidates.len());
@@ -223,8 +429,12 @@ impl<'a> Search<'a> {
                 offset = offset.saturating_sub(discarded);
             }
 
+            // The query tree of the bucket only contains the words kept by the words criterion.
+            let bucket_matched_words =
+                query_tree.as_ref().map_or(0, |qt| matched_parts_count(qt, query_parts));
             for candidate in candidates.by_ref().take(self.limit - documents_ids.len()) {
                 documents_ids.push(candidate?);
+                matched_words.push(bucket_matched_words);
             }
             if documents_ids.len() == self.limit {
                 break;
@@ -232,7 +442,196 @@ impl<'a> Search<'a> {
             excluded_candidates = candidates.into_excluded();
         }
 
-        Ok(SearchResult { matching_words, candidates: initial_candidates, documents_ids })
+        let external_ids = if self.external_ids {
+            let external_ids = self.index.external_ids(self.rtxn, documents_ids.iter().copied())?;
+            external_ids.into_iter().map(String::from).collect()
+        } else {
+            Vec::new()
+        };
+
+        let relaxation_hints = if self.relaxation_hints && initial_candidates.is_empty() {
+            self.compute_relaxation_hints()?
+        } else {
+            Vec::new()
+        };
+
+        let corrected = match self.correction_threshold {
+            Some(threshold) if initial_candidates.len() < threshold => {
+                self.execute_corrected()?.map(Box::new)
+            }
+            _ => None,
+        };
+
+        Ok(SearchResult {
+            matching_words,
+            candidates: initial_candidates,
+            documents_ids,
+            matched_words,
+            query_words: query_parts.len(),
+            external_ids,
+            relaxation_hints,
+            corrected,
+        })
+    }
+
+    /// Executes this search with the best correction of the query given by [`Suggest`],
+    /// `None` when the query can't be corrected.
+    fn execute_corrected(&self) -> Result<Option<CorrectedSearchResult>> {
+        let query = match &self.query {
+            Some(query) => query,
+            None => return Ok(None),
+        };
+
+        let mut suggest = Suggest::new(self.rtxn, self.index, query.as_str());
+        suggest.limit(1);
+        let suggestion = match suggest.execute()?.into_iter().next() {
+            Some(suggestion) => suggestion,
+            None => return Ok(None),
+        };
+
+        let mut search = self.relaxed();
+        search.query = Some(suggestion.query.clone());
+        search.offset = self.offset;
+        search.external_ids = self.external_ids;
+        let result = search.execute()?;
+
+        Ok(Some(CorrectedSearchResult { query: suggestion.query, typos: suggestion.typos, result }))
+    }
+
+    /// Executes this search again without each one of the conditions of the top-level
+    /// conjunction of the filter, then without the least frequent word of the query,
+    /// and returns the relaxations that return some documents.
+    fn compute_relaxation_hints(&self) -> Result<Vec<RelaxationHint>> {
+        let mut hints = Vec::new();
+
+        if let Some(filter) = &self.filter {
+            for (attributes, filter) in filter.relaxations() {
+                let mut search = self.relaxed();
+                search.filter = filter;
+                let candidates_count = search.execute()?.candidates.len();
+                if candidates_count != 0 {
+                    hints.push(RelaxationHint::RemoveFilter { attributes, candidates_count });
+                }
+            }
+        }
+
+        if let Some(query) = &self.query {
+            if let Some((word, query)) = self.drop_least_frequent_word(query)? {
+                let mut search = self.relaxed();
+                search.query = Some(query.clone());
+                let candidates_count = search.execute()?.candidates.len();
+                if candidates_count != 0 {
+                    hints.push(RelaxationHint::DropWord { word, query, candidates_count });
+                }
+            }
+        }
+
+        Ok(hints)
+    }
+
+    /// Returns the word of the query contained in the fewest documents and the query without it,
+    /// `None` when the query doesn't contain at least two words.
+    fn drop_least_frequent_word(&self, query: &str) -> Result<Option<(String, String)>> {
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.stop_words()?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+        let result;
+        let tokens = match self.tokenizer {
+            Some(tokenizer) => tokenizer.tokenize(query),
+            None => {
+                result = analyzer.analyze(query);
+                result.tokens().collect()
+            }
+        };
+        let tokens = mark_stop_words(tokens, stop_words.as_ref());
+
+        let words: Vec<_> = tokens.iter().filter(|token| token.is_word()).collect();
+        if words.len() < 2 {
+            return Ok(None);
+        }
+
+        let word_docids_len = self.index.word_docids.remap_data_type::<RoaringBitmapLenCodec>();
+        let mut least_frequent = None;
+        for token in words {
+            let count = word_docids_len.get(self.rtxn, token.text())?.unwrap_or(0);
+            if least_frequent.as_ref().map_or(true, |(min, _)| count < *min) {
+                least_frequent = Some((count, token));
+            }
+        }
+
+        Ok(least_frequent.map(|(_, token)| {
+            let before = query[..token.byte_start].split_whitespace();
+            let after = query[token.byte_end..].split_whitespace();
+            let query = before.chain(after).collect::<Vec<_>>().join(" ");
+            (token.text().to_string(), query)
+        }))
+    }
+
+    /// Returns the stop words removed from the query, `None` when they are searched.
+    fn stop_words(&self) -> Result<Option<fst::Set<&'a [u8]>>> {
+        if self.ignore_stop_words {
+            self.index.stop_words(self.rtxn)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the documents the search is restricted to, the intersection of the given
+    /// candidates and of the documents matching the access control tokens, if any.
+    fn allowed_candidates(&self) -> Result<Option<RoaringBitmap>> {
+        let acl_candidates = match &self.acl {
+            Some(tokens) => {
+                let mut acl_candidates = RoaringBitmap::new();
+                for token in tokens {
+                    if let Some(docids) = self.index.acl_token_docids.get(self.rtxn, token)? {
+                        acl_candidates |= docids;
+                    }
+                }
+                Some(acl_candidates)
+            }
+            None => None,
+        };
+
+        // The given candidates can contain ids that are not, or no longer, used by documents.
+        let candidates = match &self.candidates {
+            Some(candidates) => Some(self.index.documents_ids(self.rtxn)? & candidates),
+            None => None,
+        };
+
+        match (candidates, acl_candidates) {
+            (Some(candidates), Some(acl_candidates)) => Ok(Some(acl_candidates & candidates)),
+            (candidates, None) => Ok(candidates),
+            (None, acl_candidates) => Ok(acl_candidates),
+        }
+    }
+
+    /// Returns a copy of this search that doesn't compute relaxation hints.
+    fn relaxed(&self) -> Search<'a> {
+        Search {
+            query: self.query.clone(),
+            filter: self.filter.clone(),
+            candidates: self.candidates.clone(),
+            acl: self.acl.clone(),
+            offset: 0,
+            limit: self.limit,
+            sort_criteria: self.sort_criteria.clone(),
+            decay: self.decay.clone(),
+            optional_words: self.optional_words,
+            min_word_match: self.min_word_match,
+            authorize_typos: self.authorize_typos,
+            words_limit: self.words_limit,
+            ignore_stop_words: self.ignore_stop_words,
+            external_ids: false,
+            relaxation_hints: false,
+            correction_threshold: None,
+            similar_to: self.similar_to,
+            tokenizer: self.tokenizer,
+            rtxn: self.rtxn,
+            index: self.index,
+        }
     }
 }
 
@@ -241,24 +640,44 @@ impl fmt::Debug for Search<'_> {
         let Search {
             query,
             filter,
+            candidates,
+            acl,
             offset,
             limit,
             sort_criteria,
+            decay,
             optional_words,
+            min_word_match,
             authorize_typos,
             words_limit,
+            ignore_stop_words,
+            external_ids,
+            relaxation_hints,
+            correction_threshold,
+            similar_to,
+            tokenizer,
             rtxn: _,
             index: _,
         } = self;
         f.debug_struct("Search")
             .field("query", query)
             .field("filter", filter)
+            .field("candidates", candidates)
+            .field("acl", acl)
             .field("offset", offset)
             .field("limit", limit)
             .field("sort_criteria", sort_criteria)
+            .field("decay", decay)
             .field("optional_words", optional_words)
+            .field("min_word_match", min_word_match)
             .field("authorize_typos", authorize_typos)
             .field("words_limit", words_limit)
+            .field("ignore_stop_words", ignore_stop_words)
+            .field("external_ids", external_ids)
+            .field("relaxation_hints", relaxation_hints)
+            .field("correction_threshold", correction_threshold)
+            .field("similar_to", similar_to)
+            .field("tokenizer", tokenizer)
             .finish()
     }
 }
@@ -269,6 +688,38 @@ pub struct SearchResult {
     pub candidates: RoaringBitmap,
     // TODO those documents ids should be associated with their criteria scores.
     pub documents_ids: Vec<DocumentId>,
+    /// The number of words of the query matched by the documents, in the same order as the
+    /// documents ids, it is the bucket of the words criterion the documents were found in.
+    pub matched_words: Vec<usize>,
+    /// The number of words of the query, a phrase counts as a single word.
+    pub query_words: usize,
+    /// The external ids of the documents, in the same order as the documents ids,
+    /// only filled when asked for with [`Search::external_ids`].
+    pub external_ids: Vec<String>,
+    /// The relaxations of the search that would have returned some documents,
+    /// only filled when the search returned none and [`Search::relaxation_hints`] is set.
+    pub relaxation_hints: Vec<RelaxationHint>,
+    /// The search executed with the corrected query, only filled when the search returned
+    /// fewer candidates than [`Search::correction_threshold`] and the query could be corrected.
+    pub corrected: Option<Box<CorrectedSearchResult>>,
+}
+
+/// The result of a search executed with the "did you mean" correction of its query.
+pub struct CorrectedSearchResult {
+    /// The corrected query, its words are separated by spaces.
+    pub query: String,
+    /// The total number of typos corrected in the query.
+    pub typos: u8,
+    pub result: SearchResult,
+}
+
+/// A change to a search that returned no document which makes it return some.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelaxationHint {
+    /// Removing the filter condition on these attributes.
+    RemoveFilter { attributes: Vec<String>, candidates_count: u64 },
+    /// Removing the least frequent word of the query, leading to this query.
+    DropWord { word: String, query: String, candidates_count: u64 },
 }
 
 pub type WordDerivationsCache = HashMap<(String, bool, u8), Vec<(String, u8)>>;
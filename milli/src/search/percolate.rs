@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use super::Filter;
+use crate::{DocumentId, Index, Result};
+
+/// A query saved in the index, the documents are matched against it
+/// when they are indexed to notify the users interested in them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredQuery {
+    /// The words that the documents must all contain.
+    pub query: String,
+    /// The filter that the documents must match.
+    pub filter: Option<String>,
+}
+
+impl StoredQuery {
+    pub fn new(query: impl Into<String>) -> StoredQuery {
+        StoredQuery { query: query.into(), filter: None }
+    }
+
+    pub fn with_filter(query: impl Into<String>, filter: impl Into<String>) -> StoredQuery {
+        StoredQuery { query: query.into(), filter: Some(filter.into()) }
+    }
+}
+
+/// Finds the stored queries matched by some documents of the index, the reverse of a search.
+///
+/// A document matches a stored query when it contains all the words
+/// of the query, without typos, and matches its filter.
+pub struct Percolate<'a> {
+    documents_ids: RoaringBitmap,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> Percolate<'a> {
+    pub fn new(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        documents_ids: RoaringBitmap,
+    ) -> Percolate<'a> {
+        Percolate { documents_ids, rtxn, index }
+    }
+
+    /// Returns the ids of the stored queries matched by each document,
+    /// the documents that don't match any query are not returned.
+    pub fn execute(&self) -> Result<BTreeMap<DocumentId, Vec<String>>> {
+        let mut matched_queries = BTreeMap::new();
+        if self.documents_ids.is_empty() {
+            return Ok(matched_queries);
+        }
+
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+
+        for (id, stored_query) in self.index.stored_queries(self.rtxn)? {
+            let mut candidates = self.documents_ids.clone();
+
+            let analyzed = analyzer.analyze(&stored_query.query);
+            for token in analyzed.tokens().filter(|token| token.is_word()) {
+                match self.index.word_docids.get(self.rtxn, token.text())? {
+                    Some(docids) => candidates &= docids,
+                    None => candidates.clear(),
+                }
+                if candidates.is_empty() {
+                    break;
+                }
+            }
+
+            if let Some(filter) = stored_query.filter.as_deref() {
+                if !candidates.is_empty() {
+                    if let Some(filter) = Filter::from_str(filter)? {
+                        candidates &= filter.evaluate(self.rtxn, self.index)?;
+                    }
+                }
+            }
+
+            for docid in candidates {
+                matched_queries.entry(docid).or_insert_with(Vec::new).push(id.clone());
+            }
+        }
+
+        Ok(matched_queries)
+    }
+}
+
+impl fmt::Debug for Percolate<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Percolate { documents_ids, rtxn: _, index: _ } = self;
+        f.debug_struct("Percolate").field("documents_ids", documents_ids).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::{btreemap, hashset};
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    #[test]
+    fn matched_queries_at_indexing_time() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let cheap_bikes = StoredQuery::with_filter("bike", "price < 100");
+        index.put_stored_query(&mut wtxn, "cheap-bikes", &cheap_bikes).unwrap();
+        index.put_stored_query(&mut wtxn, "red-bikes", &StoredQuery::new("red bike")).unwrap();
+        index.put_stored_query(&mut wtxn, "cars", &StoredQuery::new("car")).unwrap();
+        let invalid = StoredQuery::with_filter("", "price <");
+        assert!(index.put_stored_query(&mut wtxn, "invalid", &invalid).is_err());
+
+        let content = documents!([
+            { "id": "a", "title": "a red bike", "price": 80 },
+            { "id": "b", "title": "a blue bike", "price": 250 },
+            { "id": "c", "title": "a red boat", "price": 50 }
+        ]);
+        let indexing_config = IndexDocumentsConfig { percolate: true, ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        let result = builder.execute().unwrap();
+
+        let expected = btreemap! {
+            S("a") => vec![S("cheap-bikes"), S("red-bikes")],
+        };
+        assert_eq!(result.matched_queries, expected);
+
+        // The queries are only matched against the documents of the update.
+        assert!(index.delete_stored_query(&mut wtxn, "cheap-bikes").unwrap());
+        let content = documents!([ { "id": "d", "title": "a bike and a car", "price": 10 } ]);
+        let indexing_config = IndexDocumentsConfig { percolate: true, ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        let result = builder.execute().unwrap();
+
+        let expected = btreemap! { S("d") => vec![S("cars")] };
+        assert_eq!(result.matched_queries, expected);
+        wtxn.commit().unwrap();
+    }
+}
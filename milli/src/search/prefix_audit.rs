@@ -0,0 +1,212 @@
+use std::{fmt, str};
+
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Streamer};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use roaring::RoaringBitmap;
+
+use crate::{Index, Result, TokenizerSettings};
+
+/// Verifies that the documents ids read from the prefix databases for the words of a query
+/// are the union of the documents ids of the words starting with these prefixes, the
+/// discrepancies are the symptom of stale prefix databases that rank the documents wrongly.
+///
+/// The prefixes are recomputed from the words databases, this is a debugging tool that
+/// must not be used on every search.
+pub struct PrefixAudit<'a> {
+    query: String,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> PrefixAudit<'a> {
+    pub fn new(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        query: impl Into<String>,
+    ) -> PrefixAudit<'a> {
+        PrefixAudit { query: query.into(), rtxn, index }
+    }
+
+    /// Returns the discrepancies found for the query words that are in the prefix databases,
+    /// an empty list means that the prefix databases are consistent for this query.
+    pub fn execute(&self) -> Result<Vec<PrefixDiscrepancy>> {
+        let query_words = self.query_words()?;
+        let words_fst = self.index.words_fst(self.rtxn)?;
+        let prefixes_fst = self.index.words_prefixes_fst(self.rtxn)?;
+        let caps = self.index.prefix_pair_proximity_caps(self.rtxn)?;
+
+        let mut discrepancies = Vec::new();
+        for (i, prefix) in query_words.iter().enumerate() {
+            if !prefixes_fst.contains(prefix) {
+                continue;
+            }
+
+            let mut words = Vec::new();
+            let mut stream = words_fst.search(Str::new(prefix).starts_with()).into_stream();
+            while let Some(word) = stream.next() {
+                // A word that isn't valid UTF-8 means that the words FST itself is corrupted.
+                words.push(str::from_utf8(word)?.to_string());
+            }
+
+            let stored = self.index.word_prefix_docids.get(self.rtxn, prefix)?.unwrap_or_default();
+            let mut expected = RoaringBitmap::new();
+            for word in &words {
+                if let Some(docids) = self.index.word_docids.get(self.rtxn, word)? {
+                    expected |= docids;
+                }
+            }
+            discrepancies.extend(PrefixDiscrepancy::new(
+                "word-prefix-docids",
+                prefix.clone(),
+                stored,
+                expected,
+            ));
+
+            // The pairs are only stored with the previous word of the query on the left.
+            let left = match i.checked_sub(1).map(|i| &query_words[i]) {
+                Some(left) => left,
+                None => continue,
+            };
+
+            for proximity in 1..=caps.max_proximity {
+                if !caps.contains(prefix, proximity) {
+                    continue;
+                }
+
+                let key = (left.as_str(), prefix.as_str(), proximity);
+                let stored = self
+                    .index
+                    .word_prefix_pair_proximity_docids
+                    .get(self.rtxn, &key)?
+                    .unwrap_or_default();
+                let mut expected = RoaringBitmap::new();
+                for word in &words {
+                    let key = (left.as_str(), word.as_str(), proximity);
+                    if let Some(docids) =
+                        self.index.word_pair_proximity_docids.get(self.rtxn, &key)?
+                    {
+                        expected |= docids;
+                    }
+                }
+                discrepancies.extend(PrefixDiscrepancy::new(
+                    "word-prefix-pair-proximity-docids",
+                    format!("{} {} {}", left, prefix, proximity),
+                    stored,
+                    expected,
+                ));
+            }
+        }
+
+        Ok(discrepancies)
+    }
+
+    /// Returns the words of the query, normalized like the words of the documents.
+    fn query_words(&self) -> Result<Vec<String>> {
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+        let analyzed = analyzer.analyze(&self.query);
+        let tokenizer_settings = TokenizerSettings::from_index(self.index, self.rtxn)?;
+        let searchable_fields = self.index.searchable_fields_ids(self.rtxn)?;
+        let tokens = tokenizer_settings.retokenize_query(
+            searchable_fields.as_deref(),
+            &self.query,
+            analyzed.tokens(),
+        );
+
+        Ok(tokens.iter().filter(|t| t.is_word()).map(|t| t.text().to_string()).collect())
+    }
+}
+
+impl fmt::Debug for PrefixAudit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let PrefixAudit { query, rtxn: _, index: _ } = self;
+        f.debug_struct("PrefixAudit").field("query", query).finish()
+    }
+}
+
+/// An entry of a prefix database that doesn't match the words it is derived from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixDiscrepancy {
+    /// The name of the prefix database.
+    pub database: &'static str,
+    /// The key of the entry, the prefix or the words and proximity of a pair.
+    pub key: String,
+    /// The documents of the words that are missing from the prefix entry.
+    pub missing: RoaringBitmap,
+    /// The documents of the prefix entry that are not part of any of its words.
+    pub extra: RoaringBitmap,
+}
+
+impl PrefixDiscrepancy {
+    fn new(
+        database: &'static str,
+        key: String,
+        stored: RoaringBitmap,
+        expected: RoaringBitmap,
+    ) -> Option<PrefixDiscrepancy> {
+        let missing = &expected - &stored;
+        let extra = stored - expected;
+        if missing.is_empty() && extra.is_empty() {
+            None
+        } else {
+            Some(PrefixDiscrepancy { database, key, missing, extra })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn stale_prefix_databases() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // There must be enough words starting with "he" for it to be a prefix.
+        let words: Vec<_> =
+            ('a'..='z').flat_map(|a| ('a'..='b').map(move |b| format!("he{}{}", a, b))).collect();
+        let words = words.join(" ");
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "text": "world hello" },
+            { "id": 1, "text": "world help" },
+            { "id": 2, "text": words }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        assert!(PrefixAudit::new(&wtxn, &index, "world he").execute().unwrap().is_empty());
+
+        // We make the prefix databases stale.
+        let mut docids = index.word_prefix_docids.get(&wtxn, "he").unwrap().unwrap();
+        docids.remove(1);
+        docids.insert(5);
+        index.word_prefix_docids.put(&mut wtxn, "he", &docids).unwrap();
+        index.word_prefix_pair_proximity_docids.delete(&mut wtxn, &("world", "he", 1)).unwrap();
+
+        let discrepancies = PrefixAudit::new(&wtxn, &index, "world he").execute().unwrap();
+        assert_eq!(discrepancies.len(), 2);
+        assert_eq!(discrepancies[0].database, "word-prefix-docids");
+        assert_eq!(discrepancies[0].key, "he");
+        assert_eq!(discrepancies[0].missing.iter().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(discrepancies[0].extra.iter().collect::<Vec<_>>(), vec![5]);
+        assert_eq!(discrepancies[1].key, "world he 1");
+        assert_eq!(discrepancies[1].missing.iter().collect::<Vec<_>>(), vec![0, 1]);
+        assert!(discrepancies[1].extra.is_empty());
+    }
+}
@@ -1,9 +1,9 @@
+use std::collections::HashSet;
 use std::{cmp, fmt, mem};
 
 use fst::Set;
 use meilisearch_tokenizer::token::SeparatorKind;
-use meilisearch_tokenizer::tokenizer::TokenStream;
-use meilisearch_tokenizer::TokenKind;
+use meilisearch_tokenizer::{Token, TokenKind};
 use roaring::RoaringBitmap;
 use slice_group_by::GroupBy;
 
@@ -157,13 +157,38 @@ trait Context {
     }
 }
 
+/// The minimum number of words of a query that the documents must contain
+/// when the words are optional, the last words are the first to be dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinWordMatch {
+    /// A number of words, all the words are required if the query contains fewer words.
+    Count(usize),
+    /// A fraction of the words between `0.0` and `1.0`, rounded up.
+    Ratio(f64),
+}
+
+impl MinWordMatch {
+    /// Returns the minimum number of words required out of `words_count` words.
+    fn resolve(&self, words_count: usize) -> usize {
+        match *self {
+            MinWordMatch::Count(count) => count.min(words_count),
+            MinWordMatch::Ratio(ratio) => {
+                let ratio = if ratio.is_nan() { 0.0 } else { ratio.max(0.0).min(1.0) };
+                ((words_count as f64 * ratio).ceil() as usize).min(words_count)
+            }
+        }
+    }
+}
+
 /// The query tree builder is the interface to build a query tree.
 pub struct QueryTreeBuilder<'a> {
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
     optional_words: bool,
+    min_word_match: Option<MinWordMatch>,
     authorize_typos: bool,
     words_limit: Option<usize>,
+    ignore_stop_words: bool,
 }
 
 impl<'a> Context for QueryTreeBuilder<'a> {
@@ -184,7 +209,15 @@ impl<'a> QueryTreeBuilder<'a> {
     /// Create a `QueryTreeBuilder` from a heed ReadOnly transaction `rtxn`
     /// and an Index `index`.
     pub fn new(rtxn: &'a heed::RoTxn<'a>, index: &'a Index) -> Self {
-        Self { rtxn, index, optional_words: true, authorize_typos: true, words_limit: None }
+        Self {
+            rtxn,
+            index,
+            optional_words: true,
+            min_word_match: None,
+            authorize_typos: true,
+            words_limit: None,
+            ignore_stop_words: true,
+        }
     }
 
     /// if `optional_words` is set to `false` the query tree will be
@@ -197,6 +230,14 @@ impl<'a> QueryTreeBuilder<'a> {
         self
     }
 
+    /// When `optional_words` is set, the minimum number of query words
+    /// that must be present in each matching document.
+    /// default value if not called: a single word
+    pub fn min_word_match(&mut self, min_word_match: MinWordMatch) -> &mut Self {
+        self.min_word_match = Some(min_word_match);
+        self
+    }
+
     /// if `authorize_typos` is set to `false` the query tree will be generated
     /// forcing all query words to match documents without any typo
     /// (the criterion `typo` will be ignored).
@@ -214,6 +255,14 @@ impl<'a> QueryTreeBuilder<'a> {
         self
     }
 
+    /// if `ignore_stop_words` is set to `false` the stop words of the index
+    /// are kept in the query tree like any other word.
+    /// default value if not called: `true`
+    pub fn ignore_stop_words(&mut self, ignore_stop_words: bool) -> &mut Self {
+        self.ignore_stop_words = ignore_stop_words;
+        self
+    }
+
     /// Build the query tree:
     /// - if `optional_words` is set to `false` the query tree will be
     ///   generated forcing all query words to be present in each matching documents
@@ -221,13 +270,18 @@ impl<'a> QueryTreeBuilder<'a> {
     /// - if `authorize_typos` is set to `false` the query tree will be generated
     ///   forcing all query words to match documents without any typo
     ///   (the criterion `typo` will be ignored)
-    pub fn build(&self, query: TokenStream) -> Result<Option<(Operation, PrimitiveQuery)>> {
-        let stop_words = self.index.stop_words(self.rtxn)?;
+    pub fn build<'t>(
+        &self,
+        query: impl Iterator<Item = Token<'t>>,
+    ) -> Result<Option<(Operation, PrimitiveQuery)>> {
+        let stop_words =
+            if self.ignore_stop_words { self.index.stop_words(self.rtxn)? } else { None };
         let primitive_query = create_primitive_query(query, stop_words, self.words_limit);
         if !primitive_query.is_empty() {
             let qt = create_query_tree(
                 self,
                 self.optional_words,
+                self.min_word_match,
                 self.authorize_typos,
                 &primitive_query,
             )?;
@@ -297,6 +351,7 @@ fn synonyms(ctx: &impl Context, word: &[&str]) -> heed::Result<Option<Vec<Operat
 fn create_query_tree(
     ctx: &impl Context,
     optional_words: bool,
+    min_word_match: Option<MinWordMatch>,
     authorize_typos: bool,
     query: &[PrimitiveQueryPart],
 ) -> Result<Operation> {
@@ -383,16 +438,20 @@ fn create_query_tree(
         Ok(Operation::and(op_children))
     }
 
-    /// Create a new branch removing the last non-phrase query parts.
+    /// Create a new branch removing the last non-phrase query parts,
+    /// keeping at least the minimum number of words.
     fn optional_word(
         ctx: &impl Context,
+        min_word_match: Option<MinWordMatch>,
         authorize_typos: bool,
         query: PrimitiveQuery,
     ) -> Result<Operation> {
         let number_phrases = query.iter().filter(|p| p.is_phrase()).count();
+        let number_words = query.len() - number_phrases;
+        let min_words = min_word_match.map_or(0, |m| m.resolve(number_words));
         let mut operation_children = Vec::new();
 
-        let start = number_phrases + (number_phrases == 0) as usize;
+        let start = number_phrases + min_words.max((number_phrases == 0) as usize);
         for len in start..=query.len() {
             let mut word_count = len - number_phrases;
             let query: Vec<_> = query
@@ -418,7 +477,7 @@ fn create_query_tree(
     }
 
     if optional_words {
-        optional_word(ctx, authorize_typos, query.to_vec())
+        optional_word(ctx, min_word_match, authorize_typos, query.to_vec())
     } else {
         ngrams(ctx, authorize_typos, query)
     }
@@ -444,8 +503,8 @@ impl PrimitiveQueryPart {
 
 /// Create primitive query from tokenized query string,
 /// the primitive query is an intermediate state to build the query tree.
-fn create_primitive_query(
-    query: TokenStream,
+fn create_primitive_query<'t>(
+    query: impl Iterator<Item = Token<'t>>,
     stop_words: Option<Set<&[u8]>>,
     words_limit: Option<usize>,
 ) -> PrimitiveQuery {
@@ -529,11 +588,40 @@ pub fn maximum_proximity(operation: &Operation) -> usize {
     }
 }
 
+/// Returns the number of parts of the query that this Operation matches, the branches
+/// of the words criterion only keep the first words of the query.
+pub fn matched_parts_count(operation: &Operation, query: &[PrimitiveQueryPart]) -> usize {
+    fn collect_words<'o>(operation: &'o Operation, words: &mut HashSet<&'o str>) {
+        match operation {
+            Operation::And(ops) | Operation::Or(_, ops) => {
+                ops.iter().for_each(|op| collect_words(op, words))
+            }
+            Operation::Phrase(phrase) => words.extend(phrase.iter().map(String::as_str)),
+            Operation::Query(query) => {
+                words.insert(query.kind.word());
+            }
+        }
+    }
+
+    let mut words = HashSet::new();
+    collect_words(operation, &mut words);
+    query
+        .iter()
+        .filter(|part| match part {
+            PrimitiveQueryPart::Word(word, _) => words.contains(word.as_str()),
+            PrimitiveQueryPart::Phrase(phrase) => {
+                phrase.iter().all(|word| words.contains(word.as_str()))
+            }
+        })
+        .count()
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
 
     use maplit::hashmap;
+    use meilisearch_tokenizer::tokenizer::TokenStream;
     use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
     use rand::rngs::StdRng;
     use rand::{Rng, SeedableRng};
@@ -556,8 +644,13 @@ mod test {
         ) -> Result<Option<(Operation, PrimitiveQuery)>> {
             let primitive_query = create_primitive_query(query, None, words_limit);
             if !primitive_query.is_empty() {
-                let qt =
-                    create_query_tree(self, optional_words, authorize_typos, &primitive_query)?;
+                let qt = create_query_tree(
+                    self,
+                    optional_words,
+                    None,
+                    authorize_typos,
+                    &primitive_query,
+                )?;
                 Ok(Some((qt, primitive_query)))
             } else {
                 Ok(None)
@@ -973,6 +1066,27 @@ mod test {
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn matched_parts_of_optional_words() {
+        let query = "hello \"hey friends\" world";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let tokens = result.tokens();
+
+        let (query_tree, primitive_query) =
+            TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        assert_eq!(primitive_query.len(), 3);
+
+        let counts: Vec<_> = match query_tree {
+            Operation::Or(true, branches) => branches
+                .iter()
+                .map(|branch| matched_parts_count(branch, &primitive_query))
+                .collect(),
+            otherwise => panic!("expected optional words branches, got {:?}", otherwise),
+        };
+        assert_eq!(counts, vec![1, 2, 3]);
+    }
+
     #[test]
     fn optional_word() {
         let query = "hey my friend ";
@@ -1058,6 +1172,39 @@ mod test {
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn min_word_match() {
+        let query = "hey my friend ";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let context = TestContext::default();
+        let primitive_query = create_primitive_query(result.tokens(), None, None);
+
+        let all_branches = create_query_tree(&context, true, None, true, &primitive_query).unwrap();
+        let branches = match all_branches {
+            Operation::Or(true, branches) => branches,
+            operation => panic!("unexpected operation {:?}", operation),
+        };
+
+        // The branch containing a single word is dropped.
+        let expected = Operation::Or(true, branches[1..].to_vec());
+        for min_word_match in &[MinWordMatch::Count(2), MinWordMatch::Ratio(0.5)] {
+            let query_tree =
+                create_query_tree(&context, true, Some(*min_word_match), true, &primitive_query)
+                    .unwrap();
+            assert_eq!(expected, query_tree);
+        }
+
+        // All the words are required.
+        let expected = branches[2].clone();
+        for min_word_match in &[MinWordMatch::Count(5), MinWordMatch::Ratio(1.0)] {
+            let query_tree =
+                create_query_tree(&context, true, Some(*min_word_match), true, &primitive_query)
+                    .unwrap();
+            assert_eq!(expected, query_tree);
+        }
+    }
+
     #[test]
     fn optional_word_phrase() {
         let query = "\"hey my\"";
@@ -0,0 +1,299 @@
+/// How a number recognized in a query is compared to the values of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Lower,
+    LowerOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn operator(self) -> &'static str {
+        match self {
+            Comparison::Lower => "<",
+            Comparison::LowerOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+/// The values of a facet field that are recognized in the queries, e.g. the colors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetVocabulary {
+    pub field: String,
+    /// The values, of one or more words, matched case insensitively.
+    pub values: Vec<String>,
+    /// The words that can introduce a value and are removed with it, e.g. `in` for the cities.
+    pub introducers: Vec<String>,
+}
+
+impl FacetVocabulary {
+    pub fn new(
+        field: impl Into<String>,
+        values: impl IntoIterator<Item = impl Into<String>>,
+    ) -> FacetVocabulary {
+        FacetVocabulary {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+            introducers: Vec::new(),
+        }
+    }
+}
+
+/// A numeric field recognized in the queries by a keyword followed by a number,
+/// e.g. `under $50` for a price lower than 50 or `size 42` for a size of 42.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericVocabulary {
+    pub field: String,
+    /// The keywords, matched case insensitively, and how they compare the field to the number.
+    pub keywords: Vec<(String, Comparison)>,
+    /// The symbols written before or after the numbers, e.g. `$`.
+    pub units: Vec<String>,
+}
+
+/// A filter recognized in a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterpretedFilter {
+    pub field: String,
+    pub comparison: Comparison,
+    /// The words of the query the filter was recognized from, e.g. `under $50`.
+    pub matched: String,
+    /// The filter expression, e.g. `price < 50`.
+    pub expression: String,
+}
+
+/// The filters recognized in a query and the words that are left to search for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryInterpretation {
+    pub query: String,
+    pub filters: Vec<InterpretedFilter>,
+}
+
+impl QueryInterpretation {
+    /// Returns the expression of all the recognized filters, the equalities on the same field
+    /// are alternatives while all the other filters must match.
+    pub fn filter_expression(&self) -> Option<String> {
+        let mut groups: Vec<(&str, Comparison, Vec<&str>)> = Vec::new();
+        for filter in &self.filters {
+            let group = groups.iter_mut().find(|(field, comparison, _)| {
+                *field == filter.field
+                    && *comparison == Comparison::Equal
+                    && filter.comparison == Comparison::Equal
+            });
+            match group {
+                Some((_, _, alternatives)) => alternatives.push(&filter.expression),
+                None => groups.push((&filter.field, filter.comparison, vec![&filter.expression])),
+            }
+        }
+
+        let expressions: Vec<_> = groups
+            .into_iter()
+            .map(|(_, _, alternatives)| match alternatives.as_slice() {
+                [expression] => expression.to_string(),
+                alternatives => format!("({})", alternatives.join(" OR ")),
+            })
+            .collect();
+
+        if expressions.is_empty() {
+            None
+        } else {
+            Some(expressions.join(" AND "))
+        }
+    }
+}
+
+/// Recognizes the filters written in natural queries, e.g. `red shoes under $50 in Paris`,
+/// from the vocabularies declared for the facet fields.
+///
+/// The interpretation is meant to be confirmed by the user before the cleaned query and
+/// the filters are given to a search, nothing is recognized without a vocabulary.
+#[derive(Debug, Clone, Default)]
+pub struct QueryInterpreter {
+    facets: Vec<FacetVocabulary>,
+    numbers: Vec<NumericVocabulary>,
+}
+
+impl QueryInterpreter {
+    pub fn new() -> QueryInterpreter {
+        QueryInterpreter::default()
+    }
+
+    pub fn facet_vocabulary(&mut self, vocabulary: FacetVocabulary) -> &mut QueryInterpreter {
+        self.facets.push(vocabulary);
+        self
+    }
+
+    pub fn numeric_vocabulary(&mut self, vocabulary: NumericVocabulary) -> &mut QueryInterpreter {
+        self.numbers.push(vocabulary);
+        self
+    }
+
+    /// Removes the recognized filters from the query, the other words are kept in order.
+    pub fn interpret(&self, query: &str) -> QueryInterpretation {
+        let words: Vec<_> = query.split_whitespace().collect();
+        let mut kept = Vec::new();
+        let mut filters = Vec::new();
+
+        let mut i = 0;
+        while i < words.len() {
+            let words = &words[i..];
+            match self.numeric_filter(words).or_else(|| self.facet_filter(words)) {
+                Some((filter, consumed)) => {
+                    filters.push(filter);
+                    i += consumed;
+                }
+                None => {
+                    kept.push(words[0]);
+                    i += 1;
+                }
+            }
+        }
+
+        QueryInterpretation { query: kept.join(" "), filters }
+    }
+
+    /// Recognizes a keyword followed by a number at the start of the words.
+    fn numeric_filter(&self, words: &[&str]) -> Option<(InterpretedFilter, usize)> {
+        let keyword = normalize(words.first()?);
+        let number = words.get(1)?;
+
+        for vocabulary in &self.numbers {
+            let comparison =
+                match vocabulary.keywords.iter().find(|(k, _)| k.to_lowercase() == keyword) {
+                    Some((_, comparison)) => *comparison,
+                    None => continue,
+                };
+
+            if let Some(number) = parse_number(number, &vocabulary.units) {
+                let expression =
+                    format!("{} {} {}", vocabulary.field, comparison.operator(), number);
+                let filter = InterpretedFilter {
+                    field: vocabulary.field.clone(),
+                    comparison,
+                    matched: words[..2].join(" "),
+                    expression,
+                };
+                return Some((filter, 2));
+            }
+        }
+
+        None
+    }
+
+    /// Recognizes the longest value, optionally introduced, at the start of the words.
+    fn facet_filter(&self, words: &[&str]) -> Option<(InterpretedFilter, usize)> {
+        let mut best: Option<(&FacetVocabulary, &str, usize)> = None;
+        for vocabulary in &self.facets {
+            let introduced = words.first().map_or(false, |word| {
+                let word = normalize(word);
+                vocabulary.introducers.iter().any(|introducer| introducer.to_lowercase() == word)
+            });
+            let skipped = introduced as usize;
+
+            for value in &vocabulary.values {
+                let value_words: Vec<_> = value.split_whitespace().collect();
+                let rest = &words[skipped..];
+                if value_words.is_empty() || value_words.len() > rest.len() {
+                    continue;
+                }
+
+                let matches = value_words
+                    .iter()
+                    .zip(rest)
+                    .all(|(value_word, word)| value_word.to_lowercase() == normalize(word));
+                let consumed = skipped + value_words.len();
+                if matches && best.map_or(true, |(_, _, best)| consumed > best) {
+                    best = Some((vocabulary, value, consumed));
+                }
+            }
+        }
+
+        best.map(|(vocabulary, value, consumed)| {
+            let quote = if value.contains('"') { '\'' } else { '"' };
+            let expression = format!("{} = {}{}{}", vocabulary.field, quote, value, quote);
+            let filter = InterpretedFilter {
+                field: vocabulary.field.clone(),
+                comparison: Comparison::Equal,
+                matched: words[..consumed].join(" "),
+                expression,
+            };
+            (filter, consumed)
+        })
+    }
+}
+
+/// Lowercases a word of the query without the punctuation around it.
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// Parses a number of the query, with or without one of the units around it.
+fn parse_number(word: &str, units: &[String]) -> Option<f64> {
+    let mut word = word.trim_end_matches(|c: char| matches!(c, ',' | '.' | ';' | '!' | '?'));
+    for unit in units {
+        word = word.strip_prefix(unit.as_str()).unwrap_or(word);
+        word = word.strip_suffix(unit.as_str()).unwrap_or(word);
+    }
+    word.parse().ok().filter(|number: &f64| number.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+
+    use super::*;
+    use crate::Filter;
+
+    fn interpreter() -> QueryInterpreter {
+        let mut interpreter = QueryInterpreter::new();
+        let mut cities = FacetVocabulary::new("city", vec!["Paris", "New York"]);
+        cities.introducers = vec![S("in")];
+        interpreter.facet_vocabulary(cities);
+        interpreter.facet_vocabulary(FacetVocabulary::new("color", vec!["red", "blue"]));
+        interpreter.numeric_vocabulary(NumericVocabulary {
+            field: S("price"),
+            keywords: vec![(S("under"), Comparison::Lower), (S("over"), Comparison::Greater)],
+            units: vec![S("$")],
+        });
+        interpreter.numeric_vocabulary(NumericVocabulary {
+            field: S("size"),
+            keywords: vec![(S("size"), Comparison::Equal)],
+            units: Vec::new(),
+        });
+        interpreter
+    }
+
+    #[test]
+    fn filters_from_natural_queries() {
+        let interpreter = interpreter();
+
+        let interpretation = interpreter.interpret("Red shoes size 42 under $50 in New York");
+        assert_eq!(interpretation.query, "shoes");
+        let matched: Vec<_> = interpretation.filters.iter().map(|f| f.matched.as_str()).collect();
+        assert_eq!(matched, vec!["Red", "size 42", "under $50", "in New York"]);
+        let expression = interpretation.filter_expression().unwrap();
+        assert_eq!(
+            expression,
+            r#"color = "red" AND size = 42 AND price < 50 AND city = "New York""#
+        );
+        assert!(Filter::from_str(&expression).unwrap().is_some());
+
+        // The values of the same field are alternatives, the bounds must all match.
+        let interpretation = interpreter.interpret("red blue bike over 10 under 99.5");
+        assert_eq!(interpretation.query, "bike");
+        let expression = interpretation.filter_expression().unwrap();
+        assert_eq!(
+            expression,
+            r#"(color = "red" OR color = "blue") AND price > 10 AND price < 99.5"#
+        );
+        assert!(Filter::from_str(&expression).unwrap().is_some());
+
+        // An introducer or a keyword alone is kept in the query.
+        let interpretation = interpreter.interpret("made in france under the sea");
+        assert_eq!(interpretation.query, "made in france under the sea");
+        assert_eq!(interpretation.filter_expression(), None);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use heed::types::ByteSlice;
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{word_derivations, WordDerivationsCache};
+use crate::error::InternalError;
+use crate::{
+    distance_between_two_points, relative_from_absolute_position, DocumentId, FieldId, Index,
+    Result, TokenizerSettings, BEU32,
+};
+
+/// The proximity given to two query words that are too far apart or not in the same
+/// attribute, the same maximum proximity as the one of the proximity criterion.
+const MAX_PROXIMITY: u32 = 8;
+
+/// Computes the signals used by the ranking rules for a query and some documents, as one
+/// feature vector per document, to train learning-to-rank models on the engine signals.
+pub struct RankingFeatures<'a> {
+    query: String,
+    documents_ids: Vec<DocumentId>,
+    facets: Vec<String>,
+    geo_point: Option<[f64; 2]>,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> RankingFeatures<'a> {
+    pub fn new(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        query: impl Into<String>,
+        documents_ids: Vec<DocumentId>,
+    ) -> RankingFeatures<'a> {
+        RankingFeatures {
+            query: query.into(),
+            documents_ids,
+            facets: Vec::new(),
+            geo_point: None,
+            rtxn,
+            index,
+        }
+    }
+
+    /// The fields whose values are returned along with the features of the documents.
+    pub fn facets(&mut self, fields: Vec<String>) -> &mut RankingFeatures<'a> {
+        self.facets = fields;
+        self
+    }
+
+    /// The point from which the geo distance of the documents is computed.
+    pub fn geo_point(&mut self, point: [f64; 2]) -> &mut RankingFeatures<'a> {
+        self.geo_point = Some(point);
+        self
+    }
+
+    /// Returns the features of the documents in the order they were given,
+    /// the documents that don't exist in the index are ignored.
+    pub fn execute(&self) -> Result<Vec<DocumentFeatures>> {
+        let query_words = self.query_words()?;
+        let words_fst = self.index.words_fst(self.rtxn)?;
+        let mut cache = WordDerivationsCache::new();
+
+        // The searchable fields are ranked by their order, all of them have the same rank
+        // when there is no searchable fields defined.
+        let attributes_ranks: HashMap<FieldId, u16> =
+            match self.index.searchable_fields_ids(self.rtxn)? {
+                Some(fields) => fields.into_iter().zip(0..).collect(),
+                None => HashMap::new(),
+            };
+
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let geo_points = match self.geo_point {
+            Some(_) => self.documents_geo_points()?,
+            None => HashMap::new(),
+        };
+
+        let mut codec = self.index.documents_codec(self.rtxn)?;
+        let documents = self.index.documents.remap_data_type::<ByteSlice>();
+        let mut features = Vec::with_capacity(self.documents_ids.len());
+        for &docid in &self.documents_ids {
+            let document = match documents.get(self.rtxn, &BEU32::new(docid))? {
+                Some(bytes) => codec.decompress(bytes)?,
+                None => continue,
+            };
+
+            // The lowest number of typos and the positions of every query word in the document.
+            let mut matches = Vec::with_capacity(query_words.len());
+            for (i, word) in query_words.iter().enumerate() {
+                let is_prefix = i + 1 == query_words.len() && !self.query.ends_with(' ');
+                let derivations =
+                    word_derivations(word, is_prefix, max_typo(word), &words_fst, &mut cache)?;
+
+                let mut best: Option<(u8, bool, RoaringBitmap)> = None;
+                for (derived, typo) in derivations {
+                    let positions = match self
+                        .index
+                        .docid_word_positions
+                        .get(self.rtxn, &(docid, derived.as_str()))?
+                    {
+                        Some(positions) => positions,
+                        None => continue,
+                    };
+                    let exact = derived == word;
+                    match &mut best {
+                        Some((best_typo, best_exact, best_positions)) => {
+                            if (*typo, !exact) < (*best_typo, !*best_exact) {
+                                *best_typo = *typo;
+                                *best_exact = exact;
+                            }
+                            *best_positions |= positions;
+                        }
+                        None => best = Some((*typo, exact, positions)),
+                    }
+                }
+                matches.push(best);
+            }
+
+            let matched_words = matches.iter().flatten().count();
+            let typos = matches.iter().flatten().map(|(typo, _, _)| *typo as u32).sum();
+            let exact_words = matches.iter().flatten().filter(|(_, exact, _)| *exact).count();
+
+            let proximity = matches
+                .windows(2)
+                .map(|pair| match (&pair[0], &pair[1]) {
+                    (Some((_, _, left)), Some((_, _, right))) => min_proximity(left, right),
+                    _ => MAX_PROXIMITY,
+                })
+                .sum();
+
+            let attribute_rank = matches
+                .iter()
+                .flatten()
+                .flat_map(|(_, _, positions)| positions.iter())
+                .map(|position| {
+                    let (field_id, _) = relative_from_absolute_position(position);
+                    attributes_ranks.get(&field_id).copied().unwrap_or(0)
+                })
+                .min();
+
+            let mut facet_values = BTreeMap::new();
+            for name in &self.facets {
+                let value = fields_ids_map.id(name).and_then(|field_id| document.get(field_id));
+                if let Some(value) = value {
+                    let value = serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+                    facet_values.insert(name.clone(), value);
+                }
+            }
+
+            let geo_distance = match (self.geo_point, geo_points.get(&docid)) {
+                (Some(origin), Some(point)) => Some(distance_between_two_points(&origin, point)),
+                _ => None,
+            };
+
+            features.push(DocumentFeatures {
+                docid,
+                matched_words,
+                typos,
+                proximity,
+                attribute_rank,
+                exact_words,
+                exact_match: !query_words.is_empty() && exact_words == query_words.len(),
+                facet_values,
+                geo_distance,
+            });
+        }
+
+        Ok(features)
+    }
+
+    /// Returns the words of the query, normalized like the words of the documents.
+    fn query_words(&self) -> Result<Vec<String>> {
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+        let analyzed = analyzer.analyze(&self.query);
+        let tokenizer_settings = TokenizerSettings::from_index(self.index, self.rtxn)?;
+        let searchable_fields = self.index.searchable_fields_ids(self.rtxn)?;
+        let tokens = tokenizer_settings.retokenize_query(
+            searchable_fields.as_deref(),
+            &self.query,
+            analyzed.tokens(),
+        );
+
+        Ok(tokens.iter().filter(|t| t.is_word()).map(|t| t.text().to_string()).collect())
+    }
+
+    /// Returns the latitude and longitude of the documents that have a geo point.
+    fn documents_geo_points(&self) -> Result<HashMap<DocumentId, [f64; 2]>> {
+        let rtree = match self.index.geo_rtree(self.rtxn)? {
+            Some(rtree) => rtree,
+            None => return Ok(HashMap::new()),
+        };
+        Ok(rtree
+            .iter()
+            .map(|point| point.data)
+            .filter(|(docid, _)| self.documents_ids.contains(docid))
+            .collect())
+    }
+}
+
+impl fmt::Debug for RankingFeatures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let RankingFeatures { query, documents_ids, facets, geo_point, rtxn: _, index: _ } = self;
+        f.debug_struct("RankingFeatures")
+            .field("query", query)
+            .field("documents_ids", documents_ids)
+            .field("facets", facets)
+            .field("geo_point", geo_point)
+            .finish()
+    }
+}
+
+/// The features of a document for a query, see [`RankingFeatures`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentFeatures {
+    pub docid: DocumentId,
+    /// The number of query words contained in the document, with or without typos.
+    pub matched_words: usize,
+    /// The sum of the lowest number of typos of the matched query words.
+    pub typos: u32,
+    /// The sum of the proximities between the consecutive query words, a missing
+    /// word or two words too far apart count as the maximum proximity of 8.
+    pub proximity: u32,
+    /// The rank of the best searchable attribute containing a query word,
+    /// `None` when the document doesn't contain any query word.
+    pub attribute_rank: Option<u16>,
+    /// The number of query words contained as is in the document, without typo or prefix.
+    pub exact_words: usize,
+    /// Whether all the query words are contained as is in the document.
+    pub exact_match: bool,
+    /// The values of the requested fields, the missing fields are not returned.
+    pub facet_values: BTreeMap<String, Value>,
+    /// The distance in meters from the requested geo point, when the document has one.
+    pub geo_distance: Option<f64>,
+}
+
+fn max_typo(word: &str) -> u8 {
+    match word.chars().count() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+/// Returns the lowest proximity between two positions of the same attribute,
+/// a word before the other one counts as one more.
+fn min_proximity(left: &RoaringBitmap, right: &RoaringBitmap) -> u32 {
+    let mut best = MAX_PROXIMITY;
+    for lpos in left {
+        for rpos in right {
+            let (lfid, lpos) = relative_from_absolute_position(lpos);
+            let (rfid, rpos) = relative_from_absolute_position(rpos);
+            if lfid != rfid {
+                continue;
+            }
+            let proximity =
+                if lpos < rpos { (rpos - lpos) as u32 } else { (lpos - rpos) as u32 + 1 };
+            best = best.min(proximity);
+        }
+    }
+    best.min(MAX_PROXIMITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::btreemap;
+    use serde_json::json;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn ranking_features() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "the black dragon", "price": 10 },
+            { "id": 1, "title": "a dragon all black", "price": 20 },
+            { "id": 2, "title": "the blakc dragon" },
+            { "id": 3, "title": "nothing to see" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut builder = RankingFeatures::new(&rtxn, &index, "black dragon ", vec![0, 1, 2, 3]);
+        builder.facets(vec![S("price")]);
+        let features = builder.execute().unwrap();
+        assert_eq!(features.len(), 4);
+
+        assert_eq!(features[0].matched_words, 2);
+        assert_eq!(features[0].typos, 0);
+        assert_eq!(features[0].proximity, 1);
+        assert!(features[0].exact_match);
+        assert_eq!(features[0].attribute_rank, Some(0));
+        assert_eq!(features[0].facet_values, btreemap! { S("price") => json!(10) });
+
+        // The words are in the reverse order.
+        assert_eq!(features[1].proximity, 3);
+        assert!(features[1].exact_match);
+
+        // "blakc" is one typo away from "black".
+        assert_eq!(features[2].matched_words, 2);
+        assert_eq!(features[2].typos, 1);
+        assert_eq!(features[2].exact_words, 1);
+        assert!(!features[2].exact_match);
+        assert!(features[2].facet_values.is_empty());
+
+        assert_eq!(features[3].matched_words, 0);
+        assert_eq!(features[3].proximity, MAX_PROXIMITY);
+        assert_eq!(features[3].attribute_rank, None);
+        assert_eq!(features[3].geo_distance, None);
+    }
+}
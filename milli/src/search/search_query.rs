@@ -0,0 +1,284 @@
+use either::Either;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::matches::{DEFAULT_CROP_MARKER, DEFAULT_HIGHLIGHT_PREFIX, DEFAULT_HIGHLIGHT_SUFFIX};
+use super::{
+    FacetDistribution, Filter, FormatOptions, MatcherBuilder, MatchingWords, MinWordMatch, Search,
+};
+use crate::error::{FilterParseError, UserError};
+use crate::{AscDesc, Index, Result, SortError};
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+pub const DEFAULT_CROP_LENGTH: usize = 10;
+
+/// A search request as it is received by an API, converted into a [`Search`]
+/// with [`Search::from_query`], the missing options take the defaults of the [`Search`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct SearchQuery {
+    pub query: Option<String>,
+    /// A filter expression, or an array of expressions joined by `AND`
+    /// where the nested arrays of expressions are joined by `OR`.
+    pub filter: Option<Value>,
+    /// The sort criteria by order of importance, e.g. `price:asc`.
+    pub sort: Option<Vec<String>>,
+    /// The facets whose distribution is computed over the candidates.
+    pub facets: Option<Vec<String>>,
+    pub limit: usize,
+    pub offset: usize,
+    pub matching_strategy: MatchingStrategy,
+    pub highlight: HighlightOptions,
+}
+
+impl Default for SearchQuery {
+    fn default() -> SearchQuery {
+        SearchQuery {
+            query: None,
+            filter: None,
+            sort: None,
+            facets: None,
+            limit: DEFAULT_SEARCH_LIMIT,
+            offset: 0,
+            matching_strategy: MatchingStrategy::default(),
+            highlight: HighlightOptions::default(),
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Returns the distribution of the requested facets, the candidates of the search
+    /// must be given to it, or `None` when no facet is requested.
+    pub fn facet_distribution<'a>(
+        &self,
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+    ) -> Option<FacetDistribution<'a>> {
+        let facets = self.facets.as_ref()?;
+        let mut distribution = FacetDistribution::new(rtxn, index);
+        distribution.facets(facets);
+        Some(distribution)
+    }
+}
+
+/// How many words of the query the documents must contain.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchingStrategy {
+    /// The last words of the query are dropped until enough documents match.
+    Last,
+    /// All the words of the query.
+    All,
+    /// At least this number of words, see [`MinWordMatch::Count`].
+    Count(usize),
+    /// At least this fraction of the words, see [`MinWordMatch::Ratio`].
+    Ratio(f64),
+}
+
+impl Default for MatchingStrategy {
+    fn default() -> MatchingStrategy {
+        MatchingStrategy::Last
+    }
+}
+
+/// Which attributes of the returned documents are highlighted and cropped, and how.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct HighlightOptions {
+    /// The attributes whose matches are highlighted, `*` for all of them.
+    pub attributes_to_highlight: Vec<String>,
+    /// The attributes cropped around their matches, `*` for all of them.
+    pub attributes_to_crop: Vec<String>,
+    /// The number of words the cropped attributes are cropped to.
+    pub crop_length: usize,
+    pub crop_marker: String,
+    pub highlight_pre_tag: String,
+    pub highlight_post_tag: String,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> HighlightOptions {
+        HighlightOptions {
+            attributes_to_highlight: Vec::new(),
+            attributes_to_crop: Vec::new(),
+            crop_length: DEFAULT_CROP_LENGTH,
+            crop_marker: DEFAULT_CROP_MARKER.to_string(),
+            highlight_pre_tag: DEFAULT_HIGHLIGHT_PREFIX.to_string(),
+            highlight_post_tag: DEFAULT_HIGHLIGHT_SUFFIX.to_string(),
+        }
+    }
+}
+
+impl HighlightOptions {
+    /// Returns a matcher builder with the markers of these options.
+    pub fn matcher_builder(&self, matching_words: MatchingWords) -> MatcherBuilder {
+        let mut builder = MatcherBuilder::from_matching_words(matching_words);
+        builder.crop_marker(self.crop_marker.clone());
+        builder.highlight_prefix(self.highlight_pre_tag.clone());
+        builder.highlight_suffix(self.highlight_post_tag.clone());
+        builder
+    }
+
+    /// Returns how the given attribute must be formatted.
+    pub fn format_options(&self, attribute: &str) -> FormatOptions {
+        let contains =
+            |attributes: &[String]| attributes.iter().any(|a| a == "*" || a == attribute);
+        FormatOptions {
+            highlight: contains(&self.attributes_to_highlight),
+            crop: if contains(&self.attributes_to_crop) { Some(self.crop_length) } else { None },
+        }
+    }
+}
+
+impl<'a> Search<'a> {
+    /// Returns a search configured with the options of the query,
+    /// the filter and the sort criteria are parsed and validated.
+    pub fn from_query(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        query: &'a SearchQuery,
+    ) -> Result<Search<'a>> {
+        let mut search = Search::new(rtxn, index);
+        if let Some(text) = &query.query {
+            search.query(text.clone());
+        }
+        if let Some(filter) = query.filter.as_ref().map(parse_filter).transpose()?.flatten() {
+            search.filter(filter);
+        }
+        if let Some(sort) = &query.sort {
+            let criteria: std::result::Result<Vec<AscDesc>, _> =
+                sort.iter().map(|text| text.parse()).collect();
+            search.sort_criteria(criteria.map_err(SortError::from)?);
+        }
+        search.limit(query.limit);
+        search.offset(query.offset);
+        match query.matching_strategy {
+            MatchingStrategy::Last => search.optional_words(true),
+            MatchingStrategy::All => search.optional_words(false),
+            MatchingStrategy::Count(count) => {
+                search.optional_words(true).min_word_match(MinWordMatch::Count(count))
+            }
+            MatchingStrategy::Ratio(ratio) => {
+                search.optional_words(true).min_word_match(MinWordMatch::Ratio(ratio))
+            }
+        };
+        Ok(search)
+    }
+}
+
+fn parse_filter(filter: &Value) -> Result<Option<Filter>> {
+    match filter {
+        Value::String(expression) => Filter::from_str(expression),
+        Value::Array(values) => {
+            let mut array = Vec::with_capacity(values.len());
+            for value in values {
+                match value {
+                    Value::String(expression) => array.push(Either::Right(expression.as_str())),
+                    Value::Array(values) => {
+                        let ors: Option<Vec<_>> = values.iter().map(Value::as_str).collect();
+                        match ors {
+                            Some(ors) => array.push(Either::Left(ors)),
+                            None => return Err(invalid_filter(value)),
+                        }
+                    }
+                    value => return Err(invalid_filter(value)),
+                }
+            }
+            Filter::from_array(array)
+        }
+        Value::Null => Ok(None),
+        value => Err(invalid_filter(value)),
+    }
+}
+
+fn invalid_filter(value: &Value) -> crate::Error {
+    let message = format!(
+        "Invalid filter `{}`, expected a string or an array of strings and arrays of strings.",
+        value
+    );
+    UserError::InvalidFilter(FilterParseError::new(message)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    #[test]
+    fn deserialize_with_defaults() {
+        let query: SearchQuery = serde_json::from_str(r#"{ "query": "hello" }"#).unwrap();
+        assert_eq!(query.query, Some(S("hello")));
+        assert_eq!(query.limit, DEFAULT_SEARCH_LIMIT);
+        assert_eq!(query.matching_strategy, MatchingStrategy::Last);
+        assert_eq!(query.highlight.highlight_pre_tag, "<em>");
+
+        let query: SearchQuery = serde_json::from_str(
+            r#"{
+                "filter": ["color = red", ["size = 1", "size = 2"]],
+                "matchingStrategy": { "count": 2 },
+                "highlight": { "attributesToCrop": ["*"], "cropLength": 5 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(query.matching_strategy, MatchingStrategy::Count(2));
+        let options = query.highlight.format_options("title");
+        assert!(!options.highlight);
+        assert_eq!(options.crop, Some(5));
+
+        assert!(serde_json::from_str::<SearchQuery>(r#"{ "unknown": true }"#).is_err());
+    }
+
+    #[test]
+    fn search_from_query() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "red shoes", "color": "red", "price": 30 },
+            { "id": 1, "title": "blue shoes", "color": "blue", "price": 20 },
+            { "id": 2, "title": "red hat", "color": "red", "price": 10 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let query: SearchQuery = serde_json::from_str(
+            r#"{
+                "query": "shoes",
+                "filter": [["color = red", "color = green"]],
+                "sort": ["price:asc"],
+                "facets": ["color"]
+            }"#,
+        )
+        .unwrap();
+        let result = Search::from_query(&rtxn, &index, &query).unwrap().execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+
+        let query: SearchQuery =
+            serde_json::from_str(r#"{ "sort": ["price:asc"], "limit": 2, "offset": 1 }"#).unwrap();
+        let result = Search::from_query(&rtxn, &index, &query).unwrap().execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0]);
+        assert!(query.facet_distribution(&rtxn, &index).is_none());
+
+        let query = SearchQuery { filter: Some(Value::from(42)), ..Default::default() };
+        assert!(Search::from_query(&rtxn, &index, &query).is_err());
+        let query = SearchQuery { sort: Some(vec![S("price:up")]), ..Default::default() };
+        assert!(Search::from_query(&rtxn, &index, &query).is_err());
+    }
+}
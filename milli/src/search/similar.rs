@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use heed::types::ByteSlice;
+use roaring::RoaringBitmap;
+use serde_json::Value;
+
+use super::Filter;
+use crate::documents_compression::DocumentsCodec;
+use crate::error::{InternalError, UserError};
+use crate::{DocumentId, FieldId, Index, Result, BEU32};
+
+/// The maximum number of words of the reference document used to find the similar documents,
+/// only the rarest ones are kept.
+const DEFAULT_MAX_TERMS: usize = 25;
+
+/// How the similarity between two documents is computed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Similarity {
+    /// The documents sharing the most rare words with the reference document,
+    /// every shared word is weighted by its inverse document frequency.
+    SharedTerms,
+    /// The documents whose vector, an array of numbers stored under the given field,
+    /// is the closest to the one of the reference document by cosine similarity.
+    Vectors { field: String },
+}
+
+impl Default for Similarity {
+    fn default() -> Similarity {
+        Similarity::SharedTerms
+    }
+}
+
+/// Finds the documents that are the most similar to a reference document,
+/// the reference document itself is never returned.
+pub struct SimilarDocuments<'a> {
+    docid: DocumentId,
+    similarity: Similarity,
+    filter: Option<Filter<'a>>,
+    offset: usize,
+    limit: usize,
+    max_terms: usize,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> SimilarDocuments<'a> {
+    pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index, docid: DocumentId) -> SimilarDocuments<'a> {
+        SimilarDocuments {
+            docid,
+            similarity: Similarity::default(),
+            filter: None,
+            offset: 0,
+            limit: 20,
+            max_terms: DEFAULT_MAX_TERMS,
+            rtxn,
+            index,
+        }
+    }
+
+    /// Finds the documents similar to the document with this external id.
+    pub fn from_external_id(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        external_id: &str,
+    ) -> Result<SimilarDocuments<'a>> {
+        let external_documents_ids = index.external_documents_ids(rtxn)?;
+        let documents_ids = index.documents_ids(rtxn)?;
+        match external_documents_ids.get(external_id) {
+            Some(docid) if documents_ids.contains(docid) => {
+                Ok(SimilarDocuments::new(rtxn, index, docid))
+            }
+            _ => {
+                let document_id = external_id.to_string();
+                Err(UserError::UnknownExternalDocumentId { document_id }.into())
+            }
+        }
+    }
+
+    pub fn similarity(&mut self, similarity: Similarity) -> &mut SimilarDocuments<'a> {
+        self.similarity = similarity;
+        self
+    }
+
+    pub fn filter(&mut self, condition: Filter<'a>) -> &mut SimilarDocuments<'a> {
+        self.filter = Some(condition);
+        self
+    }
+
+    pub fn offset(&mut self, offset: usize) -> &mut SimilarDocuments<'a> {
+        self.offset = offset;
+        self
+    }
+
+    pub fn limit(&mut self, limit: usize) -> &mut SimilarDocuments<'a> {
+        self.limit = limit;
+        self
+    }
+
+    /// The number of the rarest words of the reference document that are used
+    /// with the [`Similarity::SharedTerms`] similarity, defaults to 25.
+    pub fn max_terms(&mut self, max_terms: usize) -> &mut SimilarDocuments<'a> {
+        self.max_terms = max_terms;
+        self
+    }
+
+    pub fn execute(&self) -> Result<SimilarDocumentsResult> {
+        let mut candidates = match &self.filter {
+            Some(condition) => condition.evaluate(self.rtxn, self.index)?,
+            None => self.index.documents_ids(self.rtxn)?,
+        };
+        candidates.remove(self.docid);
+
+        let scores = match &self.similarity {
+            Similarity::SharedTerms => self.shared_terms_scores(&candidates)?,
+            Similarity::Vectors { field } => {
+                match self.index.fields_ids_map(self.rtxn)?.id(field) {
+                    Some(field_id) => self.vectors_scores(field_id, &candidates)?,
+                    None => Vec::new(),
+                }
+            }
+        };
+
+        let (documents_ids, scores) =
+            sort_scores(scores, self.offset, self.limit).into_iter().unzip();
+        Ok(SimilarDocumentsResult { documents_ids, scores })
+    }
+
+    /// Scores the candidates by summing the inverse document frequencies of
+    /// the rarest words they share with the reference document.
+    fn shared_terms_scores(&self, candidates: &RoaringBitmap) -> Result<Vec<(DocumentId, f64)>> {
+        shared_terms_scores(self.rtxn, self.index, self.docid, self.max_terms, candidates)
+    }
+
+    /// Scores the candidates by the cosine similarity of their vectors
+    /// with the one of the reference document.
+    fn vectors_scores(
+        &self,
+        field_id: FieldId,
+        candidates: &RoaringBitmap,
+    ) -> Result<Vec<(DocumentId, f64)>> {
+        let mut codec = self.index.documents_codec(self.rtxn)?;
+        let reference = match self.document_vector(&mut codec, self.docid, field_id)? {
+            Some(vector) => vector,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut scores = Vec::new();
+        for docid in candidates {
+            if let Some(vector) = self.document_vector(&mut codec, docid, field_id)? {
+                if let Some(score) = cosine_similarity(&reference, &vector) {
+                    scores.push((docid, score));
+                }
+            }
+        }
+
+        Ok(scores)
+    }
+
+    /// Returns the vector stored under the given field of the document, if it is an array of numbers.
+    fn document_vector(
+        &self,
+        codec: &mut DocumentsCodec,
+        docid: DocumentId,
+        field_id: FieldId,
+    ) -> Result<Option<Vec<f64>>> {
+        let documents = self.index.documents.remap_data_type::<ByteSlice>();
+        let document = match documents.get(self.rtxn, &BEU32::new(docid))? {
+            Some(bytes) => codec.decompress(bytes)?,
+            None => return Ok(None),
+        };
+
+        match document.get(field_id) {
+            Some(value) => {
+                let value: Value =
+                    serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+                let vector = match value {
+                    Value::Array(values) => values.iter().map(Value::as_f64).collect(),
+                    _ => None,
+                };
+                Ok(vector)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for SimilarDocuments<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let SimilarDocuments {
+            docid,
+            similarity,
+            filter,
+            offset,
+            limit,
+            max_terms,
+            rtxn: _,
+            index: _,
+        } = self;
+        f.debug_struct("SimilarDocuments")
+            .field("docid", docid)
+            .field("similarity", similarity)
+            .field("filter", filter)
+            .field("offset", offset)
+            .field("limit", limit)
+            .field("max_terms", max_terms)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SimilarDocumentsResult {
+    /// The similar documents, the most similar first.
+    pub documents_ids: Vec<DocumentId>,
+    /// The similarity scores of the documents, in the same order as the documents ids.
+    pub scores: Vec<f64>,
+}
+
+/// Scores the candidates by summing the inverse document frequencies of
+/// the `max_terms` rarest words they share with the reference document.
+pub(crate) fn shared_terms_scores(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    docid: DocumentId,
+    max_terms: usize,
+    candidates: &RoaringBitmap,
+) -> Result<Vec<(DocumentId, f64)>> {
+    let number_of_documents = index.number_of_documents(rtxn)? as f64;
+
+    let mut words = Vec::new();
+    for result in index.docid_word_positions.prefix_iter(rtxn, &(docid, ""))? {
+        let ((_docid, word), _positions) = result?;
+        if let Some(docids) = index.word_docids.get(rtxn, word)? {
+            // The words contained in every document don't tell anything.
+            let idf = (number_of_documents / docids.len() as f64).ln();
+            if idf > 0.0 {
+                words.push((idf, docids));
+            }
+        }
+    }
+
+    // We only keep the rarest words, the ones with the highest inverse document frequency.
+    words.sort_unstable_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    words.truncate(max_terms);
+
+    let mut scores = HashMap::new();
+    for (idf, docids) in words {
+        for docid in docids & candidates {
+            *scores.entry(docid).or_insert(0.0) += idf;
+        }
+    }
+
+    Ok(scores.into_iter().collect())
+}
+
+/// Sorts the scores by decreasing score then by increasing document id and paginates them.
+pub(crate) fn sort_scores(
+    mut scores: Vec<(DocumentId, f64)>,
+    offset: usize,
+    limit: usize,
+) -> Vec<(DocumentId, f64)> {
+    scores.sort_unstable_by(|(da, a), (db, b)| {
+        b.partial_cmp(a).unwrap_or(Ordering::Equal).then_with(|| da.cmp(db))
+    });
+    scores.into_iter().skip(offset).take(limit).collect()
+}
+
+fn cosine_similarity(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let dot: f64 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        None
+    } else {
+        Some(dot / (norm_a * norm_b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    fn index_with_documents() -> (tempfile::TempDir, Index) {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genre") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the dragon and the wizard", "genre": "fantasy", "v": [1.0, 0.0] },
+            { "id": 1, "title": "the wizard of the dragon mountain", "genre": "fantasy", "v": [0.9, 0.1] },
+            { "id": 2, "title": "the dragon", "genre": "history", "v": [0.0, 1.0] },
+            { "id": 3, "title": "the cooking book", "genre": "fantasy", "v": [0.5, 0.5] }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        (path, index)
+    }
+
+    fn external_ids(
+        index: &Index,
+        rtxn: &heed::RoTxn,
+        result: &SimilarDocumentsResult,
+    ) -> Vec<String> {
+        let external_ids = index.external_ids(rtxn, result.documents_ids.iter().copied()).unwrap();
+        external_ids.into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn shared_terms() {
+        let (_path, index) = index_with_documents();
+        let rtxn = index.read_txn().unwrap();
+
+        let similar = SimilarDocuments::from_external_id(&rtxn, &index, "0").unwrap();
+        let result = similar.execute().unwrap();
+        // "wizard" is rarer than "dragon" and "the" is everywhere.
+        assert_eq!(external_ids(&index, &rtxn, &result), vec![S("1"), S("2")]);
+
+        let mut similar = SimilarDocuments::from_external_id(&rtxn, &index, "0").unwrap();
+        similar.filter(Filter::from_str("genre = history").unwrap().unwrap());
+        let result = similar.execute().unwrap();
+        assert_eq!(external_ids(&index, &rtxn, &result), vec![S("2")]);
+
+        let error = SimilarDocuments::from_external_id(&rtxn, &index, "42").unwrap_err();
+        assert!(error.to_string().contains("42"));
+    }
+
+    #[test]
+    fn search_similar_to() {
+        let (_path, index) = index_with_documents();
+        let rtxn = index.read_txn().unwrap();
+        let docid = index.external_documents_ids(&rtxn).unwrap().get("0").unwrap();
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.similar_to(docid).external_ids(true);
+        let result = search.execute().unwrap();
+        // The document itself is excluded and the one sharing the rarest words comes first.
+        assert_eq!(result.external_ids, vec![S("1"), S("2")]);
+
+        search.filter(Filter::from_str("genre = history").unwrap().unwrap());
+        let result = search.execute().unwrap();
+        assert_eq!(result.external_ids, vec![S("2")]);
+    }
+
+    #[test]
+    fn vectors() {
+        let (_path, index) = index_with_documents();
+        let rtxn = index.read_txn().unwrap();
+
+        let mut similar = SimilarDocuments::from_external_id(&rtxn, &index, "0").unwrap();
+        similar.similarity(Similarity::Vectors { field: S("v") });
+        let result = similar.execute().unwrap();
+        assert_eq!(external_ids(&index, &rtxn, &result), vec![S("1"), S("3"), S("2")]);
+        assert!(result.scores.windows(2).all(|w| w[0] >= w[1]));
+
+        similar.limit(1).offset(1);
+        let result = similar.execute().unwrap();
+        assert_eq!(external_ids(&index, &rtxn, &result), vec![S("3")]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::cmp::Reverse;
+use std::fmt;
+
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use roaring::RoaringBitmap;
+
+use super::{word_derivations, WordDerivationsCache};
+use crate::{Index, Result, TokenizerSettings};
+
+/// The maximum number of corrections considered for every word of the query.
+const MAX_CORRECTIONS_PER_WORD: usize = 5;
+
+/// Builds corrected versions of a query, the "did you mean" of a search, from the words
+/// of the index that are one or two typos away from the words of the query.
+///
+/// The suggestions are ranked by the number of documents containing all of their words,
+/// no search is executed to compute them.
+pub struct Suggest<'a> {
+    query: String,
+    limit: usize,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> Suggest<'a> {
+    pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index, query: impl Into<String>) -> Suggest<'a> {
+        Suggest { query: query.into(), limit: 5, rtxn, index }
+    }
+
+    pub fn limit(&mut self, limit: usize) -> &mut Suggest<'a> {
+        self.limit = limit;
+        self
+    }
+
+    pub fn execute(&self) -> Result<Vec<Suggestion>> {
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+        let analyzed = analyzer.analyze(&self.query);
+        let tokenizer_settings = TokenizerSettings::from_index(self.index, self.rtxn)?;
+        let searchable_fields = self.index.searchable_fields_ids(self.rtxn)?;
+        let tokens = tokenizer_settings.retokenize_query(
+            searchable_fields.as_deref(),
+            &self.query,
+            analyzed.tokens(),
+        );
+
+        let words_fst = self.index.words_fst(self.rtxn)?;
+        let mut cache = WordDerivationsCache::new();
+        let mut beam = vec![Candidate::default()];
+        let beam_width = self.limit.saturating_mul(4).max(1);
+
+        for token in tokens.iter().filter(|t| t.is_word() || t.is_stopword()) {
+            let word = token.text();
+
+            // The stop words are not corrected and are ignored when counting the documents.
+            if token.is_stopword() {
+                beam.iter_mut().for_each(|candidate| candidate.words.push(word.to_string()));
+                continue;
+            }
+
+            let max_typo = match word.chars().count() {
+                0..=4 => 0,
+                5..=8 => 1,
+                _ => 2,
+            };
+
+            let mut corrections = Vec::new();
+            let derivations = word_derivations(word, false, max_typo, &words_fst, &mut cache)?;
+            for (derived, typo) in derivations {
+                if let Some(docids) = self.index.word_docids.get(self.rtxn, derived)? {
+                    corrections.push((derived.clone(), *typo, docids));
+                }
+            }
+            corrections.sort_unstable_by_key(|(_, typo, docids)| (*typo, Reverse(docids.len())));
+            corrections.truncate(MAX_CORRECTIONS_PER_WORD);
+
+            let mut next_beam = Vec::new();
+            for candidate in &beam {
+                for (derived, typo, docids) in &corrections {
+                    let docids = match &candidate.docids {
+                        Some(candidate_docids) => candidate_docids & docids,
+                        None => docids.clone(),
+                    };
+                    if docids.is_empty() {
+                        continue;
+                    }
+
+                    let mut words = candidate.words.clone();
+                    words.push(derived.clone());
+                    let typos = candidate.typos + typo;
+                    next_beam.push(Candidate { words, typos, docids: Some(docids) });
+                }
+            }
+
+            next_beam.sort_unstable_by_key(|c| (Reverse(c.documents_count()), c.typos));
+            next_beam.truncate(beam_width);
+            beam = next_beam;
+        }
+
+        // The query itself is not a suggestion.
+        let suggestions = beam
+            .into_iter()
+            .filter(|candidate| candidate.typos != 0)
+            .take(self.limit)
+            .map(|candidate| Suggestion {
+                query: candidate.words.join(" "),
+                typos: candidate.typos,
+                documents_count: candidate.documents_count(),
+            })
+            .collect();
+
+        Ok(suggestions)
+    }
+}
+
+impl fmt::Debug for Suggest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Suggest { query, limit, rtxn: _, index: _ } = self;
+        f.debug_struct("Suggest").field("query", query).field("limit", limit).finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The corrected query, its words are separated by spaces.
+    pub query: String,
+    /// The total number of typos corrected in the query.
+    pub typos: u8,
+    /// The number of documents containing all the words of the corrected query.
+    pub documents_count: u64,
+}
+
+#[derive(Default)]
+struct Candidate {
+    words: Vec<String>,
+    typos: u8,
+    /// The documents containing all the words, `None` while no word has been seen.
+    docids: Option<RoaringBitmap>,
+}
+
+impl Candidate {
+    fn documents_count(&self) -> u64 {
+        self.docids.as_ref().map_or(0, RoaringBitmap::len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn suggestions() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "the hobbit and the dragon" },
+            { "id": 1, "title": "the dragon rider" },
+            { "id": 2, "title": "dragons everywhere" },
+            { "id": 3, "title": "the hobbit" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let suggestions = Suggest::new(&rtxn, &index, "drgon").execute().unwrap();
+        let queries: Vec<_> = suggestions.iter().map(|s| s.query.as_str()).collect();
+        // "dragons" is two typos away from "drgon".
+        assert_eq!(queries, vec!["dragon"]);
+        assert_eq!(suggestions[0].typos, 1);
+        assert_eq!(suggestions[0].documents_count, 2);
+
+        let suggestions = Suggest::new(&rtxn, &index, "hobit dragon").execute().unwrap();
+        let queries: Vec<_> = suggestions.iter().map(|s| s.query.as_str()).collect();
+        assert_eq!(queries, vec!["hobbit dragon"]);
+
+        // Nothing to correct.
+        let suggestions = Suggest::new(&rtxn, &index, "the dragon").execute().unwrap();
+        assert!(suggestions.is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use std::marker::PhantomData;
+
+use bstr::ByteSlice as _;
+use heed::types::ByteSlice;
+use heed::BytesDecode;
+use tracing::warn;
+
+/// An iterator over the entries of a database that skips the entries that can't be decoded,
+/// it logs their keys and counts them instead of returning an error, so that a single bad
+/// entry doesn't make the whole database unreadable when exporting or computing statistics.
+///
+/// The errors of LMDB itself are still returned.
+pub struct SkipCorrupted<'t, KC, DC> {
+    iter: heed::RoIter<'t, ByteSlice, ByteSlice>,
+    db_name: &'static str,
+    skipped: u64,
+    _marker: PhantomData<(KC, DC)>,
+}
+
+impl<'t, KC, DC> SkipCorrupted<'t, KC, DC> {
+    /// Wraps an iterator over the raw entries of a database, the entries are decoded with
+    /// `KC` and `DC`, the iterator is given by `remap_types::<ByteSlice, ByteSlice>().iter()`.
+    pub fn new(
+        db_name: &'static str,
+        iter: heed::RoIter<'t, ByteSlice, ByteSlice>,
+    ) -> SkipCorrupted<'t, KC, DC> {
+        SkipCorrupted { iter, db_name, skipped: 0, _marker: PhantomData }
+    }
+
+    /// Returns the number of entries skipped so far.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl<'t, KC, DC> Iterator for SkipCorrupted<'t, KC, DC>
+where
+    KC: BytesDecode<'t>,
+    DC: BytesDecode<'t>,
+{
+    type Item = heed::Result<(KC::DItem, DC::DItem)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, data) = match self.iter.next()? {
+                Ok(entry) => entry,
+                Err(error) => return Some(Err(error)),
+            };
+            match (KC::bytes_decode(key), DC::bytes_decode(data)) {
+                (Some(key), Some(data)) => return Some(Ok((key, data))),
+                _ => {
+                    self.skipped += 1;
+                    warn!(
+                        "Skipping a corrupted entry of the {} database with the key {:?}",
+                        self.db_name,
+                        key.as_bstr()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::types::Str;
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::index::db_name;
+    use crate::{Index, RoaringBitmapCodec};
+
+    #[test]
+    fn skip_corrupted_entries() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let raw = index.word_docids.remap_types::<ByteSlice, ByteSlice>();
+        index.word_docids.put(&mut wtxn, "hello", &(0..3).collect()).unwrap();
+        raw.put(&mut wtxn, b"corrupted", b"not a bitmap").unwrap();
+        index.word_docids.put(&mut wtxn, "world", &(2..4).collect()).unwrap();
+
+        let mut iter = SkipCorrupted::<Str, RoaringBitmapCodec>::new(
+            db_name::WORD_DOCIDS,
+            raw.iter(&wtxn).unwrap(),
+        );
+        let words: Vec<_> = iter.by_ref().map(|result| result.unwrap().0).collect();
+        assert_eq!(words, vec!["hello", "world"]);
+        assert_eq!(iter.skipped(), 1);
+    }
+}
@@ -0,0 +1,815 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::{fmt, mem};
+
+use heed::RoTxn;
+use meilisearch_tokenizer::token::SeparatorKind;
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, Token, TokenKind};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::UserError;
+use crate::{FieldId, Index, Result};
+
+/// Splits a text into tokens, a custom tokenizer can be given to the
+/// [`IndexerConfig`](crate::update::IndexerConfig) and to the [`Search`](crate::Search)
+/// to replace the built-in analyzer, the same one must be given to both.
+pub trait Tokenize: Send + Sync {
+    /// Returns the normalized tokens of the text, separators included,
+    /// the bytes ranges of the tokens must refer to the given text.
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Token<'t>>;
+}
+
+impl<'a> fmt::Debug for dyn Tokenize + 'a {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Tokenize")
+    }
+}
+
+/// The built-in tokenizer, it uses the meilisearch analyzer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultTokenizer;
+
+thread_local! {
+    /// The analyzer of the default tokenizer, it is built once by thread.
+    static DEFAULT_ANALYZER: Analyzer<'static, Vec<u8>> =
+        Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+}
+
+impl Tokenize for DefaultTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Token<'t>> {
+        DEFAULT_ANALYZER.with(|analyzer| {
+            let analyzed = analyzer.analyze(text);
+            analyzed
+                .tokens()
+                .map(|token| Token {
+                    kind: token.kind,
+                    word: Cow::Owned(token.word.into_owned()),
+                    char_index: token.char_index,
+                    byte_start: token.byte_start,
+                    byte_end: token.byte_end,
+                    char_map: token.char_map,
+                })
+                .collect()
+        })
+    }
+}
+
+/// Marks the words of the tokens that are stop words, whatever the tokenizer
+/// that produced them, the built-in analyzer already marks them itself.
+pub(crate) fn mark_stop_words<'t, A: AsRef<[u8]>>(
+    tokens: Vec<Token<'t>>,
+    stop_words: Option<&fst::Set<A>>,
+) -> Vec<Token<'t>> {
+    let stop_words = match stop_words {
+        Some(stop_words) => stop_words,
+        None => return tokens,
+    };
+
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if token.is_word() && stop_words.contains(token.text()) {
+                token.kind = TokenKind::StopWord;
+            }
+            token
+        })
+        .collect()
+}
+
+/// The protected patterns and the dictionary of an index compiled into regexes, the clones
+/// of an index share them and they are compiled again when its settings generation changes.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CompiledPatternsCache {
+    compiled: Arc<Mutex<Option<CompiledPatterns>>>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledPatterns {
+    generation: u64,
+    protected_patterns: Vec<Regex>,
+    dictionary: Option<Regex>,
+}
+
+impl CompiledPatternsCache {
+    /// Forgets the compiled regexes, it must be called when the settings generation is
+    /// incremented, a write transaction that is aborted can have compiled the next one.
+    pub(crate) fn clear(&self) {
+        *self.compiled.lock().unwrap() = None;
+    }
+
+    /// Returns the compiled protected patterns and dictionary of the index,
+    /// they are only compiled when the settings generation changed.
+    fn get_or_compile(&self, index: &Index, rtxn: &RoTxn) -> Result<(Vec<Regex>, Option<Regex>)> {
+        let generation = index.settings_generation(rtxn)?;
+        if let Some(compiled) = self.compiled.lock().unwrap().as_ref() {
+            if compiled.generation == generation {
+                return Ok((compiled.protected_patterns.clone(), compiled.dictionary.clone()));
+            }
+        }
+
+        let protected_patterns = match index.protected_patterns(rtxn)? {
+            Some(patterns) => {
+                patterns.iter().map(ProtectedPattern::to_regex).collect::<Result<_>>()?
+            }
+            None => Vec::new(),
+        };
+        let dictionary =
+            index.dictionary(rtxn)?.and_then(|words| TokenizerSettings::dictionary_regex(&words));
+
+        let compiled = CompiledPatterns {
+            generation,
+            protected_patterns: protected_patterns.clone(),
+            dictionary: dictionary.clone(),
+        };
+        *self.compiled.lock().unwrap() = Some(compiled);
+
+        Ok((protected_patterns, dictionary))
+    }
+}
+
+/// A pattern whose matches are indexed and searched as single words, e.g. `GTX-1080`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProtectedPattern {
+    /// A string that is matched case-insensitively.
+    Literal(String),
+    /// A regular expression.
+    Regex(String),
+}
+
+impl ProtectedPattern {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ProtectedPattern::Literal(literal) => literal,
+            ProtectedPattern::Regex(regex) => regex,
+        }
+    }
+
+    /// Compiles the pattern, returns an error if the regular expression is invalid.
+    pub fn to_regex(&self) -> Result<Regex> {
+        let result = match self {
+            ProtectedPattern::Literal(literal) => {
+                Regex::new(&format!("(?i){}", regex::escape(literal)))
+            }
+            ProtectedPattern::Regex(regex) => Regex::new(regex),
+        };
+
+        result.map_err(|e| {
+            UserError::InvalidProtectedPattern {
+                pattern: self.as_str().to_string(),
+                message: e.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// The way the emoji found in the documents and the queries are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EmojiPolicy {
+    /// The emoji are searchable tokens, as any other word.
+    Index,
+    /// The emoji are removed, they are considered as separators.
+    Strip,
+    /// The emoji are replaced by the words of their name, e.g. 🙂 by "slightly smiling face".
+    Name,
+}
+
+impl Default for EmojiPolicy {
+    fn default() -> Self {
+        EmojiPolicy::Index
+    }
+}
+
+/// The settings of an index that change the way the texts are split into tokens,
+/// they are applied on the tokens produced by the analyzer, in the same way for
+/// the documents fields and for the queries.
+#[derive(Debug, Default, Clone)]
+pub struct TokenizerSettings {
+    /// Additional strings that separate the words, e.g. `&`.
+    pub separator_tokens: BTreeSet<String>,
+    /// Strings that are kept in the words instead of separating them, e.g. `+` or `#`.
+    pub non_separator_tokens: BTreeSet<String>,
+    /// The patterns whose matches are kept as single words, separators included.
+    pub protected_patterns: Vec<Regex>,
+    /// The words of the dictionary, compiled with [`TokenizerSettings::dictionary_regex`],
+    /// they are kept whole when they start and end on the boundaries of the tokens.
+    pub dictionary: Option<Regex>,
+    /// Whether the emoji are indexed, stripped or replaced by their names.
+    pub emoji_policy: EmojiPolicy,
+    /// The fields in which the camelCase and snake_case identifiers are split into sub-words.
+    pub split_identifiers_fields: HashSet<FieldId>,
+}
+
+impl TokenizerSettings {
+    /// Reads the tokenizer settings of the index, the regexes are compiled once
+    /// by settings generation and shared by the searches and the indexing.
+    pub fn from_index(index: &Index, rtxn: &RoTxn) -> Result<Self> {
+        let (protected_patterns, dictionary) =
+            index.compiled_patterns.get_or_compile(index, rtxn)?;
+
+        Ok(TokenizerSettings {
+            separator_tokens: index.separator_tokens(rtxn)?.unwrap_or_default(),
+            non_separator_tokens: index.non_separator_tokens(rtxn)?.unwrap_or_default(),
+            protected_patterns,
+            dictionary,
+            emoji_policy: index.emoji_policy(rtxn)?,
+            split_identifiers_fields: index.split_identifiers_fields_ids(rtxn)?,
+        })
+    }
+
+    /// Compiles the words of the dictionary into a case-insensitive regex,
+    /// the longest words are tried first.
+    pub fn dictionary_regex<S: AsRef<str>>(words: &BTreeSet<S>) -> Option<Regex> {
+        let mut words: Vec<_> = words.iter().map(AsRef::as_ref).filter(|w| !w.is_empty()).collect();
+        if words.is_empty() {
+            return None;
+        }
+
+        words.sort_unstable_by_key(|word| Reverse(word.len()));
+        let alternation = words.into_iter().map(regex::escape).collect::<Vec<_>>().join("|");
+        // The words are escaped, the regex is always valid.
+        Regex::new(&format!("(?i)(?:{})", alternation)).ok()
+    }
+
+    /// Returns `true` if the tokens produced by the analyzer are kept as is.
+    pub fn is_noop(&self) -> bool {
+        self.separator_tokens.is_empty()
+            && self.non_separator_tokens.is_empty()
+            && self.protected_patterns.is_empty()
+            && self.dictionary.is_none()
+            && self.emoji_policy == EmojiPolicy::Index
+    }
+
+    /// Splits and merges the tokens produced by the analyzer for the given text,
+    /// according to the protected patterns, the customized separators and the emoji policy.
+    pub fn retokenize<'t>(
+        &self,
+        text: &'t str,
+        tokens: impl IntoIterator<Item = Token<'t>>,
+    ) -> Vec<Token<'t>> {
+        if self.is_noop() {
+            return tokens.into_iter().collect();
+        }
+
+        let mut output = Vec::new();
+        for (token, protected) in self.protect(text, tokens) {
+            if protected {
+                output.push((token, false));
+            } else if token.is_word() {
+                self.split_word(text, token, &mut output);
+            } else if token.is_separator().is_some() {
+                self.split_separator(text, token, &mut output);
+            } else {
+                output.push((token, false));
+            }
+        }
+
+        let output = merge_glued_words(output);
+        match self.emoji_policy {
+            EmojiPolicy::Index => output,
+            policy => apply_emoji_policy(text, output, policy),
+        }
+    }
+
+    /// Retokenizes the text of a field, the identifiers are split if the field enables it.
+    pub fn retokenize_field<'t>(
+        &self,
+        field_id: FieldId,
+        text: &'t str,
+        tokens: impl IntoIterator<Item = Token<'t>>,
+    ) -> Vec<Token<'t>> {
+        let tokens = self.retokenize(text, tokens);
+        if self.split_identifiers_fields.contains(&field_id) {
+            split_identifiers(text, tokens)
+        } else {
+            tokens
+        }
+    }
+
+    /// Retokenizes a query, the identifiers are split if one of the searchable fields
+    /// enables it, all the fields are searchable when `searchable_fields` is `None`.
+    pub fn retokenize_query<'t>(
+        &self,
+        searchable_fields: Option<&[FieldId]>,
+        text: &'t str,
+        tokens: impl IntoIterator<Item = Token<'t>>,
+    ) -> Vec<Token<'t>> {
+        let tokens = self.retokenize(text, tokens);
+        let split = match searchable_fields {
+            Some(fields) => fields.iter().any(|fid| self.split_identifiers_fields.contains(fid)),
+            None => !self.split_identifiers_fields.is_empty(),
+        };
+
+        if split {
+            split_identifiers(text, tokens)
+        } else {
+            tokens
+        }
+    }
+
+    /// Merges the tokens that overlap a match of the protected patterns or a word of the
+    /// dictionary into single words, the merged words are marked as protected.
+    fn protect<'t>(
+        &self,
+        text: &'t str,
+        tokens: impl IntoIterator<Item = Token<'t>>,
+    ) -> Vec<(Token<'t>, bool)> {
+        let tokens: Vec<_> = tokens.into_iter().collect();
+        let mut ranges = self.protected_ranges(text, &tokens).into_iter().peekable();
+        let mut output = Vec::new();
+        let mut group = Vec::new();
+        for token in tokens {
+            while ranges.peek().map_or(false, |range| range.end <= token.byte_start) {
+                if !group.is_empty() {
+                    output.push((merge_tokens(mem::take(&mut group)), true));
+                }
+                ranges.next();
+            }
+
+            match ranges.peek() {
+                Some(range) if range.start < token.byte_end => group.push(token),
+                _ => output.push((token, false)),
+            }
+        }
+        if !group.is_empty() {
+            output.push((merge_tokens(group), true));
+        }
+
+        output
+    }
+
+    /// Returns the non-overlapping bytes ranges matched by the protected patterns and
+    /// the dictionary, the leftmost and then longest match wins.
+    fn protected_ranges(&self, text: &str, tokens: &[Token]) -> Vec<Range<usize>> {
+        let is_start = |i| tokens.binary_search_by_key(&i, |t| t.byte_start).is_ok();
+        let is_end = |i| tokens.binary_search_by_key(&i, |t| t.byte_end).is_ok();
+        let dictionary_matches = self
+            .dictionary
+            .iter()
+            .flat_map(|regex| regex.find_iter(text))
+            .filter(|m| is_start(m.start()) && is_end(m.end()));
+
+        let mut matches: Vec<_> = self
+            .protected_patterns
+            .iter()
+            .flat_map(|regex| regex.find_iter(text))
+            .chain(dictionary_matches)
+            .filter(|m| !m.range().is_empty())
+            .map(|m| m.range())
+            .collect();
+        matches.sort_unstable_by_key(|range| (range.start, Reverse(range.end)));
+
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for range in matches {
+            if ranges.last().map_or(true, |last| last.end <= range.start) {
+                ranges.push(range);
+            }
+        }
+        ranges
+    }
+
+    /// Splits the word around the custom separators it contains.
+    fn split_word<'t>(&self, text: &'t str, token: Token<'t>, output: &mut Vec<(Token<'t>, bool)>) {
+        let original = &text[token.byte_start..token.byte_end];
+        let separators = find_all(original, &self.separator_tokens);
+        if separators.is_empty() {
+            return output.push((token, false));
+        }
+
+        let mut start = 0;
+        for (sep_start, sep_end) in separators {
+            if start < sep_start {
+                output.push((sub_word(text, &token, start, sep_start), false));
+            }
+            let kind = TokenKind::Separator(SeparatorKind::Soft);
+            output.push((sub_token(text, &token, kind, sep_start, sep_end), false));
+            start = sep_end;
+        }
+        if start < original.len() {
+            output.push((sub_word(text, &token, start, original.len()), false));
+        }
+    }
+
+    /// Extracts the non separators from the separator, they are marked
+    /// to be glued to the words around them.
+    fn split_separator<'t>(
+        &self,
+        text: &'t str,
+        token: Token<'t>,
+        output: &mut Vec<(Token<'t>, bool)>,
+    ) {
+        let original = &text[token.byte_start..token.byte_end];
+        let non_separators = find_all(original, &self.non_separator_tokens);
+        if non_separators.is_empty() {
+            return output.push((token, false));
+        }
+
+        let mut start = 0;
+        for (glue_start, glue_end) in non_separators {
+            if start < glue_start {
+                output.push((sub_token(text, &token, token.kind, start, glue_start), false));
+            }
+            output.push((sub_token(text, &token, TokenKind::Word, glue_start, glue_end), true));
+            start = glue_end;
+        }
+        if start < original.len() {
+            output.push((sub_token(text, &token, token.kind, start, original.len()), false));
+        }
+    }
+}
+
+/// Returns the non-overlapping bytes ranges of the patterns found in the text,
+/// the longest pattern wins when many start at the same position.
+fn find_all(text: &str, patterns: &BTreeSet<String>) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let longest = patterns
+            .iter()
+            .filter(|p| !p.is_empty() && text[start..].starts_with(p.as_str()))
+            .map(String::len)
+            .max();
+        match longest {
+            Some(len) => {
+                found.push((start, start + len));
+                start += len;
+            }
+            None => start += text[start..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    found
+}
+
+/// Creates a token from a part of the original text of the given token,
+/// the word is the part of the original text.
+fn sub_token<'t>(
+    text: &'t str,
+    token: &Token<'t>,
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+) -> Token<'t> {
+    let byte_start = token.byte_start + start;
+    let byte_end = token.byte_start + end;
+    Token {
+        kind,
+        word: Cow::Borrowed(&text[byte_start..byte_end]),
+        char_index: token.char_index + text[token.byte_start..byte_start].chars().count(),
+        byte_start,
+        byte_end,
+        char_map: None,
+    }
+}
+
+/// Creates a word token from a part of the original text of the given word, the normalized
+/// word is sliced according to the char map or the part is normalized again.
+fn sub_word<'t>(text: &'t str, token: &Token<'t>, start: usize, end: usize) -> Token<'t> {
+    let mut sub = sub_token(text, token, token.kind, start, end);
+    match &token.char_map {
+        Some(char_map) => {
+            // The char map associates the length in bytes of the original
+            // chars to the length in bytes of their normalized form.
+            let original = &text[token.byte_start..token.byte_end];
+            let first = original[..start].chars().count();
+            let count = original[start..end].chars().count();
+            let offset: usize = char_map[..first].iter().map(|(_, n)| *n as usize).sum();
+            let char_map = &char_map[first..first + count];
+            let length: usize = char_map.iter().map(|(_, n)| *n as usize).sum();
+            sub.word = Cow::Owned(token.word[offset..offset + length].to_string());
+            sub.char_map = Some(char_map.to_vec());
+        }
+        None => {
+            // The offsets are the ones of the original text, they can only be used on the
+            // normalized word when the tokenizer didn't change it, e.g. `é` becomes `e`.
+            let original = &text[token.byte_start..token.byte_end];
+            let part = &original[start..end];
+            sub.word = if token.word == original {
+                Cow::Owned(part.to_string())
+            } else {
+                Cow::Owned(normalize(part))
+            };
+        }
+    }
+    sub
+}
+
+/// Normalizes a part of the original text of a word like the built-in analyzer does.
+fn normalize(text: &str) -> String {
+    DEFAULT_ANALYZER.with(|analyzer| {
+        let analyzed = analyzer.analyze(text);
+        analyzed.tokens().map(|token| token.word.into_owned()).collect()
+    })
+}
+
+/// Splits the camelCase and snake_case identifiers into sub-words separated by soft
+/// separators, e.g. `getHTTPResponse` into `get`, `http` and `response`.
+fn split_identifiers<'t>(text: &'t str, tokens: Vec<Token<'t>>) -> Vec<Token<'t>> {
+    let mut output = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        if !token.is_word() {
+            output.push(token);
+            continue;
+        }
+
+        let original = &text[token.byte_start..token.byte_end];
+        let mut start = 0;
+        for (boundary, separator_len) in identifier_boundaries(original) {
+            if start < boundary {
+                output.push(sub_word(text, &token, start, boundary));
+            }
+            let kind = TokenKind::Separator(SeparatorKind::Soft);
+            output.push(sub_token(text, &token, kind, boundary, boundary + separator_len));
+            start = boundary + separator_len;
+        }
+
+        if start == 0 {
+            output.push(token);
+        } else if start < original.len() {
+            output.push(sub_word(text, &token, start, original.len()));
+        }
+    }
+
+    output
+}
+
+/// Returns the bytes offsets where the identifier must be split along with the length
+/// of the separator found there, underscores are separators, case changes are not.
+fn identifier_boundaries(word: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<_> = word.char_indices().collect();
+    let mut boundaries = Vec::new();
+    for (i, &(offset, c)) in chars.iter().enumerate() {
+        if c == '_' {
+            boundaries.push((offset, c.len_utf8()));
+            continue;
+        }
+
+        let prev = i.checked_sub(1).map(|i| chars[i].1);
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        let is_boundary = match prev {
+            // getUser: a lowercase letter or a digit followed by an uppercase letter.
+            Some(p) if c.is_uppercase() && (p.is_lowercase() || p.is_numeric()) => true,
+            // HTTPResponse: the last uppercase letter of an acronym starts the next word.
+            Some(p) if c.is_uppercase() && p.is_uppercase() => {
+                next.map_or(false, char::is_lowercase)
+            }
+            _ => false,
+        };
+        if is_boundary && offset != 0 {
+            boundaries.push((offset, 0));
+        }
+    }
+
+    boundaries
+}
+
+/// Removes the emoji or replaces them by the words of their name, the words
+/// are separated by soft separators and keep the bytes range of the emoji.
+fn apply_emoji_policy<'t>(
+    text: &'t str,
+    tokens: Vec<Token<'t>>,
+    policy: EmojiPolicy,
+) -> Vec<Token<'t>> {
+    let mut output = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let is_word = token.is_word() || token.kind == TokenKind::Unknown;
+        let emoji =
+            if is_word { emojis::get(&text[token.byte_start..token.byte_end]) } else { None };
+        let emoji = match emoji {
+            Some(emoji) => emoji,
+            None => {
+                output.push(token);
+                continue;
+            }
+        };
+
+        let separator = |token: &Token<'t>| Token {
+            kind: TokenKind::Separator(SeparatorKind::Soft),
+            word: Cow::Borrowed(" "),
+            char_map: None,
+            ..token.clone()
+        };
+
+        match policy {
+            EmojiPolicy::Index => output.push(token),
+            EmojiPolicy::Strip => output.push(separator(&token)),
+            EmojiPolicy::Name => {
+                for (i, word) in emoji.name().split_whitespace().enumerate() {
+                    if i != 0 {
+                        output.push(separator(&token));
+                    }
+                    output.push(Token {
+                        kind: TokenKind::Word,
+                        word: Cow::Owned(word.to_lowercase()),
+                        char_map: None,
+                        ..token.clone()
+                    });
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Merges the contiguous words and glued tokens into single words.
+fn merge_glued_words(tokens: Vec<(Token, bool)>) -> Vec<Token> {
+    fn flush<'t>(group: &mut Vec<Token<'t>>, glued: &mut bool, output: &mut Vec<Token<'t>>) {
+        if mem::take(glued) {
+            output.push(merge_tokens(mem::take(group)));
+        } else {
+            output.append(group);
+        }
+    }
+
+    let mut output = Vec::with_capacity(tokens.len());
+    let mut group: Vec<Token> = Vec::new();
+    let mut group_glued = false;
+    for (token, glue) in tokens {
+        if !(token.is_word() || glue) {
+            flush(&mut group, &mut group_glued, &mut output);
+            output.push(token);
+            continue;
+        }
+
+        if group.last().map_or(false, |last| last.byte_end != token.byte_start) {
+            flush(&mut group, &mut group_glued, &mut output);
+        }
+        group_glued |= glue;
+        group.push(token);
+    }
+    flush(&mut group, &mut group_glued, &mut output);
+
+    output
+}
+
+fn merge_tokens(tokens: Vec<Token>) -> Token {
+    let has_char_map = tokens.iter().any(|t| t.char_map.is_some());
+    let mut word = String::new();
+    let mut char_map = Vec::new();
+    for token in &tokens {
+        word.push_str(&token.word);
+        if has_char_map {
+            match &token.char_map {
+                Some(map) => char_map.extend_from_slice(map),
+                None => char_map.extend(token.word.chars().map(|c| {
+                    let len = c.len_utf8() as u8;
+                    (len, len)
+                })),
+            }
+        }
+    }
+
+    let first = &tokens[0];
+    Token {
+        kind: TokenKind::Word,
+        word: Cow::Owned(word),
+        char_index: first.char_index,
+        byte_start: first.byte_start,
+        byte_end: tokens[tokens.len() - 1].byte_end,
+        char_map: if has_char_map { Some(char_map) } else { None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreeset;
+    use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+
+    use super::*;
+
+    fn words(settings: &TokenizerSettings, text: &str) -> Vec<String> {
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let analyzed = analyzer.analyze(text);
+        let tokens = settings.retokenize(text, analyzed.tokens());
+        tokens.into_iter().filter(|t| t.is_word()).map(|t| t.word.into_owned()).collect()
+    }
+
+    #[test]
+    fn custom_tokenizer() {
+        /// Only splits the text on whitespaces and keeps the case.
+        struct WhitespaceTokenizer;
+
+        impl Tokenize for WhitespaceTokenizer {
+            fn tokenize<'t>(&self, text: &'t str) -> Vec<Token<'t>> {
+                let mut tokens = Vec::new();
+                let mut byte_start = 0;
+                for (char_index, word) in text.split(' ').enumerate() {
+                    tokens.push(Token {
+                        kind: TokenKind::Word,
+                        word: Cow::Borrowed(word),
+                        char_index,
+                        byte_start,
+                        byte_end: byte_start + word.len(),
+                        char_map: None,
+                    });
+                    byte_start += word.len() + 1;
+                }
+                tokens
+            }
+        }
+
+        let settings = TokenizerSettings::default();
+        let text = "Hello-World C++";
+        let tokens = settings.retokenize(text, WhitespaceTokenizer.tokenize(text));
+        let words: Vec<_> = tokens.iter().map(|t| t.text()).collect();
+        assert_eq!(words, ["Hello-World", "C++"]);
+
+        let tokens = DefaultTokenizer.tokenize(text);
+        let words: Vec<_> = tokens.iter().filter(|t| t.is_word()).map(|t| t.text()).collect();
+        assert_eq!(words, ["hello", "world", "c"]);
+    }
+
+    #[test]
+    fn stop_words_of_any_tokenizer() {
+        let stop_words = fst::Set::from_iter(vec!["the"]).unwrap();
+        let tokens = DefaultTokenizer.tokenize("the cat");
+        assert!(tokens.iter().all(|t| !t.is_stopword()));
+
+        let tokens = mark_stop_words(tokens, Some(&stop_words));
+        let stop_words: Vec<_> =
+            tokens.iter().filter(|t| t.is_stopword()).map(|t| t.text()).collect();
+        assert_eq!(stop_words, ["the"]);
+    }
+
+    #[test]
+    fn non_separator_tokens() {
+        let mut settings = TokenizerSettings::default();
+        settings.non_separator_tokens.insert("+".to_string());
+        settings.non_separator_tokens.insert("#".to_string());
+
+        assert_eq!(words(&settings, "I love C++ and #rust"), ["i", "love", "c++", "and", "#rust"]);
+    }
+
+    #[test]
+    fn protected_patterns() {
+        let mut settings = TokenizerSettings::default();
+        let literal = ProtectedPattern::Literal("C++".to_string());
+        let regex = ProtectedPattern::Regex(r"GTX-\d+".to_string());
+        settings.protected_patterns = vec![literal.to_regex().unwrap(), regex.to_regex().unwrap()];
+
+        assert_eq!(
+            words(&settings, "c++ on a GTX-1080 box"),
+            ["c++", "on", "a", "gtx-1080", "box"]
+        );
+
+        let invalid = ProtectedPattern::Regex("GTX-(".to_string());
+        assert!(invalid.to_regex().is_err());
+    }
+
+    #[test]
+    fn dictionary() {
+        let mut settings = TokenizerSettings::default();
+        let dictionary = btreeset! { "New-York", "H2O" };
+        settings.dictionary = TokenizerSettings::dictionary_regex(&dictionary);
+
+        assert_eq!(words(&settings, "new-york has h2o"), ["new-york", "has", "h2o"]);
+        // the words must be aligned on the tokens.
+        assert_eq!(words(&settings, "new-yorkshire"), ["new", "yorkshire"]);
+    }
+
+    #[test]
+    fn emoji_policy() {
+        let mut settings = TokenizerSettings::default();
+        assert_eq!(words(&settings, "hello 🙂"), ["hello", "🙂"]);
+
+        settings.emoji_policy = EmojiPolicy::Strip;
+        assert_eq!(words(&settings, "hello 🙂"), ["hello"]);
+
+        settings.emoji_policy = EmojiPolicy::Name;
+        assert_eq!(words(&settings, "hello 🙂"), ["hello", "slightly", "smiling", "face"]);
+    }
+
+    #[test]
+    fn split_identifiers() {
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let text = "getHTTPResponse user_name plain";
+        let analyzed = analyzer.analyze(text);
+        let tokens = super::split_identifiers(text, analyzed.tokens().collect());
+        let words: Vec<_> = tokens.iter().filter(|t| t.is_word()).map(|t| t.text()).collect();
+        assert_eq!(words, ["get", "http", "response", "user", "name", "plain"]);
+
+        // The normalized sub-words are not sliced with the offsets of the original text.
+        let text = "caféBar Été_hiver";
+        let analyzed = analyzer.analyze(text);
+        let tokens = super::split_identifiers(text, analyzed.tokens().collect());
+        let words: Vec<_> = tokens.iter().filter(|t| t.is_word()).map(|t| t.text()).collect();
+        assert_eq!(words, ["cafe", "bar", "ete", "hiver"]);
+    }
+
+    #[test]
+    fn separator_tokens() {
+        let mut settings = TokenizerSettings::default();
+        settings.separator_tokens.insert("x".to_string());
+
+        assert_eq!(words(&settings, "Hello 10x20"), ["hello", "10", "20"]);
+        // The normalized words are shorter than the original ones.
+        assert_eq!(words(&settings, "éx1"), ["e", "1"]);
+        assert_eq!(words(&settings, "cafébarxÉtoile"), ["cafebar", "etoile"]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::io::{Read, Seek};
+
+use crate::documents::DocumentBatchReader;
+use crate::update::{
+    DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexerConfig, UpdateIndexingStep,
+};
+use crate::{Index, Result};
+
+/// An addition of documents waiting in the queue of a scheduler.
+pub struct PendingAddition<R> {
+    pub config: IndexDocumentsConfig,
+    pub reader: DocumentBatchReader<R>,
+}
+
+/// Consecutive additions of documents with the same method and settings, indexed together
+/// in a single transform pass instead of one pass per addition.
+pub struct BatchedAdditions<R> {
+    config: IndexDocumentsConfig,
+    readers: Vec<DocumentBatchReader<R>>,
+}
+
+#[derive(Debug)]
+pub struct BatchedAdditionsResult {
+    /// The result of the whole batch, as if the documents were sent in a single addition.
+    pub result: DocumentAdditionResult,
+    /// The number of documents indexed from each of the additions, in the order of the queue.
+    pub indexed_documents: Vec<u64>,
+}
+
+impl<R: Read + Seek> BatchedAdditions<R> {
+    /// Pops the additions at the front of the queue that can be indexed together, i.e. that
+    /// share the configuration of the first one. Returns `None` when the queue is empty.
+    pub fn plan(queue: &mut VecDeque<PendingAddition<R>>) -> Option<BatchedAdditions<R>> {
+        let PendingAddition { config, reader } = queue.pop_front()?;
+        let mut readers = vec![reader];
+        while let Some(addition) = queue.pop_front() {
+            if addition.config != config {
+                // The addition starts the next batch, it is put back in front of the queue.
+                queue.push_front(addition);
+                break;
+            }
+            readers.push(addition.reader);
+        }
+        Some(BatchedAdditions { config, readers })
+    }
+
+    /// The number of additions coalesced in this batch.
+    pub fn len(&self) -> usize {
+        self.readers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readers.is_empty()
+    }
+
+    pub fn config(&self) -> &IndexDocumentsConfig {
+        &self.config
+    }
+
+    pub fn execute<'i, F>(
+        self,
+        wtxn: &mut heed::RwTxn<'i, '_>,
+        index: &'i Index,
+        indexer_config: &IndexerConfig,
+        progress: F,
+    ) -> Result<BatchedAdditionsResult>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        let BatchedAdditions { config, readers } = self;
+        let mut builder = IndexDocuments::new(wtxn, index, indexer_config, config, progress);
+        let mut indexed_documents = Vec::with_capacity(readers.len());
+        for reader in readers {
+            indexed_documents.push(builder.add_documents(reader)?);
+        }
+        let result = builder.execute()?;
+
+        Ok(BatchedAdditionsResult { result, indexed_documents })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::IndexDocumentsMethod;
+
+    #[test]
+    fn coalesce_consecutive_additions() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let replace = IndexDocumentsConfig::default();
+        let update = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            ..Default::default()
+        };
+
+        let mut queue: VecDeque<PendingAddition<Cursor<Vec<u8>>>> = VecDeque::new();
+        queue.push_back(PendingAddition {
+            config: replace.clone(),
+            reader: documents!([{ "id": 1, "name": "kevin" }, { "id": 2, "name": "kevina" }]),
+        });
+        queue.push_back(PendingAddition {
+            config: replace.clone(),
+            reader: documents!([{ "id": 2, "name": "benoit" }]),
+        });
+        queue.push_back(PendingAddition {
+            config: update,
+            reader: documents!([{ "id": 1, "age": 25 }]),
+        });
+
+        let config = IndexerConfig::default();
+        let batch = BatchedAdditions::plan(&mut queue).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.config(), &replace);
+        assert_eq!(queue.len(), 1);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let result = batch.execute(&mut wtxn, &index, &config, |_| ()).unwrap();
+        assert_eq!(result.indexed_documents, vec![2, 1]);
+        assert_eq!(result.result.number_of_documents, 2);
+
+        let batch = BatchedAdditions::plan(&mut queue).unwrap();
+        assert_eq!(batch.len(), 1);
+        let result = batch.execute(&mut wtxn, &index, &config, |_| ()).unwrap();
+        assert_eq!(result.indexed_documents, vec![1]);
+        wtxn.commit().unwrap();
+
+        assert!(BatchedAdditions::plan(&mut queue).is_none());
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let name = fields_ids_map.id("name").unwrap();
+        let age = fields_ids_map.id("age").unwrap();
+        let docids = index.external_documents_ids(&rtxn).unwrap();
+        let docid = docids.get("2").unwrap();
+        let (_, document) = index.documents(&rtxn, Some(docid)).unwrap().remove(0);
+        assert_eq!(document.get(name), Some(&br#""benoit""#[..]));
+        let docid = docids.get("1").unwrap();
+        let (_, document) = index.documents(&rtxn, Some(docid)).unwrap().remove(0);
+        assert_eq!(document.get(name), Some(&br#""kevin""#[..]));
+        assert_eq!(document.get(age), Some(&b"25"[..]));
+    }
+}
@@ -1,24 +1,74 @@
 use roaring::RoaringBitmap;
 use time::OffsetDateTime;
 
-use crate::{ExternalDocumentsIds, FieldDistribution, Index, Result};
+use super::DeleteDocuments;
+use crate::{
+    ExternalDocumentsIds, FieldDistribution, FieldTypesDistribution, FieldsIdsMap, Filter, Index,
+    Result,
+};
 
 pub struct ClearDocuments<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
+    retain_fields_ids_map: bool,
+    retain_external_documents_ids: bool,
+    only_documents: Option<RoaringBitmap>,
 }
 
 impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
     pub fn new(wtxn: &'t mut heed::RwTxn<'i, 'u>, index: &'i Index) -> ClearDocuments<'t, 'u, 'i> {
-        ClearDocuments { wtxn, index }
+        ClearDocuments {
+            wtxn,
+            index,
+            retain_fields_ids_map: true,
+            retain_external_documents_ids: false,
+            only_documents: None,
+        }
+    }
+
+    /// Whether the fields ids map is kept, the fields keep their ids when the documents
+    /// are reloaded. Defaults to `true`, otherwise the ids are reassigned from scratch.
+    pub fn retain_fields_ids_map(&mut self, retain: bool) {
+        self.retain_fields_ids_map = retain;
+    }
+
+    /// Whether the external documents ids are kept, the documents reloaded with the
+    /// same external ids get back their previous internal ids. Defaults to `false`.
+    pub fn retain_external_documents_ids(&mut self, retain: bool) {
+        self.retain_external_documents_ids = retain;
+    }
+
+    /// Only clears the documents matching the filter. When the filter only selects whole
+    /// facet partitions (e.g. `tenant = kevin`) the documents are directly retrieved from
+    /// the facet databases.
+    ///
+    /// The matching documents are removed with a [`DeleteDocuments`] unless the filter
+    /// matches all of them, the retain options only apply to the latter case.
+    pub fn only_matching(&mut self, filter: &Filter) -> Result<()> {
+        let docids = match filter.facet_partitions(self.wtxn, self.index)? {
+            Some(docids) => docids,
+            None => filter.evaluate(self.wtxn, self.index)?,
+        };
+        self.only_documents = Some(docids);
+        Ok(())
     }
 
     pub fn execute(self) -> Result<u64> {
+        if let Some(docids) = &self.only_documents {
+            let documents_ids = self.index.documents_ids(self.wtxn)?;
+            if !documents_ids.is_subset(docids) {
+                let mut builder = DeleteDocuments::new(self.wtxn, self.index)?;
+                builder.delete_documents(docids);
+                return Ok(builder.execute()?.deleted_documents);
+            }
+        }
+
         self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
         let Index {
             env: _env,
             main: _main,
             word_docids,
+            exact_word_docids,
             word_prefix_docids,
             docid_word_positions,
             word_pair_proximity_docids,
@@ -26,35 +76,61 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
             word_position_docids,
             field_id_word_count_docids,
             word_prefix_position_docids,
+            word_fid_docids,
             facet_id_f64_docids,
             facet_id_string_docids,
+            facet_id_date_docids,
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            docid_external_ids,
+            acl_token_docids,
+            compiled_patterns: _compiled_patterns,
         } = self.index;
 
         // We retrieve the number of documents ids that we are deleting.
         let number_of_documents = self.index.number_of_documents(self.wtxn)?;
         let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
 
+        // The internal ids of the retained external ids must not be given to other documents.
+        if self.retain_external_documents_ids {
+            let documents_ids = self.index.documents_ids(self.wtxn)?;
+            let reserved = self.index.reserved_documents_ids(self.wtxn)? | documents_ids;
+            self.index.put_reserved_documents_ids(self.wtxn, &reserved)?;
+        } else {
+            self.index.put_external_documents_ids(self.wtxn, &ExternalDocumentsIds::default())?;
+            self.index.delete_reserved_documents_ids(self.wtxn)?;
+            self.index.delete_document_id_policy(self.wtxn)?;
+            docid_external_ids.clear(self.wtxn)?;
+        }
+
+        if !self.retain_fields_ids_map {
+            self.index.put_fields_ids_map(self.wtxn, &FieldsIdsMap::new())?;
+        }
+
         // We clean some of the main engine datastructures.
         self.index.put_words_fst(self.wtxn, &fst::Set::default())?;
         self.index.put_words_prefixes_fst(self.wtxn, &fst::Set::default())?;
-        self.index.put_external_documents_ids(self.wtxn, &ExternalDocumentsIds::default())?;
         self.index.put_documents_ids(self.wtxn, &RoaringBitmap::default())?;
         self.index.put_field_distribution(self.wtxn, &FieldDistribution::default())?;
+        self.index.put_field_types_distribution(self.wtxn, &FieldTypesDistribution::default())?;
         self.index.delete_geo_rtree(self.wtxn)?;
         self.index.delete_geo_faceted_documents_ids(self.wtxn)?;
+        self.index.delete_flattened_faceted_fields(self.wtxn)?;
+        self.index.delete_proximity_window(self.wtxn)?;
 
         // We clean all the faceted documents ids.
         let empty = RoaringBitmap::default();
         for field_id in faceted_fields {
             self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &empty)?;
             self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_facet_id_bool_docids(self.wtxn, field_id, true, &empty)?;
+            self.index.put_facet_id_bool_docids(self.wtxn, field_id, false, &empty)?;
         }
 
         // Clear the other databases.
         word_docids.clear(self.wtxn)?;
+        exact_word_docids.clear(self.wtxn)?;
         word_prefix_docids.clear(self.wtxn)?;
         docid_word_positions.clear(self.wtxn)?;
         word_pair_proximity_docids.clear(self.wtxn)?;
@@ -62,11 +138,14 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         word_position_docids.clear(self.wtxn)?;
         field_id_word_count_docids.clear(self.wtxn)?;
         word_prefix_position_docids.clear(self.wtxn)?;
+        word_fid_docids.clear(self.wtxn)?;
         facet_id_f64_docids.clear(self.wtxn)?;
         facet_id_string_docids.clear(self.wtxn)?;
+        facet_id_date_docids.clear(self.wtxn)?;
         field_id_docid_facet_f64s.clear(self.wtxn)?;
         field_id_docid_facet_strings.clear(self.wtxn)?;
         documents.clear(self.wtxn)?;
+        acl_token_docids.clear(self.wtxn)?;
 
         Ok(number_of_documents)
     }
@@ -74,10 +153,12 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
 
 #[cfg(test)]
 mod tests {
+    use big_s::S;
     use heed::EnvOpenOptions;
+    use maplit::hashset;
 
     use super::*;
-    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
 
     #[test]
     fn clear_documents() {
@@ -117,15 +198,139 @@ mod tests {
         assert!(index.geo_faceted_documents_ids(&rtxn).unwrap().is_empty());
 
         assert!(index.word_docids.is_empty(&rtxn).unwrap());
+        assert!(index.exact_word_docids.is_empty(&rtxn).unwrap());
         assert!(index.word_prefix_docids.is_empty(&rtxn).unwrap());
         assert!(index.docid_word_positions.is_empty(&rtxn).unwrap());
         assert!(index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
         assert!(index.field_id_word_count_docids.is_empty(&rtxn).unwrap());
         assert!(index.word_prefix_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        assert!(index.word_fid_docids.is_empty(&rtxn).unwrap());
         assert!(index.facet_id_f64_docids.is_empty(&rtxn).unwrap());
         assert!(index.facet_id_string_docids.is_empty(&rtxn).unwrap());
+        assert!(index.facet_id_date_docids.is_empty(&rtxn).unwrap());
         assert!(index.field_id_docid_facet_f64s.is_empty(&rtxn).unwrap());
         assert!(index.field_id_docid_facet_strings.is_empty(&rtxn).unwrap());
         assert!(index.documents.is_empty(&rtxn).unwrap());
+        assert!(index.docid_external_ids.is_empty(&rtxn).unwrap());
+        assert!(index.acl_token_docids.is_empty(&rtxn).unwrap());
+        assert!(index.document_id_policy(&rtxn).unwrap().is_none());
+    }
+
+    #[test]
+    fn clear_documents_retaining_ids() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin", "age": 20 },
+            { "id": 1, "name": "kevina" },
+            { "id": 2, "name": "benoit", "country": "France" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let config = IndexerConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let fields_ids: Vec<_> = fields_ids_map.iter().collect();
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        let kevina = external_documents_ids.get("1").unwrap();
+
+        let mut builder = ClearDocuments::new(&mut wtxn, &index);
+        builder.retain_external_documents_ids(true);
+        assert_eq!(builder.execute().unwrap(), 3);
+
+        assert!(index.documents_ids(&wtxn).unwrap().is_empty());
+        assert_eq!(index.external_documents_ids(&wtxn).unwrap().get("1"), Some(kevina));
+        assert_eq!(index.reserved_documents_ids(&wtxn).unwrap().len(), 3);
+
+        // A new document must not take the id of a retained one
+        // and a reloaded document must get its id back.
+        let content = documents!([
+            { "id": 3, "country": "Italy" },
+            { "id": 1, "name": "kevina" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let new_fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        assert_eq!(new_fields_ids_map.iter().collect::<Vec<_>>(), fields_ids);
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert_eq!(external_documents_ids.get("1"), Some(kevina));
+        let italy = external_documents_ids.get("3").unwrap();
+        let reserved_documents_ids = index.reserved_documents_ids(&wtxn).unwrap();
+        assert!(!reserved_documents_ids.contains(italy));
+        assert!(!reserved_documents_ids.contains(kevina));
+        assert_eq!(reserved_documents_ids.len(), 2);
+        assert_eq!(index.documents(&wtxn, Some(kevina)).unwrap().len(), 1);
+        assert_eq!(index.documents_ids(&wtxn).unwrap().len(), 2);
+
+        // Without the retain options everything is reset.
+        let mut builder = ClearDocuments::new(&mut wtxn, &index);
+        builder.retain_fields_ids_map(false);
+        assert_eq!(builder.execute().unwrap(), 2);
+
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.fields_ids_map(&rtxn).unwrap().is_empty());
+        assert!(index.external_documents_ids(&rtxn).unwrap().is_empty());
+        assert!(index.reserved_documents_ids(&rtxn).unwrap().is_empty());
+        assert!(index.docid_external_ids.is_empty(&rtxn).unwrap());
+    }
+
+    #[test]
+    fn clear_documents_only_matching() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("tenant") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "kevin", "tenant": "a" },
+            { "id": 1, "name": "kevina", "tenant": "b" },
+            { "id": 2, "name": "benoit", "tenant": "c" },
+            { "id": 3, "name": "bernard", "tenant": "a" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let filter = Filter::from_str("tenant = A").unwrap().unwrap();
+        let mut builder = ClearDocuments::new(&mut wtxn, &index);
+        builder.only_matching(&filter).unwrap();
+        assert_eq!(builder.execute().unwrap(), 2);
+
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert!(external_documents_ids.get("0").is_none());
+        assert!(external_documents_ids.get("3").is_none());
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 2);
+
+        // The remaining documents all match, the index is entirely cleared.
+        let filter = Filter::from_str("tenant = b OR tenant = c").unwrap().unwrap();
+        let mut builder = ClearDocuments::new(&mut wtxn, &index);
+        builder.only_matching(&filter).unwrap();
+        assert_eq!(builder.execute().unwrap(), 2);
+
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.documents_ids(&rtxn).unwrap().is_empty());
+        assert!(index.facet_id_string_docids.is_empty(&rtxn).unwrap());
+        assert!(index.facet_id_date_docids.is_empty(&rtxn).unwrap());
+        assert!(index.documents.is_empty(&rtxn).unwrap());
     }
 }
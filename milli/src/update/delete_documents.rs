@@ -6,16 +6,15 @@ use heed::types::ByteSlice;
 use heed::{BytesDecode, BytesEncode};
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use time::OffsetDateTime;
 
-use super::ClearDocuments;
-use crate::error::{InternalError, SerializationError, UserError};
+use super::{geo_rtree, ClearDocuments};
+use crate::error::SerializationError;
+use crate::field_types::remove_document_types;
 use crate::heed_codec::facet::{
     FacetLevelValueU32Codec, FacetStringLevelZeroValueCodec, FacetStringZeroBoundsValueCodec,
 };
 use crate::heed_codec::CboRoaringBitmapCodec;
-use crate::index::{db_name, main_key};
 use crate::{DocumentId, ExternalDocumentsIds, FieldId, Index, Result, SmallString32, BEU32};
 
 pub struct DeleteDocuments<'t, 'u, 'i> {
@@ -23,6 +22,7 @@ pub struct DeleteDocuments<'t, 'u, 'i> {
     index: &'i Index,
     external_documents_ids: ExternalDocumentsIds<'static>,
     documents_ids: RoaringBitmap,
+    kept_geo_documents_ids: RoaringBitmap,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -43,6 +43,7 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             index,
             external_documents_ids,
             documents_ids: RoaringBitmap::new(),
+            kept_geo_documents_ids: RoaringBitmap::new(),
         })
     }
 
@@ -54,6 +55,12 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         self.documents_ids |= docids;
     }
 
+    /// Keeps the geo data of these documents, used when they are replaced
+    /// by documents with the same coordinates.
+    pub(crate) fn keep_geo_documents(&mut self, docids: &RoaringBitmap) {
+        self.kept_geo_documents_ids |= docids;
+    }
+
     pub fn delete_external_id(&mut self, external_id: &str) -> Option<u32> {
         let docid = self.external_documents_ids.get(external_id)?;
         self.delete_document(docid);
@@ -80,8 +87,11 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         self.index.put_documents_ids(self.wtxn, &documents_ids)?;
 
         // We can execute a ClearDocuments operation when the number of documents
-        // to delete is exactly the number of documents in the database.
-        if current_documents_ids_len == self.documents_ids.len() {
+        // to delete is exactly the number of documents in the database and that
+        // we don't have to keep the geo data of some of them.
+        if current_documents_ids_len == self.documents_ids.len()
+            && self.kept_geo_documents_ids.is_empty()
+        {
             let remaining_documents = ClearDocuments::new(self.wtxn, self.index).execute()?;
             return Ok(DocumentDeletionResult {
                 deleted_documents: current_documents_ids_len,
@@ -90,24 +100,12 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         }
 
         let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
-        let primary_key = self.index.primary_key(self.wtxn)?.ok_or_else(|| {
-            InternalError::DatabaseMissingEntry {
-                db_name: db_name::MAIN,
-                key: Some(main_key::PRIMARY_KEY_KEY),
-            }
-        })?;
-
-        // Since we already checked if the DB was empty, if we can't find the primary key, then
-        // something is wrong, and we must return an error.
-        let id_field = match fields_ids_map.id(primary_key) {
-            Some(field) => field,
-            None => return Err(UserError::MissingPrimaryKey.into()),
-        };
 
         let Index {
             env: _env,
             main: _main,
             word_docids,
+            exact_word_docids,
             word_prefix_docids,
             docid_word_positions,
             word_pair_proximity_docids,
@@ -115,45 +113,53 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             word_prefix_pair_proximity_docids,
             word_position_docids,
             word_prefix_position_docids,
+            word_fid_docids,
             facet_id_f64_docids,
             facet_id_string_docids,
+            facet_id_date_docids,
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            docid_external_ids,
+            acl_token_docids,
+            compiled_patterns: _compiled_patterns,
         } = self.index;
 
         // Number of fields for each document that has been deleted.
         let mut fields_ids_distribution_diff = HashMap::new();
+        let mut field_types_distribution = self.index.field_types_distribution(self.wtxn)?;
 
         // Retrieve the words and the external documents ids contained in the documents.
         let mut words = Vec::new();
         let mut external_ids = Vec::new();
+        let mut codec = self.index.documents_codec(self.wtxn)?;
+        let raw_documents = documents.remap_data_type::<ByteSlice>();
         for docid in &self.documents_ids {
             // We create an iterator to be able to get the content and delete the document
             // content itself. It's faster to acquire a cursor to get and delete,
             // as we avoid traversing the LMDB B-Tree two times but only once.
             let key = BEU32::new(docid);
-            let mut iter = documents.range_mut(self.wtxn, &(key..=key))?;
-            if let Some((_key, obkv)) = iter.next().transpose()? {
+            let mut iter = raw_documents.range_mut(self.wtxn, &(key..=key))?;
+            if let Some((_key, bytes)) = iter.next().transpose()? {
+                let obkv = codec.decompress(bytes)?;
                 for (field_id, _) in obkv.iter() {
                     *fields_ids_distribution_diff.entry(field_id).or_default() += 1;
                 }
+                let obkv = obkv.as_obkv();
+                remove_document_types(&mut field_types_distribution, &fields_ids_map, obkv)?;
 
-                if let Some(content) = obkv.get(id_field) {
-                    let external_id = match serde_json::from_slice(content).unwrap() {
-                        Value::String(string) => SmallString32::from(string.as_str()),
-                        Value::Number(number) => SmallString32::from(number.to_string()),
-                        document_id => {
-                            return Err(UserError::InvalidDocumentId { document_id }.into())
-                        }
-                    };
-                    external_ids.push(external_id);
-                }
                 // safety: we don't keep references from inside the LMDB database.
                 unsafe { iter.del_current()? };
             }
             drop(iter);
 
+            // We retrieve the external id of the document from the reverse lookup
+            // database, this way we don't need to decode the primary key of the document.
+            if let Some(external_id) = docid_external_ids.get(self.wtxn, &key)? {
+                external_ids.push(SmallString32::from(external_id));
+            }
+            docid_external_ids.delete(self.wtxn, &key)?;
+
             // We iterate through the words positions of the document id,
             // retrieve the word and delete the positions.
             let mut iter = docid_word_positions.prefix_iter_mut(self.wtxn, &(docid, ""))?;
@@ -183,6 +189,7 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         }
 
         self.index.put_field_distribution(self.wtxn, &field_distribution)?;
+        self.index.put_field_types_distribution(self.wtxn, &field_types_distribution)?;
 
         // We create the FST map of the external ids that we must delete.
         external_ids.sort_unstable();
@@ -373,6 +380,60 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
 
         drop(iter);
 
+        // We delete the documents ids that are under the word fid docids.
+        let mut iter = word_fid_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
+        while let Some(result) = iter.next() {
+            let (bytes, mut docids) = result?;
+            let previous_len = docids.len();
+            docids -= &self.documents_ids;
+            if docids.is_empty() {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            } else if docids.len() != previous_len {
+                let bytes = bytes.to_owned();
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.put_current(&bytes, &docids)? };
+            }
+        }
+
+        drop(iter);
+
+        // We delete the documents ids that are under the exact words.
+        let mut iter = exact_word_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
+        while let Some(result) = iter.next() {
+            let (bytes, mut docids) = result?;
+            let previous_len = docids.len();
+            docids -= &self.documents_ids;
+            if docids.is_empty() {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            } else if docids.len() != previous_len {
+                let bytes = bytes.to_owned();
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.put_current(&bytes, &docids)? };
+            }
+        }
+
+        drop(iter);
+
+        // We delete the documents ids that are under the access control tokens.
+        let mut iter = acl_token_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
+        while let Some(result) = iter.next() {
+            let (bytes, mut docids) = result?;
+            let previous_len = docids.len();
+            docids -= &self.documents_ids;
+            if docids.is_empty() {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            } else if docids.len() != previous_len {
+                let bytes = bytes.to_owned();
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.put_current(&bytes, &docids)? };
+            }
+        }
+
+        drop(iter);
+
         // Remove the documents ids from the field id word count database.
         let mut iter = field_id_word_count_docids.iter_mut(self.wtxn)?;
         while let Some((key, mut docids)) = iter.next().transpose()? {
@@ -393,19 +454,16 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         if let Some(mut rtree) = self.index.geo_rtree(self.wtxn)? {
             let mut geo_faceted_doc_ids = self.index.geo_faceted_documents_ids(self.wtxn)?;
 
-            let (points_to_remove, docids_to_remove): (Vec<_>, RoaringBitmap) = rtree
-                .iter()
-                .filter(|&point| self.documents_ids.contains(point.data.0))
-                .cloned()
-                .map(|point| (point, point.data.0))
-                .unzip();
-            points_to_remove.iter().for_each(|point| {
-                rtree.remove(&point);
-            });
-            geo_faceted_doc_ids -= docids_to_remove;
+            let geo_documents_ids = &self.documents_ids - &self.kept_geo_documents_ids;
+            let docids_to_remove = geo_rtree::remove_points(&mut rtree, &geo_documents_ids);
+
+            // We only rewrite the geo data when some points are removed.
+            if !docids_to_remove.is_empty() {
+                geo_faceted_doc_ids -= docids_to_remove;
 
-            self.index.put_geo_rtree(self.wtxn, &rtree)?;
-            self.index.put_geo_faceted_documents_ids(self.wtxn, &geo_faceted_doc_ids)?;
+                self.index.put_geo_rtree(self.wtxn, &rtree)?;
+                self.index.put_geo_faceted_documents_ids(self.wtxn, &geo_faceted_doc_ids)?;
+            }
         }
 
         // We delete the documents ids that are under the facet field id values.
@@ -421,6 +479,12 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             &self.documents_ids,
         )?;
 
+        remove_docids_from_facet_field_id_number_docids(
+            self.wtxn,
+            facet_id_date_docids,
+            &self.documents_ids,
+        )?;
+
         // Remove the documents ids from the faceted documents ids.
         for field_id in self.index.faceted_fields_ids(self.wtxn)? {
             // Remove docids from the number faceted documents ids
@@ -448,6 +512,13 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                 &self.documents_ids,
                 |(_fid, docid, _value)| docid,
             )?;
+
+            // Remove docids from the boolean faceted documents ids
+            for value in [true, false] {
+                let mut docids = self.index.facet_id_bool_docids(self.wtxn, field_id, value)?;
+                docids -= &self.documents_ids;
+                self.index.put_facet_id_bool_docids(self.wtxn, field_id, value, &docids)?;
+            }
         }
 
         Ok(DocumentDeletionResult {
@@ -616,6 +687,46 @@ mod tests {
         assert!(index.field_distribution(&rtxn).unwrap().is_empty());
     }
 
+    #[test]
+    fn delete_documents_external_ids_after_reindexing() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": "1", "name": "kevina" },
+            { "id": "benoit", "name": "benoit" }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // the documents are reindexed, the external ids must be kept.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("name")]);
+        builder.execute(|_| ()).unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        assert!(builder.delete_external_id("benoit").is_some());
+        builder.execute().unwrap();
+
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let documents_ids = index.documents_ids(&rtxn).unwrap();
+        let mut external_ids = index.external_ids(&rtxn, documents_ids).unwrap();
+        external_ids.sort_unstable();
+        assert_eq!(external_ids, vec!["0", "1"]);
+        assert_eq!(index.docid_external_ids.len(&rtxn).unwrap(), 2);
+        assert!(index.external_documents_ids(&rtxn).unwrap().get("benoit").is_none());
+    }
+
     #[test]
     fn delete_documents_with_strange_primary_key() {
         let path = tempfile::tempdir().unwrap();
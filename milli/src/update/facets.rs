@@ -1,15 +1,16 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::num::{NonZeroU8, NonZeroUsize};
-use std::{cmp, mem};
+use std::{cmp, io, mem};
 
 use grenad::{CompressionType, Reader, Writer};
 use heed::types::{ByteSlice, DecodeIgnore};
-use heed::{BytesEncode, Error};
-use log::debug;
+use heed::{BytesDecode, BytesEncode, Error};
 use roaring::RoaringBitmap;
 use time::OffsetDateTime;
+use tracing::debug;
 
-use crate::error::InternalError;
+use crate::error::{InternalError, SerializationError};
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetLevelValueU32Codec, FacetStringLevelZeroCodec,
     FacetStringLevelZeroValueCodec, FacetStringZeroBoundsValueCodec,
@@ -23,10 +24,90 @@ pub struct Facets<'t, 'u, 'i> {
     index: &'i Index,
     pub(crate) chunk_compression_type: CompressionType,
     pub(crate) chunk_compression_level: Option<u32>,
+    /// Restricts the computation of the levels to these fields, all the faceted fields
+    /// are computed when `None`.
+    pub(crate) fields_ids: Option<HashSet<FieldId>>,
+    /// The facet values written by a documents addition, only the levels of the fields they
+    /// are in are updated, in place when possible. All the levels are computed when `None`.
+    pub(crate) delta: Option<FacetValuesDelta>,
+    /// The fields that are sortable but not filterable, only their level 0 is stored as the
+    /// levels are only used to filter on ranges of values, they are sorted by their level 0.
+    sortable_only_fields: HashSet<FieldId>,
     level_group_size: NonZeroUsize,
     min_level_size: NonZeroUsize,
 }
 
+/// The facet values written into the level 0 of the facet databases by a documents addition,
+/// along with the documents ids added to them, read from the chunks before they are written.
+#[derive(Debug, Default)]
+pub(crate) struct FacetValuesDelta {
+    numbers: HashMap<FieldId, Vec<(f64, RoaringBitmap)>>,
+    strings: HashMap<FieldId, Vec<(String, RoaringBitmap)>>,
+    /// The fields that got strings which were not in the index, the string levels
+    /// refer to the values by position and can't be updated in place.
+    new_strings: HashSet<FieldId>,
+    /// Whether documents were deleted too, the levels of all the faceted fields
+    /// must then be checked as they may not match their level 0 anymore.
+    documents_deleted: bool,
+}
+
+impl FacetValuesDelta {
+    pub(crate) fn new(documents_deleted: bool) -> FacetValuesDelta {
+        FacetValuesDelta { documents_deleted, ..FacetValuesDelta::default() }
+    }
+
+    /// Registers the facet numbers of a chunk of the `facet_id_f64_docids` database.
+    pub(crate) fn register_numbers<R: io::Read + io::Seek>(
+        &mut self,
+        chunk: grenad::Reader<R>,
+    ) -> Result<()> {
+        let db_name = Some(crate::index::db_name::FACET_ID_F64_DOCIDS);
+        let mut cursor = chunk.into_cursor()?;
+        while let Some((key, value)) = cursor.move_on_next()? {
+            let (field_id, _level, number, _) = FacetLevelValueF64Codec::bytes_decode(key)
+                .ok_or(SerializationError::Decoding { db_name })?;
+            let docids = CboRoaringBitmapCodec::deserialize_from(value)?;
+            self.numbers.entry(field_id).or_default().push((number, docids));
+        }
+        Ok(())
+    }
+
+    /// Registers the facet strings of a chunk of the `facet_id_string_docids` database,
+    /// it must be called before the chunk is written to know the strings that are new.
+    pub(crate) fn register_strings<R: io::Read + io::Seek>(
+        &mut self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        chunk: grenad::Reader<R>,
+    ) -> Result<()> {
+        let db_name = Some(crate::index::db_name::FACET_ID_STRING_DOCIDS);
+        let db = index.facet_id_string_docids.remap_data_type::<DecodeIgnore>();
+        let mut cursor = chunk.into_cursor()?;
+        while let Some((key, value)) = cursor.move_on_next()? {
+            let (field_id, string) = FacetStringLevelZeroCodec::bytes_decode(key)
+                .ok_or(SerializationError::Decoding { db_name })?;
+            let (_original, docids) = FacetStringLevelZeroValueCodec::bytes_decode(value)
+                .ok_or(SerializationError::Decoding { db_name })?;
+            if self.new_strings.contains(&field_id) {
+                continue;
+            }
+            if db.get(rtxn, &(field_id, string))?.is_none() {
+                self.new_strings.insert(field_id);
+                self.strings.remove(&field_id);
+            } else {
+                self.strings.entry(field_id).or_default().push((string.to_string(), docids));
+            }
+        }
+        Ok(())
+    }
+
+    fn contains(&self, field_id: FieldId) -> bool {
+        self.numbers.contains_key(&field_id)
+            || self.strings.contains_key(&field_id)
+            || self.new_strings.contains(&field_id)
+    }
+}
+
 impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
     pub fn new(wtxn: &'t mut heed::RwTxn<'i, 'u>, index: &'i Index) -> Facets<'t, 'u, 'i> {
         Facets {
@@ -34,6 +115,9 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
             index,
             chunk_compression_type: CompressionType::None,
             chunk_compression_level: None,
+            fields_ids: None,
+            delta: None,
+            sortable_only_fields: HashSet::new(),
             level_group_size: NonZeroUsize::new(4).unwrap(),
             min_level_size: NonZeroUsize::new(5).unwrap(),
         }
@@ -50,86 +134,333 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
     }
 
     #[logging_timer::time("Facets::{}")]
-    pub fn execute(self) -> Result<()> {
+    pub fn execute(mut self) -> Result<()> {
         self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
         // We get the faceted fields to be able to create the facet levels.
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let mut faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        if let Some(fields_ids) = &self.fields_ids {
+            faceted_fields.retain(|field_id| fields_ids.contains(field_id));
+        }
+
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let filterable_fields: HashSet<_> = self
+            .index
+            .filterable_fields_with_flattened(self.wtxn)?
+            .iter()
+            .filter_map(|name| fields_ids_map.id(name))
+            .collect();
+        self.sortable_only_fields =
+            faceted_fields.difference(&filterable_fields).copied().collect();
+
+        // The levels of the fields untouched by the documents addition are already up to date.
+        let delta = self.delta.take();
+        if let Some(delta) = delta.as_ref().filter(|delta| !delta.documents_deleted) {
+            faceted_fields.retain(|field_id| delta.contains(*field_id));
+        }
 
         debug!("Computing and writing the facet values levels docids into LMDB on disk...");
 
         for field_id in faceted_fields {
-            // Clear the facet string levels.
-            clear_field_string_levels(
-                self.wtxn,
-                self.index.facet_id_string_docids.remap_types::<ByteSlice, DecodeIgnore>(),
-                field_id,
-            )?;
-
-            // Compute and store the faceted strings documents ids.
-            let string_documents_ids = compute_faceted_strings_documents_ids(
-                self.wtxn,
-                self.index.facet_id_string_docids.remap_key_type::<ByteSlice>(),
-                field_id,
-            )?;
-
-            let facet_string_levels = compute_facet_string_levels(
-                self.wtxn,
-                self.index.facet_id_string_docids,
-                self.chunk_compression_type,
-                self.chunk_compression_level,
-                self.level_group_size,
-                self.min_level_size,
-                field_id,
-            )?;
-
-            // Clear the facet number levels.
-            clear_field_number_levels(self.wtxn, self.index.facet_id_f64_docids, field_id)?;
-
-            // Compute and store the faceted numbers documents ids.
-            let number_documents_ids = compute_faceted_numbers_documents_ids(
-                self.wtxn,
-                self.index.facet_id_f64_docids.remap_key_type::<ByteSlice>(),
-                field_id,
-            )?;
-
-            let facet_number_levels = compute_facet_number_levels(
-                self.wtxn,
-                self.index.facet_id_f64_docids,
-                self.chunk_compression_type,
-                self.chunk_compression_level,
-                self.level_group_size,
-                self.min_level_size,
-                field_id,
-            )?;
-
-            self.index.put_string_faceted_documents_ids(
-                self.wtxn,
-                field_id,
-                &string_documents_ids,
-            )?;
-            self.index.put_number_faceted_documents_ids(
-                self.wtxn,
-                field_id,
-                &number_documents_ids,
-            )?;
-
-            write_into_lmdb_database(
-                self.wtxn,
-                *self.index.facet_id_f64_docids.as_polymorph(),
-                facet_number_levels,
-                |_, _| Err(InternalError::IndexingMergingKeys { process: "facet number levels" })?,
-            )?;
-
-            write_into_lmdb_database(
-                self.wtxn,
-                *self.index.facet_id_string_docids.as_polymorph(),
-                facet_string_levels,
-                |_, _| Err(InternalError::IndexingMergingKeys { process: "facet string levels" })?,
-            )?;
+            let strings_updated = match &delta {
+                Some(delta) if !delta.new_strings.contains(&field_id) => {
+                    let values = delta.strings.get(&field_id).map_or(&[][..], Vec::as_slice);
+                    self.update_string_levels(field_id, values)?
+                }
+                _ => false,
+            };
+            if !strings_updated {
+                self.compute_string_levels(field_id)?;
+            }
+
+            let numbers_updated = match &delta {
+                Some(delta) => {
+                    let values = delta.numbers.get(&field_id).map_or(&[][..], Vec::as_slice);
+                    self.update_number_levels(field_id, values)?
+                }
+                None => false,
+            };
+            if !numbers_updated {
+                self.compute_number_levels(field_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clears and computes the string levels and faceted documents ids of the field.
+    fn compute_string_levels(&mut self, field_id: FieldId) -> Result<()> {
+        // Clear the facet string levels.
+        clear_field_string_levels(
+            self.wtxn,
+            self.index.facet_id_string_docids.remap_types::<ByteSlice, DecodeIgnore>(),
+            field_id,
+        )?;
+
+        // Compute and store the faceted strings documents ids.
+        let string_documents_ids = compute_faceted_strings_documents_ids(
+            self.wtxn,
+            self.index.facet_id_string_docids.remap_key_type::<ByteSlice>(),
+            field_id,
+        )?;
+
+        self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &string_documents_ids)?;
+        if self.sortable_only_fields.contains(&field_id) {
+            return Ok(());
         }
 
+        let facet_string_levels = compute_facet_string_levels(
+            self.wtxn,
+            self.index.facet_id_string_docids,
+            self.chunk_compression_type,
+            self.chunk_compression_level,
+            self.level_group_size,
+            self.min_level_size,
+            field_id,
+        )?;
+
+        write_into_lmdb_database(
+            self.wtxn,
+            *self.index.facet_id_string_docids.as_polymorph(),
+            facet_string_levels,
+            |_, _| Err(InternalError::IndexingMergingKeys { process: "facet string levels" })?,
+        )?;
+
         Ok(())
     }
+
+    /// Clears and computes the number levels and faceted documents ids of the field.
+    fn compute_number_levels(&mut self, field_id: FieldId) -> Result<()> {
+        // Clear the facet number levels.
+        clear_field_number_levels(self.wtxn, self.index.facet_id_f64_docids, field_id)?;
+
+        // Compute and store the faceted numbers documents ids.
+        let number_documents_ids = compute_faceted_numbers_documents_ids(
+            self.wtxn,
+            self.index.facet_id_f64_docids.remap_key_type::<ByteSlice>(),
+            field_id,
+        )?;
+
+        self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &number_documents_ids)?;
+        if self.sortable_only_fields.contains(&field_id) {
+            return Ok(());
+        }
+
+        let facet_number_levels = compute_facet_number_levels(
+            self.wtxn,
+            self.index.facet_id_f64_docids,
+            self.chunk_compression_type,
+            self.chunk_compression_level,
+            self.level_group_size,
+            self.min_level_size,
+            field_id,
+        )?;
+
+        write_into_lmdb_database(
+            self.wtxn,
+            *self.index.facet_id_f64_docids.as_polymorph(),
+            facet_number_levels,
+            |_, _| Err(InternalError::IndexingMergingKeys { process: "facet number levels" })?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Adds the documents ids of the given numbers to the groups containing them, without
+    /// changing the groups. Returns `false`, without writing anything, when the levels of
+    /// the field must be computed again: when a number is outside of all the groups of a
+    /// level, or when the number of values of the level 0 asks for another number of levels.
+    fn update_number_levels(
+        &mut self,
+        field_id: FieldId,
+        values: &[(f64, RoaringBitmap)],
+    ) -> Result<bool> {
+        let db = self.index.facet_id_f64_docids;
+        let level_0_range = {
+            let left = (field_id, 0, f64::MIN, f64::MIN);
+            let right = (field_id, 0, f64::MAX, f64::MAX);
+            left..=right
+        };
+        let first_level_size = db
+            .remap_data_type::<DecodeIgnore>()
+            .range(self.wtxn, &level_0_range)?
+            .fold(Ok(0usize), |count, result| result.and(count).map(|c| c + 1))?;
+
+        let levels_range = {
+            let left = (field_id, 1, f64::MIN, f64::MIN);
+            let right = (field_id, u8::MAX, f64::MAX, f64::MAX);
+            left..=right
+        };
+        let last_level = match db
+            .remap_data_type::<DecodeIgnore>()
+            .rev_range(self.wtxn, &levels_range)?
+            .next()
+        {
+            Some(result) => {
+                let ((_, level, _, _), ()) = result?;
+                level
+            }
+            None => 0,
+        };
+        if last_level != self.number_of_levels(field_id, first_level_size) {
+            return Ok(false);
+        }
+
+        // The groups are found before anything is written, the same group
+        // may contain many numbers, their documents ids are merged.
+        let mut groups = BTreeMap::new();
+        let mut documents_ids = RoaringBitmap::new();
+        for (number, docids) in values {
+            for level in 1..=last_level {
+                let range =
+                    (field_id, level, f64::MIN, f64::MIN)..=(field_id, level, *number, f64::MAX);
+                match db.rev_range(self.wtxn, &range)?.next().transpose()? {
+                    Some(((_, _, left, right), group_docids)) if right >= *number => {
+                        let key = (level, left.to_bits(), right.to_bits());
+                        *groups.entry(key).or_insert(group_docids) |= docids;
+                    }
+                    _ => return Ok(false),
+                }
+            }
+            documents_ids |= docids;
+        }
+
+        for ((level, left, right), docids) in groups {
+            let key = (field_id, level, f64::from_bits(left), f64::from_bits(right));
+            db.put(self.wtxn, &key, &docids)?;
+        }
+
+        documents_ids |= self.index.number_faceted_documents_ids(self.wtxn, field_id)?;
+        self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &documents_ids)?;
+
+        Ok(true)
+    }
+
+    /// Adds the documents ids of the given strings, which must already be in the level 0,
+    /// to the groups containing them. Returns `false`, without writing anything, when the
+    /// levels of the field don't match its level 0 anymore and must be computed again.
+    fn update_string_levels(
+        &mut self,
+        field_id: FieldId,
+        values: &[(String, RoaringBitmap)],
+    ) -> Result<bool> {
+        let db_name = Some(crate::index::db_name::FACET_ID_STRING_DOCIDS);
+        let db = self.index.facet_id_string_docids.remap_types::<ByteSlice, ByteSlice>();
+        let level_prefix = |level: u8| {
+            let mut prefix = field_id.to_be_bytes().to_vec();
+            prefix.push(level);
+            prefix
+        };
+
+        let first_level_size = db
+            .remap_data_type::<DecodeIgnore>()
+            .prefix_iter(self.wtxn, &level_prefix(0))?
+            .fold(Ok(0usize), |count, result| result.and(count).map(|c| c + 1))?;
+
+        // The groups refer to the strings by position, the last group of each
+        // level must end on the last string of the level 0.
+        let mut last_level = 0;
+        for level in 1..=u8::MAX {
+            match db
+                .remap_data_type::<DecodeIgnore>()
+                .rev_prefix_iter(self.wtxn, &level_prefix(level))?
+                .next()
+            {
+                Some(result) => {
+                    let (key, _) = result?;
+                    let (_, _, _, right) = FacetLevelValueU32Codec::bytes_decode(key)
+                        .ok_or(SerializationError::Decoding { db_name })?;
+                    if right as usize + 1 != first_level_size {
+                        return Ok(false);
+                    }
+                    last_level = level;
+                }
+                None => break,
+            }
+        }
+        if last_level != self.number_of_levels(field_id, first_level_size) {
+            return Ok(false);
+        }
+
+        let mut values: Vec<_> = values.iter().collect();
+        values.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        // The groups of the level 1 store their bounds, the strings are found in them in
+        // order, the groups of the higher levels contain the positions of these groups.
+        let mut added_docids: BTreeMap<(u8, u32, u32), RoaringBitmap> = BTreeMap::new();
+        let mut documents_ids = RoaringBitmap::new();
+        let mut values = values.into_iter().peekable();
+        for result in db.prefix_iter(self.wtxn, &level_prefix(1))? {
+            let (key, value) = result?;
+            let (_, _, left_id, right_id) = FacetLevelValueU32Codec::bytes_decode(key)
+                .ok_or(SerializationError::Decoding { db_name })?;
+            let (bounds, ()) = FacetStringZeroBoundsValueCodec::<DecodeIgnore>::bytes_decode(value)
+                .ok_or(SerializationError::Decoding { db_name })?;
+            let (left, right) = bounds.ok_or(SerializationError::Decoding { db_name })?;
+
+            while let Some((string, docids)) = values.next_if(|(s, _)| s.as_str() <= right) {
+                if string.as_str() < left {
+                    return Ok(false);
+                }
+                *added_docids.entry((1, left_id, right_id)).or_default() |= docids;
+                documents_ids |= docids;
+            }
+        }
+        if values.next().is_some() {
+            return Ok(false);
+        }
+
+        for level in 2..=last_level {
+            let lower_groups: Vec<_> = added_docids
+                .range((level - 1, 0, 0)..=(level - 1, u32::MAX, u32::MAX))
+                .map(|(&(_, left_id, _), docids)| (left_id, docids.clone()))
+                .collect();
+            let level = NonZeroU8::new(level).unwrap();
+            for (position, docids) in lower_groups {
+                let range = (field_id, level, 0, 0)..=(field_id, level, position, u32::MAX);
+                let db = db.remap_types::<FacetLevelValueU32Codec, DecodeIgnore>();
+                match db.rev_range(self.wtxn, &range)?.next().transpose()? {
+                    Some(((_, _, left_id, right_id), ())) if right_id >= position => {
+                        *added_docids.entry((level.get(), left_id, right_id)).or_default() |=
+                            docids;
+                    }
+                    _ => return Ok(false),
+                }
+            }
+        }
+
+        // The groups keep their bounds, only their documents ids are updated.
+        for ((level, left_id, right_id), docids) in added_docids {
+            let key = (field_id, NonZeroU8::new(level).unwrap(), left_id, right_id);
+            let key = FacetLevelValueU32Codec::bytes_encode(&key).ok_or(Error::Encoding)?;
+            let value = db.get(self.wtxn, &key)?.ok_or(SerializationError::Decoding { db_name })?;
+            let (bounds, group_docids) =
+                FacetStringZeroBoundsValueCodec::<CboRoaringBitmapCodec>::bytes_decode(value)
+                    .ok_or(SerializationError::Decoding { db_name })?;
+            let bounds = bounds.map(|(left, right)| (left.to_string(), right.to_string()));
+            let bounds = bounds.as_ref().map(|(left, right)| (left.as_str(), right.as_str()));
+            let data = (bounds, group_docids | docids);
+            let data =
+                FacetStringZeroBoundsValueCodec::<CboRoaringBitmapCodec>::bytes_encode(&data)
+                    .ok_or(Error::Encoding)?;
+            db.put(self.wtxn, &key, &data)?;
+        }
+
+        documents_ids |= self.index.string_faceted_documents_ids(self.wtxn, field_id)?;
+        self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &documents_ids)?;
+
+        Ok(true)
+    }
+
+    /// Returns the number of levels, above the level 0, of a field with this number of values.
+    fn number_of_levels(&self, field_id: FieldId, first_level_size: usize) -> u8 {
+        if self.sortable_only_fields.contains(&field_id) {
+            return 0;
+        }
+        (1u8..)
+            .map(|l| self.level_group_size.get().pow(l as u32))
+            .take_while(|s| first_level_size / *s >= self.min_level_size.get())
+            .count() as u8
+    }
 }
 
 fn clear_field_number_levels<'t>(
@@ -342,3 +673,145 @@ fn write_string_entry(
     writer.insert(&key, &data)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+    use serde_json::json;
+
+    use super::*;
+    use crate::search::FacetNumberIter;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::Filter;
+
+    /// Adds the documents with the given ids, their prices and colors are taken among 20 and 5
+    /// values, or are new values when `new_values` is set.
+    fn add_documents<'i>(
+        wtxn: &mut heed::RwTxn<'i, '_>,
+        index: &'i Index,
+        ids: std::ops::Range<u32>,
+        new_values: bool,
+    ) {
+        let colors = ["amber", "blue", "coral", "denim", "ebony"];
+        let documents: Vec<_> = ids
+            .map(|id| match new_values {
+                true => json!({ "id": id, "price": 1000, "color": "zinc" }),
+                false => {
+                    json!({ "id": id, "price": id % 20 * 10, "color": colors[id as usize % 5] })
+                }
+            })
+            .collect();
+        let content = documents!(documents);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig {
+            facet_level_group_size: NonZeroUsize::new(2),
+            facet_min_level_size: NonZeroUsize::new(2),
+            ..IndexDocumentsConfig::default()
+        };
+        let mut builder = IndexDocuments::new(wtxn, index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+    }
+
+    #[test]
+    fn incremental_levels() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("price"), S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let levels = |rtxn: &heed::RoTxn| -> Vec<(Vec<u8>, Vec<u8>)> {
+            let numbers = index.facet_id_f64_docids.remap_types::<ByteSlice, ByteSlice>();
+            let strings = index.facet_id_string_docids.remap_types::<ByteSlice, ByteSlice>();
+            let entries = numbers.iter(rtxn).unwrap().chain(strings.iter(rtxn).unwrap());
+            entries
+                .map(Result::unwrap)
+                .filter(|(key, _)| key[2] != 0)
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .collect()
+        };
+        let docids = |rtxn: &heed::RoTxn, filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+
+        add_documents(&mut wtxn, &index, 0..20, false);
+        let before = levels(&wtxn);
+        assert!(!before.is_empty());
+
+        // The documents with known values are added to the groups, which are kept.
+        add_documents(&mut wtxn, &index, 20..40, false);
+        let after = levels(&wtxn);
+        let keys = |levels: &[(Vec<u8>, Vec<u8>)]| {
+            levels.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&before), keys(&after));
+        assert_ne!(before, after);
+        assert_eq!(docids(&wtxn, "price >= 150"), vec![15, 16, 17, 18, 19, 35, 36, 37, 38, 39]);
+        assert_eq!(docids(&wtxn, "color = coral AND price < 100"), vec![2, 7, 22, 27]);
+
+        // The new values make the levels be computed again, like they would from scratch.
+        add_documents(&mut wtxn, &index, 40..41, true);
+        let incremental = levels(&wtxn);
+        assert_eq!(docids(&wtxn, "price > 200"), vec![40]);
+        assert_eq!(docids(&wtxn, "color = zinc"), vec![40]);
+
+        let mut builder = Facets::new(&mut wtxn, &index);
+        builder.level_group_size(NonZeroUsize::new(2).unwrap());
+        builder.min_level_size(NonZeroUsize::new(2).unwrap());
+        builder.execute().unwrap();
+        assert_eq!(incremental, levels(&wtxn));
+    }
+
+    #[test]
+    fn sortable_only_fields_have_no_levels() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+        add_documents(&mut wtxn, &index, 0..20, false);
+
+        let levels_count = |rtxn: &heed::RoTxn, name: &str| {
+            let field_id = index.fields_ids_map(rtxn).unwrap().id(name).unwrap();
+            let numbers = index.facet_id_f64_docids.remap_types::<ByteSlice, DecodeIgnore>();
+            let strings = index.facet_id_string_docids.remap_types::<ByteSlice, DecodeIgnore>();
+            let entries = numbers
+                .prefix_iter(rtxn, &field_id.to_be_bytes())
+                .unwrap()
+                .chain(strings.prefix_iter(rtxn, &field_id.to_be_bytes()).unwrap());
+            entries.map(Result::unwrap).filter(|(key, _)| key[2] != 0).count()
+        };
+        assert_eq!(levels_count(&wtxn, "price"), 0);
+        assert_ne!(levels_count(&wtxn, "color"), 0);
+
+        // The documents are still sorted by their level 0.
+        let price = index.fields_ids_map(&wtxn).unwrap().id("price").unwrap();
+        let candidates = index.documents_ids(&wtxn).unwrap();
+        let iter = FacetNumberIter::new_reverse_reducing(&wtxn, &index, price, candidates);
+        let (highest, docids) = iter.unwrap().next().unwrap().unwrap();
+        assert_eq!(highest, 190.0);
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![19]);
+
+        // The levels are computed once the field is filterable too.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color"), S("price") });
+        builder.execute(|_| ()).unwrap();
+        assert_ne!(levels_count(&wtxn, "price"), 0);
+    }
+}
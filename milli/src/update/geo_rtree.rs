@@ -0,0 +1,84 @@
+use roaring::RoaringBitmap;
+use rstar::RTree;
+
+use crate::GeoPoint;
+
+/// When the points changed by an update represent more than this fraction of the points
+/// of the rtree, it is entirely rebuilt with a bulk load instead of being updated point by
+/// point. A packed rtree is faster to build and to query than one built by insertions.
+pub(crate) const BULK_LOAD_THRESHOLD: f64 = 0.3;
+
+fn exceeds_threshold(changed: usize, total: usize) -> bool {
+    total != 0 && changed as f64 / total as f64 > BULK_LOAD_THRESHOLD
+}
+
+/// Inserts the points into the rtree, rebuilding it when many points are inserted.
+pub(crate) fn insert_points(rtree: &mut RTree<GeoPoint>, points: Vec<GeoPoint>) {
+    if exceeds_threshold(points.len(), rtree.size() + points.len()) {
+        let mut all_points: Vec<_> = rtree.iter().cloned().collect();
+        all_points.extend(points);
+        *rtree = RTree::bulk_load(all_points);
+    } else {
+        points.into_iter().for_each(|point| rtree.insert(point));
+    }
+}
+
+/// Removes the points of the given documents from the rtree, rebuilding it when many points
+/// are removed. Returns the documents ids whose points have been removed.
+pub(crate) fn remove_points(rtree: &mut RTree<GeoPoint>, docids: &RoaringBitmap) -> RoaringBitmap {
+    let points_to_remove: Vec<_> =
+        rtree.iter().filter(|&point| docids.contains(point.data.0)).cloned().collect();
+    let removed_docids = points_to_remove.iter().map(|point| point.data.0).collect();
+
+    if exceeds_threshold(points_to_remove.len(), rtree.size()) {
+        let remaining_points =
+            rtree.iter().filter(|&point| !docids.contains(point.data.0)).cloned().collect();
+        *rtree = RTree::bulk_load(remaining_points);
+    } else {
+        points_to_remove.iter().for_each(|point| {
+            rtree.remove(point);
+        });
+    }
+
+    removed_docids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lat_lng_to_xyz;
+
+    fn point(docid: u32) -> GeoPoint {
+        let lat_lng = [docid as f64 / 10.0, docid as f64 / 20.0];
+        GeoPoint::new(lat_lng_to_xyz(&lat_lng), (docid, lat_lng))
+    }
+
+    fn docids(rtree: &RTree<GeoPoint>) -> Vec<u32> {
+        let mut docids: Vec<_> = rtree.iter().map(|point| point.data.0).collect();
+        docids.sort_unstable();
+        docids
+    }
+
+    #[test]
+    fn insert_and_remove_points() {
+        let mut rtree = RTree::new();
+
+        // bulk loaded as every point is new.
+        insert_points(&mut rtree, (0..100).map(point).collect());
+        assert_eq!(docids(&rtree), (0..100).collect::<Vec<_>>());
+
+        // inserted one by one.
+        insert_points(&mut rtree, vec![point(100)]);
+        assert_eq!(rtree.size(), 101);
+
+        // removed one by one.
+        let removed = remove_points(&mut rtree, &(0..10).collect());
+        assert_eq!(removed, (0..10).collect());
+        assert_eq!(docids(&rtree), (10..101).collect::<Vec<_>>());
+
+        // bulk loaded from the remaining points, unknown documents are ignored.
+        let removed = remove_points(&mut rtree, &(50..200).collect());
+        assert_eq!(removed, (50..101).collect());
+        assert_eq!(docids(&rtree), (10..50).collect::<Vec<_>>());
+    }
+}
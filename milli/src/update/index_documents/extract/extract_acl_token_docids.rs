@@ -0,0 +1,76 @@
+use std::io;
+use std::iter::FromIterator;
+
+use roaring::RoaringBitmap;
+use serde_json::Value;
+
+use super::helpers::{
+    create_tracked_sorter, merge_roaring_bitmaps, serialize_roaring_bitmap, sorter_into_reader,
+    try_split_array_at, GrenadParameters, TrackedTmpFile,
+};
+use crate::error::SerializationError;
+use crate::index::db_name::DOCUMENTS;
+use crate::limits::MAX_LMDB_KEY_LENGTH;
+use crate::{FieldId, InternalError, Result, UserError};
+
+/// Extracts the access control tokens contained in each document under the `_acl` field,
+/// a string or an array of strings, a `null` value grants no token.
+///
+/// Returns a grenad reader with the list of extracted tokens and
+/// documents ids from the given chunk of documents.
+#[logging_timer::time]
+pub fn extract_acl_token_docids<R: io::Read + io::Seek>(
+    obkv_documents: grenad::Reader<R>,
+    indexer: GrenadParameters,
+    primary_key_id: FieldId,
+    acl_field_id: FieldId,
+) -> Result<grenad::Reader<TrackedTmpFile>> {
+    let max_memory = indexer.max_memory_by_thread();
+
+    let mut acl_token_docids_sorter =
+        create_tracked_sorter(merge_roaring_bitmaps, &indexer, max_memory);
+
+    let mut value_buffer = Vec::new();
+    let mut cursor = obkv_documents.into_cursor()?;
+    while let Some((key, value)) = cursor.move_on_next()? {
+        let (document_id_bytes, _) = try_split_array_at(key)
+            .ok_or_else(|| SerializationError::Decoding { db_name: Some(DOCUMENTS) })?;
+        let document_id = u32::from_be_bytes(document_id_bytes);
+
+        let obkv = obkv::KvReader::<FieldId>::new(value);
+        let acl: Value = match obkv.get(acl_field_id) {
+            Some(acl) => serde_json::from_slice(acl).map_err(InternalError::SerdeJson)?,
+            None => continue,
+        };
+
+        let tokens: Option<Vec<&str>> = match &acl {
+            Value::Null => Some(Vec::new()),
+            Value::String(token) => Some(vec![token]),
+            Value::Array(values) => values.iter().map(Value::as_str).collect(),
+            _ => None,
+        };
+
+        match tokens {
+            Some(tokens) => {
+                let bitmap = RoaringBitmap::from_iter(Some(document_id));
+                serialize_roaring_bitmap(&bitmap, &mut value_buffer)?;
+                // the tokens that can't be stored as an LMDB key are ignored.
+                let tokens = tokens
+                    .into_iter()
+                    .filter(|token| !token.is_empty() && token.len() <= MAX_LMDB_KEY_LENGTH);
+                for token in tokens {
+                    acl_token_docids_sorter.insert(token.as_bytes(), &value_buffer)?;
+                }
+            }
+            None => {
+                // All document must have a primary key so we can unwrap safely here
+                let primary_key = obkv.get(primary_key_id).unwrap();
+                let primary_key =
+                    serde_json::from_slice(primary_key).map_err(InternalError::SerdeJson)?;
+                Err(UserError::InvalidAclField { document_id: primary_key, value: acl })?
+            }
+        }
+    }
+
+    sorter_into_reader(acl_token_docids_sorter, indexer)
+}
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::convert::TryInto;
-use std::fs::File;
+use std::iter::FromIterator;
 use std::{io, mem, str};
 
 use meilisearch_tokenizer::token::SeparatorKind;
@@ -8,36 +8,48 @@ use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, Token, TokenKind};
 use roaring::RoaringBitmap;
 use serde_json::Value;
 
-use super::helpers::{concat_u32s_array, create_sorter, sorter_into_reader, GrenadParameters};
+use super::helpers::{
+    concat_u32s_array, create_tracked_sorter, merge_roaring_bitmaps, serialize_roaring_bitmap,
+    sorter_into_reader, GrenadParameters, TrackedTmpFile,
+};
 use crate::error::{InternalError, SerializationError};
-use crate::{absolute_from_relative_position, FieldId, Result, MAX_POSITION_PER_ATTRIBUTE};
+use crate::limits::MAX_WORD_LENGTH;
+use crate::tokenization::mark_stop_words;
+use crate::{
+    absolute_from_relative_position, FieldId, Result, Tokenize, TokenizerSettings,
+    MAX_POSITION_PER_ATTRIBUTE,
+};
 
 /// Extracts the word and positions where this word appear and
 /// prefixes it by the document id.
 ///
-/// Returns the generated internal documents ids and a grenad reader
-/// with the list of extracted words from the given chunk of documents.
+/// Returns the generated internal documents ids, a grenad reader
+/// with the list of extracted words from the given chunk of documents
+/// and a grenad reader with the words as written in the documents, only
+/// lowercased, and the documents ids containing them.
 #[logging_timer::time]
 pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     searchable_fields: &Option<HashSet<FieldId>>,
     stop_words: Option<&fst::Set<&[u8]>>,
+    tokenizer_settings: &TokenizerSettings,
+    tokenizer: Option<&dyn Tokenize>,
     max_positions_per_attributes: Option<u32>,
-) -> Result<(RoaringBitmap, grenad::Reader<File>)> {
+    max_word_length: Option<usize>,
+) -> Result<(RoaringBitmap, grenad::Reader<TrackedTmpFile>, grenad::Reader<TrackedTmpFile>)> {
     let max_positions_per_attributes = max_positions_per_attributes
         .map_or(MAX_POSITION_PER_ATTRIBUTE, |max| max.min(MAX_POSITION_PER_ATTRIBUTE));
+    let max_word_length = max_word_length.map_or(MAX_WORD_LENGTH, |max| max.min(MAX_WORD_LENGTH));
     let max_memory = indexer.max_memory_by_thread();
 
     let mut documents_ids = RoaringBitmap::new();
-    let mut docid_word_positions_sorter = create_sorter(
-        concat_u32s_array,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory,
-    );
+    let mut docid_word_positions_sorter =
+        create_tracked_sorter(concat_u32s_array, &indexer, max_memory);
+    let mut exact_word_docids_sorter =
+        create_tracked_sorter(merge_roaring_bitmaps, &indexer, max_memory);
 
+    let mut value_buffer = Vec::new();
     let mut key_buffer = Vec::new();
     let mut field_buffer = String::new();
     let mut config = AnalyzerConfig::default();
@@ -55,6 +67,8 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
         let obkv = obkv::KvReader::<FieldId>::new(value);
 
         documents_ids.push(document_id);
+        let document_bitmap = RoaringBitmap::from_iter(Some(document_id));
+        serialize_roaring_bitmap(&document_bitmap, &mut value_buffer)?;
         key_buffer.clear();
         key_buffer.extend_from_slice(&document_id.to_be_bytes());
 
@@ -64,16 +78,35 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
                     serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
                 field_buffer.clear();
                 if let Some(field) = json_to_string(&value, &mut field_buffer) {
-                    let analyzed = analyzer.analyze(field);
-                    let tokens = process_tokens(analyzed.tokens())
+                    let analyzed;
+                    let tokens = match tokenizer {
+                        Some(tokenizer) => tokenizer.tokenize(field),
+                        None => {
+                            analyzed = analyzer.analyze(field);
+                            analyzed.tokens().collect()
+                        }
+                    };
+                    let tokens = tokenizer_settings.retokenize_field(field_id, field, tokens);
+                    let tokens = mark_stop_words(tokens, stop_words);
+                    let tokens = process_tokens(tokens.into_iter())
                         .take_while(|(p, _)| (*p as u32) < max_positions_per_attributes);
 
                     for (index, token) in tokens {
+                        let original = field.get(token.byte_start..token.byte_end);
                         let token = token.text().trim();
-                        if !token.is_empty() {
+                        if !token.is_empty() && token.len() <= max_word_length {
                             key_buffer.truncate(mem::size_of::<u32>());
                             key_buffer.extend_from_slice(token.as_bytes());
 
+                            // The exact word is the original text of the token, only lowercased,
+                            // it can't be found again by the search when it is too long.
+                            if let Some(original) = original.map(|o| o.trim().to_lowercase()) {
+                                if !original.is_empty() && original.len() <= max_word_length {
+                                    exact_word_docids_sorter
+                                        .insert(original.as_bytes(), &value_buffer)?;
+                                }
+                            }
+
                             let position: u16 = index
                                 .try_into()
                                 .map_err(|_| SerializationError::InvalidNumberSerialization)?;
@@ -87,7 +120,9 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
         }
     }
 
-    sorter_into_reader(docid_word_positions_sorter, indexer).map(|reader| (documents_ids, reader))
+    let docid_word_positions = sorter_into_reader(docid_word_positions_sorter, indexer.clone())?;
+    let exact_word_docids = sorter_into_reader(exact_word_docids_sorter, indexer)?;
+    Ok((documents_ids, docid_word_positions, exact_word_docids))
 }
 
 /// Transform a JSON value into a string that can be indexed.
@@ -0,0 +1,42 @@
+use std::{io, str};
+
+use heed::BytesEncode;
+
+use super::helpers::{
+    create_tracked_sorter, merge_cbo_roaring_bitmaps, sorter_into_reader, try_split_array_at,
+    GrenadParameters, TrackedTmpFile,
+};
+use crate::facet::parse_date;
+use crate::heed_codec::facet::FieldIdDateCodec;
+use crate::{FieldId, Result};
+
+/// Extracts the timestamps of the facet strings that are RFC3339 dates
+/// and the documents ids where these dates appear.
+///
+/// Returns a grenad reader with the list of extracted dates and
+/// documents ids from the given chunk of docid facet string positions.
+#[logging_timer::time]
+pub fn extract_facet_date_docids<R: io::Read + io::Seek>(
+    docid_fid_facet_string: grenad::Reader<R>,
+    indexer: GrenadParameters,
+) -> Result<grenad::Reader<TrackedTmpFile>> {
+    let max_memory = indexer.max_memory_by_thread();
+
+    let mut facet_date_docids_sorter =
+        create_tracked_sorter(merge_cbo_roaring_bitmaps, &indexer, max_memory);
+
+    let mut cursor = docid_fid_facet_string.into_cursor()?;
+    while let Some((key, original_value_bytes)) = cursor.move_on_next()? {
+        let (field_id_bytes, bytes) = try_split_array_at(key).unwrap();
+        let field_id = FieldId::from_be_bytes(field_id_bytes);
+        let (document_id_bytes, _normalized_value_bytes) = try_split_array_at(bytes).unwrap();
+        let document_id = u32::from_be_bytes(document_id_bytes);
+
+        if let Some(timestamp) = parse_date(str::from_utf8(original_value_bytes)?) {
+            let key_bytes = FieldIdDateCodec::bytes_encode(&(field_id, timestamp)).unwrap();
+            facet_date_docids_sorter.insert(key_bytes, document_id.to_ne_bytes())?;
+        }
+    }
+
+    sorter_into_reader(facet_date_docids_sorter, indexer)
+}
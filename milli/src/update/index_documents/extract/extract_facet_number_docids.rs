@@ -1,10 +1,10 @@
-use std::fs::File;
 use std::io;
 
 use heed::{BytesDecode, BytesEncode};
 
 use super::helpers::{
-    create_sorter, merge_cbo_roaring_bitmaps, sorter_into_reader, GrenadParameters,
+    create_tracked_sorter, merge_cbo_roaring_bitmaps, sorter_into_reader, GrenadParameters,
+    TrackedTmpFile,
 };
 use crate::heed_codec::facet::{FacetLevelValueF64Codec, FieldDocIdFacetF64Codec};
 use crate::Result;
@@ -17,16 +17,11 @@ use crate::Result;
 pub fn extract_facet_number_docids<R: io::Read + io::Seek>(
     docid_fid_facet_number: grenad::Reader<R>,
     indexer: GrenadParameters,
-) -> Result<grenad::Reader<File>> {
+) -> Result<grenad::Reader<TrackedTmpFile>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut facet_number_docids_sorter = create_sorter(
-        merge_cbo_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory,
-    );
+    let mut facet_number_docids_sorter =
+        create_tracked_sorter(merge_cbo_roaring_bitmaps, &indexer, max_memory);
 
     let mut cursor = docid_fid_facet_number.into_cursor()?;
     while let Some((key_bytes, _)) = cursor.move_on_next()? {
@@ -1,12 +1,11 @@
-use std::fs::File;
 use std::iter::FromIterator;
 use std::{io, str};
 
 use roaring::RoaringBitmap;
 
 use super::helpers::{
-    create_sorter, keep_first_prefix_value_merge_roaring_bitmaps, sorter_into_reader,
-    try_split_array_at, GrenadParameters,
+    create_tracked_sorter, keep_first_prefix_value_merge_roaring_bitmaps, sorter_into_reader,
+    try_split_array_at, GrenadParameters, TrackedTmpFile,
 };
 use crate::heed_codec::facet::{encode_prefix_string, FacetStringLevelZeroCodec};
 use crate::{FieldId, Result};
@@ -19,16 +18,11 @@ use crate::{FieldId, Result};
 pub fn extract_facet_string_docids<R: io::Read + io::Seek>(
     docid_fid_facet_string: grenad::Reader<R>,
     indexer: GrenadParameters,
-) -> Result<grenad::Reader<File>> {
+) -> Result<grenad::Reader<TrackedTmpFile>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut facet_string_docids_sorter = create_sorter(
-        keep_first_prefix_value_merge_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory,
-    );
+    let mut facet_string_docids_sorter =
+        create_tracked_sorter(keep_first_prefix_value_merge_roaring_bitmaps, &indexer, max_memory);
 
     let mut key_buffer = Vec::new();
     let mut value_buffer = Vec::new();
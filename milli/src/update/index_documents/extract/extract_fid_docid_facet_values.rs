@@ -1,98 +1,136 @@
 use std::collections::HashSet;
-use std::fs::File;
 use std::io;
 use std::mem::size_of;
 
 use heed::zerocopy::AsBytes;
 use serde_json::Value;
 
-use super::helpers::{create_sorter, keep_first, sorter_into_reader, GrenadParameters};
+use super::helpers::{
+    create_tracked_sorter, keep_first, sorter_into_reader, GrenadParameters, TrackedTmpFile,
+};
+use super::NestedField;
 use crate::error::InternalError;
 use crate::facet::value_encoding::f64_into_bytes;
+use crate::limits::MAX_FACET_VALUE_LENGTH;
 use crate::{DocumentId, FieldId, Result};
 
-/// Extracts the facet values of each faceted field of each document.
+/// Extracts the facet values of each faceted field of each document,
+/// the values of the nested faceted fields are extracted from their top-level field.
 ///
-/// Returns the generated grenad reader containing the docid the fid and the orginal value as key
-/// and the normalized value as value extracted from the given chunk of documents.
+/// Returns the generated grenad readers containing the docid the fid and the orginal value as key
+/// and the normalized value as value extracted from the given chunk of documents, for the numbers,
+/// the strings and the booleans, the booleans are not faceted as strings.
 #[logging_timer::time]
 pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     faceted_fields: &HashSet<FieldId>,
-) -> Result<(grenad::Reader<File>, grenad::Reader<File>)> {
+    nested_faceted_fields: &[NestedField],
+    max_facet_value_length: Option<usize>,
+) -> Result<(
+    grenad::Reader<TrackedTmpFile>,
+    grenad::Reader<TrackedTmpFile>,
+    grenad::Reader<TrackedTmpFile>,
+)> {
     let max_memory = indexer.max_memory_by_thread();
+    let max_facet_value_length = max_facet_value_length
+        .map_or(MAX_FACET_VALUE_LENGTH, |max| max.min(MAX_FACET_VALUE_LENGTH));
 
-    let mut fid_docid_facet_numbers_sorter = create_sorter(
-        keep_first,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
-    );
+    let mut fid_docid_facet_numbers_sorter =
+        create_tracked_sorter(keep_first, &indexer, max_memory.map(|m| m / 3));
 
-    let mut fid_docid_facet_strings_sorter = create_sorter(
-        keep_first,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
-    );
+    let mut fid_docid_facet_strings_sorter =
+        create_tracked_sorter(keep_first, &indexer, max_memory.map(|m| m / 3));
+
+    let mut fid_docid_facet_bools_sorter =
+        create_tracked_sorter(keep_first, &indexer, max_memory.map(|m| m / 3));
 
     let mut key_buffer = Vec::new();
     let mut cursor = obkv_documents.into_cursor()?;
     while let Some((docid_bytes, value)) = cursor.move_on_next()? {
         let obkv = obkv::KvReader::new(value);
 
+        let mut facet_values = Vec::new();
         for (field_id, field_bytes) in obkv.iter() {
             if faceted_fields.contains(&field_id) {
                 let value =
                     serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
-                let (numbers, strings) = extract_facet_values(&value);
+                facet_values.push((field_id, extract_facet_values(&value)));
+            }
+        }
+
+        for nested_field in nested_faceted_fields {
+            if let Some(field_bytes) = obkv.get(nested_field.parent_id) {
+                let value: Value =
+                    serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
+                let mut numbers = Vec::new();
+                let mut strings = Vec::new();
+                let mut bools = Vec::new();
+                for value in nested_field.values(&value) {
+                    let (value_numbers, value_strings, value_bools) = extract_facet_values(value);
+                    numbers.extend(value_numbers);
+                    strings.extend(value_strings);
+                    bools.extend(value_bools);
+                }
+                facet_values.push((nested_field.field_id, (numbers, strings, bools)));
+            }
+        }
 
-                key_buffer.clear();
+        for (field_id, (numbers, strings, bools)) in facet_values {
+            key_buffer.clear();
 
-                // prefix key with the field_id and the document_id
-                key_buffer.extend_from_slice(&field_id.to_be_bytes());
-                key_buffer.extend_from_slice(&docid_bytes);
+            // prefix key with the field_id and the document_id
+            key_buffer.extend_from_slice(&field_id.to_be_bytes());
+            key_buffer.extend_from_slice(&docid_bytes);
 
-                // insert facet numbers in sorter
-                for number in numbers {
-                    key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
-                    if let Some(value_bytes) = f64_into_bytes(number) {
-                        key_buffer.extend_from_slice(&value_bytes);
-                        key_buffer.extend_from_slice(&number.to_be_bytes());
+            // insert facet numbers in sorter
+            for number in numbers {
+                key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
+                if let Some(value_bytes) = f64_into_bytes(number) {
+                    key_buffer.extend_from_slice(&value_bytes);
+                    key_buffer.extend_from_slice(&number.to_be_bytes());
 
-                        fid_docid_facet_numbers_sorter.insert(&key_buffer, ().as_bytes())?;
-                    }
+                    fid_docid_facet_numbers_sorter.insert(&key_buffer, ().as_bytes())?;
                 }
+            }
 
-                // insert  normalized and original facet string in sorter
-                for (normalized, original) in strings.into_iter().filter(|(n, _)| !n.is_empty()) {
-                    key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
-                    key_buffer.extend_from_slice(normalized.as_bytes());
-                    fid_docid_facet_strings_sorter.insert(&key_buffer, original.as_bytes())?;
-                }
+            // insert  normalized and original facet string in sorter
+            let strings = strings
+                .into_iter()
+                .filter(|(n, _)| !n.is_empty() && n.len() <= max_facet_value_length);
+            for (normalized, original) in strings {
+                key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
+                key_buffer.extend_from_slice(normalized.as_bytes());
+                fid_docid_facet_strings_sorter.insert(&key_buffer, original.as_bytes())?;
+            }
+
+            // insert facet booleans in sorter
+            for boolean in bools {
+                key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
+                key_buffer.push(boolean as u8);
+                fid_docid_facet_bools_sorter.insert(&key_buffer, ().as_bytes())?;
             }
         }
     }
 
     Ok((
         sorter_into_reader(fid_docid_facet_numbers_sorter, indexer.clone())?,
-        sorter_into_reader(fid_docid_facet_strings_sorter, indexer)?,
+        sorter_into_reader(fid_docid_facet_strings_sorter, indexer.clone())?,
+        sorter_into_reader(fid_docid_facet_bools_sorter, indexer)?,
     ))
 }
 
-fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
+fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>, Vec<bool>) {
     fn inner_extract_facet_values(
         value: &Value,
         can_recurse: bool,
         output_numbers: &mut Vec<f64>,
         output_strings: &mut Vec<(String, String)>,
+        output_bools: &mut Vec<bool>,
     ) {
         match value {
             Value::Null => (),
-            Value::Bool(b) => output_strings.push((b.to_string(), b.to_string())),
+            Value::Bool(b) => output_bools.push(*b),
             Value::Number(number) => {
                 if let Some(float) = number.as_f64() {
                     output_numbers.push(float);
@@ -105,7 +143,13 @@ fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
             Value::Array(values) => {
                 if can_recurse {
                     for value in values {
-                        inner_extract_facet_values(value, false, output_numbers, output_strings);
+                        inner_extract_facet_values(
+                            value,
+                            false,
+                            output_numbers,
+                            output_strings,
+                            output_bools,
+                        );
                     }
                 }
             }
@@ -115,7 +159,14 @@ fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
 
     let mut facet_number_values = Vec::new();
     let mut facet_string_values = Vec::new();
-    inner_extract_facet_values(value, true, &mut facet_number_values, &mut facet_string_values);
+    let mut facet_bool_values = Vec::new();
+    inner_extract_facet_values(
+        value,
+        true,
+        &mut facet_number_values,
+        &mut facet_string_values,
+        &mut facet_bool_values,
+    );
 
-    (facet_number_values, facet_string_values)
+    (facet_number_values, facet_string_values, facet_bool_values)
 }
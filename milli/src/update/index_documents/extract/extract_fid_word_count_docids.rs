@@ -1,12 +1,9 @@
 use std::collections::HashMap;
-use std::fs::File;
 use std::{cmp, io};
 
-use grenad::Sorter;
-
 use super::helpers::{
-    create_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
-    try_split_array_at, GrenadParameters, MergeFn,
+    create_tracked_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
+    try_split_array_at, GrenadParameters, TrackedSorter, TrackedTmpFile,
 };
 use crate::error::SerializationError;
 use crate::index::db_name::DOCID_WORD_POSITIONS;
@@ -21,16 +18,11 @@ use crate::{relative_from_absolute_position, DocumentId, FieldId, Result};
 pub fn extract_fid_word_count_docids<R: io::Read + io::Seek>(
     docid_word_positions: grenad::Reader<R>,
     indexer: GrenadParameters,
-) -> Result<grenad::Reader<File>> {
+) -> Result<grenad::Reader<TrackedTmpFile>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut fid_word_count_docids_sorter = create_sorter(
-        merge_cbo_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory,
-    );
+    let mut fid_word_count_docids_sorter =
+        create_tracked_sorter(merge_cbo_roaring_bitmaps, &indexer, max_memory);
 
     // This map is assumed to not consume a lot of memory.
     let mut document_fid_wordcount = HashMap::new();
@@ -75,7 +67,7 @@ pub fn extract_fid_word_count_docids<R: io::Read + io::Seek>(
 }
 
 fn drain_document_fid_wordcount_into_sorter(
-    fid_word_count_docids_sorter: &mut Sorter<MergeFn>,
+    fid_word_count_docids_sorter: &mut TrackedSorter,
     document_fid_wordcount: &mut HashMap<FieldId, u32>,
     document_id: DocumentId,
 ) -> Result<()> {
@@ -1,13 +1,17 @@
-use std::fs::File;
+use std::convert::TryInto;
 use std::io;
 
 use concat_arrays::concat_arrays;
+use roaring::RoaringBitmap;
 use serde_json::Value;
 
-use super::helpers::{create_writer, writer_into_reader, GrenadParameters};
-use crate::{FieldId, InternalError, Result, UserError};
+use super::helpers::{
+    create_tracked_writer, tracked_writer_into_reader, GrenadParameters, TrackedTmpFile,
+};
+use crate::{DocumentId, FieldId, InternalError, Result, UserError};
 
-/// Extracts the geographical coordinates contained in each document under the `_geo` field.
+/// Extracts the geographical coordinates contained in each document under the `_geo` field,
+/// the documents whose coordinates didn't change are skipped.
 ///
 /// Returns the generated grenad reader containing the docid as key associated to the (latitude, longitude)
 pub fn extract_geo_points<R: io::Read + io::Seek>(
@@ -15,15 +19,17 @@ pub fn extract_geo_points<R: io::Read + io::Seek>(
     indexer: GrenadParameters,
     primary_key_id: FieldId,
     geo_field_id: FieldId,
-) -> Result<grenad::Reader<File>> {
-    let mut writer = create_writer(
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        tempfile::tempfile()?,
-    );
+    unchanged_documents_ids: &RoaringBitmap,
+) -> Result<grenad::Reader<TrackedTmpFile>> {
+    let mut writer = create_tracked_writer(&indexer)?;
 
     let mut cursor = obkv_documents.into_cursor()?;
     while let Some((docid_bytes, value)) = cursor.move_on_next()? {
+        let docid = docid_bytes.try_into().map(DocumentId::from_be_bytes).unwrap();
+        if unchanged_documents_ids.contains(docid) {
+            continue;
+        }
+
         let obkv = obkv::KvReader::new(value);
         let point: Value = match obkv.get(geo_field_id) {
             Some(point) => serde_json::from_slice(point).map_err(InternalError::SerdeJson)?,
@@ -43,5 +49,5 @@ pub fn extract_geo_points<R: io::Read + io::Seek>(
         }
     }
 
-    Ok(writer_into_reader(writer)?)
+    tracked_writer_into_reader(writer)
 }
@@ -0,0 +1,49 @@
+use std::collections::BTreeSet;
+use std::io;
+
+use super::helpers::{
+    create_tracked_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
+    try_split_array_at, GrenadParameters, TrackedTmpFile,
+};
+use crate::error::SerializationError;
+use crate::index::db_name::DOCID_WORD_POSITIONS;
+use crate::{relative_from_absolute_position, DocumentId, Result};
+
+/// Extracts the words and the field ids in which they appear along with the documents ids.
+///
+/// Returns a grenad reader with the list of extracted words at field ids and
+/// documents ids from the given chunk of docid word positions.
+#[logging_timer::time]
+pub fn extract_word_fid_docids<R: io::Read + io::Seek>(
+    docid_word_positions: grenad::Reader<R>,
+    indexer: GrenadParameters,
+) -> Result<grenad::Reader<TrackedTmpFile>> {
+    let max_memory = indexer.max_memory_by_thread();
+
+    let mut word_fid_docids_sorter =
+        create_tracked_sorter(merge_cbo_roaring_bitmaps, &indexer, max_memory);
+
+    let mut fids = BTreeSet::new();
+    let mut key_buffer = Vec::new();
+    let mut cursor = docid_word_positions.into_cursor()?;
+    while let Some((key, value)) = cursor.move_on_next()? {
+        let (document_id_bytes, word_bytes) = try_split_array_at(key)
+            .ok_or_else(|| SerializationError::Decoding { db_name: Some(DOCID_WORD_POSITIONS) })?;
+        let document_id = DocumentId::from_be_bytes(document_id_bytes);
+
+        // A word generally appears many times in the same field,
+        // we only insert one entry by field.
+        fids.clear();
+        fids.extend(read_u32_ne_bytes(value).map(|p| relative_from_absolute_position(p).0));
+
+        for fid in &fids {
+            key_buffer.clear();
+            key_buffer.extend_from_slice(word_bytes);
+            key_buffer.extend_from_slice(&fid.to_be_bytes());
+
+            word_fid_docids_sorter.insert(&key_buffer, &document_id.to_ne_bytes())?;
+        }
+    }
+
+    sorter_into_reader(word_fid_docids_sorter, indexer)
+}
@@ -1,35 +1,30 @@
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
-use std::fs::File;
 use std::{cmp, io, mem, str, vec};
 
 use super::helpers::{
-    create_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
-    try_split_array_at, GrenadParameters, MergeFn,
+    create_tracked_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
+    try_split_array_at, GrenadParameters, TrackedSorter, TrackedTmpFile,
 };
 use crate::error::SerializationError;
 use crate::index::db_name::DOCID_WORD_POSITIONS;
-use crate::proximity::{positions_proximity, MAX_DISTANCE};
+use crate::proximity::positions_proximity;
 use crate::{DocumentId, Result};
 
 /// Extracts the best proximity between pairs of words and the documents ids where this pair appear.
 ///
 /// Returns a grenad reader with the list of extracted word pairs proximities and
-/// documents ids from the given chunk of docid word positions.
+/// documents ids from the given chunk of docid word positions, up to the given proximity.
 #[logging_timer::time]
 pub fn extract_word_pair_proximity_docids<R: io::Read + io::Seek>(
     docid_word_positions: grenad::Reader<R>,
     indexer: GrenadParameters,
-) -> Result<grenad::Reader<File>> {
+    max_proximity: u32,
+) -> Result<grenad::Reader<TrackedTmpFile>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut word_pair_proximity_docids_sorter = create_sorter(
-        merge_cbo_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
-    );
+    let mut word_pair_proximity_docids_sorter =
+        create_tracked_sorter(merge_cbo_roaring_bitmaps, &indexer, max_memory.map(|m| m / 2));
 
     // This map is assumed to not consume a lot of memory.
     let mut document_word_positions_heap = BinaryHeap::new();
@@ -48,6 +43,7 @@ pub fn extract_word_pair_proximity_docids<R: io::Read + io::Seek>(
             document_word_positions_into_sorter(
                 curr_document_id,
                 document_word_positions_heap,
+                max_proximity,
                 &mut word_pair_proximity_docids_sorter,
             )?;
             current_document_id = Some(document_id);
@@ -69,6 +65,7 @@ pub fn extract_word_pair_proximity_docids<R: io::Read + io::Seek>(
         document_word_positions_into_sorter(
             document_id,
             document_word_positions_heap,
+            max_proximity,
             &mut word_pair_proximity_docids_sorter,
         )?;
     }
@@ -76,14 +73,16 @@ pub fn extract_word_pair_proximity_docids<R: io::Read + io::Seek>(
     sorter_into_reader(word_pair_proximity_docids_sorter, indexer)
 }
 
-/// Fills the list of all pairs of words with the shortest proximity between 1 and 7 inclusive.
+/// Fills the list of all pairs of words with the shortest proximity between 1 and
+/// `max_proximity` inclusive.
 ///
 /// This list is used by the engine to calculate the documents containing words that are
 /// close to each other.
 fn document_word_positions_into_sorter<'b>(
     document_id: DocumentId,
     mut word_positions_heap: BinaryHeap<PeekedWordPosition<vec::IntoIter<u32>>>,
-    word_pair_proximity_docids_sorter: &mut grenad::Sorter<MergeFn>,
+    max_proximity: u32,
+    word_pair_proximity_docids_sorter: &mut TrackedSorter,
 ) -> Result<()> {
     let mut word_pair_proximity = HashMap::new();
     let mut ordered_peeked_word_positions = Vec::new();
@@ -98,7 +97,7 @@ fn document_word_positions_into_sorter<'b>(
         if let Some((head, tail)) = ordered_peeked_word_positions.split_first() {
             for PeekedWordPosition { word, position, .. } in tail {
                 let prox = positions_proximity(head.position, *position);
-                if prox > 0 && prox < MAX_DISTANCE {
+                if prox > 0 && prox <= max_proximity {
                     word_pair_proximity
                         .entry((head.word.clone(), word.clone()))
                         .and_modify(|p| {
@@ -108,7 +107,7 @@ fn document_word_positions_into_sorter<'b>(
 
                     // We also compute the inverse proximity.
                     let prox = prox + 1;
-                    if prox < MAX_DISTANCE {
+                    if prox <= max_proximity {
                         word_pair_proximity
                             .entry((word.clone(), head.word.clone()))
                             .and_modify(|p| {
@@ -1,62 +1,91 @@
+mod extract_acl_token_docids;
 mod extract_docid_word_positions;
+mod extract_facet_date_docids;
 mod extract_facet_number_docids;
 mod extract_facet_string_docids;
 mod extract_fid_docid_facet_values;
 mod extract_fid_word_count_docids;
 mod extract_geo_points;
 mod extract_word_docids;
+mod extract_word_fid_docids;
 mod extract_word_pair_proximity_docids;
 mod extract_word_position_docids;
 
 use std::collections::HashSet;
-use std::fs::File;
+use std::sync::Arc;
+use std::time::Instant;
 
 use crossbeam_channel::Sender;
-use log::debug;
 use rayon::prelude::*;
+use roaring::RoaringBitmap;
+use tracing::debug;
 
+use self::extract_acl_token_docids::extract_acl_token_docids;
 use self::extract_docid_word_positions::extract_docid_word_positions;
+use self::extract_facet_date_docids::extract_facet_date_docids;
 use self::extract_facet_number_docids::extract_facet_number_docids;
 use self::extract_facet_string_docids::extract_facet_string_docids;
 use self::extract_fid_docid_facet_values::extract_fid_docid_facet_values;
 use self::extract_fid_word_count_docids::extract_fid_word_count_docids;
-use self::extract_geo_points::extract_geo_points;
+pub(super) use self::extract_geo_points::extract_geo_points;
 use self::extract_word_docids::extract_word_docids;
+use self::extract_word_fid_docids::extract_word_fid_docids;
 use self::extract_word_pair_proximity_docids::extract_word_pair_proximity_docids;
 use self::extract_word_position_docids::extract_word_position_docids;
 use super::helpers::{
-    as_cloneable_grenad, keep_first_prefix_value_merge_roaring_bitmaps, merge_cbo_roaring_bitmaps,
-    merge_readers, merge_roaring_bitmaps, CursorClonableMmap, GrenadParameters, MergeFn,
+    as_cloneable_grenad, count_merge_conflicts, keep_first_prefix_value_merge_roaring_bitmaps,
+    merge_cbo_roaring_bitmaps, merge_readers, merge_roaring_bitmaps, CursorClonableMmap,
+    GrenadParameters, MergeFn, TrackedTmpFile,
 };
+use super::nested_fields::NestedField;
 use super::{helpers, TypedChunk};
-use crate::{FieldId, Result};
+use crate::update::UpdateIndexingStep;
+use crate::{FieldId, ProximityPrecision, Result, Tokenize, TokenizerSettings};
 
 /// Extract data for each databases from obkv documents in parallel.
 /// Send data in grenad file over provided Sender.
 pub(crate) fn data_from_obkv_documents(
-    obkv_chunks: impl Iterator<Item = Result<grenad::Reader<File>>> + Send,
+    obkv_chunks: impl Iterator<Item = Result<grenad::Reader<TrackedTmpFile>>> + Send,
     indexer: GrenadParameters,
     lmdb_writer_sx: Sender<Result<TypedChunk>>,
+    write_documents: bool,
     searchable_fields: Option<HashSet<FieldId>>,
     faceted_fields: HashSet<FieldId>,
+    nested_faceted_fields: Vec<NestedField>,
     primary_key_id: FieldId,
     geo_field_id: Option<FieldId>,
+    unchanged_geo_documents_ids: Arc<RoaringBitmap>,
+    acl_field_id: Option<FieldId>,
     stop_words: Option<fst::Set<&[u8]>>,
+    tokenizer_settings: &TokenizerSettings,
+    tokenizer: Option<&dyn Tokenize>,
     max_positions_per_attributes: Option<u32>,
+    max_word_length: Option<usize>,
+    max_facet_value_length: Option<usize>,
+    proximity_precision: ProximityPrecision,
+    proximity_window: u32,
 ) -> Result<()> {
     let result: Result<(Vec<_>, (Vec<_>, Vec<_>))> = obkv_chunks
         .par_bridge()
         .map(|result| {
             extract_documents_data(
                 result,
-                indexer,
+                indexer.clone(),
                 lmdb_writer_sx.clone(),
+                write_documents,
                 &searchable_fields,
                 &faceted_fields,
+                &nested_faceted_fields,
                 primary_key_id,
                 geo_field_id,
+                &unchanged_geo_documents_ids,
+                acl_field_id,
                 &stop_words,
+                tokenizer_settings,
+                tokenizer,
                 max_positions_per_attributes,
+                max_word_length,
+                max_facet_value_length,
             )
         })
         .collect();
@@ -66,15 +95,20 @@ pub(crate) fn data_from_obkv_documents(
         (docid_fid_facet_numbers_chunks, docid_fid_facet_strings_chunks),
     ) = result?;
 
-    spawn_extraction_task(
-        docid_word_positions_chunks.clone(),
-        indexer.clone(),
-        lmdb_writer_sx.clone(),
-        extract_word_pair_proximity_docids,
-        merge_cbo_roaring_bitmaps,
-        TypedChunk::WordPairProximityDocids,
-        "word-pair-proximity-docids",
-    );
+    // The pairs of words are not stored when the proximity is computed by attribute.
+    if proximity_precision == ProximityPrecision::Exact {
+        spawn_extraction_task(
+            docid_word_positions_chunks.clone(),
+            indexer.clone(),
+            lmdb_writer_sx.clone(),
+            move |chunk, indexer| {
+                extract_word_pair_proximity_docids(chunk, indexer, proximity_window)
+            },
+            merge_cbo_roaring_bitmaps,
+            TypedChunk::WordPairProximityDocids,
+            "word-pair-proximity-docids",
+        );
+    }
 
     spawn_extraction_task(
         docid_word_positions_chunks.clone(),
@@ -106,6 +140,16 @@ pub(crate) fn data_from_obkv_documents(
         "word-position-docids",
     );
 
+    spawn_extraction_task(
+        docid_word_positions_chunks.clone(),
+        indexer.clone(),
+        lmdb_writer_sx.clone(),
+        extract_word_fid_docids,
+        merge_cbo_roaring_bitmaps,
+        TypedChunk::WordFidDocids,
+        "word-fid-docids",
+    );
+
     spawn_extraction_task(
         docid_fid_facet_strings_chunks.clone(),
         indexer.clone(),
@@ -116,6 +160,16 @@ pub(crate) fn data_from_obkv_documents(
         "field-id-facet-string-docids",
     );
 
+    spawn_extraction_task(
+        docid_fid_facet_strings_chunks.clone(),
+        indexer.clone(),
+        lmdb_writer_sx.clone(),
+        extract_facet_date_docids,
+        merge_cbo_roaring_bitmaps,
+        TypedChunk::FieldIdFacetDateDocids,
+        "field-id-facet-date-docids",
+    );
+
     spawn_extraction_task(
         docid_fid_facet_numbers_chunks.clone(),
         indexer.clone(),
@@ -132,7 +186,7 @@ pub(crate) fn data_from_obkv_documents(
 /// Spawn a new task to extract data for a specific DB using extract_fn.
 /// Generated grenad chunks are merged using the merge_fn.
 /// The result of merged chunks is serialized as TypedChunk using the serialize_fn
-/// and sent into lmdb_writer_sx.
+/// and sent into lmdb_writer_sx, followed by the metrics of the extraction.
 fn spawn_extraction_task<FE, FS>(
     chunks: Vec<grenad::Reader<CursorClonableMmap>>,
     indexer: GrenadParameters,
@@ -142,23 +196,66 @@ fn spawn_extraction_task<FE, FS>(
     serialize_fn: FS,
     name: &'static str,
 ) where
-    FE: Fn(grenad::Reader<CursorClonableMmap>, GrenadParameters) -> Result<grenad::Reader<File>>
+    FE: Fn(
+            grenad::Reader<CursorClonableMmap>,
+            GrenadParameters,
+        ) -> Result<grenad::Reader<TrackedTmpFile>>
         + Sync
         + Send
         + 'static,
-    FS: Fn(grenad::Reader<File>) -> TypedChunk + Sync + Send + 'static,
+    FS: Fn(grenad::Reader<TrackedTmpFile>) -> TypedChunk + Sync + Send + 'static,
 {
+    let span = tracing::debug_span!("extract", database = name);
+    let indexer = indexer.with_tmp_disk_phase(name);
     rayon::spawn(move || {
-        let chunks: Result<Vec<_>> =
-            chunks.into_par_iter().map(|chunk| extract_fn(chunk, indexer.clone())).collect();
-        rayon::spawn(move || match chunks {
-            Ok(chunks) => {
+        let _entered = span.enter();
+        let before = Instant::now();
+        let count_conflicts = indexer.count_merge_conflicts;
+        let chunks: Result<Vec<_>> = chunks
+            .into_par_iter()
+            .map(|chunk| {
+                let (result, conflicts) =
+                    count_merge_conflicts(count_conflicts, || extract_fn(chunk, indexer.clone()));
+                result.map(|chunk| (chunk, conflicts))
+            })
+            .collect();
+        let span = span.clone();
+        rayon::spawn(move || {
+            let _entered = span.enter();
+            let result = chunks.and_then(|chunks| {
+                let (chunks, conflicts): (Vec<_>, Vec<Option<u64>>) = chunks.into_iter().unzip();
+                let mut merge_conflicts: Option<u64> =
+                    count_conflicts.then(|| conflicts.into_iter().flatten().sum());
+                let mut spilled_bytes = 0;
+                for chunk in &chunks {
+                    spilled_bytes += chunk.get_ref().get_ref().metadata()?.len();
+                }
+                let number_of_chunks = chunks.len();
                 debug!("merge {} database", name);
-                let reader = merge_readers(chunks, merge_fn, indexer);
-                let _ = lmdb_writer_sx.send(reader.map(|r| serialize_fn(r)));
-            }
-            Err(e) => {
-                let _ = lmdb_writer_sx.send(Err(e));
+                let (reader, conflicts) = count_merge_conflicts(count_conflicts, || {
+                    merge_readers(chunks, merge_fn, indexer.clone())
+                });
+                let reader = reader?;
+                merge_conflicts = merge_conflicts.zip(conflicts).map(|(a, b)| a + b);
+                let metrics = UpdateIndexingStep::ExtractDatabase {
+                    database: name,
+                    chunks: number_of_chunks,
+                    spilled_bytes,
+                    bytes: reader.get_ref().get_ref().metadata()?.len(),
+                    merge_conflicts,
+                    duration: before.elapsed(),
+                };
+                Ok((reader, metrics))
+            });
+
+            match result {
+                Ok((reader, metrics)) => {
+                    let _ = lmdb_writer_sx.send(Ok(serialize_fn(reader)));
+                    let _ = lmdb_writer_sx.send(Ok(TypedChunk::Metrics(metrics)));
+                }
+                Err(e) => {
+                    let _ = lmdb_writer_sx.send(Err(e));
+                }
             }
         })
     });
@@ -170,51 +267,111 @@ fn spawn_extraction_task<FE, FS>(
 /// - docid_word_positions
 /// - docid_fid_facet_numbers
 /// - docid_fid_facet_strings
+/// - acl_token_docids
 fn extract_documents_data(
-    documents_chunk: Result<grenad::Reader<File>>,
+    documents_chunk: Result<grenad::Reader<TrackedTmpFile>>,
     indexer: GrenadParameters,
     lmdb_writer_sx: Sender<Result<TypedChunk>>,
+    write_documents: bool,
     searchable_fields: &Option<HashSet<FieldId>>,
     faceted_fields: &HashSet<FieldId>,
+    nested_faceted_fields: &[NestedField],
     primary_key_id: FieldId,
     geo_field_id: Option<FieldId>,
+    unchanged_geo_documents_ids: &Arc<RoaringBitmap>,
+    acl_field_id: Option<FieldId>,
     stop_words: &Option<fst::Set<&[u8]>>,
+    tokenizer_settings: &TokenizerSettings,
+    tokenizer: Option<&dyn Tokenize>,
     max_positions_per_attributes: Option<u32>,
+    max_word_length: Option<usize>,
+    max_facet_value_length: Option<usize>,
 ) -> Result<(
     grenad::Reader<CursorClonableMmap>,
     (grenad::Reader<CursorClonableMmap>, grenad::Reader<CursorClonableMmap>),
 )> {
     let documents_chunk = documents_chunk.and_then(|c| unsafe { as_cloneable_grenad(&c) })?;
 
-    let _ = lmdb_writer_sx.send(Ok(TypedChunk::Documents(documents_chunk.clone())));
+    // The documents are not written again when only some databases are rebuilt from them.
+    if write_documents {
+        let _ = lmdb_writer_sx.send(Ok(TypedChunk::Documents(documents_chunk.clone())));
+    }
+
+    if let Some(acl_field_id) = acl_field_id {
+        let documents_chunk_cloned = documents_chunk.clone();
+        let lmdb_writer_sx_cloned = lmdb_writer_sx.clone();
+        let indexer_cloned = indexer.with_tmp_disk_phase("acl-token-docids");
+        rayon::spawn(move || {
+            let result = extract_acl_token_docids(
+                documents_chunk_cloned,
+                indexer_cloned,
+                primary_key_id,
+                acl_field_id,
+            );
+            let _ = lmdb_writer_sx_cloned.send(result.map(TypedChunk::AclTokenDocids));
+        });
+    }
 
     if let Some(geo_field_id) = geo_field_id {
         let documents_chunk_cloned = documents_chunk.clone();
         let lmdb_writer_sx_cloned = lmdb_writer_sx.clone();
+        let unchanged_geo_documents_ids = unchanged_geo_documents_ids.clone();
+        let indexer_cloned = indexer.with_tmp_disk_phase("geo-points");
         rayon::spawn(move || {
-            let result =
-                extract_geo_points(documents_chunk_cloned, indexer, primary_key_id, geo_field_id);
-            let _ = match result {
-                Ok(geo_points) => lmdb_writer_sx_cloned.send(Ok(TypedChunk::GeoPoints(geo_points))),
-                Err(error) => lmdb_writer_sx_cloned.send(Err(error)),
-            };
+            let before = Instant::now();
+            let result = extract_geo_points(
+                documents_chunk_cloned,
+                indexer_cloned.clone(),
+                primary_key_id,
+                geo_field_id,
+                &unchanged_geo_documents_ids,
+            );
+            let result = result.and_then(|geo_points| {
+                let bytes = geo_points.get_ref().get_ref().metadata()?.len();
+                let metrics = UpdateIndexingStep::ExtractDatabase {
+                    database: "geo-points",
+                    chunks: 1,
+                    spilled_bytes: bytes,
+                    bytes,
+                    merge_conflicts: indexer_cloned.count_merge_conflicts.then(|| 0),
+                    duration: before.elapsed(),
+                };
+                Ok((geo_points, metrics))
+            });
+            match result {
+                Ok((geo_points, metrics)) => {
+                    let _ = lmdb_writer_sx_cloned.send(Ok(TypedChunk::GeoPoints(geo_points)));
+                    let _ = lmdb_writer_sx_cloned.send(Ok(TypedChunk::Metrics(metrics)));
+                }
+                Err(error) => {
+                    let _ = lmdb_writer_sx_cloned.send(Err(error));
+                }
+            }
         });
     }
 
     let (docid_word_positions_chunk, docid_fid_facet_values_chunks): (Result<_>, Result<_>) =
         rayon::join(
             || {
-                let (documents_ids, docid_word_positions_chunk) = extract_docid_word_positions(
-                    documents_chunk.clone(),
-                    indexer.clone(),
-                    searchable_fields,
-                    stop_words.as_ref(),
-                    max_positions_per_attributes,
-                )?;
+                let (documents_ids, docid_word_positions_chunk, exact_word_docids_chunk) =
+                    extract_docid_word_positions(
+                        documents_chunk.clone(),
+                        indexer.with_tmp_disk_phase("docid-word-positions"),
+                        searchable_fields,
+                        stop_words.as_ref(),
+                        tokenizer_settings,
+                        tokenizer,
+                        max_positions_per_attributes,
+                        max_word_length,
+                    )?;
 
                 // send documents_ids to DB writer
                 let _ = lmdb_writer_sx.send(Ok(TypedChunk::NewDocumentsIds(documents_ids)));
 
+                // send exact_word_docids_chunk to DB writer
+                let _ =
+                    lmdb_writer_sx.send(Ok(TypedChunk::ExactWordDocids(exact_word_docids_chunk)));
+
                 // send docid_word_positions_chunk to DB writer
                 let docid_word_positions_chunk =
                     unsafe { as_cloneable_grenad(&docid_word_positions_chunk)? };
@@ -224,12 +381,21 @@ fn extract_documents_data(
                 Ok(docid_word_positions_chunk)
             },
             || {
-                let (docid_fid_facet_numbers_chunk, docid_fid_facet_strings_chunk) =
-                    extract_fid_docid_facet_values(
-                        documents_chunk.clone(),
-                        indexer.clone(),
-                        faceted_fields,
-                    )?;
+                let (
+                    docid_fid_facet_numbers_chunk,
+                    docid_fid_facet_strings_chunk,
+                    docid_fid_facet_bools_chunk,
+                ) = extract_fid_docid_facet_values(
+                    documents_chunk.clone(),
+                    indexer.with_tmp_disk_phase("field-id-docid-facet-values"),
+                    faceted_fields,
+                    nested_faceted_fields,
+                    max_facet_value_length,
+                )?;
+
+                // send docid_fid_facet_bools_chunk to DB writer
+                let _ = lmdb_writer_sx
+                    .send(Ok(TypedChunk::FieldIdFacetBoolDocids(docid_fid_facet_bools_chunk)));
 
                 // send docid_fid_facet_numbers_chunk to DB writer
                 let docid_fid_facet_numbers_chunk =
@@ -1,14 +1,16 @@
 use std::borrow::Cow;
 use std::fs::File;
-use std::io::{self, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
 use grenad::{CompressionType, MergerIter, Reader, Sorter};
 use heed::types::ByteSlice;
-use log::debug;
+use tracing::debug;
 
 use super::{ClonableMmap, MergeFn};
-use crate::error::InternalError;
+use crate::error::{InternalError, UserError};
 use crate::Result;
 
 pub type CursorClonableMmap = io::Cursor<ClonableMmap>;
@@ -48,18 +50,46 @@ pub fn create_sorter(
     builder.build()
 }
 
+/// A sorter that spills its chunks in temporary files accounted in the temporary disk usage.
+pub type TrackedSorter = grenad::Sorter<MergeFn, TrackedChunkCreator>;
+
+/// Creates a sorter like [`create_sorter`] but the chunks it spills on disk are accounted,
+/// while they are written, in the temporary disk usage of the grenad parameters.
+pub fn create_tracked_sorter(
+    merge: MergeFn,
+    indexer: &GrenadParameters,
+    max_memory: Option<usize>,
+) -> TrackedSorter {
+    let mut builder = grenad::Sorter::builder(merge);
+    builder.chunk_compression_type(indexer.chunk_compression_type);
+    if let Some(level) = indexer.chunk_compression_level {
+        builder.chunk_compression_level(level);
+    }
+    if let Some(nb_chunks) = indexer.max_nb_chunks {
+        builder.max_nb_chunks(nb_chunks);
+    }
+    if let Some(memory) = max_memory {
+        builder.dump_threshold(memory);
+        builder.allow_realloc(false);
+    }
+    let tmp_disk_usage = indexer.tmp_disk_usage.clone();
+    builder.chunk_creator(TrackedChunkCreator { tmp_disk_usage }).build()
+}
+
+/// Creates a writer into a temporary file accounted in the temporary disk usage.
+pub fn create_tracked_writer(indexer: &GrenadParameters) -> Result<grenad::Writer<TrackedTmpFile>> {
+    let file = TrackedTmpFile::new(indexer.tmp_disk_usage.clone())?;
+    Ok(create_writer(indexer.chunk_compression_type, indexer.chunk_compression_level, file))
+}
+
 pub fn sorter_into_reader(
-    sorter: grenad::Sorter<MergeFn>,
+    sorter: TrackedSorter,
     indexer: GrenadParameters,
-) -> Result<grenad::Reader<File>> {
-    let mut writer = create_writer(
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        tempfile::tempfile()?,
-    );
+) -> Result<grenad::Reader<TrackedTmpFile>> {
+    let mut writer = create_tracked_writer(&indexer)?;
     sorter.write_into_stream_writer(&mut writer)?;
 
-    Ok(writer_into_reader(writer)?)
+    Ok(tracked_writer_into_reader(writer)?)
 }
 
 pub fn writer_into_reader(writer: grenad::Writer<File>) -> Result<grenad::Reader<File>> {
@@ -68,10 +98,19 @@ pub fn writer_into_reader(writer: grenad::Writer<File>) -> Result<grenad::Reader
     grenad::Reader::new(file).map_err(Into::into)
 }
 
+/// The bytes of the file stay accounted in the temporary disk usage until the reader is dropped.
+pub fn tracked_writer_into_reader(
+    writer: grenad::Writer<TrackedTmpFile>,
+) -> Result<grenad::Reader<TrackedTmpFile>> {
+    let mut file = writer.into_inner()?;
+    file.seek(SeekFrom::Start(0))?;
+    grenad::Reader::new(file).map_err(Into::into)
+}
+
 pub unsafe fn as_cloneable_grenad(
-    reader: &grenad::Reader<File>,
+    reader: &grenad::Reader<TrackedTmpFile>,
 ) -> Result<grenad::Reader<CursorClonableMmap>> {
-    let file = reader.get_ref();
+    let file = reader.get_ref().get_ref();
     let mmap = memmap2::Mmap::map(file)?;
     let cursor = io::Cursor::new(ClonableMmap::from(mmap));
     let reader = grenad::Reader::new(cursor)?;
@@ -82,29 +121,27 @@ pub fn merge_readers<R: io::Read + io::Seek>(
     readers: Vec<grenad::Reader<R>>,
     merge_fn: MergeFn,
     indexer: GrenadParameters,
-) -> Result<grenad::Reader<File>> {
+) -> Result<grenad::Reader<TrackedTmpFile>> {
     let mut merger_builder = grenad::MergerBuilder::new(merge_fn);
     for reader in readers {
         merger_builder.push(reader.into_cursor()?);
     }
 
     let merger = merger_builder.build();
-    let mut writer = create_writer(
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        tempfile::tempfile()?,
-    );
+    let mut writer = create_tracked_writer(&indexer)?;
     merger.write_into_stream_writer(&mut writer)?;
 
-    Ok(writer_into_reader(writer)?)
+    Ok(tracked_writer_into_reader(writer)?)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct GrenadParameters {
     pub chunk_compression_type: CompressionType,
     pub chunk_compression_level: Option<u32>,
     pub max_memory: Option<usize>,
     pub max_nb_chunks: Option<usize>,
+    pub tmp_disk_usage: TmpDiskUsage,
+    pub count_merge_conflicts: bool,
 }
 
 impl Default for GrenadParameters {
@@ -114,6 +151,8 @@ impl Default for GrenadParameters {
             chunk_compression_level: None,
             max_memory: None,
             max_nb_chunks: None,
+            tmp_disk_usage: TmpDiskUsage::default(),
+            count_merge_conflicts: false,
         }
     }
 }
@@ -125,6 +164,163 @@ impl GrenadParameters {
     pub fn max_memory_by_thread(&self) -> Option<usize> {
         self.max_memory.map(|max_memory| max_memory / rayon::current_num_threads())
     }
+
+    /// Returns the same parameters but the temporary files written with them are reported
+    /// to the given phase when the maximum temporary disk usage is reached.
+    pub fn with_tmp_disk_phase(&self, phase: &'static str) -> GrenadParameters {
+        GrenadParameters { tmp_disk_usage: self.tmp_disk_usage.with_phase(phase), ..self.clone() }
+    }
+}
+
+/// Sums the sizes of the temporary files written by the phases of an indexing, shared by
+/// all the threads, and fails as soon as the sum goes beyond the maximum allowed.
+///
+/// The bytes are accounted before being written in a [`TrackedTmpFile`] and released when
+/// the file is dropped, the sum is the disk space used by the files alive at any time.
+/// The memory maps of a file are not accounted, they keep its space used once it is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct TmpDiskUsage {
+    max: Option<u64>,
+    used: Arc<AtomicU64>,
+    phase: &'static str,
+}
+
+impl TmpDiskUsage {
+    pub fn new(max: Option<u64>) -> TmpDiskUsage {
+        TmpDiskUsage { max, used: Arc::default(), phase: "indexing" }
+    }
+
+    /// Returns a handle on the same sum that reports the given phase if the maximum is reached.
+    pub fn with_phase(&self, phase: &'static str) -> TmpDiskUsage {
+        TmpDiskUsage { phase, ..self.clone() }
+    }
+
+    /// Accounts bytes that are about to be written, fails without accounting them if
+    /// they would go beyond the maximum allowed.
+    fn reserve(&self, bytes: u64) -> std::result::Result<(), UserError> {
+        let result = self.used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+            let used = used + bytes;
+            match self.max {
+                Some(max) if used > max => None,
+                _ => Some(used),
+            }
+        });
+
+        match (result, self.max) {
+            (Err(used), Some(max)) => Err(UserError::MaxTmpDiskUsageReached {
+                phase: self.phase,
+                used: used + bytes,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn release(&self, bytes: u64) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// A temporary file that accounts the bytes written into it in a [`TmpDiskUsage`],
+/// a write that would go beyond the maximum fails before anything is written.
+/// The bytes are released from the temporary disk usage when the file is dropped.
+pub struct TrackedTmpFile {
+    file: File,
+    written: u64,
+    tmp_disk_usage: TmpDiskUsage,
+}
+
+impl TrackedTmpFile {
+    pub fn new(tmp_disk_usage: TmpDiskUsage) -> io::Result<TrackedTmpFile> {
+        Ok(TrackedTmpFile { file: tempfile::tempfile()?, written: 0, tmp_disk_usage })
+    }
+
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+}
+
+impl Drop for TrackedTmpFile {
+    fn drop(&mut self) {
+        self.tmp_disk_usage.release(self.written);
+    }
+}
+
+impl Write for TrackedTmpFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The user error is unwrapped from the io error when converted into a milli error.
+        self.tmp_disk_usage
+            .reserve(buf.len() as u64)
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+        let result = self.file.write(buf);
+        let written = result.as_ref().map_or(0, |written| *written);
+        self.tmp_disk_usage.release((buf.len() - written) as u64);
+        self.written += written as u64;
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Read for TrackedTmpFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for TrackedTmpFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+/// Creates the chunks of a [`TrackedSorter`] as [`TrackedTmpFile`]s.
+#[derive(Debug, Clone)]
+pub struct TrackedChunkCreator {
+    tmp_disk_usage: TmpDiskUsage,
+}
+
+impl grenad::ChunkCreator for TrackedChunkCreator {
+    type Chunk = TrackedTmpFile;
+    type Error = io::Error;
+
+    fn create(&self) -> io::Result<TrackedTmpFile> {
+        TrackedTmpFile::new(self.tmp_disk_usage.clone())
+    }
+}
+
+/// The number of documents chunks of every thread when the chunks size is tuned, several
+/// chunks balance the threads when some documents take longer to extract than others.
+const CHUNKS_PER_THREAD: u64 = 4;
+/// A tuned documents chunk contains at least this number of documents of the average size.
+const MIN_DOCUMENTS_PER_CHUNK: u64 = 100;
+/// The data extracted from a documents chunk is held in memory before being spilled on disk,
+/// it is several times bigger than the documents.
+const EXTRACTION_MEMORY_FACTOR: u64 = 8;
+const MIN_DOCUMENTS_CHUNK_SIZE: u64 = 64 * 1024; // 64KiB
+const MAX_DOCUMENTS_CHUNK_SIZE: u64 = 256 * 1024 * 1024; // 256MiB
+
+/// Returns a documents chunk size that splits the documents into enough chunks for all the
+/// threads, with enough documents in each chunk to amortize the cost of a chunk, and that
+/// fits the memory given to a thread.
+pub fn tuned_documents_chunk_size(
+    documents_size: u64,
+    documents_count: usize,
+    threads: usize,
+    max_memory: Option<usize>,
+) -> usize {
+    let threads = threads.max(1) as u64;
+    let average_document_size = documents_size / documents_count.max(1) as u64;
+
+    let mut size = documents_size / (threads * CHUNKS_PER_THREAD);
+    size = size.max(average_document_size * MIN_DOCUMENTS_PER_CHUNK);
+    if let Some(max_memory) = max_memory {
+        size = size.min(max_memory as u64 / threads / EXTRACTION_MEMORY_FACTOR);
+    }
+
+    size.max(MIN_DOCUMENTS_CHUNK_SIZE).min(MAX_DOCUMENTS_CHUNK_SIZE) as usize
 }
 
 /// Returns an iterator that outputs grenad readers of obkv documents
@@ -137,43 +333,39 @@ pub fn grenad_obkv_into_chunks<R: io::Read + io::Seek>(
     reader: grenad::Reader<R>,
     indexer: GrenadParameters,
     documents_chunk_size: usize,
-) -> Result<impl Iterator<Item = Result<grenad::Reader<File>>>> {
+) -> Result<impl Iterator<Item = Result<grenad::Reader<TrackedTmpFile>>>> {
     let mut continue_reading = true;
     let mut cursor = reader.into_cursor()?;
 
-    let indexer_clone = indexer.clone();
+    let indexer_clone = indexer.with_tmp_disk_phase("documents-chunks");
     let mut transposer = move || {
         if !continue_reading {
             return Ok(None);
         }
 
         let mut current_chunk_size = 0u64;
-        let mut obkv_documents = create_writer(
-            indexer_clone.chunk_compression_type,
-            indexer_clone.chunk_compression_level,
-            tempfile::tempfile()?,
-        );
+        let mut obkv_documents = create_tracked_writer(&indexer_clone)?;
 
         while let Some((document_id, obkv)) = cursor.move_on_next()? {
             obkv_documents.insert(document_id, obkv)?;
             current_chunk_size += document_id.len() as u64 + obkv.len() as u64;
 
             if current_chunk_size >= documents_chunk_size as u64 {
-                return writer_into_reader(obkv_documents).map(Some);
+                return tracked_writer_into_reader(obkv_documents).map(Some);
             }
         }
 
         continue_reading = false;
-        writer_into_reader(obkv_documents).map(Some)
+        tracked_writer_into_reader(obkv_documents).map(Some)
     };
 
     Ok(std::iter::from_fn(move || transposer().transpose()))
 }
 
-pub fn write_into_lmdb_database(
+pub fn write_into_lmdb_database<R: io::Read + io::Seek>(
     wtxn: &mut heed::RwTxn,
     database: heed::PolyDatabase,
-    reader: Reader<File>,
+    reader: Reader<R>,
     merge: MergeFn,
 ) -> Result<()> {
     debug!("Writing MTBL stores...");
@@ -242,3 +434,56 @@ fn merger_iter_into_lmdb_database<R: io::Read + io::Seek>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::update::index_documents::helpers::keep_first;
+
+    #[test]
+    fn tracked_sorter_fails_while_spilling() {
+        let tmp_disk_usage = TmpDiskUsage::new(Some(1024)).with_phase("test");
+        let indexer = GrenadParameters { tmp_disk_usage, ..Default::default() };
+        let mut sorter = create_tracked_sorter(keep_first, &indexer, Some(10 * 1024));
+
+        // The sorter spills its entries long before they are read back.
+        let error = (0u32..10_000)
+            .map(|i| sorter.insert(i.to_be_bytes(), [0; 100]))
+            .find_map(|result| result.err())
+            .expect("the sorter must spill beyond the maximum");
+        assert!(matches!(
+            crate::Error::from(error),
+            crate::Error::UserError(UserError::MaxTmpDiskUsageReached {
+                phase: "test",
+                max: 1024,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn tracked_files_release_their_usage_when_dropped() {
+        let tmp_disk_usage = TmpDiskUsage::new(Some(64 * 1024));
+        let indexer = GrenadParameters { tmp_disk_usage, ..Default::default() };
+        let used = || indexer.tmp_disk_usage.used.load(Ordering::Relaxed);
+
+        // Every phase spills about 16KiB of chunks and writes them back into a 16KiB file,
+        // the phases together write far more than the maximum but never at the same time.
+        for phase in 0u8..10 {
+            let mut sorter = create_tracked_sorter(keep_first, &indexer, Some(4 * 1024));
+            for i in 0u32..160 {
+                sorter.insert(i.to_be_bytes(), [phase; 100]).unwrap();
+            }
+            let reader = sorter_into_reader(sorter, indexer.clone()).unwrap();
+            assert!(used() >= 160 * 100);
+
+            let mut cursor = reader.into_cursor().unwrap();
+            assert_eq!(
+                cursor.move_on_first().unwrap(),
+                Some((&0u32.to_be_bytes()[..], &[phase; 100][..]))
+            );
+            drop(cursor);
+            assert_eq!(used(), 0);
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::io;
 use std::result::Result as StdResult;
 
@@ -7,11 +9,43 @@ use roaring::RoaringBitmap;
 use super::read_u32_ne_bytes;
 use crate::heed_codec::facet::{decode_prefix_string, encode_prefix_string};
 use crate::heed_codec::CboRoaringBitmapCodec;
-use crate::Result;
+use crate::{FieldId, Result};
 
 pub type MergeFn = for<'a> fn(&[u8], &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>>;
 
+thread_local! {
+    /// The number of key conflicts resolved by the merge functions on this thread,
+    /// `None` when they are not counted.
+    static MERGE_CONFLICTS: Cell<Option<u64>> = Cell::new(None);
+}
+
+/// Runs the function and returns the number of key conflicts the merge functions
+/// resolved on this thread meanwhile, a key seen `n` times counts for `n - 1` conflicts.
+///
+/// The merges must be done on the calling thread, e.g. by a sorter or a merger.
+pub fn count_merge_conflicts<T>(enabled: bool, f: impl FnOnce() -> T) -> (T, Option<u64>) {
+    if !enabled {
+        return (f(), None);
+    }
+    let previous = MERGE_CONFLICTS.with(|conflicts| conflicts.replace(Some(0)));
+    let output = f();
+    let counted = MERGE_CONFLICTS.with(|conflicts| conflicts.replace(previous));
+    if let (Some(previous), Some(counted)) = (previous, counted) {
+        MERGE_CONFLICTS.with(|conflicts| conflicts.set(Some(previous + counted)));
+    }
+    (output, counted)
+}
+
+fn record_merge_conflicts(values: usize) {
+    MERGE_CONFLICTS.with(|conflicts| {
+        if let Some(count) = conflicts.get() {
+            conflicts.set(Some(count + values.saturating_sub(1) as u64));
+        }
+    })
+}
+
 pub fn concat_u32s_array<'a>(_key: &[u8], values: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(values.len());
     if values.len() == 1 {
         Ok(values[0].clone())
     } else {
@@ -33,6 +67,7 @@ pub fn serialize_roaring_bitmap(bitmap: &RoaringBitmap, buffer: &mut Vec<u8>) ->
 }
 
 pub fn merge_roaring_bitmaps<'a>(_key: &[u8], values: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(values.len());
     if values.len() == 1 {
         Ok(values[0].clone())
     } else {
@@ -53,6 +88,7 @@ pub fn keep_first_prefix_value_merge_roaring_bitmaps<'a>(
     _key: &[u8],
     values: &[Cow<'a, [u8]>],
 ) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(values.len());
     if values.len() == 1 {
         Ok(values[0].clone())
     } else {
@@ -77,16 +113,19 @@ pub fn keep_first_prefix_value_merge_roaring_bitmaps<'a>(
 }
 
 pub fn keep_first<'a>(_key: &[u8], values: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(values.len());
     Ok(values[0].clone())
 }
 
 /// Only the last value associated with an id is kept.
 pub fn keep_latest_obkv<'a>(_key: &[u8], obkvs: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(obkvs.len());
     Ok(obkvs.last().unwrap().clone())
 }
 
 /// Merge all the obks in the order we see them.
 pub fn merge_obkvs<'a>(_key: &[u8], obkvs: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(obkvs.len());
     Ok(obkvs
         .into_iter()
         .cloned()
@@ -116,10 +155,36 @@ pub fn merge_two_obkvs(base: obkv::KvReaderU16, update: obkv::KvReaderU16, buffe
     writer.finish().unwrap();
 }
 
+/// Merges the update with the specified fields of the base, the values of the update
+/// are kept when both of them contain one of these fields.
+pub fn merge_kept_fields_of_obkv(
+    base: obkv::KvReaderU16,
+    update: obkv::KvReaderU16,
+    kept_fields: &HashSet<FieldId>,
+    buffer: &mut Vec<u8>,
+) {
+    use itertools::merge_join_by;
+    use itertools::EitherOrBoth::{Both, Left, Right};
+
+    buffer.clear();
+
+    let mut writer = obkv::KvWriter::new(buffer);
+    for eob in merge_join_by(base.iter(), update.iter(), |(b, _), (u, _)| b.cmp(u)) {
+        match eob {
+            Left((k, v)) if kept_fields.contains(&k) => writer.insert(k, v).unwrap(),
+            Left(_) => (),
+            Both(_, (k, v)) | Right((k, v)) => writer.insert(k, v).unwrap(),
+        }
+    }
+
+    writer.finish().unwrap();
+}
+
 pub fn merge_cbo_roaring_bitmaps<'a>(
     _key: &[u8],
     values: &[Cow<'a, [u8]>],
 ) -> Result<Cow<'a, [u8]>> {
+    record_merge_conflicts(values.len());
     if values.len() == 1 {
         Ok(values[0].clone())
     } else {
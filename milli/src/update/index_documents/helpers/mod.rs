@@ -8,18 +8,23 @@ use std::convert::{TryFrom, TryInto};
 pub use clonable_mmap::{ClonableMmap, CursorClonableMmap};
 use fst::{IntoStreamer, Streamer};
 pub use grenad_helpers::{
-    as_cloneable_grenad, create_sorter, create_writer, grenad_obkv_into_chunks, merge_readers,
-    sorter_into_lmdb_database, sorter_into_reader, write_into_lmdb_database, writer_into_reader,
-    GrenadParameters,
+    as_cloneable_grenad, create_sorter, create_tracked_sorter, create_tracked_writer,
+    create_writer, grenad_obkv_into_chunks, merge_readers, sorter_into_lmdb_database,
+    sorter_into_reader, tracked_writer_into_reader, tuned_documents_chunk_size,
+    write_into_lmdb_database, writer_into_reader, GrenadParameters, TmpDiskUsage, TrackedSorter,
+    TrackedTmpFile,
 };
 pub use merge_functions::{
-    concat_u32s_array, keep_first, keep_first_prefix_value_merge_roaring_bitmaps, keep_latest_obkv,
-    merge_cbo_roaring_bitmaps, merge_obkvs, merge_roaring_bitmaps, merge_two_obkvs,
+    concat_u32s_array, count_merge_conflicts, keep_first,
+    keep_first_prefix_value_merge_roaring_bitmaps, keep_latest_obkv, merge_cbo_roaring_bitmaps,
+    merge_kept_fields_of_obkv, merge_obkvs, merge_roaring_bitmaps, merge_two_obkvs,
     roaring_bitmap_from_u32s_array, serialize_roaring_bitmap, MergeFn,
 };
 
+use crate::limits::MAX_LMDB_KEY_LENGTH;
+
 pub fn valid_lmdb_key(key: impl AsRef<[u8]>) -> bool {
-    key.as_ref().len() <= 511 && !key.as_ref().is_empty()
+    key.as_ref().len() <= MAX_LMDB_KEY_LENGTH && !key.as_ref().is_empty()
 }
 
 /// Divides one slice into two at an index, returns `None` if mid is out of bounds.
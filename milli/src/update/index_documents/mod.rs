@@ -1,48 +1,76 @@
 mod extract;
 mod helpers;
+mod nested_fields;
+mod persisted_batches;
 mod transform;
 mod typed_chunk;
 
-use std::collections::HashSet;
+use std::cmp;
+use std::collections::{BTreeMap, HashSet};
 use std::io::{Read, Seek};
 use std::iter::FromIterator;
 use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
 use crossbeam_channel::{Receiver, Sender};
-use log::debug;
+use itertools::{Either, Itertools};
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
 use slice_group_by::GroupBy;
+use tracing::debug;
 use typed_chunk::{write_typed_chunk_into_index, TypedChunk};
 
+use self::extract::extract_geo_points;
 pub use self::helpers::{
     as_cloneable_grenad, create_sorter, create_writer, fst_stream_into_hashset,
     fst_stream_into_vec, merge_cbo_roaring_bitmaps, merge_roaring_bitmaps,
     sorter_into_lmdb_database, write_into_lmdb_database, writer_into_reader, ClonableMmap, MergeFn,
 };
-use self::helpers::{grenad_obkv_into_chunks, GrenadParameters};
-pub use self::transform::{Transform, TransformOutput};
+use self::helpers::{
+    grenad_obkv_into_chunks, keep_first, tuned_documents_chunk_size, GrenadParameters,
+};
+pub use self::nested_fields::FlattenConfig;
+use self::nested_fields::{flatten_object_fields, register_nested_fields};
+pub use self::transform::{
+    DocidGeneration, DocumentIdCharset, DocumentIdCharsetKind, DocumentIdNormalization,
+    DocumentIdPolicy, GenerateDocumentId, NormalizeDocumentId, Transform, TransformDocuments,
+    TransformOutput,
+};
 use crate::documents::DocumentBatchReader;
+use crate::error::UserError;
+use crate::index::ACL_FIELD_NAME;
+use crate::proximity::MAX_PAIR_PROXIMITY;
+use crate::update::facets::FacetValuesDelta;
 pub use crate::update::index_documents::helpers::CursorClonableMmap;
 use crate::update::{
     self, Facets, IndexerConfig, UpdateIndexingStep, WordPrefixDocids,
     WordPrefixPairProximityDocids, WordPrefixPositionDocids, WordsPrefixesFst,
 };
-use crate::{Index, Result};
+use crate::{FieldsIdsMap, Index, Percolate, Result, TokenizerSettings};
 
-static MERGED_DATABASE_COUNT: usize = 7;
+static MERGED_DATABASE_COUNT: usize = 9;
 static PREFIX_DATABASE_COUNT: usize = 5;
 static TOTAL_POSTING_DATABASE_COUNT: usize = MERGED_DATABASE_COUNT + PREFIX_DATABASE_COUNT;
+const DEFAULT_DOCUMENTS_CHUNK_SIZE: usize = 1024 * 1024 * 4; // 4MiB
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug)]
 pub struct DocumentAdditionResult {
     /// The number of documents that were indexed during the update
     pub indexed_documents: u64,
     /// The total number of documents in the index after the update
     pub number_of_documents: u64,
+    /// The ids of the stored queries matched by the indexed documents, by external document id,
+    /// only filled when [`IndexDocumentsConfig::percolate`] is set.
+    pub matched_queries: BTreeMap<String, Vec<String>>,
+    /// The documents that were not indexed because of their ids, by position in the documents
+    /// added to the builder, only filled when [`IndexDocumentsConfig::skip_invalid_documents`]
+    /// is set.
+    pub rejected_documents: Vec<(usize, UserError)>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum IndexDocumentsMethod {
     /// Replace the previous document with the new one,
@@ -52,6 +80,10 @@ pub enum IndexDocumentsMethod {
     /// Merge the previous version of the document with the new version,
     /// replacing old attributes values with the new ones and add the new attributes.
     UpdateDocuments,
+
+    /// Replace the previous document with the new one but keep the values of the
+    /// specified fields of the previous document that the new one doesn't contain.
+    ReplaceKeepingFields(Vec<String>),
 }
 
 impl Default for IndexDocumentsMethod {
@@ -68,18 +100,69 @@ pub struct IndexDocuments<'t, 'u, 'i, 'a, F> {
     transform: Option<Transform<'a, 'i>>,
     progress: F,
     added_documents: u64,
+    /// The fields ids map and the primary key of the index before the documents were added,
+    /// restored once the documents are validated.
+    fields_before_additions: Option<(FieldsIdsMap, Option<String>)>,
+    /// The facet values written by the documents, the facet levels are updated with them.
+    facet_values_delta: Option<FacetValuesDelta>,
+}
+
+/// The databases rebuilt when the documents stored in the index are reindexed after a
+/// settings update, only the ones affected by the updated settings are rebuilt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ReindexScope {
+    /// The documents are rewritten and all the databases are rebuilt.
+    pub full: bool,
+    /// The databases of the words, when the searchable fields or the tokenization changed.
+    pub words: bool,
+    /// The facet and geo databases, when the faceted fields changed.
+    pub facets: bool,
+}
+
+impl ReindexScope {
+    pub fn full() -> ReindexScope {
+        ReindexScope { full: true, words: true, facets: true }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.full && !self.words && !self.facets
+    }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct IndexDocumentsConfig {
     pub facet_level_group_size: Option<NonZeroUsize>,
     pub facet_min_level_size: Option<NonZeroUsize>,
     pub words_prefix_threshold: Option<u32>,
     pub max_prefix_length: Option<usize>,
+    /// The highest proximity stored in the word prefix pair proximity database, 4 by default.
+    pub prefix_pair_max_proximity: Option<u8>,
+    /// The longest prefix stored in the word prefix pair proximity database, 2 by default.
+    pub prefix_pair_max_prefix_length: Option<usize>,
     pub words_positions_level_group_size: Option<NonZeroU32>,
     pub words_positions_min_level_size: Option<NonZeroU32>,
     pub update_method: IndexDocumentsMethod,
     pub autogenerate_docids: bool,
+    /// How the missing documents ids are generated, random UUIDs by default.
+    pub docid_generation: DocidGeneration,
+    /// With the `UpdateDocuments` method, a field set to `null` is removed
+    /// from the document instead of being stored as a `null` value.
+    pub null_deletes_fields: bool,
+    /// How the document ids made of digits are normalized, they are kept as they are by default.
+    pub document_id_normalization: DocumentIdNormalization,
+    /// The characters accepted in the document ids, the restricted ones by default.
+    /// Both must stay the same for every addition, see [`Index::document_id_policy`].
+    pub document_id_charset: DocumentIdCharset,
+    /// How the names of the faceted fields nested in objects are resolved.
+    pub flatten: FlattenConfig,
+    /// Matches the indexed documents against the queries stored in the index,
+    /// see [`DocumentAdditionResult::matched_queries`].
+    pub percolate: bool,
+    /// Skips the documents with an invalid or missing id instead of failing the whole addition,
+    /// see [`DocumentAdditionResult::rejected_documents`].
+    pub skip_invalid_documents: bool,
+    /// The id of the update given by the embedder, recorded in the logs of the addition.
+    pub update_id: Option<u64>,
 }
 
 impl<'t, 'u, 'i, 'a, F> IndexDocuments<'t, 'u, 'i, 'a, F>
@@ -93,21 +176,28 @@ where
         config: IndexDocumentsConfig,
         progress: F,
     ) -> IndexDocuments<'t, 'u, 'i, 'a, F> {
-        let transform = Some(Transform::new(
+        let mut transform = Transform::new(
             &index,
             indexer_config,
-            config.update_method,
+            config.update_method.clone(),
             config.autogenerate_docids,
-        ));
+            config.null_deletes_fields,
+            config.document_id_normalization,
+            config.document_id_charset.clone(),
+        );
+        transform.docid_generation = config.docid_generation.clone();
+        transform.skip_invalid_documents = config.skip_invalid_documents;
 
         IndexDocuments {
-            transform,
+            transform: Some(transform),
             config,
             indexer_config,
             progress,
             wtxn,
             index,
             added_documents: 0,
+            fields_before_additions: None,
+            facet_values_delta: None,
         }
     }
 
@@ -126,6 +216,7 @@ where
             return Ok(0);
         }
 
+        self.remember_fields_before_additions()?;
         let indexed_documents = self
             .transform
             .as_mut()
@@ -138,40 +229,224 @@ where
         Ok(indexed_documents)
     }
 
+    /// Writes the documents of the following batches in this directory, after the batches
+    /// already persisted in it, so that another builder can resume them if the process stops
+    /// before the update is committed. The directory must be removed once committed.
+    pub fn persist_batches(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.transform.as_mut().expect("Invalid document addition state").persist_batches(path)
+    }
+
+    /// Adds the batches of documents persisted in this directory by a previous builder.
+    ///
+    /// Returns the number of documents added to the builder.
+    pub fn resume_batches(&mut self, path: impl AsRef<Path>) -> Result<u64> {
+        self.remember_fields_before_additions()?;
+        let resumed_documents = self
+            .transform
+            .as_mut()
+            .expect("Invalid document addition state")
+            .resume_batches(path, self.wtxn)? as u64;
+
+        self.added_documents += resumed_documents;
+
+        Ok(resumed_documents)
+    }
+
+    fn remember_fields_before_additions(&mut self) -> Result<()> {
+        if self.fields_before_additions.is_none() {
+            let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+            let primary_key = self.index.primary_key(self.wtxn)?.map(String::from);
+            self.fields_before_additions = Some((fields_ids_map, primary_key));
+        }
+        Ok(())
+    }
+
+    /// Checks the added documents like an indexing would, their ids and their `_geo` field,
+    /// without indexing them, to reject a batch before starting a long indexing.
+    ///
+    /// The fields and the primary key registered while the documents were added are removed,
+    /// nothing is left in the transaction. Returns the result the indexing would have, without
+    /// the matched queries.
+    pub fn validate_only(mut self) -> Result<DocumentAdditionResult> {
+        let result = self.validate_added_documents();
+        if let Some((fields_ids_map, primary_key)) = self.fields_before_additions.take() {
+            self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
+            match primary_key {
+                Some(primary_key) => self.index.put_primary_key(self.wtxn, &primary_key)?,
+                None => {
+                    self.index.delete_primary_key(self.wtxn)?;
+                }
+            }
+        }
+        result
+    }
+
+    fn validate_added_documents(&mut self) -> Result<DocumentAdditionResult> {
+        let transform = self.transform.take().expect("Invalid document addition state");
+        let number_of_documents = self.index.number_of_documents(self.wtxn)?;
+        if self.added_documents == 0 {
+            return Ok(DocumentAdditionResult {
+                indexed_documents: 0,
+                number_of_documents,
+                matched_queries: BTreeMap::new(),
+                rejected_documents: transform.into_rejected_documents(),
+            });
+        }
+
+        let output = transform.output_from_sorter(self.wtxn, &self.progress)?;
+        let mut fields_ids_map = output.fields_ids_map.clone();
+        let faceted_fields_names = self.index.faceted_fields(self.wtxn)?;
+        register_nested_fields(
+            &mut fields_ids_map,
+            faceted_fields_names.iter().map(String::as_str).sorted(),
+            self.config.flatten,
+        )?;
+
+        // The `_geo` fields are only extracted, and checked, when they are filterable or sortable.
+        if let (Some(geo_field_id), TransformDocuments::File(file)) =
+            (fields_ids_map.id("_geo"), &output.documents)
+        {
+            let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+            if faceted_fields.contains(&geo_field_id) {
+                let primary_key_id = fields_ids_map.id(&output.primary_key).unwrap();
+                extract_geo_points(
+                    grenad::Reader::new(file.get_ref().try_clone()?)?,
+                    GrenadParameters::default(),
+                    primary_key_id,
+                    geo_field_id,
+                    &output.unchanged_geo_documents_ids,
+                )?;
+            }
+        }
+
+        Ok(DocumentAdditionResult {
+            indexed_documents: output.documents_count as u64,
+            number_of_documents: number_of_documents + output.new_documents_ids.len(),
+            matched_queries: BTreeMap::new(),
+            rejected_documents: output.rejected_documents,
+        })
+    }
+
     #[logging_timer::time("IndexDocuments::{}")]
     pub fn execute(mut self) -> Result<DocumentAdditionResult> {
+        let span = self.index.span(self.wtxn, "index_documents");
+        if let Some(update_id) = self.config.update_id {
+            span.record("update_id", &update_id);
+        }
+        let _entered = span.enter();
+
         if self.added_documents == 0 {
             let number_of_documents = self.index.number_of_documents(self.wtxn)?;
-            return Ok(DocumentAdditionResult { indexed_documents: 0, number_of_documents });
+            let transform = self.transform.take().expect("Invalid document addition state");
+            return Ok(DocumentAdditionResult {
+                indexed_documents: 0,
+                number_of_documents,
+                matched_queries: BTreeMap::new(),
+                rejected_documents: transform.into_rejected_documents(),
+            });
         }
-        let output = self
-            .transform
-            .take()
-            .expect("Invalid document addition state")
-            .output_from_sorter(self.wtxn, &self.progress)?;
+        let mut output = tracing::debug_span!("transform").in_scope(|| {
+            self.transform
+                .take()
+                .expect("Invalid document addition state")
+                .output_from_sorter(self.wtxn, &self.progress)
+        })?;
         let indexed_documents = output.documents_count as u64;
-        let number_of_documents = self.execute_raw(output)?;
-
-        Ok(DocumentAdditionResult { indexed_documents, number_of_documents })
+        let rejected_documents = std::mem::take(&mut output.rejected_documents);
+        let (number_of_documents, matched_queries) =
+            self.index_transform_output(output, ReindexScope::full())?;
+
+        Ok(DocumentAdditionResult {
+            indexed_documents,
+            number_of_documents,
+            matched_queries,
+            rejected_documents,
+        })
     }
 
     /// Returns the total number of documents in the index after the update.
-    #[logging_timer::time("IndexDocuments::{}")]
     pub fn execute_raw(self, output: TransformOutput) -> Result<u64>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        self.execute_reindex(output, ReindexScope::full())
+    }
+
+    /// Only rebuilds the databases of the given scope from the documents of the output,
+    /// the other databases must be left untouched by the changes leading to the reindex.
+    pub(crate) fn execute_reindex(self, output: TransformOutput, scope: ReindexScope) -> Result<u64>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        self.index_transform_output(output, scope)
+            .map(|(number_of_documents, _)| number_of_documents)
+    }
+
+    /// Returns the total number of documents in the index after the update
+    /// and the stored queries matched by the indexed documents.
+    #[logging_timer::time("IndexDocuments::{}")]
+    fn index_transform_output(
+        mut self,
+        output: TransformOutput,
+        scope: ReindexScope,
+    ) -> Result<(u64, BTreeMap<String, Vec<String>>)>
     where
         F: Fn(UpdateIndexingStep) + Sync,
     {
         let TransformOutput {
             primary_key,
-            fields_ids_map,
+            mut fields_ids_map,
             field_distribution,
+            field_types_distribution,
             external_documents_ids,
             new_documents_ids,
             replaced_documents_ids,
+            unchanged_geo_documents_ids,
             documents_count,
-            documents_file,
+            documents,
+            docid_external_ids_file,
+            rejected_documents: _,
+            tmp_disk_usage,
         } = output;
 
+        // The objects of the faceted fields are flattened, the names of the fields found in
+        // them, e.g. `tags.name` for `tags: [{ "name": .. }]`, are faceted like the others.
+        if scope.full || scope.facets {
+            let faceted_fields_names = self.index.faceted_fields(self.wtxn)?;
+            let faceted_fields: Vec<_> = faceted_fields_names
+                .iter()
+                .filter_map(|name| fields_ids_map.id(name).map(|id| (id, name.as_str())))
+                .collect();
+            let mut flattened_fields = self.index.flattened_faceted_fields(self.wtxn)?;
+            let readers = match &documents {
+                TransformDocuments::File(file) => {
+                    vec![grenad::Reader::new(file.get_ref().try_clone()?)?]
+                }
+                TransformDocuments::Chunks(chunks) => chunks
+                    .iter()
+                    .map(|chunk| -> Result<_> {
+                        Ok(grenad::Reader::new(chunk.get_ref().get_ref().try_clone()?)?)
+                    })
+                    .collect::<Result<_>>()?,
+            };
+            for reader in readers {
+                let fields = flatten_object_fields(reader, &faceted_fields, self.config.flatten)?;
+                for (name, parent) in fields {
+                    flattened_fields.entry(name).or_insert(parent);
+                }
+            }
+            self.index.put_flattened_faceted_fields(self.wtxn, &flattened_fields)?;
+        }
+
+        // The faceted fields nested in the objects of the documents, written with the dot
+        // notation, are given their own field ids to be extracted from their top-level field.
+        let faceted_fields_names = self.index.faceted_fields(self.wtxn)?;
+        let nested_faceted_fields = register_nested_fields(
+            &mut fields_ids_map,
+            faceted_fields_names.iter().map(String::as_str).sorted(),
+            self.config.flatten,
+        )?;
+
         // The fields_ids_map is put back to the store now so the rest of the transaction sees an
         // up to date field map.
         self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
@@ -195,8 +470,6 @@ where
             }
         };
 
-        let documents_file = grenad::Reader::new(documents_file)?;
-
         // create LMDB writer channel
         let (lmdb_writer_sx, lmdb_writer_rx): (
             Sender<Result<TypedChunk>>,
@@ -206,14 +479,33 @@ where
         // get the primary key field id
         let primary_key_id = fields_ids_map.id(&primary_key).unwrap();
 
-        // get searchable fields for word databases
-        let searchable_fields =
-            self.index.searchable_fields_ids(self.wtxn)?.map(HashSet::from_iter);
+        // get the fid of the `_acl` field, its tokens are only extracted with the documents.
+        let acl_field_id = fields_ids_map.id(ACL_FIELD_NAME);
+
+        // get searchable fields for word databases, no word is extracted out of the scope.
+        // The access control tokens are never searchable.
+        let searchable_fields = if scope.full || scope.words {
+            match self.index.searchable_fields_ids(self.wtxn)? {
+                Some(fields) => Some(HashSet::from_iter(
+                    fields.into_iter().filter(|f| Some(*f) != acl_field_id),
+                )),
+                None if acl_field_id.is_some() => {
+                    Some(fields_ids_map.ids().filter(|f| Some(*f) != acl_field_id).collect())
+                }
+                None => None,
+            }
+        } else {
+            Some(HashSet::new())
+        };
         // get filterable fields for facet databases
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let (faceted_fields, nested_faceted_fields) = if scope.full || scope.facets {
+            (self.index.faceted_fields_ids(self.wtxn)?, nested_faceted_fields)
+        } else {
+            (HashSet::new(), Vec::new())
+        };
         // get the fid of the `_geo` field.
         let geo_field_id = match self.index.fields_ids_map(self.wtxn)?.id("_geo") {
-            Some(gfid) => {
+            Some(gfid) if scope.full || scope.facets => {
                 let is_sortable = self.index.sortable_fields_ids(self.wtxn)?.contains(&gfid);
                 let is_filterable = self.index.filterable_fields_ids(self.wtxn)?.contains(&gfid);
                 if is_sortable || is_filterable {
@@ -222,26 +514,72 @@ where
                     None
                 }
             }
-            None => None,
+            _ => None,
         };
 
+        // the replaced documents whose coordinates didn't change keep their geo data.
+        let unchanged_geo_documents_ids = Arc::new(unchanged_geo_documents_ids);
+
         let stop_words = self.index.stop_words(self.wtxn)?;
+        let proximity_precision = self.index.proximity_precision(self.wtxn)?;
+        let proximity_window = self
+            .indexer_config
+            .proximity_window
+            .map_or(MAX_PAIR_PROXIMITY, |window| cmp::min(window, MAX_PAIR_PROXIMITY));
+        // The searches compute the farther pairs by attribute, the smallest window is kept.
+        if proximity_window < MAX_PAIR_PROXIMITY {
+            let current = self.index.proximity_window(self.wtxn)?.unwrap_or(MAX_PAIR_PROXIMITY);
+            self.index.put_proximity_window(self.wtxn, cmp::min(current, proximity_window))?;
+        }
+        let tokenizer_settings = TokenizerSettings::from_index(self.index, self.wtxn)?;
+
+        // The size of the documents chunks is tuned to the documents and the machine when it is
+        // not set, the documents are not split into chunks when they already are.
+        let documents_chunk_size = match self.indexer_config.documents_chunk_size {
+            Some(documents_chunk_size) => documents_chunk_size,
+            None => match &documents {
+                TransformDocuments::File(file) => tuned_documents_chunk_size(
+                    file.get_ref().metadata()?.len(),
+                    documents_count,
+                    pool.current_num_threads(),
+                    self.indexer_config.max_memory,
+                ),
+                TransformDocuments::Chunks(_) => DEFAULT_DOCUMENTS_CHUNK_SIZE,
+            },
+        };
+        if let TransformDocuments::File(_) = documents {
+            (self.progress)(UpdateIndexingStep::DocumentsChunkSize {
+                documents_chunk_size,
+                tuned: self.indexer_config.documents_chunk_size.is_none(),
+            });
+        }
 
-        // Run extraction pipeline in parallel.
+        // Run extraction pipeline in parallel, the extraction tasks are
+        // spawned on the pool and must log in the span of the update.
+        let span = tracing::Span::current();
         pool.install(|| {
+            let _entered = span.enter();
             let params = GrenadParameters {
                 chunk_compression_type: self.indexer_config.chunk_compression_type,
                 chunk_compression_level: self.indexer_config.chunk_compression_level,
                 max_memory: self.indexer_config.max_memory,
                 max_nb_chunks: self.indexer_config.max_nb_chunks, // default value, may be chosen.
+                tmp_disk_usage,
+                count_merge_conflicts: self.indexer_config.count_merge_conflicts,
             };
 
-            // split obkv file into several chuncks
-            let chunk_iter = grenad_obkv_into_chunks(
-                documents_file,
-                params.clone(),
-                self.indexer_config.documents_chunk_size.unwrap_or(1024 * 1024 * 4), // 4MiB
-            );
+            // split obkv file into several chuncks, unless it already is
+            let chunk_iter = match documents {
+                TransformDocuments::File(file) => grenad::Reader::new(file)
+                    .map_err(Into::into)
+                    .and_then(|reader| {
+                        grenad_obkv_into_chunks(reader, params.clone(), documents_chunk_size)
+                    })
+                    .map(Either::Left),
+                TransformDocuments::Chunks(chunks) => {
+                    Ok(Either::Right(chunks.into_iter().map(|chunk| -> Result<_> { Ok(chunk) })))
+                }
+            };
 
             let result = chunk_iter.map(|chunk_iter| {
                 // extract all databases from the chunked obkv douments
@@ -249,12 +587,22 @@ where
                     chunk_iter,
                     params,
                     lmdb_writer_sx.clone(),
+                    scope.full,
                     searchable_fields,
                     faceted_fields,
+                    nested_faceted_fields,
                     primary_key_id,
                     geo_field_id,
+                    unchanged_geo_documents_ids.clone(),
+                    acl_field_id.filter(|_| scope.full),
                     stop_words,
+                    &tokenizer_settings,
+                    self.indexer_config.tokenizer.as_deref(),
                     self.indexer_config.max_positions_per_attributes,
+                    self.indexer_config.max_word_length,
+                    self.indexer_config.max_facet_value_length,
+                    proximity_precision,
+                    proximity_window,
                 )
             });
 
@@ -272,12 +620,19 @@ where
             let mut deletion_builder = update::DeleteDocuments::new(self.wtxn, self.index)?;
             debug!("documents to delete {:?}", replaced_documents_ids);
             deletion_builder.delete_documents(&replaced_documents_ids);
+            deletion_builder.keep_geo_documents(&unchanged_geo_documents_ids);
             let deleted_documents_count = deletion_builder.execute()?;
             debug!("{} documents actually deleted", deleted_documents_count.deleted_documents);
         }
 
         let index_documents_ids = self.index.documents_ids(self.wtxn)?;
         let index_is_empty = index_documents_ids.len() == 0;
+        // The facet levels of an empty index, or of reindexed documents, are computed from scratch.
+        let mut facet_values_delta = if index_is_empty || !scope.full {
+            None
+        } else {
+            Some(FacetValuesDelta::new(!replaced_documents_ids.is_empty()))
+        };
         let mut final_documents_ids = RoaringBitmap::new();
         let mut word_pair_proximity_docids = Vec::new();
         let mut word_position_docids = Vec::new();
@@ -306,11 +661,40 @@ where
                     word_position_docids.push(cloneable_chunk);
                     TypedChunk::WordPositionDocids(chunk)
                 }
+                TypedChunk::FieldIdFacetNumberDocids(chunk) => {
+                    if let Some(delta) = facet_values_delta.as_mut() {
+                        delta.register_numbers(unsafe { as_cloneable_grenad(&chunk)? })?;
+                    }
+                    TypedChunk::FieldIdFacetNumberDocids(chunk)
+                }
+                TypedChunk::FieldIdFacetStringDocids(chunk) => {
+                    if let Some(delta) = facet_values_delta.as_mut() {
+                        let cloneable_chunk = unsafe { as_cloneable_grenad(&chunk)? };
+                        delta.register_strings(self.wtxn, self.index, cloneable_chunk)?;
+                    }
+                    TypedChunk::FieldIdFacetStringDocids(chunk)
+                }
                 otherwise => otherwise,
             };
 
-            let (docids, is_merged_database) =
-                write_typed_chunk_into_index(typed_chunk, &self.index, self.wtxn, index_is_empty)?;
+            if let TypedChunk::Metrics(step) = typed_chunk {
+                (self.progress)(step);
+                continue;
+            }
+
+            let before = Instant::now();
+            let database_and_size = typed_chunk.database_and_size()?;
+            let (docids, is_merged_database) = write_typed_chunk_into_index(
+                typed_chunk,
+                &self.index,
+                self.wtxn,
+                index_is_empty,
+                pool,
+            )?;
+            if let Some((database, bytes)) = database_and_size {
+                let duration = before.elapsed();
+                (self.progress)(UpdateIndexingStep::WriteDatabase { database, bytes, duration });
+            }
             if !docids.is_empty() {
                 final_documents_ids |= docids;
                 let documents_seen_count = final_documents_ids.len();
@@ -334,6 +718,7 @@ where
 
         // We write the field distribution into the main database
         self.index.put_field_distribution(self.wtxn, &field_distribution)?;
+        self.index.put_field_types_distribution(self.wtxn, &field_types_distribution)?;
 
         // We write the primary key field id into the main database
         self.index.put_primary_key(self.wtxn, &primary_key)?;
@@ -341,21 +726,53 @@ where
         // We write the external documents ids into the main database.
         self.index.put_external_documents_ids(self.wtxn, &external_documents_ids)?;
 
-        let all_documents_ids = index_documents_ids | new_documents_ids | replaced_documents_ids;
+        // We write the reverse lookup from the internal to the external documents ids.
+        let docid_external_ids = grenad::Reader::new(docid_external_ids_file)?;
+        write_into_lmdb_database(
+            self.wtxn,
+            *self.index.docid_external_ids.as_polymorph(),
+            docid_external_ids,
+            keep_first,
+        )?;
+
+        // The reserved ids reused by the reloaded documents are no longer reserved.
+        let reserved_documents_ids = self.index.reserved_documents_ids(self.wtxn)?;
+        if !reserved_documents_ids.is_disjoint(&new_documents_ids) {
+            let reserved_documents_ids = reserved_documents_ids - &new_documents_ids;
+            self.index.put_reserved_documents_ids(self.wtxn, &reserved_documents_ids)?;
+        }
+
+        let indexed_documents_ids = new_documents_ids | replaced_documents_ids;
+        let all_documents_ids = index_documents_ids | &indexed_documents_ids;
         self.index.put_documents_ids(self.wtxn, &all_documents_ids)?;
 
-        self.execute_prefix_databases(
-            word_docids,
-            word_pair_proximity_docids,
-            word_position_docids,
-        )?;
+        self.facet_values_delta = facet_values_delta;
+        tracing::debug_span!("prefix_databases").in_scope(|| {
+            self.execute_prefix_databases(
+                word_docids,
+                word_pair_proximity_docids,
+                word_position_docids,
+            )
+        })?;
+
+        // We match the indexed documents against the stored queries
+        // once the facet levels used by their filters are computed.
+        let mut matched_queries = BTreeMap::new();
+        if self.config.percolate {
+            let _entered = tracing::debug_span!("percolate").entered();
+            let percolate = Percolate::new(self.wtxn, self.index, indexed_documents_ids);
+            for (docid, queries) in percolate.execute()? {
+                let external_id = self.index.external_ids(self.wtxn, Some(docid))?[0];
+                matched_queries.insert(external_id.to_string(), queries);
+            }
+        }
 
-        Ok(all_documents_ids.len())
+        Ok((all_documents_ids.len(), matched_queries))
     }
 
     #[logging_timer::time("IndexDocuments::{}")]
     pub fn execute_prefix_databases(
-        self,
+        &mut self,
         word_docids: Vec<grenad::Reader<CursorClonableMmap>>,
         word_pair_proximity_docids: Vec<grenad::Reader<CursorClonableMmap>>,
         word_position_docids: Vec<grenad::Reader<CursorClonableMmap>>,
@@ -376,6 +793,7 @@ where
         if let Some(value) = self.config.facet_min_level_size {
             builder.min_level_size(value);
         }
+        builder.delta = self.facet_values_delta.take();
         builder.execute()?;
 
         databases_seen += 1;
@@ -387,15 +805,20 @@ where
         let previous_words_prefixes_fst =
             self.index.words_prefixes_fst(self.wtxn)?.map_data(|cow| cow.into_owned())?;
 
-        // Run the words prefixes update operation.
-        let mut builder = WordsPrefixesFst::new(self.wtxn, self.index);
-        if let Some(value) = self.config.words_prefix_threshold {
-            builder.threshold(value);
-        }
-        if let Some(value) = self.config.max_prefix_length {
-            builder.max_prefix_length(value);
+        // Run the words prefixes update operation, there is no prefix when the prefix
+        // databases are disabled and the entries of the previous prefixes are removed.
+        if self.index.prefix_databases(self.wtxn)? {
+            let mut builder = WordsPrefixesFst::new(self.wtxn, self.index);
+            if let Some(value) = self.config.words_prefix_threshold {
+                builder.threshold(value);
+            }
+            if let Some(value) = self.config.max_prefix_length {
+                builder.max_prefix_length(value);
+            }
+            builder.execute()?;
+        } else {
+            self.index.put_words_prefixes_fst(self.wtxn, &fst::Set::default())?;
         }
-        builder.execute()?;
 
         let current_prefix_fst = self.index.words_prefixes_fst(self.wtxn)?;
 
@@ -449,6 +872,12 @@ where
         builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
         builder.max_nb_chunks = self.indexer_config.max_nb_chunks;
         builder.max_memory = self.indexer_config.max_memory;
+        if let Some(value) = self.config.prefix_pair_max_proximity {
+            builder.max_proximity(value);
+        }
+        if let Some(value) = self.config.prefix_pair_max_prefix_length {
+            builder.max_prefix_length(value);
+        }
         builder.execute(
             word_pair_proximity_docids,
             &new_prefix_fst_words,
@@ -497,9 +926,11 @@ mod tests {
 
     use big_s::S;
     use heed::EnvOpenOptions;
+    use maplit::hashset;
 
     use super::*;
     use crate::documents::DocumentBatchBuilder;
+    use crate::index::db_name;
     use crate::update::DeleteDocuments;
     use crate::HashMap;
 
@@ -599,7 +1030,7 @@ mod tests {
         // Check that we get only one document from the database.
         let docs = index.documents(&rtxn, Some(0)).unwrap();
         assert_eq!(docs.len(), 1);
-        let (id, doc) = docs[0];
+        let (id, doc) = docs[0].clone();
         assert_eq!(id, 0);
 
         // Check that this document is equal to the last one sent.
@@ -625,7 +1056,7 @@ mod tests {
         // Check that we get only one document from the database.
         let docs = index.documents(&rtxn, Some(0)).unwrap();
         assert_eq!(docs.len(), 1);
-        let (id, doc) = docs[0];
+        let (id, doc) = docs[0].clone();
         assert_eq!(id, 0);
 
         // Check that this document is equal to the last one sent.
@@ -637,6 +1068,271 @@ mod tests {
         drop(rtxn);
     }
 
+    #[test]
+    fn null_deletes_fields_document_merge() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([ { "id": 1, "name": "kevin", "age": 25 } ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            null_deletes_fields: true,
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // We unset the age of the document and set its city.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([ { "id": 1, "age": null, "city": "paris", "country": null } ]);
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docs = index.documents(&rtxn, Some(0)).unwrap();
+        let (_, doc) = docs[0].clone();
+        let mut doc_iter = doc.iter();
+        assert_eq!(doc_iter.next(), Some((0, &b"1"[..])));
+        assert_eq!(doc_iter.next(), Some((1, &br#""kevin""#[..])));
+        assert_eq!(doc_iter.next(), Some((3, &br#""paris""#[..])));
+        assert_eq!(doc_iter.next(), None);
+
+        let field_distribution = index.field_distribution(&rtxn).unwrap();
+        assert!(!field_distribution.contains_key("age"));
+        assert!(!field_distribution.contains_key("country"));
+        drop(rtxn);
+    }
+
+    #[test]
+    fn strip_leading_zeros_of_documents_ids() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "001", "name": "kevin" },
+            { "id": 1, "name": "kevina" },
+            { "id": "000", "name": "benoit" },
+            { "id": "010a", "name": "bob" }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig {
+            document_id_normalization: DocumentIdNormalization::StripLeadingZeros,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // "001" and 1 are the same document, the ids that are not only digits are kept.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert!(external_documents_ids.get("001").is_none());
+        assert!(external_documents_ids.get("010a").is_some());
+        let docid = external_documents_ids.get("0").unwrap();
+        let (_, doc) = index.documents(&rtxn, Some(docid)).unwrap()[0].clone();
+        assert_eq!(doc.get(0), Some(&br#""0""#[..]));
+
+        let docid = external_documents_ids.get("1").unwrap();
+        let (_, doc) = index.documents(&rtxn, Some(docid)).unwrap()[0].clone();
+        assert_eq!(doc.get(1), Some(&br#""kevina""#[..]));
+        drop(rtxn);
+    }
+
+    #[test]
+    fn documents_ids_charsets() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = || {
+            documents!([
+                { "id": "catalog/shoes.42", "name": "kevin" },
+                { "id": "plain-id_1", "name": "kevina" }
+            ])
+        };
+        let config = IndexerConfig::default();
+
+        // The ids with dots and slashes are refused by default.
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        assert!(builder.add_documents(content()).is_err());
+        drop(builder);
+
+        let charset = DocumentIdCharset::Any;
+        let indexing_config =
+            IndexDocumentsConfig { document_id_charset: charset.clone(), ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content()).unwrap();
+        builder.execute().unwrap();
+
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        let hashed = charset.internal_id("catalog/shoes.42").unwrap();
+        assert!(hashed.starts_with('#'));
+        assert_eq!(hashed.len(), 65);
+        assert!(external_documents_ids.get(&*hashed).is_some());
+        assert!(external_documents_ids.get("plain-id_1").is_some());
+        drop(external_documents_ids);
+
+        // The policy is stored, the following batches can't use another one.
+        let policy = index.document_id_policy(&wtxn).unwrap().unwrap();
+        assert_eq!(policy.charset, DocumentIdCharsetKind::Any);
+        assert_eq!(policy.external_id("catalog/shoes.42"), Some(hashed));
+        let normalize = |id: &str| Some(id.replace(|c: char| c == '/' || c == '.', "-"));
+        let charset = DocumentIdCharset::Custom(Arc::new(normalize));
+        let indexing_config =
+            IndexDocumentsConfig { document_id_charset: charset, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        let err = builder.add_documents(content()).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::UserError(UserError::DocumentIdPolicyCannotBeChanged { .. })
+        ));
+        drop(builder);
+        wtxn.commit().unwrap();
+
+        // A clear that doesn't retain the external ids resets the policy.
+        let mut wtxn = index.write_txn().unwrap();
+        update::ClearDocuments::new(&mut wtxn, &index).execute().unwrap();
+        assert!(index.document_id_policy(&wtxn).unwrap().is_none());
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content()).unwrap();
+        builder.execute().unwrap();
+
+        // The documents keep the ids they were sent with.
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        let docid = external_documents_ids.get("catalog-shoes-42").unwrap();
+        let (_, doc) = index.documents(&wtxn, Some(docid)).unwrap()[0].clone();
+        assert_eq!(doc.get(0), Some(&br#""catalog/shoes.42""#[..]));
+    }
+
+    #[test]
+    fn documents_ids_generation_strategies() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        fn add_documents<'t, 'u, 'i>(
+            wtxn: &'t mut heed::RwTxn<'i, 'u>,
+            index: &'i Index,
+            config: &IndexerConfig,
+            docid_generation: DocidGeneration,
+        ) {
+            let content = documents!([{ "name": "kevin" }, { "name": "kevina" }]);
+            let indexing_config = IndexDocumentsConfig {
+                autogenerate_docids: true,
+                docid_generation,
+                ..Default::default()
+            };
+            let mut builder = IndexDocuments::new(wtxn, index, config, indexing_config, |_| ());
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+        }
+        let external_ids = |wtxn: &heed::RwTxn| {
+            let mut external_ids = index.external_documents_ids(wtxn).unwrap().to_hash_map();
+            let mut external_ids: Vec<_> = external_ids.drain().map(|(id, _)| id).collect();
+            external_ids.sort_unstable();
+            external_ids
+        };
+
+        // The sequential ids follow the biggest integer id of the index.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 41, "name": "benoit" }, { "id": "abc", "name": "bob" }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        add_documents(&mut wtxn, &index, &config, DocidGeneration::Sequential);
+        assert_eq!(external_ids(&wtxn), vec!["41", "42", "43", "abc"]);
+
+        // The ids sent in the same batch are never generated, even after the generated ones,
+        // and the generated ids can't be sent by the following batches of the builder.
+        let content = documents!([{ "name": "kevin" }, { "id": 44, "name": "bob" }]);
+        let indexing_config = IndexDocumentsConfig {
+            autogenerate_docids: true,
+            docid_generation: DocidGeneration::Sequential,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        let content = documents!([{ "id": 45, "name": "benoit" }]);
+        let error = builder.add_documents(content).unwrap_err();
+        assert!(matches!(error, crate::Error::UserError(UserError::InvalidDocumentId { .. })));
+        drop(builder);
+        drop(wtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "name": "kevin" }, { "id": 44, "name": "bob" }]);
+        let indexing_config = IndexDocumentsConfig {
+            autogenerate_docids: true,
+            docid_generation: DocidGeneration::Sequential,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert_eq!(external_ids(&wtxn), vec!["44", "45"]);
+        drop(wtxn);
+
+        // The biggest integer id can be generated once, the following ones are exhausted.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": u64::MAX - 1, "name": "kevin" }, { "name": "kevina" }]);
+        let indexing_config = IndexDocumentsConfig {
+            autogenerate_docids: true,
+            docid_generation: DocidGeneration::Sequential,
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert_eq!(external_ids(&wtxn), vec!["18446744073709551614", "18446744073709551615"]);
+
+        let content = documents!([{ "name": "bob" }]);
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        let error = builder.add_documents(content).unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::SequentialDocumentIdsExhausted)
+        ));
+        drop(builder);
+        drop(wtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        add_documents(&mut wtxn, &index, &config, DocidGeneration::UuidV7);
+        let uuids: Vec<_> = external_ids(&wtxn).into_iter().filter(|id| id.len() == 36).collect();
+        assert_eq!(uuids.len(), 2);
+        assert!(uuids.iter().all(|id| id.as_bytes()[14] == b'7'));
+        drop(wtxn);
+
+        let counter = std::sync::atomic::AtomicUsize::new(0);
+        let generate = move || {
+            let count = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            format!("legacy-{}", count)
+        };
+        let mut wtxn = index.write_txn().unwrap();
+        add_documents(&mut wtxn, &index, &config, DocidGeneration::Custom(Arc::new(generate)));
+        assert!(index.external_documents_ids(&wtxn).unwrap().get("legacy-1").is_some());
+    }
+
     #[test]
     fn not_auto_generated_documents_ids() {
         let path = tempfile::tempdir().unwrap();
@@ -713,7 +1409,7 @@ mod tests {
         let docs = index.documents(&rtxn, vec![0, 1, 2]).unwrap();
         let (kevin_id, _) =
             docs.iter().find(|(_, d)| d.get(0).unwrap() == br#""updated kevin""#).unwrap();
-        let (id, doc) = docs[*kevin_id as usize];
+        let (id, doc) = docs[*kevin_id as usize].clone();
         assert_eq!(id, *kevin_id);
 
         // Check that this document is equal to the last
@@ -866,24 +1562,190 @@ mod tests {
     }
 
     #[test]
-    fn simple_documents_replace() {
+    fn databases_metrics() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
         let index = Index::new(options, &path).unwrap();
 
-        // First we send 3 documents with an id for only one of them.
+        let config = IndexerConfig::default();
         let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
         let documents = documents!([
-          { "id": 2,    "title": "Pride and Prejudice",                    "author": "Jane Austin",              "genre": "romance",    "price": 3.5, "_geo": { "lat": 12, "lng": 42 } },
-          { "id": 456,  "title": "Le Petit Prince",                        "author": "Antoine de Saint-Exupéry", "genre": "adventure" , "price": 10.0 },
-          { "id": 1,    "title": "Alice In Wonderland",                    "author": "Lewis Carroll",            "genre": "fantasy",    "price": 25.99 },
-          { "id": 1344, "title": "The Hobbit",                             "author": "J. R. R. Tolkien",         "genre": "fantasy" },
-          { "id": 4,    "title": "Harry Potter and the Half-Blood Prince", "author": "J. K. Rowling",            "genre": "fantasy" },
-          { "id": 42,   "title": "The Hitchhiker's Guide to the Galaxy",   "author": "Douglas Adams", "_geo": { "lat": 35, "lng": 23 } }
+            { "id": 0, "title": "hello world", "price": 10 },
+            { "id": 1, "title": "hello there", "price": 20 }
         ]);
-        let config = IndexerConfig::default();
-        let indexing_config = IndexDocumentsConfig {
+        let steps = std::sync::Mutex::new(Vec::new());
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |step| {
+                steps.lock().unwrap().push(step)
+            });
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let steps = steps.into_inner().unwrap();
+        let extracted: Vec<_> = steps
+            .iter()
+            .filter_map(|step| match step {
+                UpdateIndexingStep::ExtractDatabase { database, chunks, bytes, .. } => {
+                    assert_eq!(*chunks, 1);
+                    assert!(*bytes > 0);
+                    Some(*database)
+                }
+                _ => None,
+            })
+            .collect();
+        assert!(extracted.contains(&"word-docids"));
+        assert!(extracted.contains(&"field-id-facet-number-docids"));
+
+        let written: Vec<_> = steps
+            .iter()
+            .filter_map(|step| match step {
+                UpdateIndexingStep::WriteDatabase { database, .. } => Some(*database),
+                _ => None,
+            })
+            .collect();
+        assert!(written.contains(&db_name::DOCUMENTS));
+        assert!(written.contains(&db_name::WORD_DOCIDS));
+        assert!(written.contains(&db_name::FACET_ID_F64_DOCIDS));
+    }
+
+    #[test]
+    fn merge_conflicts_metrics() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        fn extract<'t, 'u, 'i>(
+            wtxn: &'t mut heed::RwTxn<'i, 'u>,
+            index: &'i Index,
+            config: &IndexerConfig,
+            documents: DocumentBatchReader<Cursor<Vec<u8>>>,
+        ) -> Vec<(&'static str, Option<u64>)> {
+            let steps = std::sync::Mutex::new(Vec::new());
+            let indexing_config = IndexDocumentsConfig::default();
+            let mut builder = IndexDocuments::new(wtxn, index, config, indexing_config, |step| {
+                if let UpdateIndexingStep::ExtractDatabase { database, merge_conflicts, .. } = step
+                {
+                    steps.lock().unwrap().push((database, merge_conflicts));
+                }
+            });
+            builder.add_documents(documents).unwrap();
+            builder.execute().unwrap();
+            steps.into_inner().unwrap()
+        }
+
+        // The conflicts are not counted by default.
+        let documents = documents!([{ "id": 0, "color": "red" }, { "id": 1, "color": "red" }]);
+        let steps = extract(&mut wtxn, &index, &config, documents);
+        assert!(steps.iter().all(|(_, conflicts)| conflicts.is_none()));
+
+        config.count_merge_conflicts = true;
+        let documents = documents!([
+            { "id": 2, "color": "blue" },
+            { "id": 3, "color": "blue" },
+            { "id": 4, "color": "Blue" },
+            { "id": 5, "color": "green" }
+        ]);
+        let steps = extract(&mut wtxn, &index, &config, documents);
+        let conflicts = |name| steps.iter().find(|(database, _)| *database == name).unwrap().1;
+        // The three blue values are merged into one key.
+        assert_eq!(conflicts("field-id-facet-string-docids"), Some(2));
+        assert_eq!(conflicts("field-id-facet-number-docids"), Some(0));
+    }
+
+    #[test]
+    fn replace_documents_keeping_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let documents = documents!([
+            { "id": 0, "title": "kevin", "tags": "old", "popularity": 12, "rank": 1 },
+            { "id": 1, "title": "bob", "popularity": 3, "rank": 2 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::ReplaceKeepingFields(vec![
+                S("popularity"),
+                S("rank"),
+            ]),
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        let documents = documents!([
+            { "id": 0, "title": "kevina" },
+            { "id": 1, "title": "bobby", "rank": 5 },
+            { "id": 2, "title": "alice" }
+        ]);
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let title = fields_ids_map.id("title").unwrap();
+        let tags = fields_ids_map.id("tags").unwrap();
+        let popularity = fields_ids_map.id("popularity").unwrap();
+        let rank = fields_ids_map.id("rank").unwrap();
+
+        let docs = index.documents(&wtxn, vec![0, 1, 2]).unwrap();
+        let (_, kevin) = docs[0].clone();
+        assert_eq!(kevin.get(title), Some(&br#""kevina""#[..]));
+        assert_eq!(kevin.get(tags), None);
+        assert_eq!(kevin.get(popularity), Some(&b"12"[..]));
+        assert_eq!(kevin.get(rank), Some(&b"1"[..]));
+
+        // The values of the new document have the priority.
+        let (_, bob) = docs[1].clone();
+        assert_eq!(bob.get(popularity), Some(&b"3"[..]));
+        assert_eq!(bob.get(rank), Some(&b"5"[..]));
+
+        let (_, alice) = docs[2].clone();
+        assert_eq!(alice.get(popularity), None);
+
+        // The kept fields are still counted in the field distribution.
+        let field_distribution = index.field_distribution(&wtxn).unwrap();
+        assert_eq!(field_distribution.get("popularity"), Some(&2));
+        assert_eq!(field_distribution.get("tags"), None);
+    }
+
+    #[test]
+    fn simple_documents_replace() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // First we send 3 documents with an id for only one of them.
+        let mut wtxn = index.write_txn().unwrap();
+        let documents = documents!([
+          { "id": 2,    "title": "Pride and Prejudice",                    "author": "Jane Austin",              "genre": "romance",    "price": 3.5, "_geo": { "lat": 12, "lng": 42 } },
+          { "id": 456,  "title": "Le Petit Prince",                        "author": "Antoine de Saint-Exupéry", "genre": "adventure" , "price": 10.0 },
+          { "id": 1,    "title": "Alice In Wonderland",                    "author": "Lewis Carroll",            "genre": "fantasy",    "price": 25.99 },
+          { "id": 1344, "title": "The Hobbit",                             "author": "J. R. R. Tolkien",         "genre": "fantasy" },
+          { "id": 4,    "title": "Harry Potter and the Half-Blood Prince", "author": "J. K. Rowling",            "genre": "fantasy" },
+          { "id": 42,   "title": "The Hitchhiker's Guide to the Galaxy",   "author": "Douglas Adams", "_geo": { "lat": 35, "lng": 23 } }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig {
             update_method: IndexDocumentsMethod::ReplaceDocuments,
             ..Default::default()
         };
@@ -965,6 +1827,55 @@ mod tests {
         wtxn.commit().unwrap();
     }
 
+    #[test]
+    fn geo_data_only_updated_when_coordinates_change() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "city": "Lille", "_geo": { "lat": 50.62, "lng": 3.05 } },
+            { "id": 1, "city": "Paris", "_geo": { "lat": 48.90, "lng": 2.37 } }
+        ]);
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // Only the city changes, the geo data is kept as is.
+        let content = documents!([
+            { "id": 0, "city": "Lille Centre" },
+            { "id": 1, "city": "Paris", "_geo": { "lat": 48.85, "lng": 2.35 } }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        let lille = external_documents_ids.get("0").unwrap();
+        let paris = external_documents_ids.get("1").unwrap();
+        assert_eq!(index.geo_faceted_documents_ids(&rtxn).unwrap().len(), 2);
+
+        let rtree = index.geo_rtree(&rtxn).unwrap().unwrap();
+        let mut points: Vec<_> = rtree.iter().map(|point| point.data).collect();
+        points.sort_unstable_by_key(|(docid, _)| *docid);
+        assert_eq!(points, vec![(lille, [50.62, 3.05]), (paris, [48.85, 2.35])]);
+    }
+
     #[test]
     fn index_more_than_256_fields() {
         let path = tempfile::tempdir().unwrap();
@@ -1043,6 +1954,51 @@ mod tests {
         assert!(index.word_docids.get(&mut rtxn, "65535").unwrap().is_some());
     }
 
+    #[test]
+    fn max_word_and_facet_value_length() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut config = IndexerConfig::default();
+        config.max_word_length = Some(6);
+        config.max_facet_value_length = Some(6);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let documents = documents!([
+            { "id": 0, "title": "short lengthiest", "color": "yellow" },
+            { "id": 1, "title": "short", "color": "turquoise" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+
+        assert!(index.word_docids.get(&wtxn, "short").unwrap().is_some());
+        assert!(index.word_docids.get(&wtxn, "lengthiest").unwrap().is_none());
+
+        let filter = crate::Filter::from_str("color = yellow").unwrap().unwrap();
+        assert_eq!(filter.evaluate(&wtxn, &index).unwrap(), RoaringBitmap::from_iter([0]));
+        let filter = crate::Filter::from_str("color = turquoise").unwrap().unwrap();
+        assert!(filter.evaluate(&wtxn, &index).unwrap().is_empty());
+
+        // The configured lengths cannot go beyond the limits of LMDB.
+        config.max_word_length = Some(usize::MAX);
+        let word = "a".repeat(crate::limits::MAX_WORD_LENGTH + 1);
+        let documents = documents!([{ "id": 2, "title": word }]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+
+        assert!(index.word_docids.get(&wtxn, &word).unwrap().is_none());
+    }
+
     #[test]
     fn index_documents_with_zeroes() {
         let path = tempfile::tempdir().unwrap();
@@ -1197,4 +2153,483 @@ mod tests {
         let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
         assert_eq!(documents_ids.len(), 1);
     }
+
+    #[test]
+    fn index_word_fid_docids() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = documents!([
+          {"id": 0, "title": "hello", "description": "world" },
+          {"id": 1, "title": "world", "description": "hello world" }
+        ]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let title = fields_ids_map.id("title").unwrap();
+        let description = fields_ids_map.id("description").unwrap();
+
+        let docids = index.word_fid_docids.get(&rtxn, &("hello", title)).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0]);
+        let docids = index.word_fid_docids.get(&rtxn, &("hello", description)).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+        let docids = index.word_fid_docids.get(&rtxn, &("world", title)).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+        let docids = index.word_fid_docids.get(&rtxn, &("world", description)).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn index_exact_word_docids() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = documents!([
+          {"id": 0, "title": "cafe" },
+          {"id": 1, "title": "Café" },
+          {"id": 2, "title": "CAFÉ crème" }
+        ]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // The documents are all found with the normalized word...
+        let docids = index.word_docids.get(&rtxn, "cafe").unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+        // ...but the exact words are only lowercased.
+        let docids = index.exact_word_docids.get(&rtxn, "cafe").unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0]);
+        let docids = index.exact_word_docids.get(&rtxn, "café").unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1, 2]);
+
+        // The documents with the words as written in the query are ranked first.
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("Café");
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 2, 0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("0");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.exact_word_docids.get(&rtxn, "cafe").unwrap().is_none());
+        let docids = index.exact_word_docids.get(&rtxn, "café").unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn nested_faceted_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let content = documents!([
+          { "id": 0, "address": { "city": "Paris", "zip": 75001 } },
+          { "id": 1, "address": [{ "city": "Lyon" }, { "city": "Paris", "zip": 75002 }] },
+          { "id": 2, "address": { "city": "Nice" } },
+          { "id": 3, "address": "nowhere" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The documents are reindexed when the nested fields become filterable.
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("address.city"), S("address.zip") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = crate::Filter::from_str("address.city = paris").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        let filter = crate::Filter::from_str("address.zip > 75001").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+
+        let distribution = crate::FacetDistribution::new(&rtxn, &index)
+            .facets(Some("address.city"))
+            .execute()
+            .unwrap();
+        let cities: Vec<_> =
+            distribution["address.city"].iter().map(|(k, v)| (k.to_lowercase(), *v)).collect();
+        assert_eq!(cities, vec![(S("lyon"), 1), (S("nice"), 1), (S("paris"), 2)]);
+        drop(rtxn);
+
+        // The nested fields of the new documents are extracted too.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([ { "id": 4, "address": { "city": "Lyon" } } ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = crate::Filter::from_str("address.city = lyon").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1, 4]);
+    }
+
+    #[test]
+    fn flattened_faceted_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("tags") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+          { "id": 0, "tags": [{ "name": "rust", "color": "red" }, { "name": "go" }] },
+          { "id": 1, "tags": [{ "name": "rust", "color": "blue" }] },
+          { "id": 2, "tags": "untagged" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let flattened = index.flattened_faceted_fields(&rtxn).unwrap();
+        assert_eq!(flattened.keys().collect::<Vec<_>>(), vec!["tags.color", "tags.name"]);
+
+        let filter = crate::Filter::from_str("tags.name = rust").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        // The distribution of the field aggregates the values of its flattened fields.
+        let distribution =
+            crate::FacetDistribution::new(&rtxn, &index).facets(Some("tags")).execute().unwrap();
+        let names: Vec<_> =
+            distribution["tags.name"].iter().map(|(k, v)| (k.to_lowercase(), *v)).collect();
+        assert_eq!(names, vec![(S("go"), 1), (S("rust"), 2)]);
+        let colors: Vec<_> =
+            distribution["tags.color"].iter().map(|(k, v)| (k.to_lowercase(), *v)).collect();
+        assert_eq!(colors, vec![(S("blue"), 1), (S("red"), 1)]);
+        drop(rtxn);
+
+        // The flattened fields are forgotten with the field they come from.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.reset_filterable_fields();
+        builder.execute(|_| ()).unwrap();
+        assert!(index.flattened_faceted_fields(&wtxn).unwrap().is_empty());
+        assert!(crate::Filter::from_str("tags.name = rust")
+            .unwrap()
+            .unwrap()
+            .evaluate(&wtxn, &index)
+            .is_err());
+    }
+
+    #[test]
+    fn max_tmp_disk_usage_reached() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = || documents!([{ "id": 0, "title": "hello world" }]);
+
+        // The documents written by the transform are already beyond the maximum.
+        let config = IndexerConfig { max_tmp_disk_usage: Some(1), ..Default::default() };
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content()).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(crate::UserError::MaxTmpDiskUsageReached {
+                phase: "transform",
+                max: 1,
+                ..
+            })
+        ));
+        drop(wtxn);
+
+        let config =
+            IndexerConfig { max_tmp_disk_usage: Some(100 * 1024 * 1024), ..Default::default() };
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content()).unwrap();
+        builder.execute().unwrap();
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 1);
+    }
+
+    #[test]
+    fn documents_chunk_size_reported() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let chunk_sizes = std::sync::Mutex::new(Vec::new());
+        let progress = |step| {
+            if let UpdateIndexingStep::DocumentsChunkSize { documents_chunk_size, tuned } = step {
+                chunk_sizes.lock().unwrap().push((documents_chunk_size, tuned));
+            }
+        };
+
+        let configs = vec![
+            IndexerConfig::default(),
+            IndexerConfig { documents_chunk_size: Some(1024), ..Default::default() },
+        ];
+        for config in &configs {
+            let mut wtxn = index.write_txn().unwrap();
+            let content = documents!([{ "id": 0, "title": "hello world" }]);
+            let indexing_config = IndexDocumentsConfig::default();
+            let mut builder =
+                IndexDocuments::new(&mut wtxn, &index, config, indexing_config, progress);
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+        }
+
+        // A few small documents are extracted in a single chunk of the minimum size.
+        let chunk_sizes = chunk_sizes.into_inner().unwrap();
+        assert_eq!(chunk_sizes, vec![(64 * 1024, true), (1024, false)]);
+    }
+
+    #[test]
+    fn documents_chunk_size_tuning() {
+        let tuned = tuned_documents_chunk_size;
+
+        // 1GiB of documents of 1KiB are split into 4 chunks per thread.
+        let gib = 1024 * 1024 * 1024;
+        assert_eq!(tuned(gib, 1024 * 1024, 8, None), 32 * 1024 * 1024);
+        // The memory of a thread must hold the data extracted from its chunk.
+        assert_eq!(tuned(gib, 1024 * 1024, 8, Some(gib as usize)), 16 * 1024 * 1024);
+        // A chunk contains at least 100 documents.
+        assert_eq!(tuned(gib, 1024, 8, None), 100 * 1024 * 1024);
+        // The chunks are never too small nor too big.
+        assert_eq!(tuned(1024, 10, 8, None), 64 * 1024);
+        assert_eq!(tuned(100 * gib, 1024, 1, None), 256 * 1024 * 1024);
+    }
+
+    #[test]
+    fn nested_primary_key() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_primary_key(S("product.id"));
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "product": { "id": "a1", "name": "shoes" } },
+            { "product": { "id": 2, "name": "hat" } },
+            { "product.id": "b3", "product": { "name": "socks" } }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The documents are replaced by their nested id.
+        let content = documents!([{ "product": { "id": "a1", "name": "boots" } }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert!(external_documents_ids.get("a1").is_some());
+        assert!(external_documents_ids.get("2").is_some());
+        assert!(external_documents_ids.get("b3").is_some());
+
+        let content = documents!([{ "product": { "name": "gloves" } }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        let error = builder.add_documents(content).unwrap_err();
+        assert!(error.to_string().contains("`product` > `id`"));
+    }
+
+    #[test]
+    fn skip_invalid_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "shoes" },
+            { "id": "not a valid id!", "title": "hat" },
+            { "title": "socks" },
+            { "id": 3, "title": "gloves" }
+        ]);
+
+        // The whole addition fails by default.
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        assert!(builder.add_documents(content).is_err());
+        drop(builder);
+
+        let content = documents!([
+            { "id": 0, "title": "shoes" },
+            { "id": "not a valid id!", "title": "hat" },
+            { "title": "socks" },
+            { "id": 3, "title": "gloves" }
+        ]);
+        let indexing_config =
+            IndexDocumentsConfig { skip_invalid_documents: true, ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        assert_eq!(builder.add_documents(content).unwrap(), 2);
+        // The positions continue across the batches added to the builder.
+        let content = documents!([{ "id": 4, "title": "scarf" }, { "id": ["5"], "title": "belt" }]);
+        assert_eq!(builder.add_documents(content).unwrap(), 1);
+        let result = builder.execute().unwrap();
+
+        assert_eq!(result.indexed_documents, 3);
+        assert_eq!(result.number_of_documents, 3);
+        let positions: Vec<_> = result.rejected_documents.iter().map(|(i, _)| *i).collect();
+        assert_eq!(positions, vec![1, 2, 5]);
+        assert!(matches!(result.rejected_documents[0].1, UserError::InvalidDocumentId { .. }));
+        assert!(matches!(result.rejected_documents[1].1, UserError::MissingDocumentId { .. }));
+        assert!(matches!(result.rejected_documents[2].1, UserError::InvalidDocumentId { .. }));
+
+        // The rejections are reported even when no document is left to index.
+        let content = documents!([{ "title": "cap" }]);
+        let indexing_config =
+            IndexDocumentsConfig { skip_invalid_documents: true, ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        assert_eq!(builder.add_documents(content).unwrap(), 0);
+        let result = builder.execute().unwrap();
+        assert_eq!(result.indexed_documents, 0);
+        assert_eq!(result.rejected_documents.len(), 1);
+    }
+
+    #[test]
+    fn validate_documents_only() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+        let fields: Vec<_> =
+            index.fields_ids_map(&wtxn).unwrap().names().map(String::from).collect();
+
+        let content = documents!([
+            { "id": 0, "title": "shoes", "_geo": { "lat": 12, "lng": 42 } },
+            { "id": 1, "title": "hat" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        let result = builder.validate_only().unwrap();
+        assert_eq!(result.indexed_documents, 2);
+        assert_eq!(result.number_of_documents, 2);
+
+        // Nothing is written, not even the new fields and the primary key.
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 0);
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        assert_eq!(fields_ids_map.names().collect::<Vec<_>>(), fields);
+        assert_eq!(index.primary_key(&wtxn).unwrap(), None);
+
+        // The invalid geo fields are reported like in a real run.
+        let content = documents!([{ "id": 2, "_geo": { "lat": "twelve" } }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        let error = builder.validate_only().unwrap_err();
+        assert!(matches!(error, crate::Error::UserError(UserError::InvalidGeoField { .. })));
+        assert_eq!(index.primary_key(&wtxn).unwrap(), None);
+    }
+
+    #[test]
+    fn resume_persisted_batches() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let batches = tempfile::tempdir().unwrap();
+
+        // The first process adds two batches and stops before committing.
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.persist_batches(batches.path()).unwrap();
+        builder.add_documents(documents!([{ "id": 1, "name": "kevin" }])).unwrap();
+        builder.add_documents(documents!([{ "id": 2, "age": 21, "name": "kevina" }])).unwrap();
+        drop(builder);
+        drop(wtxn);
+
+        // Another field is indexed in the meantime and takes the first field id.
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(documents!([{ "country": "france", "id": 3 }])).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        assert_eq!(builder.resume_batches(batches.path()).unwrap(), 2);
+        builder.add_documents(documents!([{ "id": 1, "name": "kevin", "age": 24 }])).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let fields: Vec<_> = fields_ids_map.ids().collect();
+        let external_ids = index.external_documents_ids(&rtxn).unwrap();
+        let docid = external_ids.get("2").unwrap();
+        let (_, obkv) = index.documents(&rtxn, Some(docid)).unwrap().pop().unwrap();
+        let document = crate::obkv_to_json(&fields, &fields_ids_map, obkv.as_obkv()).unwrap();
+        assert_eq!(document["age"], serde_json::json!(21));
+        assert_eq!(document["name"], serde_json::json!("kevina"));
+        assert!(!document.contains_key("country"));
+        let docid = external_ids.get("1").unwrap();
+        let (_, obkv) = index.documents(&rtxn, Some(docid)).unwrap().pop().unwrap();
+        let document = crate::obkv_to_json(&fields, &fields_ids_map, obkv.as_obkv()).unwrap();
+        assert_eq!(document["age"], serde_json::json!(24));
+    }
 }
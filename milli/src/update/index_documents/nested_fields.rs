@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use serde_json::Value;
+
+use crate::error::{InternalError, UserError};
+use crate::{FieldId, FieldsIdsMap, Result};
+
+/// How the names of the fields nested in the objects of the documents are written
+/// and resolved, e.g. `address.city` for the `city` of the `address` object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlattenConfig {
+    /// The separator between the keys of a nested field name, a `.` by default.
+    pub separator: char,
+    /// The maximum number of keys following the top-level field in a nested field name,
+    /// the deeper nested fields are ignored, unlimited by default.
+    pub max_depth: Option<usize>,
+    /// Whether a numeric key selects the element at this position in an array,
+    /// e.g. `addresses.0.city`, by default the keys apply to all the elements.
+    pub positional_arrays: bool,
+}
+
+impl Default for FlattenConfig {
+    fn default() -> FlattenConfig {
+        FlattenConfig { separator: '.', max_depth: None, positional_arrays: false }
+    }
+}
+
+/// A field nested in the objects of a top-level field of the documents,
+/// it is given its own field id to be faceted like the top-level fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedField {
+    /// The field id given to the nested field.
+    pub field_id: FieldId,
+    /// The top-level field of the documents containing the nested field.
+    pub parent_id: FieldId,
+    /// The keys to follow in the value of the top-level field.
+    pub path: Vec<String>,
+    /// Whether the numeric keys select the elements of the arrays by position.
+    pub positional_arrays: bool,
+}
+
+impl NestedField {
+    /// Returns the values found under the path of this nested field, the arrays of
+    /// objects are traversed and the values of all of their objects are returned,
+    /// unless a numeric key selects one of their elements with positional arrays.
+    pub fn values<'v>(&self, parent_value: &'v Value) -> Vec<&'v Value> {
+        fn inner_values<'v>(
+            value: &'v Value,
+            path: &[String],
+            positional_arrays: bool,
+            output: &mut Vec<&'v Value>,
+        ) {
+            match (value, path.split_first()) {
+                (value, None) => output.push(value),
+                (Value::Object(object), Some((key, tail))) => {
+                    if let Some(value) = object.get(key) {
+                        inner_values(value, tail, positional_arrays, output);
+                    }
+                }
+                (Value::Array(values), Some((key, tail))) => match key.parse::<usize>() {
+                    Ok(position) if positional_arrays => {
+                        if let Some(value) = values.get(position) {
+                            inner_values(value, tail, positional_arrays, output);
+                        }
+                    }
+                    _ => values
+                        .iter()
+                        .for_each(|value| inner_values(value, path, positional_arrays, output)),
+                },
+                (_, Some(_)) => (),
+            }
+        }
+
+        let mut output = Vec::new();
+        inner_values(parent_value, &self.path, self.positional_arrays, &mut output);
+        output
+    }
+}
+
+/// Gives a field id to the given field names that are nested in a top-level field of the
+/// documents, the shortest prefix of a name that is a known field is the top-level field.
+///
+/// The names that are top-level fields themselves, the ones without a known top-level
+/// field and the ones nested deeper than the maximum depth are ignored.
+pub fn register_nested_fields<'n>(
+    fields_ids_map: &mut FieldsIdsMap,
+    names: impl IntoIterator<Item = &'n str>,
+    config: FlattenConfig,
+) -> Result<Vec<NestedField>> {
+    let separator = config.separator;
+    let mut nested_fields = Vec::new();
+    for name in names {
+        let parent = name
+            .match_indices(separator)
+            .map(|(i, _)| &name[..i])
+            .find_map(|prefix| fields_ids_map.id(prefix).map(|id| (prefix, id)));
+
+        if let Some((prefix, parent_id)) = parent {
+            let path: Vec<_> = name[prefix.len() + separator.len_utf8()..]
+                .split(separator)
+                .map(String::from)
+                .collect();
+            if config.max_depth.map_or(false, |max_depth| path.len() > max_depth) {
+                continue;
+            }
+
+            let field_id = match fields_ids_map.id(name) {
+                Some(field_id) => field_id,
+                None => fields_ids_map.insert(name).ok_or(UserError::AttributeLimitReached)?,
+            };
+            let positional_arrays = config.positional_arrays;
+            nested_fields.push(NestedField { field_id, parent_id, path, positional_arrays });
+        }
+    }
+
+    Ok(nested_fields)
+}
+
+/// Inserts the names of the fields nested in the objects of the value of a top-level field,
+/// e.g. `tags.name` and `tags.color` for `tags: [{ "name": .., "color": .. }]`, the objects
+/// of the arrays are traversed like [`NestedField::values`] does.
+///
+/// The keys containing the separator can't be written with it and are ignored,
+/// like the fields nested deeper than the maximum depth.
+pub fn flattened_field_names(
+    name: &str,
+    value: &Value,
+    config: FlattenConfig,
+    output: &mut Vec<String>,
+) {
+    fn inner(
+        name: &str,
+        depth: usize,
+        value: &Value,
+        config: FlattenConfig,
+        output: &mut Vec<String>,
+    ) {
+        match value {
+            Value::Object(object) => {
+                if config.max_depth.map_or(false, |max_depth| depth >= max_depth) {
+                    return;
+                }
+                for (key, value) in object.iter().filter(|(key, _)| !key.contains(config.separator))
+                {
+                    let name = format!("{}{}{}", name, config.separator, key);
+                    inner(&name, depth + 1, value, config, output);
+                }
+            }
+            Value::Array(values) => {
+                values.iter().for_each(|value| inner(name, depth, value, config, output))
+            }
+            _ if depth > 0 && !output.iter().any(|n| n == name) => output.push(name.to_string()),
+            _ => (),
+        }
+    }
+
+    inner(name, 0, value, config, output)
+}
+
+/// Returns the names of the fields nested in the objects of the given top-level fields
+/// of the obkv documents, associated with the name of the top-level field they are in.
+pub fn flatten_object_fields<R: io::Read + io::Seek>(
+    obkv_documents: grenad::Reader<R>,
+    fields: &[(FieldId, &str)],
+    config: FlattenConfig,
+) -> Result<BTreeMap<String, String>> {
+    let mut flattened_fields = BTreeMap::new();
+    let mut names = Vec::new();
+    let mut cursor = obkv_documents.into_cursor()?;
+    while let Some((_, value)) = cursor.move_on_next()? {
+        let obkv = obkv::KvReader::<FieldId>::new(value);
+        for (field_id, name) in fields {
+            if let Some(field_bytes) = obkv.get(*field_id) {
+                let value: Value =
+                    serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
+                names.clear();
+                flattened_field_names(name, &value, config, &mut names);
+                for flattened in names.drain(..) {
+                    flattened_fields.entry(flattened).or_insert_with(|| name.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(flattened_fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn register_and_extract() {
+        let mut fields_ids_map = FieldsIdsMap::new();
+        let id = fields_ids_map.insert("id").unwrap();
+        let address = fields_ids_map.insert("address").unwrap();
+
+        let names = vec!["id", "address.city", "address.geo.country", "unknown.field"];
+        let config = FlattenConfig::default();
+        let nested_fields = register_nested_fields(&mut fields_ids_map, names, config).unwrap();
+        assert_eq!(nested_fields.len(), 2);
+        assert_eq!(nested_fields[0].parent_id, address);
+        assert_eq!(nested_fields[0].path, vec!["city"]);
+        assert_eq!(fields_ids_map.id("address.city"), Some(nested_fields[0].field_id));
+        assert_eq!(nested_fields[1].path, vec!["geo", "country"]);
+        assert_ne!(nested_fields[1].field_id, id);
+        assert_eq!(fields_ids_map.id("unknown.field"), None);
+
+        // The fields ids are kept when registering the fields again.
+        let names = vec!["address.city"];
+        let again = register_nested_fields(&mut fields_ids_map, names, config).unwrap();
+        assert_eq!(again[0], nested_fields[0]);
+
+        let value = json!([
+            { "city": "Paris", "geo": { "country": "France" } },
+            { "city": ["Lyon", "Nice"] },
+            "not an object"
+        ]);
+        assert_eq!(
+            nested_fields[0].values(&value),
+            vec![&json!("Paris"), &json!(["Lyon", "Nice"])]
+        );
+        assert_eq!(nested_fields[1].values(&value), vec![&json!("France")]);
+    }
+
+    #[test]
+    fn flatten_config() {
+        let mut fields_ids_map = FieldsIdsMap::new();
+        fields_ids_map.insert("address").unwrap();
+
+        // The keys containing dots are not split with another separator.
+        let config = FlattenConfig { separator: '/', max_depth: Some(1), positional_arrays: true };
+        let names = vec!["address/geo.country", "address/0/city", "address/geo/country"];
+        let nested_fields = register_nested_fields(&mut fields_ids_map, names, config).unwrap();
+        assert_eq!(nested_fields.len(), 1);
+        assert_eq!(nested_fields[0].path, vec!["geo.country"]);
+        assert_eq!(fields_ids_map.id("address/0/city"), None);
+
+        let value = json!({ "geo.country": "France" });
+        assert_eq!(nested_fields[0].values(&value), vec![&json!("France")]);
+
+        let config = FlattenConfig { positional_arrays: true, ..FlattenConfig::default() };
+        let names = vec!["address.1.city", "address.city"];
+        let nested_fields = register_nested_fields(&mut fields_ids_map, names, config).unwrap();
+        let value = json!([{ "city": "Paris" }, { "city": "Lyon" }]);
+        assert_eq!(nested_fields[0].values(&value), vec![&json!("Lyon")]);
+        assert_eq!(nested_fields[1].values(&value), vec![&json!("Paris"), &json!("Lyon")]);
+    }
+
+    #[test]
+    fn flatten_field_names() {
+        let value = json!([
+            { "name": "rust", "color": "red", "meta": { "score": 2, "a/b": 1 } },
+            { "name": "go", "aliases": ["golang"] },
+            "not an object"
+        ]);
+
+        let mut names = Vec::new();
+        flattened_field_names("tags", &value, FlattenConfig::default(), &mut names);
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["tags.aliases", "tags.color", "tags.meta.a/b", "tags.meta.score", "tags.name"]
+        );
+
+        let config = FlattenConfig { separator: '/', max_depth: Some(1), positional_arrays: false };
+        let mut names = Vec::new();
+        flattened_field_names("tags", &value, config, &mut names);
+        names.sort();
+        assert_eq!(names, vec!["tags/aliases", "tags/color", "tags/name"]);
+
+        // The values that are not objects have no nested fields.
+        let mut names = Vec::new();
+        flattened_field_names("tags", &json!(["rust", 12]), FlattenConfig::default(), &mut names);
+        assert!(names.is_empty());
+    }
+}
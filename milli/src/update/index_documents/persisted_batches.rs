@@ -0,0 +1,97 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::helpers::{create_writer, MergeFn};
+use crate::error::InternalError;
+use crate::update::IndexerConfig;
+use crate::{FieldsIdsMap, Result};
+
+const STATE_FILE_NAME: &str = "state.json";
+
+/// Describes the batches persisted in a directory, it is rewritten after every batch.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct BatchesState {
+    pub primary_key: Option<String>,
+    /// The fields ids of the documents of the batches, the ones of the index
+    /// can have changed when the batches are resumed.
+    pub fields_ids_map: FieldsIdsMap,
+    /// The file names of the batches, in the order they were added, and their number of documents.
+    pub batches: Vec<(String, usize)>,
+}
+
+/// A directory where the batches of documents given to a `Transform` are written,
+/// sorted by external id, so that another process can resume them after a crash.
+pub(crate) struct PersistedBatches {
+    path: PathBuf,
+    state: BatchesState,
+}
+
+impl PersistedBatches {
+    /// Opens or creates the directory, the batches already persisted in it are kept.
+    pub fn open(path: impl AsRef<Path>) -> Result<PersistedBatches> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+        let state = read_state(&path)?.unwrap_or_default();
+        Ok(PersistedBatches { path, state })
+    }
+
+    /// Writes the documents of a batch and then the state that refers to them,
+    /// a batch is never resumed if the process stopped while writing it.
+    pub fn write_batch(
+        &mut self,
+        sorter: grenad::Sorter<MergeFn>,
+        documents_count: usize,
+        primary_key: &str,
+        fields_ids_map: &FieldsIdsMap,
+        indexer_settings: &IndexerConfig,
+    ) -> Result<()> {
+        let name = format!("batch-{}.grenad", self.state.batches.len());
+        let mut writer = create_writer(
+            indexer_settings.chunk_compression_type,
+            indexer_settings.chunk_compression_level,
+            File::create(self.path.join(&name))?,
+        );
+        sorter.write_into_stream_writer(&mut writer)?;
+        writer.into_inner()?.sync_all()?;
+
+        self.state.primary_key = Some(primary_key.to_string());
+        self.state.fields_ids_map = fields_ids_map.clone();
+        self.state.batches.push((name, documents_count));
+        write_state(&self.path, &self.state)
+    }
+}
+
+/// Reads the state and the batches persisted in a directory, in the order they were added.
+pub(crate) fn read_batches(
+    path: impl AsRef<Path>,
+) -> Result<(BatchesState, Vec<grenad::Reader<File>>)> {
+    let path = path.as_ref();
+    let state = read_state(path)?.unwrap_or_default();
+    let mut readers = Vec::with_capacity(state.batches.len());
+    for (name, _) in &state.batches {
+        readers.push(grenad::Reader::new(File::open(path.join(name))?)?);
+    }
+    Ok((state, readers))
+}
+
+fn read_state(path: &Path) -> Result<Option<BatchesState>> {
+    match File::open(path.join(STATE_FILE_NAME)) {
+        Ok(file) => Ok(Some(serde_json::from_reader(file).map_err(InternalError::SerdeJson)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The state is written into a temporary file renamed over the previous one,
+/// it is never read partially written.
+fn write_state(path: &Path, state: &BatchesState) -> Result<()> {
+    let tmp_path = path.join(format!("{}.tmp", STATE_FILE_NAME));
+    let mut file = File::create(&tmp_path)?;
+    serde_json::to_writer(&mut file, state).map_err(InternalError::SerdeJson)?;
+    file.sync_all()?;
+    fs::rename(tmp_path, path.join(STATE_FILE_NAME))?;
+    Ok(())
+}
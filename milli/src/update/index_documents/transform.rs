@@ -1,24 +1,36 @@
 use std::borrow::Cow;
 use std::collections::btree_map::Entry;
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io::{Read, Seek, SeekFrom};
-use std::time::Instant;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+use heed::types::ByteSlice;
 use itertools::Itertools;
-use log::info;
-use roaring::RoaringBitmap;
+use roaring::{RoaringBitmap, RoaringTreemap};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tracing::info;
 
 use super::helpers::{
-    create_sorter, create_writer, keep_latest_obkv, merge_obkvs, merge_two_obkvs, MergeFn,
+    create_sorter, create_tracked_sorter, create_tracked_writer, keep_first, keep_latest_obkv,
+    merge_kept_fields_of_obkv, merge_obkvs, merge_two_obkvs, tracked_writer_into_reader,
+    GrenadParameters, MergeFn, TmpDiskUsage, TrackedSorter, TrackedTmpFile,
 };
-use super::{IndexDocumentsMethod, IndexerConfig};
+use super::persisted_batches::{read_batches, PersistedBatches};
+use super::{IndexDocumentsMethod, IndexerConfig, DEFAULT_DOCUMENTS_CHUNK_SIZE};
 use crate::documents::{DocumentBatchReader, DocumentsBatchIndex};
-use crate::error::{Error, InternalError, UserError};
+use crate::error::{Error, FieldIdMapMissingEntry, InternalError, UserError};
+use crate::field_types::{add_document_types, remove_document_types};
 use crate::index::db_name;
 use crate::update::{AvailableDocumentsIds, UpdateIndexingStep};
-use crate::{ExternalDocumentsIds, FieldDistribution, FieldId, FieldsIdsMap, Index, Result, BEU32};
+use crate::{
+    ExternalDocumentsIds, FieldDistribution, FieldId, FieldTypesDistribution, FieldsIdsMap, Index,
+    Result, BEU32,
+};
 
 const DEFAULT_PRIMARY_KEY_NAME: &str = "id";
 
@@ -26,27 +38,68 @@ pub struct TransformOutput {
     pub primary_key: String,
     pub fields_ids_map: FieldsIdsMap,
     pub field_distribution: FieldDistribution,
+    pub field_types_distribution: FieldTypesDistribution,
     pub external_documents_ids: ExternalDocumentsIds<'static>,
     pub new_documents_ids: RoaringBitmap,
     pub replaced_documents_ids: RoaringBitmap,
+    /// The replaced documents whose `_geo` field didn't change.
+    pub unchanged_geo_documents_ids: RoaringBitmap,
     pub documents_count: usize,
-    pub documents_file: File,
+    pub documents: TransformDocuments,
+    pub docid_external_ids_file: TrackedTmpFile,
+    /// The documents skipped because of their ids, by position in the documents read.
+    pub rejected_documents: Vec<(usize, UserError)>,
+    /// The temporary disk usage of the transform, continued by the indexing of its output.
+    pub tmp_disk_usage: TmpDiskUsage,
+}
+
+/// The documents of a `TransformOutput`, written under their internal ids.
+pub enum TransformDocuments {
+    /// A single file that is split into chunks to be extracted in parallel.
+    File(TrackedTmpFile),
+    /// The chunks extracted in parallel, written directly from the documents of the index
+    /// when they are reindexed, to avoid copying them once more into chunks.
+    Chunks(Vec<grenad::Reader<TrackedTmpFile>>),
 }
 
 /// Extract the external ids, deduplicate and compute the new internal documents ids
 /// and fields ids, writing all the documents under their internal ids into a final file.
 ///
 /// Outputs the new `FieldsIdsMap`, the new `UsersIdsDocumentsIds` map, the new documents ids,
-/// the replaced documents ids, the number of documents in this update and the file
-/// containing all those documents.
+/// the replaced documents ids, the number of documents in this update, the file
+/// containing all those documents and the file mapping their internal ids to their external ids.
 pub struct Transform<'a, 'i> {
     pub index: &'i Index,
     indexer_settings: &'a IndexerConfig,
     pub autogenerate_docids: bool,
     pub index_documents_method: IndexDocumentsMethod,
-
-    sorter: grenad::Sorter<MergeFn>,
+    /// Whether the `null` values of the updates remove the fields
+    /// from the documents, only with the `UpdateDocuments` method.
+    pub null_deletes_fields: bool,
+    pub document_id_normalization: DocumentIdNormalization,
+    pub document_id_charset: DocumentIdCharset,
+    /// How the missing documents ids are generated, with `autogenerate_docids`.
+    pub docid_generation: DocidGeneration,
+    /// Whether the documents with an invalid or missing id are skipped
+    /// instead of failing the whole addition.
+    pub skip_invalid_documents: bool,
+
+    merge_function: MergeFn,
+    /// The parameters of the temporary files written while transforming the documents.
+    grenad_parameters: GrenadParameters,
+    sorter: TrackedSorter,
     documents_count: usize,
+    /// The number of documents read, including the rejected ones.
+    documents_seen: usize,
+    rejected_documents: Vec<(usize, UserError)>,
+    persisted_batches: Option<PersistedBatches>,
+    next_sequential_docid: Option<u64>,
+    /// The biggest integer id sent with the documents, the sequential ids follow it.
+    biggest_integer_docid: Option<u64>,
+    /// The sequential ids generated by this transform, they can't be sent afterward.
+    generated_sequential_docids: RoaringTreemap,
+    /// The milliseconds and the counter of the last UUID v7 generated.
+    last_uuid_v7: (u64, u16),
 }
 
 /// Create a mapping between the field ids found in the document batch and the one that were
@@ -87,30 +140,54 @@ impl<'a, 'i> Transform<'a, 'i> {
         indexer_settings: &'a IndexerConfig,
         index_documents_method: IndexDocumentsMethod,
         autogenerate_docids: bool,
+        null_deletes_fields: bool,
+        document_id_normalization: DocumentIdNormalization,
+        document_id_charset: DocumentIdCharset,
     ) -> Self {
         // We must choose the appropriate merge function for when two or more documents
         // with the same user id must be merged or fully replaced in the same batch.
         let merge_function = match index_documents_method {
             IndexDocumentsMethod::ReplaceDocuments => keep_latest_obkv,
             IndexDocumentsMethod::UpdateDocuments => merge_obkvs,
+            IndexDocumentsMethod::ReplaceKeepingFields(_) => keep_latest_obkv,
+        };
+
+        // The files written by the transform are the first ones of the temporary disk usage.
+        let grenad_parameters = GrenadParameters {
+            chunk_compression_type: indexer_settings.chunk_compression_type,
+            chunk_compression_level: indexer_settings.chunk_compression_level,
+            max_memory: indexer_settings.max_memory,
+            max_nb_chunks: indexer_settings.max_nb_chunks,
+            tmp_disk_usage: TmpDiskUsage::new(indexer_settings.max_tmp_disk_usage)
+                .with_phase("transform"),
+            count_merge_conflicts: indexer_settings.count_merge_conflicts,
         };
 
         // We initialize the sorter with the user indexing settings.
-        let sorter = create_sorter(
-            merge_function,
-            indexer_settings.chunk_compression_type,
-            indexer_settings.chunk_compression_level,
-            indexer_settings.max_nb_chunks,
-            indexer_settings.max_memory,
-        );
+        let sorter =
+            create_tracked_sorter(merge_function, &grenad_parameters, grenad_parameters.max_memory);
 
         Transform {
             index,
             indexer_settings,
             autogenerate_docids,
+            merge_function,
+            grenad_parameters,
             sorter,
             documents_count: 0,
+            documents_seen: 0,
+            rejected_documents: Vec::new(),
+            persisted_batches: None,
             index_documents_method,
+            null_deletes_fields,
+            document_id_normalization,
+            document_id_charset,
+            docid_generation: DocidGeneration::default(),
+            skip_invalid_documents: false,
+            next_sequential_docid: None,
+            biggest_integer_docid: None,
+            generated_sequential_docids: RoaringTreemap::new(),
+            last_uuid_v7: (0, 0),
         }
     }
 
@@ -141,10 +218,52 @@ impl<'a, 'i> Transform<'a, 'i> {
             self.autogenerate_docids,
         )?;
 
+        // The same ids must be stored the same way by every batch, the policy of
+        // the index is only written once the first batch has been read.
+        let document_id_policy = DocumentIdPolicy {
+            normalization: self.document_id_normalization,
+            charset: self.document_id_charset.kind(),
+        };
+        let current_document_id_policy = self.index.document_id_policy(wtxn)?;
+        if let Some(current) = current_document_id_policy {
+            if current != document_id_policy {
+                return Err(UserError::DocumentIdPolicyCannotBeChanged { current }.into());
+            }
+        }
+
+        // A primary key like `product.id` is either a top-level field of the documents
+        // or the `id` of their `product` object, the shortest known top-level field is used.
+        let nested_primary_key: Vec<(FieldId, Vec<String>)> = primary_key_name
+            .match_indices('.')
+            .filter_map(|(i, _)| {
+                let parent_id = fields_ids_map.id(&primary_key_name[..i])?;
+                Some((parent_id, primary_key_name[i + 1..].split('.').map(String::from).collect()))
+            })
+            .collect();
+
+        // The batch is also written in its own sorter when the batches are persisted.
+        let mut batch_sorter = self.persisted_batches.as_ref().map(|_| {
+            create_sorter(
+                self.merge_function,
+                self.indexer_settings.chunk_compression_type,
+                self.indexer_settings.chunk_compression_level,
+                self.indexer_settings.max_nb_chunks,
+                self.indexer_settings.max_memory,
+            )
+        });
+
         let mut obkv_buffer = Vec::new();
         let mut documents_count = 0;
         let mut external_id_buffer = Vec::new();
         let mut field_buffer: Vec<(u16, &[u8])> = Vec::new();
+        // The documents waiting for a sequential id, in their order in the batch.
+        let sequential = self.docid_generation == DocidGeneration::Sequential;
+        let mut sequential_documents = if self.autogenerate_docids && sequential {
+            Some(create_tracked_writer(&self.grenad_parameters)?)
+        } else {
+            None
+        };
+        let mut sequential_documents_count: u64 = 0;
         while let Some((addition_index, document)) = reader.next_document_with_index()? {
             let mut field_buffer_cache = drop_and_reuse(field_buffer);
             if self.indexer_settings.log_every_n.map_or(false, |len| documents_count % len == 0) {
@@ -163,58 +282,84 @@ impl<'a, 'i> Transform<'a, 'i> {
             // it, transform it into a string and validate it, and then update it in the
             // document. If none is found, and we were told to generate missing document ids, then
             // we create the missing field, and update the new document.
-            let mut uuid_buffer = [0; uuid::adapter::Hyphenated::LENGTH];
-            let external_id =
-                match field_buffer_cache.iter_mut().find(|(id, _)| *id == primary_key_id) {
-                    Some((_, bytes)) => {
-                        let value = match serde_json::from_slice(bytes).unwrap() {
-                            Value::String(string) => match validate_document_id(&string) {
-                                Some(s) if s.len() == string.len() => string,
-                                Some(s) => s.to_string(),
-                                None => {
-                                    return Err(UserError::InvalidDocumentId {
-                                        document_id: Value::String(string),
-                                    }
-                                    .into())
-                                }
-                            },
-                            Value::Number(number) => number.to_string(),
-                            content => {
-                                return Err(UserError::InvalidDocumentId {
-                                    document_id: content.clone(),
-                                }
-                                .into())
+            let external_id = match field_buffer_cache
+                .iter_mut()
+                .find(|(id, _)| *id == primary_key_id)
+            {
+                Some((_, bytes)) => {
+                    let value = serde_json::from_slice(bytes).unwrap();
+                    match self.external_id_from_value(value) {
+                        Ok((value, normalized)) => {
+                            serde_json::to_writer(&mut external_id_buffer, &value).unwrap();
+                            // The document is stored with its normalized id.
+                            if normalized {
+                                *bytes = &external_id_buffer;
                             }
-                        };
-                        serde_json::to_writer(&mut external_id_buffer, &value).unwrap();
-                        Cow::Owned(value)
+                            self.check_sent_docid(value).map(Some)
+                        }
+                        Err(error) => Err(error),
                     }
-                    None => {
-                        if !self.autogenerate_docids {
-                            let mut json = Map::new();
-                            for (key, value) in document.iter() {
-                                let key = addition_index.name(key).cloned();
-                                let value = serde_json::from_slice::<Value>(&value).ok();
-
-                                if let Some((k, v)) = key.zip(value) {
-                                    json.insert(k, v);
-                                }
+                }
+                None => match nested_primary_key_value(&field_buffer_cache, &nested_primary_key) {
+                    // The nested ids are kept as they are in the stored documents.
+                    Some(value) => self
+                        .external_id_from_value(value)
+                        .and_then(|(id, _)| self.check_sent_docid(id))
+                        .map(Some),
+                    None if !self.autogenerate_docids => {
+                        let mut json = Map::new();
+                        for (key, value) in document.iter() {
+                            let key = addition_index.name(key).cloned();
+                            let value = serde_json::from_slice::<Value>(&value).ok();
+
+                            if let Some((k, v)) = key.zip(value) {
+                                json.insert(k, v);
                             }
+                        }
 
-                            return Err(UserError::MissingDocumentId {
-                                primary_key: primary_key_name,
-                                document: json,
+                        Err(UserError::MissingDocumentId {
+                            primary_key: primary_key_name.clone(),
+                            document: json,
+                        }
+                        .into())
+                    }
+                    // The sequential ids are generated once all the ids of the batch are
+                    // known, so that they are never the same as an id sent later in the batch.
+                    None if sequential => Ok(None),
+                    None => {
+                        let value = self.generate_docid(wtxn)?;
+                        match self.external_id_from_value(value.clone()) {
+                            Ok((external_id, _)) => {
+                                serde_json::to_writer(&mut external_id_buffer, &value).unwrap();
+                                field_buffer_cache.push((primary_key_id, &external_id_buffer));
+                                Ok(Some(external_id))
                             }
-                            .into());
+                            Err(error) => Err(error),
                         }
-
-                        let uuid =
-                            uuid::Uuid::new_v4().to_hyphenated().encode_lower(&mut uuid_buffer);
-                        serde_json::to_writer(&mut external_id_buffer, &uuid).unwrap();
-                        field_buffer_cache.push((primary_key_id, &external_id_buffer));
-                        Cow::Borrowed(&*uuid)
                     }
-                };
+                },
+            };
+
+            let document_position = self.documents_seen;
+            self.documents_seen += 1;
+            let external_id = match external_id {
+                Ok(Some(external_id)) => Some(external_id),
+                Ok(None) => None,
+                Err(Error::UserError(error))
+                    if self.skip_invalid_documents
+                        && matches!(
+                            error,
+                            UserError::InvalidDocumentId { .. }
+                                | UserError::MissingDocumentId { .. }
+                        ) =>
+                {
+                    self.rejected_documents.push((document_position, error));
+                    field_buffer = drop_and_reuse(field_buffer_cache);
+                    external_id_buffer.clear();
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
 
             // Insertion in a obkv need to be done with keys ordered. For now they are ordered
             // according to the document addition key order, so we sort it according to the
@@ -227,8 +372,22 @@ impl<'a, 'i> Transform<'a, 'i> {
                 writer.insert(*k, v)?;
             }
 
-            // We use the extracted/generated user id as the key for this document.
-            self.sorter.insert(&external_id.as_ref().as_bytes(), &obkv_buffer)?;
+            // We use the extracted/generated user id as the key for this document,
+            // the documents waiting for a sequential id are kept in their order.
+            match external_id {
+                Some(external_id) => {
+                    self.sorter.insert(external_id.as_bytes(), &obkv_buffer)?;
+                    if let Some(batch_sorter) = batch_sorter.as_mut() {
+                        batch_sorter.insert(external_id.as_bytes(), &obkv_buffer)?;
+                    }
+                }
+                None => {
+                    if let Some(writer) = sequential_documents.as_mut() {
+                        writer.insert(sequential_documents_count.to_be_bytes(), &obkv_buffer)?;
+                        sequential_documents_count += 1;
+                    }
+                }
+            }
             documents_count += 1;
 
             progress_callback(UpdateIndexingStep::RemapDocumentAddition {
@@ -240,21 +399,207 @@ impl<'a, 'i> Transform<'a, 'i> {
             external_id_buffer.clear();
         }
 
+        // Now that every id of the batch is known, the sequential ids are generated.
+        if let Some(writer) = sequential_documents {
+            let mut cursor = tracked_writer_into_reader(writer)?.into_cursor()?;
+            while let Some((_, obkv)) = cursor.move_on_next()? {
+                let value = self.generate_docid(wtxn)?;
+                let external_id = value.to_string();
+                serde_json::to_writer(&mut external_id_buffer, &value).unwrap();
+
+                let mut writer = obkv::KvWriter::new(&mut obkv_buffer);
+                let mut fields = obkv::KvReaderU16::new(obkv).iter().peekable();
+                while let Some((field_id, value)) = fields.next_if(|(id, _)| *id < primary_key_id) {
+                    writer.insert(field_id, value)?;
+                }
+                writer.insert(primary_key_id, &external_id_buffer)?;
+                for (field_id, value) in fields {
+                    writer.insert(field_id, value)?;
+                }
+
+                self.sorter.insert(external_id.as_bytes(), &obkv_buffer)?;
+                if let Some(batch_sorter) = batch_sorter.as_mut() {
+                    batch_sorter.insert(external_id.as_bytes(), &obkv_buffer)?;
+                }
+                obkv_buffer.clear();
+                external_id_buffer.clear();
+            }
+        }
+
         progress_callback(UpdateIndexingStep::RemapDocumentAddition {
             documents_seen: documents_count,
         });
 
+        if let Some((persisted_batches, batch_sorter)) =
+            self.persisted_batches.as_mut().zip(batch_sorter)
+        {
+            persisted_batches.write_batch(
+                batch_sorter,
+                documents_count,
+                &primary_key_name,
+                &fields_ids_map,
+                self.indexer_settings,
+            )?;
+        }
+
         self.index.put_fields_ids_map(wtxn, &fields_ids_map)?;
         self.index.put_primary_key(wtxn, &primary_key_name)?;
+        if current_document_id_policy.is_none() {
+            self.index.put_document_id_policy(wtxn, &document_id_policy)?;
+        }
         self.documents_count += documents_count;
         // Now that we have a valid sorter that contains the user id and the obkv we
         // give it to the last transforming function which returns the TransformOutput.
         Ok(documents_count)
     }
 
+    /// Writes every following batch of documents in this directory, after the batches
+    /// already persisted in it, before the batch is considered read.
+    /// Returns the external id of a document from the value of its primary key,
+    /// and whether it was normalized.
+    fn external_id_from_value(&self, value: Value) -> Result<(String, bool)> {
+        match value {
+            Value::String(string) => match self.document_id_charset.internal_id(&string) {
+                Some(s) => match self.document_id_normalization.normalize(&s) {
+                    Some(id) => Ok((id.to_string(), true)),
+                    None if s == string => Ok((string, false)),
+                    None => Ok((s.into_owned(), false)),
+                },
+                None => {
+                    Err(UserError::InvalidDocumentId { document_id: Value::String(string) }.into())
+                }
+            },
+            Value::Number(number) => Ok((number.to_string(), false)),
+            content => Err(UserError::InvalidDocumentId { document_id: content }.into()),
+        }
+    }
+
+    /// Keeps track of the integer ids sent with the documents, the sequential ids follow them.
+    /// Returns an error if the id was already generated for another document by this transform.
+    fn check_sent_docid(&mut self, external_id: String) -> Result<String> {
+        if let Ok(id) = external_id.parse::<u64>() {
+            if self.generated_sequential_docids.contains(id) {
+                let document_id = Value::String(external_id);
+                return Err(UserError::InvalidDocumentId { document_id }.into());
+            }
+            self.biggest_integer_docid = self.biggest_integer_docid.max(Some(id));
+        }
+        Ok(external_id)
+    }
+
+    /// Returns a new document id generated with the strategy of the transform.
+    fn generate_docid(&mut self, rtxn: &heed::RoTxn) -> Result<Value> {
+        match &self.docid_generation {
+            DocidGeneration::UuidV4 => {
+                let mut buffer = [0; uuid::adapter::Hyphenated::LENGTH];
+                let uuid = uuid::Uuid::new_v4().to_hyphenated().encode_lower(&mut buffer);
+                Ok(Value::String(uuid.to_string()))
+            }
+            DocidGeneration::UuidV7 => Ok(Value::String(uuid_v7(&mut self.last_uuid_v7))),
+            DocidGeneration::Sequential => {
+                let next = match self.next_sequential_docid {
+                    Some(next) => next,
+                    None => {
+                        let mut biggest = None;
+                        for result in self.index.docid_external_ids.iter(rtxn)? {
+                            let (_docid, external_id) = result?;
+                            biggest = biggest.max(external_id.parse::<u64>().ok());
+                        }
+                        following_docid(biggest)?
+                    }
+                };
+                // The ids sent with the documents of this transform are never generated.
+                let next = next.max(following_docid(self.biggest_integer_docid)?);
+                // The biggest id can only be generated once, the next one is then exhausted.
+                if self.generated_sequential_docids.contains(next) {
+                    return Err(UserError::SequentialDocumentIdsExhausted.into());
+                }
+                self.next_sequential_docid = Some(next.saturating_add(1));
+                self.generated_sequential_docids.insert(next);
+                Ok(Value::from(next))
+            }
+            DocidGeneration::Custom(generate) => Ok(Value::String(generate.generate())),
+        }
+    }
+
+    pub fn persist_batches(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.persisted_batches = Some(PersistedBatches::open(path)?);
+        Ok(())
+    }
+
+    /// Reads the batches of documents persisted in this directory by a previous `Transform`,
+    /// their fields are remapped to the fields of the index.
+    pub fn resume_batches(
+        &mut self,
+        path: impl AsRef<Path>,
+        wtxn: &mut heed::RwTxn,
+    ) -> Result<usize> {
+        let (state, readers) = read_batches(path)?;
+        let primary_key = match state.primary_key {
+            Some(primary_key) => primary_key,
+            None => return Ok(0),
+        };
+
+        if let Some(current) = self.index.primary_key(wtxn)? {
+            if current != primary_key {
+                return Err(UserError::PrimaryKeyCannotBeChanged(current.to_string()).into());
+            }
+        }
+
+        let mut fields_ids_map = self.index.fields_ids_map(wtxn)?;
+        let mut mapping = HashMap::new();
+        for (field_id, name) in state.fields_ids_map.iter() {
+            let new_field_id = match fields_ids_map.id(name) {
+                Some(id) => id,
+                None => fields_ids_map.insert(name).ok_or(UserError::AttributeLimitReached)?,
+            };
+            mapping.insert(field_id, new_field_id);
+        }
+
+        let mut obkv_buffer = Vec::new();
+        let mut field_buffer: Vec<(FieldId, &[u8])> = Vec::new();
+        for reader in readers {
+            let mut cursor = reader.into_cursor()?;
+            while let Some((external_id, obkv)) = cursor.move_on_next()? {
+                let mut field_buffer_cache = drop_and_reuse(field_buffer);
+                for (field_id, value) in obkv::KvReaderU16::new(obkv).iter() {
+                    let field_id = mapping.get(&field_id).copied().ok_or(
+                        InternalError::FieldIdMapMissingEntry(FieldIdMapMissingEntry::FieldId {
+                            field_id,
+                            process: "Transform::resume_batches",
+                        }),
+                    )?;
+                    field_buffer_cache.push((field_id, value));
+                }
+                field_buffer_cache.sort_unstable_by_key(|(field_id, _)| *field_id);
+
+                let mut writer = obkv::KvWriter::new(&mut obkv_buffer);
+                for (field_id, value) in field_buffer_cache.iter() {
+                    writer.insert(*field_id, value)?;
+                }
+                self.sorter.insert(external_id, &obkv_buffer)?;
+
+                obkv_buffer.clear();
+                field_buffer = drop_and_reuse(field_buffer_cache);
+            }
+        }
+
+        let documents_count = state.batches.iter().map(|(_, count)| count).sum();
+        self.index.put_fields_ids_map(wtxn, &fields_ids_map)?;
+        self.index.put_primary_key(wtxn, &primary_key)?;
+        self.documents_count += documents_count;
+        self.documents_seen += documents_count;
+        Ok(documents_count)
+    }
+
     /// Generate the `TransformOutput` based on the given sorter that can be generated from any
     /// format like CSV, JSON or JSON stream. This sorter must contain a key that is the document
     /// id for the user side and the value must be an obkv where keys are valid fields ids.
+    /// Returns the documents skipped because of their ids when nothing else is left to output.
+    pub(crate) fn into_rejected_documents(self) -> Vec<(usize, UserError)> {
+        self.rejected_documents
+    }
+
     pub(crate) fn output_from_sorter<F>(
         self,
         wtxn: &mut heed::RwTxn,
@@ -269,18 +614,26 @@ impl<'a, 'i> Transform<'a, 'i> {
             .ok_or(Error::UserError(UserError::MissingPrimaryKey))?
             .to_string();
         let fields_ids_map = self.index.fields_ids_map(wtxn)?;
+        let geo_field_id = fields_ids_map.id("_geo");
         let approximate_number_of_documents = self.documents_count;
 
         let mut external_documents_ids = self.index.external_documents_ids(wtxn).unwrap();
         let documents_ids = self.index.documents_ids(wtxn)?;
         let mut field_distribution = self.index.field_distribution(wtxn)?;
-        let mut available_documents_ids = AvailableDocumentsIds::from_documents_ids(&documents_ids);
+        let mut field_types_distribution = self.index.field_types_distribution(wtxn)?;
+        let store_documents = self.index.store_documents(wtxn)?;
+        let mut documents_codec = self.index.documents_codec(wtxn)?;
+        // The ids kept for the external ids retained by a clear are not available.
+        let reserved_documents_ids = self.index.reserved_documents_ids(wtxn)?;
+        let used_documents_ids = &documents_ids | &reserved_documents_ids;
+        let mut available_documents_ids =
+            AvailableDocumentsIds::from_documents_ids(&used_documents_ids);
 
         // consume sorter, in order to free the internal allocation, before creating a new one.
         let mut iter = self.sorter.into_stream_merger_iter()?;
 
         // Once we have sort and deduplicated the documents we write them into a final file.
-        let mut final_sorter = create_sorter(
+        let mut final_sorter = create_tracked_sorter(
             |_id, obkvs| {
                 if obkvs.len() == 1 {
                     Ok(obkvs[0].clone())
@@ -288,15 +641,30 @@ impl<'a, 'i> Transform<'a, 'i> {
                     Err(InternalError::IndexingMergingKeys { process: "documents" }.into())
                 }
             },
-            self.indexer_settings.chunk_compression_type,
-            self.indexer_settings.chunk_compression_level,
-            self.indexer_settings.max_nb_chunks,
-            self.indexer_settings.max_memory,
+            &self.grenad_parameters,
+            self.grenad_parameters.max_memory,
+        );
+        // We also write the external id of every document under its internal id.
+        let mut docid_external_ids_sorter = create_tracked_sorter(
+            keep_first,
+            &self.grenad_parameters,
+            self.grenad_parameters.max_memory,
         );
         let mut new_external_documents_ids_builder = fst::MapBuilder::memory();
         let mut replaced_documents_ids = RoaringBitmap::new();
+        let mut unchanged_geo_documents_ids = RoaringBitmap::new();
         let mut new_documents_ids = RoaringBitmap::new();
         let mut obkv_buffer = Vec::new();
+        let mut non_null_obkv_buffer = Vec::new();
+        let remove_null_fields = self.null_deletes_fields
+            && self.index_documents_method == IndexDocumentsMethod::UpdateDocuments;
+        // The fields of the replaced documents that are kept by the replacement.
+        let kept_fields_ids: HashSet<FieldId> = match &self.index_documents_method {
+            IndexDocumentsMethod::ReplaceKeepingFields(fields) => {
+                fields.iter().filter_map(|name| fields_ids_map.id(name)).collect()
+            }
+            _ => HashSet::new(),
+        };
 
         // While we write into final file we get or generate the internal documents ids.
         let mut documents_count = 0;
@@ -309,18 +677,40 @@ impl<'a, 'i> Transform<'a, 'i> {
             }
 
             let (docid, obkv) = match external_documents_ids.get(external_id) {
+                // The external ids retained by a clear keep their internal ids but their
+                // documents are gone, the reloaded documents are new ones reusing them.
+                Some(docid)
+                    if reserved_documents_ids.contains(docid) && !documents_ids.contains(docid) =>
+                {
+                    new_documents_ids.insert(docid);
+                    (docid, update_obkv)
+                }
+                // The replaced documents are not stored, they can only be replaced entirely.
+                Some(docid) if !store_documents => {
+                    if self.index_documents_method != IndexDocumentsMethod::ReplaceDocuments {
+                        let operation = "update the documents partially";
+                        return Err(UserError::DocumentsNotStored { operation }.into());
+                    }
+                    replaced_documents_ids.insert(docid);
+                    (docid, update_obkv)
+                }
                 Some(docid) => {
                     // If we find the user id in the current external documents ids map
                     // we use it and insert it in the list of replaced documents.
                     replaced_documents_ids.insert(docid);
 
                     let key = BEU32::new(docid);
-                    let base_obkv = self.index.documents.get(wtxn, &key)?.ok_or(
-                        InternalError::DatabaseMissingEntry {
+                    let base_bytes = self
+                        .index
+                        .documents
+                        .remap_data_type::<ByteSlice>()
+                        .get(wtxn, &key)?
+                        .ok_or(InternalError::DatabaseMissingEntry {
                             db_name: db_name::DOCUMENTS,
                             key: None,
-                        },
-                    )?;
+                        })?;
+                    let base_document = documents_codec.decompress(base_bytes)?;
+                    let base_obkv = base_document.as_obkv();
 
                     // we remove all the fields that were already counted
                     for (field_id, _) in base_obkv.iter() {
@@ -334,17 +724,44 @@ impl<'a, 'i> Transform<'a, 'i> {
                             };
                         }
                     }
+                    remove_document_types(
+                        &mut field_types_distribution,
+                        &fields_ids_map,
+                        base_obkv,
+                    )?;
 
                     // Depending on the update indexing method we will merge
                     // the document update with the current document or not.
-                    match self.index_documents_method {
-                        IndexDocumentsMethod::ReplaceDocuments => (docid, update_obkv),
+                    let obkv = match self.index_documents_method {
+                        IndexDocumentsMethod::ReplaceDocuments => update_obkv,
                         IndexDocumentsMethod::UpdateDocuments => {
                             let update_obkv = obkv::KvReader::new(update_obkv);
                             merge_two_obkvs(base_obkv, update_obkv, &mut obkv_buffer);
-                            (docid, obkv_buffer.as_slice())
+                            obkv_buffer.as_slice()
+                        }
+                        IndexDocumentsMethod::ReplaceKeepingFields(_) => {
+                            let update_obkv = obkv::KvReader::new(update_obkv);
+                            merge_kept_fields_of_obkv(
+                                base_obkv,
+                                update_obkv,
+                                &kept_fields_ids,
+                                &mut obkv_buffer,
+                            );
+                            obkv_buffer.as_slice()
+                        }
+                    };
+
+                    // We keep track of the documents whose coordinates are the same,
+                    // the geo data doesn't need to be rewritten for them.
+                    if let Some(geo_field_id) = geo_field_id {
+                        let base_geo = base_obkv.get(geo_field_id);
+                        let geo = obkv::KvReader::new(obkv).get(geo_field_id);
+                        if base_geo.is_some() && base_geo == geo {
+                            unchanged_geo_documents_ids.insert(docid);
                         }
                     }
+
+                    (docid, obkv)
                 }
                 None => {
                     // If this user id is new we add it to the external documents ids map
@@ -357,8 +774,17 @@ impl<'a, 'i> Transform<'a, 'i> {
                 }
             };
 
+            // The fields set to null by the update are removed from the document.
+            let obkv = if remove_null_fields {
+                remove_null_values(obkv::KvReader::new(obkv), &mut non_null_obkv_buffer);
+                non_null_obkv_buffer.as_slice()
+            } else {
+                obkv
+            };
+
             // We insert the document under the documents ids map into the final file.
             final_sorter.insert(docid.to_be_bytes(), obkv)?;
+            docid_external_ids_sorter.insert(docid.to_be_bytes(), external_id)?;
             documents_count += 1;
 
             let reader = obkv::KvReader::new(obkv);
@@ -366,6 +792,7 @@ impl<'a, 'i> Transform<'a, 'i> {
                 let field_name = fields_ids_map.name(field_id).unwrap();
                 *field_distribution.entry(field_name.to_string()).or_default() += 1;
             }
+            add_document_types(&mut field_types_distribution, &fields_ids_map, reader)?;
         }
 
         progress_callback(UpdateIndexingStep::ComputeIdsAndMergeDocuments {
@@ -374,11 +801,7 @@ impl<'a, 'i> Transform<'a, 'i> {
         });
 
         // We create a final writer to write the new documents in order from the sorter.
-        let mut writer = create_writer(
-            self.indexer_settings.chunk_compression_type,
-            self.indexer_settings.chunk_compression_level,
-            tempfile::tempfile()?,
-        );
+        let mut writer = create_tracked_writer(&self.grenad_parameters)?;
 
         // Once we have written all the documents into the final sorter, we write the documents
         // into this writer, extract the file and reset the seek to be able to read it again.
@@ -386,6 +809,11 @@ impl<'a, 'i> Transform<'a, 'i> {
         let mut documents_file = writer.into_inner()?;
         documents_file.seek(SeekFrom::Start(0))?;
 
+        let mut writer = create_tracked_writer(&self.grenad_parameters)?;
+        docid_external_ids_sorter.write_into_stream_writer(&mut writer)?;
+        let mut docid_external_ids_file = writer.into_inner()?;
+        docid_external_ids_file.seek(SeekFrom::Start(0))?;
+
         let before_docids_merging = Instant::now();
         // We merge the new external ids with existing external documents ids.
         let new_external_documents_ids = new_external_documents_ids_builder.into_map();
@@ -397,11 +825,16 @@ impl<'a, 'i> Transform<'a, 'i> {
             primary_key,
             fields_ids_map,
             field_distribution,
+            field_types_distribution,
             external_documents_ids: external_documents_ids.into_static(),
             new_documents_ids,
             replaced_documents_ids,
+            unchanged_geo_documents_ids,
             documents_count,
-            documents_file,
+            documents: TransformDocuments::File(documents_file),
+            docid_external_ids_file,
+            rejected_documents: self.rejected_documents,
+            tmp_disk_usage: self.grenad_parameters.tmp_disk_usage,
         })
     }
 
@@ -418,50 +851,85 @@ impl<'a, 'i> Transform<'a, 'i> {
         let primary_key =
             self.index.primary_key(wtxn)?.ok_or(UserError::MissingPrimaryKey)?.to_string();
         let field_distribution = self.index.field_distribution(wtxn)?;
+        let field_types_distribution = self.index.field_types_distribution(wtxn)?;
         let external_documents_ids = self.index.external_documents_ids(wtxn)?;
         let documents_ids = self.index.documents_ids(wtxn)?;
         let documents_count = documents_ids.len() as usize;
 
-        // We create a final writer to write the new documents in order from the sorter.
-        let mut writer = create_writer(
-            self.indexer_settings.chunk_compression_type,
-            self.indexer_settings.chunk_compression_level,
-            tempfile::tempfile()?,
-        );
-
+        // When the fields ids didn't change, the documents are written as they are stored.
+        let same_fields_ids = old_fields_ids_map.iter().eq(new_fields_ids_map.iter());
+        let documents_chunk_size =
+            self.indexer_settings.documents_chunk_size.unwrap_or(DEFAULT_DOCUMENTS_CHUNK_SIZE);
+
+        // The documents are directly written into the chunks given to the extractors, the
+        // documents of the index are not copied into an intermediate file before being chunked.
+        let new_chunk = || create_tracked_writer(&self.grenad_parameters);
+        let mut documents_chunks = Vec::new();
+        let mut writer = new_chunk()?;
+        let mut current_chunk_size = 0;
         let mut obkv_buffer = Vec::new();
-        for result in self.index.documents.iter(wtxn)? {
-            let (docid, obkv) = result?;
+        let mut documents_codec = self.index.documents_codec(wtxn)?;
+        let documents = self.index.documents.remap_data_type::<ByteSlice>();
+        for result in documents.iter(wtxn)? {
+            let (docid, bytes) = result?;
             let docid = docid.get();
+            let document = documents_codec.decompress(bytes)?;
+
+            let obkv = if same_fields_ids {
+                document.as_bytes()
+            } else {
+                let obkv = document.as_obkv();
+                obkv_buffer.clear();
+                let mut obkv_writer = obkv::KvWriter::<_, FieldId>::new(&mut obkv_buffer);
+
+                // We iterate over the new `FieldsIdsMap` ids in order and construct the new obkv.
+                for (id, name) in new_fields_ids_map.iter() {
+                    if let Some(val) = old_fields_ids_map.id(name).and_then(|id| obkv.get(id)) {
+                        obkv_writer.insert(id, val)?;
+                    }
+                }
+                obkv_writer.into_inner()?;
+                &obkv_buffer[..]
+            };
 
-            obkv_buffer.clear();
-            let mut obkv_writer = obkv::KvWriter::<_, FieldId>::new(&mut obkv_buffer);
+            writer.insert(docid.to_be_bytes(), obkv)?;
+            current_chunk_size += 4 + obkv.len();
 
-            // We iterate over the new `FieldsIdsMap` ids in order and construct the new obkv.
-            for (id, name) in new_fields_ids_map.iter() {
-                if let Some(val) = old_fields_ids_map.id(name).and_then(|id| obkv.get(id)) {
-                    obkv_writer.insert(id, val)?;
-                }
+            if current_chunk_size >= documents_chunk_size {
+                let chunk = std::mem::replace(&mut writer, new_chunk()?);
+                documents_chunks.push(tracked_writer_into_reader(chunk)?);
+                current_chunk_size = 0;
             }
+        }
 
-            let buffer = obkv_writer.into_inner()?;
-            writer.insert(docid.to_be_bytes(), buffer)?;
+        // The last chunk is kept when it isn't empty or when there is no document at all.
+        if current_chunk_size != 0 || documents_chunks.is_empty() {
+            documents_chunks.push(tracked_writer_into_reader(writer)?);
         }
 
-        // Once we have written all the documents, we extract
-        // the file and reset the seek to be able to read it again.
-        let mut documents_file = writer.into_inner()?;
-        documents_file.seek(SeekFrom::Start(0))?;
+        // The documents are about to be cleared, we keep their external ids to write them back.
+        let mut writer = create_tracked_writer(&self.grenad_parameters)?;
+        for result in self.index.docid_external_ids.iter(wtxn)? {
+            let (docid, external_id) = result?;
+            writer.insert(docid.get().to_be_bytes(), external_id)?;
+        }
+        let mut docid_external_ids_file = writer.into_inner()?;
+        docid_external_ids_file.seek(SeekFrom::Start(0))?;
 
         Ok(TransformOutput {
             primary_key,
             fields_ids_map: new_fields_ids_map,
             field_distribution,
+            field_types_distribution,
             external_documents_ids: external_documents_ids.into_static(),
             new_documents_ids: documents_ids,
             replaced_documents_ids: RoaringBitmap::default(),
+            unchanged_geo_documents_ids: RoaringBitmap::default(),
             documents_count,
-            documents_file,
+            documents: TransformDocuments::Chunks(documents_chunks),
+            docid_external_ids_file,
+            rejected_documents: Vec::new(),
+            tmp_disk_usage: self.grenad_parameters.tmp_disk_usage,
         })
     }
 }
@@ -502,6 +970,272 @@ fn compute_primary_key_pair(
     }
 }
 
+/// How the document ids that only contain digits are normalized, so that the ids sent
+/// by different sources in different forms, e.g. `001` and `1`, are the same document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum DocumentIdNormalization {
+    /// The ids are kept as they are sent, `001` and `1` are two different documents.
+    Verbatim,
+    /// The leading zeros of the ids that only contain digits are removed, `001` becomes `1`.
+    StripLeadingZeros,
+}
+
+impl DocumentIdNormalization {
+    /// Returns the normalized id when it is different from the given one.
+    fn normalize(self, document_id: &str) -> Option<&str> {
+        match self {
+            DocumentIdNormalization::Verbatim => None,
+            DocumentIdNormalization::StripLeadingZeros => {
+                if document_id.len() > 1
+                    && document_id.starts_with('0')
+                    && document_id.bytes().all(|b| b.is_ascii_digit())
+                {
+                    let stripped = document_id.trim_start_matches('0');
+                    Some(if stripped.is_empty() { "0" } else { stripped })
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl Default for DocumentIdNormalization {
+    fn default() -> Self {
+        Self::Verbatim
+    }
+}
+
+/// Gives the id to use for a document id sent by the user, or `None` if it is invalid,
+/// e.g. to accept the ids of a legacy catalog.
+pub trait NormalizeDocumentId: Send + Sync {
+    fn normalize(&self, document_id: &str) -> Option<String>;
+}
+
+impl<F> NormalizeDocumentId for F
+where
+    F: Fn(&str) -> Option<String> + Send + Sync,
+{
+    fn normalize(&self, document_id: &str) -> Option<String> {
+        self(document_id)
+    }
+}
+
+impl<'a> fmt::Debug for dyn NormalizeDocumentId + 'a {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("NormalizeDocumentId")
+    }
+}
+
+/// The characters accepted in the document ids.
+#[derive(Debug, Clone)]
+pub enum DocumentIdCharset {
+    /// The ASCII alphanumeric characters, `-` and `_`.
+    Restricted,
+    /// Any UTF-8 character, the ids containing other characters than the restricted ones
+    /// are hashed internally with SHA-256, see [`DocumentIdCharset::internal_id`].
+    Any,
+    /// The ids are given by a function, the documents keep the ids they were sent with.
+    Custom(Arc<dyn NormalizeDocumentId>),
+}
+
+impl DocumentIdCharset {
+    /// Returns the id under which a document is stored in the external documents ids,
+    /// it must be used to retrieve or delete the document, or `None` if the id is invalid.
+    pub fn internal_id<'a>(&self, document_id: &'a str) -> Option<Cow<'a, str>> {
+        match self {
+            DocumentIdCharset::Restricted => validate_document_id(document_id).map(Cow::Borrowed),
+            DocumentIdCharset::Any => {
+                let document_id = document_id.trim();
+                if document_id.is_empty() {
+                    None
+                } else if let Some(document_id) = validate_document_id(document_id) {
+                    Some(Cow::Borrowed(document_id))
+                } else {
+                    // The hashes contain a `#` that is never part of the restricted ids,
+                    // a collision would silently merge two different documents.
+                    let digest = Sha256::digest(document_id.as_bytes());
+                    Some(Cow::Owned(format!("#{:x}", digest)))
+                }
+            }
+            DocumentIdCharset::Custom(normalize) => {
+                normalize.normalize(document_id).filter(|id| !id.is_empty()).map(Cow::Owned)
+            }
+        }
+    }
+
+    /// Returns the kind of this charset, as stored in the [`DocumentIdPolicy`] of an index.
+    pub fn kind(&self) -> DocumentIdCharsetKind {
+        match self {
+            DocumentIdCharset::Restricted => DocumentIdCharsetKind::Restricted,
+            DocumentIdCharset::Any => DocumentIdCharsetKind::Any,
+            DocumentIdCharset::Custom(_) => DocumentIdCharsetKind::Custom,
+        }
+    }
+}
+
+impl Default for DocumentIdCharset {
+    fn default() -> Self {
+        Self::Restricted
+    }
+}
+
+impl PartialEq for DocumentIdCharset {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Restricted, Self::Restricted) | (Self::Any, Self::Any) => true,
+            (Self::Custom(left), Self::Custom(right)) => Arc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+/// The kind of a [`DocumentIdCharset`], the functions of the custom charsets aren't stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentIdCharsetKind {
+    Restricted,
+    Any,
+    Custom,
+}
+
+/// How the document ids sent by the user are stored in the external documents ids. It is
+/// stored in the index by the first document addition, the following ones must use the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DocumentIdPolicy {
+    pub normalization: DocumentIdNormalization,
+    pub charset: DocumentIdCharsetKind,
+}
+
+impl DocumentIdPolicy {
+    /// Returns the id under which a document id sent by the user is stored in the external
+    /// documents ids, or `None` if it is invalid. The ids given to a custom charset can't be
+    /// converted again, they are returned as they are.
+    pub fn external_id<'a>(&self, document_id: &'a str) -> Option<Cow<'a, str>> {
+        let internal_id = match self.charset {
+            DocumentIdCharsetKind::Restricted => {
+                DocumentIdCharset::Restricted.internal_id(document_id)?
+            }
+            DocumentIdCharsetKind::Any => DocumentIdCharset::Any.internal_id(document_id)?,
+            DocumentIdCharsetKind::Custom => return Some(Cow::Borrowed(document_id)),
+        };
+        match self.normalization.normalize(&internal_id) {
+            Some(normalized) => Some(Cow::Owned(normalized.to_string())),
+            None => Some(internal_id),
+        }
+    }
+}
+
+/// Generates the ids of the documents that don't have one.
+pub trait GenerateDocumentId: Send + Sync {
+    fn generate(&self) -> String;
+}
+
+impl<F> GenerateDocumentId for F
+where
+    F: Fn() -> String + Send + Sync,
+{
+    fn generate(&self) -> String {
+        self()
+    }
+}
+
+impl<'a> fmt::Debug for dyn GenerateDocumentId + 'a {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("GenerateDocumentId")
+    }
+}
+
+/// How the ids of the documents that don't have one are generated.
+#[derive(Debug, Clone)]
+pub enum DocidGeneration {
+    /// Random UUIDs.
+    UuidV4,
+    /// UUIDs starting with the time they are generated at, they are sorted by generation time.
+    UuidV7,
+    /// Integers following the biggest integer id of the index and of the documents sent.
+    Sequential,
+    /// The ids are given by a function, they must be valid documents ids.
+    Custom(Arc<dyn GenerateDocumentId>),
+}
+
+impl Default for DocidGeneration {
+    fn default() -> Self {
+        Self::UuidV4
+    }
+}
+
+impl PartialEq for DocidGeneration {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::UuidV4, Self::UuidV4)
+            | (Self::UuidV7, Self::UuidV7)
+            | (Self::Sequential, Self::Sequential) => true,
+            (Self::Custom(left), Self::Custom(right)) => Arc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+/// Returns the id following the biggest integer id, the first one when there is none.
+fn following_docid(biggest: Option<u64>) -> Result<u64> {
+    match biggest {
+        Some(id) => {
+            id.checked_add(1).ok_or_else(|| UserError::SequentialDocumentIdsExhausted.into())
+        }
+        None => Ok(0),
+    }
+}
+
+/// Returns a UUID version 7, its first 48 bits are the milliseconds since the unix epoch,
+/// the next 12 bits a counter and the other ones are random. The counter starts at a random
+/// value every millisecond and is incremented for the ids generated in the same millisecond,
+/// or when the clock goes backward, so that the ids given the same last state are sorted.
+fn uuid_v7(last: &mut (u64, u16)) -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    // The version 4 already has the variant of the version 7.
+    let mut bytes = *uuid::Uuid::new_v4().as_bytes();
+    let (last_millis, last_counter) = *last;
+    *last = if millis > last_millis {
+        // Only the lower half of the counter is random to leave room for the increments.
+        (millis, u16::from_be_bytes([bytes[6], bytes[7]]) & 0x07ff)
+    } else if last_counter < 0x0fff {
+        (last_millis, last_counter + 1)
+    } else {
+        (last_millis + 1, 0)
+    };
+    let (millis, counter) = *last;
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    bytes[6] = 0x70 | (counter >> 8) as u8;
+    bytes[7] = counter as u8;
+    let mut buffer = [0; uuid::adapter::Hyphenated::LENGTH];
+    uuid::Uuid::from_bytes(bytes).to_hyphenated().encode_lower(&mut buffer).to_string()
+}
+
+/// Returns the value found by following the path of a nested primary key in the objects
+/// of a top-level field of the document.
+fn nested_primary_key_value(
+    fields: &[(FieldId, &[u8])],
+    nested_primary_key: &[(FieldId, Vec<String>)],
+) -> Option<Value> {
+    for (parent_id, path) in nested_primary_key {
+        let bytes = match fields.iter().find(|(id, _)| id == parent_id) {
+            Some((_, bytes)) => bytes,
+            None => continue,
+        };
+        let parent: Value = match serde_json::from_slice(bytes) {
+            Ok(parent) => parent,
+            Err(_) => continue,
+        };
+        let value = path.iter().try_fold(&parent, |value, key| value.as_object()?.get(key));
+        if let Some(value) = value {
+            return Some(value.clone());
+        }
+    }
+    None
+}
+
 fn validate_document_id(document_id: &str) -> Option<&str> {
     let document_id = document_id.trim();
     Some(document_id).filter(|id| {
@@ -510,6 +1244,16 @@ fn validate_document_id(document_id: &str) -> Option<&str> {
     })
 }
 
+/// Writes the fields of the obkv whose value is not the JSON `null` into the buffer.
+fn remove_null_values(obkv: obkv::KvReaderU16, buffer: &mut Vec<u8>) {
+    buffer.clear();
+    let mut writer = obkv::KvWriter::new(buffer);
+    for (field_id, value) in obkv.iter().filter(|(_, value)| *value != b"null") {
+        writer.insert(field_id, value).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
 /// Drops all the value of type `U` in vec, and reuses the allocation to create a `Vec<T>`.
 ///
 /// The size and alignment of T and U must match.
@@ -589,4 +1333,12 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn uuid_v7_sorted_in_the_same_millisecond() {
+        let mut last = (0, 0);
+        let uuids: Vec<_> = (0..5000).map(|_| uuid_v7(&mut last)).collect();
+        assert!(uuids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(uuids.iter().all(|id| id.as_bytes()[14] == b'7'));
+    }
 }
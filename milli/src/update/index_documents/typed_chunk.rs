@@ -1,45 +1,132 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
-use std::fs::File;
 use std::io;
 
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Streamer};
 use heed::types::ByteSlice;
 use heed::{BytesDecode, RwTxn};
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use roaring::RoaringBitmap;
 
 use super::helpers::{
     self, roaring_bitmap_from_u32s_array, serialize_roaring_bitmap, valid_lmdb_key,
-    CursorClonableMmap,
+    CursorClonableMmap, TrackedTmpFile,
 };
 use crate::heed_codec::facet::{decode_prefix_string, encode_prefix_string};
+use crate::index::db_name;
 use crate::update::index_documents::helpers::as_cloneable_grenad;
+use crate::update::{geo_rtree, UpdateIndexingStep};
 use crate::{
-    lat_lng_to_xyz, BoRoaringBitmapCodec, CboRoaringBitmapCodec, DocumentId, GeoPoint, Index,
-    Result,
+    lat_lng_to_xyz, BoRoaringBitmapCodec, CboRoaringBitmapCodec, DocumentId, FieldId, GeoPoint,
+    Index, Result,
 };
 
+/// The number of entries whose values are merged with the ones of the database in parallel,
+/// the entries are then written in order by the single writer of the LMDB environment.
+const MERGE_BATCH_SIZE: usize = 4096;
+
 pub(crate) enum TypedChunk {
     DocidWordPositions(grenad::Reader<CursorClonableMmap>),
     FieldIdDocidFacetStrings(grenad::Reader<CursorClonableMmap>),
     FieldIdDocidFacetNumbers(grenad::Reader<CursorClonableMmap>),
     Documents(grenad::Reader<CursorClonableMmap>),
-    FieldIdWordcountDocids(grenad::Reader<File>),
+    FieldIdWordcountDocids(grenad::Reader<TrackedTmpFile>),
     NewDocumentsIds(RoaringBitmap),
-    WordDocids(grenad::Reader<File>),
-    WordPositionDocids(grenad::Reader<File>),
-    WordPairProximityDocids(grenad::Reader<File>),
-    FieldIdFacetStringDocids(grenad::Reader<File>),
-    FieldIdFacetNumberDocids(grenad::Reader<File>),
-    GeoPoints(grenad::Reader<File>),
+    WordDocids(grenad::Reader<TrackedTmpFile>),
+    /// The words as written in the documents, only lowercased, not added to the words fst,
+    /// one chunk is sent by chunk of documents.
+    ExactWordDocids(grenad::Reader<TrackedTmpFile>),
+    /// The documents ids to remove from the words, the words left
+    /// without any document are removed from the words fst.
+    WordDocidsDeletions(grenad::Reader<TrackedTmpFile>),
+    WordPositionDocids(grenad::Reader<TrackedTmpFile>),
+    WordFidDocids(grenad::Reader<TrackedTmpFile>),
+    WordPairProximityDocids(grenad::Reader<TrackedTmpFile>),
+    FieldIdFacetStringDocids(grenad::Reader<TrackedTmpFile>),
+    FieldIdFacetNumberDocids(grenad::Reader<TrackedTmpFile>),
+    FieldIdFacetDateDocids(grenad::Reader<TrackedTmpFile>),
+    /// The field ids, documents ids and booleans, written in the `true` and `false` bitmaps.
+    FieldIdFacetBoolDocids(grenad::Reader<TrackedTmpFile>),
+    GeoPoints(grenad::Reader<TrackedTmpFile>),
+    /// The access control tokens and documents ids, one chunk is sent by chunk of documents.
+    AclTokenDocids(grenad::Reader<TrackedTmpFile>),
+    /// Metrics of the extraction reported to the progress callback, nothing is written.
+    Metrics(UpdateIndexingStep),
+}
+
+impl TypedChunk {
+    /// Returns the name of the database the chunk is written into along with its size
+    /// in bytes, `None` when the chunk doesn't contain extracted data.
+    pub(crate) fn database_and_size(&self) -> Result<Option<(&'static str, u64)>> {
+        fn mmap_size(reader: &grenad::Reader<CursorClonableMmap>) -> u64 {
+            reader.get_ref().get_ref().as_ref().len() as u64
+        }
+
+        fn file_size(reader: &grenad::Reader<TrackedTmpFile>) -> Result<u64> {
+            Ok(reader.get_ref().get_ref().metadata()?.len())
+        }
+
+        Ok(match self {
+            TypedChunk::DocidWordPositions(reader) => {
+                Some((db_name::DOCID_WORD_POSITIONS, mmap_size(reader)))
+            }
+            TypedChunk::FieldIdDocidFacetStrings(reader) => {
+                Some((db_name::FIELD_ID_DOCID_FACET_STRINGS, mmap_size(reader)))
+            }
+            TypedChunk::FieldIdDocidFacetNumbers(reader) => {
+                Some((db_name::FIELD_ID_DOCID_FACET_F64S, mmap_size(reader)))
+            }
+            TypedChunk::Documents(reader) => Some((db_name::DOCUMENTS, mmap_size(reader))),
+            TypedChunk::FieldIdWordcountDocids(reader) => {
+                Some((db_name::FIELD_ID_WORD_COUNT_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::WordDocids(reader) | TypedChunk::WordDocidsDeletions(reader) => {
+                Some((db_name::WORD_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::ExactWordDocids(reader) => {
+                Some((db_name::EXACT_WORD_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::WordPositionDocids(reader) => {
+                Some((db_name::WORD_POSITION_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::WordFidDocids(reader) => {
+                Some((db_name::WORD_FID_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::WordPairProximityDocids(reader) => {
+                Some((db_name::WORD_PAIR_PROXIMITY_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::FieldIdFacetStringDocids(reader) => {
+                Some((db_name::FACET_ID_STRING_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::FieldIdFacetNumberDocids(reader) => {
+                Some((db_name::FACET_ID_F64_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::FieldIdFacetDateDocids(reader) => {
+                Some((db_name::FACET_ID_DATE_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::AclTokenDocids(reader) => {
+                Some((db_name::ACL_TOKEN_DOCIDS, file_size(reader)?))
+            }
+            TypedChunk::FieldIdFacetBoolDocids(reader) | TypedChunk::GeoPoints(reader) => {
+                Some((db_name::MAIN, file_size(reader)?))
+            }
+            TypedChunk::NewDocumentsIds(_) | TypedChunk::Metrics(_) => None,
+        })
+    }
 }
 
-/// Write typed chunk in the corresponding LMDB database of the provided index.
+/// Write typed chunk in the corresponding LMDB database of the provided index,
+/// the values are serialized and merged on the threads of the pool.
 /// Return new documents seen.
 pub(crate) fn write_typed_chunk_into_index(
     typed_chunk: TypedChunk,
     index: &Index,
     wtxn: &mut RwTxn,
     index_is_empty: bool,
+    pool: &ThreadPool,
 ) -> Result<(RoaringBitmap, bool)> {
     let mut is_merged_database = false;
     match typed_chunk {
@@ -49,6 +136,7 @@ pub(crate) fn write_typed_chunk_into_index(
                 &index.docid_word_positions,
                 wtxn,
                 index_is_empty,
+                pool,
                 |value, buffer| {
                     // ensure that values are unique and ordered
                     let positions = roaring_bitmap_from_u32s_array(value);
@@ -67,9 +155,13 @@ pub(crate) fn write_typed_chunk_into_index(
             )?;
         }
         TypedChunk::Documents(obkv_documents_iter) => {
-            let mut cursor = obkv_documents_iter.into_cursor()?;
-            while let Some((key, value)) = cursor.move_on_next()? {
-                index.documents.remap_types::<ByteSlice, ByteSlice>().put(wtxn, key, value)?;
+            if index.store_documents(wtxn)? {
+                let mut codec = index.documents_codec(wtxn)?;
+                let mut cursor = obkv_documents_iter.into_cursor()?;
+                while let Some((key, value)) = cursor.move_on_next()? {
+                    let value = codec.compress(value)?;
+                    index.documents.remap_types::<ByteSlice, ByteSlice>().put(wtxn, key, &value)?;
+                }
             }
         }
         TypedChunk::FieldIdWordcountDocids(fid_word_count_docids_iter) => {
@@ -78,6 +170,7 @@ pub(crate) fn write_typed_chunk_into_index(
                 &index.field_id_word_count_docids,
                 wtxn,
                 index_is_empty,
+                pool,
                 |value, _buffer| Ok(value),
                 merge_cbo_roaring_bitmaps,
             )?;
@@ -86,6 +179,7 @@ pub(crate) fn write_typed_chunk_into_index(
         TypedChunk::NewDocumentsIds(documents_ids) => {
             return Ok((documents_ids, is_merged_database))
         }
+        TypedChunk::Metrics(_) => (),
         TypedChunk::WordDocids(word_docids_iter) => {
             let word_docids_iter = unsafe { as_cloneable_grenad(&word_docids_iter) }?;
             append_entries_into_database(
@@ -93,6 +187,7 @@ pub(crate) fn write_typed_chunk_into_index(
                 &index.word_docids,
                 wtxn,
                 index_is_empty,
+                pool,
                 |value, _buffer| Ok(value),
                 merge_roaring_bitmaps,
             )?;
@@ -116,12 +211,113 @@ pub(crate) fn write_typed_chunk_into_index(
             index.put_words_fst(wtxn, &fst)?;
             is_merged_database = true;
         }
+        TypedChunk::ExactWordDocids(exact_word_docids_iter) => {
+            // A chunk is sent by chunk of documents, the words of the
+            // chunks can't be appended and are always merged.
+            write_entries_into_database(
+                exact_word_docids_iter,
+                &index.exact_word_docids,
+                wtxn,
+                false,
+                pool,
+                |value, _buffer| Ok(value),
+                merge_roaring_bitmaps,
+            )?;
+        }
+        TypedChunk::AclTokenDocids(acl_token_docids_iter) => {
+            write_entries_into_database(
+                acl_token_docids_iter,
+                &index.acl_token_docids,
+                wtxn,
+                false,
+                pool,
+                |value, _buffer| Ok(value),
+                merge_roaring_bitmaps,
+            )?;
+        }
+        TypedChunk::WordDocidsDeletions(word_docids_iter) => {
+            let word_docids_iter = unsafe { as_cloneable_grenad(&word_docids_iter) }?;
+            let deleted_words = remove_entries_from_database(
+                word_docids_iter.clone(),
+                &index.word_docids,
+                wtxn,
+                remove_roaring_bitmaps,
+            )?;
+
+            // remove the words without documents from the words fst
+            if !deleted_words.is_empty() {
+                let deleted_words = fst::Set::from_iter(deleted_words)?;
+                let db_fst = index.words_fst(wtxn)?;
+                let difference_stream = db_fst.op().add(deleted_words.stream()).difference();
+                let mut builder = fst::SetBuilder::memory();
+                builder.extend_stream(difference_stream)?;
+                let fst = builder.into_set();
+                index.put_words_fst(wtxn, &fst)?;
+            }
+
+            // recompute the prefixes of the modified words from the remaining words
+            let prefixes = {
+                let prefixes_fst = index.words_prefixes_fst(wtxn)?;
+                let mut prefixes = BTreeSet::new();
+                let mut cursor = word_docids_iter.into_cursor()?;
+                while let Some((word, _value)) = cursor.move_on_next()? {
+                    let word = std::str::from_utf8(word)?;
+                    for (i, _) in word.char_indices().skip(1) {
+                        if prefixes_fst.contains(&word[..i]) {
+                            prefixes.insert(word[..i].to_string());
+                        }
+                    }
+                    if prefixes_fst.contains(word) {
+                        prefixes.insert(word.to_string());
+                    }
+                }
+                prefixes
+            };
+
+            for prefix in prefixes {
+                let words = {
+                    let words_fst = index.words_fst(wtxn)?;
+                    let mut stream =
+                        words_fst.search(Str::new(&prefix).starts_with()).into_stream();
+                    let mut words = Vec::new();
+                    while let Some(word) = stream.next() {
+                        words.push(std::str::from_utf8(word)?.to_string());
+                    }
+                    words
+                };
+
+                let mut docids = RoaringBitmap::new();
+                for word in words {
+                    if let Some(word_docids) = index.word_docids.get(wtxn, &word)? {
+                        docids |= word_docids;
+                    }
+                }
+                if docids.is_empty() {
+                    index.word_prefix_docids.delete(wtxn, &prefix)?;
+                } else {
+                    index.word_prefix_docids.put(wtxn, &prefix, &docids)?;
+                }
+            }
+        }
         TypedChunk::WordPositionDocids(word_position_docids_iter) => {
             append_entries_into_database(
                 word_position_docids_iter,
                 &index.word_position_docids,
                 wtxn,
                 index_is_empty,
+                pool,
+                |value, _buffer| Ok(value),
+                merge_cbo_roaring_bitmaps,
+            )?;
+            is_merged_database = true;
+        }
+        TypedChunk::WordFidDocids(word_fid_docids_iter) => {
+            append_entries_into_database(
+                word_fid_docids_iter,
+                &index.word_fid_docids,
+                wtxn,
+                index_is_empty,
+                pool,
                 |value, _buffer| Ok(value),
                 merge_cbo_roaring_bitmaps,
             )?;
@@ -133,6 +329,19 @@ pub(crate) fn write_typed_chunk_into_index(
                 &index.facet_id_f64_docids,
                 wtxn,
                 index_is_empty,
+                pool,
+                |value, _buffer| Ok(value),
+                merge_cbo_roaring_bitmaps,
+            )?;
+            is_merged_database = true;
+        }
+        TypedChunk::FieldIdFacetDateDocids(facet_id_date_docids_iter) => {
+            append_entries_into_database(
+                facet_id_date_docids_iter,
+                &index.facet_id_date_docids,
+                wtxn,
+                index_is_empty,
+                pool,
                 |value, _buffer| Ok(value),
                 merge_cbo_roaring_bitmaps,
             )?;
@@ -144,6 +353,7 @@ pub(crate) fn write_typed_chunk_into_index(
                 &index.word_pair_proximity_docids,
                 wtxn,
                 index_is_empty,
+                pool,
                 |value, _buffer| Ok(value),
                 merge_cbo_roaring_bitmaps,
             )?;
@@ -175,6 +385,7 @@ pub(crate) fn write_typed_chunk_into_index(
                 &index.facet_id_string_docids,
                 wtxn,
                 index_is_empty,
+                pool,
                 |value, _buffer| Ok(value),
                 |new_values, db_values, buffer| {
                     let (_, new_values) = decode_prefix_string(new_values).unwrap();
@@ -188,10 +399,28 @@ pub(crate) fn write_typed_chunk_into_index(
             )?;
             is_merged_database = true;
         }
+        TypedChunk::FieldIdFacetBoolDocids(fid_docid_facet_bools) => {
+            let mut bools_docids: BTreeMap<(FieldId, bool), RoaringBitmap> = BTreeMap::new();
+            let mut cursor = fid_docid_facet_bools.into_cursor()?;
+            while let Some((key, _value)) = cursor.move_on_next()? {
+                let (field_id_bytes, bytes) = helpers::try_split_array_at(key).unwrap();
+                let (docid_bytes, bytes) = helpers::try_split_array_at(bytes).unwrap();
+                let field_id = FieldId::from_be_bytes(field_id_bytes);
+                let docid = DocumentId::from_be_bytes(docid_bytes);
+                let value = bytes.first() == Some(&1);
+                bools_docids.entry((field_id, value)).or_default().insert(docid);
+            }
+
+            for ((field_id, value), docids) in bools_docids {
+                let docids = index.facet_id_bool_docids(wtxn, field_id, value)? | docids;
+                index.put_facet_id_bool_docids(wtxn, field_id, value, &docids)?;
+            }
+        }
         TypedChunk::GeoPoints(geo_points) => {
             let mut rtree = index.geo_rtree(wtxn)?.unwrap_or_default();
             let mut geo_faceted_docids = index.geo_faceted_documents_ids(wtxn)?;
 
+            let mut points = Vec::new();
             let mut cursor = geo_points.into_cursor()?;
             while let Some((key, value)) = cursor.move_on_next()? {
                 // convert the key back to a u32 (4 bytes)
@@ -203,11 +432,16 @@ pub(crate) fn write_typed_chunk_into_index(
                 let point = [f64::from_ne_bytes(lat), f64::from_ne_bytes(lng)];
                 let xyz_point = lat_lng_to_xyz(&point);
 
-                rtree.insert(GeoPoint::new(xyz_point, (docid, point)));
+                points.push(GeoPoint::new(xyz_point, (docid, point)));
                 geo_faceted_docids.insert(docid);
             }
-            index.put_geo_rtree(wtxn, &rtree)?;
-            index.put_geo_faceted_documents_ids(wtxn, &geo_faceted_docids)?;
+
+            // The points of the documents whose coordinates didn't change are not extracted.
+            if !points.is_empty() {
+                geo_rtree::insert_points(&mut rtree, points);
+                index.put_geo_rtree(wtxn, &rtree)?;
+                index.put_geo_faceted_documents_ids(wtxn, &geo_faceted_docids)?;
+            }
         }
     }
 
@@ -232,40 +466,115 @@ fn merge_cbo_roaring_bitmaps(
     )?)
 }
 
+fn remove_roaring_bitmaps(del_value: &[u8], db_value: &[u8], buffer: &mut Vec<u8>) -> Result<()> {
+    let del_value = RoaringBitmap::deserialize_from(del_value)?;
+    let db_value = RoaringBitmap::deserialize_from(db_value)?;
+    let value = db_value - del_value;
+    if !value.is_empty() {
+        serialize_roaring_bitmap(&value, buffer)?;
+    }
+    Ok(())
+}
+
+/// Remove provided entries from database using remove_values function, it must write the
+/// remaining value in the buffer and leave it empty when nothing remains.
+/// Returns the keys of the entries that are deleted as they don't have any value left.
+fn remove_entries_from_database<R, K, V, FR>(
+    data: grenad::Reader<R>,
+    database: &heed::Database<K, V>,
+    wtxn: &mut RwTxn,
+    remove_values: FR,
+) -> Result<Vec<Vec<u8>>>
+where
+    R: io::Read + io::Seek,
+    FR: Fn(&[u8], &[u8], &mut Vec<u8>) -> Result<()>,
+{
+    let mut buffer = Vec::new();
+    let mut deleted_keys = Vec::new();
+    let database = database.remap_types::<ByteSlice, ByteSlice>();
+
+    let mut cursor = data.into_cursor()?;
+    while let Some((key, value)) = cursor.move_on_next()? {
+        if valid_lmdb_key(key) {
+            buffer.clear();
+            match database.get(wtxn, key)? {
+                Some(db_value) => remove_values(value, db_value, &mut buffer)?,
+                None => continue,
+            }
+            if buffer.is_empty() {
+                database.delete(wtxn, key)?;
+                deleted_keys.push(key.to_vec());
+            } else {
+                database.put(wtxn, key, &buffer)?;
+            }
+        }
+    }
+
+    Ok(deleted_keys)
+}
+
 /// Write provided entries in database using serialize_value function.
 /// merge_values function is used if an entry already exist in the database.
+///
+/// The entries are read from the database and written by the current thread, by batches
+/// whose values are serialized and merged in parallel on the pool.
 fn write_entries_into_database<R, K, V, FS, FM>(
     data: grenad::Reader<R>,
     database: &heed::Database<K, V>,
     wtxn: &mut RwTxn,
     index_is_empty: bool,
+    pool: &ThreadPool,
     serialize_value: FS,
     merge_values: FM,
 ) -> Result<()>
 where
     R: io::Read + io::Seek,
-    FS: for<'a> Fn(&'a [u8], &'a mut Vec<u8>) -> Result<&'a [u8]>,
-    FM: Fn(&[u8], &[u8], &mut Vec<u8>) -> Result<()>,
+    FS: for<'a> Fn(&'a [u8], &'a mut Vec<u8>) -> Result<&'a [u8]> + Sync,
+    FM: Fn(&[u8], &[u8], &mut Vec<u8>) -> Result<()> + Sync,
 {
-    let mut buffer = Vec::new();
     let database = database.remap_types::<ByteSlice, ByteSlice>();
 
+    let mut batch = Vec::with_capacity(MERGE_BATCH_SIZE);
     let mut cursor = data.into_cursor()?;
-    while let Some((key, value)) = cursor.move_on_next()? {
-        if valid_lmdb_key(key) {
-            buffer.clear();
-            let value = if index_is_empty {
-                serialize_value(value, &mut buffer)?
-            } else {
-                match database.get(wtxn, key)? {
-                    Some(prev_value) => {
-                        merge_values(value, prev_value, &mut buffer)?;
-                        &buffer[..]
-                    }
-                    None => serialize_value(value, &mut buffer)?,
-                }
-            };
-            database.put(wtxn, key, value)?;
+    loop {
+        let entry = cursor.move_on_next()?;
+        let is_last = entry.is_none();
+        if let Some((key, value)) = entry {
+            if valid_lmdb_key(key) {
+                let prev_value = if index_is_empty {
+                    None
+                } else {
+                    database.get(wtxn, key)?.map(<[u8]>::to_vec)
+                };
+                batch.push((key.to_vec(), value.to_vec(), prev_value));
+            }
+        }
+
+        if batch.len() == MERGE_BATCH_SIZE || (is_last && !batch.is_empty()) {
+            let values: Result<Vec<_>> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|(_, value, prev_value)| {
+                        let mut buffer = Vec::new();
+                        match prev_value {
+                            Some(prev_value) => merge_values(value, prev_value, &mut buffer)?,
+                            None => {
+                                let value = serialize_value(value, &mut buffer)?.to_vec();
+                                buffer = value;
+                            }
+                        }
+                        Ok(buffer)
+                    })
+                    .collect()
+            });
+
+            for ((key, _, _), value) in batch.drain(..).zip(values?) {
+                database.put(wtxn, &key, &value)?;
+            }
+        }
+
+        if is_last {
+            break;
         }
     }
 
@@ -281,13 +590,14 @@ fn append_entries_into_database<R, K, V, FS, FM>(
     database: &heed::Database<K, V>,
     wtxn: &mut RwTxn,
     index_is_empty: bool,
+    pool: &ThreadPool,
     serialize_value: FS,
     merge_values: FM,
 ) -> Result<()>
 where
     R: io::Read + io::Seek,
-    FS: for<'a> Fn(&'a [u8], &'a mut Vec<u8>) -> Result<&'a [u8]>,
-    FM: Fn(&[u8], &[u8], &mut Vec<u8>) -> Result<()>,
+    FS: for<'a> Fn(&'a [u8], &'a mut Vec<u8>) -> Result<&'a [u8]> + Sync,
+    FM: Fn(&[u8], &[u8], &mut Vec<u8>) -> Result<()> + Sync,
 {
     if !index_is_empty {
         return write_entries_into_database(
@@ -295,6 +605,7 @@ where
             database,
             wtxn,
             false,
+            pool,
             serialize_value,
             merge_values,
         );
@@ -314,3 +625,119 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::index_documents::helpers::{
+        create_tracked_writer, tracked_writer_into_reader, GrenadParameters,
+    };
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn word_docids_deletions() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // There must be enough words starting with "hel" for it to be a prefix.
+        let words: Vec<_> =
+            ('a'..='z').flat_map(|a| ('a'..='b').map(move |b| format!("hel{}{}", a, b))).collect();
+        let words = words.join(" ");
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "text": "hello world" },
+            { "id": 1, "text": "hello there" },
+            { "id": 2, "text": words }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert_eq!(index.word_prefix_docids.get(&wtxn, "hel").unwrap().unwrap().len(), 3);
+
+        // We remove "hello" and "world" from the first document.
+        let mut writer = create_tracked_writer(&GrenadParameters::default()).unwrap();
+        let mut docids = RoaringBitmap::new();
+        docids.insert(0);
+        let mut buffer = Vec::new();
+        docids.serialize_into(&mut buffer).unwrap();
+        writer.insert("hello", &buffer).unwrap();
+        writer.insert("world", &buffer).unwrap();
+        let chunk = TypedChunk::WordDocidsDeletions(tracked_writer_into_reader(writer).unwrap());
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        write_typed_chunk_into_index(chunk, &index, &mut wtxn, false, &pool).unwrap();
+
+        let hello = index.word_docids.get(&wtxn, "hello").unwrap().unwrap();
+        assert_eq!(hello.iter().collect::<Vec<_>>(), vec![1]);
+        assert!(index.word_docids.get(&wtxn, "world").unwrap().is_none());
+        let words_fst = index.words_fst(&wtxn).unwrap();
+        assert!(words_fst.contains("hello"));
+        assert!(!words_fst.contains("world"));
+        let hel = index.word_prefix_docids.get(&wtxn, "hel").unwrap().unwrap();
+        assert_eq!(hel.iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn parallel_merges_write_the_same_entries() {
+        // More entries than a batch, half of them are merged with the values of the database.
+        let count = MERGE_BATCH_SIZE * 2 + 10;
+        let word_docids = |threads| {
+            let path = tempfile::tempdir().unwrap();
+            let mut options = EnvOpenOptions::new();
+            options.map_size(100 * 1024 * 1024); // 100 MB
+            let index = Index::new(options, &path).unwrap();
+
+            let mut wtxn = index.write_txn().unwrap();
+            let docids: RoaringBitmap = (0..2).collect();
+            for i in (0..count).step_by(2) {
+                index.word_docids.put(&mut wtxn, &format!("word{:05}", i), &docids).unwrap();
+            }
+
+            let mut writer = create_tracked_writer(&GrenadParameters::default()).unwrap();
+            let mut buffer = Vec::new();
+            for i in 0..count {
+                buffer.clear();
+                let docids: RoaringBitmap = Some(i as u32 + 10).into_iter().collect();
+                docids.serialize_into(&mut buffer).unwrap();
+                writer.insert(format!("word{:05}", i), &buffer).unwrap();
+            }
+            let reader = tracked_writer_into_reader(writer).unwrap();
+
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            write_entries_into_database(
+                reader,
+                &index.word_docids,
+                &mut wtxn,
+                false,
+                &pool,
+                |value, _buffer| Ok(value),
+                merge_roaring_bitmaps,
+            )
+            .unwrap();
+
+            let entries: Vec<_> = index
+                .word_docids
+                .iter(&wtxn)
+                .unwrap()
+                .map(|result| {
+                    let (word, docids) = result.unwrap();
+                    (word.to_string(), docids.iter().collect::<Vec<_>>())
+                })
+                .collect();
+            entries
+        };
+
+        let serial = word_docids(1);
+        assert_eq!(serial.len(), count);
+        assert_eq!(serial[0], (S("word00000"), vec![0, 1, 10]));
+        assert_eq!(serial[1], (S("word00001"), vec![11]));
+        assert_eq!(word_docids(4), serial);
+    }
+}
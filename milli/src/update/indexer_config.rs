@@ -1,16 +1,42 @@
+use std::sync::Arc;
+
 use grenad::CompressionType;
 use rayon::ThreadPool;
 
+use crate::Tokenize;
+
 #[derive(Debug)]
 pub struct IndexerConfig {
     pub log_every_n: Option<usize>,
     pub max_nb_chunks: Option<usize>,
+    /// The size of the chunks the documents are split into to be extracted in parallel,
+    /// it is tuned to the documents, the threads and the `max_memory` when not set.
     pub documents_chunk_size: Option<usize>,
     pub max_memory: Option<usize>,
     pub chunk_compression_type: CompressionType,
     pub chunk_compression_level: Option<u32>,
     pub thread_pool: Option<ThreadPool>,
     pub max_positions_per_attributes: Option<u32>,
+    /// The maximum proximity between the pairs of words that are stored, it makes the index
+    /// smaller and the farther pairs are only ranked by whether they share an attribute.
+    /// It defaults to and cannot go beyond [`MAX_PAIR_PROXIMITY`](crate::proximity::MAX_PAIR_PROXIMITY).
+    pub proximity_window: Option<u32>,
+    /// The maximum length, in bytes, of the indexed words, the longer words are ignored.
+    /// It defaults to and cannot go beyond [`MAX_WORD_LENGTH`](crate::limits::MAX_WORD_LENGTH).
+    pub max_word_length: Option<usize>,
+    /// The maximum length, in bytes, of the normalized facet strings, the longer values are ignored.
+    /// It defaults to and cannot go beyond
+    /// [`MAX_FACET_VALUE_LENGTH`](crate::limits::MAX_FACET_VALUE_LENGTH).
+    pub max_facet_value_length: Option<usize>,
+    /// The maximum number of bytes the temporary files of an indexing can use on disk,
+    /// the indexing fails with the phase that went beyond it.
+    pub max_tmp_disk_usage: Option<u64>,
+    /// Counts the key conflicts the merges resolve while extracting each database,
+    /// to diagnose the datasets where many keys are duplicated, e.g. identical facet values.
+    pub count_merge_conflicts: bool,
+    /// A custom tokenizer used in place of the built-in analyzer,
+    /// it must also be given to the searches.
+    pub tokenizer: Option<Arc<dyn Tokenize>>,
 }
 
 impl Default for IndexerConfig {
@@ -24,6 +50,12 @@ impl Default for IndexerConfig {
             chunk_compression_level: None,
             thread_pool: None,
             max_positions_per_attributes: None,
+            proximity_window: None,
+            max_word_length: None,
+            max_facet_value_length: None,
+            max_tmp_disk_usage: None,
+            count_merge_conflicts: false,
+            tokenizer: None,
         }
     }
 }
@@ -0,0 +1,144 @@
+use heed::{EnvOpenOptions, RwTxn};
+use tracing::debug;
+
+use crate::error::{Error, UserError};
+use crate::{Index, Result};
+
+/// The map size of the LMDB environments must be a multiple of the OS page size.
+const PAGE_SIZE: usize = 4096;
+
+/// Runs an update in a write transaction and, when the update fails because the map of the
+/// index is full, aborts the transaction, reopens the index with a bigger map and retries it.
+///
+/// The environment of an index can't be resized while it is opened, the index is closed and
+/// reopened with only the map size set, all the other clones of the index must be dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct MapSizeGrowth {
+    map_size: usize,
+    growth_factor: f64,
+    max_map_size: Option<usize>,
+}
+
+impl MapSizeGrowth {
+    /// The map size must be the one the index was opened with.
+    pub fn new(map_size: usize) -> MapSizeGrowth {
+        MapSizeGrowth { map_size, growth_factor: 2.0, max_map_size: None }
+    }
+
+    /// The factor by which the map size is multiplied every time the map is full, 2 by default.
+    pub fn growth_factor(&mut self, growth_factor: f64) -> &mut Self {
+        self.growth_factor = growth_factor.max(1.1);
+        self
+    }
+
+    /// The map size over which the map is not grown anymore, unlimited by default.
+    pub fn max_map_size(&mut self, max_map_size: usize) -> &mut Self {
+        self.max_map_size = Some(max_map_size);
+        self
+    }
+
+    /// The current map size of the index, grown by the updates that filled the map.
+    pub fn map_size(&self) -> usize {
+        self.map_size
+    }
+
+    /// Executes the update and commits it, the update is called again with a new transaction
+    /// every time the map is full so it must be able to start again from the beginning, e.g.
+    /// by reading the documents to index again. Returns the index reopened with the new map
+    /// size along with the result of the update.
+    pub fn execute<T, F>(&mut self, mut index: Index, mut update: F) -> Result<(Index, T)>
+    where
+        F: FnMut(&Index, &mut RwTxn) -> Result<T>,
+    {
+        loop {
+            let mut wtxn = index.write_txn()?;
+            let result = update(&index, &mut wtxn).and_then(|output| {
+                wtxn.commit()?;
+                Ok(output)
+            });
+
+            match result {
+                Err(Error::UserError(UserError::MaxDatabaseSizeReached)) => {
+                    let map_size = (self.map_size as f64 * self.growth_factor) as usize;
+                    let map_size = (map_size + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+                    if self.max_map_size.map_or(false, |max| map_size > max) {
+                        return Err(UserError::MaxDatabaseSizeReached.into());
+                    }
+
+                    debug!("The map is full, growing it from {} to {}", self.map_size, map_size);
+                    let path = index.path().to_path_buf();
+                    index.prepare_for_closing().wait();
+                    let mut options = EnvOpenOptions::new();
+                    options.map_size(map_size);
+                    index = Index::new(options, path)?;
+                    self.map_size = map_size;
+                }
+                Ok(output) => return Ok((index, output)),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn grow_when_full() {
+        let path = tempfile::tempdir().unwrap();
+        let map_size = 100 * PAGE_SIZE;
+        let mut options = EnvOpenOptions::new();
+        options.map_size(map_size);
+        let index = Index::new(options, &path).unwrap();
+
+        // Enough documents with different words to fill the map.
+        let documents: Vec<_> = (0..5000)
+            .map(|i| json!({ "id": i, "text": format!("word{} other{} last{}", i, i * 7, i * 13) }))
+            .collect();
+        let documents = serde_json::to_vec(&documents).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut growth = MapSizeGrowth::new(map_size);
+        let mut attempts = 0;
+        let (index, result) = growth
+            .execute(index, |index, wtxn| {
+                attempts += 1;
+                let mut writer = std::io::Cursor::new(Vec::new());
+                let mut builder = DocumentBatchBuilder::new(&mut writer)?;
+                builder.extend_from_json(&documents[..])?;
+                builder.finish()?;
+                writer.set_position(0);
+                let reader = DocumentBatchReader::from_reader(writer)?;
+
+                let indexing_config = IndexDocumentsConfig::default();
+                let mut builder =
+                    IndexDocuments::new(wtxn, index, &config, indexing_config, |_| ());
+                builder.add_documents(reader)?;
+                builder.execute()
+            })
+            .unwrap();
+
+        assert!(attempts > 1);
+        assert!(growth.map_size() > map_size);
+        assert_eq!(result.number_of_documents, 5000);
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 5000);
+        drop(rtxn);
+
+        // The map is not grown past the maximum.
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(map_size);
+        let index = Index::new(options, &path).unwrap();
+        let mut growth = MapSizeGrowth::new(map_size);
+        growth.max_map_size(map_size);
+        let result = growth
+            .execute(index, |_, _| -> Result<()> { Err(UserError::MaxDatabaseSizeReached.into()) });
+        assert!(matches!(result, Err(Error::UserError(UserError::MaxDatabaseSizeReached))));
+    }
+}
@@ -1,25 +1,42 @@
 pub use self::available_documents_ids::AvailableDocumentsIds;
+pub use self::batched_additions::{BatchedAdditions, BatchedAdditionsResult, PendingAddition};
 pub use self::clear_documents::ClearDocuments;
 pub use self::delete_documents::{DeleteDocuments, DocumentDeletionResult};
 pub use self::facets::Facets;
 pub use self::index_documents::{
-    DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod,
+    DocidGeneration, DocumentAdditionResult, DocumentIdCharset, DocumentIdCharsetKind,
+    DocumentIdNormalization, DocumentIdPolicy, FlattenConfig, GenerateDocumentId, IndexDocuments,
+    IndexDocumentsConfig, IndexDocumentsMethod, NormalizeDocumentId,
 };
 pub use self::indexer_config::IndexerConfig;
-pub use self::settings::{Setting, Settings};
+pub use self::map_size_growth::MapSizeGrowth;
+pub use self::rename_facet_values::RenameFacetValues;
+pub use self::rename_field::RenameField;
+pub use self::settings::{Setting, Settings, SettingsPayload};
+pub use self::update_documents_by_function::{DocumentsUpdateResult, UpdateDocumentsByFunction};
+pub use self::update_numeric_fields::UpdateNumericFields;
 pub use self::update_step::UpdateIndexingStep;
 pub use self::word_prefix_docids::WordPrefixDocids;
-pub use self::word_prefix_pair_proximity_docids::WordPrefixPairProximityDocids;
+pub use self::word_prefix_pair_proximity_docids::{
+    PrefixPairProximityCaps, WordPrefixPairProximityDocids,
+};
 pub use self::words_prefix_position_docids::WordPrefixPositionDocids;
 pub use self::words_prefixes_fst::WordsPrefixesFst;
 
 mod available_documents_ids;
+mod batched_additions;
 mod clear_documents;
 mod delete_documents;
 mod facets;
+mod geo_rtree;
 mod index_documents;
 mod indexer_config;
+mod map_size_growth;
+mod rename_facet_values;
+mod rename_field;
 mod settings;
+mod update_documents_by_function;
+mod update_numeric_fields;
 mod update_step;
 mod word_prefix_docids;
 mod word_prefix_pair_proximity_docids;
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use heed::types::ByteSlice;
+use roaring::RoaringBitmap;
+use serde_json::Value;
+
+use super::Facets;
+use crate::error::{InternalError, UserError};
+use crate::index::db_name;
+use crate::{FieldId, Index, Result, BEU32};
+
+/// Renames the string values of a faceted field, or merges several values into one,
+/// e.g. to clean up the colors of a catalog without sending all the documents again.
+///
+/// The stored documents and the facet databases are rewritten, the words databases are
+/// never touched, this is why the field must not be searchable.
+pub struct RenameFacetValues<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    field: String,
+    /// The new values, indexed by the normalized values they replace.
+    renames: BTreeMap<String, String>,
+}
+
+impl<'t, 'u, 'i> RenameFacetValues<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        field: impl Into<String>,
+    ) -> RenameFacetValues<'t, 'u, 'i> {
+        RenameFacetValues { wtxn, index, field: field.into(), renames: BTreeMap::new() }
+    }
+
+    /// Replaces a value by another one, the values are matched like the filters match them,
+    /// case insensitively and without the surrounding whitespaces.
+    pub fn rename_value(&mut self, from: impl AsRef<str>, to: impl Into<String>) -> &mut Self {
+        self.renames.insert(normalize(from.as_ref()), to.into());
+        self
+    }
+
+    /// Replaces all these values by a single one.
+    pub fn merge_values(
+        &mut self,
+        from: impl IntoIterator<Item = impl AsRef<str>>,
+        to: impl Into<String>,
+    ) -> &mut Self {
+        let to = to.into();
+        for from in from {
+            self.rename_value(from, to.clone());
+        }
+        self
+    }
+
+    /// Writes the new values and returns the number of updated documents.
+    pub fn execute(self) -> Result<u64> {
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let field_id = match fields_ids_map.id(&self.field) {
+            Some(field_id) if self.index.faceted_fields_ids(self.wtxn)?.contains(&field_id) => {
+                field_id
+            }
+            _ => return Err(invalid_rename(&self.field, "it is not filterable nor sortable")),
+        };
+        let searchable_fields = self.index.searchable_fields_ids(self.wtxn)?;
+        if searchable_fields.map_or(true, |fields| fields.contains(&field_id)) {
+            return Err(invalid_rename(&self.field, "it is searchable"));
+        }
+        if self.renames.values().any(|to| normalize(to).is_empty()) {
+            return Err(invalid_rename(&self.field, "the new value is empty"));
+        }
+
+        // The documents of every renamed value, all the values are renamed at once
+        // so that a value can be renamed into another value that is itself renamed.
+        let mut renamed = Vec::new();
+        let mut documents_ids = RoaringBitmap::new();
+        for (from, to) in &self.renames {
+            if let Some((original, docids)) =
+                self.index.facet_id_string_docids.get(self.wtxn, &(field_id, from.as_str()))?
+            {
+                if original != to || *from != normalize(to) {
+                    documents_ids |= &docids;
+                    renamed.push((from.as_str(), to.as_str(), docids));
+                }
+            }
+        }
+        if renamed.is_empty() {
+            return Ok(0);
+        }
+        self.index.ensure_documents_stored(self.wtxn, "rename the facet values")?;
+
+        // We validate all the documents before writing anything, the values of the fields
+        // nested in the objects of another field are not stored under their own field.
+        let mut codec = self.index.documents_codec(self.wtxn)?;
+        let mut documents = Vec::with_capacity(documents_ids.len() as usize);
+        for docid in &documents_ids {
+            let key = BEU32::new(docid);
+            let bytes =
+                self.index.documents.remap_data_type::<ByteSlice>().get(self.wtxn, &key)?.ok_or(
+                    InternalError::DatabaseMissingEntry { db_name: db_name::DOCUMENTS, key: None },
+                )?;
+            let obkv = codec.decompress(bytes)?;
+            if obkv.get(field_id).is_none() {
+                return Err(invalid_rename(&self.field, "it is nested in another field"));
+            }
+            let document: BTreeMap<FieldId, Vec<u8>> =
+                obkv.iter().map(|(field_id, value)| (field_id, value.to_vec())).collect();
+            documents.push((key, document));
+        }
+
+        // The level 0 of the facet string database.
+        let mut merged: BTreeMap<String, (String, RoaringBitmap)> = BTreeMap::new();
+        for (from, _, _) in &renamed {
+            self.index.facet_id_string_docids.delete(self.wtxn, &(field_id, *from))?;
+        }
+        for (_, to, docids) in &renamed {
+            let normalized = normalize(to);
+            if !merged.contains_key(&normalized) {
+                // The documents that already have the new value keep it.
+                let key = (field_id, normalized.as_str());
+                let previous = self.index.facet_id_string_docids.get(self.wtxn, &key)?;
+                let previous = previous.map(|(_, docids)| docids).unwrap_or_default();
+                merged.insert(normalized.clone(), (to.to_string(), previous));
+            }
+            let (original, merged_docids) = merged.get_mut(&normalized).unwrap();
+            *original = to.to_string();
+            *merged_docids |= docids;
+        }
+        for (normalized, (original, docids)) in &merged {
+            let key = (field_id, normalized.as_str());
+            self.index.facet_id_string_docids.put(
+                self.wtxn,
+                &key,
+                &(original.as_str(), docids.clone()),
+            )?;
+        }
+
+        // The values of each document.
+        for docid in &documents_ids {
+            for (from, _, docids) in &renamed {
+                if docids.contains(docid) {
+                    self.index
+                        .field_id_docid_facet_strings
+                        .delete(self.wtxn, &(field_id, docid, *from))?;
+                }
+            }
+            for (_, to, docids) in &renamed {
+                if docids.contains(docid) {
+                    let normalized = normalize(to);
+                    let key = (field_id, docid, normalized.as_str());
+                    self.index.field_id_docid_facet_strings.put(self.wtxn, &key, to)?;
+                }
+            }
+        }
+
+        // The stored documents.
+        let mut buffer = Vec::new();
+        for (key, mut document) in documents {
+            let bytes = &document[&field_id];
+            let mut value: Value =
+                serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?;
+            rename_values(&mut value, true, &self.renames);
+            let bytes = serde_json::to_vec(&value).map_err(InternalError::SerdeJson)?;
+            document.insert(field_id, bytes);
+
+            buffer.clear();
+            let mut writer = obkv::KvWriter::<_, FieldId>::new(&mut buffer);
+            for (field_id, value) in &document {
+                writer.insert(*field_id, value)?;
+            }
+            writer.finish()?;
+            let bytes = codec.compress(&buffer)?;
+            self.index.documents.remap_data_type::<ByteSlice>().put(self.wtxn, &key, &bytes)?;
+        }
+
+        // The facet levels and the faceted documents ids are recomputed from the level 0.
+        let mut builder = Facets::new(self.wtxn, self.index);
+        builder.fields_ids = Some(std::iter::once(field_id).collect());
+        builder.execute()?;
+
+        Ok(documents_ids.len())
+    }
+}
+
+fn invalid_rename(field: &str, message: &'static str) -> crate::Error {
+    UserError::InvalidFacetValueRename { field: field.to_string(), message }.into()
+}
+
+/// Normalizes a value like the facet values are normalized when they are extracted.
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+/// Renames the values of a document field like they are extracted, the strings,
+/// either directly in the field or in an array, the booleans are not facet strings.
+fn rename_values(value: &mut Value, can_recurse: bool, renames: &BTreeMap<String, String>) {
+    let normalized = match value {
+        Value::String(string) => normalize(string),
+        Value::Array(values) if can_recurse => {
+            values.iter_mut().for_each(|value| rename_values(value, false, renames));
+            return;
+        }
+        _ => return,
+    };
+    if let Some(to) = renames.get(&normalized) {
+        *value = Value::String(to.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::{obkv_to_json, Filter};
+
+    #[test]
+    fn rename_and_merge_colors() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title")]);
+        builder.set_filterable_fields(hashset! { S("color"), S("title") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "shoes", "color": "Red" },
+            { "id": 1, "title": "shirt", "color": "crimson " },
+            { "id": 2, "title": "hat", "color": ["Blue", "Crimson"] },
+            { "id": 3, "title": "socks", "color": "green" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = RenameFacetValues::new(&mut wtxn, &index, "color");
+        builder.merge_values(vec!["red", "CRIMSON"], "Red");
+        builder.rename_value("blue", "Navy");
+        assert_eq!(builder.execute().unwrap(), 3);
+
+        let docids = |filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&wtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+        assert_eq!(docids("color = red"), vec![0, 1, 2]);
+        assert_eq!(docids("color = navy"), vec![2]);
+        assert_eq!(docids("color = green"), vec![3]);
+        assert!(docids("color = crimson OR color = blue").is_empty());
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let color = fields_ids_map.id("color").unwrap();
+        let (original, _) =
+            index.facet_id_string_docids.get(&wtxn, &(color, "red")).unwrap().unwrap();
+        assert_eq!(original, "Red");
+        let original = index.field_id_docid_facet_strings.get(&wtxn, &(color, 1, "red")).unwrap();
+        assert_eq!(original, Some("Red"));
+
+        let (_, obkv) = index.documents(&wtxn, Some(2)).unwrap().pop().unwrap();
+        let document = obkv_to_json(
+            &fields_ids_map.ids().collect::<Vec<_>>(),
+            &fields_ids_map,
+            obkv.as_obkv(),
+        )
+        .unwrap();
+        assert_eq!(document["color"], serde_json::json!(["Navy", "Red"]));
+
+        // The searchable fields can't be renamed without reindexing the words.
+        let mut builder = RenameFacetValues::new(&mut wtxn, &index, "title");
+        builder.rename_value("hat", "cap");
+        assert!(builder.execute().is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, HashSet};
+
+use time::OffsetDateTime;
+
+use crate::error::{is_reserved_keyword, UserError};
+use crate::{Criterion, Index, Result};
+
+/// Gives a new name to a field of the documents without reindexing them.
+///
+/// The databases and the stored documents refer to the fields by their ids, only the fields
+/// ids map, the field distribution and the settings that refer to the field are rewritten.
+/// The filters of the stored queries are not rewritten.
+pub struct RenameField<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    name: String,
+    new_name: String,
+}
+
+impl<'t, 'u, 'i> RenameField<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        name: impl Into<String>,
+        new_name: impl Into<String>,
+    ) -> RenameField<'t, 'u, 'i> {
+        RenameField { wtxn, index, name: name.into(), new_name: new_name.into() }
+    }
+
+    pub fn execute(self) -> Result<()> {
+        let RenameField { wtxn, index, name, new_name } = self;
+
+        if is_reserved_keyword(&name) || is_reserved_keyword(&new_name) {
+            return Err(invalid_rename(&name, "the name is reserved"));
+        }
+        if new_name.trim().is_empty() {
+            return Err(invalid_rename(&name, "the new name is empty"));
+        }
+
+        // The values of the nested fields are found by following the keys of the objects
+        // of the top-level fields, these keys can't be renamed without reindexing.
+        let mut fields_ids_map = index.fields_ids_map(wtxn)?;
+        let is_nested_with = |parent: &str, child: &str| {
+            child.len() > parent.len() + 1
+                && child.starts_with(parent)
+                && child[parent.len()..].starts_with('.')
+        };
+        if fields_ids_map.names().any(|other| is_nested_with(&name, other)) {
+            return Err(invalid_rename(&name, "fields are nested in it"));
+        }
+        if fields_ids_map.names().any(|other| is_nested_with(other, &name)) {
+            return Err(invalid_rename(&name, "it is nested in another field"));
+        }
+        if fields_ids_map.id(&new_name).is_some() {
+            return Err(invalid_rename(&name, "the new name is already used"));
+        }
+        if fields_ids_map.rename(&name, &new_name).is_none() {
+            return Err(invalid_rename(&name, "it is unknown"));
+        }
+        index.put_fields_ids_map(wtxn, &fields_ids_map)?;
+
+        let rename = |field: &str| if field == name { new_name.clone() } else { field.to_string() };
+        let rename_set = |fields: HashSet<String>| -> HashSet<String> {
+            fields.iter().map(|f| rename(f)).collect()
+        };
+
+        let mut field_distribution = index.field_distribution(wtxn)?;
+        if let Some(count) = field_distribution.remove(&name) {
+            field_distribution.insert(new_name.clone(), count);
+            index.put_field_distribution(wtxn, &field_distribution)?;
+        }
+
+        let mut field_types_distribution = index.field_types_distribution(wtxn)?;
+        if let Some(types) = field_types_distribution.remove(&name) {
+            field_types_distribution.insert(new_name.clone(), types);
+            index.put_field_types_distribution(wtxn, &field_types_distribution)?;
+        }
+
+        if index.primary_key(wtxn)? == Some(name.as_str()) {
+            index.put_primary_key(wtxn, &new_name)?;
+        }
+
+        let displayed_fields: Option<Vec<_>> =
+            index.displayed_fields(wtxn)?.map(|fields| fields.into_iter().map(&rename).collect());
+        if let Some(fields) = displayed_fields {
+            let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+            index.put_displayed_fields(wtxn, &fields)?;
+        }
+
+        let searchable_fields: Option<Vec<_>> =
+            index.searchable_fields(wtxn)?.map(|fields| fields.into_iter().map(&rename).collect());
+        if let Some(fields) = searchable_fields {
+            let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+            index.put_searchable_fields(wtxn, &fields)?;
+        }
+
+        let fields = rename_set(index.filterable_fields(wtxn)?);
+        index.put_filterable_fields(wtxn, &fields)?;
+        let fields = rename_set(index.sortable_fields(wtxn)?);
+        index.put_sortable_fields(wtxn, &fields)?;
+        let fields = rename_set(index.split_identifiers_fields(wtxn)?);
+        index.put_split_identifiers_fields(wtxn, &fields)?;
+        let flattened_fields: BTreeMap<_, _> = index
+            .flattened_faceted_fields(wtxn)?
+            .into_iter()
+            .map(|(field, parent)| (field, rename(&parent)))
+            .collect();
+        index.put_flattened_faceted_fields(wtxn, &flattened_fields)?;
+
+        if index.distinct_field(wtxn)? == Some(name.as_str()) {
+            index.put_distinct_field(wtxn, &new_name)?;
+        }
+
+        let criteria: Vec<_> = index
+            .criteria(wtxn)?
+            .into_iter()
+            .map(|criterion| match criterion {
+                Criterion::Asc(field) => Criterion::Asc(rename(&field)),
+                Criterion::Desc(field) => Criterion::Desc(rename(&field)),
+                criterion => criterion,
+            })
+            .collect();
+        index.put_criteria(wtxn, &criteria)?;
+
+        index.set_updated_at(wtxn, &OffsetDateTime::now_utc())?;
+        index.increment_settings_generation(wtxn)?;
+
+        Ok(())
+    }
+}
+
+fn invalid_rename(field: &str, message: &'static str) -> crate::Error {
+    UserError::InvalidFieldRename { field: field.to_string(), message }.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::{obkv_to_json, Filter, Search};
+
+    #[test]
+    fn rename_field_without_reindexing() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("color")]);
+        builder.set_displayed_fields(vec![S("title"), S("color")]);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.set_sortable_fields(hashset! { S("popularity") });
+        builder.set_criteria(vec![S("words"), S("popularity:desc")]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "shoes", "color": "red", "popularity": 2 },
+            { "id": 1, "title": "red shirt", "color": "blue", "popularity": 1 },
+            { "id": 2, "title": "hat", "color": "red", "popularity": 3 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let words_before = index.word_docids.len(&wtxn).unwrap();
+        RenameField::new(&mut wtxn, &index, "color", "colour").execute().unwrap();
+        RenameField::new(&mut wtxn, &index, "popularity", "rank").execute().unwrap();
+
+        assert_eq!(index.searchable_fields(&wtxn).unwrap(), Some(vec!["title", "colour"]));
+        assert_eq!(index.displayed_fields(&wtxn).unwrap(), Some(vec!["title", "colour"]));
+        assert_eq!(index.filterable_fields(&wtxn).unwrap(), hashset! { S("colour") });
+        assert_eq!(index.sortable_fields(&wtxn).unwrap(), hashset! { S("rank") });
+        assert_eq!(index.criteria(&wtxn).unwrap()[1], Criterion::Desc(S("rank")));
+        assert_eq!(index.field_distribution(&wtxn).unwrap().get("colour"), Some(&3));
+        assert_eq!(index.word_docids.len(&wtxn).unwrap(), words_before);
+
+        let filter = Filter::from_str("colour = red").unwrap().unwrap();
+        let docids = filter.evaluate(&wtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2]);
+
+        let mut search = Search::new(&wtxn, &index);
+        search.query("red");
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 0, 1]);
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let (_, obkv) = index.documents(&wtxn, Some(0)).unwrap().pop().unwrap();
+        let document = obkv_to_json(
+            &fields_ids_map.ids().collect::<Vec<_>>(),
+            &fields_ids_map,
+            obkv.as_obkv(),
+        )
+        .unwrap();
+        assert_eq!(document["colour"], serde_json::json!("red"));
+        assert_eq!(document["rank"], serde_json::json!(2));
+
+        // A field can't take the name of another one nor be renamed twice.
+        assert!(RenameField::new(&mut wtxn, &index, "colour", "title").execute().is_err());
+        assert!(RenameField::new(&mut wtxn, &index, "color", "tint").execute().is_err());
+    }
+}
@@ -6,13 +6,22 @@ use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use time::OffsetDateTime;
 
-use super::index_documents::{IndexDocumentsConfig, Transform};
+use roaring::RoaringBitmap;
+
+use super::index_documents::{
+    DocumentIdCharset, DocumentIdNormalization, IndexDocumentsConfig, ReindexScope, Transform,
+};
 use super::IndexerConfig;
 use crate::criterion::Criterion;
+use crate::documents_compression::{recompress_documents, train_dictionary};
+use crate::dump::DumpSettings;
 use crate::error::UserError;
 use crate::update::index_documents::IndexDocumentsMethod;
-use crate::update::{ClearDocuments, IndexDocuments, UpdateIndexingStep};
-use crate::{FieldsIdsMap, Index, Result};
+use crate::update::{ClearDocuments, Facets, IndexDocuments, UpdateIndexingStep};
+use crate::{
+    EmojiPolicy, FieldId, FieldsIdsMap, Index, ProtectedPattern, ProximityPrecision, Result,
+    TypoCosts,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Setting<T> {
@@ -74,6 +83,182 @@ impl<'de, T: Deserialize<'de>> Deserialize<'de> for Setting<T> {
     }
 }
 
+/// The settings of an index as sent by a client, a missing field is left
+/// untouched and a `null` field is reset to its default value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SettingsPayload {
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub searchable_fields: Setting<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub displayed_fields: Setting<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub filterable_fields: Setting<HashSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub sortable_fields: Setting<HashSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub criteria: Setting<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub stop_words: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub separator_tokens: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub non_separator_tokens: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub protected_patterns: Setting<Vec<ProtectedPattern>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub emoji_policy: Setting<EmojiPolicy>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub prefix_databases: Setting<bool>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub proximity_precision: Setting<ProximityPrecision>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub store_documents: Setting<bool>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub compress_documents: Setting<bool>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub dictionary: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub split_identifiers_fields: Setting<HashSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub distinct_field: Setting<String>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub synonyms: Setting<HashMap<String, Vec<String>>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub typo_costs: Setting<TypoCosts>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub primary_key: Setting<String>,
+}
+
+impl SettingsPayload {
+    /// Sets or resets the settings of this payload on the builder.
+    pub fn apply(self, builder: &mut Settings) {
+        let SettingsPayload {
+            searchable_fields,
+            displayed_fields,
+            filterable_fields,
+            sortable_fields,
+            criteria,
+            stop_words,
+            separator_tokens,
+            non_separator_tokens,
+            protected_patterns,
+            emoji_policy,
+            prefix_databases,
+            proximity_precision,
+            store_documents,
+            compress_documents,
+            dictionary,
+            split_identifiers_fields,
+            distinct_field,
+            synonyms,
+            typo_costs,
+            primary_key,
+        } = self;
+
+        match searchable_fields {
+            Setting::Set(value) => builder.set_searchable_fields(value),
+            Setting::Reset => builder.reset_searchable_fields(),
+            Setting::NotSet => (),
+        }
+        match displayed_fields {
+            Setting::Set(value) => builder.set_displayed_fields(value),
+            Setting::Reset => builder.reset_displayed_fields(),
+            Setting::NotSet => (),
+        }
+        match filterable_fields {
+            Setting::Set(value) => builder.set_filterable_fields(value),
+            Setting::Reset => builder.reset_filterable_fields(),
+            Setting::NotSet => (),
+        }
+        match sortable_fields {
+            Setting::Set(value) => builder.set_sortable_fields(value),
+            Setting::Reset => builder.reset_sortable_fields(),
+            Setting::NotSet => (),
+        }
+        match criteria {
+            Setting::Set(value) => builder.set_criteria(value),
+            Setting::Reset => builder.reset_criteria(),
+            Setting::NotSet => (),
+        }
+        match stop_words {
+            Setting::Set(value) => builder.set_stop_words(value),
+            Setting::Reset => builder.reset_stop_words(),
+            Setting::NotSet => (),
+        }
+        match separator_tokens {
+            Setting::Set(value) => builder.set_separator_tokens(value),
+            Setting::Reset => builder.reset_separator_tokens(),
+            Setting::NotSet => (),
+        }
+        match non_separator_tokens {
+            Setting::Set(value) => builder.set_non_separator_tokens(value),
+            Setting::Reset => builder.reset_non_separator_tokens(),
+            Setting::NotSet => (),
+        }
+        match protected_patterns {
+            Setting::Set(value) => builder.set_protected_patterns(value),
+            Setting::Reset => builder.reset_protected_patterns(),
+            Setting::NotSet => (),
+        }
+        match emoji_policy {
+            Setting::Set(value) => builder.set_emoji_policy(value),
+            Setting::Reset => builder.reset_emoji_policy(),
+            Setting::NotSet => (),
+        }
+        match prefix_databases {
+            Setting::Set(value) => builder.set_prefix_databases(value),
+            Setting::Reset => builder.reset_prefix_databases(),
+            Setting::NotSet => (),
+        }
+        match proximity_precision {
+            Setting::Set(value) => builder.set_proximity_precision(value),
+            Setting::Reset => builder.reset_proximity_precision(),
+            Setting::NotSet => (),
+        }
+        match store_documents {
+            Setting::Set(value) => builder.set_store_documents(value),
+            Setting::Reset => builder.reset_store_documents(),
+            Setting::NotSet => (),
+        }
+        match compress_documents {
+            Setting::Set(value) => builder.set_compress_documents(value),
+            Setting::Reset => builder.reset_compress_documents(),
+            Setting::NotSet => (),
+        }
+        match dictionary {
+            Setting::Set(value) => builder.set_dictionary(value),
+            Setting::Reset => builder.reset_dictionary(),
+            Setting::NotSet => (),
+        }
+        match split_identifiers_fields {
+            Setting::Set(value) => builder.set_split_identifiers_fields(value),
+            Setting::Reset => builder.reset_split_identifiers_fields(),
+            Setting::NotSet => (),
+        }
+        match distinct_field {
+            Setting::Set(value) => builder.set_distinct_field(value),
+            Setting::Reset => builder.reset_distinct_field(),
+            Setting::NotSet => (),
+        }
+        match synonyms {
+            Setting::Set(value) => builder.set_synonyms(value),
+            Setting::Reset => builder.reset_synonyms(),
+            Setting::NotSet => (),
+        }
+        match typo_costs {
+            Setting::Set(value) => builder.set_typo_costs(value),
+            Setting::Reset => builder.reset_typo_costs(),
+            Setting::NotSet => (),
+        }
+        match primary_key {
+            Setting::Set(value) => builder.set_primary_key(value),
+            Setting::Reset => builder.reset_primary_key(),
+            Setting::NotSet => (),
+        }
+    }
+}
+
 pub struct Settings<'a, 't, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
@@ -86,9 +271,22 @@ pub struct Settings<'a, 't, 'u, 'i> {
     sortable_fields: Setting<HashSet<String>>,
     criteria: Setting<Vec<String>>,
     stop_words: Setting<BTreeSet<String>>,
+    separator_tokens: Setting<BTreeSet<String>>,
+    non_separator_tokens: Setting<BTreeSet<String>>,
+    protected_patterns: Setting<Vec<ProtectedPattern>>,
+    emoji_policy: Setting<EmojiPolicy>,
+    prefix_databases: Setting<bool>,
+    proximity_precision: Setting<ProximityPrecision>,
+    store_documents: Setting<bool>,
+    compress_documents: Setting<bool>,
+    dictionary: Setting<BTreeSet<String>>,
+    split_identifiers_fields: Setting<HashSet<String>>,
     distinct_field: Setting<String>,
     synonyms: Setting<HashMap<String, Vec<String>>>,
+    typo_costs: Setting<TypoCosts>,
     primary_key: Setting<String>,
+
+    update_id: Option<u64>,
 }
 
 impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
@@ -106,9 +304,21 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             sortable_fields: Setting::NotSet,
             criteria: Setting::NotSet,
             stop_words: Setting::NotSet,
+            separator_tokens: Setting::NotSet,
+            non_separator_tokens: Setting::NotSet,
+            protected_patterns: Setting::NotSet,
+            emoji_policy: Setting::NotSet,
+            prefix_databases: Setting::NotSet,
+            proximity_precision: Setting::NotSet,
+            store_documents: Setting::NotSet,
+            compress_documents: Setting::NotSet,
+            dictionary: Setting::NotSet,
+            split_identifiers_fields: Setting::NotSet,
             distinct_field: Setting::NotSet,
             synonyms: Setting::NotSet,
+            typo_costs: Setting::NotSet,
             primary_key: Setting::NotSet,
+            update_id: None,
             indexer_config,
         }
     }
@@ -162,6 +372,109 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             if stop_words.is_empty() { Setting::Reset } else { Setting::Set(stop_words) }
     }
 
+    pub fn reset_separator_tokens(&mut self) {
+        self.separator_tokens = Setting::Reset;
+    }
+
+    pub fn set_separator_tokens(&mut self, separator_tokens: BTreeSet<String>) {
+        self.separator_tokens = if separator_tokens.is_empty() {
+            Setting::Reset
+        } else {
+            Setting::Set(separator_tokens)
+        }
+    }
+
+    pub fn reset_non_separator_tokens(&mut self) {
+        self.non_separator_tokens = Setting::Reset;
+    }
+
+    pub fn set_non_separator_tokens(&mut self, non_separator_tokens: BTreeSet<String>) {
+        self.non_separator_tokens = if non_separator_tokens.is_empty() {
+            Setting::Reset
+        } else {
+            Setting::Set(non_separator_tokens)
+        }
+    }
+
+    pub fn reset_protected_patterns(&mut self) {
+        self.protected_patterns = Setting::Reset;
+    }
+
+    pub fn set_protected_patterns(&mut self, patterns: Vec<ProtectedPattern>) {
+        self.protected_patterns =
+            if patterns.is_empty() { Setting::Reset } else { Setting::Set(patterns) }
+    }
+
+    pub fn reset_emoji_policy(&mut self) {
+        self.emoji_policy = Setting::Reset;
+    }
+
+    pub fn set_emoji_policy(&mut self, policy: EmojiPolicy) {
+        self.emoji_policy = Setting::Set(policy);
+    }
+
+    pub fn reset_prefix_databases(&mut self) {
+        self.prefix_databases = Setting::Reset;
+    }
+
+    /// Disabling the prefix databases saves the time and space they take
+    /// in the indexes that are not queried with prefixes.
+    pub fn set_prefix_databases(&mut self, enabled: bool) {
+        self.prefix_databases = Setting::Set(enabled);
+    }
+
+    pub fn reset_proximity_precision(&mut self) {
+        self.proximity_precision = Setting::Reset;
+    }
+
+    /// Computing the proximity by attribute trades the accuracy of the proximity criterion
+    /// for a much smaller index, the pairs of words are not stored anymore.
+    pub fn set_proximity_precision(&mut self, precision: ProximityPrecision) {
+        self.proximity_precision = Setting::Set(precision);
+    }
+
+    pub fn reset_store_documents(&mut self) {
+        self.store_documents = Setting::Reset;
+    }
+
+    /// Not storing the documents saves the space they take in the indexes whose documents
+    /// are kept elsewhere, the searches only return their ids. The stored documents are
+    /// deleted, and the documents can't be partially updated nor reindexed anymore.
+    ///
+    /// The field distribution can't be updated when the documents are replaced or deleted.
+    pub fn set_store_documents(&mut self, enabled: bool) {
+        self.store_documents = Setting::Set(enabled);
+    }
+
+    pub fn reset_compress_documents(&mut self) {
+        self.compress_documents = Setting::Reset;
+    }
+
+    /// Compresses the stored documents with zstd and a dictionary trained on a sample of
+    /// the documents of the index, it must be enabled once the index contains representative
+    /// documents. Enabling it again trains a new dictionary on the current documents.
+    pub fn set_compress_documents(&mut self, enabled: bool) {
+        self.compress_documents = Setting::Set(enabled);
+    }
+
+    pub fn reset_dictionary(&mut self) {
+        self.dictionary = Setting::Reset;
+    }
+
+    pub fn set_dictionary(&mut self, dictionary: BTreeSet<String>) {
+        self.dictionary =
+            if dictionary.is_empty() { Setting::Reset } else { Setting::Set(dictionary) }
+    }
+
+    pub fn reset_split_identifiers_fields(&mut self) {
+        self.split_identifiers_fields = Setting::Reset;
+    }
+
+    /// The camelCase and snake_case identifiers of these fields are split into sub-words.
+    pub fn set_split_identifiers_fields(&mut self, names: HashSet<String>) {
+        self.split_identifiers_fields = Setting::Set(names);
+    }
+
     pub fn reset_distinct_field(&mut self) {
         self.distinct_field = Setting::Reset;
     }
@@ -178,6 +491,16 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.synonyms = if synonyms.is_empty() { Setting::Reset } else { Setting::Set(synonyms) }
     }
 
+    pub fn reset_typo_costs(&mut self) {
+        self.typo_costs = Setting::Reset;
+    }
+
+    /// The costs of the typos are only used by the typo criterion, e.g. to rank the long
+    /// compound words with a typo as the exact ones.
+    pub fn set_typo_costs(&mut self, costs: TypoCosts) {
+        self.typo_costs = Setting::Set(costs);
+    }
+
     pub fn reset_primary_key(&mut self) {
         self.primary_key = Setting::Reset;
     }
@@ -186,7 +509,18 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.primary_key = Setting::Set(primary_key);
     }
 
-    fn reindex<F>(&mut self, cb: &F, old_fields_ids_map: FieldsIdsMap) -> Result<()>
+    /// Sets the id of the update given by the embedder, recorded in the logs of the update.
+    pub fn set_update_id(&mut self, update_id: u64) {
+        self.update_id = Some(update_id);
+    }
+
+    fn reindex<F>(
+        &mut self,
+        cb: &F,
+        old_fields_ids_map: FieldsIdsMap,
+        old_faceted_fields: HashSet<String>,
+        scope: ReindexScope,
+    ) -> Result<()>
     where
         F: Fn(UpdateIndexingStep) + Sync,
     {
@@ -196,12 +530,16 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         if self.index.number_of_documents(&self.wtxn)? == 0 {
             return Ok(());
         }
+        self.index.ensure_documents_stored(self.wtxn, "reindex the documents")?;
 
         let transform = Transform::new(
             &self.index,
             &self.indexer_config,
             IndexDocumentsMethod::ReplaceDocuments,
             false,
+            false,
+            DocumentIdNormalization::Verbatim,
+            DocumentIdCharset::default(),
         );
 
         // We remap the documents fields based on the new `FieldsIdsMap`.
@@ -211,8 +549,23 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             fields_ids_map.clone(),
         )?;
 
-        // We clear the full database (words-fst, documents ids and documents content).
-        ClearDocuments::new(self.wtxn, self.index).execute()?;
+        if scope.full {
+            // We clear the full database (words-fst, documents ids and documents content).
+            ClearDocuments::new(self.wtxn, self.index).execute()?;
+        } else {
+            // We only clear the databases rebuilt from the documents, the documents stay.
+            if scope.words {
+                self.clear_words_databases()?;
+            }
+            if scope.facets {
+                let faceted_fields = old_faceted_fields
+                    .iter()
+                    .chain(self.index.faceted_fields(self.wtxn)?.iter())
+                    .filter_map(|name| fields_ids_map.id(name))
+                    .collect();
+                self.clear_facets_databases(faceted_fields)?;
+            }
+        }
 
         // We index the generated `TransformOutput` which must contain
         // all the documents with fields in the newly defined searchable order.
@@ -223,8 +576,45 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             IndexDocumentsConfig::default(),
             &cb,
         );
-        indexing_builder.execute_raw(output)?;
+        indexing_builder.execute_reindex(output, scope)?;
+
+        Ok(())
+    }
+
+    fn clear_words_databases(&mut self) -> Result<()> {
+        self.index.put_words_fst(self.wtxn, &fst::Set::default())?;
+        self.index.put_words_prefixes_fst(self.wtxn, &fst::Set::default())?;
+        self.index.word_docids.clear(self.wtxn)?;
+        self.index.exact_word_docids.clear(self.wtxn)?;
+        self.index.word_prefix_docids.clear(self.wtxn)?;
+        self.index.docid_word_positions.clear(self.wtxn)?;
+        self.index.word_pair_proximity_docids.clear(self.wtxn)?;
+        self.index.delete_proximity_window(self.wtxn)?;
+        self.index.word_prefix_pair_proximity_docids.clear(self.wtxn)?;
+        self.index.word_position_docids.clear(self.wtxn)?;
+        self.index.field_id_word_count_docids.clear(self.wtxn)?;
+        self.index.word_prefix_position_docids.clear(self.wtxn)?;
+        self.index.word_fid_docids.clear(self.wtxn)?;
+        Ok(())
+    }
 
+    /// Clears the facet and geo databases and the faceted documents ids of the given fields.
+    fn clear_facets_databases(&mut self, faceted_fields: HashSet<FieldId>) -> Result<()> {
+        self.index.delete_geo_rtree(self.wtxn)?;
+        self.index.delete_geo_faceted_documents_ids(self.wtxn)?;
+        self.index.delete_flattened_faceted_fields(self.wtxn)?;
+        let empty = RoaringBitmap::default();
+        for field_id in faceted_fields {
+            self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_facet_id_bool_docids(self.wtxn, field_id, true, &empty)?;
+            self.index.put_facet_id_bool_docids(self.wtxn, field_id, false, &empty)?;
+        }
+        self.index.facet_id_f64_docids.clear(self.wtxn)?;
+        self.index.facet_id_string_docids.clear(self.wtxn)?;
+        self.index.facet_id_date_docids.clear(self.wtxn)?;
+        self.index.field_id_docid_facet_f64s.clear(self.wtxn)?;
+        self.index.field_id_docid_facet_strings.clear(self.wtxn)?;
         Ok(())
     }
 
@@ -314,6 +704,178 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    fn update_separator_tokens(&mut self) -> Result<bool> {
+        match self.separator_tokens {
+            Setting::Set(ref separator_tokens) => {
+                let current = self.index.separator_tokens(self.wtxn)?;
+                if current.as_ref() != Some(separator_tokens) {
+                    self.index.put_separator_tokens(self.wtxn, separator_tokens)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_separator_tokens(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_non_separator_tokens(&mut self) -> Result<bool> {
+        match self.non_separator_tokens {
+            Setting::Set(ref non_separator_tokens) => {
+                let current = self.index.non_separator_tokens(self.wtxn)?;
+                if current.as_ref() != Some(non_separator_tokens) {
+                    self.index.put_non_separator_tokens(self.wtxn, non_separator_tokens)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_non_separator_tokens(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_protected_patterns(&mut self) -> Result<bool> {
+        match self.protected_patterns {
+            Setting::Set(ref patterns) => {
+                // We make sure that the patterns are valid before storing them.
+                for pattern in patterns {
+                    pattern.to_regex()?;
+                }
+
+                let current = self.index.protected_patterns(self.wtxn)?;
+                if current.as_ref() != Some(patterns) {
+                    self.index.put_protected_patterns(self.wtxn, patterns)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_protected_patterns(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_emoji_policy(&mut self) -> Result<bool> {
+        let current = self.index.emoji_policy(self.wtxn)?;
+        match self.emoji_policy {
+            Setting::Set(policy) => {
+                self.index.put_emoji_policy(self.wtxn, policy)?;
+                Ok(current != policy)
+            }
+            Setting::Reset => {
+                self.index.delete_emoji_policy(self.wtxn)?;
+                Ok(current != EmojiPolicy::default())
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_prefix_databases(&mut self) -> Result<bool> {
+        let current = self.index.prefix_databases(self.wtxn)?;
+        match self.prefix_databases {
+            Setting::Set(enabled) => {
+                self.index.put_prefix_databases(self.wtxn, enabled)?;
+                Ok(current != enabled)
+            }
+            Setting::Reset => {
+                self.index.delete_prefix_databases(self.wtxn)?;
+                Ok(!current)
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_proximity_precision(&mut self) -> Result<bool> {
+        let current = self.index.proximity_precision(self.wtxn)?;
+        match self.proximity_precision {
+            Setting::Set(precision) => {
+                self.index.put_proximity_precision(self.wtxn, precision)?;
+                Ok(current != precision)
+            }
+            Setting::Reset => {
+                self.index.delete_proximity_precision(self.wtxn)?;
+                Ok(current != ProximityPrecision::default())
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_dictionary(&mut self) -> Result<bool> {
+        match self.dictionary {
+            Setting::Set(ref dictionary) => {
+                let current = self.index.dictionary(self.wtxn)?;
+                if current.as_ref() != Some(dictionary) {
+                    self.index.put_dictionary(self.wtxn, dictionary)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_dictionary(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_compress_documents(&mut self) -> Result<()> {
+        match self.compress_documents {
+            Setting::Set(true) => {
+                self.index.ensure_documents_stored(self.wtxn, "compress the documents")?;
+                let dictionary = train_dictionary(self.wtxn, self.index)?;
+                recompress_documents(self.wtxn, self.index, Some(&dictionary))?;
+            }
+            Setting::Set(false) | Setting::Reset => {
+                if self.index.documents_compressed(self.wtxn)? {
+                    recompress_documents(self.wtxn, self.index, None)?;
+                }
+            }
+            Setting::NotSet => (),
+        }
+        Ok(())
+    }
+
+    fn update_store_documents(&mut self) -> Result<()> {
+        let current = self.index.store_documents(self.wtxn)?;
+        let enabled = match self.store_documents {
+            Setting::Set(enabled) => enabled,
+            Setting::Reset => true,
+            Setting::NotSet => return Ok(()),
+        };
+
+        if enabled && !current && self.index.number_of_documents(self.wtxn)? != 0 {
+            // The documents added while they were not stored would never be.
+            let operation = "start storing them, the index must be cleared first";
+            return Err(UserError::DocumentsNotStored { operation }.into());
+        }
+        if !enabled && current {
+            self.index.documents.clear(self.wtxn)?;
+        }
+
+        match self.store_documents {
+            Setting::Set(enabled) => self.index.put_store_documents(self.wtxn, enabled)?,
+            _ => {
+                self.index.delete_store_documents(self.wtxn)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn update_split_identifiers_fields(&mut self) -> Result<bool> {
+        let current = self.index.split_identifiers_fields(self.wtxn)?;
+        match self.split_identifiers_fields {
+            Setting::Set(ref fields) => {
+                self.index.put_split_identifiers_fields(self.wtxn, fields)?;
+                Ok(&current != fields)
+            }
+            Setting::Reset => {
+                self.index.delete_split_identifiers_fields(self.wtxn)?;
+                Ok(!current.is_empty())
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
     fn update_synonyms(&mut self) -> Result<bool> {
         match self.synonyms {
             Setting::Set(ref synonyms) => {
@@ -371,6 +933,17 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    fn update_typo_costs(&mut self) -> Result<()> {
+        match self.typo_costs {
+            Setting::Set(ref costs) => self.index.put_typo_costs(self.wtxn, costs)?,
+            Setting::Reset => {
+                self.index.delete_typo_costs(self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+        Ok(())
+    }
+
     fn update_filterable(&mut self) -> Result<()> {
         match self.filterable_fields {
             Setting::Set(ref fields) => {
@@ -454,9 +1027,18 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
     where
         F: Fn(UpdateIndexingStep) + Sync,
     {
+        let span = self.index.span(self.wtxn, "settings");
+        if let Some(update_id) = self.update_id {
+            span.record("update_id", &update_id);
+        }
+        let _entered = span.enter();
+
         self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
 
+        let old_settings = DumpSettings::from_index(self.index, self.wtxn)?;
+        let old_documents_storage = self.documents_storage()?;
         let old_faceted_fields = self.index.faceted_fields(&self.wtxn)?;
+        let old_filterable_fields = self.index.filterable_fields_with_flattened(&self.wtxn)?;
         let old_fields_ids_map = self.index.fields_ids_map(&self.wtxn)?;
 
         self.update_displayed()?;
@@ -471,17 +1053,89 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         // an Asc/Desc criterion or a filtered attribute as be added or removed.
         let new_faceted_fields = self.index.faceted_fields(&self.wtxn)?;
         let faceted_updated = old_faceted_fields != new_faceted_fields;
+        let new_filterable_fields = self.index.filterable_fields_with_flattened(&self.wtxn)?;
 
         let stop_words_updated = self.update_stop_words()?;
-        let synonyms_updated = self.update_synonyms()?;
+        let separator_tokens_updated = self.update_separator_tokens()?;
+        let non_separator_tokens_updated = self.update_non_separator_tokens()?;
+        let protected_patterns_updated = self.update_protected_patterns()?;
+        let emoji_policy_updated = self.update_emoji_policy()?;
+        let dictionary_updated = self.update_dictionary()?;
+        let proximity_precision_updated = self.update_proximity_precision()?;
+        let split_identifiers_updated = self.update_split_identifiers_fields()?;
+        // The synonyms and the typo costs are only used at search time, nothing is reindexed.
+        self.update_synonyms()?;
+        self.update_typo_costs()?;
         let searchable_updated = self.update_searchable()?;
+        let prefix_databases_updated = self.update_prefix_databases()?;
+
+        // The generation is bumped before the reindexing, it must not reuse what was
+        // computed from the previous settings, e.g. the compiled protected patterns.
+        let settings_updated = DumpSettings::from_index(self.index, self.wtxn)? != old_settings;
+        if settings_updated {
+            self.index.increment_settings_generation(self.wtxn)?;
+        }
 
-        if stop_words_updated || faceted_updated || synonyms_updated || searchable_updated {
-            self.reindex(&progress_callback, old_fields_ids_map)?;
+        // The documents are only rewritten when the fields ids changed, otherwise only the
+        // databases affected by the updated settings are rebuilt from the stored documents.
+        let new_fields_ids_map = self.index.fields_ids_map(&self.wtxn)?;
+        let fields_ids_changed = old_fields_ids_map.iter().ne(new_fields_ids_map.iter());
+        let scope = ReindexScope {
+            full: fields_ids_changed,
+            words: stop_words_updated
+                || separator_tokens_updated
+                || non_separator_tokens_updated
+                || protected_patterns_updated
+                || emoji_policy_updated
+                || dictionary_updated
+                || proximity_precision_updated
+                || split_identifiers_updated
+                || searchable_updated,
+            facets: faceted_updated,
+        };
+
+        if !scope.is_empty() {
+            self.reindex(&progress_callback, old_fields_ids_map, old_faceted_fields, scope)?;
+        } else if prefix_databases_updated {
+            // The prefix databases are computed or cleared from the words databases.
+            let mut indexing_builder = IndexDocuments::new(
+                self.wtxn,
+                self.index,
+                &self.indexer_config,
+                IndexDocumentsConfig::default(),
+                &progress_callback,
+            );
+            indexing_builder.execute_prefix_databases(Vec::new(), Vec::new(), Vec::new())?;
+        } else if old_filterable_fields != new_filterable_fields {
+            // The fields that are only sortable have no facet levels, the levels of the fields
+            // that became filterable, or stopped being, are computed or cleared.
+            let fields_ids = old_filterable_fields
+                .symmetric_difference(&new_filterable_fields)
+                .filter_map(|name| new_fields_ids_map.id(name))
+                .collect();
+            let mut builder = Facets::new(self.wtxn, self.index);
+            builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+            builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+            builder.fields_ids = Some(fields_ids);
+            builder.execute()?;
+        }
+
+        // The documents are only compressed or deleted once they are reindexed.
+        self.update_compress_documents()?;
+        self.update_store_documents()?;
+        if !settings_updated && self.documents_storage()? != old_documents_storage {
+            self.index.increment_settings_generation(self.wtxn)?;
         }
 
         Ok(())
     }
+
+    /// Returns whether the documents are stored and whether they are compressed.
+    fn documents_storage(&self) -> Result<(bool, bool)> {
+        let stored = self.index.store_documents(&self.wtxn)?;
+        let compressed = self.index.documents_compressed(&self.wtxn)?;
+        Ok((stored, compressed))
+    }
 }
 
 #[cfg(test)]
@@ -489,11 +1143,11 @@ mod tests {
     use big_s::S;
     use heed::types::ByteSlice;
     use heed::EnvOpenOptions;
-    use maplit::{btreeset, hashmap, hashset};
+    use maplit::{btreemap, btreeset, hashmap, hashset};
 
     use super::*;
     use crate::error::Error;
-    use crate::update::IndexDocuments;
+    use crate::update::{DeleteDocuments, IndexDocuments};
     use crate::{Criterion, Filter, SearchResult};
 
     #[test]
@@ -724,8 +1378,12 @@ mod tests {
         println!("fidmap: {:?}", fidmap);
         for document in index.all_documents(&rtxn).unwrap() {
             let document = document.unwrap();
-            let json = crate::obkv_to_json(&fidmap.ids().collect::<Vec<_>>(), &fidmap, document.1)
-                .unwrap();
+            let json = crate::obkv_to_json(
+                &fidmap.ids().collect::<Vec<_>>(),
+                &fidmap,
+                document.1.as_obkv(),
+            )
+            .unwrap();
             println!("json: {:?}", json);
         }
         let count = index
@@ -766,65 +1424,403 @@ mod tests {
     }
 
     #[test]
-    fn set_asc_desc_field() {
+    fn only_reindex_affected_databases() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
         let index = Index::new(options, &path).unwrap();
-        let config = IndexerConfig::default();
 
-        // Set the filterable fields to be the age.
+        let config = IndexerConfig::default();
         let mut wtxn = index.write_txn().unwrap();
-        let mut builder = Settings::new(&mut wtxn, &index, &config);
-        // Don't display the generated `id` field.
-        builder.set_displayed_fields(vec![S("name")]);
-        builder.set_criteria(vec![S("age:asc")]);
-        builder.execute(|_| ()).unwrap();
-
-        // Then index some documents.
         let content = documents!([
-            { "name": "kevin", "age": 23},
-            { "name": "kevina", "age": 21 },
-            { "name": "benoit", "age": 34 }
+            { "id": 0, "name": "the kevin", "age": 23 },
+            { "id": 1, "name": "the kevina", "age": 21 },
+            { "id": 2, "name": "benoit", "age": 34 }
         ]);
-        let indexing_config =
-            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
-        let mut builder =
-            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
         builder.add_documents(content).unwrap();
         builder.execute().unwrap();
-        wtxn.commit().unwrap();
 
-        // Run an empty query just to ensure that the search results are ordered.
-        let rtxn = index.read_txn().unwrap();
-        let SearchResult { documents_ids, .. } = index.search(&rtxn).execute().unwrap();
-        let documents = index.documents(&rtxn, documents_ids).unwrap();
+        // We put sentinels in the word and facet databases to see which ones are rebuilt.
+        let sentinel: RoaringBitmap = Some(42).into_iter().collect();
+        index.word_docids.put(&mut wtxn, "sentinel", &sentinel).unwrap();
 
-        // Fetch the documents "age" field in the ordre in which the documents appear.
-        let age_field_id = index.fields_ids_map(&rtxn).unwrap().id("age").unwrap();
-        let iter = documents.into_iter().map(|(_, doc)| {
-            let bytes = doc.get(age_field_id).unwrap();
-            let string = std::str::from_utf8(bytes).unwrap();
-            string.parse::<u32>().unwrap()
-        });
+        // The displayed fields don't trigger any reindex.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_displayed_fields(vec![S("name")]);
+        builder.execute(|_| ()).unwrap();
+        assert!(index.word_docids.get(&wtxn, "sentinel").unwrap().is_some());
 
-        assert_eq!(iter.collect::<Vec<_>>(), vec![21, 23, 34]);
+        // The filterable fields only rebuild the facet databases.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+        assert!(index.word_docids.get(&wtxn, "sentinel").unwrap().is_some());
+        let filter = Filter::from_str("age < 30").unwrap().unwrap();
+        assert_eq!(filter.evaluate(&wtxn, &index).unwrap(), (0..2).collect::<RoaringBitmap>());
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+
+        // The stop words only rebuild the word databases.
+        let facet_entries = index.facet_id_f64_docids.len(&wtxn).unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+        assert!(index.word_docids.get(&wtxn, "sentinel").unwrap().is_none());
+        assert!(index.word_docids.get(&wtxn, "kevina").unwrap().is_some());
+        assert_eq!(index.facet_id_f64_docids.len(&wtxn).unwrap(), facet_entries);
+        assert_eq!(filter.evaluate(&wtxn, &index).unwrap(), (0..2).collect::<RoaringBitmap>());
+
+        // Removing the filterable field removes its facet values.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_filterable_fields();
+        builder.execute(|_| ()).unwrap();
+        assert!(index.facet_id_f64_docids.is_empty(&wtxn).unwrap());
+        assert!(index.word_docids.get(&wtxn, "kevina").unwrap().is_some());
+        wtxn.commit().unwrap();
     }
 
     #[test]
-    fn set_distinct_field() {
+    fn reindex_documents_in_chunks() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
         let index = Index::new(options, &path).unwrap();
-        let config = IndexerConfig::default();
 
-        // Set the filterable fields to be the age.
+        // Every document is written in its own chunk when the documents are reindexed.
+        let config = IndexerConfig { documents_chunk_size: Some(1), ..Default::default() };
         let mut wtxn = index.write_txn().unwrap();
-        let mut builder = Settings::new(&mut wtxn, &index, &config);
-        // Don't display the generated `id` field.
-        builder.set_displayed_fields(vec![S("name"), S("age")]);
-        builder.set_distinct_field(S("age"));
+        let content = documents!([
+            { "id": 0, "name": "kevin", "age": 23 },
+            { "id": 1, "name": "kevina", "age": 21 },
+            { "id": 2, "name": "benoit", "age": 34 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The fields ids don't change, the documents are chunked as they are stored.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+        let filter = Filter::from_str("age < 30").unwrap().unwrap();
+        assert_eq!(filter.evaluate(&wtxn, &index).unwrap(), (0..2).collect::<RoaringBitmap>());
+
+        // The searchable fields reorder the fields ids, the documents are rewritten.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("age"), S("name")]);
+        builder.execute(|_| ()).unwrap();
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+        assert_eq!(filter.evaluate(&wtxn, &index).unwrap(), (0..2).collect::<RoaringBitmap>());
+        let result = index.search(&wtxn).query("benoit").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2]);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn disable_prefix_databases() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // There must be enough words starting with "hel" for it to be a prefix.
+        let words: Vec<_> =
+            ('a'..='z').flat_map(|a| ('a'..='b').map(move |b| format!("hel{}{}", a, b))).collect();
+        let words = words.join(" ");
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "hello world" },
+            { "id": 1, "name": words }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert!(index.words_prefixes_fst(&wtxn).unwrap().contains("hel"));
+        assert!(!index.word_prefix_docids.is_empty(&wtxn).unwrap());
+
+        // The prefixes are removed without any reindex.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_prefix_databases(false);
+        builder.execute(|_| ()).unwrap();
+        assert!(!index.prefix_databases(&wtxn).unwrap());
+        assert!(index.words_prefixes_fst(&wtxn).unwrap().is_empty());
+        assert!(index.word_prefix_docids.is_empty(&wtxn).unwrap());
+        assert!(index.word_prefix_pair_proximity_docids.is_empty(&wtxn).unwrap());
+        assert!(index.word_prefix_position_docids.is_empty(&wtxn).unwrap());
+
+        // The prefix queries are still answered from the words.
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("hel").execute().unwrap();
+        assert_eq!(documents_ids.len(), 2);
+
+        // The new documents don't fill the prefix databases either.
+        let content = documents!([ { "id": 2, "name": "help" } ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert!(index.word_prefix_docids.is_empty(&wtxn).unwrap());
+
+        // The prefixes are computed back from the words.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_prefix_databases();
+        builder.execute(|_| ()).unwrap();
+        assert!(index.words_prefixes_fst(&wtxn).unwrap().contains("hel"));
+        let docids = index.word_prefix_docids.get(&wtxn, "hel").unwrap().unwrap();
+        assert_eq!(docids.len(), 3);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn proximity_precision_and_window() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "big house", "description": "" },
+            { "id": 1, "title": "big", "description": "house" },
+            { "id": 2, "title": "big red and blue house", "description": "" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert!(!index.word_pair_proximity_docids.is_empty(&wtxn).unwrap());
+
+        // The pairs of words are not stored when the proximity is computed by attribute.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_proximity_precision(ProximityPrecision::ByAttribute);
+        builder.execute(|_| ()).unwrap();
+        assert_eq!(index.proximity_precision(&wtxn).unwrap(), ProximityPrecision::ByAttribute);
+        assert!(index.word_pair_proximity_docids.is_empty(&wtxn).unwrap());
+        assert!(index.word_prefix_pair_proximity_docids.is_empty(&wtxn).unwrap());
+
+        // The documents with both words in the same attribute are the closest ones.
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("big house").execute().unwrap();
+        assert_eq!(documents_ids.len(), 3);
+        assert_eq!(documents_ids[2], 1);
+
+        // The pairs are stored again up to the proximity window.
+        let config = IndexerConfig { proximity_window: Some(1), ..Default::default() };
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_proximity_precision();
+        builder.execute(|_| ()).unwrap();
+        assert_eq!(index.proximity_window(&wtxn).unwrap(), Some(1));
+        let pairs = index.word_pair_proximity_docids;
+        assert!(pairs.get(&wtxn, &("big", "house", 1)).unwrap().is_some());
+        assert!(pairs.get(&wtxn, &("big", "house", 4)).unwrap().is_none());
+
+        // The farther pairs are ranked by attribute.
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("big house").execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2, 1]);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn set_and_reset_typo_costs() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("description")]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "", "description": "donaudampfschiffahrt" },
+            { "id": 1, "title": "donaudampfschiffahrx", "description": "" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The document without any typo comes first.
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("donaudampfschiffahrt").execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        // A typo in a long word costs nothing, the attribute ranks the documents.
+        let costs = TypoCosts { free_typos: btreemap! { 12 => 1 }, ..Default::default() };
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_typo_costs(costs.clone());
+        builder.execute(|_| ()).unwrap();
+        assert_eq!(index.typo_costs(&wtxn).unwrap(), costs);
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("donaudampfschiffahrt").execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0]);
+
+        // All the documents are in the same bucket.
+        let costs = TypoCosts { max_buckets: Some(1), ..Default::default() };
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_typo_costs(costs);
+        builder.execute(|_| ()).unwrap();
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("donaudampfschiffahrt").execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0]);
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_typo_costs();
+        builder.execute(|_| ()).unwrap();
+        assert_eq!(index.typo_costs(&wtxn).unwrap(), TypoCosts::default());
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("donaudampfschiffahrt").execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn do_not_store_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevina" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The already stored documents are deleted.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_store_documents(false);
+        builder.execute(|_| ()).unwrap();
+        assert!(!index.store_documents(&wtxn).unwrap());
+        assert!(index.documents.is_empty(&wtxn).unwrap());
+
+        // The new documents are indexed but not stored, the search returns their ids.
+        let content = documents!([ { "id": 2, "name": "kevin" }, { "id": 1, "name": "bob" } ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert!(index.documents.is_empty(&wtxn).unwrap());
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("kevin").execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2]);
+        assert!(matches!(
+            index.documents(&wtxn, documents_ids),
+            Err(Error::UserError(UserError::DocumentsNotStored { .. }))
+        ));
+
+        // The documents can't be partially updated.
+        let content = documents!([ { "id": 2, "age": 21 } ]);
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        assert!(matches!(
+            builder.execute(),
+            Err(Error::UserError(UserError::DocumentsNotStored { .. }))
+        ));
+
+        // But they can still be deleted.
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("0");
+        assert_eq!(builder.execute().unwrap().deleted_documents, 1);
+        let SearchResult { documents_ids, .. } =
+            index.search(&wtxn).query("kevin").execute().unwrap();
+        assert_eq!(documents_ids, vec![2]);
+
+        // Neither reindexing nor storing the documents again are possible.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("name")]);
+        assert!(matches!(
+            builder.execute(|_| ()),
+            Err(Error::UserError(UserError::DocumentsNotStored { .. }))
+        ));
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_store_documents();
+        assert!(matches!(
+            builder.execute(|_| ()),
+            Err(Error::UserError(UserError::DocumentsNotStored { .. }))
+        ));
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn set_asc_desc_field() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        // Set the filterable fields to be the age.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        // Don't display the generated `id` field.
+        builder.set_displayed_fields(vec![S("name")]);
+        builder.set_criteria(vec![S("age:asc")]);
+        builder.execute(|_| ()).unwrap();
+
+        // Then index some documents.
+        let content = documents!([
+            { "name": "kevin", "age": 23},
+            { "name": "kevina", "age": 21 },
+            { "name": "benoit", "age": 34 }
+        ]);
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // Run an empty query just to ensure that the search results are ordered.
+        let rtxn = index.read_txn().unwrap();
+        let SearchResult { documents_ids, .. } = index.search(&rtxn).execute().unwrap();
+        let documents = index.documents(&rtxn, documents_ids).unwrap();
+
+        // Fetch the documents "age" field in the ordre in which the documents appear.
+        let age_field_id = index.fields_ids_map(&rtxn).unwrap().id("age").unwrap();
+        let iter = documents.into_iter().map(|(_, doc)| {
+            let bytes = doc.get(age_field_id).unwrap();
+            let string = std::str::from_utf8(bytes).unwrap();
+            string.parse::<u32>().unwrap()
+        });
+
+        assert_eq!(iter.collect::<Vec<_>>(), vec![21, 23, 34]);
+    }
+
+    #[test]
+    fn set_distinct_field() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        // Set the filterable fields to be the age.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        // Don't display the generated `id` field.
+        builder.set_displayed_fields(vec![S("name"), S("age")]);
+        builder.set_distinct_field(S("age"));
         builder.execute(|_| ()).unwrap();
 
         // Then index some documents.
@@ -960,6 +1956,356 @@ mod tests {
         assert_eq!(result.documents_ids.len(), 1); // there is one benoit in our data
     }
 
+    #[test]
+    fn set_and_reset_protected_patterns() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "name": "kevin", "product": "GTX-1080" },
+            { "name": "kevina", "product": "GTX 1070" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // An invalid regex is refused.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_protected_patterns(vec![ProtectedPattern::Regex(S("GTX-("))]);
+        let error = builder.execute(|_| ()).unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::InvalidProtectedPattern { .. })));
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_protected_patterns(vec![ProtectedPattern::Regex(S(r"GTX-\d+"))]);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let patterns = index.protected_patterns(&rtxn).unwrap();
+        assert_eq!(patterns, Some(vec![ProtectedPattern::Regex(S(r"GTX-\d+"))]));
+
+        // The identifier is now a single word.
+        let result = index.search(&rtxn).query("gtx-1080 ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("1080 ").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_protected_patterns();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.protected_patterns(&rtxn).unwrap(), None);
+        let result = index.search(&rtxn).query("1080 ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+    }
+
+    #[test]
+    fn protected_patterns_compiled_in_an_aborted_update() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "name": "kevin", "product": "GTX-1080" },
+            { "name": "kevina", "product": "RTX-3080" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The patterns of the aborted update are compiled by its search.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_protected_patterns(vec![ProtectedPattern::Regex(S(r"GTX-\d+"))]);
+        builder.execute(|_| ()).unwrap();
+        let result = index.search(&wtxn).query("gtx-1080 ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        wtxn.abort().unwrap();
+
+        // The next update has the same settings generation but other patterns.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_protected_patterns(vec![ProtectedPattern::Regex(S(r"RTX-\d+"))]);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("rtx-3080 ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        let result = index.search(&rtxn).query("1080 ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+    }
+
+    #[test]
+    fn set_and_reset_split_identifiers_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "getUserName", "code": "getUserName" },
+            { "id": 1, "title": "plain", "code": "user_name" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_split_identifiers_fields(hashset! { S("code") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.split_identifiers_fields(&rtxn).unwrap(), hashset! { S("code") });
+
+        // only the code field is split into sub-words.
+        let code = index.fields_ids_map(&rtxn).unwrap().id("code").unwrap();
+        let title = index.fields_ids_map(&rtxn).unwrap().id("title").unwrap();
+        let docids = index.word_fid_docids.get(&rtxn, &("user", code)).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1]);
+        assert!(index.word_fid_docids.get(&rtxn, &("user", title)).unwrap().is_none());
+
+        // the query is split too as a searchable field enables the splitting.
+        let result = index.search(&rtxn).query("userName").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_split_identifiers_fields();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.split_identifiers_fields(&rtxn).unwrap().is_empty());
+        assert!(index.word_fid_docids.get(&rtxn, &("user", code)).unwrap().is_none());
+    }
+
+    #[test]
+    fn set_and_reset_dictionary() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "name": "kevin", "city": "New-York" },
+            { "name": "kevina", "city": "York" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_dictionary(btreeset! { S("new-york") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.dictionary(&rtxn).unwrap(), Some(btreeset! { S("new-york") }));
+        let result = index.search(&rtxn).query("new-york ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("york ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_dictionary();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.dictionary(&rtxn).unwrap(), None);
+        let result = index.search(&rtxn).query("york ").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+    }
+
+    #[test]
+    fn set_and_reset_emoji_policy() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "name": "kevin", "status": "feeling 🙂" },
+            { "name": "kevina", "status": "feeling sad" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_emoji_policy(EmojiPolicy::Name);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The emoji is searchable by its name and the query emoji is normalized too.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.emoji_policy(&rtxn).unwrap(), EmojiPolicy::Name);
+        let result = index.search(&rtxn).query("smiling ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("🙂").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_emoji_policy(EmojiPolicy::Strip);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("smiling ").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_emoji_policy();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.emoji_policy(&rtxn).unwrap(), EmojiPolicy::Index);
+    }
+
+    #[test]
+    fn settings_generation() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.settings_generation(&rtxn).unwrap(), 0);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_stop_words();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.settings_generation(&rtxn).unwrap(), 2);
+        drop(rtxn);
+
+        // An update that doesn't change any setting keeps the generation.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_stop_words();
+        builder.execute(|_| ()).unwrap();
+        let builder = Settings::new(&mut wtxn, &index, &config);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.settings_generation(&rtxn).unwrap(), 2);
+        drop(rtxn);
+
+        // An aborted update doesn't change the generation.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("a") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.abort().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.settings_generation(&rtxn).unwrap(), 2);
+    }
+
+    #[test]
+    fn set_and_reset_separator_tokens() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "name": "kevin", "maxim": "I love C++" },
+            { "name": "kevina", "maxim": "I love C" },
+            { "name": "benoit", "maxim": "Rock&Roll" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The documents are reindexed with the new separators.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_separator_tokens(btreeset! { S("&") });
+        builder.set_non_separator_tokens(btreeset! { S("+") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.separator_tokens(&rtxn).unwrap(), Some(btreeset! { S("&") }));
+        assert_eq!(index.non_separator_tokens(&rtxn).unwrap(), Some(btreeset! { S("+") }));
+
+        let result = index.search(&rtxn).query("c++ ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("roll ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_separator_tokens();
+        builder.reset_non_separator_tokens();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.separator_tokens(&rtxn).unwrap(), None);
+        assert_eq!(index.non_separator_tokens(&rtxn).unwrap(), None);
+
+        // "c++" is split again and matches both maxims.
+        let result = index.search(&rtxn).query("c++ ").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+    }
+
     #[test]
     fn set_and_reset_synonyms() {
         let path = tempfile::tempdir().unwrap();
@@ -1193,4 +2539,47 @@ mod tests {
         let line = std::str::from_utf8(content.get(fid).unwrap()).unwrap();
         assert_eq!(line, r#""Star Wars""#);
     }
+
+    #[test]
+    fn settings_payload_tri_state() {
+        let payload: SettingsPayload = serde_json::from_str(
+            r#"{ "filterableFields": ["genres"], "stopWords": null, "distinctField": "id" }"#,
+        )
+        .unwrap();
+        assert_eq!(payload.filterable_fields, Setting::Set(hashset! { S("genres") }));
+        assert_eq!(payload.stop_words, Setting::Reset);
+        assert_eq!(payload.displayed_fields, Setting::NotSet);
+
+        // The fields that are not set are not serialized and the reset ones are null.
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "filterableFields": ["genres"], "stopWords": null, "distinctField": "id" })
+        );
+        assert!(serde_json::from_str::<SettingsPayload>(r#"{ "unknown": [] }"#).is_err());
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.set_displayed_fields(vec![S("title")]);
+        builder.execute(|_| ()).unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        payload.apply(&mut builder);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.filterable_fields(&rtxn).unwrap(), hashset! { S("genres") });
+        assert_eq!(index.distinct_field(&rtxn).unwrap(), Some("id"));
+        assert!(index.stop_words(&rtxn).unwrap().is_none());
+        // The displayed fields were missing from the payload, they are untouched.
+        assert_eq!(index.displayed_fields(&rtxn).unwrap(), Some(vec!["title"]));
+    }
 }
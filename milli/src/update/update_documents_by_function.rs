@@ -0,0 +1,233 @@
+use std::io::Cursor;
+
+use heed::types::ByteSlice;
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    DeleteDocuments, IndexDocuments, IndexDocumentsConfig, IndexerConfig, UpdateIndexingStep,
+};
+use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use crate::error::{InternalError, Object, UserError};
+use crate::{obkv_to_json, Filter, Index, Result, BEU32};
+
+/// Applies a function to the documents of the index, or to the ones matching a filter, and
+/// reindexes the documents it modified. The function can also delete the documents by
+/// returning `None`, all of this is done in the same transaction.
+pub struct UpdateDocumentsByFunction<'t, 'u, 'i, 'a, F> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    indexer_config: &'a IndexerConfig,
+    documents_ids: Option<RoaringBitmap>,
+    progress: F,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentsUpdateResult {
+    /// The number of documents modified by the function.
+    pub updated_documents: u64,
+    /// The number of documents deleted by the function.
+    pub deleted_documents: u64,
+    /// The total number of documents in the index after the update.
+    pub number_of_documents: u64,
+}
+
+impl<'t, 'u, 'i, 'a, F> UpdateDocumentsByFunction<'t, 'u, 'i, 'a, F>
+where
+    F: Fn(UpdateIndexingStep) + Sync,
+{
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        indexer_config: &'a IndexerConfig,
+        progress: F,
+    ) -> UpdateDocumentsByFunction<'t, 'u, 'i, 'a, F> {
+        UpdateDocumentsByFunction { wtxn, index, indexer_config, documents_ids: None, progress }
+    }
+
+    /// Only applies the function to the documents matching the filter.
+    pub fn filter(&mut self, filter: &Filter) -> Result<()> {
+        self.documents_ids = Some(filter.evaluate(self.wtxn, self.index)?);
+        Ok(())
+    }
+
+    /// Calls the function with every selected document, the document is replaced by the
+    /// returned one when it differs and is deleted when `None` is returned.
+    ///
+    /// The primary key of a document identifies it, the function can't change it.
+    pub fn execute<U>(self, mut function: U) -> Result<DocumentsUpdateResult>
+    where
+        U: FnMut(Object) -> Result<Option<Object>>,
+    {
+        let mut documents_ids = self.index.documents_ids(self.wtxn)?;
+        if let Some(selected) = &self.documents_ids {
+            documents_ids &= selected;
+        }
+
+        let primary_key = match self.index.primary_key(self.wtxn)? {
+            Some(primary_key) if !documents_ids.is_empty() => primary_key.to_string(),
+            _ => {
+                let number_of_documents = self.index.number_of_documents(self.wtxn)?;
+                return Ok(DocumentsUpdateResult {
+                    updated_documents: 0,
+                    deleted_documents: 0,
+                    number_of_documents,
+                });
+            }
+        };
+
+        self.index.ensure_documents_stored(self.wtxn, "update the documents by function")?;
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let all_fields: Vec<_> = fields_ids_map.ids().collect();
+
+        let mut codec = self.index.documents_codec(self.wtxn)?;
+        let mut deleted_documents_ids = RoaringBitmap::new();
+        let mut updated_documents = Vec::new();
+        for docid in documents_ids {
+            let bytes = self
+                .index
+                .documents
+                .remap_data_type::<ByteSlice>()
+                .get(self.wtxn, &BEU32::new(docid))?
+                .ok_or(UserError::UnknownInternalDocumentId { document_id: docid })?;
+            let obkv = codec.decompress(bytes)?;
+            let document = obkv_to_json(&all_fields, &fields_ids_map, obkv.as_obkv())?;
+            let primary_key_value = document.get(&primary_key).cloned();
+
+            match function(document.clone())? {
+                Some(mut new_document) if new_document != document => {
+                    match primary_key_value {
+                        Some(value) => new_document.insert(primary_key.clone(), value),
+                        None => new_document.remove(&primary_key),
+                    };
+                    updated_documents.push(Value::Object(new_document));
+                }
+                Some(_unchanged) => (),
+                None => {
+                    deleted_documents_ids.insert(docid);
+                }
+            }
+        }
+
+        let deleted_documents = if deleted_documents_ids.is_empty() {
+            0
+        } else {
+            let mut builder = DeleteDocuments::new(self.wtxn, self.index)?;
+            builder.delete_documents(&deleted_documents_ids);
+            builder.execute()?.deleted_documents
+        };
+
+        let updated_documents_count = updated_documents.len() as u64;
+        if !updated_documents.is_empty() {
+            // The modified documents are entirely replaced, the fields removed
+            // by the function are removed from the documents.
+            let mut writer = Cursor::new(Vec::new());
+            let mut builder = DocumentBatchBuilder::new(&mut writer)?;
+            let json = serde_json::to_vec(&updated_documents).map_err(InternalError::SerdeJson)?;
+            builder.extend_from_json(Cursor::new(json))?;
+            builder.finish()?;
+            writer.set_position(0);
+            let reader = DocumentBatchReader::from_reader(writer)?;
+
+            let config = IndexDocumentsConfig::default();
+            let mut builder = IndexDocuments::new(
+                self.wtxn,
+                self.index,
+                self.indexer_config,
+                config,
+                self.progress,
+            );
+            builder.add_documents(reader)?;
+            builder.execute()?;
+        }
+
+        Ok(DocumentsUpdateResult {
+            updated_documents: updated_documents_count,
+            deleted_documents,
+            number_of_documents: self.index.number_of_documents(self.wtxn)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+    use serde_json::json;
+
+    use super::*;
+    use crate::update::Settings;
+
+    #[test]
+    fn update_documents_by_function() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("shop") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "table", "shop": "a", "price": 100 },
+            { "id": 1, "name": "chair", "shop": "a", "price": 50, "discontinued": true },
+            { "id": 2, "name": "lamp", "shop": "a", "price": 20 },
+            { "id": 3, "name": "bed", "shop": "b", "price": 300 }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // Raises the prices of the shop a, removes the discontinued products
+        // and leaves the cheap ones untouched.
+        let mut builder = UpdateDocumentsByFunction::new(&mut wtxn, &index, &config, |_| ());
+        builder.filter(&Filter::from_str("shop = a").unwrap().unwrap()).unwrap();
+        let result = builder
+            .execute(|mut document| {
+                if document.contains_key("discontinued") {
+                    return Ok(None);
+                }
+                let price = document["price"].as_u64().unwrap();
+                if price > 30 {
+                    document.insert(S("price"), json!(price * 2));
+                    // The primary key can't be changed.
+                    document.insert(S("id"), json!(42));
+                }
+                Ok(Some(document))
+            })
+            .unwrap();
+
+        assert_eq!(
+            result,
+            DocumentsUpdateResult {
+                updated_documents: 1,
+                deleted_documents: 1,
+                number_of_documents: 3
+            }
+        );
+
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let all_fields: Vec<_> = fields_ids_map.ids().collect();
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert!(external_documents_ids.get("1").is_none());
+        assert!(external_documents_ids.get("42").is_none());
+
+        let price = |id| {
+            let docid = external_documents_ids.get(id).unwrap();
+            let (_, obkv) = index.documents(&rtxn, Some(docid)).unwrap().remove(0);
+            obkv_to_json(&all_fields, &fields_ids_map, obkv.as_obkv()).unwrap()["price"].clone()
+        };
+        assert_eq!(price("0"), json!(200));
+        assert_eq!(price("2"), json!(20));
+        assert_eq!(price("3"), json!(300));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, HashSet};
+
+use heed::types::ByteSlice;
+use serde_json::Value;
+
+use super::Facets;
+use crate::error::{FieldIdMapMissingEntry, InternalError, UserError};
+use crate::heed_codec::facet::{FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec};
+use crate::index::db_name;
+use crate::{DocumentId, FieldId, Index, Result, BEU32};
+
+/// Changes the numeric values of some fields of the documents without reindexing them,
+/// e.g. to refresh the popularity of the documents every night.
+///
+/// Only the stored documents and the facet databases are written, the words databases are
+/// never touched, this is why the fields must be faceted and must not be searchable.
+pub struct UpdateNumericFields<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    updates: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+impl<'t, 'u, 'i> UpdateNumericFields<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+    ) -> UpdateNumericFields<'t, 'u, 'i> {
+        UpdateNumericFields { wtxn, index, updates: BTreeMap::new() }
+    }
+
+    /// Sets the value of a field of the document with this external id.
+    pub fn update_field(
+        &mut self,
+        external_id: impl Into<String>,
+        field: impl Into<String>,
+        value: f64,
+    ) -> &mut Self {
+        self.updates.entry(external_id.into()).or_default().insert(field.into(), value);
+        self
+    }
+
+    /// Writes the new values and returns the number of updated documents.
+    pub fn execute(self) -> Result<u64> {
+        if self.updates.is_empty() {
+            return Ok(0);
+        }
+
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let searchable_fields = self.index.searchable_fields_ids(self.wtxn)?;
+        let documents_ids = self.index.documents_ids(self.wtxn)?;
+
+        // We validate all the updates before writing anything.
+        let mut updates = Vec::with_capacity(self.updates.len());
+        let external_documents_ids = self.index.external_documents_ids(self.wtxn)?;
+        for (external_id, fields) in &self.updates {
+            let docid = match external_documents_ids.get(external_id) {
+                Some(docid) if documents_ids.contains(docid) => docid,
+                _ => {
+                    let document_id = external_id.clone();
+                    return Err(UserError::UnknownExternalDocumentId { document_id }.into());
+                }
+            };
+
+            let mut values = BTreeMap::new();
+            for (name, &value) in fields {
+                let field_id = match fields_ids_map.id(name) {
+                    Some(field_id) if faceted_fields.contains(&field_id) => field_id,
+                    _ => return Err(invalid_update(name, "it is not filterable nor sortable")),
+                };
+                if searchable_fields.as_ref().map_or(true, |fields| fields.contains(&field_id)) {
+                    return Err(invalid_update(name, "it is searchable"));
+                }
+                if !value.is_finite() {
+                    return Err(invalid_update(name, "the value is not a finite number"));
+                }
+                values.insert(field_id, value);
+            }
+            updates.push((docid, values));
+        }
+        drop(external_documents_ids);
+        self.index.ensure_documents_stored(self.wtxn, "update the numeric fields")?;
+
+        let mut field_distribution = self.index.field_distribution(self.wtxn)?;
+        let mut field_types_distribution = self.index.field_types_distribution(self.wtxn)?;
+        let mut updated_fields = HashSet::new();
+        let mut buffer = Vec::new();
+        let mut codec = self.index.documents_codec(self.wtxn)?;
+        for (docid, values) in &updates {
+            let key = BEU32::new(*docid);
+            let bytes =
+                self.index.documents.remap_data_type::<ByteSlice>().get(self.wtxn, &key)?.ok_or(
+                    InternalError::DatabaseMissingEntry { db_name: db_name::DOCUMENTS, key: None },
+                )?;
+            let obkv = codec.decompress(bytes)?;
+            let mut document: BTreeMap<FieldId, Vec<u8>> =
+                obkv.iter().map(|(field_id, value)| (field_id, value.to_vec())).collect();
+
+            for (&field_id, &value) in values {
+                let bytes =
+                    serde_json::to_vec(&json_number(value)).map_err(InternalError::SerdeJson)?;
+                let name =
+                    fields_ids_map.name(field_id).ok_or(FieldIdMapMissingEntry::FieldId {
+                        field_id,
+                        process: "UpdateNumericFields",
+                    })?;
+                let field_types = field_types_distribution.entry(name.to_string()).or_default();
+                match document.insert(field_id, bytes) {
+                    Some(old_bytes) => {
+                        let old_value: Value =
+                            serde_json::from_slice(&old_bytes).map_err(InternalError::SerdeJson)?;
+                        field_types.remove(&old_value);
+                    }
+                    None => *field_distribution.entry(name.to_string()).or_default() += 1,
+                }
+                field_types.add(&json_number(value));
+
+                remove_facet_values(self.wtxn, self.index, field_id, *docid)?;
+                add_facet_number(self.wtxn, self.index, field_id, *docid, value)?;
+                updated_fields.insert(field_id);
+            }
+
+            buffer.clear();
+            let mut writer = obkv::KvWriter::<_, FieldId>::new(&mut buffer);
+            for (field_id, value) in &document {
+                writer.insert(*field_id, value)?;
+            }
+            writer.finish()?;
+            let bytes = codec.compress(&buffer)?;
+            self.index.documents.remap_data_type::<ByteSlice>().put(self.wtxn, &key, &bytes)?;
+        }
+
+        self.index.put_field_distribution(self.wtxn, &field_distribution)?;
+        self.index.put_field_types_distribution(self.wtxn, &field_types_distribution)?;
+
+        // The facet levels and the faceted documents ids are recomputed from the level 0.
+        let mut builder = Facets::new(self.wtxn, self.index);
+        builder.fields_ids = Some(updated_fields);
+        builder.execute()?;
+
+        Ok(updates.len() as u64)
+    }
+}
+
+fn invalid_update(field: &str, message: &'static str) -> crate::Error {
+    UserError::InvalidNumericFieldUpdate { field: field.to_string(), message }.into()
+}
+
+/// Keeps the integers as integers in the stored documents.
+fn json_number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() <= (1u64 << f64::MANTISSA_DIGITS) as f64 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+/// Removes the document from the level 0 of the facet databases of this field, the numbers
+/// and the strings values are removed as the previous value can be of any type.
+fn remove_facet_values(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    field_id: FieldId,
+    docid: DocumentId,
+) -> Result<()> {
+    let mut prefix = field_id.to_be_bytes().to_vec();
+    prefix.extend_from_slice(&docid.to_be_bytes());
+
+    let mut numbers = Vec::new();
+    let mut iter = index
+        .field_id_docid_facet_f64s
+        .remap_key_type::<ByteSlice>()
+        .prefix_iter_mut(wtxn, &prefix)?
+        .remap_key_type::<FieldDocIdFacetF64Codec>();
+    while let Some(result) = iter.next() {
+        let ((_, _, number), ()) = result?;
+        numbers.push(number);
+        // safety: we don't keep references from inside the LMDB database.
+        unsafe { iter.del_current()? };
+    }
+    drop(iter);
+
+    for number in numbers {
+        let key = (field_id, 0, number, number);
+        if let Some(mut docids) = index.facet_id_f64_docids.get(wtxn, &key)? {
+            docids.remove(docid);
+            if docids.is_empty() {
+                index.facet_id_f64_docids.delete(wtxn, &key)?;
+            } else {
+                index.facet_id_f64_docids.put(wtxn, &key, &docids)?;
+            }
+        }
+    }
+
+    let mut strings = Vec::new();
+    let mut iter = index
+        .field_id_docid_facet_strings
+        .remap_key_type::<ByteSlice>()
+        .prefix_iter_mut(wtxn, &prefix)?
+        .remap_key_type::<FieldDocIdFacetStringCodec>();
+    while let Some(result) = iter.next() {
+        let ((_, _, normalized), _) = result?;
+        strings.push(normalized.to_string());
+        // safety: we don't keep references from inside the LMDB database.
+        unsafe { iter.del_current()? };
+    }
+    drop(iter);
+
+    for normalized in strings {
+        let key = (field_id, normalized.as_str());
+        if let Some((original, mut docids)) = index.facet_id_string_docids.get(wtxn, &key)? {
+            let original = original.to_string();
+            docids.remove(docid);
+            if docids.is_empty() {
+                index.facet_id_string_docids.delete(wtxn, &key)?;
+            } else {
+                index.facet_id_string_docids.put(wtxn, &key, &(original.as_str(), docids))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the document to the level 0 of the facet number database of this field.
+fn add_facet_number(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    field_id: FieldId,
+    docid: DocumentId,
+    number: f64,
+) -> Result<()> {
+    index.field_id_docid_facet_f64s.put(wtxn, &(field_id, docid, number), &())?;
+
+    let key = (field_id, 0, number, number);
+    let mut docids = index.facet_id_f64_docids.get(wtxn, &key)?.unwrap_or_default();
+    docids.insert(docid);
+    index.facet_id_f64_docids.put(wtxn, &key, &docids)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::{obkv_to_json, Filter};
+
+    #[test]
+    fn update_popularity_without_reindexing() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title")]);
+        builder.set_filterable_fields(hashset! { S("popularity"), S("title") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "hello", "popularity": 12 },
+            { "id": 1, "title": "world", "popularity": "unknown" },
+            { "id": 2, "title": "hello world" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let words_before = index.word_docids.len(&wtxn).unwrap();
+
+        let mut builder = UpdateNumericFields::new(&mut wtxn, &index);
+        builder.update_field("0", "popularity", 42.0);
+        builder.update_field("1", "popularity", 7.5);
+        builder.update_field("2", "popularity", 42.0);
+        assert_eq!(builder.execute().unwrap(), 3);
+
+        let filter = Filter::from_str("popularity = 42").unwrap().unwrap();
+        let docids = filter.evaluate(&wtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2]);
+        let filter = Filter::from_str("popularity < 10").unwrap().unwrap();
+        let docids = filter.evaluate(&wtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+        let filter = Filter::from_str("popularity = 12 OR popularity = unknown").unwrap().unwrap();
+        assert!(filter.evaluate(&wtxn, &index).unwrap().is_empty());
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let (_, obkv) = index.documents(&wtxn, Some(2)).unwrap().pop().unwrap();
+        let document = obkv_to_json(
+            &fields_ids_map.ids().collect::<Vec<_>>(),
+            &fields_ids_map,
+            obkv.as_obkv(),
+        )
+        .unwrap();
+        assert_eq!(document["popularity"], serde_json::json!(42));
+        assert_eq!(index.field_distribution(&wtxn).unwrap().get("popularity"), Some(&3));
+        assert_eq!(index.word_docids.len(&wtxn).unwrap(), words_before);
+
+        // The searchable fields can't be updated without reindexing the words.
+        let mut builder = UpdateNumericFields::new(&mut wtxn, &index);
+        builder.update_field("0", "title", 1.0);
+        assert!(builder.execute().is_err());
+    }
+}
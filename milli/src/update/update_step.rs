@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use UpdateIndexingStep::*;
 
 #[derive(Debug, Clone, Copy)]
@@ -17,6 +19,26 @@ pub enum UpdateIndexingStep {
     /// Merge the previously extracted data (words and facets) into the final LMDB database.
     /// These extracted data are split into multiple databases.
     MergeDataIntoFinalDatabase { databases_seen: usize, total_databases: usize },
+
+    /// The data of a database was extracted from the documents, `chunks` is the number of
+    /// chunks extracted from the documents chunks, spilled on disk for a total of
+    /// `spilled_bytes`, and merged into `bytes`. The `merge_conflicts` are the number of
+    /// times a key was merged with another value of the same key, when they are counted.
+    ExtractDatabase {
+        database: &'static str,
+        chunks: usize,
+        spilled_bytes: u64,
+        bytes: u64,
+        merge_conflicts: Option<u64>,
+        duration: Duration,
+    },
+
+    /// The size of the chunks the documents are split into to be extracted in parallel,
+    /// `tuned` when it was chosen from the documents and the machine.
+    DocumentsChunkSize { documents_chunk_size: usize, tuned: bool },
+
+    /// A chunk of extracted data of `bytes` was written into an LMDB database.
+    WriteDatabase { database: &'static str, bytes: u64, duration: Duration },
 }
 
 impl UpdateIndexingStep {
@@ -24,8 +46,8 @@ impl UpdateIndexingStep {
         match self {
             RemapDocumentAddition { .. } => 0,
             ComputeIdsAndMergeDocuments { .. } => 1,
-            IndexDocuments { .. } => 2,
-            MergeDataIntoFinalDatabase { .. } => 3,
+            IndexDocuments { .. } | DocumentsChunkSize { .. } | ExtractDatabase { .. } => 2,
+            MergeDataIntoFinalDatabase { .. } | WriteDatabase { .. } => 3,
         }
     }
 
@@ -2,6 +2,7 @@ use std::collections::{HashMap, HashSet};
 
 use grenad::{CompressionType, MergerBuilder};
 use heed::types::ByteSlice;
+use roaring::RoaringBitmap;
 
 use crate::update::index_documents::{
     create_sorter, merge_roaring_bitmaps, sorter_into_lmdb_database, CursorClonableMmap, MergeFn,
@@ -56,13 +57,18 @@ impl<'t, 'u, 'i> WordPrefixDocids<'t, 'u, 'i> {
         }
         let mut word_docids_iter = word_docids_merger.build().into_stream_merger_iter()?;
 
+        let mut buffer = Vec::new();
         let mut current_prefixes: Option<&&[String]> = None;
         let mut prefixes_cache = HashMap::new();
         while let Some((word, data)) = word_docids_iter.next()? {
             current_prefixes = match current_prefixes.take() {
                 Some(prefixes) if word.starts_with(&prefixes[0].as_bytes()) => Some(prefixes),
                 _otherwise => {
-                    write_prefixes_in_sorter(&mut prefixes_cache, &mut prefix_docids_sorter)?;
+                    write_prefixes_in_sorter(
+                        &mut prefixes_cache,
+                        &mut prefix_docids_sorter,
+                        &mut buffer,
+                    )?;
                     common_prefix_fst_words
                         .iter()
                         .find(|prefixes| word.starts_with(&prefixes[0].as_bytes()))
@@ -70,12 +76,13 @@ impl<'t, 'u, 'i> WordPrefixDocids<'t, 'u, 'i> {
             };
 
             if let Some(prefixes) = current_prefixes {
+                let docids = RoaringBitmap::deserialize_from(data)?;
                 for prefix in prefixes.iter() {
                     if word.starts_with(prefix.as_bytes()) {
                         match prefixes_cache.get_mut(prefix.as_bytes()) {
-                            Some(value) => value.push(data.to_owned()),
+                            Some(value) => *value |= &docids,
                             None => {
-                                prefixes_cache.insert(prefix.clone().into(), vec![data.to_owned()]);
+                                prefixes_cache.insert(prefix.clone().into(), docids.clone());
                             }
                         }
                     }
@@ -83,7 +90,7 @@ impl<'t, 'u, 'i> WordPrefixDocids<'t, 'u, 'i> {
             }
         }
 
-        write_prefixes_in_sorter(&mut prefixes_cache, &mut prefix_docids_sorter)?;
+        write_prefixes_in_sorter(&mut prefixes_cache, &mut prefix_docids_sorter, &mut buffer)?;
 
         // We fetch the docids associated to the newly added word prefix fst only.
         let db = self.index.word_docids.remap_data_type::<ByteSlice>();
@@ -117,15 +124,64 @@ impl<'t, 'u, 'i> WordPrefixDocids<'t, 'u, 'i> {
     }
 }
 
+/// Writes the docids of the prefixes into the sorter, the bitmaps are merged in the cache
+/// while the words are read and serialized one by one into the buffer.
 fn write_prefixes_in_sorter(
-    prefixes: &mut HashMap<Vec<u8>, Vec<Vec<u8>>>,
+    prefixes: &mut HashMap<Vec<u8>, RoaringBitmap>,
     sorter: &mut grenad::Sorter<MergeFn>,
+    buffer: &mut Vec<u8>,
 ) -> Result<()> {
-    for (key, data_slices) in prefixes.drain() {
-        for data in data_slices {
-            sorter.insert(&key, data)?;
-        }
+    for (key, docids) in prefixes.drain() {
+        buffer.clear();
+        docids.serialize_into(&mut *buffer)?;
+        sorter.insert(&key, buffer.as_slice())?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn prefix_docids_are_merged() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // There must be enough words starting with "hel" for it to be a prefix.
+        let words: Vec<_> =
+            ('a'..='z').flat_map(|a| ('a'..='b').map(move |b| format!("hel{}{}", a, b))).collect();
+        let words = words.join(" ");
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 0, "text": words }, { "id": 1, "text": "hello" }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The docids of the new words are merged with the ones of the existing prefixes.
+        let content = documents!([{ "id": 2, "text": "helaa help" }, { "id": 3, "text": "help" }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let prefixes = index.words_prefixes_fst(&wtxn).unwrap();
+        assert!(prefixes.contains("hel"));
+        let mut expected = RoaringBitmap::new();
+        for result in index.word_docids.prefix_iter(&wtxn, "hel").unwrap() {
+            expected |= result.unwrap().1;
+        }
+        let docids = index.word_prefix_docids.get(&wtxn, "hel").unwrap().unwrap();
+        assert_eq!(docids, expected);
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+}
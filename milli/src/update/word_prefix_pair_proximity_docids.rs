@@ -3,15 +3,39 @@ use std::collections::{HashMap, HashSet};
 use grenad::{CompressionType, MergerBuilder};
 use heed::types::ByteSlice;
 use heed::BytesDecode;
-use log::debug;
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
 use slice_group_by::GroupBy;
+use tracing::debug;
 
+use crate::heed_codec::CboRoaringBitmapCodec;
 use crate::update::index_documents::{
     create_sorter, merge_cbo_roaring_bitmaps, sorter_into_lmdb_database, CursorClonableMmap,
     MergeFn,
 };
 use crate::{Index, Result, StrStrU8Codec};
 
+/// The bounds of the word prefix pairs stored in the word prefix pair proximity database,
+/// the pairs out of these bounds are computed from the word pairs at query time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefixPairProximityCaps {
+    pub max_proximity: u8,
+    pub max_prefix_length: usize,
+}
+
+impl Default for PrefixPairProximityCaps {
+    fn default() -> PrefixPairProximityCaps {
+        PrefixPairProximityCaps { max_proximity: 4, max_prefix_length: 2 }
+    }
+}
+
+impl PrefixPairProximityCaps {
+    /// Whether the pairs of a word and this prefix at this proximity are stored in the database.
+    pub fn contains(&self, prefix: &str, proximity: u8) -> bool {
+        proximity <= self.max_proximity && prefix.len() <= self.max_prefix_length
+    }
+}
+
 pub struct WordPrefixPairProximityDocids<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
@@ -19,8 +43,7 @@ pub struct WordPrefixPairProximityDocids<'t, 'u, 'i> {
     pub(crate) chunk_compression_level: Option<u32>,
     pub(crate) max_nb_chunks: Option<usize>,
     pub(crate) max_memory: Option<usize>,
-    max_proximity: u8,
-    max_prefix_length: usize,
+    caps: PrefixPairProximityCaps,
 }
 
 impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
@@ -35,8 +58,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
             chunk_compression_level: None,
             max_nb_chunks: None,
             max_memory: None,
-            max_proximity: 4,
-            max_prefix_length: 2,
+            caps: PrefixPairProximityCaps::default(),
         }
     }
 
@@ -47,7 +69,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
     /// Default value is 4. This value must be lower or equal than 7 and will be clamped
     /// to this bound otherwise.
     pub fn max_proximity(&mut self, value: u8) -> &mut Self {
-        self.max_proximity = value.max(7);
+        self.caps.max_proximity = value.min(7);
         self
     }
 
@@ -57,7 +79,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
     ///
     /// Default value is 2.
     pub fn max_prefix_length(&mut self, value: usize) -> &mut Self {
-        self.max_prefix_length = value;
+        self.caps.max_prefix_length = value;
         self
     }
 
@@ -71,6 +93,18 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
     ) -> Result<()> {
         debug!("Computing and writing the word prefix pair proximity docids into LMDB on disk...");
 
+        // The pairs of the documents already indexed are only stored up to the previous caps,
+        // the search can only rely on the database for the pairs under both of them.
+        let caps = if self.index.word_prefix_pair_proximity_docids.is_empty(self.wtxn)? {
+            self.caps
+        } else {
+            let previous = self.index.prefix_pair_proximity_caps(self.wtxn)?;
+            PrefixPairProximityCaps {
+                max_proximity: previous.max_proximity.min(self.caps.max_proximity),
+                max_prefix_length: previous.max_prefix_length.min(self.caps.max_prefix_length),
+            }
+        };
+
         let new_prefix_fst_words: Vec<_> =
             new_prefix_fst_words.linear_group_by_key(|x| x.chars().nth(0).unwrap()).collect();
 
@@ -97,7 +131,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
         let mut prefixes_cache = HashMap::new();
         while let Some((key, data)) = wppd_iter.next()? {
             let (w1, w2, prox) = StrStrU8Codec::bytes_decode(key).ok_or(heed::Error::Decoding)?;
-            if prox > self.max_proximity {
+            if prox > self.caps.max_proximity {
                 continue;
             }
 
@@ -107,7 +141,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
                 &mut prefixes_cache,
                 &mut word_prefix_pair_proximity_docids_sorter,
                 common_prefix_fst_words,
-                self.max_prefix_length,
+                self.caps.max_prefix_length,
                 w1,
                 w2,
                 prox,
@@ -118,6 +152,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
         write_prefixes_in_sorter(
             &mut prefixes_cache,
             &mut word_prefix_pair_proximity_docids_sorter,
+            &mut buffer,
         )?;
 
         // We compute the prefix docids associated with the newly added prefixes
@@ -129,7 +164,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
         let mut current_prefixes: Option<&&[String]> = None;
         let mut prefixes_cache = HashMap::new();
         while let Some(((w1, w2, prox), data)) = db_iter.next().transpose()? {
-            if prox > self.max_proximity {
+            if prox > self.caps.max_proximity {
                 continue;
             }
 
@@ -139,7 +174,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
                 &mut prefixes_cache,
                 &mut word_prefix_pair_proximity_docids_sorter,
                 &new_prefix_fst_words,
-                self.max_prefix_length,
+                self.caps.max_prefix_length,
                 w1,
                 w2,
                 prox,
@@ -150,6 +185,7 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
         write_prefixes_in_sorter(
             &mut prefixes_cache,
             &mut word_prefix_pair_proximity_docids_sorter,
+            &mut buffer,
         )?;
 
         drop(db_iter);
@@ -179,18 +215,23 @@ impl<'t, 'u, 'i> WordPrefixPairProximityDocids<'t, 'u, 'i> {
             merge_cbo_roaring_bitmaps,
         )?;
 
+        self.index.put_prefix_pair_proximity_caps(self.wtxn, caps)?;
+
         Ok(())
     }
 }
 
+/// Writes the docids of the prefixes into the sorter, the bitmaps are merged in the cache
+/// while the words are read and serialized one by one into the buffer.
 fn write_prefixes_in_sorter(
-    prefixes: &mut HashMap<Vec<u8>, Vec<Vec<u8>>>,
+    prefixes: &mut HashMap<Vec<u8>, RoaringBitmap>,
     sorter: &mut grenad::Sorter<MergeFn>,
+    buffer: &mut Vec<u8>,
 ) -> Result<()> {
-    for (key, data_slices) in prefixes.drain() {
-        for data in data_slices {
-            sorter.insert(&key, data)?;
-        }
+    for (key, docids) in prefixes.drain() {
+        buffer.clear();
+        CboRoaringBitmapCodec::serialize_into(&docids, buffer);
+        sorter.insert(&key, buffer.as_slice())?;
     }
 
     Ok(())
@@ -204,7 +245,7 @@ fn write_prefixes_in_sorter(
 fn insert_current_prefix_data_in_sorter<'a>(
     buffer: &mut Vec<u8>,
     current_prefixes: &mut Option<&'a &'a [String]>,
-    prefixes_cache: &mut HashMap<Vec<u8>, Vec<Vec<u8>>>,
+    prefixes_cache: &mut HashMap<Vec<u8>, RoaringBitmap>,
     word_prefix_pair_proximity_docids_sorter: &mut grenad::Sorter<MergeFn>,
     prefix_fst_keys: &'a [&'a [std::string::String]],
     max_prefix_length: usize,
@@ -216,12 +257,17 @@ fn insert_current_prefix_data_in_sorter<'a>(
     *current_prefixes = match current_prefixes.take() {
         Some(prefixes) if w2.starts_with(&prefixes[0]) => Some(prefixes),
         _otherwise => {
-            write_prefixes_in_sorter(prefixes_cache, word_prefix_pair_proximity_docids_sorter)?;
+            write_prefixes_in_sorter(
+                prefixes_cache,
+                word_prefix_pair_proximity_docids_sorter,
+                buffer,
+            )?;
             prefix_fst_keys.iter().find(|prefixes| w2.starts_with(&prefixes[0]))
         }
     };
 
     if let Some(prefixes) = current_prefixes {
+        let docids = CboRoaringBitmapCodec::deserialize_from(data)?;
         buffer.clear();
         buffer.extend_from_slice(w1.as_bytes());
         buffer.push(0);
@@ -232,9 +278,9 @@ fn insert_current_prefix_data_in_sorter<'a>(
                 buffer.push(prox);
 
                 match prefixes_cache.get_mut(buffer.as_slice()) {
-                    Some(value) => value.push(data.to_owned()),
+                    Some(value) => *value |= &docids,
                     None => {
-                        prefixes_cache.insert(buffer.clone(), vec![data.to_owned()]);
+                        prefixes_cache.insert(buffer.clone(), docids.clone());
                     }
                 }
             }
@@ -243,3 +289,72 @@ fn insert_current_prefix_data_in_sorter<'a>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn capped_prefix_pairs() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // There must be enough words starting with "hel" for it to be a prefix.
+        let words: Vec<_> =
+            ('a'..='z').flat_map(|a| ('a'..='b').map(move |b| format!("hel{}{}", a, b))).collect();
+        let words = words.join(" ");
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "text": "world hello" },
+            { "id": 1, "text": "world big red hello" },
+            { "id": 2, "text": words }
+        ]);
+        let indexing_config = IndexDocumentsConfig {
+            prefix_pair_max_proximity: Some(1),
+            prefix_pair_max_prefix_length: Some(3),
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ());
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let caps = PrefixPairProximityCaps { max_proximity: 1, max_prefix_length: 3 };
+        assert_eq!(index.prefix_pair_proximity_caps(&wtxn).unwrap(), caps);
+        let db = index.word_prefix_pair_proximity_docids;
+        assert!(db.get(&wtxn, &("world", "hel", 1)).unwrap().is_some());
+        assert!(db.get(&wtxn, &("world", "hel", 3)).unwrap().is_none());
+        for result in db.iter(&wtxn).unwrap() {
+            let ((_, prefix, proximity), _) = result.unwrap();
+            assert!(caps.contains(prefix, proximity));
+        }
+
+        // The pairs over the caps are computed from the word pairs.
+        let result = index.search(&wtxn).query("world hel").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+
+        // The caps can only shrink as the documents already indexed don't have the
+        // pairs of the bigger ones.
+        let content = documents!([{ "id": 3, "text": "world of hello" }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        );
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        let caps = PrefixPairProximityCaps { max_proximity: 1, max_prefix_length: 2 };
+        assert_eq!(index.prefix_pair_proximity_caps(&wtxn).unwrap(), caps);
+
+        let result = index.search(&wtxn).query("world hel").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 3, 1]);
+    }
+}
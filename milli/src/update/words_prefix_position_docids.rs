@@ -5,10 +5,11 @@ use std::{cmp, str};
 use grenad::{CompressionType, MergerBuilder};
 use heed::types::ByteSlice;
 use heed::{BytesDecode, BytesEncode};
-use log::debug;
+use roaring::RoaringBitmap;
+use tracing::debug;
 
 use crate::error::SerializationError;
-use crate::heed_codec::StrBEU32Codec;
+use crate::heed_codec::{CboRoaringBitmapCodec, StrBEU32Codec};
 use crate::index::main_key::WORDS_PREFIXES_FST_KEY;
 use crate::update::index_documents::{
     create_sorter, merge_cbo_roaring_bitmaps, sorter_into_lmdb_database, CursorClonableMmap,
@@ -81,6 +82,7 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
 
         // We fetch all the new common prefixes between the previous and new prefix fst.
         let mut buffer = Vec::new();
+        let mut value_buffer = Vec::new();
         let mut current_prefixes: Option<&&[String]> = None;
         let mut prefixes_cache = HashMap::new();
         while let Some((key, data)) = word_position_docids_iter.next()? {
@@ -92,21 +94,23 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
                     write_prefixes_in_sorter(
                         &mut prefixes_cache,
                         &mut prefix_position_docids_sorter,
+                        &mut value_buffer,
                     )?;
                     common_prefix_fst_words.iter().find(|prefixes| word.starts_with(&prefixes[0]))
                 }
             };
 
             if let Some(prefixes) = current_prefixes {
+                let docids = CboRoaringBitmapCodec::deserialize_from(data)?;
                 for prefix in prefixes.iter() {
                     if word.starts_with(prefix) {
                         buffer.clear();
                         buffer.extend_from_slice(prefix.as_bytes());
                         buffer.extend_from_slice(&pos.to_be_bytes());
                         match prefixes_cache.get_mut(&buffer) {
-                            Some(value) => value.push(data.to_owned()),
+                            Some(value) => *value |= &docids,
                             None => {
-                                prefixes_cache.insert(buffer.clone(), vec![data.to_owned()]);
+                                prefixes_cache.insert(buffer.clone(), docids.clone());
                             }
                         }
                     }
@@ -114,7 +118,11 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
             }
         }
 
-        write_prefixes_in_sorter(&mut prefixes_cache, &mut prefix_position_docids_sorter)?;
+        write_prefixes_in_sorter(
+            &mut prefixes_cache,
+            &mut prefix_position_docids_sorter,
+            &mut value_buffer,
+        )?;
 
         // We fetch the docids associated to the newly added word prefix fst only.
         let db = self.index.word_position_docids.remap_data_type::<ByteSlice>();
@@ -162,14 +170,17 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
     }
 }
 
+/// Writes the docids of the prefixes into the sorter, the bitmaps are merged in the cache
+/// while the words are read and serialized one by one into the buffer.
 fn write_prefixes_in_sorter(
-    prefixes: &mut HashMap<Vec<u8>, Vec<Vec<u8>>>,
+    prefixes: &mut HashMap<Vec<u8>, RoaringBitmap>,
     sorter: &mut grenad::Sorter<MergeFn>,
+    buffer: &mut Vec<u8>,
 ) -> Result<()> {
-    for (key, data_slices) in prefixes.drain() {
-        for data in data_slices {
-            sorter.insert(&key, data)?;
-        }
+    for (key, docids) in prefixes.drain() {
+        buffer.clear();
+        CboRoaringBitmapCodec::serialize_into(&docids, buffer);
+        sorter.insert(&key, buffer.as_slice())?;
     }
 
     Ok(())
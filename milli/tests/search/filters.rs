@@ -1,5 +1,6 @@
+use big_s::S;
 use either::{Either, Left, Right};
-use milli::{Criterion, Filter, Search, SearchResult};
+use milli::{Criterion, Filter, RelaxationHint, Search, SearchResult};
 use Criterion::*;
 
 use crate::search::{self, EXTERNAL_DOCUMENTS_IDS};
@@ -77,3 +78,53 @@ test_filter!(
     lower_complex_filter_2,
     vec![Left(vec!["tag=red", "tag=green"]), Left(vec!["asc_desc_rank<3", "asc_desc_rank<1"])]
 );
+
+#[test]
+fn relaxation_hints() {
+    let criteria = vec![Words, Typo, Proximity, Attribute, Exactness];
+    let index = search::setup_search_index_with_criteria(&criteria);
+    let rtxn = index.read_txn().unwrap();
+
+    let mut search = Search::new(&rtxn, &index);
+    search.query("hello");
+    search.filter(Filter::from_str("tag = green AND asc_desc_rank = 5").unwrap().unwrap());
+    search.relaxation_hints(true);
+    let SearchResult { documents_ids, relaxation_hints, .. } = search.execute().unwrap();
+    assert!(documents_ids.is_empty());
+
+    let attributes: Vec<_> = relaxation_hints
+        .iter()
+        .map(|hint| match hint {
+            RelaxationHint::RemoveFilter { attributes, candidates_count } => {
+                assert_ne!(*candidates_count, 0);
+                attributes.clone()
+            }
+            hint => panic!("unexpected hint {:?}", hint),
+        })
+        .collect();
+    assert_eq!(attributes, vec![vec![S("tag")], vec![S("asc_desc_rank")]]);
+
+    let mut search = Search::new(&rtxn, &index);
+    search.query("hello zzzyyyxxx");
+    search.optional_words(false);
+    search.relaxation_hints(true);
+    let SearchResult { documents_ids, relaxation_hints, .. } = search.execute().unwrap();
+    assert!(documents_ids.is_empty());
+
+    match relaxation_hints.as_slice() {
+        [RelaxationHint::DropWord { word, query, candidates_count }] => {
+            assert_eq!(word, "zzzyyyxxx");
+            assert_eq!(query, "hello");
+            assert_ne!(*candidates_count, 0);
+        }
+        hints => panic!("unexpected hints {:?}", hints),
+    }
+
+    // The hints are only computed when the search returns no document.
+    let mut search = Search::new(&rtxn, &index);
+    search.query("hello");
+    search.relaxation_hints(true);
+    let SearchResult { documents_ids, relaxation_hints, .. } = search.execute().unwrap();
+    assert!(!documents_ids.is_empty());
+    assert!(relaxation_hints.is_empty());
+}
@@ -446,16 +446,16 @@ fn criteria_ascdesc() {
 
         let expected_document_ids = match criterion {
             Asc(field_name) if field_name == "name" => {
-                documents.iter().sorted_by_key(|(_, obkv)| obkv.get(0).unwrap())
+                documents.iter().sorted_by_key(|(_, obkv)| obkv.get(0).unwrap().to_vec())
             }
             Desc(field_name) if field_name == "name" => {
-                documents.iter().sorted_by_key(|(_, obkv)| Reverse(obkv.get(0).unwrap()))
+                documents.iter().sorted_by_key(|(_, obkv)| Reverse(obkv.get(0).unwrap().to_vec()))
             }
             Asc(field_name) if field_name == "name" => {
-                documents.iter().sorted_by_key(|(_, obkv)| obkv.get(1).unwrap())
+                documents.iter().sorted_by_key(|(_, obkv)| obkv.get(1).unwrap().to_vec())
             }
             Desc(field_name) if field_name == "name" => {
-                documents.iter().sorted_by_key(|(_, obkv)| Reverse(obkv.get(1).unwrap()))
+                documents.iter().sorted_by_key(|(_, obkv)| Reverse(obkv.get(1).unwrap().to_vec()))
             }
             _ => continue,
         }
@@ -465,3 +465,30 @@ fn criteria_ascdesc() {
         assert_eq!(documents_ids, expected_document_ids);
     }
 }
+
+#[test]
+fn corrected_query_results() {
+    let criteria = vec![Words, Typo, Proximity, Attribute, Exactness];
+    let index = search::setup_search_index_with_criteria(&criteria);
+    let rtxn = index.read_txn().unwrap();
+
+    let mut search = Search::new(&rtxn, &index);
+    search.query("amrica");
+    search.authorize_typos(false);
+    search.correction_threshold(1);
+    let SearchResult { documents_ids, corrected, .. } = search.execute().unwrap();
+    assert!(documents_ids.is_empty());
+
+    let corrected = corrected.unwrap();
+    assert_eq!(corrected.query, "america");
+    assert_eq!(corrected.typos, 1);
+    assert!(!corrected.result.documents_ids.is_empty());
+
+    // The corrected search is only executed below the threshold.
+    let mut search = Search::new(&rtxn, &index);
+    search.query("america");
+    search.correction_threshold(1);
+    let SearchResult { documents_ids, corrected, .. } = search.execute().unwrap();
+    assert!(!documents_ids.is_empty());
+    assert!(corrected.is_none());
+}